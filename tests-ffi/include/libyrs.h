@@ -245,6 +245,27 @@ typedef struct YSubscription {} YSubscription;
  */
 #define ERR_NOT_ENOUGH_MEMORY 7
 
+/**
+ * Error code: arithmetic overflow of a decoded integer value (eg. clock or length).
+ */
+#define ERR_CODE_INT_OVERFLOW 8
+
+/**
+ * Error code: decoded payload exceeded one of the hard limits (eg. nesting depth).
+ */
+#define ERR_CODE_LIMIT_EXCEEDED 9
+
+/**
+ * Error code: decoded string was not a valid UTF-8 sequence.
+ */
+#define ERR_CODE_INVALID_UTF8 10
+
+/**
+ * Error code: decoded update could not be integrated into a document (eg. it referenced a parent
+ * which is not a shared type).
+ */
+#define ERR_CODE_INVALID_UPDATE 11
+
 #define YCHANGE_ADD 1
 
 #define YCHANGE_RETAIN 0
@@ -283,6 +304,12 @@ typedef struct YSubscription {} YSubscription;
  */
 #define Y_EVENT_CHANGE_RETAIN 3
 
+/**
+ * Tag used to identify `YEventChange` (see: `yevent_delta` function) case, when a range of
+ * existing elements has been moved to a new position within an observed array.
+ */
+#define Y_EVENT_CHANGE_MOVE 4
+
 /**
  * Tag used to identify `YEventKeyChange` (see: `yevent_keys` function) case, when a new entry has
  * been inserted into a map component of shared collection.
@@ -866,6 +893,10 @@ typedef struct YDelta {
  * `len` field informs about number of removed elements.
  * 3. `Y_EVENT_CHANGE_RETAIN` marks a number of elements that have not been changed, counted from
  * the previous element. `len` field informs about number of retained elements.
+ * 4. `Y_EVENT_CHANGE_MOVE` marks a number of existing elements moved into current position.
+ * `len` field informs about number of moved elements, while `from` and `to` fields inform
+ * about their index before and after the transaction. Their former position is marked by
+ * `Y_EVENT_CHANGE_DELETE`.
  *
  * A list of changes returned by `yarray_event_delta`/`yxml_event_delta` enables to locate a
  * position of all changes within an observed collection by using a combination of added/deleted
@@ -883,6 +914,8 @@ typedef struct YEventChange {
    * case `len` field informs about number of removed elements.
    * 3. `Y_EVENT_CHANGE_RETAIN` marks a number of elements that have not been changed, counted
    * from the previous element. `len` field informs about number of retained elements.
+   * 4. `Y_EVENT_CHANGE_MOVE` marks a number of existing elements moved into current position.
+   * `len` field informs about number of moved elements.
    */
   char tag;
   /**
@@ -895,6 +928,16 @@ typedef struct YEventChange {
    * length stored in `len` field) of newly inserted values.
    */
   const struct YOutput *values;
+  /**
+   * Used in case when current change is of `Y_EVENT_CHANGE_MOVE` type. Contains an index of
+   * the first moved element in the collection state before the transaction.
+   */
+  uint32_t from;
+  /**
+   * Used in case when current change is of `Y_EVENT_CHANGE_MOVE` type. Contains an index of
+   * the first moved element in the collection state after the transaction.
+   */
+  uint32_t to;
 } YEventChange;
 
 /**
@@ -1375,6 +1418,11 @@ char *yupdate_debug_v2(const char *update, uint32_t update_len);
  * - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
  * - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
  * - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+ * - `ERR_NOT_ENOUGH_MEMORY` (**7**): not enough memory to perform an operation.
+ * - `ERR_CODE_INT_OVERFLOW` (**8**): arithmetic overflow of a decoded integer value.
+ * - `ERR_CODE_LIMIT_EXCEEDED` (**9**): decoded payload exceeded one of the hard limits.
+ * - `ERR_CODE_INVALID_UTF8` (**10**): decoded string was not a valid UTF-8 sequence.
+ * - `ERR_CODE_INVALID_UPDATE` (**11**): decoded update could not be integrated into a document.
  */
 uint8_t ytransaction_apply(YTransaction *txn,
                            const char *diff,
@@ -1394,6 +1442,11 @@ uint8_t ytransaction_apply(YTransaction *txn,
  * - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
  * - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
  * - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+ * - `ERR_NOT_ENOUGH_MEMORY` (**7**): not enough memory to perform an operation.
+ * - `ERR_CODE_INT_OVERFLOW` (**8**): arithmetic overflow of a decoded integer value.
+ * - `ERR_CODE_LIMIT_EXCEEDED` (**9**): decoded payload exceeded one of the hard limits.
+ * - `ERR_CODE_INVALID_UTF8` (**10**): decoded string was not a valid UTF-8 sequence.
+ * - `ERR_CODE_INVALID_UPDATE` (**11**): decoded update could not be integrated into a document.
  */
 uint8_t ytransaction_apply_v2(YTransaction *txn,
                               const char *diff,
@@ -2189,7 +2242,8 @@ YSubscription *yxmltext_observe(const Branch *xml,
 /**
  * Subscribes a given callback function `cb` to changes made by this shared type instance as well
  * as all nested shared types living within it. Callbacks are triggered whenever a
- * `ytransaction_commit` is called.
+ * `ytransaction_commit` is called. Changes of nested keyed lists and sets are not reported, as
+ * these types are not supported by C bindings yet.
  *
  * Returns a subscription ID which can be then used to unsubscribe this callback by using
  * `yunobserve` function.
@@ -45,4 +45,29 @@ export const testAwareness = tc => {
     t.assert(lastChange.removed.length === 1)
     t.compare(aw1.getStates().get(0), undefined)
     t.compare(lastChangeLocal, lastChange)
-}
\ No newline at end of file
+}
+
+/**
+ * @param {t.TestCase} tc
+ */
+export const testYAwareness = tc => {
+    const doc1 = new Y.YDoc({clientID: 0})
+    const doc2 = new Y.YDoc({clientID: 1})
+    const aw1 = new Y.YAwareness(doc1)
+    const aw2 = new Y.YAwareness(doc2)
+    aw1.on('update', /** @param {any} p */({added, updated, removed}) => {
+        const enc = aw1.encodeUpdate(added.concat(updated).concat(removed))
+        aw2.applyUpdate(enc, 'custom')
+    })
+    let lastChange = /** @type {any} */ (null)
+    aw2.on('change', change => {
+        lastChange = change
+    })
+    aw1.setLocalState({x: 3})
+    t.compare(lastChange.added, [0])
+    t.compare(aw2.getStates().get(0), {x: 3})
+
+    aw1.removeStates([0])
+    t.assert(lastChange.removed.length === 1)
+    t.compare(aw2.getStates().get(0), undefined)
+}
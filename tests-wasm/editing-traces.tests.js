@@ -72,4 +72,37 @@ export const testEditingTraceSephBlog = tc => {
  */
 export const testEditingTraceSvelteComponent = tc => {
     run(tc, '../assets/editing-traces/sequential_traces/sveltecomponent.json.gz')
-}
\ No newline at end of file
+}
+
+/**
+ * Compares the cost of receiving updates generated by high-frequency typing as copies
+ * (`update` event) and as zero-copy views over wasm memory (`updateView` event).
+ *
+ * @param {t.TestCase} tc
+ */
+export const testUpdateCopyVsView = tc => {
+    const {txns} = JSON.parse(zlib.gunzipSync(fs.readFileSync('../assets/editing-traces/sequential_traces/automerge-paper.json.gz')))
+    for (const event of ['update', 'updateView']) {
+        const doc = new Y.YDoc()
+        const text = doc.getText('text')
+        let received = 0
+        doc.on(event, update => {
+            received += update.length
+        })
+        const start = performance.now()
+        for (const {patches} of txns) {
+            for (const [pos, del, chunk] of patches) {
+                // every keystroke is committed in a separate transaction
+                if (del !== 0) {
+                    text.delete(pos, del)
+                }
+                if (chunk && chunk !== '') {
+                    text.insert(pos, chunk)
+                }
+            }
+        }
+        const end = performance.now()
+        console.log(`'${event}' execution time: `, (end - start), 'milliseconds', `(${received} bytes received)`)
+        t.assert(received > 0)
+    }
+}
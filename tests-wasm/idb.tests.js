@@ -0,0 +1,60 @@
+import 'fake-indexeddb/auto'
+import * as Y from 'ywasm'
+import * as t from 'lib0/testing'
+
+/**
+ * Waits until updates stored in the background have been written to IndexedDB.
+ */
+const flush = () => new Promise(resolve => setTimeout(resolve, 50))
+
+/**
+ * @param {t.TestCase} tc
+ */
+export const testIndexeddbPersistence = async tc => {
+    if (Y.IndexeddbPersistence === undefined) {
+        return // ywasm was built without `indexeddb` feature
+    }
+    const d1 = new Y.YDoc({clientID: 1})
+    const p1 = new Y.IndexeddbPersistence(tc.testName, d1)
+    await p1.whenSynced
+    t.assert(p1.synced)
+    const text1 = d1.getText('text')
+    text1.insert(0, 'hello')
+    text1.insert(5, ' world')
+    await flush()
+    p1.destroy()
+
+    const d2 = new Y.YDoc({clientID: 2})
+    const p2 = new Y.IndexeddbPersistence(tc.testName, d2)
+    await p2.whenSynced
+    t.compare(d2.getText('text').toString(), 'hello world')
+    await p2.clearData()
+}
+
+/**
+ * Stored updates are compacted using multiple IndexedDB requests, which must complete before
+ * their transaction gets committed.
+ *
+ * @param {t.TestCase} tc
+ */
+export const testIndexeddbCompaction = async tc => {
+    if (Y.IndexeddbPersistence === undefined) {
+        return // ywasm was built without `indexeddb` feature
+    }
+    const d1 = new Y.YDoc({clientID: 1})
+    const p1 = new Y.IndexeddbPersistence(tc.testName, d1)
+    await p1.whenSynced
+    const text1 = d1.getText('text')
+    for (let i = 0; i < 600; i++) {
+        text1.insert(i, 'a')
+    }
+    await flush()
+    t.assert(p1.synced)
+    p1.destroy()
+
+    const d2 = new Y.YDoc({clientID: 2})
+    const p2 = new Y.IndexeddbPersistence(tc.testName, d2)
+    await p2.whenSynced
+    t.compare(d2.getText('text').toString(), 'a'.repeat(600))
+    await p2.clearData()
+}
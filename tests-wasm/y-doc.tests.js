@@ -41,6 +41,39 @@ export const testOnUpdate = tc => {
     t.compare(origin, null)
 }
 
+/**
+ * @param {t.TestCase} tc
+ */
+export const testOnUpdateView = tc => {
+    const d1 = new Y.YDoc({clientID: 1})
+    const text1 = d1.getText('text')
+    text1.insert(0, 'hello')
+    let update = Y.encodeStateAsUpdate(d1)
+
+    const d2 = new Y.YDoc({clientID: 2})
+    const text2 = d2.getText('text')
+    let actual;
+    const callback = (e, tx) => {
+        // view is valid only until callback returns, it must be copied to be retained
+        actual = e.slice()
+    }
+    d2.on('updateView', callback);
+    Y.applyUpdate(d2, update, d1.id)
+
+    t.compare(text1.toString(), text2.toString())
+    t.compare(actual, update)
+
+    t.assert(d2.off('updateView', callback), 'off "updateView" failed')
+    actual = null
+
+    text1.insert(5, 'world')
+    update = Y.encodeStateAsUpdate(d1)
+    Y.applyUpdate(d2, update, d1.id)
+
+    t.compare(text1.toString(), text2.toString())
+    t.compare(actual, null)
+}
+
 /**
  * @param {t.TestCase} tccls
  */
@@ -111,6 +144,35 @@ export const testOnAfterTransaction = tc => {
     t.compare(event, null)
 }
 
+/**
+ * @param {t.TestCase} tc
+ */
+export const testTransactionOrigin = tc => {
+    const doc = new Y.YDoc({clientID: 1})
+    const text = doc.getText('text')
+    const provider = {name: 'provider'}
+    let origins = []
+    doc.on('afterTransaction', txn => origins.push(txn.origin))
+
+    doc.transact(txn => text.insert(0, 'a', undefined, txn), provider)
+    doc.transact(txn => text.insert(0, 'b', undefined, txn), provider)
+    doc.transact(txn => text.insert(0, 'c', undefined, txn), {name: 'provider'})
+    doc.transact(txn => text.insert(0, 'd', undefined, txn), 'abcd')
+    doc.transact(txn => text.insert(0, 'e', undefined, txn), 42)
+    doc.transact(txn => text.insert(0, 'f', undefined, txn), true)
+    doc.transact(txn => text.insert(0, 'g', undefined, txn), -0)
+
+    t.compare(origins.length, 7)
+    t.assert(origins[0] === provider, 'original JS object should be passed as origin')
+    t.assert(Y.sameOrigin(origins[0], origins[1]), 'the same object should be the same origin')
+    t.assert(!Y.sameOrigin(origins[0], origins[2]), 'different objects should be different origins')
+    t.compare(origins[3], 'abcd')
+    t.assert(Y.sameOrigin(origins[3], 'abcd'), 'equal strings should be the same origin')
+    t.compare(origins[4], 42)
+    t.assert(origins[5] === true, 'primitive values should be passed as origin')
+    t.assert(Object.is(origins[6], -0), 'numbers should be passed as origin')
+}
+
 /**
  * @param {t.TestCase} tc
  */
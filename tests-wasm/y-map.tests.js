@@ -172,4 +172,48 @@ export const testObserversUsingObservedeep = tc => {
     map.get('map').get('array').insert(0, ['content'])
     t.assert(calls === 3)
     t.compare(paths, [[], ['map'], ['map', 'array']])
-}
\ No newline at end of file
+}
+/**
+ * @param {t.TestCase} tc
+ */
+export const testToJsonDeepPlain = tc => {
+    const d1 = new Y.YDoc()
+    const x = d1.getMap('test')
+    const prelim = new Y.YMap({a: new Y.YArray([1, 2]), b: new Y.YText('hello')})
+    const bin = new Uint8Array([1, 2, 3])
+
+    x.set('key', 'value')
+    x.set('bin', bin)
+    x.set('nested', new Y.YMap({c: 'C'}))
+
+    const expected = {a: [1, 2], b: 'hello'}
+    t.compare(prelim.toJsonDeep({plain: true}), expected)
+    t.compare(structuredClone(prelim.toJsonDeep({plain: true})), expected)
+
+    const json = x.toJsonDeep({plain: true})
+    t.compare(json, {key: 'value', bin, nested: {c: 'C'}})
+    t.compare(d1.toJsonDeep({plain: true}), {test: json})
+}
+
+/**
+ * @param {t.TestCase} tc
+ */
+export const testObserverExceptionIsRethrown = tc => {
+    const d1 = new Y.YDoc()
+    const x = d1.getMap('test')
+    const callback = e => {
+        throw new Error('observer failure')
+    }
+    x.observe(callback)
+
+    t.fails(() => x.set('key', 'value'))
+    // document is still usable after exception has been rethrown
+    x.unobserve(callback)
+    x.set('key2', 'value2')
+    t.compare(x.toJson(), {key: 'value', key2: 'value2'})
+
+    const integrated = new Y.YMap()
+    x.set('nested', integrated)
+    t.fails(() => x.set('again', integrated))
+    t.fails(() => x.set('wrapped', new Y.YMap({inner: integrated})))
+}
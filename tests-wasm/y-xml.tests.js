@@ -215,10 +215,14 @@ export const testXmlElementObserver = tc => {
     let target = null
     let attributes = null
     let nodes = null
+    let attributesChanged = null
+    let childListChanged = null
     let callback = e => {
         target = e.target
         attributes = e.keys
         nodes = e.delta
+        attributesChanged = e.attributesChanged
+        childListChanged = e.childListChanged
     }
     x.observe(callback)
 
@@ -233,6 +237,8 @@ export const testXmlElementObserver = tc => {
         attr1: {action: 'add', newValue: 'value1'},
         attr2: {action: 'add', newValue: 'value2'}
     })
+    t.compare(attributesChanged.sort(), ['attr1', 'attr2'])
+    t.compare(childListChanged, false)
     target = null
     attributes = null
     nodes = null
@@ -260,6 +266,8 @@ export const testXmlElementObserver = tc => {
     t.compare(target.toString(), x.toString())
     t.compare(nodes[0].insert.length, 2) // [{ insert: [div, p] }]
     t.compare(attributes, {})
+    t.compare(attributesChanged, [])
+    t.compare(childListChanged, true)
     target = null
     attributes = null
     nodes = null
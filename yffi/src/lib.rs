@@ -312,6 +312,15 @@ impl Into<Options> for YOptions {
             auto_load: if self.auto_load == 0 { false } else { true },
             should_load: if self.should_load == 0 { false } else { true },
             offset_kind: encoding,
+            codec: None,
+            text_block_capacity: Options::DEFAULT_TEXT_BLOCK_CAPACITY,
+            text_normalizer: None,
+            map_conflict_resolver: None,
+            callback_panic_hook: None,
+            max_blocks: None,
+            max_content_bytes: None,
+            read_only: false,
+            root_aliases: HashMap::new(),
         }
     }
 }
@@ -1074,6 +1083,11 @@ pub unsafe extern "C" fn yupdate_debug_v2(update: *const c_char, update_len: u32
 /// - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
 /// - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
 /// - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+/// - `ERR_NOT_ENOUGH_MEMORY` (**7**): not enough memory to perform an operation.
+/// - `ERR_CODE_INT_OVERFLOW` (**8**): arithmetic overflow of a decoded integer value.
+/// - `ERR_CODE_LIMIT_EXCEEDED` (**9**): decoded payload exceeded one of the hard limits.
+/// - `ERR_CODE_INVALID_UTF8` (**10**): decoded string was not a valid UTF-8 sequence.
+/// - `ERR_CODE_INVALID_UPDATE` (**11**): decoded update could not be integrated into a document.
 #[no_mangle]
 pub unsafe extern "C" fn ytransaction_apply(
     txn: *mut Transaction,
@@ -1091,8 +1105,10 @@ pub unsafe extern "C" fn ytransaction_apply(
             let txn = txn
                 .as_mut()
                 .expect("provided transaction was not writeable");
-            txn.apply_update(update);
-            0
+            match txn.apply_update(update) {
+                Ok(()) => 0,
+                Err(_) => ERR_CODE_INVALID_UPDATE,
+            }
         }
         Err(e) => err_code(e),
     }
@@ -1111,6 +1127,11 @@ pub unsafe extern "C" fn ytransaction_apply(
 /// - `ERR_CODE_UNEXPECTED_VALUE` (**4**): decoded enum tag value was not among known cases.
 /// - `ERR_CODE_INVALID_JSON` (**5**): failure when trying to decode JSON content.
 /// - `ERR_CODE_OTHER` (**6**): other error type than the one specified.
+/// - `ERR_NOT_ENOUGH_MEMORY` (**7**): not enough memory to perform an operation.
+/// - `ERR_CODE_INT_OVERFLOW` (**8**): arithmetic overflow of a decoded integer value.
+/// - `ERR_CODE_LIMIT_EXCEEDED` (**9**): decoded payload exceeded one of the hard limits.
+/// - `ERR_CODE_INVALID_UTF8` (**10**): decoded string was not a valid UTF-8 sequence.
+/// - `ERR_CODE_INVALID_UPDATE` (**11**): decoded update could not be integrated into a document.
 #[no_mangle]
 pub unsafe extern "C" fn ytransaction_apply_v2(
     txn: *mut Transaction,
@@ -1127,8 +1148,10 @@ pub unsafe extern "C" fn ytransaction_apply_v2(
             let txn = txn
                 .as_mut()
                 .expect("provided transaction was not writeable");
-            txn.apply_update(update);
-            0
+            match txn.apply_update(update) {
+                Ok(()) => 0,
+                Err(_) => ERR_CODE_INVALID_UPDATE,
+            }
         }
         Err(e) => err_code(e),
     }
@@ -1155,6 +1178,19 @@ pub const ERR_CODE_OTHER: u8 = 6;
 /// Error code: not enough memory to perform an operation.
 pub const ERR_NOT_ENOUGH_MEMORY: u8 = 7;
 
+/// Error code: arithmetic overflow of a decoded integer value (eg. clock or length).
+pub const ERR_CODE_INT_OVERFLOW: u8 = 8;
+
+/// Error code: decoded payload exceeded one of the hard limits (eg. nesting depth).
+pub const ERR_CODE_LIMIT_EXCEEDED: u8 = 9;
+
+/// Error code: decoded string was not a valid UTF-8 sequence.
+pub const ERR_CODE_INVALID_UTF8: u8 = 10;
+
+/// Error code: decoded update could not be integrated into a document (eg. it referenced a parent
+/// which is not a shared type).
+pub const ERR_CODE_INVALID_UPDATE: u8 = 11;
+
 fn err_code(e: Error) -> u8 {
     match e {
         Error::InvalidVarInt => ERR_CODE_VAR_INT,
@@ -1162,6 +1198,10 @@ fn err_code(e: Error) -> u8 {
         Error::UnexpectedValue => ERR_CODE_UNEXPECTED_VALUE,
         Error::InvalidJSON(_) => ERR_CODE_INVALID_JSON,
         Error::NotEnoughMemory(_) => ERR_NOT_ENOUGH_MEMORY,
+        Error::IntegerOverflow(_) => ERR_CODE_INT_OVERFLOW,
+        Error::LimitExceeded(_, _) => ERR_CODE_LIMIT_EXCEEDED,
+        Error::InvalidUtf8(_) => ERR_CODE_INVALID_UTF8,
+        Error::Io(_) => ERR_CODE_IO,
     }
 }
 
@@ -1831,7 +1871,7 @@ pub unsafe extern "C" fn yxmlattr_iter_next(iterator: *mut Attributes) -> *mut Y
     if let Some((name, value)) = iter.0.next() {
         Box::into_raw(Box::new(YXmlAttr {
             name: CString::new(name).unwrap().into_raw(),
-            value: CString::new(value).unwrap().into_raw(),
+            value: CString::new(value.to_string()).unwrap().into_raw(),
         }))
     } else {
         std::ptr::null_mut()
@@ -2764,6 +2804,10 @@ impl From<Value> for YOutput {
             Value::YXmlText(v) => Self::from(v),
             Value::YDoc(v) => Self::from(v),
             Value::YWeakLink(v) => Self::from(v),
+            // keyed lists are not supported by C bindings yet
+            Value::YKeyedList(v) => Self::from(BranchPtr::from(v.as_ref())),
+            // sets are not supported by C bindings yet
+            Value::YSet(v) => Self::from(BranchPtr::from(v.as_ref())),
             Value::UndefinedRef(v) => Self::from(v),
         }
     }
@@ -3519,7 +3563,8 @@ pub unsafe extern "C" fn yxmltext_observe(
 
 /// Subscribes a given callback function `cb` to changes made by this shared type instance as well
 /// as all nested shared types living within it. Callbacks are triggered whenever a
-/// `ytransaction_commit` is called.
+/// `ytransaction_commit` is called. Changes of nested keyed lists and sets are not reported, as
+/// these types are not supported by C bindings yet.
 ///
 /// Returns a subscription ID which can be then used to unsubscribe this callback by using
 /// `yunobserve` function.
@@ -3534,7 +3579,10 @@ pub unsafe extern "C" fn yobserve_deep(
     let state = CallbackState::new(state);
     let branch = ytype.as_mut().unwrap();
     let subscription = branch.observe_deep(move |txn, events| {
-        let events: Vec<_> = events.iter().map(|e| YEvent::new(txn, e)).collect();
+        let events: Vec<_> = events.iter().filter_map(|e| YEvent::new(txn, e)).collect();
+        if events.is_empty() {
+            return;
+        }
         let len = events.len() as u32;
         cb(state.0, len, events.as_ptr());
     });
@@ -3753,8 +3801,8 @@ pub struct YEvent {
 }
 
 impl YEvent {
-    fn new<'doc>(txn: &yrs::TransactionMut<'doc>, e: &Event) -> YEvent {
-        match e {
+    fn new<'doc>(txn: &yrs::TransactionMut<'doc>, e: &Event) -> Option<YEvent> {
+        let e = match e {
             Event::Text(e) => YEvent {
                 tag: Y_TEXT,
                 content: YEventContent {
@@ -3795,7 +3843,10 @@ impl YEvent {
                     weak: YWeakLinkEvent::new(e, txn),
                 },
             },
-        }
+            // keyed lists and sets are not supported by C bindings yet
+            Event::KeyedList(_) | Event::Set(_) => return None,
+        };
+        Some(e)
     }
 }
 
@@ -4540,15 +4591,15 @@ pub unsafe extern "C" fn ytype_kind(branch: *const Branch) -> i8 {
     if let Some(branch) = branch.as_ref() {
         match branch.type_ref() {
             TypeRef::Array => Y_ARRAY,
-            TypeRef::Map => Y_MAP,
+            TypeRef::Map | TypeRef::XmlHook(_) => Y_MAP,
             TypeRef::Text => Y_TEXT,
             TypeRef::XmlElement(_) => Y_XML_ELEM,
             TypeRef::XmlText => Y_XML_TEXT,
             TypeRef::XmlFragment => Y_XML_FRAG,
             TypeRef::SubDoc => Y_DOC,
             TypeRef::WeakLink(_) => Y_WEAK_LINK,
-            TypeRef::XmlHook => 0,
-            TypeRef::Undefined => 0,
+            // keyed lists and sets are not supported by C bindings yet
+            TypeRef::KeyedList | TypeRef::Set | TypeRef::Undefined => 0,
         }
     } else {
         0
@@ -4632,6 +4683,10 @@ pub const Y_EVENT_CHANGE_DELETE: c_char = 2;
 /// been detected for a particular range of observed collection.
 pub const Y_EVENT_CHANGE_RETAIN: c_char = 3;
 
+/// Tag used to identify `YEventChange` (see: `yevent_delta` function) case, when a range of
+/// existing elements has been moved to a new position within an observed array.
+pub const Y_EVENT_CHANGE_MOVE: c_char = 4;
+
 /// A data type representing a single change detected over an observed shared collection. A type
 /// of change can be detected using a `tag` field:
 ///
@@ -4642,6 +4697,10 @@ pub const Y_EVENT_CHANGE_RETAIN: c_char = 3;
 /// `len` field informs about number of removed elements.
 /// 3. `Y_EVENT_CHANGE_RETAIN` marks a number of elements that have not been changed, counted from
 /// the previous element. `len` field informs about number of retained elements.
+/// 4. `Y_EVENT_CHANGE_MOVE` marks a number of existing elements moved into current position.
+/// `len` field informs about number of moved elements, while `from` and `to` fields inform
+/// about their index before and after the transaction. Their former position is marked by
+/// `Y_EVENT_CHANGE_DELETE`.
 ///
 /// A list of changes returned by `yarray_event_delta`/`yxml_event_delta` enables to locate a
 /// position of all changes within an observed collection by using a combination of added/deleted
@@ -4658,6 +4717,8 @@ pub struct YEventChange {
     /// case `len` field informs about number of removed elements.
     /// 3. `Y_EVENT_CHANGE_RETAIN` marks a number of elements that have not been changed, counted
     /// from the previous element. `len` field informs about number of retained elements.
+    /// 4. `Y_EVENT_CHANGE_MOVE` marks a number of existing elements moved into current position.
+    /// `len` field informs about number of moved elements.
     pub tag: c_char,
 
     /// Number of element affected by current type of a change. It can refer to a number of
@@ -4667,6 +4728,14 @@ pub struct YEventChange {
     /// Used in case when current change is of `Y_EVENT_CHANGE_ADD` type. Contains a list (of
     /// length stored in `len` field) of newly inserted values.
     pub values: *const YOutput,
+
+    /// Used in case when current change is of `Y_EVENT_CHANGE_MOVE` type. Contains an index of
+    /// the first moved element in the collection state before the transaction.
+    pub from: u32,
+
+    /// Used in case when current change is of `Y_EVENT_CHANGE_MOVE` type. Contains an index of
+    /// the first moved element in the collection state after the transaction.
+    pub to: u32,
 }
 
 impl<'a> From<&'a Change> for YEventChange {
@@ -4685,17 +4754,38 @@ impl<'a> From<&'a Change> for YEventChange {
                     tag: Y_EVENT_CHANGE_ADD,
                     len,
                     values,
+                    from: 0,
+                    to: 0,
                 }
             }
             Change::Removed(len) => YEventChange {
                 tag: Y_EVENT_CHANGE_DELETE,
                 len: *len as u32,
                 values: null(),
+                from: 0,
+                to: 0,
             },
             Change::Retain(len) => YEventChange {
                 tag: Y_EVENT_CHANGE_RETAIN,
                 len: *len as u32,
                 values: null(),
+                from: 0,
+                to: 0,
+            },
+            Change::Moved { from, to, len } => YEventChange {
+                tag: Y_EVENT_CHANGE_MOVE,
+                len: *len,
+                values: null(),
+                from: *from,
+                to: *to,
+            },
+            // changes not known to this version of bindings are skipped
+            _ => YEventChange {
+                tag: Y_EVENT_CHANGE_RETAIN,
+                len: 0,
+                values: null(),
+                from: 0,
+                to: 0,
             },
         }
     }
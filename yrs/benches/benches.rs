@@ -6,7 +6,7 @@ use std::cell::Cell;
 use std::collections::HashMap;
 use yrs::encoding::read::{Cursor, Read};
 use yrs::updates::decoder::Decode;
-use yrs::{Array, Doc, Map, MapRef, Text, TextRef, Transact, TransactionMut, Update};
+use yrs::{Array, Doc, Map, MapRef, Options, Text, TextRef, Transact, TransactionMut, Update};
 
 const N: usize = 6000;
 const SQRT_N: usize = 77 * 20;
@@ -261,8 +261,10 @@ where
                     apply(&mut txn2, t2, o2);
                     let u2 = txn2.encode_update_v1();
 
-                    txn1.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
-                    txn2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+                    txn1.apply_update(Update::decode_v1(u2.as_slice()).unwrap())
+                        .unwrap();
+                    txn2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+                        .unwrap();
                 }
             });
         },
@@ -378,7 +380,8 @@ where
             let (doc, _) = iter.next().unwrap();
             let mut txn = doc.transact_mut();
             while let Some((_, update)) = iter.next() {
-                txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+                txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                    .unwrap();
             }
         });
     });
@@ -425,7 +428,8 @@ fn b3_4(c: &mut Criterion, name: &str) {
             let (doc, _) = iter.next().unwrap();
             let mut txn = doc.transact_mut();
             while let Some((_, update)) = iter.next() {
-                txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+                txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                    .unwrap();
             }
         });
     });
@@ -466,12 +470,68 @@ fn b4_2(c: &mut Criterion, name: &str) {
         |b, (doc, _txt, buf)| {
             b.iter(|| {
                 let mut txn = doc.transact_mut();
-                txn.apply_update(Update::decode_v1(buf.as_slice()).unwrap());
+                txn.apply_update(Update::decode_v1(buf.as_slice()).unwrap())
+                    .unwrap();
             });
         },
     );
 }
 
+fn b5_1(c: &mut Criterion, name: &str) {
+    let input = read_input("./assets/bench-input/b4-editing-trace.bin");
+    for capacity in [0, 64, 1024, Options::DEFAULT_TEXT_BLOCK_CAPACITY] {
+        c.bench_with_input(BenchmarkId::new(name, capacity), &input, |b, input| {
+            b.iter(|| {
+                let doc = Doc::with_options(Options {
+                    text_block_capacity: capacity,
+                    ..Options::default()
+                });
+                let txt = doc.get_or_insert_text("text");
+                for i in input {
+                    let mut txn = doc.transact_mut();
+                    match i {
+                        TextOp::Insert(idx, chunk) => txt.insert(&mut txn, *idx, chunk),
+                        TextOp::Delete(idx, len) => txt.remove_range(&mut txn, *idx, *len),
+                    }
+                }
+            });
+        });
+    }
+}
+
+fn b5_2(c: &mut Criterion, name: &str) {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let content = gen_string(&mut rng, 1024 * 1024, 1024 * 1024 + 1);
+    let ops: Vec<_> = (0..N)
+        .map(|i| {
+            let idx = rng.gen_range(0..(content.len() + i) as u32);
+            TextOp::Insert(idx, gen_string(&mut rng, 1, 2))
+        })
+        .collect();
+    for capacity in [1024, Options::DEFAULT_TEXT_BLOCK_CAPACITY, usize::MAX] {
+        c.bench_with_input(
+            BenchmarkId::new(name, capacity),
+            &(&content, &ops),
+            |b, (content, ops)| {
+                b.iter(|| {
+                    let doc = Doc::with_options(Options {
+                        text_block_capacity: capacity,
+                        ..Options::default()
+                    });
+                    let txt = doc.get_or_insert_text("text");
+                    txt.push_large(&mut doc.transact_mut(), content);
+                    for op in ops.iter() {
+                        let mut txn = doc.transact_mut();
+                        if let TextOp::Insert(idx, chunk) = op {
+                            txt.insert(&mut txn, *idx, chunk);
+                        }
+                    }
+                });
+            },
+        );
+    }
+}
+
 fn read_input(fpath: &str) -> Vec<TextOp> {
     use std::fs::File;
     use yrs::updates::decoder::DecoderV1;
@@ -551,6 +611,14 @@ fn bench(c: &mut Criterion) {
     b3_4(c, "[B3.4] 20√N clients concurrently insert text in Array");
     b4_2(c, "[B4.2] Apply real-world document snapshot of size");
     b4_1(c, "[B4.1] Apply real-world editing dataset");
+    b5_1(
+        c,
+        "[B5.1] Apply real-world editing dataset with text block capacity",
+    );
+    b5_2(
+        c,
+        "[B5.2] Insert N characters at random positions of 1MiB text with text block capacity",
+    );
 }
 
 criterion_group! {
@@ -0,0 +1,92 @@
+//! y-websocket compatible sync client. It connects to a given room, synchronizes a `Y.Text`
+//! named "text" with other peers, appends a given line of text to it and prints its contents
+//! whenever they change.
+//!
+//! Run with:
+//!
+//! ```sh
+//! cargo run --example sync_client --features sync-demo -- ws://127.0.0.1:1234/room "hello"
+//! ```
+
+use std::io::ErrorKind;
+use std::time::Duration;
+use tungstenite::stream::MaybeTlsStream;
+use yrs::encoding::read::Cursor;
+use yrs::sync::{Awareness, DefaultProtocol, Message, MessageReader, Protocol, SyncMessage};
+use yrs::updates::decoder::DecoderV1;
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::{Doc, GetString, Text, Transact};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let url = args
+        .next()
+        .unwrap_or_else(|| "ws://127.0.0.1:1234/room".to_string());
+    let line = args.next();
+
+    let (mut ws, _) = tungstenite::connect(url.as_str())?;
+    if let MaybeTlsStream::Plain(stream) = ws.get_mut() {
+        stream.set_read_timeout(Some(Duration::from_millis(50)))?;
+    }
+
+    let mut awareness = Awareness::new(Doc::new());
+    let text = awareness.doc().get_or_insert_text("text");
+    let (tx, rx) = std::sync::mpsc::channel();
+    let _sub = awareness
+        .doc()
+        .observe_update_v1(move |_, e| {
+            let msg = Message::Sync(SyncMessage::Update(e.update.clone()));
+            let _ = tx.send(msg.encode_v1());
+        })
+        .unwrap();
+
+    let protocol = DefaultProtocol;
+    let mut encoder = EncoderV1::new();
+    protocol.start(&awareness, &mut encoder)?;
+    ws.send(tungstenite::Message::Binary(encoder.to_vec()))?;
+
+    let mut synced = false;
+    let mut last = String::new();
+    loop {
+        while let Ok(payload) = rx.try_recv() {
+            ws.send(tungstenite::Message::Binary(payload))?;
+        }
+        let data = match ws.read() {
+            Ok(tungstenite::Message::Binary(data)) => data,
+            Ok(tungstenite::Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut encoder = EncoderV1::new();
+        let mut decoder = DecoderV1::new(Cursor::new(&data));
+        for msg in MessageReader::new(&mut decoder) {
+            let msg = msg?;
+            let is_sync_step2 = matches!(msg, Message::Sync(SyncMessage::SyncStep2(_)));
+            if let Some(reply) = protocol.handle_msg(&mut awareness, msg)? {
+                reply.encode(&mut encoder);
+            }
+            if is_sync_step2 && !synced {
+                synced = true;
+                if let Some(line) = &line {
+                    text.push(&mut awareness.doc().transact_mut(), line);
+                }
+            }
+        }
+        let reply = encoder.to_vec();
+        if !reply.is_empty() {
+            ws.send(tungstenite::Message::Binary(reply))?;
+        }
+
+        let current = text.get_string(&awareness.doc().transact());
+        if current != last {
+            println!("{}", current);
+            last = current;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! y-websocket compatible sync server. Every websocket connection path is treated as a separate
+//! room (document), which state is kept in memory for as long as the server is running.
+//!
+//! Run with:
+//!
+//! ```sh
+//! cargo run --example sync_server --features sync-demo -- 127.0.0.1:1234
+//! ```
+//!
+//! Yjs clients can connect to it using `new WebsocketProvider('ws://127.0.0.1:1234', 'room', doc)`.
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tungstenite::handshake::server::{Request, Response};
+use yrs::encoding::read::Cursor;
+use yrs::sync::{Awareness, DefaultProtocol, Message, MessageReader, Protocol, SyncMessage};
+use yrs::updates::decoder::DecoderV1;
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::{Doc, Subscription};
+
+type Peers = Arc<Mutex<Vec<Sender<Vec<u8>>>>>;
+
+struct Room {
+    awareness: Mutex<Awareness>,
+    peers: Peers,
+    _subscriptions: [Subscription; 2],
+}
+
+impl Room {
+    fn new() -> Self {
+        let peers: Peers = Arc::default();
+        let awareness = Awareness::new(Doc::new());
+        let sink = peers.clone();
+        let on_update = awareness
+            .doc()
+            .observe_update_v1(move |_, e| {
+                let msg = Message::Sync(SyncMessage::Update(e.update.clone()));
+                broadcast(&sink, msg.encode_v1());
+            })
+            .unwrap();
+        let sink = peers.clone();
+        let on_awareness = awareness.on_update(move |awareness, e, _| {
+            if let Ok(update) = awareness.update_with_clients(e.all_changes()) {
+                broadcast(&sink, Message::Awareness(update).encode_v1());
+            }
+        });
+        Room {
+            awareness: Mutex::new(awareness),
+            peers,
+            _subscriptions: [on_update, on_awareness],
+        }
+    }
+}
+
+fn broadcast(peers: &Peers, payload: Vec<u8>) {
+    let mut peers = peers.lock().unwrap();
+    // remove peers which have disconnected
+    peers.retain(|peer| peer.send(payload.clone()).is_ok());
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    rooms: Arc<Mutex<HashMap<String, Arc<Room>>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let requested_path: Arc<Mutex<String>> = Arc::default();
+    let sink = requested_path.clone();
+    let mut ws = tungstenite::accept_hdr(stream, move |req: &Request, res: Response| {
+        *sink.lock().unwrap() = req.uri().path().trim_start_matches('/').to_string();
+        Ok(res)
+    })?;
+    let path = requested_path.lock().unwrap().clone();
+    ws.get_mut()
+        .set_read_timeout(Some(Duration::from_millis(50)))?;
+
+    let room = rooms
+        .lock()
+        .unwrap()
+        .entry(path.clone())
+        .or_insert_with(|| Arc::new(Room::new()))
+        .clone();
+    let (tx, rx) = channel();
+    room.peers.lock().unwrap().push(tx);
+    println!("peer joined room '{}'", path);
+
+    let protocol = DefaultProtocol;
+    let mut encoder = EncoderV1::new();
+    protocol.start(&room.awareness.lock().unwrap(), &mut encoder)?;
+    ws.send(tungstenite::Message::Binary(encoder.to_vec()))?;
+
+    loop {
+        while let Ok(payload) = rx.try_recv() {
+            ws.send(tungstenite::Message::Binary(payload))?;
+        }
+        let data = match ws.read() {
+            Ok(tungstenite::Message::Binary(data)) => data,
+            Ok(tungstenite::Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut encoder = EncoderV1::new();
+        {
+            let mut awareness = room.awareness.lock().unwrap();
+            let mut decoder = DecoderV1::new(Cursor::new(&data));
+            for msg in MessageReader::new(&mut decoder) {
+                if let Some(reply) = protocol.handle_msg(&mut awareness, msg?)? {
+                    reply.encode(&mut encoder);
+                }
+            }
+        }
+        let reply = encoder.to_vec();
+        if !reply.is_empty() {
+            ws.send(tungstenite::Message::Binary(reply))?;
+        }
+    }
+    println!("peer left room '{}'", path);
+    Ok(())
+}
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:1234".to_string());
+    let listener = TcpListener::bind(&addr).expect("failed to bind server address");
+    println!("y-websocket server listening on ws://{}", addr);
+
+    let rooms: Arc<Mutex<HashMap<String, Arc<Room>>>> = Arc::default();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let rooms = rooms.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, rooms) {
+                eprintln!("connection failed: {}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use yrs::encoding::read::Cursor;
+use yrs::Any;
+
+fuzz_target!(|data: &[u8]| {
+    let mut decoder = Cursor::new(data);
+    let _ = Any::decode(&mut decoder);
+});
@@ -0,0 +1,22 @@
+//! Decodes and applies lib0 v1 encoded updates onto a new document. Applying malformed updates
+//! must fail with an error, never with a panic. Seed corpus built from the compatibility tests:
+//!
+//! ```sh
+//! cargo fuzz run apply_update_v1 fuzz/seeds/apply_update_v1
+//! ```
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, ReadTxn, Transact, Update};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(update) = Update::decode_v1(data) {
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let _ = txn.apply_update(update);
+        let _ = txn.encode_update_v1();
+        drop(txn);
+        let _ = doc.transact().encode_state_as_update_v1(&Default::default());
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use yrs::updates::decoder::Decode;
+use yrs::Update;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Update::decode_v1(data);
+});
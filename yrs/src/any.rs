@@ -1,13 +1,49 @@
-use crate::encoding::read::{Error, Read};
+use crate::encoding::read::{Error, Read, MAX_ANY_DEPTH, MAX_PREALLOCATED_LEN};
 use crate::encoding::write::Write;
-use std::cmp::PartialEq;
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::PartialEq;
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::convert::TryFrom;
-use std::sync::Arc;
 
 pub const F64_MAX_SAFE_INTEGER: f64 = (i64::pow(2, 53) - 1) as f64;
 pub const F64_MIN_SAFE_INTEGER: f64 = -F64_MAX_SAFE_INTEGER;
 
+/// Error returned by checked numeric conversions of [Any], ie. [Any::try_to_i64].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberConversionError {
+    /// Value is not an [Any::Number] nor [Any::BigInt].
+    NotANumber,
+    /// Floating point number has a fractional part and cannot be converted into an integer.
+    NotAnInteger,
+    /// Number doesn't fit into the target type without losing precision.
+    OutOfRange,
+}
+
+impl core::fmt::Display for NumberConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            NumberConversionError::NotANumber => f.write_str("value is not a number"),
+            NumberConversionError::NotAnInteger => f.write_str("number is not an integer"),
+            NumberConversionError::OutOfRange => {
+                f.write_str("number cannot be converted without losing precision")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NumberConversionError {}
+
+#[inline]
+fn is_safe_integer(num: i64) -> bool {
+    num <= F64_MAX_SAFE_INTEGER as i64 && num >= F64_MIN_SAFE_INTEGER as i64
+}
+
 /// Any is an enum with a potentially associated value that is used to represent JSON values
 /// and supports efficient encoding of those values.
 #[derive(Debug, Clone, PartialEq)]
@@ -20,7 +56,12 @@ pub enum Any {
     String(Arc<str>),
     Buffer(Arc<[u8]>),
     Array(Arc<[Any]>),
+    #[cfg(feature = "std")]
     Map(Arc<HashMap<String, Any>>),
+    /// Without `std` feature, maps are represented using [BTreeMap], as [HashMap] is not
+    /// available in `no_std` environments.
+    #[cfg(not(feature = "std"))]
+    Map(Arc<BTreeMap<String, Any>>),
 }
 
 impl Any {
@@ -34,7 +75,66 @@ impl Any {
         T::try_from(self)
     }
 
+    /// Returns true if current value is a number, which can be represented as an integer
+    /// without losing precision: either a [Any::BigInt] or an [Any::Number] with no fractional
+    /// part, that fits within a safe integer range of f64.
+    pub fn is_integer(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Returns an integer value of current number if it can be represented as [i64] without
+    /// losing precision. Returns `None` for non-numeric values, numbers with fractional part
+    /// and floating point numbers outside of the safe integer range.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.try_to_i64().ok()
+    }
+
+    /// Returns a floating point value of current number if it can be represented as [f64]
+    /// without losing precision. Returns `None` for non-numeric values and [Any::BigInt]s
+    /// outside of the safe integer range.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.try_to_f64().ok()
+    }
+
+    /// Checked conversion of current value into [i64]. Unlike `TryFrom<Any> for i64`, which
+    /// truncates fractional part and saturates out of range numbers, this method fails whenever
+    /// conversion would be lossy.
+    pub fn try_to_i64(&self) -> Result<i64, NumberConversionError> {
+        match self {
+            Any::BigInt(num) => Ok(*num),
+            Any::Number(num) => {
+                // `f64::fract` is not available in `no_std`, see `Any::encode` for details
+                if *num > F64_MAX_SAFE_INTEGER || *num < F64_MIN_SAFE_INTEGER {
+                    Err(NumberConversionError::OutOfRange)
+                } else if (*num as i64 as f64) != *num {
+                    Err(NumberConversionError::NotAnInteger)
+                } else {
+                    Ok(*num as i64)
+                }
+            }
+            _ => Err(NumberConversionError::NotANumber),
+        }
+    }
+
+    /// Checked conversion of current value into [f64]. Fails for [Any::BigInt] values which
+    /// cannot be represented as f64 without losing precision.
+    pub fn try_to_f64(&self) -> Result<f64, NumberConversionError> {
+        match self {
+            Any::Number(num) => Ok(*num),
+            Any::BigInt(num) if is_safe_integer(*num) => Ok(*num as f64),
+            Any::BigInt(_) => Err(NumberConversionError::OutOfRange),
+            _ => Err(NumberConversionError::NotANumber),
+        }
+    }
+
     pub fn decode<R: Read>(decoder: &mut R) -> Result<Self, Error> {
+        Self::decode_nested(decoder, 0)
+    }
+
+    fn decode_nested<R: Read>(decoder: &mut R, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_ANY_DEPTH {
+            return Err(Error::LimitExceeded("Any nesting depth", MAX_ANY_DEPTH));
+        }
         Ok(match decoder.read_u8()? {
             // CASE 127: undefined
             127 => Any::Undefined,
@@ -60,19 +160,22 @@ impl Any {
             // CASE 118: Map<string,Any>
             118 => {
                 let len: usize = decoder.read_var()?;
-                let mut map = HashMap::with_capacity(len);
+                #[cfg(feature = "std")]
+                let mut map = HashMap::with_capacity(len.min(MAX_PREALLOCATED_LEN));
+                #[cfg(not(feature = "std"))]
+                let mut map = BTreeMap::new();
                 for _ in 0..len {
                     let key = decoder.read_string()?;
-                    map.insert(key.to_owned(), Any::decode(decoder)?);
+                    map.insert(key.to_owned(), Any::decode_nested(decoder, depth + 1)?);
                 }
                 Any::Map(Arc::new(map))
             }
             // CASE 117: Array<Any>
             117 => {
                 let len: usize = decoder.read_var()?;
-                let mut arr = Vec::with_capacity(len);
+                let mut arr = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN));
                 for _ in 0..len {
-                    arr.push(Any::decode(decoder)?);
+                    arr.push(Any::decode_nested(decoder, depth + 1)?);
                 }
                 Any::Array(Arc::from(arr))
             }
@@ -134,7 +237,9 @@ impl Any {
                 encoder.write_string(&str)
             }
             Any::Number(num) => {
-                let num_truncated = num.trunc();
+                // `f64::trunc` is not available in `no_std`, but for numbers within a safe
+                // integer range a cast to integer and back has the same effect
+                let num_truncated = *num as i64 as f64;
                 if num_truncated == *num
                     && num_truncated <= F64_MAX_SAFE_INTEGER
                     && num_truncated >= F64_MIN_SAFE_INTEGER
@@ -182,10 +287,17 @@ impl Any {
         }
     }
 
+    /// Parses a given JSON string. Parsing errors report a position at which they occurred
+    /// (see: [Error::json_location]).
+    ///
+    /// Input must be a valid JSON document (RFC 8259): extensions such as trailing commas or
+    /// comments are rejected.
+    #[cfg(feature = "std")]
     pub fn from_json(src: &str) -> Result<Self, Error> {
         Ok(serde_json::from_str(src)?)
     }
 
+    #[cfg(feature = "std")]
     pub fn to_json(&self, buf: &mut String) {
         use serde::Serialize;
         use serde_json::Serializer;
@@ -198,8 +310,8 @@ impl Any {
     }
 }
 
-impl std::fmt::Display for Any {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Any {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Any::Null => f.write_str("null"),
             Any::Undefined => f.write_str("undefined"),
@@ -267,9 +379,8 @@ macro_rules! impl_from_bigint {
     ($t:ty) => {
         impl From<$t> for Any {
             fn from(value: $t) -> Self {
-                let v = value as f64;
-                if v <= F64_MAX_SAFE_INTEGER && v >= F64_MIN_SAFE_INTEGER {
-                    Self::Number(v)
+                if is_safe_integer(value as i64) {
+                    Self::Number(value as f64)
                 } else {
                     Self::BigInt(value as i64)
                 }
@@ -306,11 +417,11 @@ impl TryFrom<u64> for Any {
         if value > i64::MAX.abs() as u64 {
             Err(value)
         } else {
-            let v = value as f64;
-            if v <= F64_MAX_SAFE_INTEGER && v >= F64_MIN_SAFE_INTEGER {
-                Ok(Any::Number(v))
+            let v = value as i64;
+            if is_safe_integer(v) {
+                Ok(Any::Number(v as f64))
             } else {
-                Ok(Any::BigInt(v as i64))
+                Ok(Any::BigInt(v))
             }
         }
     }
@@ -341,7 +452,7 @@ impl TryFrom<usize> for Any {
 
     #[cfg(target_pointer_width = "64")]
     fn try_from(value: usize) -> Result<Self, Self::Error> {
-        use std::convert::TryInto;
+        use core::convert::TryInto;
         if let Ok(v) = (value as u64).try_into() {
             Ok(v)
         } else {
@@ -491,6 +602,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 impl<T> From<HashMap<String, T>> for Any
 where
     T: Into<Any>,
@@ -504,6 +616,25 @@ where
     }
 }
 
+impl<T> From<BTreeMap<String, T>> for Any
+where
+    T: Into<Any>,
+{
+    fn from(v: BTreeMap<String, T>) -> Any {
+        let map = v.into_iter().map(|(key, value)| (key, value.into()));
+        Any::Map(Arc::new(map.collect()))
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(not(feature = "std"))]
+    pub use alloc::collections::BTreeMap as Map;
+    pub use alloc::sync::Arc;
+    #[cfg(feature = "std")]
+    pub use std::collections::HashMap as Map;
+}
+
 // This code is based on serde_json::json! macro (see: https://docs.rs/serde_json/latest/src/serde_json/macros.rs.html#53-58).
 // Kudos to the original authors.
 
@@ -712,14 +843,16 @@ macro_rules! any_internal {
     };
 
     ({}) => {
-        $crate::any::Any::Map(std::sync::Arc::new(std::collections::HashMap::new()))
+        $crate::any::Any::Map($crate::any::__private::Arc::new(
+            $crate::any::__private::Map::new(),
+        ))
     };
 
     ({ $($tt:tt)+ }) => {
         $crate::any::Any::Map({
-            let mut object = std::collections::HashMap::new();
+            let mut object = $crate::any::__private::Map::new();
             any_internal!(@object object () ($($tt)+) ($($tt)+));
-            std::sync::Arc::new(object)
+            $crate::any::__private::Arc::new(object)
         })
     };
 
@@ -734,7 +867,7 @@ macro_rules! any_internal {
 #[doc(hidden)]
 macro_rules! any_internal_array {
     ($($content:tt)*) => {
-        std::sync::Arc::from([$($content)*])
+        $crate::any::__private::Arc::from([$($content)*])
     };
 }
 
@@ -749,3 +882,85 @@ macro_rules! any_unexpected {
 macro_rules! any_expect_expr_comma {
     ($e:expr , $($tt:tt)*) => {};
 }
+
+#[cfg(test)]
+mod test {
+    use crate::any::{Any, NumberConversionError, F64_MAX_SAFE_INTEGER};
+    use crate::encoding::read::Cursor;
+    use crate::encoding::write::Write;
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+
+    fn roundtrip(any: &Any) -> Any {
+        let mut buf = Vec::new();
+        any.encode(&mut buf);
+        Any::decode(&mut Cursor::new(&buf)).unwrap()
+    }
+
+    #[test]
+    fn large_integers_are_not_rounded() {
+        let id = i64::MAX - 1;
+        let any = Any::from(id);
+        assert_eq!(any, Any::BigInt(id));
+        assert_eq!(roundtrip(&any).as_i64(), Some(id));
+
+        let any = Any::try_from(u64::MAX >> 2).unwrap();
+        assert_eq!(any.as_i64(), Some((u64::MAX >> 2) as i64));
+
+        let safe = F64_MAX_SAFE_INTEGER as i64;
+        assert_eq!(Any::from(safe), Any::Number(F64_MAX_SAFE_INTEGER));
+        assert_eq!(roundtrip(&Any::from(safe)).as_i64(), Some(safe));
+    }
+
+    #[test]
+    fn decoded_integers_outside_of_safe_range() {
+        // integers written by non-JS encoders are decoded as numbers, like they always were,
+        // but checked conversions report that their precision has been lost
+        let mut buf = Vec::new();
+        buf.write_u8(125);
+        buf.write_var(i64::MAX - 1);
+        let any = Any::decode(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(any, Any::Number((i64::MAX - 1) as f64));
+        assert_eq!(any.try_to_i64(), Err(NumberConversionError::OutOfRange));
+    }
+
+    #[test]
+    fn checked_number_conversions() {
+        assert!(Any::Number(12.0).is_integer());
+        assert!(Any::BigInt(12).is_integer());
+        assert!(!Any::Number(1.5).is_integer());
+
+        assert_eq!(Any::Number(-3.0).try_to_i64(), Ok(-3));
+        assert_eq!(
+            Any::Number(1.5).try_to_i64(),
+            Err(NumberConversionError::NotAnInteger)
+        );
+        assert_eq!(
+            Any::Number(1e300).try_to_i64(),
+            Err(NumberConversionError::OutOfRange)
+        );
+        assert_eq!(
+            Any::from("1").try_to_i64(),
+            Err(NumberConversionError::NotANumber)
+        );
+
+        assert_eq!(Any::BigInt(1 << 40).try_to_f64(), Ok((1i64 << 40) as f64));
+        assert_eq!(
+            Any::BigInt(i64::MAX).try_to_f64(),
+            Err(NumberConversionError::OutOfRange)
+        );
+        assert_eq!(Any::Number(0.25).as_f64(), Some(0.25));
+    }
+
+    #[test]
+    fn json_errors_report_location() {
+        let err = Any::from_json("{\n  \"a\": [1, 2,]\n}").unwrap_err();
+        assert_eq!(err.json_location().map(|(line, _)| line), Some(2));
+
+        let err = Any::from_json("[1, 2").unwrap_err();
+        assert_eq!(err.json_location(), Some((1, 5)));
+
+        let err = Any::from_json("[1, 2] // comment").unwrap_err();
+        assert_eq!(err.json_location(), Some((1, 8)));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::branch::{Branch, BranchPtr};
-use crate::doc::{DocAddr, OffsetKind};
-use crate::encoding::read::Error;
+use crate::doc::{DocAddr, OffsetKind, QuotaExceeded};
+use crate::encoding::read::{Error, MAX_PREALLOCATED_LEN};
+use crate::encoding::write::Write;
 use crate::gc::GCCollector;
 use crate::moving::Move;
 use crate::slice::{BlockSlice, GCSlice, ItemSlice};
@@ -15,6 +16,7 @@ use crate::utils::OptionExt;
 use crate::*;
 use serde::{Deserialize, Serialize};
 use smallstr::SmallString;
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::fmt::Formatter;
@@ -93,9 +95,62 @@ pub struct ID {
 }
 
 impl ID {
+    /// Creates a new block identifier from a given `client` identifier and `clock` value.
     pub fn new(client: ClientID, clock: u32) -> Self {
         ID { client, clock }
     }
+
+    /// Returns an identifier of the element inserted by the same client `offset` elements after
+    /// current one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resulting clock value would overflow. See [ID::checked_offset] for
+    /// non-panicking alternative.
+    pub fn offset(&self, offset: u32) -> ID {
+        self.checked_offset(offset).expect("ID clock overflow")
+    }
+
+    /// Returns an identifier of the element inserted by the same client `offset` elements after
+    /// current one, or `None` if the resulting clock value would overflow.
+    pub fn checked_offset(&self, offset: u32) -> Option<ID> {
+        Some(ID::new(self.client, self.clock.checked_add(offset)?))
+    }
+
+    /// Returns a number of elements inserted by the same client between `other` identifier and
+    /// current one. Returns `None` if both identifiers belong to different clients or `other`
+    /// was inserted after current one.
+    pub fn distance_from(&self, other: &ID) -> Option<u32> {
+        if self.client == other.client {
+            self.clock.checked_sub(other.clock)
+        } else {
+            None
+        }
+    }
+}
+
+/// Error returned when an [ID] could not be parsed from its string representation.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid block ID '{0}': expected format '<client#clock>'")]
+pub struct ParseIDError(String);
+
+impl std::str::FromStr for ID {
+    type Err = ParseIDError;
+
+    /// Parses an [ID] from the format produced by its [Display](std::fmt::Display)
+    /// implementation, ie. `<client#clock>`. Angle brackets are optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseIDError(s.to_string());
+        let inner = s.trim();
+        let inner = match inner.strip_prefix('<') {
+            Some(inner) => inner.strip_suffix('>').ok_or_else(err)?,
+            None => inner,
+        };
+        let (client, clock) = inner.split_once('#').ok_or_else(err)?;
+        let client = client.parse().map_err(|_| err())?;
+        let clock = clock.parse().map_err(|_| err())?;
+        Ok(ID::new(client, clock))
+    }
 }
 
 pub(crate) enum BlockCell {
@@ -239,12 +294,12 @@ impl ItemPtr {
         items_to_delete: &DeleteSet,
         s1: &UndoStack<M>,
         s2: &UndoStack<M>,
-    ) -> Option<ItemPtr> {
+    ) -> Result<Option<ItemPtr>, QuotaExceeded> {
         let self_ptr = self.clone();
         let item = self.deref_mut();
         if let Some(redone) = item.redone.as_ref() {
-            let slice = txn.store.blocks.get_item_clean_start(redone)?;
-            return Some(txn.store.materialize(slice));
+            let slice = txn.store.blocks.get_item_clean_start(redone);
+            return Ok(slice.map(|slice| txn.store.materialize(slice)));
         }
 
         let mut parent_block = item.parent.as_branch().and_then(|b| b.item);
@@ -255,10 +310,10 @@ impl ItemPtr {
                 if parent.redone.is_none()
                     && (!redo_items.contains(&parent)
                         || parent
-                            .redo(txn, redo_items, items_to_delete, s1, s2)
+                            .redo(txn, redo_items, items_to_delete, s1, s2)?
                             .is_none())
                 {
-                    return None;
+                    return Ok(None);
                 }
                 let mut redone = parent.redone;
                 while let Some(id) = redone.as_ref() {
@@ -323,7 +378,7 @@ impl ItemPtr {
                     if left_item.right.is_some() {
                         // It is not possible to redo this item because it conflicts with a
                         // change from another client
-                        return None;
+                        return Ok(None);
                     }
                 }
             } else {
@@ -400,12 +455,14 @@ impl ItemPtr {
         );
         item.redone = Some(*redone_item.id());
         redone_item.info.set_keep();
+        txn.ensure_writable();
+        txn.try_reserve_quota(&redone_item.content)?;
         let mut block_ptr = ItemPtr::from(&mut redone_item);
 
         block_ptr.integrate(txn, 0);
 
         txn.store_mut().blocks.push_block(redone_item);
-        Some(block_ptr)
+        Ok(Some(block_ptr))
     }
 
     pub(crate) fn keep(&self, keep: bool) {
@@ -632,6 +689,26 @@ impl ItemPtr {
                 this.right = r;
             }
 
+            if let Some(parent_sub) = &this.parent_sub {
+                if txn.store.options.map_conflict_resolver.is_some() {
+                    // current item either lost with a concurrent write it didn't know about or
+                    // overrides a concurrent write it didn't know about
+                    let loser = match (this.left, this.right) {
+                        (_, Some(_)) => Some(self_ptr),
+                        (Some(left), None)
+                            if !left.is_deleted() && this.origin != Some(left.last_id()) =>
+                        {
+                            Some(left)
+                        }
+                        _ => None,
+                    };
+                    if let Some(loser) = loser {
+                        txn.map_conflicts
+                            .push((parent_ref, parent_sub.clone(), loser));
+                    }
+                }
+            }
+
             if let Some(right) = this.right.as_deref_mut() {
                 right.left = Some(self_ptr);
             } else if let Some(parent_sub) = &this.parent_sub {
@@ -1265,6 +1342,36 @@ impl Item {
         self.info.is_deleted()
     }
 
+    /// Checks if the first element of current item is visible to a given transaction. For
+    /// transactions gated by a snapshot (see [Doc::transact_at]) this means that it was already
+    /// present and not yet deleted at that snapshot. Otherwise this is an equivalent of
+    /// `!item.is_deleted()`. Use [Item::visible_ranges] for items that may consist of many
+    /// elements.
+    ///
+    /// [Doc::transact_at]: crate::Doc::transact_at
+    pub(crate) fn is_visible<T: ReadTxn>(&self, txn: &T) -> bool {
+        match txn.gated_snapshot() {
+            Some(gate) => gate.is_visible(&self.id),
+            None => !self.is_deleted(),
+        }
+    }
+
+    /// Calls `f` for every range of offsets of elements within current item, which are visible
+    /// to a given transaction (see [Item::is_visible]). Item may be visible only partially to
+    /// transactions gated by a snapshot, if the snapshot was taken in the middle of its insertion
+    /// or deletion.
+    pub(crate) fn visible_ranges<T, F>(&self, txn: &T, mut f: F)
+    where
+        T: ReadTxn,
+        F: FnMut(std::ops::Range<u32>),
+    {
+        match txn.gated_snapshot() {
+            Some(gate) => gate.visible_ranges(&self.id, self.len, f),
+            None if self.is_deleted() => {}
+            None => f(0..self.len),
+        }
+    }
+
     /// Checks if item content can be considered countable. Countable elements can be split
     /// and joined together.
     pub fn is_countable(&self) -> bool {
@@ -1279,7 +1386,7 @@ impl Item {
     /// blocks to be already present in block store - which may not be the case during block
     /// decoding. We decode entire update first, and apply individual blocks second, hence
     /// repair function is called before applying the block rather than on decode.
-    pub(crate) fn repair(&mut self, store: &mut Store) {
+    pub(crate) fn repair(&mut self, store: &mut Store) -> Result<(), UpdateError> {
         if let Some(origin) = self.origin.as_ref() {
             self.left = store
                 .blocks
@@ -1328,13 +1435,25 @@ impl Item {
                             TypePtr::Branch(BranchPtr::from(branch.as_ref()))
                         }
                         ItemContent::Deleted(_) => TypePtr::Unknown,
-                        _ => panic!("Defect: parent points to a block which is not a shared type"),
+                        _ => return Err(UpdateError::InvalidParent(self.id, *id)),
                     }
                 } else {
                     TypePtr::Unknown
                 }
             }
         };
+
+        if let (ItemContent::Move(m), TypePtr::Branch(parent)) = (&self.content, &self.parent) {
+            // moved range must be a part of the same collection as the move itself
+            for id in [m.start.id(), m.end.id()].iter().flatten() {
+                if let Some(item) = store.blocks.get_item(id) {
+                    if item.parent != TypePtr::Branch(*parent) {
+                        return Err(UpdateError::InvalidMove(self.id));
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Returns a length of a block. For most situation it works like [Item::content_len] with a
@@ -1365,6 +1484,7 @@ impl Item {
 
     pub(crate) fn gc(&mut self, collector: &mut GCCollector, parent_gc: bool) {
         if self.is_deleted() && !self.info.is_keep() {
+            collector.release(&self.content);
             self.content.gc(collector);
             let len = self.len();
             if parent_gc {
@@ -1496,6 +1616,28 @@ pub(crate) fn split_str(str: &str, offset: usize, kind: OffsetKind) -> (&str, &s
     str.split_at(off)
 }
 
+/// Returns a part of a given `str` between UTF-16 offsets of a given `range`.
+pub(crate) fn utf16_slice(str: &str, range: std::ops::Range<u32>) -> &str {
+    let (_, rest) = split_str(str, range.start as usize, OffsetKind::Utf16);
+    let (slice, _) = split_str(rest, (range.end - range.start) as usize, OffsetKind::Utf16);
+    slice
+}
+
+/// Kind of content stored in a block. See: [ItemContent::kind].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentKind {
+    Any,
+    Binary,
+    Deleted,
+    Doc,
+    Json,
+    Embed,
+    Format,
+    String,
+    Type,
+    Move,
+}
+
 /// An enum describing the type of a user data content stored as part of one or more
 /// (if items were squashed) insert operations.
 #[derive(Debug, PartialEq)]
@@ -1537,6 +1679,22 @@ pub enum ItemContent {
 }
 
 impl ItemContent {
+    /// Returns a kind of current content.
+    pub fn kind(&self) -> ContentKind {
+        match self {
+            ItemContent::Any(_) => ContentKind::Any,
+            ItemContent::Binary(_) => ContentKind::Binary,
+            ItemContent::Deleted(_) => ContentKind::Deleted,
+            ItemContent::Doc(_, _) => ContentKind::Doc,
+            ItemContent::JSON(_) => ContentKind::Json,
+            ItemContent::Embed(_) => ContentKind::Embed,
+            ItemContent::Format(_, _) => ContentKind::Format,
+            ItemContent::String(_) => ContentKind::String,
+            ItemContent::Type(_) => ContentKind::Type,
+            ItemContent::Move(_) => ContentKind::Move,
+        }
+    }
+
     /// Returns a reference number used to determine a content type.
     /// It's used during encoding/decoding of a containing block.
     pub fn get_ref_number(&self) -> u8 {
@@ -1554,6 +1712,39 @@ impl ItemContent {
         }
     }
 
+    /// Returns an approximate number of bytes occupied by current content, used to enforce
+    /// [Options::max_content_bytes]. Primitive values are measured by the length of their binary
+    /// encoding, while nested shared types, sub-documents and tombstones are not counted.
+    pub(crate) fn byte_size(&self) -> usize {
+        /// Sink which only counts the bytes written into it.
+        struct ByteCounter(usize);
+
+        impl Write for ByteCounter {
+            #[inline]
+            fn write_all(&mut self, buf: &[u8]) {
+                self.0 += buf.len();
+            }
+        }
+
+        fn any_size(any: &Any) -> usize {
+            let mut counter = ByteCounter(0);
+            any.encode(&mut counter);
+            counter.0
+        }
+        match self {
+            ItemContent::Any(values) => values.iter().map(any_size).sum(),
+            ItemContent::Binary(bytes) => bytes.len(),
+            ItemContent::JSON(values) => values.iter().map(String::len).sum(),
+            ItemContent::Embed(value) => any_size(value),
+            ItemContent::Format(key, value) => key.len() + any_size(value),
+            ItemContent::String(s) => s.len(OffsetKind::Bytes),
+            ItemContent::Deleted(_)
+            | ItemContent::Doc(_, _)
+            | ItemContent::Type(_)
+            | ItemContent::Move(_) => 0,
+        }
+    }
+
     /// Checks if item content can be considered countable. Countable elements contribute to
     /// a length of the block they are contained by. Most of the item content variants are countable
     /// with exception for [ItemContent::Deleted] (which length describes number of removed
@@ -1784,7 +1975,7 @@ impl ItemContent {
             BLOCK_ITEM_JSON_REF_NUMBER => {
                 let mut remaining = decoder.read_len()? as i32;
                 let mut buf = Vec::new();
-                buf.try_reserve(remaining as usize)?;
+                buf.try_reserve((remaining.max(0) as usize).min(MAX_PREALLOCATED_LEN))?;
 
                 while remaining >= 0 {
                     buf.push(decoder.read_string()?.to_owned());
@@ -1807,7 +1998,7 @@ impl ItemContent {
             BLOCK_ITEM_ANY_REF_NUMBER => {
                 let len = decoder.read_len()? as usize;
                 let mut values = Vec::new();
-                values.try_reserve(len)?;
+                values.try_reserve(len.min(MAX_PREALLOCATED_LEN))?;
 
                 let mut i = 0;
                 while i < len {
@@ -2056,8 +2247,10 @@ impl std::fmt::Display for ItemContent {
                     write!(f, "<xml element: {}>", name)
                 }
                 TypeRef::XmlFragment => write!(f, "<xml fragment>"),
-                TypeRef::XmlHook => write!(f, "<xml hook>"),
+                TypeRef::XmlHook(name) => write!(f, "<xml hook: {}>", name),
                 TypeRef::XmlText => write!(f, "<xml text>"),
+                TypeRef::KeyedList => write!(f, "<keyed list>"),
+                TypeRef::Set => write!(f, "<set>"),
                 #[cfg(feature = "weak")]
                 TypeRef::WeakLink(s) => write!(f, "<weak({}..{})>", s.quote_start, s.quote_end),
                 _ => write!(f, "<undefined type ref>"),
@@ -2123,7 +2316,12 @@ pub(crate) struct PrelimString(pub SmallString<[u8; 8]>);
 impl Prelim for PrelimString {
     type Return = Unused;
 
-    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+    fn into_content(self, txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        if let Some(normalizer) = txn.store().options.text_normalizer.clone() {
+            if let Cow::Owned(normalized) = normalizer.normalize(&self.0) {
+                return (ItemContent::String(normalized.as_str().into()), None);
+            }
+        }
         (ItemContent::String(self.0.into()), None)
     }
 
@@ -2188,6 +2386,69 @@ where
     }
 }
 
+/// Prelim container for binary buffers (eg. images or attachments), which can be inserted into
+/// [Array] and used as [Text] embeds. Unlike [Any::Buffer], which is serialized as a part of
+/// JSON-like values, a binary prelim is stored as a dedicated [ItemContent::Binary] block that is
+/// encoded as raw bytes and can be read lazily using [Array::read_binary_at] without
+/// materializing a whole buffer.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Array, Doc, Transact};
+/// use yrs::block::BinaryPrelim;
+///
+/// let doc = Doc::new();
+/// let array = doc.get_or_insert_array("attachments");
+/// let mut txn = doc.transact_mut();
+/// array.push_back(&mut txn, BinaryPrelim::from(vec![1u8, 2, 3, 4, 5]));
+///
+/// let mut buf = [0u8; 3];
+/// assert_eq!(array.binary_len(&txn, 0), Some(5));
+/// assert_eq!(array.read_binary_at(&txn, 0, 2, &mut buf), Some(3));
+/// assert_eq!(buf, [3, 4, 5]);
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryPrelim(Vec<u8>);
+
+impl BinaryPrelim {
+    pub fn new(data: Vec<u8>) -> Self {
+        BinaryPrelim(data)
+    }
+}
+
+impl Prelim for BinaryPrelim {
+    type Return = Unused;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        (ItemContent::Binary(self.0), None)
+    }
+
+    fn integrate(self, _txn: &mut TransactionMut, _inner_ref: BranchPtr) {}
+}
+
+impl From<Vec<u8>> for BinaryPrelim {
+    #[inline]
+    fn from(data: Vec<u8>) -> Self {
+        BinaryPrelim(data)
+    }
+}
+
+impl<'a> From<&'a [u8]> for BinaryPrelim {
+    #[inline]
+    fn from(data: &'a [u8]) -> Self {
+        BinaryPrelim(data.to_vec())
+    }
+}
+
+impl From<BinaryPrelim> for EmbedPrelim<BinaryPrelim> {
+    #[inline]
+    fn from(value: BinaryPrelim) -> Self {
+        EmbedPrelim::Shared(value)
+    }
+}
+
 impl std::fmt::Display for ID {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "<{}#{}>", self.client, self.clock)
@@ -2209,10 +2470,36 @@ impl std::fmt::Display for ItemPtr {
 
 #[cfg(test)]
 mod test {
-    use crate::block::{split_str, SplittableString};
+    use crate::block::{split_str, ClientID, ParseIDError, SplittableString};
     use crate::doc::OffsetKind;
+    use crate::ID;
     use std::ops::Deref;
 
+    #[test]
+    fn id_string_representation() {
+        let id = ID::new(ClientID::MAX, 123);
+        let str = id.to_string();
+        assert_eq!(str, "<18446744073709551615#123>");
+        assert_eq!(str.parse::<ID>(), Ok(id));
+        assert_eq!("1#2".parse::<ID>(), Ok(ID::new(1, 2)));
+        assert_eq!("<1#2".parse::<ID>(), Err(ParseIDError("<1#2".to_string())));
+        assert!("1-2".parse::<ID>().is_err());
+        assert!("a#2".parse::<ID>().is_err());
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(serde_json::from_str::<ID>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn id_clock_arithmetic() {
+        let id = ID::new(1, 10);
+        assert_eq!(id.offset(5), ID::new(1, 15));
+        assert_eq!(ID::new(1, u32::MAX).checked_offset(1), None);
+        assert_eq!(id.offset(5).distance_from(&id), Some(5));
+        assert_eq!(id.distance_from(&id.offset(5)), None);
+        assert_eq!(id.distance_from(&ID::new(2, 0)), None);
+    }
+
     #[test]
     fn splittable_string_len() {
         let s: SplittableString = "Zażółć gęślą jaźń😀 女".into();
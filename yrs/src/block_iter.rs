@@ -2,7 +2,7 @@ use crate::block::{Item, ItemContent, ItemPtr, Prelim};
 use crate::branch::BranchPtr;
 use crate::moving::{Move, StickyIndex};
 use crate::transaction::{ReadTxn, TransactionMut};
-use crate::types::{TypePtr, Value};
+use crate::types::{EditError, TypePtr, Value};
 use crate::{Assoc, ID};
 
 /// Struct used for iterating over the sequence of item's values with respect to a potential
@@ -359,6 +359,17 @@ impl BlockIter {
     }
 
     pub(crate) fn slice<T: ReadTxn>(&mut self, txn: &T, buf: &mut [Value]) -> u32 {
+        self.slice_with_ids(txn, buf, None)
+    }
+
+    /// Works like [BlockIter::slice], but also writes the [ID]s of all read elements into `ids`
+    /// buffer (if provided), which must be at least as long as `buf`.
+    pub(crate) fn slice_with_ids<T: ReadTxn>(
+        &mut self,
+        txn: &T,
+        buf: &mut [Value],
+        mut ids: Option<&mut [ID]>,
+    ) -> u32 {
         let mut len = buf.len() as u32;
         if self.index + len > self.branch.content_len() {
             return 0;
@@ -381,6 +392,12 @@ impl BlockIter {
                                 .content
                                 .read(self.rel as usize, &mut buf[read as usize..])
                                 as u32;
+                            if let Some(ids) = ids.as_deref_mut() {
+                                for i in 0..r {
+                                    ids[(read + i) as usize] =
+                                        ID::new(item.id.client, item.id.clock + self.rel + i);
+                                }
+                            }
                             read += r;
                             len -= r;
                             if self.rel + r == item.content_len(encoding) {
@@ -458,6 +475,21 @@ impl BlockIter {
     }
 
     pub fn insert_contents<V: Prelim>(&mut self, txn: &mut TransactionMut, value: V) -> ItemPtr {
+        match self.try_insert_contents(txn, value) {
+            Ok(ptr) => ptr,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Inserts a `value` at the current position of an iterator. Returns an error without
+    /// modifying a document if current transaction cannot make local edits or inserted content
+    /// would exceed document quotas.
+    pub fn try_insert_contents<V: Prelim>(
+        &mut self,
+        txn: &mut TransactionMut,
+        value: V,
+    ) -> Result<ItemPtr, EditError> {
+        txn.check_writable()?;
         self.reduce_moves(txn);
         self.split_rel(txn);
         let id = {
@@ -485,6 +517,7 @@ impl BlockIter {
             None,
             content,
         );
+        txn.try_reserve_quota(&block.content)?;
         let mut block_ptr = ItemPtr::from(&mut block);
 
         block_ptr.integrate(txn, 0);
@@ -502,7 +535,7 @@ impl BlockIter {
             self.reached_end = true;
         }
 
-        block_ptr
+        Ok(block_ptr)
     }
 
     pub fn insert_move(&mut self, txn: &mut TransactionMut, start: StickyIndex, end: StickyIndex) {
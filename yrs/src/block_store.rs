@@ -1,4 +1,4 @@
-use crate::block::{BlockCell, BlockRange, ClientID, Item, ItemPtr, GC, ID};
+use crate::block::{BlockCell, BlockRange, ClientID, Item, ItemContent, ItemPtr, GC, ID};
 use crate::encoding::read::Error;
 use crate::slice::ItemSlice;
 use crate::types::TypePtr;
@@ -61,11 +61,13 @@ impl ClientBlockList {
     /// found using binary search algorithm, or a index under which this block should be inserted.
     pub(crate) fn find_pivot(&self, clock: u32) -> Option<usize> {
         let mut left = 0;
-        let mut right = self.list.len() - 1;
+        let mut right = self.list.len().checked_sub(1)?;
         let mut block = &self[right];
         let (mut start, mut end) = block.clock_range();
         if start == clock {
             Some(right)
+        } else if clock > end {
+            None
         } else {
             let mut mid = ((clock / end) * right as u32) as usize;
             while left <= right {
@@ -76,6 +78,8 @@ impl ClientBlockList {
                         return Some(mid);
                     }
                     left = mid + 1;
+                } else if mid == 0 {
+                    break;
                 } else {
                     right = mid - 1;
                 }
@@ -111,6 +115,11 @@ impl ClientBlockList {
         self.list.insert(index, cell);
     }
 
+    /// Reserves capacity for at least `additional` more blocks to be pushed into this list.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.list.reserve(additional);
+    }
+
     /// Returns a number of blocks stored within this list.
     pub fn len(&self) -> usize {
         self.list.len()
@@ -125,7 +134,10 @@ impl ClientBlockList {
     /// squashed into its left neighbor. In such case a squash result will be returned in order to
     /// later on rewire left/right neighbor changes that may have occurred as a result of squashing
     /// and block removal.
-    pub(crate) fn squash_left(&mut self, index: usize) {
+    ///
+    /// Text blocks are squashed only as long as their combined length (in bytes) doesn't exceed
+    /// a given `text_block_capacity`.
+    pub(crate) fn squash_left(&mut self, index: usize, text_block_capacity: usize) {
         let (l, r) = self.list.split_at_mut(index);
         let left = &mut l[index - 1];
         let right = &mut r[0];
@@ -137,6 +149,15 @@ impl ClientBlockList {
             (BlockCell::Block(left), BlockCell::Block(right)) => {
                 let mut left = ItemPtr::from(left);
                 let right = ItemPtr::from(right);
+                if let (ItemContent::String(l), ItemContent::String(r)) =
+                    (&left.content, &right.content)
+                {
+                    let len = l.len(OffsetKind::Bytes) + r.len(OffsetKind::Bytes);
+                    if len > text_block_capacity {
+                        // keep large text blocks apart, so that splitting them stays cheap
+                        return;
+                    }
+                }
                 if left.try_squash(right) {
                     if let Some(key) = right.parent_sub.as_deref() {
                         if let TypePtr::Branch(mut parent) = right.parent {
@@ -204,6 +225,11 @@ impl BlockStore {
         }
     }
 
+    /// Returns a total number of blocks (both items and GC-ed ranges) stored by all clients.
+    pub fn block_count(&self) -> usize {
+        self.clients.values().map(|list| list.len()).sum()
+    }
+
     pub fn push_block(&mut self, block: Box<Item>) {
         let id = block.id();
         match self.clients.entry(id.client) {
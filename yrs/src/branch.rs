@@ -1,14 +1,18 @@
-use crate::block::{BlockCell, Item, ItemContent, ItemPosition, ItemPtr, Prelim};
+use crate::block::{
+    utf16_slice, BlockCell, ContentKind, Item, ItemContent, ItemPosition, ItemPtr, Prelim,
+};
 use crate::types::array::ArrayEvent;
+use crate::types::keyed_list::{KeyedListEvent, KeyedListRef};
 use crate::types::map::MapEvent;
+use crate::types::set::{SetEvent, SetRef};
 use crate::types::text::TextEvent;
 use crate::types::xml::{XmlEvent, XmlTextEvent};
 use crate::types::{
-    Entries, Event, Events, Path, PathSegment, RootRef, SharedRef, TypePtr, TypeRef,
+    map_entry, Entries, Event, Events, Path, PathSegment, RootRef, SharedRef, TypePtr, TypeRef,
 };
 use crate::{
-    ArrayRef, Doc, MapRef, Observer, Origin, ReadTxn, Subscription, TextRef, TransactionMut, Value,
-    WriteTxn, XmlElementRef, XmlFragmentRef, XmlTextRef, ID,
+    ArrayRef, Doc, MapRef, Observer, OffsetKind, Origin, ReadTxn, Snapshot, Subscription, TextRef,
+    TransactionMut, Value, WriteTxn, XmlElementRef, XmlFragmentRef, XmlTextRef, ID,
 };
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
@@ -36,12 +40,21 @@ impl BranchPtr {
         subs: HashSet<Option<Arc<str>>>,
     ) -> Option<Event> {
         let e = self.make_event(subs)?;
-        self.observers.trigger(|fun| fun(txn, &e));
+        let hook = txn.store().options.callback_panic_hook.as_ref();
+        txn.defer_panic(self.observers.trigger(hook, |fun| fun(txn, &e)));
         Some(e)
     }
 
     pub(crate) fn trigger_deep(&self, txn: &TransactionMut, e: &Events) {
-        self.deep_observers.trigger(|fun| fun(txn, e));
+        let hook = txn.store().options.callback_panic_hook.as_ref();
+        txn.defer_panic(self.deep_observers.trigger(hook, |fun| fun(txn, e)));
+    }
+
+    /// Returns a mutable reference to metadata of a pointed branch. Caller must guarantee an
+    /// exclusive access to a branch (i.e. by holding a read-write transaction) for the whole
+    /// lifetime `'a`.
+    pub(crate) unsafe fn meta_mut<'a>(self) -> &'a mut BranchMeta {
+        &mut (*self.0.as_ptr()).meta
     }
 }
 
@@ -120,9 +133,12 @@ impl Into<Value> for BranchPtr {
             TypeRef::XmlElement(_) => Value::YXmlElement(XmlElementRef::from(self)),
             TypeRef::XmlFragment => Value::YXmlFragment(XmlFragmentRef::from(self)),
             TypeRef::XmlText => Value::YXmlText(XmlTextRef::from(self)),
-            //TYPE_REFS_XML_HOOK => Value::YXmlHook(XmlHookRef::from(self)),
+            // hooks are exposed as generic maps, see: XmlHookRef::try_from
+            TypeRef::XmlHook(_) => Value::YMap(MapRef::from(self)),
             #[cfg(feature = "weak")]
             TypeRef::WeakLink(_) => Value::YWeakLink(crate::WeakRef::from(self)),
+            TypeRef::KeyedList => Value::YKeyedList(KeyedListRef::from(self)),
+            TypeRef::Set => Value::YSet(SetRef::from(self)),
             _ => Value::UndefinedRef(self),
         }
     }
@@ -199,6 +215,9 @@ pub struct Branch {
     pub(crate) observers: Observer<ObserveFn>,
 
     pub(crate) deep_observers: Observer<DeepObserveFn>,
+
+    /// Local, non-replicated metadata attached to this branch. See [BranchMeta].
+    pub(crate) meta: BranchMeta,
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -211,6 +230,86 @@ type ObserveFn = Box<dyn Fn(&TransactionMut, &Event) + 'static>;
 #[cfg(target_family = "wasm")]
 type DeepObserveFn = Box<dyn Fn(&TransactionMut, &Events) + 'static>;
 
+#[cfg(not(target_family = "wasm"))]
+type MetaValue = dyn std::any::Any + Send + Sync;
+#[cfg(target_family = "wasm")]
+type MetaValue = dyn std::any::Any;
+
+/// Local metadata attached to a [Branch] of a shared collection. It's a type map, which can hold
+/// at most one value of any given type, eg. editor-specific caches, schema versions or rendering
+/// hints. Metadata is **not replicated** - it's not encoded in document updates and it's not
+/// persisted - and lives only as long as the branch it's attached to.
+///
+/// Metadata can be accessed via [SharedRef::meta] and [SharedRef::meta_mut].
+#[derive(Default)]
+pub struct BranchMeta(HashMap<std::any::TypeId, Box<MetaValue>>);
+
+impl BranchMeta {
+    /// Returns a reference to a value of type `T`, if it was inserted before.
+    pub fn get<T: std::any::Any>(&self) -> Option<&T> {
+        let value = self.0.get(&std::any::TypeId::of::<T>())?;
+        value.downcast_ref()
+    }
+
+    /// Returns a mutable reference to a value of type `T`, if it was inserted before.
+    pub fn get_mut<T: std::any::Any>(&mut self) -> Option<&mut T> {
+        let value = self.0.get_mut(&std::any::TypeId::of::<T>())?;
+        value.downcast_mut()
+    }
+
+    /// Inserts a `value`, returning the previous value of the same type if there was any.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn insert<T: std::any::Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        let prev = self
+            .0
+            .insert(std::any::TypeId::of::<T>(), Box::new(value))?;
+        prev.downcast().ok().map(|prev| *prev)
+    }
+
+    /// Inserts a `value`, returning the previous value of the same type if there was any.
+    #[cfg(target_family = "wasm")]
+    pub fn insert<T: std::any::Any>(&mut self, value: T) -> Option<T> {
+        let prev = self
+            .0
+            .insert(std::any::TypeId::of::<T>(), Box::new(value))?;
+        prev.downcast().ok().map(|prev| *prev)
+    }
+
+    /// Removes and returns a value of type `T`, if there was any.
+    pub fn remove<T: std::any::Any>(&mut self) -> Option<T> {
+        let value = self.0.remove(&std::any::TypeId::of::<T>())?;
+        value.downcast().ok().map(|value| *value)
+    }
+
+    /// Checks if a value of type `T` was inserted.
+    pub fn contains<T: std::any::Any>(&self) -> bool {
+        self.0.contains_key(&std::any::TypeId::of::<T>())
+    }
+
+    /// Returns a number of values stored in this metadata map.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Checks if this metadata map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Removes all values stored in this metadata map.
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+impl std::fmt::Debug for BranchMeta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BranchMeta")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
 impl std::fmt::Debug for Branch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         std::fmt::Display::fmt(self, f)
@@ -241,6 +340,7 @@ impl Branch {
             type_ref,
             observers: Observer::default(),
             deep_observers: Observer::default(),
+            meta: BranchMeta::default(),
         })
     }
 
@@ -291,6 +391,38 @@ impl Branch {
         self.content_len
     }
 
+    /// Recomputes cached [Branch::len] and [Branch::content_len] of an indexed sequence component
+    /// of a current branch node by counting its non-deleted blocks. Returns `true` if cached
+    /// values were out of sync and had to be corrected.
+    ///
+    /// Under normal circumstances cached lengths are always up to date, so this method is meant
+    /// to be used as a diagnostic tool: see [IntegrityIssue::BranchLength].
+    ///
+    /// [IntegrityIssue::BranchLength]: crate::IntegrityIssue::BranchLength
+    pub fn recompute_lengths(&mut self, txn: &mut TransactionMut) -> bool {
+        let (block_len, content_len) = self.compute_lengths(txn.store().options.offset_kind);
+        let changed = self.block_len != block_len || self.content_len != content_len;
+        self.block_len = block_len;
+        self.content_len = content_len;
+        changed
+    }
+
+    /// Computes [Branch::len] and [Branch::content_len] by counting non-deleted blocks
+    /// of an indexed sequence component of a current branch node.
+    pub(crate) fn compute_lengths(&self, offset_kind: OffsetKind) -> (u32, u32) {
+        let mut block_len = 0;
+        let mut content_len = 0;
+        let mut current = self.start;
+        while let Some(item) = current {
+            if !item.is_deleted() && item.is_countable() {
+                block_len += item.len;
+                content_len += item.content_len(offset_kind);
+            }
+            current = item.right;
+        }
+        (block_len, content_len)
+    }
+
     /// Get iterator over (String, Block) entries of a map component of a current root type.
     /// Deleted blocks are skipped by this iterator.
     pub(crate) fn entries<'a, T: ReadTxn + 'a>(&'a self, txn: &'a T) -> Entries<'a, &'a T, T> {
@@ -305,13 +437,30 @@ impl Branch {
 
     /// Returns a materialized value of non-deleted entry under a given `key` of a map component
     /// of a current root type.
-    pub(crate) fn get<T: ReadTxn>(&self, _txn: &T, key: &str) -> Option<Value> {
-        let item = self.map.get(key)?;
-        if !item.is_deleted() {
-            item.content.get_last()
-        } else {
-            None
+    pub(crate) fn get<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<Value> {
+        let item = map_entry(self.map.get(key)?, txn)?;
+        item.content.get_last()
+    }
+
+    /// Returns an item of an indexed sequence component of a current branch node, which contains
+    /// an element visible to a given transaction at a given `index`, together with an offset of
+    /// that element within the item. Returns `None` if `index` is out of visible range.
+    pub(crate) fn find_visible<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<(ItemPtr, u32)> {
+        if let Some(gate) = txn.gated_snapshot() {
+            return gate.sequence(txn, self).find(index);
         }
+        let mut index = index;
+        let mut ptr = self.start;
+        while let Some(item) = ptr {
+            if !item.is_deleted() && item.is_countable() {
+                if index < item.len {
+                    return Some((item, index));
+                }
+                index -= item.len;
+            }
+            ptr = item.right;
+        }
+        None
     }
 
     /// Given an `index` parameter, returns an item content reference which contains that index
@@ -504,23 +653,50 @@ impl Branch {
                 path.push_front(PathSegment::Key(parent_sub));
             } else {
                 // parent is array-ish
-                let mut i = 0;
-                let mut c = child.start;
-                while let Some(ptr) = c {
-                    if ptr.id() == &item_id {
-                        break;
-                    }
-                    if !ptr.is_deleted() && ptr.is_countable() {
-                        i += ptr.len();
-                    }
-                    c = ptr.right;
-                }
-                path.push_front(PathSegment::Index(i));
+                path.push_front(PathSegment::Index(Self::index_of(child, &item_id)));
             }
         }
         path
     }
 
+    /// Returns a full path leading from a root-level type to a given `item`. First segment of
+    /// returned path is always a [PathSegment::Key] with a root type name. Returns `None` if any
+    /// of the item ancestors is not integrated into a block store.
+    pub(crate) fn item_path(mut item: ItemPtr) -> Option<Path> {
+        let mut path = VecDeque::default();
+        loop {
+            let parent = *item.parent.as_branch()?;
+            if let Some(parent_sub) = item.parent_sub.clone() {
+                path.push_front(PathSegment::Key(parent_sub));
+            } else {
+                path.push_front(PathSegment::Index(Self::index_of(parent, &item.id)));
+            }
+            match parent.item {
+                Some(ptr) => item = ptr,
+                None => {
+                    path.push_front(PathSegment::Key(parent.name.clone()?));
+                    return Some(path);
+                }
+            }
+        }
+    }
+
+    /// Returns an index of an item with a given `id` within an indexed sequence of `parent`.
+    fn index_of(parent: BranchPtr, id: &ID) -> u32 {
+        let mut i = 0;
+        let mut c = parent.start;
+        while let Some(ptr) = c {
+            if ptr.id() == id {
+                break;
+            }
+            if !ptr.is_deleted() && ptr.is_countable() {
+                i += ptr.len();
+            }
+            c = ptr.right;
+        }
+        i
+    }
+
     #[cfg(not(target_family = "wasm"))]
     pub fn observe<F>(&mut self, f: F) -> Subscription
     where
@@ -574,20 +750,6 @@ impl Branch {
         self.deep_observers.subscribe_with(key, Box::new(f))
     }
 
-    pub(crate) fn is_parent_of(&self, mut ptr: Option<ItemPtr>) -> bool {
-        while let Some(i) = ptr.as_deref() {
-            if let Some(parent) = i.parent.as_branch() {
-                if parent.deref() == self {
-                    return true;
-                }
-                ptr = parent.item;
-            } else {
-                break;
-            }
-        }
-        false
-    }
-
     pub(crate) fn make_event(&self, keys: HashSet<Option<Arc<str>>>) -> Option<Event> {
         let self_ptr = BranchPtr::from(self);
         let event = match self.type_ref() {
@@ -598,6 +760,8 @@ impl Branch {
                 Event::XmlFragment(XmlEvent::new(self_ptr, keys))
             }
             TypeRef::XmlText => Event::XmlText(XmlTextEvent::new(self_ptr, keys)),
+            TypeRef::KeyedList => Event::KeyedList(KeyedListEvent::new(self_ptr, keys)),
+            TypeRef::Set => Event::Set(SetEvent::new(self_ptr)),
             #[cfg(feature = "weak")]
             TypeRef::WeakLink(_) => Event::Weak(crate::types::weak::WeakEvent::new(self_ptr)),
             _ => return None,
@@ -607,6 +771,95 @@ impl Branch {
     }
 }
 
+/// Elements of an indexed sequence component of a [Branch], which are visible at a given snapshot,
+/// indexed by their position. It's built once per branch by a [SnapshotGate] and then reused by
+/// all reads of a gated transaction.
+///
+/// [SnapshotGate]: crate::SnapshotGate
+#[derive(Debug)]
+pub(crate) struct VisibleSequence {
+    parts: Vec<VisiblePart>,
+    /// Number of visible elements.
+    len: u32,
+    /// Length of visible content measured using document's [OffsetKind].
+    content_len: u32,
+}
+
+/// Range of elements of a single item, visible at a given snapshot.
+#[derive(Debug, Clone)]
+pub(crate) struct VisiblePart {
+    pub item: ItemPtr,
+    /// Offsets of visible elements within an item.
+    pub range: std::ops::Range<u32>,
+    /// Index of the first element of this part within a [VisibleSequence].
+    pub index: u32,
+}
+
+impl VisibleSequence {
+    pub(crate) fn new(branch: &Branch, snapshot: &Snapshot, kind: OffsetKind) -> Self {
+        let mut parts = Vec::new();
+        let mut len = 0;
+        let mut content_len = 0;
+        let mut current = branch.start;
+        while let Some(item) = current {
+            if item.is_countable() {
+                snapshot.visible_ranges(&item.id, item.len, |range| {
+                    content_len += match &item.content {
+                        ItemContent::String(s) => {
+                            let s = utf16_slice(s.as_str(), range.clone());
+                            match kind {
+                                OffsetKind::Bytes => s.len() as u32,
+                                OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+                            }
+                        }
+                        _ => range.end - range.start,
+                    };
+                    let index = len;
+                    len += range.end - range.start;
+                    parts.push(VisiblePart { item, range, index });
+                });
+            }
+            current = item.right;
+        }
+        VisibleSequence {
+            parts,
+            len,
+            content_len,
+        }
+    }
+
+    /// Returns a number of visible elements.
+    pub(crate) fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns a length of visible content measured using document's [OffsetKind].
+    pub(crate) fn content_len(&self) -> u32 {
+        self.content_len
+    }
+
+    /// Returns an item containing visible element at a given `index` together with an offset of
+    /// that element within the item.
+    pub(crate) fn find(&self, index: u32) -> Option<(ItemPtr, u32)> {
+        if index >= self.len {
+            return None;
+        }
+        let i = self.parts.partition_point(|p| p.index <= index) - 1;
+        let part = &self.parts[i];
+        Some((part.item, part.range.start + index - part.index))
+    }
+
+    /// Returns a visible value at a given `index`.
+    pub(crate) fn get(&self, index: u32) -> Option<Value> {
+        let (item, offset) = self.find(index)?;
+        let mut buf = [Value::default(); 1];
+        if item.content.read(offset as usize, &mut buf) == 0 {
+            return None;
+        }
+        Some(std::mem::take(&mut buf[0]))
+    }
+}
+
 pub(crate) struct Iter<'a, T> {
     ptr: Option<&'a ItemPtr>,
     _txn: &'a T,
@@ -628,6 +881,80 @@ impl<'a, T: ReadTxn> Iterator for Iter<'a, T> {
     }
 }
 
+/// Information about a single block of a shared collection, returned by [BranchBlocks].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Identifier of the first element stored in a block.
+    pub id: ID,
+    /// Kind of content stored in a block.
+    pub kind: ContentKind,
+    /// Number of elements stored in a block. Length of text is measured in UTF-16 code units.
+    pub len: u32,
+    /// Whether a block has been deleted (it's a tombstone).
+    pub deleted: bool,
+    /// Map component key of a block or `None` if block belongs to a sequence component.
+    pub key: Option<Arc<str>>,
+}
+
+impl From<ItemPtr> for BlockInfo {
+    fn from(item: ItemPtr) -> Self {
+        BlockInfo {
+            id: item.id,
+            kind: item.content.kind(),
+            len: item.len,
+            deleted: item.is_deleted(),
+            key: item.parent_sub.clone(),
+        }
+    }
+}
+
+/// Iterator over all blocks (including deleted ones) of a shared collection, created with
+/// [SharedRef::blocks]. Blocks of a sequence component are returned first, in their order, and
+/// then the blocks of map component: ordered by key and from the oldest to the newest value
+/// assigned to that key.
+#[derive(Debug)]
+pub struct BranchBlocks<'a> {
+    current: Option<ItemPtr>,
+    starts: std::vec::IntoIter<ItemPtr>,
+    _txn: PhantomData<&'a ()>,
+}
+
+impl<'a> BranchBlocks<'a> {
+    pub(crate) fn new(branch: &Branch) -> Self {
+        let mut entries: Vec<_> = branch.map.iter().collect();
+        entries.sort_by_key(|(key, _)| *key);
+        let starts: Vec<_> = entries
+            .into_iter()
+            .map(|(_, &item)| {
+                let mut first = item;
+                while let Some(left) = first.left {
+                    first = left;
+                }
+                first
+            })
+            .collect();
+        BranchBlocks {
+            current: branch.start,
+            starts: starts.into_iter(),
+            _txn: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for BranchBlocks<'a> {
+    type Item = BlockInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.current {
+                self.current = item.right;
+                return Some(BlockInfo::from(item));
+            }
+            self.current = Some(self.starts.next()?);
+        }
+    }
+}
+
 /// A logical reference to a root-level shared collection. It can be shared across different
 /// documents to reference the same logical type.
 ///
@@ -674,7 +1001,8 @@ impl<S: RootRef> Root<S> {
     /// it if it wasn't instantiated before.
     pub fn get_or_create<T: WriteTxn>(&self, txn: &mut T) -> S {
         let store = txn.store_mut();
-        let branch = store.get_or_create_type(self.name.clone(), S::type_ref());
+        let name = store.root_name(&self.name);
+        let branch = store.get_or_create_type(name, S::type_ref());
         S::from(branch)
     }
 }
@@ -683,7 +1011,8 @@ impl<S: SharedRef> Root<S> {
     /// Returns a reference to a shared collection current [Root] represents, or returns `None` if
     /// that collection hasn't been instantiated yet.
     pub fn get<T: ReadTxn>(&self, txn: &T) -> Option<S> {
-        txn.store().get_type(self.name.clone()).map(S::from)
+        let store = txn.store();
+        store.get_type(store.root_name(&self.name)).map(S::from)
     }
 }
 
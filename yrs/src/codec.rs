@@ -0,0 +1,242 @@
+use crate::encoding::read;
+use crate::update::UpdateError;
+use std::borrow::Cow;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Trait used to wrap document updates into an opaque envelope before they are emitted by
+/// a document, and to unwrap them before they are applied. It's primarily meant to enable end-to-end
+/// encryption of updates with an AEAD cipher chosen by the caller, so that intermediate sync
+/// servers and persistence layers never see plaintext document contents.
+///
+/// Codec can be configured via [Options::codec](crate::Options::codec). When present, all encoded
+/// updates leaving a document are sealed by it. This includes:
+///
+/// - updates passed to [Doc::observe_update_v1](crate::Doc::observe_update_v1) and
+///   [Doc::observe_update_v2](crate::Doc::observe_update_v2) callbacks,
+/// - updates returned by [ReadTxn::encode_state_as_update_v1](crate::ReadTxn::encode_state_as_update_v1),
+///   [ReadTxn::encode_diff_v1](crate::ReadTxn::encode_diff_v1),
+///   [TransactionMut::encode_update_v1](crate::TransactionMut::encode_update_v1) and their
+///   v2, buffered and streaming counterparts,
+/// - sync-step-2 replies produced by [sync::Protocol](crate::sync::Protocol).
+///
+/// All of these can be opened by [TransactionMut::apply_update_v1](crate::TransactionMut::apply_update_v1)
+/// and [TransactionMut::apply_update_v2](crate::TransactionMut::apply_update_v2) or by the sync
+/// protocol of a document using the same codec.
+///
+/// Methods writing updates into a caller-provided [Encoder](crate::updates::encoder::Encoder)
+/// (eg. [ReadTxn::encode_state_as_update](crate::ReadTxn::encode_state_as_update)) and
+/// [Update] values themselves are not sealed, since they are building blocks for custom
+/// encodings rather than a transport format.
+///
+/// [Update]: crate::Update
+pub trait UpdateCodec: Send + Sync {
+    /// Wraps an encoded `update` into an envelope.
+    fn seal(&self, update: Vec<u8>) -> Vec<u8>;
+
+    /// Unwraps an `envelope` produced by [UpdateCodec::seal], returning an encoded update.
+    fn open(&self, envelope: &[u8]) -> Result<Vec<u8>, CodecError>;
+}
+
+/// Shared reference to an [UpdateCodec] stored within document [Options](crate::Options).
+/// Two codec references are equal only if they point to the same codec instance.
+#[derive(Clone)]
+pub struct Codec(Arc<dyn UpdateCodec>);
+
+impl Codec {
+    pub fn new<C: UpdateCodec + 'static>(codec: C) -> Self {
+        Codec(Arc::new(codec))
+    }
+}
+
+impl From<Arc<dyn UpdateCodec>> for Codec {
+    fn from(codec: Arc<dyn UpdateCodec>) -> Self {
+        Codec(codec)
+    }
+}
+
+impl std::ops::Deref for Codec {
+    type Target = dyn UpdateCodec;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl PartialEq for Codec {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Codec {}
+
+impl std::fmt::Debug for Codec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Codec({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+/// Seals an encoded `update` with a given `codec`. Update is returned as is if there's no codec.
+pub(crate) fn seal(codec: Option<&Codec>, update: Vec<u8>) -> Vec<u8> {
+    match codec {
+        Some(codec) => codec.seal(update),
+        None => update,
+    }
+}
+
+/// Opens an `envelope` with a given `codec`. Envelope is returned as is if there's no codec.
+pub(crate) fn open<'a>(
+    codec: Option<&Codec>,
+    envelope: &'a [u8],
+) -> Result<Cow<'a, [u8]>, CodecError> {
+    match codec {
+        Some(codec) => Ok(Cow::Owned(codec.open(envelope)?)),
+        None => Ok(Cow::Borrowed(envelope)),
+    }
+}
+
+/// Error returned when an update envelope could not be opened or decoded.
+#[derive(Debug, Error)]
+pub enum CodecError {
+    /// Envelope could not be opened by configured [UpdateCodec], eg. because it was encrypted
+    /// with a different key or was tampered with.
+    #[error("failed to open update envelope: {0}")]
+    Open(String),
+    /// Update contained within an envelope could not be decoded.
+    #[error("failed to decode update: {0}")]
+    Decode(#[from] read::Error),
+    /// Decoded update could not be integrated into a document.
+    #[error(transparent)]
+    Apply(#[from] UpdateError),
+}
+
+#[cfg(test)]
+mod test {
+    use crate::codec::{Codec, CodecError, UpdateCodec};
+    use crate::sync::{Awareness, DefaultProtocol, Message, Protocol};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, GetString, Options, ReadTxn, SharedRef, StateVector, Text, Transact};
+    use std::sync::{Arc, Mutex};
+
+    /// Toy cipher used for testing purposes only: XORs payload with a key and appends
+    /// a checksum, which is validated when the envelope is opened.
+    struct XorCodec(u8);
+
+    impl UpdateCodec for XorCodec {
+        fn seal(&self, update: Vec<u8>) -> Vec<u8> {
+            let checksum = update.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+            let mut envelope: Vec<u8> = update.into_iter().map(|b| b ^ self.0).collect();
+            envelope.push(checksum);
+            envelope
+        }
+
+        fn open(&self, envelope: &[u8]) -> Result<Vec<u8>, CodecError> {
+            let (checksum, payload) = envelope
+                .split_last()
+                .ok_or_else(|| CodecError::Open("empty envelope".into()))?;
+            let update: Vec<u8> = payload.iter().map(|b| b ^ self.0).collect();
+            if update.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != *checksum {
+                return Err(CodecError::Open("checksum mismatch".into()));
+            }
+            Ok(update)
+        }
+    }
+
+    fn doc_with_codec(client_id: u64, key: u8) -> Doc {
+        Doc::with_options(Options {
+            codec: Some(Codec::new(XorCodec(key))),
+            ..Options::with_client_id(client_id)
+        })
+    }
+
+    #[test]
+    fn update_envelopes() {
+        let d1 = doc_with_codec(1, 0b1010_1010);
+        let envelopes = Arc::new(Mutex::new(Vec::new()));
+        let sink = envelopes.clone();
+        let _sub = d1
+            .observe_update_v1(move |_, e| sink.lock().unwrap().push(e.update.clone()))
+            .unwrap();
+        let txt1 = d1.get_or_insert_text("text");
+        txt1.push(&mut d1.transact_mut(), "hello world");
+
+        let envelopes = envelopes.lock().unwrap();
+        assert_eq!(envelopes.len(), 1);
+        let plaintext = String::from_utf8_lossy(&envelopes[0]);
+        assert!(!plaintext.contains("hello world"));
+
+        let d2 = doc_with_codec(2, 0b1010_1010);
+        let txt2 = d2.get_or_insert_text("text");
+        d2.transact_mut().apply_update_v1(&envelopes[0]).unwrap();
+        assert_eq!(txt2.get_string(&d2.transact()), "hello world");
+
+        // document using a different key is not able to open an envelope
+        let d3 = doc_with_codec(3, 0b0101_0101);
+        let res = d3.transact_mut().apply_update_v1(&envelopes[0]);
+        assert!(matches!(res, Err(CodecError::Open(_))));
+
+        // documents without codec configured apply plaintext updates
+        let d4 = Doc::with_client_id(4);
+        let res = d4.transact_mut().apply_update_v1(&envelopes[0]);
+        assert!(matches!(res, Err(CodecError::Decode(_))));
+    }
+
+    #[test]
+    fn encoded_state_is_sealed() {
+        let d1 = doc_with_codec(1, 0b1010_1010);
+        let txt1 = d1.get_or_insert_text("text");
+        txt1.push(&mut d1.transact_mut(), "hello world");
+
+        let txn = d1.transact();
+        let sv = StateVector::default();
+        let mut streamed = Vec::new();
+        txn.encode_state_as_update_to(&sv, &mut streamed).unwrap();
+        let mut buffered = Vec::new();
+        txn.encode_state_as_update_v1_into(&sv, &mut buffered);
+        let envelopes = [
+            (txn.encode_state_as_update_v1(&sv), false),
+            (txn.encode_state_as_update_v2(&sv), true),
+            (txn.encode_diff_v1(&sv), false),
+            (txn.encode_diff_v2(&sv), true),
+            (txt1.encode_state_as_update_v1(&txn, &sv), false),
+            (streamed, false),
+            (buffered, false),
+        ];
+        drop(txn);
+        for (envelope, v2) in envelopes {
+            assert!(!String::from_utf8_lossy(&envelope).contains("hello world"));
+            let d2 = doc_with_codec(2, 0b1010_1010);
+            let txt2 = d2.get_or_insert_text("text");
+            let mut txn = d2.transact_mut();
+            if v2 {
+                txn.apply_update_v2(&envelope).unwrap();
+            } else {
+                txn.apply_update_v1(&envelope).unwrap();
+            }
+            assert_eq!(txt2.get_string(&txn), "hello world");
+        }
+    }
+
+    #[test]
+    fn sync_protocol_messages_are_sealed() {
+        let d1 = doc_with_codec(1, 0b1010_1010);
+        let txt1 = d1.get_or_insert_text("text");
+        txt1.push(&mut d1.transact_mut(), "hello world");
+        let a1 = Awareness::new(d1);
+        let d2 = doc_with_codec(2, 0b1010_1010);
+        let txt2 = d2.get_or_insert_text("text");
+        let mut a2 = Awareness::new(d2);
+
+        let sv = a2.doc().transact().state_vector();
+        let reply = DefaultProtocol.handle_sync_step1(&a1, sv).unwrap().unwrap();
+        let encoded = reply.encode_v1();
+        assert!(!String::from_utf8_lossy(&encoded).contains("hello world"));
+
+        let msg = Message::decode_v1(&encoded).unwrap();
+        DefaultProtocol.handle_msg(&mut a2, msg).unwrap();
+        assert_eq!(txt2.get_string(&a2.doc().transact()), "hello world");
+    }
+}
@@ -0,0 +1,154 @@
+use crate::block::ClientID;
+use crate::branch::{BranchID, BranchPtr};
+use crate::sync::time::{Clock, Timestamp};
+use crate::types::TypePtr;
+use crate::Origin;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Metadata about a committed read-write transaction, recorded when commit log has been enabled
+/// for a given document (see: [Doc::enable_commit_log](crate::Doc::enable_commit_log)).
+///
+/// Commit info is available for every transaction which changed the document state - either by
+/// local changes or remote updates applied - from the moment its changes have been committed,
+/// via [TransactionMut::commit_info](crate::TransactionMut::commit_info). This means it can be
+/// accessed inside of type observers and transaction cleanup callbacks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// Logical (Lamport-style) sequence number of this commit. It's incremented with every
+    /// recorded transaction, therefore it defines a causal order of commits observed by the local
+    /// document: if commit `a` happened before `b` then `a.seq < b.seq`.
+    pub seq: u64,
+    /// Wall-clock timestamp of the commit obtained from the [Clock] provided to the commit log.
+    /// Unlike [CommitInfo::seq], this value is not guaranteed to be monotonic.
+    pub timestamp: Timestamp,
+    /// Client identifier of a document which committed the transaction.
+    pub client_id: ClientID,
+    /// Origin of the transaction, if one was provided.
+    pub origin: Option<Origin>,
+}
+
+/// Auxiliary log of metadata about recently committed transactions. It keeps at most a configured
+/// number of most recent entries, discarding the oldest ones. Commit log is not a part of the
+/// document state and is never persisted nor propagated to remote peers.
+///
+/// Optionally, commit log can also maintain an index of the latest commits which modified
+/// particular shared collections (see: [Doc::enable_last_modified](crate::Doc::enable_last_modified)).
+pub struct CommitLog {
+    clock: Arc<dyn Clock>,
+    entries: VecDeque<CommitInfo>,
+    capacity: usize,
+    seq: u64,
+    last_modified: Option<HashMap<BranchID, CommitInfo>>,
+}
+
+impl CommitLog {
+    pub(crate) fn new(clock: Arc<dyn Clock>, capacity: usize) -> Self {
+        CommitLog {
+            clock,
+            entries: VecDeque::with_capacity(capacity.min(1024)),
+            capacity,
+            seq: 0,
+            last_modified: None,
+        }
+    }
+
+    pub(crate) fn track_last_modified(&mut self, enabled: bool) {
+        if !enabled {
+            self.last_modified = None;
+        } else if self.last_modified.is_none() {
+            self.last_modified = Some(HashMap::new());
+        }
+    }
+
+    /// Marks given `branch` and all of its parents as modified by a commit described by `info`.
+    pub(crate) fn mark_modified(&mut self, branch: BranchPtr, info: &CommitInfo) {
+        if let Some(index) = self.last_modified.as_mut() {
+            let mut current = Some(branch);
+            while let Some(branch) = current {
+                let prev = index.insert(branch.id(), info.clone());
+                if let Some(prev) = prev {
+                    if prev.seq == info.seq {
+                        // parents have been already marked within the same commit
+                        break;
+                    }
+                }
+                current = match branch.item {
+                    Some(item) => match &item.parent {
+                        TypePtr::Branch(parent) => Some(*parent),
+                        _ => None,
+                    },
+                    None => None,
+                };
+            }
+        }
+    }
+
+    /// Returns metadata of the latest commit, which modified a shared collection identified by
+    /// a given `id` either directly or through any of its nested collections. Returns `None` if
+    /// collection was not modified since tracking has been enabled or if tracking is disabled
+    /// (see: [Doc::enable_last_modified](crate::Doc::enable_last_modified)).
+    pub fn last_modified(&self, id: &BranchID) -> Option<&CommitInfo> {
+        self.last_modified.as_ref()?.get(id)
+    }
+
+    /// Records a new commit in the log and returns its metadata.
+    pub(crate) fn record(&mut self, client_id: ClientID, origin: Option<Origin>) -> CommitInfo {
+        self.seq += 1;
+        let info = CommitInfo {
+            seq: self.seq,
+            timestamp: self.clock.now(),
+            client_id,
+            origin,
+        };
+        if self.capacity > 0 {
+            if self.entries.len() == self.capacity {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(info.clone());
+        }
+        info
+    }
+
+    /// Returns metadata of the most recent commit recorded by this log.
+    pub fn last(&self) -> Option<&CommitInfo> {
+        self.entries.back()
+    }
+
+    /// Returns an iterator over recorded commits, starting from the oldest one.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &CommitInfo> {
+        self.entries.iter()
+    }
+
+    /// Returns a number of commits currently held by this log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if this log has no commits recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the sequence number of the latest recorded commit, or 0 if nothing was committed
+    /// since the log has been enabled.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Returns a maximum number of entries kept in this log.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl std::fmt::Debug for CommitLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommitLog")
+            .field("seq", &self.seq)
+            .field("capacity", &self.capacity)
+            .field("entries", &self.entries)
+            .field("last_modified", &self.last_modified)
+            .finish()
+    }
+}
@@ -1,19 +1,27 @@
 use crate::block::{ClientID, ItemContent, ItemPtr, Prelim};
-use crate::branch::BranchPtr;
+use crate::branch::{Branch, BranchPtr};
+use crate::codec::{Codec, CodecError};
+use crate::commit::CommitLog;
 use crate::encoding::read::Error;
-use crate::event::{SubdocsEvent, TransactionCleanupEvent, UpdateEvent};
-use crate::store::{Store, StoreRef};
-use crate::transaction::{Origin, Transaction, TransactionMut};
-use crate::types::{RootRef, ToJson, Value};
+use crate::event::{SubdocUpdateEvent, SubdocsEvent, TransactionCleanupEvent, UpdateEvent};
+use crate::integrity::IntegrityReport;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::observer::{kind, CallbackPanicHook, ObserverKey};
+use crate::store::{Store, StoreEvents, StoreRef};
+use crate::sync::Clock;
+use crate::transaction::{Origin, Transaction, TransactionMut, TxnOptions};
+use crate::types::{Path, PathSegment, RootRef, ToJson, TypeRef, Value};
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::OptionExt;
 use crate::{
-    uuid_v4, uuid_v4_from, ArrayRef, BranchID, MapRef, ReadTxn, TextRef, Uuid, WriteTxn,
-    XmlFragmentRef,
+    uuid_v4, uuid_v4_from, ArrayRef, BranchID, KeyedListRef, MapConflictResolver, MapRef, ReadTxn,
+    SetRef, Snapshot, TextRef, Uuid, WriteTxn, XmlFragmentRef,
 };
 use crate::{Any, Subscription};
 use atomic_refcell::{AtomicRefCell, BorrowError, BorrowMutError};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::Formatter;
@@ -50,7 +58,7 @@ use thiserror::Error;
 ///
 /// // both update and state vector are serializable, we can pass the over the wire
 /// // now apply update to a remote document
-/// remote_txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+/// remote_txn.apply_update(Update::decode_v1(update.as_slice()).unwrap()).unwrap();
 /// ```
 #[repr(transparent)]
 #[derive(Debug, Clone)]
@@ -112,6 +120,53 @@ impl Doc {
         }
     }
 
+    /// Creates a new document with a configured set of [Options] and applies a sequence of
+    /// `updates` encoded using lib0 v1 encoding (eg. read from a persistent storage) to it, all
+    /// within a single transaction.
+    ///
+    /// Failing updates don't stop the process: all remaining updates are still applied. If any
+    /// of them failed, a [LoadError] is returned, which contains a document with all successfully
+    /// applied updates together with errors of the failed ones.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Options, ReadTxn, Text, Transact};
+    ///
+    /// let source = Doc::new();
+    /// let text = source.get_or_insert_text("text");
+    /// let mut updates = Vec::new();
+    /// for chunk in ["hello", " ", "world"] {
+    ///     let sv = source.transact().state_vector();
+    ///     text.push(&mut source.transact_mut(), chunk);
+    ///     updates.push(source.transact().encode_state_as_update_v1(&sv));
+    /// }
+    ///
+    /// let doc = Doc::from_updates(Options::default(), updates.iter().map(Vec::as_slice)).unwrap();
+    /// let text = doc.get_or_insert_text("text");
+    /// assert_eq!(text.get_string(&doc.transact()), "hello world");
+    /// ```
+    pub fn from_updates<'a, I>(options: Options, updates: I) -> Result<Self, LoadError>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let doc = Doc::with_options(options);
+        let mut errors = Vec::new();
+        {
+            let mut txn = doc.transact_mut();
+            for (i, update) in updates.into_iter().enumerate() {
+                if let Err(e) = txn.apply_update_v1(update) {
+                    errors.push((i, e));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(doc)
+        } else {
+            Err(LoadError { doc, errors })
+        }
+    }
+
     pub(crate) fn subdoc(parent: ItemPtr, options: Options) -> Self {
         let mut store = Store::new(options);
         store.parent = Some(parent);
@@ -178,6 +233,42 @@ impl Doc {
         MapRef::root(name).get_or_create(&mut self.transact_mut())
     }
 
+    /// Returns a [KeyedListRef] data structure stored under a given `name`. Keyed lists are used
+    /// for storing key-value entries in a user-defined order.
+    ///
+    /// If no structure under defined `name` existed before, it will be created and returned
+    /// instead.
+    ///
+    /// If a structure under defined `name` already existed, but its type was different it will be
+    /// reinterpreted as a keyed list.
+    ///
+    /// # Panics
+    ///
+    /// This method requires exclusive access to an underlying document store. If there
+    /// is another transaction in process, it will panic. It's advised to define all root shared
+    /// types during the document creation.
+    pub fn get_or_insert_keyed_list<N: Into<Arc<str>>>(&self, name: N) -> KeyedListRef {
+        KeyedListRef::root(name).get_or_create(&mut self.transact_mut())
+    }
+
+    /// Returns a [SetRef] data structure stored under a given `name`. Sets are used for storing
+    /// collections of unique primitive values, like tags or reactions.
+    ///
+    /// If no structure under defined `name` existed before, it will be created and returned
+    /// instead.
+    ///
+    /// If a structure under defined `name` already existed, but its type was different it will be
+    /// reinterpreted as a set.
+    ///
+    /// # Panics
+    ///
+    /// This method requires exclusive access to an underlying document store. If there
+    /// is another transaction in process, it will panic. It's advised to define all root shared
+    /// types during the document creation.
+    pub fn get_or_insert_set<N: Into<Arc<str>>>(&self, name: N) -> SetRef {
+        SetRef::root(name).get_or_create(&mut self.transact_mut())
+    }
+
     /// Returns an [ArrayRef] data structure stored under a given `name`. Array structures are used for
     /// storing a sequences of elements in ordered manner, positioning given element accordingly
     /// to its index.
@@ -245,14 +336,12 @@ impl Doc {
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_update_v1_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UpdateV1>,
         F: Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .update_v1_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.update_v1_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
@@ -265,24 +354,22 @@ impl Doc {
     #[cfg(target_family = "wasm")]
     pub fn observe_update_v1_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UpdateV1>,
         F: Fn(&TransactionMut, &UpdateEvent) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .update_v1_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.update_v1_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     pub fn unobserve_update_v1<K>(&self, key: K) -> Result<bool, BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UpdateV1>,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.update_v1_events.unsubscribe(&key.into()))
+        Ok(events.update_v1_events.unsubscribe(&key.into_origin()))
     }
 
     /// Subscribe callback function for any changes performed within transaction scope. These
@@ -310,14 +397,12 @@ impl Doc {
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_update_v2_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UpdateV2>,
         F: Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .update_v2_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.update_v2_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
@@ -330,24 +415,261 @@ impl Doc {
     #[cfg(target_family = "wasm")]
     pub fn observe_update_v2_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UpdateV2>,
         F: Fn(&TransactionMut, &UpdateEvent) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .update_v2_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.update_v2_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     pub fn unobserve_update_v2<K>(&self, key: K) -> Result<bool, BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UpdateV2>,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.update_v2_events.unsubscribe(&key.into()))
+        Ok(events.update_v2_events.unsubscribe(&key.into_origin()))
+    }
+
+    /// Pauses delivery of update events to callbacks subscribed via [Doc::observe_update_v1] and
+    /// [Doc::observe_update_v2]. Updates of transactions committed from now on are encoded at
+    /// commit time and kept aside, until they are delivered by either
+    /// [Doc::flush_pending_events] or [Doc::resume_observers]. Updates are encoded only for
+    /// versions having any subscribers at the moment of commit.
+    ///
+    /// Other observers (eg. shared type observers or [Doc::observe_after_transaction]) depend on
+    /// a state of committed transaction, so they are not affected.
+    pub fn pause_observers(&self) -> Result<(), BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.pause_updates();
+        Ok(())
+    }
+
+    /// Forces delivery of all update events deferred since observers have been paused (see:
+    /// [Doc::pause_observers]). Observers stay paused afterwards. This is a no-op if there are no
+    /// deferred events.
+    ///
+    /// Deferred updates are delivered in the same order in which their transactions have been
+    /// committed, each one as a separate event. For every transaction, [Doc::observe_update_v1]
+    /// callbacks are called before [Doc::observe_update_v2] ones. Callbacks receive a new
+    /// read-write transaction opened for the purpose of flushing, which contains no changes, but
+    /// carries the [TransactionMut::origin] of a transaction that produced delivered update.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let updates = Arc::new(Mutex::new(Vec::new()));
+    /// let u = updates.clone();
+    /// let _sub = doc
+    ///     .observe_update_v1(move |_, e| u.lock().unwrap().push(e.update.clone()))
+    ///     .unwrap();
+    ///
+    /// doc.pause_observers().unwrap();
+    /// text.push(&mut doc.transact_mut(), "a");
+    /// text.push(&mut doc.transact_mut(), "b");
+    /// assert!(updates.lock().unwrap().is_empty());
+    ///
+    /// doc.flush_pending_events().unwrap();
+    /// assert_eq!(updates.lock().unwrap().len(), 2);
+    /// ```
+    pub fn flush_pending_events(&self) -> Result<(), TransactionAcqError> {
+        let mut txn = self.try_transact_mut()?;
+        txn.emit_deferred_updates(false);
+        Ok(())
+    }
+
+    /// Resumes delivery of update events paused by [Doc::pause_observers], flushing all deferred
+    /// events first (see: [Doc::flush_pending_events]).
+    pub fn resume_observers(&self) -> Result<(), TransactionAcqError> {
+        let mut txn = self.try_transact_mut()?;
+        txn.emit_deferred_updates(true);
+        Ok(())
+    }
+
+    /// Subscribe callback function for changes performed within transactions of any sub-document
+    /// nested (directly or indirectly) within current document. These changes are encoded using
+    /// lib0 v1 encoding, exactly as they would be by [Doc::observe_update_v1] callbacks registered
+    /// on the sub-document itself, and tagged with sub-document's guid. Callback is called with
+    /// sub-document's transaction, once it's committed.
+    ///
+    /// This way a single observer can be used eg. to persist changes of an entire tree of
+    /// documents without subscribing to each one of loaded sub-documents separately.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_subdoc_update_v1<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &SubdocUpdateEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdoc_update_v1_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function for changes performed within transactions of any sub-document
+    /// nested within current document. See [Doc::observe_subdoc_update_v1] for details.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_subdoc_update_v1_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::SubdocUpdateV1>,
+        F: Fn(&TransactionMut, &SubdocUpdateEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.subdoc_update_v1_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    /// Subscribe callback function for changes performed within transactions of any sub-document
+    /// nested within current document. See [Doc::observe_subdoc_update_v1] for details.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(target_family = "wasm")]
+    pub fn observe_subdoc_update_v1_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::SubdocUpdateV1>,
+        F: Fn(&TransactionMut, &SubdocUpdateEvent) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.subdoc_update_v1_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    pub fn unobserve_subdoc_update_v1<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    where
+        K: ObserverKey<kind::SubdocUpdateV1>,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdoc_update_v1_events.unsubscribe(&key.into_origin()))
+    }
+
+    /// Subscribe callback function for changes performed within transactions of any sub-document
+    /// nested (directly or indirectly) within current document. These changes are encoded using
+    /// lib0 v2 encoding, exactly as they would be by [Doc::observe_update_v2] callbacks registered
+    /// on the sub-document itself, and tagged with sub-document's guid. Callback is called with
+    /// sub-document's transaction, once it's committed.
+    ///
+    /// This way a single observer can be used eg. to persist changes of an entire tree of
+    /// documents without subscribing to each one of loaded sub-documents separately.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_subdoc_update_v2<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &SubdocUpdateEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdoc_update_v2_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function for changes performed within transactions of any sub-document
+    /// nested within current document. See [Doc::observe_subdoc_update_v2] for details.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_subdoc_update_v2_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::SubdocUpdateV2>,
+        F: Fn(&TransactionMut, &SubdocUpdateEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.subdoc_update_v2_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    /// Subscribe callback function for changes performed within transactions of any sub-document
+    /// nested within current document. See [Doc::observe_subdoc_update_v2] for details.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(target_family = "wasm")]
+    pub fn observe_subdoc_update_v2_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::SubdocUpdateV2>,
+        F: Fn(&TransactionMut, &SubdocUpdateEvent) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.subdoc_update_v2_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    pub fn unobserve_subdoc_update_v2<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    where
+        K: ObserverKey<kind::SubdocUpdateV2>,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdoc_update_v2_events.unsubscribe(&key.into_origin()))
+    }
+
+    /// Subscribe callback function, which will be called whenever a local edit or a remote update
+    /// has been rejected for exceeding one of the document quotas (see: [Options::max_blocks] and
+    /// [Options::max_content_bytes]).
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_quota_exceeded<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &QuotaExceeded) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.quota_exceeded_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, which will be called whenever a local edit or a remote update
+    /// has been rejected for exceeding one of the document quotas.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_quota_exceeded_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::QuotaExceeded>,
+        F: Fn(&TransactionMut, &QuotaExceeded) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.quota_exceeded_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    /// Subscribe callback function, which will be called whenever a local edit or a remote update
+    /// has been rejected for exceeding one of the document quotas.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(target_family = "wasm")]
+    pub fn observe_quota_exceeded_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::QuotaExceeded>,
+        F: Fn(&TransactionMut, &QuotaExceeded) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.quota_exceeded_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    pub fn unobserve_quota_exceeded<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    where
+        K: ObserverKey<kind::QuotaExceeded>,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.quota_exceeded_events.unsubscribe(&key.into_origin()))
     }
 
     /// Subscribe callback function to updates on the `Doc`. The callback will receive state updates and
@@ -367,14 +689,12 @@ impl Doc {
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_transaction_cleanup_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::TransactionCleanup>,
         F: Fn(&TransactionMut, &TransactionCleanupEvent) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .transaction_cleanup_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.transaction_cleanup_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
@@ -383,24 +703,24 @@ impl Doc {
     #[cfg(target_family = "wasm")]
     pub fn observe_transaction_cleanup_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::TransactionCleanup>,
         F: Fn(&TransactionMut, &TransactionCleanupEvent) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .transaction_cleanup_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.transaction_cleanup_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     pub fn unobserve_transaction_cleanup<K>(&self, key: K) -> Result<bool, BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::TransactionCleanup>,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.transaction_cleanup_events.unsubscribe(&key.into()))
+        Ok(events
+            .transaction_cleanup_events
+            .unsubscribe(&key.into_origin()))
     }
 
     #[cfg(not(target_family = "wasm"))]
@@ -416,38 +736,36 @@ impl Doc {
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_after_transaction_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AfterTransaction>,
         F: Fn(&mut TransactionMut) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .after_transaction_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.after_transaction_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     #[cfg(target_family = "wasm")]
     pub fn observe_after_transaction_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AfterTransaction>,
         F: Fn(&mut TransactionMut) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .after_transaction_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.after_transaction_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     pub fn unobserve_after_transaction<K>(&self, key: K) -> Result<bool, BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AfterTransaction>,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.after_transaction_events.unsubscribe(&key.into()))
+        Ok(events
+            .after_transaction_events
+            .unsubscribe(&key.into_origin()))
     }
 
     /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
@@ -467,14 +785,12 @@ impl Doc {
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_subdocs_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Subdocs>,
         F: Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .subdocs_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.subdocs_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
@@ -483,24 +799,22 @@ impl Doc {
     #[cfg(target_family = "wasm")]
     pub fn observe_subdocs_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Subdocs>,
         F: Fn(&TransactionMut, &SubdocsEvent) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .subdocs_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.subdocs_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     pub fn unobserve_subdocs<K>(&self, key: K) -> Result<bool, BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Subdocs>,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.subdocs_events.unsubscribe(&key.into()))
+        Ok(events.subdocs_events.unsubscribe(&key.into_origin()))
     }
 
     /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
@@ -518,41 +832,461 @@ impl Doc {
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_destroy_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Destroy>,
         F: Fn(&TransactionMut, &Doc) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .destroy_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.destroy_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
     pub fn unobserve_destroy<K>(&self, key: K) -> Result<bool, BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Destroy>,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.destroy_events.unsubscribe(&key.into()))
+        Ok(events.destroy_events.unsubscribe(&key.into_origin()))
     }
 
     /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
     #[cfg(target_family = "wasm")]
     pub fn observe_destroy_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Destroy>,
         F: Fn(&TransactionMut, &Doc) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .destroy_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.destroy_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    /// Subscribe callback function, that will be called whenever a new root-level type has been
+    /// defined in current document, either locally or as a result of integrating a remote update.
+    /// Callback receives a name of a new root type and its [TypeRef]. Root types defined by remote
+    /// updates are not aware of their type until they are accessed locally, therefore they are
+    /// reported as [TypeRef::Undefined].
+    ///
+    /// Callbacks are called once the transaction, which defined new root types, is committed.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use yrs::{Doc, Transact, WriteTxn};
+    ///
+    /// let doc = Doc::new();
+    /// let names = Arc::new(Mutex::new(Vec::new()));
+    /// let names_c = names.clone();
+    /// let _sub = doc.observe_root_added(move |_txn, name, _type_ref| {
+    ///     names_c.lock().unwrap().push(name.to_string());
+    /// }).unwrap();
+    ///
+    /// {
+    ///     let mut txn = doc.transact_mut();
+    ///     txn.get_or_insert_text("text");
+    ///     txn.get_or_insert_map("map");
+    ///     txn.get_or_insert_text("text"); // already defined
+    /// }
+    /// assert_eq!(*names.lock().unwrap(), vec!["text".to_string(), "map".to_string()]);
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_root_added<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &str, &TypeRef) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.root_added_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called whenever a new root-level type has been
+    /// defined in current document. See: [Doc::observe_root_added].
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_root_added_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::RootAdded>,
+        F: Fn(&TransactionMut, &str, &TypeRef) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.root_added_events.subscribe_with_key(key, Box::new(f));
         Ok(())
     }
 
+    /// Subscribe callback function, that will be called whenever a new root-level type has been
+    /// defined in current document.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(target_family = "wasm")]
+    pub fn observe_root_added_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: ObserverKey<kind::RootAdded>,
+        F: Fn(&TransactionMut, &str, &TypeRef) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events.root_added_events.subscribe_with_key(key, Box::new(f));
+        Ok(())
+    }
+
+    pub fn unobserve_root_added<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    where
+        K: ObserverKey<kind::RootAdded>,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.root_added_events.unsubscribe(&key.into_origin()))
+    }
+
+    /// Enables recording of [CommitInfo] metadata for every read-write transaction, which changed
+    /// the state of a current document. Commit timestamps are obtained from a given `clock`. At most
+    /// `capacity` of the latest commits are kept in the [CommitLog] (accessible via
+    /// [ReadTxn::commit_log]), while metadata of the current transaction is always available via
+    /// [TransactionMut::commit_info].
+    ///
+    /// If commit log was already enabled, it will be replaced with a new one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ReadTxn, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// doc.enable_commit_log(|| 1700000000000, 16).unwrap();
+    /// let _sub = doc.observe_transaction_cleanup(|txn, _| {
+    ///     // commit info is present only if transaction changed the document
+    ///     if let Some(info) = txn.commit_info() {
+    ///         assert_eq!(info.timestamp, 1700000000000);
+    ///     }
+    /// }).unwrap();
+    ///
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut_with("local"), "hello");
+    ///
+    /// let txn = doc.transact();
+    /// let last = txn.commit_log().unwrap().last().unwrap();
+    /// assert_eq!(last.seq, 1);
+    /// ```
+    pub fn enable_commit_log<C>(&self, clock: C, capacity: usize) -> Result<(), BorrowMutError>
+    where
+        C: Clock + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.commit_log = Some(Box::new(CommitLog::new(Arc::new(clock), capacity)));
+        Ok(())
+    }
+
+    /// Enables (or disables if `enabled` is false) tracking of the latest commits which modified
+    /// particular shared collections, queryable via [SharedRef::last_modified]. A collection is
+    /// considered modified if any of its own or nested collections content has been changed.
+    ///
+    /// Tracking is maintained as part of the [CommitLog], therefore it requires commit log to be
+    /// enabled first via [Doc::enable_commit_log]. Returns `false` if commit log was not enabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, MapPrelim, SharedRef, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// doc.enable_commit_log(|| 1700000000000, 0).unwrap();
+    /// doc.enable_last_modified(true).unwrap();
+    ///
+    /// let sections = doc.get_or_insert_map("sections");
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello");
+    ///
+    /// let txn = doc.transact();
+    /// assert!(sections.last_modified(&txn).is_none());
+    /// assert_eq!(text.last_modified(&txn).unwrap().timestamp, 1700000000000);
+    /// ```
+    pub fn enable_last_modified(&self, enabled: bool) -> Result<bool, BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        match r.commit_log.as_mut() {
+            Some(log) => {
+                log.track_last_modified(enabled);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Disables recording of transaction commits metadata enabled previously via
+    /// [Doc::enable_commit_log], dropping all recorded entries.
+    pub fn disable_commit_log(&self) -> Result<(), BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        r.commit_log = None;
+        Ok(())
+    }
+
+    /// Validates internal invariants of the document store visible to a given transaction and
+    /// returns a report containing all detected violations. This includes consistency of left and
+    /// right neighbor links, parent links of blocks, block lengths, cached lengths of shared
+    /// collections, map entries pointing at the latest assigned blocks and ranges of the pending
+    /// delete set, which must only refer to blocks that have not been integrated yet.
+    ///
+    /// This is a diagnostic tool: it traverses the entire block store, so its cost is
+    /// proportional to the document size.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello world");
+    ///
+    /// let report = doc.check_integrity(&doc.transact());
+    /// assert!(report.is_ok(), "{}", report);
+    /// ```
+    pub fn check_integrity<T: ReadTxn>(&self, txn: &T) -> IntegrityReport {
+        IntegrityReport::check(txn)
+    }
+
+    /// Returns a registry of metrics collected while applying remote updates to this document,
+    /// like time spent on integration, number of created, merged and skipped blocks or sizes of
+    /// pending queues. See [crate::metrics] module for details.
+    ///
+    /// Returned registry is shared with the document, so it can be kept and read at any time
+    /// (e.g. from another thread) without acquiring a transaction.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.store.metrics().clone()
+    }
+
+    /// Resolves a value found under a given absolute `path`. The first segment of a path must be
+    /// a [PathSegment::Key] with a name of a root-level type, while the following segments are
+    /// used to descend into nested map entries (keys) and sequence elements (indexes). The last
+    /// segment may point to either a shared collection or a primitive value.
+    ///
+    /// Returns `None` if any of the path segments doesn't exist in the current document state.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, ArrayPrelim, Doc, Map, MapPrelim, Transact};
+    /// use yrs::types::{Path, PathSegment};
+    ///
+    /// let doc = Doc::new();
+    /// let pages = doc.get_or_insert_array("pages");
+    /// let mut txn = doc.transact_mut();
+    /// let page = pages.push_back(&mut txn, MapPrelim::<String>::new());
+    /// page.insert(&mut txn, "title", "hello");
+    ///
+    /// // `$.pages[0].title` serialized as JSON and sent over the wire
+    /// let path: Path = serde_json::from_str(r#"["pages", 0, "title"]"#).unwrap();
+    /// assert_eq!(doc.resolve_path(&txn, &path), Some("hello".into()));
+    /// ```
+    pub fn resolve_path<T: ReadTxn>(&self, txn: &T, path: &Path) -> Option<Value> {
+        let mut segments = path.iter();
+        let root = match segments.next()? {
+            PathSegment::Key(name) => txn.store().get_type(name.clone())?,
+            PathSegment::Index(_) => return None,
+        };
+        let mut branch = Some(root);
+        let mut value: Value = root.into();
+        for segment in segments {
+            let parent = branch?;
+            let (content, offset) = match segment {
+                PathSegment::Key(key) => {
+                    let item = parent.map.get(key)?;
+                    if item.is_deleted() {
+                        return None;
+                    }
+                    (&item.content, item.len() as usize - 1)
+                }
+                PathSegment::Index(index) => parent.get_at(*index)?,
+            };
+            let mut buf = [Value::default()];
+            if content.read(offset, &mut buf) == 0 {
+                return None;
+            }
+            let [next] = buf;
+            branch = match content {
+                ItemContent::Type(inner) => Some(BranchPtr::from(inner)),
+                _ => None,
+            };
+            value = next;
+        }
+        Some(value)
+    }
+
+    /// Creates and returns a read-write transaction with an `origin` attached, which has been
+    /// caused by other transactions described by a `parents` origin chain (see:
+    /// [TransactionMut::origin_chain]). Parent origins are available via
+    /// [TransactionMut::parent_origins] to all callbacks triggered by the returned transaction.
+    ///
+    /// # Errors
+    ///
+    /// Only one read-write transaction can be active at the same time. If any other transaction -
+    /// be it a read-write or read-only one - is active at the same time, this method will return
+    /// a [TransactionAcqError::ExclusiveAcqFailed] error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use yrs::{Doc, Origin, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let chain = Arc::new(Mutex::new(None));
+    /// let _sub = {
+    ///     let chain = chain.clone();
+    ///     doc.observe_after_transaction(move |txn| {
+    ///         // don't react to changes we caused ourselves
+    ///         if !txn.is_caused_by(&Origin::from("normalize")) {
+    ///             *chain.lock().unwrap() = Some(txn.origin_chain());
+    ///         }
+    ///     })
+    ///     .unwrap()
+    /// };
+    ///
+    /// text.push(&mut doc.transact_mut_with("user"), "hello");
+    /// let parents = chain.lock().unwrap().take().unwrap();
+    /// let txn = doc.try_transact_mut_caused_by("normalize", parents).unwrap();
+    /// assert_eq!(txn.parent_origins(), &[Origin::from("user")]);
+    /// ```
+    pub fn try_transact_mut_caused_by<T>(
+        &self,
+        origin: T,
+        parents: Vec<Origin>,
+    ) -> Result<TransactionMut<'_>, TransactionAcqError>
+    where
+        T: Into<Origin>,
+    {
+        let mut txn = self.try_transact_mut_with(origin)?;
+        txn.parent_origins = parents;
+        Ok(txn)
+    }
+
+    /// Creates and returns a read-write transaction with an `origin` attached, which has been
+    /// caused by other transactions described by a `parents` origin chain. See
+    /// [Doc::try_transact_mut_caused_by] for details.
+    ///
+    /// # Panics
+    ///
+    /// Only one read-write transaction can be active at the same time. If any other transaction -
+    /// be it a read-write or read-only one - is active at the same time, this method will panic.
+    pub fn transact_mut_caused_by<T>(&self, origin: T, parents: Vec<Origin>) -> TransactionMut<'_>
+    where
+        T: Into<Origin>,
+    {
+        self.try_transact_mut_caused_by(origin, parents)
+            .expect("there's another active transaction at the moment")
+    }
+
+    /// Creates and returns a read-write transaction configured with given `options`. This way
+    /// it's possible to eg. disable garbage collection only for the scope of a specific
+    /// transaction instead of setting [Options::skip_gc] for the whole document.
+    ///
+    /// # Errors
+    ///
+    /// Only one read-write transaction can be active at the same time. If any other transaction -
+    /// be it a read-write or read-only one - is active at the same time, this method will return
+    /// a [TransactionAcqError::ExclusiveAcqFailed] error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact, TxnOptions};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello world");
+    ///
+    /// // deleted content is retained, even though garbage collection is enabled
+    /// let mut txn = doc.try_transact_mut_with_opts(TxnOptions {
+    ///     origin: Some("snapshot".into()),
+    ///     gc: Some(false),
+    /// }).unwrap();
+    /// text.remove_range(&mut txn, 5, 6);
+    /// assert_eq!(text.get_string(&txn), "hello");
+    /// ```
+    pub fn try_transact_mut_with_opts(
+        &self,
+        options: TxnOptions,
+    ) -> Result<TransactionMut<'_>, TransactionAcqError> {
+        let store = self.store.try_borrow_mut()?;
+        Ok(TransactionMut::with_options(self.clone(), store, options))
+    }
+
+    /// Creates and returns a read-write transaction configured with given `options`. See
+    /// [Doc::try_transact_mut_with_opts] for details.
+    ///
+    /// # Panics
+    ///
+    /// Only one read-write transaction can be active at the same time. If any other transaction -
+    /// be it a read-write or read-only one - is active at the same time, this method will panic.
+    pub fn transact_mut_with_opts(&self, options: TxnOptions) -> TransactionMut<'_> {
+        self.try_transact_mut_with_opts(options)
+            .expect("there's another active transaction at the moment")
+    }
+
+    /// Creates and returns a read-only transaction, which reads document contents as they were
+    /// at the moment when given `snapshot` was taken: elements inserted after it and elements
+    /// already deleted at that point are not visible. This applies to `get_string`, `to_json`,
+    /// `len`, [Text::diff] and line-oriented text methods, map, array and XML node lookups and
+    /// their iterators, which makes it possible to view past versions of a document without
+    /// restoring them into a separate [Doc].
+    ///
+    /// Reading past versions requires deleted content to be retained, so a document should be
+    /// configured with [Options::skip_gc] enabled. Gated reads don't modify the document (see:
+    /// [SnapshotGate]). Positional reads (eg. [Array::get]) don't take moved array elements into
+    /// account.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Options, ReadTxn, Text, Transact};
+    ///
+    /// let doc = Doc::with_options(Options { skip_gc: true, ..Options::default() });
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// let snapshot = doc.transact().snapshot();
+    ///
+    /// text.push(&mut doc.transact_mut(), " world");
+    /// text.remove_range(&mut doc.transact_mut(), 0, 1);
+    ///
+    /// assert_eq!(text.get_string(&doc.transact_at(&snapshot)), "hello");
+    /// assert_eq!(text.get_string(&doc.transact()), "ello world");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If any other transaction is active at the same time, this method will return
+    /// a [TransactionAcqError].
+    ///
+    /// [Array::get]: crate::Array::get
+    /// [Text::diff]: crate::Text::diff
+    /// [SnapshotGate]: crate::SnapshotGate
+    pub fn try_transact_at(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Result<Transaction<'_>, TransactionAcqError> {
+        let store = self.store.try_borrow()?;
+        Ok(Transaction::with_snapshot(store, snapshot.clone()))
+    }
+
+    /// Creates and returns a read-only transaction gated by a given `snapshot`. See
+    /// [Doc::try_transact_at] for details.
+    ///
+    /// # Panics
+    ///
+    /// If any other transaction is active at the same time, this method will panic.
+    pub fn transact_at(&self, snapshot: &Snapshot) -> Transaction<'_> {
+        self.try_transact_at(snapshot)
+            .expect("there's another active transaction at the moment")
+    }
+
     /// Sends a load request to a parent document. Works only if current document is a sub-document
     /// of an another document.
     pub fn load<T>(&self, parent_txn: &mut T)
@@ -606,15 +1340,48 @@ impl Doc {
         }
         // super.destroy(): cleanup the events
         if let Some(events) = txn.store_mut().events.take() {
-            events.destroy_events.trigger(|cb| cb(&txn, self));
+            let hook = txn.store().options.callback_panic_hook.clone();
+            let panic = events
+                .destroy_events
+                .trigger(hook.as_ref(), |cb| cb(&txn, self));
+            txn.defer_panic(panic);
+        }
+    }
+
+    /// Destroys a sub document with a given `guid` living in a current document (see:
+    /// [Doc::destroy]) and removes a block referencing it from the document store. Unlike
+    /// [Doc::destroy], which keeps the sub document reference in place - so that it can be loaded
+    /// again - this method deletes that reference, which is then propagated to remote peers, and
+    /// garbage collects its block even if [Options::skip_gc] is enabled. This way applications
+    /// creating many ephemeral sub documents don't accumulate their blocks.
+    ///
+    /// Destroyed sub document is reported by [SubdocsEvent::removed] and [SubdocsEvent::destroyed].
+    /// Returns `false` if no such sub document was found.
+    ///
+    /// # Panics
+    ///
+    /// This method requires exclusive access to an underlying document store. If there is another
+    /// transaction in process, it will panic.
+    pub fn destroy_subdoc(&self, guid: &Uuid) -> bool {
+        let mut txn = self.transact_mut();
+        let subdoc = match txn.subdoc(guid) {
+            Some(subdoc) => subdoc.clone(),
+            None => return false,
+        };
+        match subdoc.parent_item() {
+            Some(item) => {
+                txn.delete(item);
+                txn.subdocs_mut().destroyed.insert(subdoc.addr(), subdoc);
+                true
+            }
+            None => false,
         }
     }
 
     /// If current document has been inserted as a sub-document, returns a reference to a parent
     /// document, which contains it.
     pub fn parent_doc(&self) -> Option<Doc> {
-        let store = unsafe { self.store.0.as_ptr().as_ref() }.unwrap();
-        if let Some(item) = store.parent.as_deref() {
+        if let Some(item) = self.parent_item().as_deref() {
             if let ItemContent::Doc(parent_doc, _) = &item.content {
                 return parent_doc.clone();
             }
@@ -624,12 +1391,94 @@ impl Doc {
     }
 
     pub fn branch_id(&self) -> Option<BranchID> {
-        let store = unsafe { self.store.0.as_ptr().as_ref() }.unwrap();
-        if let Some(item) = store.parent {
-            Some(BranchID::Nested(item.id))
-        } else {
-            None
+        let item = self.parent_item()?;
+        Some(BranchID::Nested(item.id))
+    }
+
+    /// Looks up a sub document with a given `guid` and returns a [Path] to a block holding it,
+    /// starting from a name of a root-level type. Returned path can be used together with
+    /// [Store::get_type_from_path] to resolve the shared collection which contains that sub
+    /// document. Returns `None` if no such sub document exists within current document.
+    ///
+    /// # Panics
+    ///
+    /// This method requires access to an underlying document store. If there is another
+    /// read-write transaction in process, it will panic.
+    pub fn find_subdoc_path(&self, guid: &Uuid) -> Option<Path> {
+        let txn = self.transact();
+        let subdoc = txn.subdoc(guid)?;
+        let item = subdoc.parent_item()?;
+        Branch::item_path(item)
+    }
+
+    /// Renames a root-level type stored under `old_name`, so that it can be accessed under
+    /// `new_name`, letting applications evolve the naming of their schema without writing
+    /// block-level migration code.
+    ///
+    /// Root types cannot be removed from a document, so instead of moving its content, this method
+    /// registers `new_name` as an alias of `old_name` in [Options::root_aliases]. Content is still
+    /// stored and replicated under `old_name`, which makes renaming replication-safe: no blocks
+    /// are written, all kinds of content (including sub-documents and move ranges) are kept in
+    /// place, calling this method many times or on many peers has the same effect as calling it
+    /// once, and peers which still use `old_name` keep collaborating on the same data.
+    ///
+    /// Aliases are not a part of a document state, so they must be registered by every peer
+    /// accessing a root type under `new_name` - either via [Options::root_aliases] or this method.
+    ///
+    /// Returns `false` if another root type was already defined under `new_name`, in which case
+    /// no alias is registered.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if provided transaction doesn't belong to current document.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact, WriteTxn};
+    ///
+    /// let doc = Doc::new();
+    /// let mut txn = doc.transact_mut();
+    /// txn.get_or_insert_text("title").push(&mut txn, "hello");
+    ///
+    /// assert!(doc.rename_root(&mut txn, "title", "heading"));
+    ///
+    /// let heading = txn.get_or_insert_text("heading");
+    /// heading.push(&mut txn, " world");
+    /// let title = txn.get_or_insert_text("title");
+    /// assert_eq!(title.get_string(&txn), "hello world");
+    /// ```
+    pub fn rename_root<N>(&self, txn: &mut TransactionMut, old_name: &str, new_name: N) -> bool
+    where
+        N: Into<Arc<str>>,
+    {
+        assert!(
+            Doc::ptr_eq(self, txn.doc()),
+            "transaction doesn't belong to current document"
+        );
+        let new_name = new_name.into();
+        let store = txn.store_mut();
+        let stored_name = store.root_name(&Arc::from(old_name));
+        if stored_name == new_name {
+            return true;
         }
+        if store.types.contains_key(&new_name) {
+            return false;
+        }
+        store.options.root_aliases.insert(new_name, stored_name);
+        true
+    }
+
+    /// Returns a pointer to a block containing current document, if it's a sub-document.
+    pub(crate) fn parent_item(&self) -> Option<ItemPtr> {
+        let store = unsafe { self.store.0.as_ptr().as_ref() }.unwrap();
+        store.parent
+    }
+
+    /// Returns event handlers of current document, if any subscription was ever made.
+    pub(crate) fn events(&self) -> Option<&StoreEvents> {
+        let store = unsafe { self.store.0.as_ptr().as_ref() }.unwrap();
+        store.events.as_deref()
     }
 
     pub fn ptr_eq(a: &Doc, b: &Doc) -> bool {
@@ -717,9 +1566,74 @@ pub struct Options {
     ///
     /// Default value: `true`.
     pub should_load: bool,
+    /// Codec used to seal updates emitted by this document and open updates applied to it, eg.
+    /// to provide end-to-end encryption. See [UpdateCodec](crate::UpdateCodec) for details. Codec
+    /// is not a part of encoded options.
+    ///
+    /// Default value: `None`.
+    pub codec: Option<Codec>,
+    /// Maximum length (in bytes) of a text block, up to which consecutive text insertions made by
+    /// the same client are merged together when transaction is committed. Smaller values result
+    /// in more fine-grained blocks, which are cheaper to split by concurrent edits, at the expense
+    /// of higher memory usage and bigger encoded document state. `0` disables merging of text
+    /// blocks altogether, while `usize::MAX` merges them without any limit. This option is local
+    /// to the document and it's not a part of encoded options.
+    ///
+    /// Default value: [Options::DEFAULT_TEXT_BLOCK_CAPACITY].
+    pub text_block_capacity: usize,
+    /// Normalizer applied to every chunk of text inserted locally into text collections (see:
+    /// [TextNormalizer]), eg. in order to convert it into Unicode NFC form. Remote changes are
+    /// never normalized. Normalizer is not a part of encoded options.
+    ///
+    /// Default value: `None`.
+    pub text_normalizer: Option<TextNormalizer>,
+    /// Resolver invoked when concurrent writes to the same [Map](crate::Map) key are integrated
+    /// (see: [MapConflictResolver]). When not set, such conflicts are resolved using default
+    /// last-writer-wins rule. Resolver is not a part of encoded options.
+    ///
+    /// Default value: `None`.
+    pub map_conflict_resolver: Option<MapConflictResolver>,
+    /// Hook called whenever an observer callback of this document panics (see:
+    /// [CallbackPanicHook]). When set, panics of observer callbacks are not propagated through
+    /// transaction commit. Without a hook, the first panic is resumed once the transaction that
+    /// triggered callbacks has been committed and dropped. Hook is not a part of encoded options.
+    ///
+    /// Default value: `None`.
+    pub callback_panic_hook: Option<CallbackPanicHook>,
+    /// Maximum number of blocks, which current document is allowed to store. Local edits and
+    /// remote updates, which would exceed this limit, are rejected with [QuotaExceeded] error and
+    /// reported to [Doc::observe_quota_exceeded] callbacks. Limit is not a part of encoded options.
+    ///
+    /// Default value: `None` (no limit).
+    pub max_blocks: Option<usize>,
+    /// Maximum number of bytes of content (see: [QuotaExceeded::ContentBytes]), which current
+    /// document is allowed to store. Local edits and remote updates, which would exceed this limit,
+    /// are rejected the same way as for [Options::max_blocks]. Content of deleted blocks counts
+    /// against this limit until they are garbage collected (see: [Options::skip_gc]). Limit is not
+    /// a part of encoded options.
+    ///
+    /// Default value: `None` (no limit).
+    pub max_content_bytes: Option<usize>,
+    /// Turns a document into a read-only replica: remote updates can still be applied via
+    /// [TransactionMut::apply_update], but local edits are rejected with [ReadOnlyError]. This
+    /// way viewer-only clients can reuse the same code as editors without risking to accidentally
+    /// produce updates of their own. Flag is not a part of encoded options.
+    ///
+    /// Default value: `false`.
+    pub read_only: bool,
+    /// Aliases of root-level types, mapping names used to access root types (eg. via
+    /// [Doc::get_or_insert_text]) to names under which they are stored in the document (see:
+    /// [Doc::rename_root]). Aliases are resolved locally, while updates keep referring to stored
+    /// names. Aliases are not a part of encoded options.
+    ///
+    /// Default value: empty.
+    pub root_aliases: HashMap<Arc<str>, Arc<str>>,
 }
 
 impl Options {
+    /// Default value of [Options::text_block_capacity] (64KiB).
+    pub const DEFAULT_TEXT_BLOCK_CAPACITY: usize = 64 * 1024;
+
     pub fn with_client_id(client_id: ClientID) -> Self {
         Options {
             client_id,
@@ -729,6 +1643,15 @@ impl Options {
             skip_gc: false,
             auto_load: false,
             should_load: true,
+            codec: None,
+            text_block_capacity: Self::DEFAULT_TEXT_BLOCK_CAPACITY,
+            text_normalizer: None,
+            map_conflict_resolver: None,
+            callback_panic_hook: None,
+            max_blocks: None,
+            max_content_bytes: None,
+            read_only: false,
+            root_aliases: HashMap::new(),
         }
     }
 
@@ -741,6 +1664,15 @@ impl Options {
             skip_gc: false,
             auto_load: false,
             should_load: true,
+            codec: None,
+            text_block_capacity: Self::DEFAULT_TEXT_BLOCK_CAPACITY,
+            text_normalizer: None,
+            map_conflict_resolver: None,
+            callback_panic_hook: None,
+            max_blocks: None,
+            max_content_bytes: None,
+            read_only: false,
+            root_aliases: HashMap::new(),
         }
     }
 
@@ -761,6 +1693,77 @@ impl Options {
     }
 }
 
+/// Function used to normalize text chunks inserted locally into text collections, configured
+/// via [Options::text_normalizer]. Its main purpose is to make sure that visually identical
+/// strings typed on different platforms (eg. macOS input methods producing NFD sequences) are
+/// stored using the same Unicode normalization form, so that they can be reliably searched and
+/// deduplicated.
+///
+/// Normalization happens before the text is inserted, so the length of inserted text may be
+/// different from the length of the chunk passed to methods like [Text::insert](crate::Text::insert).
+/// Normalizer should never return an empty string for non-empty input - in such case the original
+/// chunk is inserted instead.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, GetString, Options, Text, TextNormalizer, Transact};
+/// use std::borrow::Cow;
+///
+/// // in practice you'd use eg. `unicode-normalization` crate: `Cow::Owned(s.nfc().collect())`
+/// let normalizer = TextNormalizer::new(|s: &str| {
+///     if s.contains("e\u{301}") {
+///         Cow::Owned(s.replace("e\u{301}", "\u{e9}"))
+///     } else {
+///         Cow::Borrowed(s)
+///     }
+/// });
+/// let doc = Doc::with_options(Options {
+///     text_normalizer: Some(normalizer),
+///     ..Options::default()
+/// });
+/// let text = doc.get_or_insert_text("text");
+/// text.push(&mut doc.transact_mut(), "cafe\u{301}");
+/// assert_eq!(text.get_string(&doc.transact()), "caf\u{e9}");
+/// ```
+#[derive(Clone)]
+pub struct TextNormalizer(Arc<NormalizeFn>);
+
+type NormalizeFn = dyn Fn(&str) -> Cow<str> + Send + Sync;
+
+impl TextNormalizer {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> Cow<str> + Send + Sync + 'static,
+    {
+        TextNormalizer(Arc::new(f))
+    }
+
+    /// Normalizes a given text `chunk`.
+    pub fn normalize<'a>(&self, chunk: &'a str) -> Cow<'a, str> {
+        let normalized = (self.0)(chunk);
+        if normalized.is_empty() && !chunk.is_empty() {
+            Cow::Borrowed(chunk)
+        } else {
+            normalized
+        }
+    }
+}
+
+impl PartialEq for TextNormalizer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TextNormalizer {}
+
+impl std::fmt::Debug for TextNormalizer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TextNormalizer({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
 impl Default for Options {
     fn default() -> Self {
         let mut rng = fastrand::Rng::new();
@@ -851,7 +1854,42 @@ pub trait Transact {
     /// a [TransactionAcqError::ExclusiveAcqFailed] error.
     fn try_transact_mut_with<T>(&self, origin: T) -> Result<TransactionMut, TransactionAcqError>
     where
-        T: Into<Origin>;
+        T: Into<Origin>;
+
+    /// Creates and returns a read-write transaction meant for making local edits. Unlike
+    /// [Self::try_transact_mut], it fails upfront with [TransactionAcqError::ReadOnly] when called
+    /// on a read-only document replica (see: [Options::read_only]), instead of letting edit
+    /// methods panic with [ReadOnlyError] later on.
+    ///
+    /// # Errors
+    ///
+    /// Apart from [TransactionAcqError::ReadOnly], this method fails for the same reasons as
+    /// [Self::try_transact_mut].
+    fn try_transact_edit(&self) -> Result<TransactionMut<'_>, TransactionAcqError> {
+        let txn = self.try_transact_mut()?;
+        if txn.is_read_only() {
+            Err(TransactionAcqError::ReadOnly)
+        } else {
+            Ok(txn)
+        }
+    }
+
+    /// Creates and returns a read-write transaction meant for making local edits, with an `origin`
+    /// classifier attached. See [Self::try_transact_edit] and [Self::try_transact_mut_with].
+    fn try_transact_edit_with<T>(
+        &self,
+        origin: T,
+    ) -> Result<TransactionMut<'_>, TransactionAcqError>
+    where
+        T: Into<Origin>,
+    {
+        let txn = self.try_transact_mut_with(origin)?;
+        if txn.is_read_only() {
+            Err(TransactionAcqError::ReadOnly)
+        } else {
+            Ok(txn)
+        }
+    }
 
     /// Creates and returns a read-write capable transaction with an `origin` classifier attached.
     /// This transaction can be used to mutate the contents of underlying document store and upon
@@ -918,6 +1956,71 @@ impl Transact for Doc {
             Some(origin.into()),
         ))
     }
+
+    fn try_transact_edit(&self) -> Result<TransactionMut<'_>, TransactionAcqError> {
+        // check upfront, so that rejected transaction is never committed
+        if self.options().read_only {
+            return Err(TransactionAcqError::ReadOnly);
+        }
+        self.try_transact_mut()
+    }
+
+    fn try_transact_edit_with<T>(
+        &self,
+        origin: T,
+    ) -> Result<TransactionMut<'_>, TransactionAcqError>
+    where
+        T: Into<Origin>,
+    {
+        if self.options().read_only {
+            return Err(TransactionAcqError::ReadOnly);
+        }
+        self.try_transact_mut_with(origin)
+    }
+}
+
+/// Error returned when a change made to a document would exceed one of the quotas configured
+/// via [Options::max_blocks] or [Options::max_content_bytes].
+///
+/// Remote updates exceeding a quota are rejected as a whole by [TransactionMut::apply_update]
+/// with [UpdateError::QuotaExceeded](crate::UpdateError::QuotaExceeded). Local edits made with
+/// fallible methods like [Text::try_insert](crate::Text::try_insert) are rejected with
+/// [EditError::QuotaExceeded](crate::EditError::QuotaExceeded), while infallible ones like
+/// [Text::insert](crate::Text::insert) panic with this error instead.
+/// In both cases, callbacks subscribed via [Doc::observe_quota_exceeded] are called beforehand.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaExceeded {
+    /// Number of blocks stored by a document would exceed [Options::max_blocks].
+    #[error("document would store {requested} blocks, exceeding its limit of {limit}")]
+    Blocks { limit: usize, requested: usize },
+    /// Size of a document content would exceed [Options::max_content_bytes]. Content size is
+    /// measured when blocks are integrated: strings and binaries by their length in bytes, other
+    /// primitive values by the length of their binary encoding. Size of deleted content is
+    /// subtracted once its blocks are garbage collected.
+    #[error("document would store {requested} bytes of content, exceeding its limit of {limit}")]
+    ContentBytes { limit: usize, requested: usize },
+}
+
+/// Error signaling an attempt to locally modify a document configured as a read-only replica
+/// (see: [Options::read_only]).
+///
+/// Since local edits are infallible, methods like [Text::insert](crate::Text::insert) panic with
+/// this error. Use [Transact::try_transact_edit] to acquire a transaction, which fails with
+/// [TransactionAcqError::ReadOnly] for read-only documents, or [TransactionMut::is_read_only]
+/// to check if edits are allowed beforehand.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("cannot modify a read-only document")]
+pub struct ReadOnlyError;
+
+/// Error returned by [Doc::from_updates] when some of the updates could not be applied.
+#[derive(Error, Debug)]
+#[error("failed to apply {} update(s) while loading a document", errors.len())]
+pub struct LoadError {
+    /// Document with all successfully applied updates.
+    pub doc: Doc,
+    /// Errors of updates, which could not be applied, paired with their positions in an input
+    /// sequence.
+    pub errors: Vec<(usize, CodecError)>,
 }
 
 #[derive(Error, Debug)]
@@ -928,6 +2031,8 @@ pub enum TransactionAcqError {
     ExclusiveAcqFailed(BorrowMutError),
     #[error("All references to a parent document containing this structure has been dropped.")]
     DocumentDropped,
+    #[error("Failed to acquire transaction for local edits. Document is a read-only replica.")]
+    ReadOnly,
 }
 
 impl From<BorrowError> for TransactionAcqError {
@@ -971,18 +2076,25 @@ impl DocAddr {
 
 #[cfg(test)]
 mod test {
-    use crate::block::ItemContent;
+    use crate::block::{BlockCell, ItemContent};
+    use crate::doc::ReadOnlyError;
+    use crate::doc::TransactionAcqError;
+    use crate::observer::SubscriptionKey;
     use crate::test_utils::exchange_updates;
-    use crate::transaction::{ReadTxn, TransactionMut};
-    use crate::types::ToJson;
-    use crate::update::Update;
+    use crate::transaction::{Origin, ReadTxn, TransactionMut, TxnOptions};
+    use crate::types::text::{Diff, YChange};
+    use crate::types::Attrs;
+    use crate::types::{Observable, ToJson, TypeRef, Value};
+    use crate::undo::UndoError;
+    use crate::update::{Update, UpdateError};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
     use crate::{
         any, Any, Array, ArrayPrelim, ArrayRef, DeleteSet, Doc, GetString, Map, MapPrelim, MapRef,
-        OffsetKind, Options, StateVector, Subscription, Text, TextRef, Transact, Uuid, WriteTxn,
-        XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlTextPrelim, XmlTextRef,
+        OffsetKind, Options, SharedRef, StateVector, Subscription, Text, TextRef, Transact, Uuid,
+        WriteTxn, XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlTextPrelim, XmlTextRef, ID,
     };
+    use crate::{CommitInfo, EditError, QuotaExceeded, UndoManager};
     use std::collections::BTreeSet;
 
     use arc_swap::ArcSwapOption;
@@ -1011,7 +2123,8 @@ mod test {
         let doc = Doc::new();
         let txt = doc.get_or_insert_text("type");
         let mut txn = doc.transact_mut();
-        txn.apply_update(Update::decode_v1(update).unwrap());
+        txn.apply_update(Update::decode_v1(update).unwrap())
+            .unwrap();
 
         let actual = txt.get_string(&txn);
         assert_eq!(actual, "210".to_owned());
@@ -1038,7 +2151,8 @@ mod test {
         let doc = Doc::new();
         let txt = doc.get_or_insert_text("type");
         let mut txn = doc.transact_mut();
-        txn.apply_update(Update::decode_v2(update).unwrap());
+        txn.apply_update(Update::decode_v2(update).unwrap())
+            .unwrap();
 
         let actual = txt.get_string(&txn);
         assert_eq!(actual, "210".to_owned());
@@ -1091,10 +2205,10 @@ mod test {
 
         // decode an update incoming from A and integrate it at B
         let update = Update::decode_v1(binary.as_slice()).unwrap();
-        let pending = update.integrate(&mut t2);
+        let outcome = update.integrate(&mut t2, None).unwrap();
 
-        assert!(pending.0.is_none());
-        assert!(pending.1.is_none());
+        assert!(outcome.pending.is_none());
+        assert!(outcome.pending_ds.is_none());
 
         // check if B sees the same thing that A does
         assert_eq!(txt.get_string(&t1), "hello world".to_string());
@@ -1119,7 +2233,8 @@ mod test {
             let mut txn2 = doc2.transact_mut();
             let sv = txn2.state_vector().encode_v1();
             let u = txn.encode_diff_v1(&StateVector::decode_v1(sv.as_slice()).unwrap());
-            txn2.apply_update(Update::decode_v1(u.as_slice()).unwrap());
+            txn2.apply_update(Update::decode_v1(u.as_slice()).unwrap())
+                .unwrap();
         }
         assert_eq!(counter.load(Ordering::SeqCst), 3); // update has been propagated
 
@@ -1130,7 +2245,8 @@ mod test {
             let mut txn2 = doc2.transact_mut();
             let sv = txn2.state_vector().encode_v1();
             let u = txn.encode_diff_v1(&StateVector::decode_v1(sv.as_slice()).unwrap());
-            txn2.apply_update(Update::decode_v1(u.as_slice()).unwrap());
+            txn2.apply_update(Update::decode_v1(u.as_slice()).unwrap())
+                .unwrap();
         }
         assert_eq!(counter.load(Ordering::SeqCst), 3); // since subscription has been dropped, update was not propagated
     }
@@ -1183,7 +2299,7 @@ mod test {
         for u in updates {
             let mut txn = doc.transact_mut();
             let u = Update::decode_v1(u.as_slice()).unwrap();
-            txn.apply_update(u);
+            txn.apply_update(u).unwrap();
         }
         assert_eq!(txt.get_string(&doc.transact()), "abcd".to_string());
     }
@@ -1217,7 +2333,7 @@ mod test {
         ];
         for u in updates {
             let u = Update::decode_v1(&u).unwrap();
-            d1.transact_mut().apply_update(u);
+            d1.transact_mut().apply_update(u).unwrap();
         }
 
         assert_eq!("a", source_1.get_string(&d1.transact()));
@@ -1229,7 +2345,7 @@ mod test {
             .transact()
             .encode_state_as_update_v1(&StateVector::decode_v1(&state_2).unwrap());
         let update = Update::decode_v1(&update).unwrap();
-        d2.transact_mut().apply_update(update);
+        d2.transact_mut().apply_update(update).unwrap();
 
         assert_eq!("a", source_2.get_string(&d2.transact()));
 
@@ -1238,7 +2354,7 @@ mod test {
             56, 4, 1, 120, 0,
         ])
         .unwrap();
-        d1.transact_mut().apply_update(update);
+        d1.transact_mut().apply_update(update).unwrap();
         assert_eq!("ab", source_1.get_string(&d1.transact()));
 
         let d3 = Doc::new();
@@ -1247,7 +2363,7 @@ mod test {
         let state_3 = StateVector::decode_v1(&state_3).unwrap();
         let update = d1.transact().encode_state_as_update_v1(&state_3);
         let update = Update::decode_v1(&update).unwrap();
-        d3.transact_mut().apply_update(update);
+        d3.transact_mut().apply_update(update).unwrap();
 
         assert_eq!("ab", source_3.get_string(&d3.transact()));
     }
@@ -1320,14 +2436,16 @@ mod test {
         let d2 = Doc::with_client_id(2);
         let txt2 = d2.get_or_insert_text("text");
         d2.transact_mut()
-            .apply_update(Update::decode_v1(&u).unwrap());
+            .apply_update(Update::decode_v1(&u).unwrap())
+            .unwrap();
 
         txt1.insert(&mut d1.transact_mut(), 5, "world");
         let u = d1
             .transact()
             .encode_state_as_update_v1(&StateVector::default());
         d2.transact_mut()
-            .apply_update(Update::decode_v1(&u).unwrap());
+            .apply_update(Update::decode_v1(&u).unwrap())
+            .unwrap();
 
         assert_eq!(
             txt1.get_string(&d1.transact()),
@@ -1411,7 +2529,7 @@ mod test {
             141, 223, 163, 226, 10, 1, 0, 1,
         ];
         let update = Update::decode_v2(bin).unwrap();
-        doc.transact_mut().apply_update(update);
+        doc.transact_mut().apply_update(update).unwrap();
 
         let root = doc.get_or_insert_map("root");
         let actual = root.to_json(&doc.transact());
@@ -1452,11 +2570,106 @@ mod test {
 
         let d2 = Doc::with_client_id(2);
         let txt2 = d2.get_or_insert_text("text");
-        d2.transact_mut().apply_update(update);
+        d2.transact_mut().apply_update(update).unwrap();
 
         assert_eq!(txt2.get_string(&d2.transact()), "hello".to_string());
     }
 
+    #[test]
+    fn transact_at_snapshot() {
+        let mut options = Options::with_client_id(1);
+        options.skip_gc = true;
+        let doc = Doc::with_options(options);
+        let txt = doc.get_or_insert_text("text");
+        let array = doc.get_or_insert_array("array");
+        let map = doc.get_or_insert_map("map");
+        {
+            let mut txn = doc.transact_mut();
+            txt.insert(&mut txn, 0, "hello");
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", 2);
+        }
+        let snapshot = doc.transact().snapshot();
+        {
+            let mut txn = doc.transact_mut();
+            txt.insert(&mut txn, 5, " world");
+            txt.remove_range(&mut txn, 0, 2);
+            array.remove(&mut txn, 1);
+            array.push_back(&mut txn, 4);
+            map.insert(&mut txn, "a", 10);
+            map.remove(&mut txn, "b");
+            map.insert(&mut txn, "c", 3);
+        }
+
+        let txn = doc.transact_at(&snapshot);
+        assert_eq!(txt.get_string(&txn), "hello");
+        assert_eq!(txt.len(&txn), 5);
+        assert_eq!(
+            txt.diff(&txn, YChange::identity),
+            vec![Diff::new("hello".into(), None)]
+        );
+        assert_eq!(array.to_json(&txn), any!([1, 2, 3]));
+        assert_eq!(array.len(&txn), 3);
+        assert_eq!(array.get(&txn, 1), Some(2.into()));
+        assert_eq!(array.iter(&txn).collect::<Vec<_>>().len(), 3);
+        assert_eq!(map.to_json(&txn), any!({"a": 1, "b": 2}));
+        assert_eq!(map.get(&txn, "a"), Some(1.into()));
+        assert!(!map.contains_key(&txn, "c"));
+        assert_eq!(map.len(&txn), 2);
+        drop(txn);
+
+        let txn = doc.transact();
+        assert_eq!(txt.get_string(&txn), "llo world");
+        assert_eq!(array.to_json(&txn), any!([1, 3, 4]));
+        assert_eq!(map.to_json(&txn), any!({"a": 10, "c": 3}));
+    }
+
+    #[test]
+    fn transact_at_snapshot_does_not_split_blocks() {
+        let mut options = Options::with_client_id(1);
+        options.skip_gc = true;
+        let doc = Doc::with_options(options);
+        let txt = doc.get_or_insert_text("text");
+        let array = doc.get_or_insert_array("array");
+        let xml = doc.get_or_insert_xml_fragment("xml");
+        {
+            let mut txn = doc.transact_mut();
+            txt.insert(&mut txn, 0, "hello world");
+            array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+            xml.insert(&mut txn, 0, XmlTextPrelim::new("a"));
+            xml.insert(&mut txn, 1, XmlTextPrelim::new("b"));
+        }
+        let snapshot = doc.transact().snapshot();
+        {
+            let mut txn = doc.transact_mut();
+            txt.remove_range(&mut txn, 3, 4);
+            txt.insert(&mut txn, 7, "!");
+            array.remove_range(&mut txn, 1, 2);
+            xml.remove_range(&mut txn, 0, 1);
+        }
+        let block_count = |doc: &Doc| doc.transact().store().blocks.get_client(&1).unwrap().len();
+        let blocks_before = block_count(&doc);
+
+        let txn = doc.transact_at(&snapshot);
+        assert_eq!(txt.get_string(&txn), "hello world");
+        assert_eq!(txt.len(&txn), 11);
+        assert_eq!(
+            txt.diff(&txn, YChange::identity),
+            vec![Diff::new("hello world".into(), None)]
+        );
+        assert_eq!(array.to_json(&txn), any!([1, 2, 3, 4]));
+        assert_eq!(array.get(&txn, 2), Some(3.into()));
+        assert_eq!(xml.len(&txn), 2);
+        drop(txn);
+
+        assert_eq!(block_count(&doc), blocks_before);
+        let txn = doc.transact();
+        assert_eq!(txt.get_string(&txn), "helorld!");
+        assert_eq!(array.to_json(&txn), any!([1, 4]));
+        assert_eq!(xml.len(&txn), 1);
+    }
+
     #[test]
     fn snapshot_non_splitting_text() {
         let mut options = Options::default();
@@ -1481,7 +2694,7 @@ mod test {
         let remote_txt = remote_doc.get_or_insert_text("name");
         let mut txn = remote_doc.transact_mut();
         let update = Update::decode_v1(&state_diff).unwrap();
-        txn.apply_update(update);
+        txn.apply_update(update).unwrap();
 
         let actual = remote_txt.get_string(&txn);
 
@@ -1687,7 +2900,7 @@ mod test {
         let mut txn = doc.transact_mut();
         for diff in diffs {
             let u = Update::decode_v1(diff.as_slice()).unwrap();
-            txn.apply_update(u);
+            txn.apply_update(u).unwrap();
         }
     }
 
@@ -1733,7 +2946,8 @@ mod test {
             let mut t2 = d2.transact_mut();
             root.remove(&mut t2, 0);
             d1.transact_mut()
-                .apply_update(Update::decode_v1(&t2.encode_update_v1()).unwrap());
+                .apply_update(Update::decode_v1(&t2.encode_update_v1()).unwrap())
+                .unwrap();
         }
 
         {
@@ -1743,7 +2957,8 @@ mod test {
             a3.push_back(&mut t3, "B");
             // D1 got update which already removed a3, but this must not cause panic
             d1.transact_mut()
-                .apply_update(Update::decode_v1(&t3.encode_update_v1()).unwrap());
+                .apply_update(Update::decode_v1(&t3.encode_update_v1()).unwrap())
+                .unwrap();
         }
 
         exchange_updates(&[&d1, &d2, &d3]);
@@ -1757,6 +2972,142 @@ mod test {
         assert_eq!(r3, r1);
     }
 
+    #[test]
+    fn changed_branches() {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let text = doc.get_or_insert_text("text");
+        let nested = root.insert(&mut doc.transact_mut(), "nested", MapPrelim::<u32>::new());
+
+        let mut txn = doc.transact_mut();
+        assert!(txn.changed_branches().is_empty());
+        nested.insert(&mut txn, "a", 1);
+        text.push(&mut txn, "hello");
+        // newly created collections are not reported
+        let created = nested.insert(&mut txn, "created", MapPrelim::<u32>::new());
+        created.insert(&mut txn, "b", 2);
+
+        let mut changed: Vec<_> = txn
+            .changed_branches()
+            .into_iter()
+            .map(|v| v.to_json(&txn))
+            .collect();
+        changed.sort_by_key(|v| v.to_string());
+        assert_eq!(
+            changed,
+            vec![any!("hello"), any!({"a": 1, "created": {"b": 2}})]
+        );
+    }
+
+    #[test]
+    fn per_transaction_gc() {
+        fn deleted_content(text: &TextRef) -> Vec<bool> {
+            let mut result = Vec::new();
+            let mut curr = text.as_ref().start;
+            while let Some(item) = curr.as_deref() {
+                if item.is_deleted() {
+                    result.push(matches!(item.content, ItemContent::String(_)));
+                }
+                curr = item.right;
+            }
+            result
+        }
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "abcdef");
+
+        // garbage collection disabled for a single transaction
+        {
+            let mut txn = doc.transact_mut_with_opts(TxnOptions {
+                origin: None,
+                gc: Some(false),
+            });
+            text.remove_range(&mut txn, 0, 1);
+        }
+        assert_eq!(deleted_content(&text), vec![true]);
+
+        // document settings apply to other transactions
+        text.remove_range(&mut doc.transact_mut(), 1, 1);
+        assert_eq!(deleted_content(&text), vec![true, false]);
+
+        // garbage collection forced despite document settings
+        let doc = Doc::with_options(Options {
+            client_id: 1,
+            skip_gc: true,
+            ..Options::default()
+        });
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "abcdef");
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
+        {
+            let mut txn = doc.transact_mut_with_opts(TxnOptions {
+                origin: Some("gc".into()),
+                gc: None,
+            });
+            assert_eq!(txn.origin(), Some(&Origin::from("gc")));
+            text.remove_range(&mut txn, 1, 1);
+            txn.force_gc();
+        }
+        assert_eq!(deleted_content(&text), vec![true, false]);
+        assert_eq!(text.get_string(&doc.transact()), "bdef");
+    }
+
+    #[test]
+    fn destroy_subdoc() {
+        let doc = Doc::with_options(Options {
+            client_id: 1,
+            skip_gc: true,
+            ..Options::default()
+        });
+        let destroyed = Arc::new(Mutex::new(Vec::new()));
+        let destroyed_c = destroyed.clone();
+        let _sub = doc.observe_subdocs(move |_, e| {
+            let mut destroyed = destroyed_c.lock().unwrap();
+            for subdoc in e.destroyed() {
+                destroyed.push(subdoc.guid().clone());
+            }
+            assert_eq!(e.removed().len(), e.destroyed().len());
+        });
+        let subdocs = doc.get_or_insert_map("subdocs");
+        let uuid_a: Uuid = "A".into();
+        let doc_a = Doc::with_options({
+            let mut o = Options::default();
+            o.guid = uuid_a.clone();
+            o
+        });
+        subdocs.insert(&mut doc.transact_mut(), "a", doc_a);
+        let doc_a = doc.transact().subdoc(&uuid_a).cloned().unwrap();
+        let destroy_called = Arc::new(AtomicU32::new(0));
+        let destroy_called_c = destroy_called.clone();
+        let _sub_destroy = doc_a
+            .observe_destroy(move |_, _| {
+                destroy_called_c.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+
+        let remote = Doc::with_client_id(2);
+        exchange_updates(&[&doc, &remote]);
+
+        assert!(doc.destroy_subdoc(&uuid_a));
+        assert!(!doc.destroy_subdoc(&uuid_a));
+        assert_eq!(destroyed.lock().unwrap().as_slice(), &[uuid_a.clone()]);
+        assert_eq!(destroy_called.load(Ordering::SeqCst), 1);
+
+        let txn = doc.transact();
+        assert_eq!(txn.subdocs().count(), 0);
+        assert_eq!(subdocs.len(&txn), 0);
+        // block has been collected even though GC is disabled
+        let block = txn.store().blocks.get_item(&ID::new(1, 0)).unwrap();
+        assert_matches!(&block.content, ItemContent::Deleted(1));
+        drop(txn);
+
+        exchange_updates(&[&doc, &remote]);
+        let txn = remote.transact();
+        assert_eq!(txn.get_map("subdocs").unwrap().len(&txn), 0);
+        assert_eq!(txn.subdocs().count(), 0);
+    }
+
     #[test]
     fn subdoc() {
         let doc = Doc::with_client_id(1);
@@ -1856,73 +3207,574 @@ mod test {
             let doc_c_ref = subdocs.insert(&mut txn, "c", doc_c);
             doc_c_ref.load(&mut txn);
         }
-        let actual = event.swap(None);
+        let actual = event.swap(None);
+        assert_eq!(
+            actual,
+            Some(Arc::new((
+                vec![uuid_c.clone()],
+                vec![],
+                vec![uuid_c.clone()]
+            )))
+        );
+
+        let guids: BTreeSet<_> = doc.transact().subdoc_guids().cloned().collect();
+        assert_eq!(guids, BTreeSet::from([uuid_a.clone(), uuid_c.clone()]));
+
+        let data = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let doc2 = Doc::new();
+        let event = Arc::new(ArcSwapOption::default());
+        let event_c = event.clone();
+        let _sub = doc2.observe_subdocs(move |_, e| {
+            let added: Vec<_> = e.added().map(|d| d.guid().clone()).collect();
+            let removed: Vec<_> = e.removed().map(|d| d.guid().clone()).collect();
+            let loaded: Vec<_> = e.loaded().map(|d| d.guid().clone()).collect();
+            event_c.store(Some(Arc::new((added, removed, loaded))));
+        });
+        let update = Update::decode_v1(&data).unwrap();
+        doc2.transact_mut().apply_update(update).unwrap();
+        let mut actual = event.swap(None).unwrap();
+        Arc::get_mut(&mut actual).unwrap().0.sort();
+        assert_eq!(
+            actual,
+            Arc::new((
+                vec![uuid_a.clone(), uuid_a.clone(), uuid_c.clone()],
+                vec![],
+                vec![]
+            ))
+        );
+
+        let subdocs = doc2.transact().get_map("mysubdocs").unwrap();
+        {
+            let mut txn = doc2.transact_mut();
+            let doc_ref = subdocs.get(&mut txn, "a").unwrap().cast::<Doc>().unwrap();
+            doc_ref.load(&mut txn);
+        }
+        let actual = event.swap(None);
+        assert_eq!(
+            actual,
+            Some(Arc::new((vec![], vec![], vec![uuid_a.clone()])))
+        );
+
+        let guids: BTreeSet<_> = doc2.transact().subdoc_guids().cloned().collect();
+        assert_eq!(guids, BTreeSet::from([uuid_a.clone(), uuid_c.clone()]));
+        {
+            let mut txn = doc2.transact_mut();
+            subdocs.remove(&mut txn, "a");
+        }
+
+        let actual = event.swap(None);
+        assert_eq!(
+            actual,
+            Some(Arc::new((vec![], vec![uuid_a.clone()], vec![])))
+        );
+
+        let mut guids: Vec<_> = doc2.transact().subdoc_guids().cloned().collect();
+        guids.sort();
+        assert_eq!(guids, vec![uuid_a.clone(), uuid_c.clone()]);
+    }
+
+    #[test]
+    fn encode_shared_ref_state_as_update() {
+        let d1 = Doc::with_client_id(1);
+        let body = d1.get_or_insert_text("body");
+        let list = d1.get_or_insert_array("list");
+        {
+            let mut txn = d1.transact_mut();
+            body.push(&mut txn, "abc");
+            list.push_back(&mut txn, "first");
+            list.push_back(&mut txn, MapPrelim::from([("a", 1)]));
+            body.push(&mut txn, "def");
+            list.push_back(&mut txn, "last");
+        }
+        let d2 = Doc::with_client_id(2);
+        exchange_updates(&[&d1, &d2]);
+        {
+            let mut txn = d2.transact_mut();
+            let list = txn.get_array("list").unwrap();
+            let nested: MapRef = list.get(&txn, 1).unwrap().cast().unwrap();
+            nested.insert(&mut txn, "b", 2);
+            nested.insert(&mut txn, "a", 3);
+            body.push(&mut txn, "ghi");
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let nested: MapRef = list.get(&d1.transact(), 1).unwrap().cast().unwrap();
+        let txn = d1.transact();
+        let v1 = nested.encode_state_as_update_v1(&txn, &StateVector::default());
+        let v2 = nested.encode_state_as_update_v2(&txn, &StateVector::default());
+        for update in [
+            Update::decode_v1(&v1).unwrap(),
+            Update::decode_v2(&v2).unwrap(),
+        ] {
+            let d3 = Doc::new();
+            let mut txn = d3.transact_mut();
+            txn.apply_update(update).unwrap();
+            assert!(txn.store().pending.is_none());
+            assert!(txn.get_text("body").is_none());
+            let list = txn.get_array("list").unwrap();
+            // "first" is included, since nested map was inserted next to it
+            assert_eq!(list.to_json(&txn), any!(["first", {"a": 3, "b": 2}]));
+        }
+
+        // only the changes not observed by the remote state vector are included
+        let d4 = Doc::new();
+        let update = list.encode_state_as_update_v1(&txn, &d4.transact().state_vector());
+        let sv = {
+            let mut txn = d4.transact_mut();
+            txn.apply_update(Update::decode_v1(&update).unwrap())
+                .unwrap();
+            assert_eq!(
+                txn.get_array("list").unwrap().to_json(&txn),
+                any!(["first", {"a": 3, "b": 2}, "last"])
+            );
+            txn.state_vector()
+        };
+        let update = list.encode_state_as_update_v1(&txn, &sv);
+        let update = Update::decode_v1(&update).unwrap();
+        assert_eq!(update.state_vector(), StateVector::default());
+    }
+
+    #[test]
+    fn encoder_pool_reuse() {
+        use crate::updates::encoder::{EncoderPool, EncoderV2};
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        let pool_v1: EncoderPool<EncoderV1> = EncoderPool::new(2);
+        let pool_v2: EncoderPool<EncoderV2> = EncoderPool::new(2);
+        let mut buf = Vec::new();
+        for i in 0..10 {
+            {
+                let mut txn = doc.transact_mut();
+                text.push(&mut txn, "abc");
+                map.insert(&mut txn, format!("key{}", i), i);
+                text.remove_range(&mut txn, 0, 1);
+            }
+            let txn = doc.transact();
+            let sv = StateVector::default();
+
+            buf.clear();
+            pool_v1.encode_into(&mut buf, |e| txn.encode_state_as_update(&sv, e));
+            assert_eq!(buf, txn.encode_state_as_update_v1(&sv));
+
+            buf.clear();
+            pool_v2.encode_into(&mut buf, |e| txn.encode_state_as_update(&sv, e));
+            assert_eq!(buf, txn.encode_state_as_update_v2(&sv));
+
+            buf.clear();
+            txn.encode_state_as_update_v1_into(&sv, &mut buf);
+            assert_eq!(buf, txn.encode_state_as_update_v1(&sv));
+
+            buf.clear();
+            txn.encode_state_as_update_v2_into(&sv, &mut buf);
+            assert_eq!(buf, txn.encode_state_as_update_v2(&sv));
+        }
+        assert_eq!(pool_v1.len(), 1);
+        assert_eq!(pool_v2.len(), 1);
+    }
+
+    #[test]
+    fn encode_state_as_update_to_writer() {
+        use crate::updates::encoder::StreamEncoderV1;
+
+        #[derive(Debug)]
+        struct ChunkWriter {
+            chunks: Vec<Vec<u8>>,
+            fail_after: usize,
+        }
+
+        impl std::io::Write for ChunkWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                if self.chunks.len() == self.fail_after {
+                    return Err(std::io::ErrorKind::BrokenPipe.into());
+                }
+                self.chunks.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        for i in 0..100 {
+            let mut txn = doc.transact_mut();
+            text.insert(&mut txn, 0, "abc");
+            map.insert(&mut txn, format!("key{}", i), i);
+            text.remove_range(&mut txn, 1, 1);
+        }
+        let txn = doc.transact();
+        let sv = StateVector::default();
+        let expected = txn.encode_state_as_update_v1(&sv);
+
+        let mut buf = Vec::new();
+        txn.encode_state_as_update_to(&sv, &mut buf).unwrap();
+        assert_eq!(buf, expected);
+
+        let writer = ChunkWriter {
+            chunks: Vec::new(),
+            fail_after: usize::MAX,
+        };
+        let mut encoder = StreamEncoderV1::with_chunk_size(writer, 64);
+        txn.encode_state_as_update(&sv, &mut encoder);
+        let writer = encoder.finish().unwrap();
+        assert!(writer.chunks.len() > 1);
+        assert_eq!(writer.chunks.concat(), expected);
+
+        let writer = ChunkWriter {
+            chunks: Vec::new(),
+            fail_after: 2,
+        };
+        let mut encoder = StreamEncoderV1::with_chunk_size(writer, 64);
+        txn.encode_state_as_update(&sv, &mut encoder);
+        let err = encoder.finish().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn resolve_path_on_remote_replica() {
+        use crate::types::{DeepObservable, Path, PathSegment, Value};
+
+        let server = Doc::with_client_id(1);
+        let pages = server.get_or_insert_array("pages");
+        {
+            let mut txn = server.transact_mut();
+            for i in 0..4 {
+                let page = pages.push_back(&mut txn, MapPrelim::<String>::new());
+                page.insert(&mut txn, "title", format!("page {}", i));
+            }
+        }
+        let client = Doc::with_client_id(2);
+        exchange_updates(&[&server, &client]);
+
+        let changed = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let changed = changed.clone();
+            pages.observe_deep(move |_, events| {
+                for e in events.iter() {
+                    let mut path = e.path();
+                    path.push_front(PathSegment::Key("pages".into()));
+                    changed.lock().unwrap().push(path);
+                }
+            })
+        };
+        {
+            let mut txn = server.transact_mut();
+            let page: MapRef = pages.get(&txn, 3).unwrap().cast().unwrap();
+            page.insert(&mut txn, "title", "renamed");
+        }
+        exchange_updates(&[&server, &client]);
+
+        let path = changed.lock().unwrap().pop().unwrap();
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, r#"["pages",3]"#);
+
+        let mut path: Path = serde_json::from_str(&json).unwrap();
+        path.push_back(PathSegment::Key("title".into()));
+        let txn = client.transact();
+        assert_eq!(client.resolve_path(&txn, &path), Some("renamed".into()));
+
+        let page = client.resolve_path(
+            &txn,
+            &Path::from(vec![
+                PathSegment::Key("pages".into()),
+                PathSegment::Index(1),
+            ]),
+        );
+        assert_matches!(page, Some(Value::YMap(_)));
+
+        let invalid: Path = serde_json::from_str(r#"["pages", 4, "title"]"#).unwrap();
+        assert_eq!(client.resolve_path(&txn, &invalid), None);
+        let invalid: Path = serde_json::from_str(r#"["pages", 3, "title", "x"]"#).unwrap();
+        assert_eq!(client.resolve_path(&txn, &invalid), None);
+        let invalid: Path = serde_json::from_str(r#"[0, "title"]"#).unwrap();
+        assert_eq!(client.resolve_path(&txn, &invalid), None);
+        assert!(serde_json::from_str::<Path>(r#"["pages", -1]"#).is_err());
+    }
+
+    #[test]
+    fn transaction_origin_chain() {
+        use crate::Origin;
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let pending = pending.clone();
+            let observed = observed.clone();
+            doc.observe_update_v1(move |txn, _| {
+                observed.lock().unwrap().push(txn.parent_origins().to_vec());
+                // schedule a normalization pass, unless it was caused by one already
+                if !txn.is_caused_by(&Origin::from("normalize")) {
+                    pending.lock().unwrap().push(txn.origin_chain());
+                }
+            })
+            .unwrap()
+        };
+
+        text.push(&mut doc.transact_mut_with("user"), "hello");
+        loop {
+            let parents = match pending.lock().unwrap().pop() {
+                Some(parents) => parents,
+                None => break,
+            };
+            let mut txn = doc.transact_mut_caused_by("normalize", parents);
+            text.push(&mut txn, "!");
+            let chain = txn.origin_chain();
+            drop(txn);
+
+            // derived transactions keep the whole chain
+            let mut txn = doc.transact_mut_caused_by("format", chain);
+            text.push(&mut txn, "?");
+            assert!(txn.is_caused_by(&Origin::from("user")));
+            assert!(!txn.is_caused_by(&Origin::from("other")));
+        }
+
+        assert_eq!(text.get_string(&doc.transact()), "hello!?");
         assert_eq!(
-            actual,
-            Some(Arc::new((
-                vec![uuid_c.clone()],
+            observed.lock().unwrap().as_slice(),
+            &[
                 vec![],
-                vec![uuid_c.clone()]
-            )))
+                vec![Origin::from("user")],
+                vec![Origin::from("normalize"), Origin::from("user")],
+            ]
         );
+    }
 
-        let guids: BTreeSet<_> = doc.transact().subdoc_guids().cloned().collect();
-        assert_eq!(guids, BTreeSet::from([uuid_a.clone(), uuid_c.clone()]));
+    #[test]
+    fn commit_log() {
+        let ts = Arc::new(AtomicU32::new(0));
+        let clock = {
+            let ts = ts.clone();
+            move || ts.fetch_add(1, Ordering::SeqCst) as u64 * 1000
+        };
+        let d1 = Doc::with_client_id(1);
+        d1.enable_commit_log(clock, 2).unwrap();
+        let text = d1.get_or_insert_text("text");
+        let infos = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let infos = infos.clone();
+            d1.observe_transaction_cleanup(move |txn, _| {
+                infos.lock().unwrap().push(txn.commit_info().cloned());
+            })
+            .unwrap()
+        };
 
-        let data = doc
+        text.push(&mut d1.transact_mut_with("a"), "hello");
+        d1.transact_mut(); // no changes
+        text.push(&mut d1.transact_mut(), " world");
+
+        // apply remote update
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        t2.push(&mut d2.transact_mut(), "!");
+        let update = d2
             .transact()
             .encode_state_as_update_v1(&StateVector::default());
+        d1.transact_mut_with("remote")
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
 
-        let doc2 = Doc::new();
-        let event = Arc::new(ArcSwapOption::default());
-        let event_c = event.clone();
-        let _sub = doc2.observe_subdocs(move |_, e| {
-            let added: Vec<_> = e.added().map(|d| d.guid().clone()).collect();
-            let removed: Vec<_> = e.removed().map(|d| d.guid().clone()).collect();
-            let loaded: Vec<_> = e.loaded().map(|d| d.guid().clone()).collect();
-            event_c.store(Some(Arc::new((added, removed, loaded))));
-        });
-        let update = Update::decode_v1(&data).unwrap();
-        doc2.transact_mut().apply_update(update);
-        let mut actual = event.swap(None).unwrap();
-        Arc::get_mut(&mut actual).unwrap().0.sort();
+        let infos: Vec<_> = infos.lock().unwrap().drain(..).collect();
         assert_eq!(
-            actual,
-            Arc::new((
-                vec![uuid_a.clone(), uuid_a.clone(), uuid_c.clone()],
-                vec![],
-                vec![]
-            ))
+            infos,
+            vec![
+                Some(CommitInfo {
+                    seq: 1,
+                    timestamp: 0,
+                    client_id: 1,
+                    origin: Some("a".into())
+                }),
+                None,
+                Some(CommitInfo {
+                    seq: 2,
+                    timestamp: 1000,
+                    client_id: 1,
+                    origin: None
+                }),
+                Some(CommitInfo {
+                    seq: 3,
+                    timestamp: 2000,
+                    client_id: 1,
+                    origin: Some("remote".into())
+                }),
+            ]
         );
 
-        let subdocs = doc2.transact().get_map("mysubdocs").unwrap();
+        let txn = d1.transact();
+        let log = txn.commit_log().unwrap();
+        assert_eq!(log.len(), 2); // capacity limit
+        let seqs: Vec<_> = log.iter().map(|i| i.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+        drop(txn);
+
+        d1.disable_commit_log().unwrap();
+        text.push(&mut d1.transact_mut(), "?");
+        assert!(d1.transact().commit_log().is_none());
+    }
+
+    #[test]
+    fn last_modified_tracking() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let text = doc.get_or_insert_text("text");
+        // commit log is required
+        assert!(!doc.enable_last_modified(true).unwrap());
+        doc.enable_commit_log(|| 0, 0).unwrap();
+        assert!(doc.enable_last_modified(true).unwrap());
+
+        let nested = array.push_back(&mut doc.transact_mut(), MapPrelim::<String>::new());
+        text.push(&mut doc.transact_mut(), "abc");
         {
-            let mut txn = doc2.transact_mut();
-            let doc_ref = subdocs.get(&mut txn, "a").unwrap().cast::<Doc>().unwrap();
-            doc_ref.load(&mut txn);
+            let txn = doc.transact();
+            assert_eq!(array.last_modified(&txn).unwrap().seq, 1);
+            assert_eq!(text.last_modified(&txn).unwrap().seq, 2);
+            assert!(nested.last_modified(&txn).is_none());
         }
-        let actual = event.swap(None);
-        assert_eq!(
-            actual,
-            Some(Arc::new((vec![], vec![], vec![uuid_a.clone()])))
-        );
 
-        let guids: BTreeSet<_> = doc2.transact().subdoc_guids().cloned().collect();
-        assert_eq!(guids, BTreeSet::from([uuid_a.clone(), uuid_c.clone()]));
+        // changes to nested collections mark their parents as well
+        nested.insert(&mut doc.transact_mut_with("nested"), "key", "value");
         {
-            let mut txn = doc2.transact_mut();
-            subdocs.remove(&mut txn, "a");
+            let txn = doc.transact();
+            let info = nested.last_modified(&txn).unwrap();
+            assert_eq!(info.seq, 3);
+            assert_eq!(info.origin, Some("nested".into()));
+            assert_eq!(array.last_modified(&txn), Some(info));
+            assert_eq!(text.last_modified(&txn).unwrap().seq, 2);
         }
 
-        let actual = event.swap(None);
+        // remote changes
+        let remote = Doc::with_client_id(2);
+        let remote_text = remote.get_or_insert_text("text");
+        remote_text.push(&mut remote.transact_mut(), "def");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        {
+            let txn = doc.transact();
+            assert_eq!(text.last_modified(&txn).unwrap().seq, 4);
+            assert_eq!(array.last_modified(&txn).unwrap().seq, 3);
+        }
+
+        doc.enable_last_modified(false).unwrap();
+        assert!(text.last_modified(&doc.transact()).is_none());
+    }
+
+    #[test]
+    fn subdoc_lookup_by_guid() {
+        use crate::types::PathSegment;
+
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let uuid_a: Uuid = "A".into();
+        let uuid_b: Uuid = "B".into();
+        {
+            let mut txn = doc.transact_mut();
+            root.insert(
+                &mut txn,
+                "a",
+                Doc::with_options(Options::with_guid_and_client_id(uuid_a.clone(), 1)),
+            );
+            let array = root.insert(&mut txn, "list", ArrayPrelim::default());
+            array.push_back(&mut txn, 1);
+            array.push_back(
+                &mut txn,
+                Doc::with_options(Options::with_guid_and_client_id(uuid_b.clone(), 1)),
+            );
+        }
+
+        let txn = doc.transact();
         assert_eq!(
-            actual,
-            Some(Arc::new((vec![], vec![uuid_a.clone()], vec![])))
+            txn.subdoc(&uuid_a).map(|d| d.guid().clone()),
+            Some(uuid_a.clone())
         );
+        assert!(txn.subdoc(&"C".into()).is_none());
+        drop(txn);
 
-        let mut guids: Vec<_> = doc2.transact().subdoc_guids().cloned().collect();
-        guids.sort();
-        assert_eq!(guids, vec![uuid_a.clone(), uuid_c.clone()]);
+        let path_a = doc.find_subdoc_path(&uuid_a).unwrap();
+        assert_eq!(
+            path_a,
+            vec![
+                PathSegment::Key("root".into()),
+                PathSegment::Key("a".into())
+            ]
+        );
+        let path_b = doc.find_subdoc_path(&uuid_b).unwrap();
+        assert_eq!(
+            path_b,
+            vec![
+                PathSegment::Key("root".into()),
+                PathSegment::Key("list".into()),
+                PathSegment::Index(1)
+            ]
+        );
+        assert!(doc.find_subdoc_path(&"C".into()).is_none());
+
+        // remote replica should be able to resolve the same paths
+        let doc2 = Doc::with_client_id(2);
+        exchange_updates(&[&doc, &doc2]);
+        assert_eq!(doc2.find_subdoc_path(&uuid_b), Some(path_b));
+    }
+
+    #[test]
+    fn observe_subdoc_update() {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let sub_a = root.insert(
+            &mut doc.transact_mut(),
+            "a",
+            Doc::with_options(Options::with_guid_and_client_id("A".into(), 1)),
+        );
+        let sub_b = sub_a.get_or_insert_map("nested").insert(
+            &mut sub_a.transact_mut(),
+            "b",
+            Doc::with_options(Options::with_guid_and_client_id("B".into(), 1)),
+        );
+
+        let root_updates = Arc::new(Mutex::new(Vec::new()));
+        let a_updates = Arc::new(Mutex::new(Vec::new()));
+        let u = root_updates.clone();
+        let _s1 = doc
+            .observe_subdoc_update_v1(move |_, e| {
+                u.lock().unwrap().push((e.guid.clone(), e.update.clone()))
+            })
+            .unwrap();
+        let u = a_updates.clone();
+        let _s2 = sub_a
+            .observe_subdoc_update_v1(move |_, e| u.lock().unwrap().push(e.guid.clone()))
+            .unwrap();
+
+        let text = sub_b.get_or_insert_text("text");
+        text.push(&mut sub_b.transact_mut(), "hello");
+        sub_a
+            .get_or_insert_map("nested")
+            .insert(&mut sub_a.transact_mut(), "key", "value");
+        // transactions without changes don't produce updates
+        drop(sub_b.transact_mut());
+
+        assert_eq!(*a_updates.lock().unwrap(), vec![Uuid::from("B")]);
+        let root_updates = root_updates.lock().unwrap();
+        let guids: Vec<_> = root_updates.iter().map(|(guid, _)| guid.clone()).collect();
+        assert_eq!(guids, vec![Uuid::from("B"), Uuid::from("A")]);
+
+        let replica = Doc::with_client_id(2);
+        let text = replica.get_or_insert_text("text");
+        replica
+            .transact_mut()
+            .apply_update(Update::decode_v1(&root_updates[0].1).unwrap())
+            .unwrap();
+        assert_eq!(text.get_string(&replica.transact()), "hello");
     }
 
     #[test]
@@ -1993,7 +3845,7 @@ mod test {
             &doc.transact()
                 .encode_state_as_update_v1(&StateVector::default()),
         );
-        doc2.transact_mut().apply_update(u.unwrap());
+        doc2.transact_mut().apply_update(u.unwrap()).unwrap();
         let doc_ref_3 = {
             let array = doc2.get_or_insert_array("test");
             array
@@ -2101,7 +3953,7 @@ mod test {
             &doc.transact()
                 .encode_state_as_update_v1(&StateVector::default()),
         );
-        doc2.transact_mut().apply_update(u.unwrap());
+        doc2.transact_mut().apply_update(u.unwrap()).unwrap();
         let subdoc_3 = {
             let array = doc2.get_or_insert_array("test");
             array
@@ -2231,7 +4083,8 @@ mod test {
         let doc = Doc::with_client_id(1);
         let txt = doc.get_or_insert_text("test");
         let mut txn = doc.transact_mut();
-        txn.apply_update(Update::decode_v1(&update).unwrap());
+        txn.apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
         let str = txt.get_string(&txn);
         assert_eq!(&str, "hello");
     }
@@ -2265,11 +4118,11 @@ mod test {
             let u2 = updates.pop().unwrap();
             let u1 = updates.pop().unwrap();
             let mut txn = d2.transact_mut();
-            txn.apply_update(u1);
+            txn.apply_update(u1).unwrap();
             assert!(txn.store.pending.is_none()); // applied
-            txn.apply_update(u3);
+            txn.apply_update(u3).unwrap();
             assert!(txn.store.pending.is_some()); // pending update waiting for u2
-            txn.apply_update(u2);
+            txn.apply_update(u2).unwrap();
             assert!(txn.store.pending.is_none()); // applied after fixing the missing update
         }
 
@@ -2321,4 +4174,497 @@ mod test {
             Err(crate::encoding::read::Error::EndOfBuffer(_))
         );
     }
+
+    #[test]
+    fn rename_root_keeps_content_under_stored_name() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let uuid_a: Uuid = "A".into();
+        {
+            let mut txn = d1.transact_mut();
+            let map = txn.get_or_insert_map("config");
+            map.insert(&mut txn, "name", "test");
+            map.insert(&mut txn, "items", ArrayPrelim::from(vec![1, 2, 3]));
+            let doc_a = Doc::with_options({
+                let mut o = Options::default();
+                o.guid = uuid_a.clone();
+                o
+            });
+            map.insert(&mut txn, "sub", doc_a);
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let sv = d1.transact().state_vector();
+        {
+            let mut txn = d1.transact_mut();
+            assert!(d1.rename_root(&mut txn, "config", "settings"));
+            // renaming is idempotent
+            assert!(d1.rename_root(&mut txn, "config", "settings"));
+            assert!(d1.rename_root(&mut txn, "settings", "settings"));
+            txn.get_or_insert_text("taken");
+            assert!(!d1.rename_root(&mut txn, "config", "taken"));
+        }
+        // no blocks were written
+        assert_eq!(d1.transact().state_vector(), sv);
+
+        {
+            let mut txn = d1.transact_mut();
+            let settings = txn.get_or_insert_map("settings");
+            settings.insert(&mut txn, "version", 2);
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        // d2 didn't register an alias, but it collaborates on the same data
+        let expected = any!({"name": "test", "items": [1, 2, 3], "version": 2});
+        let txn = d1.transact();
+        let settings = txn.get_map("settings").unwrap();
+        let mut json = settings.to_json(&txn);
+        if let Any::Map(map) = &mut json {
+            assert!(Arc::get_mut(map).unwrap().remove("sub").is_some());
+        }
+        assert_eq!(json, expected);
+        assert!(txn.subdoc(&uuid_a).is_some());
+        assert!(!d1.options().root_aliases.contains_key("taken"));
+
+        let txn = d2.transact();
+        assert!(txn.get_map("settings").is_none());
+        let config = txn.get_map("config").unwrap();
+        assert_eq!(config.len(&txn), 4);
+        assert_eq!(config.get(&txn, "version"), Some(Value::from(2)));
+        assert!(txn.subdoc(&uuid_a).is_some());
+    }
+
+    #[test]
+    fn doc_observers_priority() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let l = log.clone();
+        let _ui1 = doc
+            .observe_update_v1(move |_, _| l.lock().unwrap().push("ui-1"))
+            .unwrap();
+        let l = log.clone();
+        let _ui2 = doc
+            .observe_update_v1(move |_, _| l.lock().unwrap().push("ui-2"))
+            .unwrap();
+        let l = log.clone();
+        let model = SubscriptionKey::new("model").with_priority(1);
+        doc.observe_update_v1_with(model, move |_, _| l.lock().unwrap().push("model"))
+            .unwrap();
+        let l = log.clone();
+        let late = SubscriptionKey::new("late").with_priority(-1);
+        doc.observe_update_v1_with(late.clone(), move |_, _| {
+            l.lock().unwrap().push("late")
+        })
+        .unwrap();
+
+        text.push(&mut doc.transact_mut(), "hello");
+        assert_eq!(*log.lock().unwrap(), vec!["model", "ui-1", "ui-2", "late"]);
+
+        log.lock().unwrap().clear();
+        assert!(doc.unobserve_update_v1(late).unwrap());
+        text.push(&mut doc.transact_mut(), " world");
+        assert_eq!(*log.lock().unwrap(), vec!["model", "ui-1", "ui-2"]);
+    }
+
+    #[test]
+    fn observe_root_added() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let added = Arc::new(Mutex::new(Vec::new()));
+        let added_c = added.clone();
+        let _sub = d2
+            .observe_root_added(move |_, name, type_ref| {
+                added_c
+                    .lock()
+                    .unwrap()
+                    .push((name.to_string(), type_ref.clone()));
+            })
+            .unwrap();
+
+        d2.get_or_insert_array("local");
+        d2.get_or_insert_array("local");
+        let text = d1.get_or_insert_text("remote");
+        text.push(&mut d1.transact_mut(), "hello");
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(
+            *added.lock().unwrap(),
+            vec![
+                ("local".to_string(), TypeRef::Array),
+                ("remote".to_string(), TypeRef::Undefined),
+            ]
+        );
+    }
+
+    #[test]
+    fn retire_client_compacts_deleted_blocks() {
+        let options = Options {
+            skip_gc: true,
+            ..Options::with_client_id(1)
+        };
+        let d1 = Doc::with_options(options);
+        let d2 = Doc::with_options(Options {
+            skip_gc: true,
+            ..Options::with_client_id(2)
+        });
+        let t2 = d2.get_or_insert_text("text");
+        t2.push(&mut d2.transact_mut(), "hello world");
+        t2.remove_range(&mut d2.transact_mut(), 5, 6);
+        exchange_updates(&[&d1, &d2]);
+
+        let mut txn = d1.transact_mut();
+        let usage = txn.store().client_usage();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].client, 2);
+        assert_eq!(usage[0].alive, 5);
+        assert_eq!(usage[0].deleted, 6);
+        assert!(!usage[0].retired);
+
+        assert!(!txn.retire_client(1));
+        assert!(txn.retire_client(2));
+        assert!(txn.store().is_retired(&2));
+
+        // blocks are not compacted until transaction is committed
+        let blocks = txn.store().blocks.get_client(&2).unwrap();
+        let deleted = blocks.iter().last().unwrap();
+        assert_matches!(deleted, BlockCell::Block(item));
+        assert_matches!(&item.content, ItemContent::String(_));
+        drop(txn);
+
+        let text = d1.get_or_insert_text("text");
+        let txn = d1.transact();
+        let blocks = txn.store().blocks.get_client(&2).unwrap();
+        let deleted = blocks.iter().last().unwrap();
+        assert_matches!(deleted, BlockCell::Block(item));
+        assert_eq!(item.content, ItemContent::Deleted(6));
+
+        let usage = txn.store().client_usage();
+        assert_eq!(usage[0].len(), 11);
+        assert!(usage[0].retired);
+        assert_eq!(text.get_string(&txn), "hello");
+        drop(txn);
+
+        // blocks of retired client deleted later on are garbage collected by deleting transaction
+        text.remove_range(&mut d1.transact_mut(), 0, 2);
+        let txn = d1.transact();
+        let blocks = txn.store().blocks.get_client(&2).unwrap();
+        let deleted = blocks.iter().next().unwrap();
+        assert_matches!(deleted, BlockCell::Block(item));
+        assert_eq!(item.content, ItemContent::Deleted(2));
+        let alive = blocks.iter().nth(1).unwrap();
+        assert_matches!(alive, BlockCell::Block(item));
+        assert_matches!(&item.content, ItemContent::String(_));
+        assert_eq!(text.get_string(&txn), "llo");
+    }
+
+    #[test]
+    fn apply_updates_in_single_commit() {
+        let mut updates = Vec::new();
+        for client_id in 1..=3 {
+            let doc = Doc::with_client_id(client_id);
+            let text = doc.get_or_insert_text("text");
+            text.push(&mut doc.transact_mut(), &client_id.to_string());
+            let update = doc
+                .transact()
+                .encode_state_as_update_v1(&StateVector::default());
+            updates.push(Update::decode_v1(&update).unwrap());
+        }
+
+        let doc = Doc::with_client_id(4);
+        let text = doc.get_or_insert_text("text");
+        let events = Arc::new(AtomicU32::new(0));
+        let updates_emitted = Arc::new(Mutex::new(Vec::new()));
+        let e = events.clone();
+        let _s1 = text.observe(move |_, _| {
+            e.fetch_add(1, Ordering::SeqCst);
+        });
+        let u = updates_emitted.clone();
+        let _s2 = doc
+            .observe_update_v1(move |_, e| u.lock().unwrap().push(e.update.clone()))
+            .unwrap();
+
+        doc.transact_mut().apply_updates(updates).unwrap();
+
+        let mut expected: Vec<_> = text.get_string(&doc.transact()).chars().collect();
+        expected.sort();
+        assert_eq!(expected, vec!['1', '2', '3']);
+        assert_eq!(events.load(Ordering::SeqCst), 1);
+        let emitted = updates_emitted.lock().unwrap();
+        assert_eq!(emitted.len(), 1);
+
+        // single emitted update contains changes of all clients
+        let d2 = Doc::with_client_id(5);
+        let t2 = d2.get_or_insert_text("text");
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&emitted[0]).unwrap())
+            .unwrap();
+        assert_eq!(
+            t2.get_string(&d2.transact()),
+            text.get_string(&doc.transact())
+        );
+    }
+
+    #[test]
+    fn from_updates_aggregates_errors() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        let mut updates = Vec::new();
+        for chunk in ["hello", " ", "world"] {
+            let sv = source.transact().state_vector();
+            text.push(&mut source.transact_mut(), chunk);
+            updates.push(source.transact().encode_state_as_update_v1(&sv));
+        }
+        updates.insert(1, vec![1, 2, 3]);
+
+        let err = Doc::from_updates(
+            Options::with_client_id(2),
+            updates.iter().map(Vec::as_slice),
+        )
+        .unwrap_err();
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.errors[0].0, 1);
+        let text = err.doc.get_or_insert_text("text");
+        assert_eq!(text.get_string(&err.doc.transact()), "hello world");
+
+        updates.remove(1);
+        let doc = Doc::from_updates(
+            Options::with_client_id(2),
+            updates.iter().map(Vec::as_slice),
+        )
+        .unwrap();
+        let text = doc.get_or_insert_text("text");
+        assert_eq!(text.get_string(&doc.transact()), "hello world");
+    }
+
+    #[test]
+    fn quota_enforcement() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        text.push(&mut source.transact_mut(), "hello world");
+        let update = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let doc = Doc::with_options(Options {
+            max_content_bytes: Some(8),
+            ..Options::with_client_id(2)
+        });
+        let rejected = Arc::new(Mutex::new(Vec::new()));
+        let r = rejected.clone();
+        let _sub = doc
+            .observe_quota_exceeded(move |_, e| r.lock().unwrap().push(*e))
+            .unwrap();
+
+        // remote update is rejected as a whole
+        let err = doc
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap_err();
+        let expected = QuotaExceeded::ContentBytes {
+            limit: 8,
+            requested: 11,
+        };
+        assert_eq!(err, UpdateError::QuotaExceeded(expected));
+        assert_eq!(*rejected.lock().unwrap(), vec![expected]);
+        let local = doc.get_or_insert_text("text");
+        assert_eq!(local.get_string(&doc.transact()), "");
+
+        // local edits within the quota are accepted
+        local.push(&mut doc.transact_mut(), "hello");
+
+        // local edits exceeding the quota are rejected by fallible methods...
+        let err = local
+            .try_insert(&mut doc.transact_mut(), 5, " world")
+            .unwrap_err();
+        let expected = QuotaExceeded::ContentBytes {
+            limit: 8,
+            requested: 11,
+        };
+        assert_eq!(err, EditError::QuotaExceeded(expected));
+
+        // ... and cause infallible ones to panic
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            local.push(&mut doc.transact_mut(), " world");
+        }));
+        assert!(result.is_err());
+        assert_eq!(rejected.lock().unwrap().len(), 3);
+        assert_eq!(local.get_string(&doc.transact()), "hello");
+
+        let doc = Doc::with_options(Options {
+            max_blocks: Some(1),
+            ..Options::with_client_id(3)
+        });
+        let mut txn = doc.transact_mut();
+        let map = txn.get_or_insert_map("map");
+        map.insert(&mut txn, "a", 1);
+        drop(txn);
+        let update = Update::decode_v1(&update).unwrap();
+        let err = doc.transact_mut().apply_update(update).unwrap_err();
+        assert_eq!(
+            err,
+            UpdateError::QuotaExceeded(QuotaExceeded::Blocks {
+                limit: 1,
+                requested: 2
+            })
+        );
+    }
+
+    #[test]
+    fn quota_released_by_gc() {
+        let doc = Doc::with_options(Options {
+            max_content_bytes: Some(8),
+            ..Options::with_client_id(1)
+        });
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "hello");
+        text.remove_range(&mut doc.transact_mut(), 0, 5);
+
+        // content of garbage collected blocks no longer counts against the quota
+        text.try_insert(&mut doc.transact_mut(), 0, "world")
+            .unwrap();
+        assert_eq!(text.get_string(&doc.transact()), "world");
+    }
+
+    #[test]
+    fn pause_and_flush_update_observers() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let origins = Arc::new(Mutex::new(Vec::new()));
+        let e = events.clone();
+        let o = origins.clone();
+        let _sub1 = doc
+            .observe_update_v1(move |txn, u| {
+                e.lock().unwrap().push(("v1", u.update.clone()));
+                o.lock().unwrap().push(txn.origin().cloned());
+            })
+            .unwrap();
+        let e = events.clone();
+        let _sub2 = doc
+            .observe_update_v2(move |_, u| e.lock().unwrap().push(("v2", u.update.clone())))
+            .unwrap();
+
+        doc.pause_observers().unwrap();
+        text.push(&mut doc.transact_mut_with("first"), "a");
+        drop(doc.transact_mut()); // no changes, no update
+        text.push(&mut doc.transact_mut(), "b");
+        assert!(events.lock().unwrap().is_empty());
+
+        doc.flush_pending_events().unwrap();
+        let kinds: Vec<_> = events.lock().unwrap().iter().map(|(k, _)| *k).collect();
+        assert_eq!(kinds, vec!["v1", "v2", "v1", "v2"]);
+        // deferred updates are delivered with origins of transactions that produced them
+        assert_eq!(
+            *origins.lock().unwrap(),
+            vec![Some(Origin::from("first")), None]
+        );
+
+        // observers are still paused after flush
+        text.push(&mut doc.transact_mut(), "c");
+        assert_eq!(events.lock().unwrap().len(), 4);
+
+        doc.resume_observers().unwrap();
+        assert_eq!(events.lock().unwrap().len(), 6);
+        text.push(&mut doc.transact_mut(), "d");
+        assert_eq!(events.lock().unwrap().len(), 8);
+
+        // replaying delivered v1 updates reproduces the document
+        let replica = Doc::with_client_id(2);
+        let txt = replica.get_or_insert_text("text");
+        {
+            let mut txn = replica.transact_mut();
+            for (kind, update) in events.lock().unwrap().iter() {
+                if *kind == "v1" {
+                    txn.apply_update(Update::decode_v1(update).unwrap())
+                        .unwrap();
+                }
+            }
+        }
+        assert_eq!(txt.get_string(&replica.transact()), "abcd");
+    }
+
+    #[test]
+    fn read_only_replica() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        text.push(&mut source.transact_mut(), "hello world");
+        {
+            let mut txn = source.transact_mut();
+            txn.get_or_insert_map("map").insert(&mut txn, "key", 1);
+            txn.get_or_insert_array("array").push_back(&mut txn, 1);
+            txn.get_or_insert_xml_fragment("xml")
+                .push_back(&mut txn, XmlElementPrelim::empty("p"));
+        }
+
+        let doc = Doc::with_options(Options {
+            read_only: true,
+            ..Options::with_client_id(2)
+        });
+        let local = doc.get_or_insert_text("text");
+        let update = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        {
+            let mut txn = doc.transact_mut();
+            assert!(txn.is_read_only());
+            txn.apply_update(Update::decode_v1(&update).unwrap())
+                .unwrap();
+            assert!(txn.is_read_only());
+        }
+        assert_eq!(local.get_string(&doc.transact()), "hello world");
+
+        assert!(matches!(
+            doc.try_transact_edit(),
+            Err(TransactionAcqError::ReadOnly)
+        ));
+        assert!(matches!(
+            doc.try_transact_edit_with("origin"),
+            Err(TransactionAcqError::ReadOnly)
+        ));
+        assert!(source.try_transact_edit().is_ok());
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            local.push(&mut doc.transact_mut(), "!");
+        }));
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            local.remove_range(&mut doc.transact_mut(), 0, 6);
+        }));
+        assert!(result.is_err());
+        assert_eq!(local.get_string(&doc.transact()), "hello world");
+
+        // fallible methods return an error instead
+        let read_only = EditError::ReadOnly(ReadOnlyError);
+        {
+            let mut txn = doc.transact_mut();
+            let map = txn.get_or_insert_map("map");
+            let array = txn.get_or_insert_array("array");
+            let xml = txn.get_or_insert_xml_fragment("xml");
+            assert_eq!(local.try_insert(&mut txn, 0, "!"), Err(read_only));
+            assert_eq!(local.try_remove_range(&mut txn, 0, 6), Err(read_only));
+            assert_eq!(
+                local.try_format(&mut txn, 0, 5, Attrs::new()),
+                Err(read_only)
+            );
+            assert_eq!(map.try_remove(&mut txn, "key"), Err(read_only));
+            assert_eq!(array.try_remove(&mut txn, 0), Err(read_only));
+            assert!(matches!(
+                xml.try_insert(&mut txn, 1, XmlElementPrelim::empty("p")),
+                Err(EditError::ReadOnly(_))
+            ));
+            assert_eq!(xml.try_remove_range(&mut txn, 0, 1), Err(read_only));
+            assert_eq!(map.len(&txn), 1);
+            assert_eq!(array.len(&txn), 1);
+            assert_eq!(xml.len(&txn), 1);
+        }
+
+        let mut mgr = UndoManager::new(&doc, &local);
+        assert!(matches!(
+            mgr.undo(),
+            Err(UndoError::Transaction(TransactionAcqError::ReadOnly))
+        ));
+        assert_eq!(doc.transact().state_vector().get(&2), 0);
+    }
 }
@@ -1,11 +1,12 @@
 pub mod read;
+#[cfg(feature = "std")]
 pub mod serde;
 pub mod varint;
 pub mod write;
 
 #[cfg(test)]
 mod test {
-    use crate::encoding::read::{Cursor, Read};
+    use crate::encoding::read::{Cursor, Error, Read, MAX_ANY_DEPTH};
     use crate::encoding::write::Write;
     use crate::Any;
     use proptest::prelude::*;
@@ -208,4 +209,54 @@ mod test {
             val.read(&mut decoder)
         }
     }
+
+    #[test]
+    fn any_decode_huge_length_prefix() {
+        // array declaring u32::MAX elements, but with no elements following
+        let mut buf = vec![117u8];
+        buf.write_var(u32::MAX);
+        let mut decoder = Cursor::new(buf.as_slice());
+        assert!(Any::decode(&mut decoder).is_err());
+
+        // the same for a map
+        let mut buf = vec![118u8];
+        buf.write_var(u32::MAX);
+        let mut decoder = Cursor::new(buf.as_slice());
+        assert!(Any::decode(&mut decoder).is_err());
+    }
+
+    #[test]
+    fn any_decode_nesting_limit() {
+        // arrays of single element nested one in another
+        let mut buf = Vec::new();
+        for _ in 0..(MAX_ANY_DEPTH + 10) {
+            buf.write_u8(117);
+            buf.write_var(1u32);
+        }
+        buf.write_u8(126);
+        let mut decoder = Cursor::new(buf.as_slice());
+        match Any::decode(&mut decoder) {
+            Err(Error::LimitExceeded(_, limit)) => assert_eq!(limit, MAX_ANY_DEPTH),
+            other => panic!("expected nesting limit error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_buf_length_overflow() {
+        let mut buf = Vec::new();
+        buf.write_var(u32::MAX);
+        buf.write_u8(1);
+        let mut decoder = Cursor::new(buf.as_slice());
+        assert!(decoder.read_buf().is_err());
+    }
+
+    #[test]
+    fn read_var_int_overflow() {
+        // varint longer than any supported integer
+        let buf = [0xffu8; 32];
+        let mut decoder = Cursor::new(&buf[..]);
+        assert!(decoder.read_var::<u64>().is_err());
+        let mut decoder = Cursor::new(&buf[..]);
+        assert!(decoder.read_var::<i64>().is_err());
+    }
 }
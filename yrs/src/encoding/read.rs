@@ -1,23 +1,121 @@
 use crate::encoding::varint::{Signed, SignedVarInt, VarInt};
-use std::collections::TryReserveError;
-use thiserror::Error;
+use alloc::collections::TryReserveError;
+use core::fmt::{Display, Formatter};
+use core::str::Utf8Error;
 
-#[derive(Error, Debug)]
+#[derive(Debug)]
 pub enum Error {
-    #[error("failed to decode variable length integer")]
+    /// Failed to decode variable length integer.
     InvalidVarInt,
 
-    #[error("while trying to read more data (expected: {0} bytes), an unexpected end of buffer was reached")]
+    /// While trying to read more data (expected number of bytes), an unexpected end of buffer
+    /// was reached.
     EndOfBuffer(usize),
 
-    #[error("while reading, an unexpected value was found")]
+    /// While reading, an unexpected value was found.
     UnexpectedValue,
 
-    #[error("failed to allocate memory: {0}")]
-    NotEnoughMemory(#[from] TryReserveError),
+    /// Failed to allocate memory.
+    NotEnoughMemory(TryReserveError),
 
-    #[error("JSON parsing error: {0}")]
-    InvalidJSON(#[from] serde_json::Error),
+    /// JSON parsing error.
+    #[cfg(feature = "std")]
+    InvalidJSON(serde_json::Error),
+
+    /// Decoded integer value (or value computed from it) doesn't fit into its type. Contains
+    /// a name of a field that was being decoded.
+    IntegerOverflow(&'static str),
+
+    /// Decoded payload exceeded one of the hard limits. Contains a name of a limit that was
+    /// exceeded together with its value.
+    LimitExceeded(&'static str, usize),
+
+    /// Decoded string was not a valid UTF-8 sequence.
+    InvalidUtf8(Utf8Error),
+
+    /// Failed to read data from an underlying I/O source.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+/// Max number of elements, that decoders are allowed to preallocate upfront based on the length
+/// prefixes read from the payload. Collections above that size will grow as their elements are
+/// being decoded, so that a malicious payload cannot request an enormous allocation without
+/// actually providing the data to fill it.
+pub const MAX_PREALLOCATED_LEN: usize = 4096;
+
+/// Max depth of nested [Any](crate::any::Any) arrays and maps that can be decoded.
+pub const MAX_ANY_DEPTH: usize = 512;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidVarInt => write!(f, "failed to decode variable length integer"),
+            Error::EndOfBuffer(len) => write!(f, "while trying to read more data (expected: {} bytes), an unexpected end of buffer was reached", len),
+            Error::UnexpectedValue => write!(f, "while reading, an unexpected value was found"),
+            Error::NotEnoughMemory(e) => write!(f, "failed to allocate memory: {}", e),
+            #[cfg(feature = "std")]
+            Error::InvalidJSON(e) => write!(f, "JSON parsing error: {}", e),
+            Error::IntegerOverflow(field) => write!(f, "integer overflow while decoding {}", field),
+            Error::LimitExceeded(limit, value) => {
+                write!(f, "{} exceeded the maximum allowed limit of {}", limit, value)
+            }
+            Error::InvalidUtf8(e) => write!(f, "invalid UTF-8 string: {}", e),
+            #[cfg(feature = "std")]
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NotEnoughMemory(e) => Some(e),
+            Error::InvalidJSON(e) => Some(e),
+            Error::InvalidUtf8(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(e: Utf8Error) -> Self {
+        Error::InvalidUtf8(e)
+    }
+}
+
+impl From<TryReserveError> for Error {
+    fn from(e: TryReserveError) -> Self {
+        Error::NotEnoughMemory(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl Error {
+    /// Returns a line and column (both counted from 1) at which JSON parsing has failed, if
+    /// current error is a JSON parsing error with a known position.
+    #[cfg(feature = "std")]
+    pub fn json_location(&self) -> Option<(usize, usize)> {
+        match self {
+            Error::InvalidJSON(e) if e.line() != 0 => Some((e.line(), e.column())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::InvalidJSON(e)
+    }
 }
 
 #[derive(Default)]
@@ -48,12 +146,13 @@ where
 impl<'a> Read for Cursor<'a> {
     /// Take a slice of the next `len` bytes and advance the position by `len`.
     fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
-        if self.next + len > self.buf.len() {
-            Err(Error::EndOfBuffer(len))
-        } else {
-            let slice = &self.buf[self.next..(self.next + len)];
-            self.next += len as usize;
-            Ok(slice)
+        match self.next.checked_add(len) {
+            Some(end) if end <= self.buf.len() => {
+                let slice = &self.buf[self.next..end];
+                self.next = end;
+                Ok(slice)
+            }
+            _ => Err(Error::EndOfBuffer(len)),
         }
     }
 
@@ -121,7 +220,7 @@ pub trait Read: Sized {
     /// Read string of variable length.
     fn read_string(&mut self) -> Result<&str, Error> {
         let buf = self.read_buf()?;
-        Ok(unsafe { std::str::from_utf8_unchecked(buf) })
+        Ok(core::str::from_utf8(buf)?)
     }
 
     /// Read float32 in big endian order
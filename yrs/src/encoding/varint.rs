@@ -1,7 +1,6 @@
 use crate::encoding::read::{Error, Read};
 use crate::encoding::write::Write;
-use std::convert::TryInto;
-use std::mem::size_of;
+use core::convert::TryInto;
 
 pub trait VarInt: Sized + Copy {
     fn write<W: Write>(&self, w: &mut W);
@@ -266,14 +265,14 @@ fn read_var_i64<R: Read>(reader: &mut R) -> Result<i64, Error> {
     let mut len: u32 = 6;
     let is_negative = r & 0b01000000 as u8 > 0;
     if r & 0b10000000 as u8 == 0 {
-        return Ok(if is_negative { -num } else { num });
+        return Ok(if is_negative { num.wrapping_neg() } else { num });
     }
     loop {
         r = reader.read_u8()?;
-        num |= (r as i64 & 0b01111111 as i64) << len;
+        num |= i64::wrapping_shl(r as i64 & 0b01111111_i64, len);
         len += 7;
         if r < 0b10000000 as u8 {
-            return Ok(if is_negative { -num } else { num });
+            return Ok(if is_negative { num.wrapping_neg() } else { num });
         }
         if len > 70 {
             return Err(Error::InvalidVarInt);
@@ -359,15 +358,15 @@ impl SignedVarInt for i64 {
         let mut len: u32 = 6;
         let is_negative = r & 0b01000000 as u8 > 0;
         if r & 0b10000000 as u8 == 0 {
-            let num = if is_negative { -num } else { num };
+            let num = if is_negative { num.wrapping_neg() } else { num };
             return Ok(Signed::new(num, is_negative));
         }
         loop {
             r = reader.read_u8()?;
-            num |= (r as i64 & 0b01111111 as i64) << len;
+            num |= i64::wrapping_shl(r as i64 & 0b01111111_i64, len);
             len += 7;
             if r < 0b10000000 as u8 {
-                let num = if is_negative { -num } else { num };
+                let num = if is_negative { num.wrapping_neg() } else { num };
                 return Ok(Signed::new(num, is_negative));
             }
             if len > 70 {
@@ -1,4 +1,5 @@
 use crate::encoding::varint::{Signed, SignedVarInt, VarInt};
+use alloc::vec::Vec;
 
 impl Write for Vec<u8> {
     fn write_all(&mut self, buf: &[u8]) {
@@ -1,6 +1,6 @@
 use crate::doc::DocAddr;
 use crate::transaction::Subdocs;
-use crate::{DeleteSet, Doc, StateVector, TransactionMut};
+use crate::{Any, DeleteSet, Doc, StateVector, TransactionMut, Uuid};
 use std::collections::HashMap;
 
 /// An update event passed to a callback subscribed with [Doc::observe_update_v1]/[Doc::observe_update_v2].
@@ -8,17 +8,52 @@ pub struct UpdateEvent {
     /// A binary which contains information about all inserted and deleted changes performed within
     /// the scope of its [TransactionMut].
     pub update: Vec<u8>,
+    pub(crate) meta: HashMap<String, Any>,
 }
 
 impl UpdateEvent {
+    /// Returns metadata attached to an update (see: [TransactionMut::set_meta]). It's already
+    /// encoded as a part of `update` payload.
+    pub fn meta(&self) -> &HashMap<String, Any> {
+        &self.meta
+    }
+
     pub(crate) fn new_v1(txn: &TransactionMut) -> Self {
         UpdateEvent {
             update: txn.encode_update_v1(),
+            meta: txn.meta.clone(),
         }
     }
     pub(crate) fn new_v2(txn: &TransactionMut) -> Self {
         UpdateEvent {
             update: txn.encode_update_v2(),
+            meta: txn.meta.clone(),
+        }
+    }
+}
+
+/// An update event passed to a callback subscribed with
+/// [Doc::observe_subdoc_update_v1]/[Doc::observe_subdoc_update_v2] of any of the ancestors of a
+/// sub-document, which has been changed.
+pub struct SubdocUpdateEvent {
+    /// Globally unique identifier of a sub-document, which produced current update.
+    pub guid: Uuid,
+    /// A binary which contains information about all inserted and deleted changes performed within
+    /// the scope of sub-document's [TransactionMut].
+    pub update: Vec<u8>,
+}
+
+impl SubdocUpdateEvent {
+    pub(crate) fn new_v1(txn: &TransactionMut) -> Self {
+        SubdocUpdateEvent {
+            guid: txn.store().options.guid.clone(),
+            update: UpdateEvent::new_v1(txn).update,
+        }
+    }
+    pub(crate) fn new_v2(txn: &TransactionMut) -> Self {
+        SubdocUpdateEvent {
+            guid: txn.store().options.guid.clone(),
+            update: UpdateEvent::new_v2(txn).update,
         }
     }
 }
@@ -47,6 +82,7 @@ pub struct SubdocsEvent {
     pub(crate) added: HashMap<DocAddr, Doc>,
     pub(crate) removed: HashMap<DocAddr, Doc>,
     pub(crate) loaded: HashMap<DocAddr, Doc>,
+    pub(crate) destroyed: HashMap<DocAddr, Doc>,
 }
 
 impl SubdocsEvent {
@@ -55,6 +91,7 @@ impl SubdocsEvent {
             added: inner.added,
             removed: inner.removed,
             loaded: inner.loaded,
+            destroyed: inner.destroyed,
         }
     }
 
@@ -75,6 +112,16 @@ impl SubdocsEvent {
     pub fn loaded(&self) -> SubdocsEventIter {
         SubdocsEventIter(self.loaded.values())
     }
+
+    /// Returns an iterator over all sub-documents destroyed within a scope of committed
+    /// transaction using [Doc::destroy_subdoc]. Blocks referencing these sub-documents have been
+    /// removed from the parent document store. Destroyed sub-documents are also reported by
+    /// [SubdocsEvent::removed].
+    ///
+    /// [Doc::destroy_subdoc]: crate::Doc::destroy_subdoc
+    pub fn destroyed(&self) -> SubdocsEventIter<'_> {
+        SubdocsEventIter(self.destroyed.values())
+    }
 }
 
 #[repr(transparent)]
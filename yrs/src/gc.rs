@@ -1,44 +1,118 @@
-use crate::block::{BlockCell, ClientID, GC};
+use crate::block::{BlockCell, ClientID, ItemContent, GC};
+use crate::block_store::ClientBlockList;
+use crate::id_set::IdRange;
+use crate::store::Store;
 use crate::{TransactionMut, ID};
 use std::collections::HashMap;
 
 #[derive(Default)]
 pub(crate) struct GCCollector {
     items: HashMap<ClientID, Vec<u32>>,
+    /// Number of content bytes released by collected items. Present only if document has
+    /// [Options::max_content_bytes] configured.
+    ///
+    /// [Options::max_content_bytes]: crate::Options::max_content_bytes
+    released_bytes: Option<usize>,
 }
 
 impl GCCollector {
+    pub fn new(store: &Store) -> Self {
+        GCCollector {
+            items: HashMap::default(),
+            released_bytes: store.options.max_content_bytes.map(|_| 0),
+        }
+    }
+
     pub fn collect(txn: &mut TransactionMut) {
-        let mut gc = Self::default();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("gc").entered();
+        let mut gc = Self::new(&txn.store);
         gc.mark_all(txn);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            marked = gc.items.values().map(Vec::len).sum::<usize>(),
+            "marked blocks for garbage collection"
+        );
         gc.collect_all_marked(txn);
     }
 
     fn mark_all(&mut self, txn: &mut TransactionMut) {
         for (client, range) in txn.delete_set.iter() {
             if let Some(blocks) = txn.store.blocks.get_client_mut(client) {
-                for delete_item in range.iter().rev() {
-                    let mut start = delete_item.start;
-                    if let Some(mut i) = blocks.find_pivot(start) {
-                        while i < blocks.len() {
-                            let block = &mut blocks[i];
-                            let len = block.len();
-                            start += len;
-                            if start > delete_item.end {
-                                break;
-                            } else {
-                                if let BlockCell::Block(item) = block {
-                                    item.gc(self, false);
-                                }
-                                i += 1;
-                            }
+                self.mark_range(blocks, range);
+            }
+        }
+    }
+
+    fn mark_range(&mut self, blocks: &mut ClientBlockList, range: &IdRange) {
+        for delete_item in range.iter().rev() {
+            let mut start = delete_item.start;
+            if let Some(mut i) = blocks.find_pivot(start) {
+                while i < blocks.len() {
+                    let block = &mut blocks[i];
+                    let len = block.len();
+                    start += len;
+                    if start > delete_item.end {
+                        break;
+                    } else {
+                        if let BlockCell::Block(item) = block {
+                            item.gc(self, false);
                         }
+                        i += 1;
                     }
                 }
             }
         }
     }
 
+    /// Garbage collects blocks of retired clients (see: [TransactionMut::retire_client]), which
+    /// were deleted within a given transaction, even if GC is disabled for the document.
+    pub fn collect_retired(txn: &mut TransactionMut) {
+        let mut gc = Self::new(&txn.store);
+        for (client, range) in txn.delete_set.iter() {
+            if txn.store.retired_clients.contains(client) {
+                if let Some(blocks) = txn.store.blocks.get_client_mut(client) {
+                    gc.mark_range(blocks, range);
+                }
+            }
+        }
+        gc.collect_all_marked(txn);
+    }
+
+    /// Garbage collects blocks referencing sub documents destroyed within a given transaction
+    /// (see: [Doc::destroy_subdoc]), even if GC is disabled for the document.
+    ///
+    /// [Doc::destroy_subdoc]: crate::Doc::destroy_subdoc
+    pub fn collect_destroyed_subdocs(txn: &mut TransactionMut) {
+        let mut gc = Self::new(&txn.store);
+        if let Some(subdocs) = txn.subdocs.as_ref() {
+            for subdoc in subdocs.destroyed.values() {
+                if let Some(mut item) = subdoc.parent_item() {
+                    item.gc(&mut gc, false);
+                }
+            }
+        }
+        gc.release_content(&mut txn.store);
+    }
+
+    /// Accounts `content` of a garbage collected item, so that it no longer counts against
+    /// [Options::max_content_bytes] once [GCCollector::release_content] is called.
+    ///
+    /// [Options::max_content_bytes]: crate::Options::max_content_bytes
+    pub(crate) fn release(&mut self, content: &ItemContent) {
+        if let Some(released) = self.released_bytes.as_mut() {
+            *released += content.byte_size();
+        }
+    }
+
+    /// Subtracts content released by collected items from document content size.
+    pub(crate) fn release_content(&mut self, store: &mut Store) {
+        if let Some(released) = self.released_bytes.as_mut() {
+            store.content_bytes = store.content_bytes.saturating_sub(*released);
+            *released = 0;
+        }
+    }
+
     /// Marks item with a given [ID] as a candidate for being GCed.
     pub(crate) fn mark(&mut self, id: &ID) {
         let client = self.items.entry(id.client).or_default();
@@ -46,7 +120,8 @@ impl GCCollector {
     }
 
     /// Garbage collects all items marked for GC.
-    fn collect_all_marked(self, txn: &mut TransactionMut) {
+    pub(crate) fn collect_all_marked(mut self, txn: &mut TransactionMut) {
+        self.release_content(&mut txn.store);
         for (client_id, clocks) in self.items.into_iter() {
             let client = txn.store.blocks.get_client_blocks_mut(client_id);
             for clock in clocks {
@@ -1,6 +1,6 @@
 use crate::block::{ClientID, ID};
 use crate::block_store::BlockStore;
-use crate::encoding::read::Error;
+use crate::encoding::read::{Error, MAX_PREALLOCATED_LEN};
 use crate::iter::TxnIterator;
 use crate::slice::BlockSlice;
 use crate::store::Store;
@@ -27,7 +27,10 @@ impl Decode for Range<u32> {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
         let clock = decoder.read_ds_clock()?;
         let len = decoder.read_ds_len()?;
-        Ok(clock..(clock + len))
+        let end = clock
+            .checked_add(len)
+            .ok_or(Error::IntegerOverflow("delete set range"))?;
+        Ok(clock..end)
     }
 }
 
@@ -79,6 +82,37 @@ impl IdRange {
         }
     }
 
+    /// Returns an [IdRange] containing all clock values included in current range, but not in the
+    /// `other` one.
+    pub fn subtract(&self, other: &IdRange) -> IdRange {
+        let mut ranges: Vec<_> = self.iter().cloned().collect();
+        ranges.sort_by_key(|r| r.start);
+        let mut others: Vec<_> = other.iter().cloned().collect();
+        others.sort_by_key(|r| r.start);
+
+        let mut result = IdRange::with_capacity(0);
+        let mut i = 0;
+        for range in ranges {
+            let mut start = range.start;
+            while i < others.len() && others[i].end <= start {
+                i += 1;
+            }
+            let mut j = i;
+            while j < others.len() && others[j].start < range.end {
+                let o = &others[j];
+                if o.start > start {
+                    result.push(start..o.start);
+                }
+                start = start.max(o.end);
+                j += 1;
+            }
+            if start < range.end {
+                result.push(start..range.end);
+            }
+        }
+        result
+    }
+
     /// Check if given clock exists within current [IdRange].
     pub fn contains(&self, clock: u32) -> bool {
         match self {
@@ -87,6 +121,22 @@ impl IdRange {
         }
     }
 
+    /// Returns ranges of current [IdRange] overlapping with a given `clocks` range. Ranges are
+    /// expected to be sorted, which is the case for squashed [IdRange]s.
+    pub(crate) fn overlapping(&self, clocks: &Range<u32>) -> &[Range<u32>] {
+        match self {
+            IdRange::Continuous(r) if r.start < clocks.end && r.end > clocks.start => {
+                std::slice::from_ref(r)
+            }
+            IdRange::Continuous(_) => &[],
+            IdRange::Fragmented(ranges) => {
+                let lo = ranges.partition_point(|r| r.end <= clocks.start);
+                let hi = lo + ranges[lo..].partition_point(|r| r.start < clocks.end);
+                &ranges[lo..hi]
+            }
+        }
+    }
+
     /// Iterate over ranges described by current [IdRange].
     pub fn iter(&self) -> IdRangeIter<'_> {
         let (range, inner) = match self {
@@ -274,7 +324,7 @@ impl Decode for IdRange {
                 Ok(IdRange::Continuous(range))
             }
             len => {
-                let mut ranges = Vec::with_capacity(len as usize);
+                let mut ranges = Vec::with_capacity((len as usize).min(MAX_PREALLOCATED_LEN));
                 let mut i = 0;
                 while i < len {
                     ranges.push(Range::decode(decoder)?);
@@ -397,6 +447,21 @@ impl IdSet {
     pub fn get(&self, client_id: &ClientID) -> Option<&IdRange> {
         self.0.get(client_id)
     }
+
+    /// Returns an [IdSet] containing all IDs included in current set, but not in the `other` one.
+    pub fn subtract(&self, other: &IdSet) -> IdSet {
+        let mut result = IdSet::new();
+        for (&client, range) in self.0.iter() {
+            let range = match other.0.get(&client) {
+                Some(other) => range.subtract(other),
+                None => range.clone(),
+            };
+            if !range.is_empty() {
+                result.0.insert(client, range);
+            }
+        }
+        result
+    }
 }
 
 impl Encode for IdSet {
@@ -569,8 +634,15 @@ impl DeleteSet {
         self.0.get(client_id)
     }
 
+    /// Returns a delete set containing all deletions included in current delete set, but not in
+    /// the `other` one.
+    pub fn subtract(&self, other: &DeleteSet) -> DeleteSet {
+        DeleteSet(self.0.subtract(&other.0))
+    }
+
     pub(crate) fn try_squash_with(&mut self, store: &mut Store) {
         // try to merge deleted / gc'd items
+        let text_block_capacity = store.options.text_block_capacity;
         for (&client, range) in self.iter() {
             let blocks = store.blocks.get_client_blocks_mut(client);
             for r in range.iter().rev() {
@@ -579,7 +651,7 @@ impl DeleteSet {
                     (blocks.len() - 1).min(1 + blocks.find_pivot(r.end - 1).unwrap_or_default());
                 let mut block = &blocks[si];
                 while si > 0 && block.clock_start() >= r.start {
-                    blocks.squash_left(si);
+                    blocks.squash_left(si, text_block_capacity);
                     si -= 1;
                     block = &blocks[si];
                 }
@@ -731,6 +803,22 @@ mod test {
     use std::collections::HashSet;
     use std::fmt::Debug;
 
+    #[test]
+    fn id_range_subtract() {
+        let a = IdRange::Fragmented(vec![0..5, 7..12, 15..20]);
+        let b = IdRange::Fragmented(vec![3..8, 10..11, 20..25]);
+        assert_eq!(
+            a.subtract(&b),
+            IdRange::Fragmented(vec![0..3, 8..10, 11..12, 15..20])
+        );
+        assert_eq!(b.subtract(&a), IdRange::Fragmented(vec![5..7, 20..25]));
+        assert!(a.subtract(&a).is_empty());
+        assert_eq!(
+            IdRange::Continuous(0..10).subtract(&IdRange::Continuous(2..4)),
+            IdRange::Fragmented(vec![0..2, 4..10])
+        );
+    }
+
     #[test]
     fn id_range_merge_continous() {
         // `b` entirely within `a`
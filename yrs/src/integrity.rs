@@ -0,0 +1,618 @@
+use crate::block::{BlockCell, ClientID, ItemContent, ItemPtr, ID};
+use crate::branch::{BranchID, BranchPtr};
+use crate::id_set::DeleteSet;
+use crate::store::Store;
+use crate::types::TypePtr;
+use crate::{OffsetKind, ReadTxn};
+use std::collections::HashSet;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+/// A single violation of document store invariants detected by [Doc::check_integrity].
+///
+/// [Doc::check_integrity]: crate::Doc::check_integrity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// Blocks inserted by a given `client` don't form a contiguous range of clock values: block
+    /// starting at `found` clock was found where a block starting at `expected` clock should be.
+    ClockGap {
+        client: ClientID,
+        expected: u32,
+        found: u32,
+    },
+    /// Length of a block with given `id` doesn't match the length of its content.
+    BlockLength { id: ID, len: u32, content_len: u32 },
+    /// Block with given `id` has a `right` neighbor, which doesn't point back to it as its left
+    /// neighbor.
+    RightLink { id: ID, right: ID },
+    /// Block with given `id` has a `left` neighbor, which doesn't point back to it as its right
+    /// neighbor.
+    LeftLink { id: ID, left: ID },
+    /// Block with given `id` was reachable from a `branch`, but it doesn't point to that branch
+    /// as its parent.
+    Parent { id: ID, branch: BranchID },
+    /// Block with given `id` was reachable from a `branch` under `expected` map key (or from
+    /// a sequence component when `None`), but it has `found` key assigned.
+    ParentSub {
+        id: ID,
+        expected: Option<Arc<str>>,
+        found: Option<Arc<str>>,
+    },
+    /// The first block of a sequence component of a `branch` has a left neighbor.
+    SequenceStart { branch: BranchID, id: ID },
+    /// Map component entry of a `branch` under given `key` doesn't point to the latest block
+    /// assigned to that key.
+    MapEntry {
+        branch: BranchID,
+        key: Arc<str>,
+        id: ID,
+    },
+    /// Cached lengths of a `branch` sequence component don't match the lengths computed by
+    /// counting its non-deleted blocks.
+    BranchLength {
+        branch: BranchID,
+        block_len: u32,
+        expected_block_len: u32,
+        content_len: u32,
+        expected_content_len: u32,
+    },
+    /// Block with given `id` has its content marked as deleted, but the block itself is not
+    /// marked as deleted.
+    DeletedContent { id: ID },
+    /// Delete set contains an empty range of `client` clocks starting at a given `clock`.
+    EmptyDeleteRange { client: ClientID, clock: u32 },
+    /// Delete set of a transaction contains a block with given `id`, which is not marked as
+    /// deleted.
+    NotDeleted { id: ID },
+    /// Delete set of a transaction contains a range of `client` clocks ending at `end`, while
+    /// blocks of that client have been integrated only up to `state` clock.
+    UnknownDeleted {
+        client: ClientID,
+        end: u32,
+        state: u32,
+    },
+    /// Pending delete set of a document contains a range of `client` clocks starting at `clock`,
+    /// while blocks of that client have been already integrated up to `state` clock, so that
+    /// the deletion should have been applied.
+    PendingDelete {
+        client: ClientID,
+        clock: u32,
+        state: u32,
+    },
+}
+
+impl std::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityIssue::ClockGap {
+                client,
+                expected,
+                found,
+            } => write!(
+                f,
+                "client {} blocks are not contiguous: expected clock {}, found {}",
+                client, expected, found
+            ),
+            IntegrityIssue::BlockLength {
+                id,
+                len,
+                content_len,
+            } => write!(
+                f,
+                "block {} has length {}, but its content length is {}",
+                id, len, content_len
+            ),
+            IntegrityIssue::RightLink { id, right } => write!(
+                f,
+                "block {} right neighbor {} doesn't point back to it",
+                id, right
+            ),
+            IntegrityIssue::LeftLink { id, left } => write!(
+                f,
+                "block {} left neighbor {} doesn't point back to it",
+                id, left
+            ),
+            IntegrityIssue::Parent { id, branch } => {
+                write!(f, "block {} doesn't point to its parent {:?}", id, branch)
+            }
+            IntegrityIssue::ParentSub {
+                id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "block {} was expected to have parent key {:?}, but found {:?}",
+                id, expected, found
+            ),
+            IntegrityIssue::SequenceStart { branch, id } => write!(
+                f,
+                "sequence of {:?} starts at block {}, which has a left neighbor",
+                branch, id
+            ),
+            IntegrityIssue::MapEntry { branch, key, id } => write!(
+                f,
+                "map entry '{}' of {:?} points to block {}, which is not the latest one",
+                key, branch, id
+            ),
+            IntegrityIssue::BranchLength {
+                branch,
+                block_len,
+                expected_block_len,
+                content_len,
+                expected_content_len,
+            } => write!(
+                f,
+                "{:?} has block length {} (expected {}) and content length {} (expected {})",
+                branch, block_len, expected_block_len, content_len, expected_content_len
+            ),
+            IntegrityIssue::DeletedContent { id } => write!(
+                f,
+                "block {} has deleted content, but is not marked as deleted",
+                id
+            ),
+            IntegrityIssue::EmptyDeleteRange { client, clock } => write!(
+                f,
+                "delete set contains an empty range of client {} at clock {}",
+                client, clock
+            ),
+            IntegrityIssue::NotDeleted { id } => write!(
+                f,
+                "block {} is a part of a delete set, but is not marked as deleted",
+                id
+            ),
+            IntegrityIssue::UnknownDeleted { client, end, state } => write!(
+                f,
+                "delete set range of client {} ends at clock {}, but blocks are known only up to {}",
+                client, end, state
+            ),
+            IntegrityIssue::PendingDelete {
+                client,
+                clock,
+                state,
+            } => write!(
+                f,
+                "pending delete set range of client {} starts at clock {}, but blocks are already integrated up to {}",
+                client, clock, state
+            ),
+        }
+    }
+}
+
+/// Report produced by [Doc::check_integrity]. It contains a list of all detected violations of
+/// document store invariants.
+///
+/// [Doc::check_integrity]: crate::Doc::check_integrity
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// Returns true if no issues were detected.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns a list of all detected issues.
+    pub fn issues(&self) -> &[IntegrityIssue] {
+        &self.issues
+    }
+
+    pub(crate) fn check<T: ReadTxn>(txn: &T) -> Self {
+        let mut checker = IntegrityChecker {
+            offset_kind: txn.store().options.offset_kind,
+            issues: Vec::new(),
+        };
+        let store = txn.store();
+        let mut branches = HashSet::new();
+        for branch in store.types.values() {
+            branches.insert(BranchPtr::from(branch));
+        }
+        for (client, blocks) in store.blocks.iter() {
+            let mut expected_clock = None;
+            for cell in blocks.iter() {
+                let (start, end) = cell.clock_range();
+                if let Some(expected) = expected_clock {
+                    if start != expected {
+                        checker.report(IntegrityIssue::ClockGap {
+                            client: *client,
+                            expected,
+                            found: start,
+                        });
+                    }
+                }
+                expected_clock = Some(end + 1);
+                if let BlockCell::Block(item) = cell {
+                    let item = ItemPtr::from(item);
+                    checker.check_item(item);
+                    if let ItemContent::Type(branch) = &item.content {
+                        if !item.is_deleted() {
+                            branches.insert(BranchPtr::from(branch));
+                        }
+                    }
+                }
+            }
+        }
+        for branch in branches {
+            checker.check_branch(branch);
+        }
+        if let Some(ds) = store.pending_ds() {
+            checker.check_pending_delete_set(store, ds);
+        }
+        IntegrityReport {
+            issues: checker.issues,
+        }
+    }
+
+    /// Checks if all ranges of a given delete set `ds` produced by a transaction refer to blocks
+    /// of a given `store`, which have been marked as deleted.
+    #[cfg(any(test, feature = "debug-integrity"))]
+    pub(crate) fn check_delete_set(store: &Store, ds: &DeleteSet) -> Self {
+        let mut checker = IntegrityChecker {
+            offset_kind: store.options.offset_kind,
+            issues: Vec::new(),
+        };
+        checker.check_delete_set(store, ds);
+        IntegrityReport {
+            issues: checker.issues,
+        }
+    }
+
+    /// Checks only the sequence blocks and cached lengths of given `branches`. This is much
+    /// cheaper than a full [IntegrityReport::check], so it can be run after every transaction.
+    #[cfg(feature = "debug-integrity")]
+    pub(crate) fn check_branches<I>(offset_kind: OffsetKind, branches: I) -> Self
+    where
+        I: IntoIterator<Item = BranchPtr>,
+    {
+        let mut checker = IntegrityChecker {
+            offset_kind,
+            issues: Vec::new(),
+        };
+        for branch in branches {
+            let mut current = branch.start;
+            while let Some(item) = current {
+                checker.check_item(item);
+                current = item.right;
+            }
+            checker.check_branch(branch);
+        }
+        IntegrityReport {
+            issues: checker.issues,
+        }
+    }
+}
+
+impl std::fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_ok() {
+            write!(f, "no integrity issues found")
+        } else {
+            writeln!(f, "found {} integrity issue(s):", self.issues.len())?;
+            for issue in self.issues.iter() {
+                writeln!(f, "- {}", issue)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+struct IntegrityChecker {
+    offset_kind: OffsetKind,
+    issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityChecker {
+    fn report(&mut self, issue: IntegrityIssue) {
+        self.issues.push(issue);
+    }
+
+    fn check_item(&mut self, item: ItemPtr) {
+        let content_len = item.content.len(OffsetKind::Utf16);
+        if item.len != content_len {
+            self.report(IntegrityIssue::BlockLength {
+                id: item.id,
+                len: item.len,
+                content_len,
+            });
+        }
+        if let Some(right) = item.right {
+            if right.left != Some(item) {
+                self.report(IntegrityIssue::RightLink {
+                    id: item.id,
+                    right: right.id,
+                });
+            }
+        }
+        if let Some(left) = item.left {
+            if left.right != Some(item) {
+                self.report(IntegrityIssue::LeftLink {
+                    id: item.id,
+                    left: left.id,
+                });
+            }
+        }
+        if let ItemContent::Deleted(_) = &item.content {
+            if !item.is_deleted() {
+                self.report(IntegrityIssue::DeletedContent { id: item.id });
+            }
+        }
+    }
+
+    #[cfg(any(test, feature = "debug-integrity"))]
+    fn check_delete_set(&mut self, store: &Store, ds: &DeleteSet) {
+        for (&client, ranges) in ds.iter() {
+            let state = store.blocks.get_clock(&client);
+            for range in ranges.iter() {
+                if range.start >= range.end {
+                    self.report(IntegrityIssue::EmptyDeleteRange {
+                        client,
+                        clock: range.start,
+                    });
+                    continue;
+                }
+                if range.end > state {
+                    self.report(IntegrityIssue::UnknownDeleted {
+                        client,
+                        end: range.end,
+                        state,
+                    });
+                }
+                let mut clock = range.start;
+                while clock < range.end.min(state) {
+                    let cell = match store.blocks.get_block(&ID::new(client, clock)) {
+                        Some(cell) => cell,
+                        None => break,
+                    };
+                    if !cell.is_deleted() {
+                        self.report(IntegrityIssue::NotDeleted {
+                            id: ID::new(client, cell.clock_start()),
+                        });
+                    }
+                    clock = cell.clock_end() + 1;
+                }
+            }
+        }
+    }
+
+    fn check_pending_delete_set(&mut self, store: &Store, ds: &DeleteSet) {
+        for (&client, ranges) in ds.iter() {
+            let state = store.blocks.get_clock(&client);
+            for range in ranges.iter() {
+                if range.start >= range.end {
+                    self.report(IntegrityIssue::EmptyDeleteRange {
+                        client,
+                        clock: range.start,
+                    });
+                } else if range.start < state {
+                    self.report(IntegrityIssue::PendingDelete {
+                        client,
+                        clock: range.start,
+                        state,
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_parent(&mut self, item: ItemPtr, branch: BranchPtr, key: Option<&Arc<str>>) {
+        let is_parent = match &item.parent {
+            TypePtr::Branch(parent) => *parent == branch,
+            _ => false,
+        };
+        if !is_parent {
+            self.report(IntegrityIssue::Parent {
+                id: item.id,
+                branch: branch.id(),
+            });
+        }
+        if item.parent_sub.as_ref() != key {
+            self.report(IntegrityIssue::ParentSub {
+                id: item.id,
+                expected: key.cloned(),
+                found: item.parent_sub.clone(),
+            });
+        }
+    }
+
+    fn check_branch(&mut self, branch: BranchPtr) {
+        if let Some(start) = branch.start {
+            if start.left.is_some() {
+                self.report(IntegrityIssue::SequenceStart {
+                    branch: branch.id(),
+                    id: start.id,
+                });
+            }
+        }
+        let mut current = branch.start;
+        while let Some(item) = current {
+            self.check_parent(item, branch, None);
+            current = item.right;
+        }
+        let (block_len, content_len) = branch.compute_lengths(self.offset_kind);
+        if block_len != branch.block_len || content_len != branch.content_len {
+            self.report(IntegrityIssue::BranchLength {
+                branch: branch.id(),
+                block_len: branch.block_len,
+                expected_block_len: block_len,
+                content_len: branch.content_len,
+                expected_content_len: content_len,
+            });
+        }
+        for (key, &item) in branch.map.iter() {
+            self.check_parent(item, branch, Some(key));
+            if item.right.is_some() {
+                self.report(IntegrityIssue::MapEntry {
+                    branch: branch.id(),
+                    key: key.clone(),
+                    id: item.id,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::branch::BranchPtr;
+    use crate::id_set::DeleteSet;
+    use crate::integrity::{IntegrityIssue, IntegrityReport};
+    use crate::moving::Assoc;
+    use crate::updates::decoder::Decode;
+    use crate::{
+        Array, Doc, GetString, Map, MapPrelim, ReadTxn, StateVector, Text, Transact, Update, ID,
+    };
+
+    #[test]
+    fn integrity_of_valid_document() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        let array = d1.get_or_insert_array("array");
+        let map = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            text.insert(&mut txn, 0, "hello world");
+            text.remove_range(&mut txn, 2, 4);
+            array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+            array.move_range_to(&mut txn, 0, Assoc::After, 1, Assoc::Before, 4);
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "a", 2);
+            map.insert(&mut txn, "nested", MapPrelim::<u32>::new());
+        }
+
+        let d2 = Doc::with_client_id(2);
+        {
+            let mut txn = d2.transact_mut();
+            let update = d1.transact().encode_state_as_update_v1(&Default::default());
+            txn.apply_update(Update::decode_v1(&update).unwrap())
+                .unwrap();
+        }
+
+        let report = d1.check_integrity(&d1.transact());
+        assert!(report.is_ok(), "{}", report);
+        let report = d2.check_integrity(&d2.transact());
+        assert!(report.is_ok(), "{}", report);
+    }
+
+    #[test]
+    fn integrity_detects_invalid_branch_length() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "hello");
+
+        let mut branch = BranchPtr::from(text.as_ref());
+        branch.block_len += 1;
+
+        let report = doc.check_integrity(&doc.transact());
+        assert_eq!(
+            report.issues(),
+            &[IntegrityIssue::BranchLength {
+                branch: branch.id(),
+                block_len: 6,
+                expected_block_len: 5,
+                content_len: 5,
+                expected_content_len: 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn recompute_branch_lengths() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3]);
+
+        let mut branch = BranchPtr::from(array.as_ref());
+        branch.block_len = 1;
+        branch.content_len = 10;
+        assert!(!doc.check_integrity(&doc.transact()).is_ok());
+
+        let mut txn = doc.transact_mut();
+        assert!(branch.recompute_lengths(&mut txn));
+        assert!(!branch.recompute_lengths(&mut txn));
+        assert_eq!(array.len(&txn), 3);
+        assert!(doc.check_integrity(&txn).is_ok());
+    }
+
+    #[test]
+    fn integrity_of_pending_delete_set() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        text.push(&mut d1.transact_mut(), "hello");
+        let sv = d1.transact().state_vector();
+        let first = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        text.push(&mut d1.transact_mut(), " world");
+        text.remove_range(&mut d1.transact_mut(), 2, 6);
+        let second = d1.transact().encode_state_as_update_v1(&sv);
+
+        // deletion spans both integrated and missing blocks
+        let d2 = Doc::with_client_id(2);
+        let mut update = Update::decode_v1(&second).unwrap();
+        update.blocks = Default::default();
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&first).unwrap())
+            .unwrap();
+        d2.transact_mut().apply_update(update).unwrap();
+        assert!(d2.transact().store().pending_ds().is_some());
+        let report = d2.check_integrity(&d2.transact());
+        assert!(report.is_ok(), "{}", report);
+
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&second).unwrap())
+            .unwrap();
+        let text = d2.get_or_insert_text("text");
+        assert_eq!(text.get_string(&d2.transact()), "herld");
+        assert!(d2.transact().store().pending_ds().is_none());
+        assert!(d2.check_integrity(&d2.transact()).is_ok());
+
+        let mut ds = DeleteSet::new();
+        ds.insert(ID::new(1, 1), 2);
+        d2.transact_mut().store_mut().pending_ds = Some(ds);
+        assert_eq!(
+            d2.check_integrity(&d2.transact()).issues(),
+            &[IntegrityIssue::PendingDelete {
+                client: 1,
+                clock: 1,
+                state: 11,
+            }]
+        );
+    }
+
+    #[test]
+    fn integrity_detects_invalid_delete_set() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "hello");
+        text.remove_range(&mut doc.transact_mut(), 0, 2);
+
+        let txn = doc.transact();
+        let mut ds = DeleteSet::new();
+        ds.insert(ID::new(1, 0), 2);
+        assert!(IntegrityReport::check_delete_set(txn.store(), &ds).is_ok());
+
+        let mut ds = DeleteSet::new();
+        ds.insert(ID::new(1, 0), 4);
+        ds.insert(ID::new(1, 6), 1);
+        ds.insert(ID::new(2, 0), 0);
+        ds.squash();
+        let report = IntegrityReport::check_delete_set(txn.store(), &ds);
+        let expected = [
+            IntegrityIssue::NotDeleted { id: ID::new(1, 2) },
+            IntegrityIssue::UnknownDeleted {
+                client: 1,
+                end: 7,
+                state: 5,
+            },
+            IntegrityIssue::EmptyDeleteRange {
+                client: 2,
+                clock: 0,
+            },
+        ];
+        assert_eq!(report.issues().len(), expected.len());
+        for issue in expected.iter() {
+            assert!(report.issues().contains(issue), "missing {:?}", issue);
+        }
+    }
+}
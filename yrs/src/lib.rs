@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/y-crdt/y-crdt/main/logo-yrs.svg",
     html_favicon_url = "https://raw.githubusercontent.com/y-crdt/y-crdt/main/logo-yrs.svg"
@@ -40,7 +41,7 @@
 //! // get update with contents not observed by remote_doc
 //! let update = doc.transact().encode_diff_v1(&StateVector::decode_v1(&remote_timestamp).unwrap());
 //! // apply update on remote doc
-//! remote_doc.transact_mut().apply_update(Update::decode_v1(&update).unwrap());
+//! remote_doc.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
 //!
 //! assert_eq!(text.get_string(&doc.transact()), remote_text.get_string(&remote_doc.transact()));
 //! ```
@@ -171,7 +172,7 @@
 //! assert_eq!(str.chars().nth(INDEX), Some('o'));
 //!
 //! // synchronize full state of doc1 -> doc2
-//! txn2.apply_update(Update::decode_v1(&txn1.encode_diff_v1(&StateVector::default())).unwrap());
+//! txn2.apply_update(Update::decode_v1(&txn1.encode_diff_v1(&StateVector::default())).unwrap()).unwrap();
 //!
 //! // Doc 2: cursor at index 1 no longer points to the same character
 //! let str = text2.get_string(&txn2);
@@ -209,7 +210,7 @@
 //! let pos = text2.sticky_index(&mut txn2, INDEX as u32, Assoc::After).unwrap();
 //!
 //! // synchronize full state of doc1 -> doc2
-//! txn2.apply_update(Update::decode_v1(&txn1.encode_diff_v1(&StateVector::default())).unwrap());
+//! txn2.apply_update(Update::decode_v1(&txn1.encode_diff_v1(&StateVector::default())).unwrap()).unwrap();
 //!
 //! // restore the index from position saved previously
 //! let idx = pos.get_offset(&txn2).unwrap();
@@ -321,7 +322,7 @@
 //!
 //! // sync changes from remote to local
 //! let update = remote.transact().encode_state_as_update_v1(&local.transact().state_vector());
-//! local.transact_mut().apply_update(Update::decode_v1(&update).unwrap());
+//! local.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
 //! assert_eq!(text1.get_string(&local.transact()), "hello worldeveryone"); // remote changes synced
 //!
 //! // undo last performed change on local
@@ -395,7 +396,7 @@
 //! let doc = Doc::new();
 //! let mut txn = doc.transact_mut();
 //! let f = txn.get_or_insert_xml_fragment("article");
-//! txn.apply_update(Update::decode_v1(&update).unwrap());
+//! txn.apply_update(Update::decode_v1(&update).unwrap()).unwrap();
 //! let text = f.get(&mut txn, 0).unwrap().into_xml_text().unwrap();
 //!
 //! assert_eq!(text.get_string(&txn), INIT);
@@ -480,7 +481,7 @@
 //! {
 //!     let mut txn = remote.transact_mut();
 //!     let update = local.transact().encode_state_as_update_v1(&txn.state_vector());
-//!     txn.apply_update(Update::decode_v1(&update).unwrap());
+//!     txn.apply_update(Update::decode_v1(&update).unwrap()).unwrap();
 //! }
 //!
 //! // after synchronizing, we can now instantiate instance of the same logical type
@@ -564,108 +565,282 @@
 //! - [Deep dive into internal architecture of Yrs](https://bartoszsypytkowski.com/yrs-architecture/).
 //! - [Detailed explanation of conflict-free reordering algorithm](https://bartoszsypytkowski.com/yata-move/) used by Yrs.
 
+extern crate alloc;
+
+#[cfg(feature = "std")]
 mod alt;
+#[cfg(feature = "std")]
 pub mod block;
+#[cfg(feature = "std")]
 mod block_store;
+#[cfg(feature = "std")]
 pub mod doc;
+#[cfg(feature = "std")]
 mod event;
+#[cfg(feature = "std")]
 mod id_set;
+#[cfg(feature = "std")]
 mod store;
+#[cfg(feature = "std")]
 mod transaction;
+#[cfg(feature = "std")]
 pub mod types;
+#[cfg(feature = "std")]
 mod update;
+#[cfg(feature = "std")]
 pub mod updates;
+#[cfg(feature = "std")]
 mod utils;
 
 pub mod any;
+#[cfg(feature = "std")]
 pub mod atomic;
+#[cfg(feature = "std")]
 mod block_iter;
+#[cfg(feature = "std")]
 pub mod branch;
+#[cfg(feature = "std")]
+mod codec;
+#[cfg(feature = "std")]
+pub mod commit;
 pub mod encoding;
+#[cfg(feature = "std")]
 mod error;
+#[cfg(feature = "std")]
 mod gc;
+#[cfg(feature = "std")]
+mod integrity;
+#[cfg(feature = "std")]
 pub mod iter;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "std")]
 mod moving;
+#[cfg(feature = "std")]
 pub mod observer;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod rewrite;
+#[cfg(feature = "std")]
 mod slice;
+#[cfg(feature = "std")]
 mod state_vector;
+#[cfg(feature = "std")]
 pub mod sync;
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test_utils;
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "std")]
 pub mod undo;
 
+#[cfg(feature = "std")]
 pub use crate::alt::{
     diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1,
     encode_state_vector_from_update_v2, merge_updates_v1, merge_updates_v2,
 };
 pub use crate::any::Any;
+#[cfg(feature = "std")]
+pub use crate::block::ClientID;
+#[cfg(feature = "std")]
+pub use crate::block::ContentKind;
+#[cfg(feature = "std")]
+pub use crate::block::ParseIDError;
+#[cfg(feature = "std")]
 pub use crate::block::ID;
+#[cfg(feature = "std")]
+pub use crate::branch::BlockInfo;
+#[cfg(feature = "std")]
+pub use crate::branch::BranchBlocks;
+#[cfg(feature = "std")]
 pub use crate::branch::BranchID;
+#[cfg(feature = "std")]
+pub use crate::branch::BranchMeta;
+#[cfg(feature = "std")]
 pub use crate::branch::Hook;
+#[cfg(feature = "std")]
 pub use crate::branch::Nested;
+#[cfg(feature = "std")]
 pub use crate::branch::Root;
+#[cfg(feature = "std")]
+pub use crate::codec::{Codec, CodecError, UpdateCodec};
+#[cfg(feature = "std")]
+pub use crate::commit::{CommitInfo, CommitLog};
+#[cfg(feature = "std")]
 pub use crate::doc::Doc;
+#[cfg(feature = "std")]
+pub use crate::doc::LoadError;
+#[cfg(feature = "std")]
 pub use crate::doc::OffsetKind;
+#[cfg(feature = "std")]
 pub use crate::doc::Options;
+#[cfg(feature = "std")]
+pub use crate::doc::QuotaExceeded;
+#[cfg(feature = "std")]
+pub use crate::doc::ReadOnlyError;
+#[cfg(feature = "std")]
+pub use crate::doc::TextNormalizer;
+#[cfg(feature = "std")]
 pub use crate::doc::Transact;
-pub use crate::event::{SubdocsEvent, SubdocsEventIter, TransactionCleanupEvent, UpdateEvent};
+#[cfg(feature = "std")]
+pub use crate::event::{
+    SubdocUpdateEvent, SubdocsEvent, SubdocsEventIter, TransactionCleanupEvent, UpdateEvent,
+};
+#[cfg(feature = "std")]
 pub use crate::id_set::DeleteSet;
+#[cfg(feature = "std")]
+pub use crate::integrity::{IntegrityIssue, IntegrityReport};
+#[cfg(feature = "std")]
 pub use crate::moving::Assoc;
+#[cfg(feature = "std")]
 pub use crate::moving::IndexScope;
+#[cfg(feature = "std")]
 pub use crate::moving::IndexedSequence;
+#[cfg(feature = "std")]
 pub use crate::moving::Offset;
+#[cfg(feature = "std")]
 pub use crate::moving::StickyIndex;
+#[cfg(feature = "std")]
 pub use crate::observer::{Observer, Subscription};
+#[cfg(feature = "std")]
 pub use crate::state_vector::Snapshot;
+#[cfg(feature = "std")]
+pub use crate::state_vector::SnapshotDelta;
+#[cfg(feature = "std")]
 pub use crate::state_vector::StateVector;
+#[cfg(feature = "std")]
+pub use crate::store::ClientUsage;
+#[cfg(feature = "std")]
 pub use crate::store::Store;
+#[cfg(feature = "std")]
+pub use crate::trace::{DocTrace, ReplayError, TraceStep};
+#[cfg(feature = "std")]
+pub use crate::transaction::AnyReadTxn;
+#[cfg(feature = "std")]
 pub use crate::transaction::Origin;
+#[cfg(feature = "std")]
 pub use crate::transaction::ReadTxn;
+#[cfg(feature = "std")]
 pub use crate::transaction::RootRefs;
+#[cfg(feature = "std")]
+pub use crate::transaction::SnapshotGate;
+#[cfg(feature = "std")]
 pub use crate::transaction::Transaction;
+#[cfg(feature = "std")]
 pub use crate::transaction::TransactionMut;
+#[cfg(feature = "std")]
+pub use crate::transaction::TxnOptions;
+#[cfg(feature = "std")]
 pub use crate::transaction::WriteTxn;
+#[cfg(feature = "std")]
 pub use crate::types::array::Array;
+#[cfg(feature = "std")]
+pub use crate::types::array::ArrayBuilder;
+#[cfg(feature = "std")]
 pub use crate::types::array::ArrayPrelim;
+#[cfg(feature = "std")]
 pub use crate::types::array::ArrayRef;
+#[cfg(feature = "std")]
+pub use crate::types::keyed_list::{KeyedList, KeyedListPrelim, KeyedListRef};
+#[cfg(feature = "std")]
 pub use crate::types::map::Map;
+#[cfg(feature = "std")]
+pub use crate::types::map::MapBuilder;
+#[cfg(feature = "std")]
+pub use crate::types::map::MapConflict;
+#[cfg(feature = "std")]
+pub use crate::types::map::MapConflictResolution;
+#[cfg(feature = "std")]
+pub use crate::types::map::MapConflictResolver;
+#[cfg(feature = "std")]
 pub use crate::types::map::MapPrelim;
+#[cfg(feature = "std")]
 pub use crate::types::map::MapRef;
+#[cfg(feature = "std")]
+pub use crate::types::set::{Set, SetPrelim, SetRef};
+#[cfg(feature = "std")]
 pub use crate::types::text::Text;
+#[cfg(feature = "std")]
+pub use crate::types::text::TextEdit;
+#[cfg(feature = "std")]
+pub use crate::types::text::TextHistory;
+#[cfg(feature = "std")]
 pub use crate::types::text::TextPrelim;
+#[cfg(feature = "std")]
 pub use crate::types::text::TextRef;
-#[cfg(feature = "weak")]
-pub use crate::types::weak::{Quotable, WeakPrelim, WeakRef};
+#[cfg(all(feature = "std", feature = "weak"))]
+pub use crate::types::weak::{DocLink, Quotable, WeakPrelim, WeakRef};
+#[cfg(feature = "std")]
 pub use crate::types::xml::Xml;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlElementPrelim;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlElementRef;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlFragment;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlFragmentPrelim;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlFragmentRef;
+#[cfg(feature = "std")]
+pub use crate::types::xml::XmlHookPrelim;
+#[cfg(feature = "std")]
+pub use crate::types::xml::XmlHookRef;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlNode;
+#[cfg(feature = "std")]
+pub use crate::types::xml::XmlNodePrelim;
+#[cfg(feature = "std")]
+pub use crate::types::xml::XmlTarget;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlTextPrelim;
+#[cfg(feature = "std")]
 pub use crate::types::xml::XmlTextRef;
+#[cfg(feature = "std")]
+pub use crate::types::CastError;
+#[cfg(feature = "std")]
 pub use crate::types::DeepObservable;
+#[cfg(feature = "std")]
+pub use crate::types::EditError;
+#[cfg(feature = "std")]
 pub use crate::types::GetString;
+#[cfg(feature = "std")]
 pub use crate::types::Observable;
+#[cfg(feature = "std")]
+pub use crate::types::OutOfBounds;
+#[cfg(feature = "std")]
 pub use crate::types::RootRef;
+#[cfg(feature = "std")]
 pub use crate::types::SharedRef;
+#[cfg(feature = "std")]
 pub use crate::types::Value;
+#[cfg(feature = "std")]
+pub use crate::update::ApplyProgress;
+#[cfg(feature = "std")]
 pub use crate::update::Update;
+#[cfg(feature = "std")]
+pub use crate::update::UpdateError;
 
+#[cfg(feature = "std")]
 pub type UndoManager = crate::undo::UndoManager<()>;
+#[cfg(feature = "std")]
 pub type Uuid = std::sync::Arc<str>;
 
 /// Generate random v4 UUID.
 /// (See: https://www.rfc-editor.org/rfc/rfc4122#section-4.4)
+#[cfg(feature = "std")]
 pub fn uuid_v4() -> Uuid {
     uuid_v4_from(&mut fastrand::Rng::new())
 }
 
 /// Generate random v4 UUID.
 /// (See: https://www.rfc-editor.org/rfc/rfc4122#section-4.4)
+#[cfg(feature = "std")]
 pub fn uuid_v4_from(rng: &mut fastrand::Rng) -> Uuid {
     let mut b = [0u8; 16];
     rng.fill(&mut b);
@@ -0,0 +1,306 @@
+//! Three-way merge of divergent document forks.
+//!
+//! Yrs documents can be forked simply by copying their state and editing copies independently,
+//! which enables git-like branching workflows. Since all changes are CRDT operations, merging
+//! two forks never fails: combining their updates always produces a consistent document. However
+//! some concurrent changes, while resolved deterministically, may still be considered conflicting
+//! from the application perspective - e.g. when the same map entry has been changed differently on
+//! both forks, only one of the values survives. [three_way] merge reports such cases, so that they
+//! can be reviewed (and possibly fixed) by users.
+
+use crate::branch::BranchPtr;
+use crate::types::{Path, PathSegment, ToJson, Value};
+use crate::updates::decoder::Decode;
+use crate::{Any, BranchID, Doc, Map, MapRef, ReadTxn, StateVector, Transact, Update, UpdateError};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Result of a [three_way] merge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    /// Update (encoded using lib0 v1 encoding) containing complete state of a merged document.
+    pub update: Vec<u8>,
+    /// Semantic conflicts detected between merged forks, ordered by their paths.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Semantic conflict detected by [three_way] merge: a map entry, which has been changed
+/// differently by both forks. Values are represented in their JSON form, with `None` meaning that
+/// an entry didn't exist (or has been removed) in a corresponding document version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    /// Path to a conflicting entry, starting from the name of a root-level type.
+    pub path: Path,
+    /// Value of an entry in a common ancestor of merged forks.
+    pub base: Option<Any>,
+    /// Value of an entry in our fork.
+    pub ours: Option<Any>,
+    /// Value of an entry in their fork.
+    pub theirs: Option<Any>,
+    /// Value of an entry chosen by the CRDT conflict resolution in the merged document.
+    pub resolved: Option<Any>,
+}
+
+/// Error returned by [three_way] merge when one of its inputs couldn't be read.
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("failed to decode {0} update: {1}")]
+    Decode(&'static str, crate::encoding::read::Error),
+    #[error("failed to apply {0} update: {1}")]
+    Apply(&'static str, UpdateError),
+}
+
+/// Merges two divergent forks - `ours` and `theirs` - of the same document, which have been created
+/// from a common ancestor state `base`. All parameters are updates encoded using lib0 v1 encoding.
+/// These can be either complete document states or deltas computed relative to `base`.
+///
+/// Returns an update with the merged document state and a report of semantic conflicts: map entries
+/// (in root-level and nested maps) which have been changed on both forks to different values.
+/// Changes made only by one of the forks, as well as changes of the other collection types (text,
+/// arrays, XML nodes) are merged automatically without conflicts.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Any, Doc, Map, ReadTxn, StateVector, Transact, Update};
+/// use yrs::merge::three_way;
+/// use yrs::types::PathSegment;
+/// use yrs::updates::decoder::Decode;
+///
+/// let base = Doc::with_client_id(1);
+/// let config = base.get_or_insert_map("config");
+/// config.insert(&mut base.transact_mut(), "theme", "light");
+/// let base = base.transact().encode_state_as_update_v1(&StateVector::default());
+///
+/// let fork = |client_id, theme: &str| {
+///     let doc = Doc::with_client_id(client_id);
+///     let config = doc.get_or_insert_map("config");
+///     let mut txn = doc.transact_mut();
+///     txn.apply_update(Update::decode_v1(&base).unwrap()).unwrap();
+///     config.insert(&mut txn, "theme", theme);
+///     txn.encode_state_as_update_v1(&StateVector::default())
+/// };
+/// let ours = fork(2, "dark");
+/// let theirs = fork(3, "solarized");
+///
+/// let result = three_way(&base, &ours, &theirs).unwrap();
+/// assert_eq!(result.conflicts.len(), 1);
+/// let conflict = &result.conflicts[0];
+/// assert_eq!(
+///     Vec::from(conflict.path.clone()),
+///     vec![PathSegment::Key("config".into()), PathSegment::Key("theme".into())]
+/// );
+/// assert_eq!(conflict.base, Some(Any::from("light")));
+/// assert_eq!(conflict.ours, Some(Any::from("dark")));
+/// assert_eq!(conflict.theirs, Some(Any::from("solarized")));
+/// ```
+pub fn three_way(base: &[u8], ours: &[u8], theirs: &[u8]) -> Result<MergeResult, MergeError> {
+    let base_doc = load(&[("base", base)])?;
+    let ours_doc = load(&[("base", base), ("ours", ours)])?;
+    let theirs_doc = load(&[("base", base), ("theirs", theirs)])?;
+    let merged_doc = load(&[("base", base), ("ours", ours), ("theirs", theirs)])?;
+
+    let base_entries = entries(&base_doc);
+    let ours_entries = entries(&ours_doc);
+    let theirs_entries = entries(&theirs_doc);
+    let merged_entries = entries(&merged_doc);
+
+    let paths: BTreeSet<&Vec<Arc<str>>> =
+        ours_entries.keys().chain(theirs_entries.keys()).collect();
+    let mut conflicts = Vec::new();
+    for path in paths {
+        let b = base_entries.get(path);
+        let o = ours_entries.get(path);
+        let t = theirs_entries.get(path);
+        let changed = |e: Option<&Entry>| e.map(|e| &e.value) != b.map(|e| &e.value);
+        if changed(o) && changed(t) && o.map(|e| &e.value) != t.map(|e| &e.value) {
+            conflicts.push(MergeConflict {
+                path: path.iter().cloned().map(PathSegment::Key).collect(),
+                base: b.map(|e| e.json.clone()),
+                ours: o.map(|e| e.json.clone()),
+                theirs: t.map(|e| e.json.clone()),
+                resolved: merged_entries.get(path).map(|e| e.json.clone()),
+            });
+        }
+    }
+
+    let update = merged_doc
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+    Ok(MergeResult { update, conflicts })
+}
+
+fn load(updates: &[(&'static str, &[u8])]) -> Result<Doc, MergeError> {
+    let doc = Doc::new();
+    {
+        let mut txn = doc.transact_mut();
+        for &(name, update) in updates {
+            let update = Update::decode_v1(update).map_err(|e| MergeError::Decode(name, e))?;
+            txn.apply_update(update)
+                .map_err(|e| MergeError::Apply(name, e))?;
+        }
+    }
+    Ok(doc)
+}
+
+/// Identity of a map entry value used to detect changes: primitive values are compared by their
+/// contents, while shared collections by their identifiers, so that concurrent edits made inside
+/// of the same collection are not considered as changes of the entry itself.
+#[derive(Debug, PartialEq)]
+enum EntryValue {
+    Any(Any),
+    Shared(BranchID),
+}
+
+struct Entry {
+    value: EntryValue,
+    json: Any,
+}
+
+/// Collects all entries of root-level and nested maps of a given document, indexed by their paths.
+fn entries(doc: &Doc) -> HashMap<Vec<Arc<str>>, Entry> {
+    let txn = doc.transact();
+    let mut result = HashMap::new();
+    for (name, value) in txn.root_refs() {
+        // root types which have not been defined locally are exposed as undefined refs
+        if let Some(branch) = value.try_branch() {
+            let map = MapRef::from(BranchPtr::from(branch));
+            collect_entries(&map, &txn, vec![Arc::from(name)], &mut result);
+        }
+    }
+    result
+}
+
+fn collect_entries<T: ReadTxn>(
+    map: &MapRef,
+    txn: &T,
+    path: Vec<Arc<str>>,
+    result: &mut HashMap<Vec<Arc<str>>, Entry>,
+) {
+    for (key, value) in map.iter(txn) {
+        let mut path = path.clone();
+        path.push(Arc::from(key));
+        if let Value::YMap(nested) = &value {
+            collect_entries(nested, txn, path.clone(), result);
+        }
+        let json = value.to_json(txn);
+        let value = match value {
+            Value::Any(any) => EntryValue::Any(any),
+            Value::YDoc(doc) => EntryValue::Any(Any::from(doc.guid().to_string())),
+            other => match other.try_branch() {
+                Some(branch) => EntryValue::Shared(branch.id()),
+                None => EntryValue::Any(json.clone()),
+            },
+        };
+        result.insert(path, Entry { value, json });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::merge::three_way;
+    use crate::types::{PathSegment, ToJson};
+    use crate::updates::decoder::Decode;
+    use crate::{
+        any, Any, Doc, GetString, Map, MapPrelim, MapRef, ReadTxn, StateVector, Text, Transact,
+        Update,
+    };
+
+    fn fork(base: &[u8], client_id: u64, f: impl FnOnce(&Doc)) -> Vec<u8> {
+        let doc = Doc::with_client_id(client_id);
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(base).unwrap())
+            .unwrap();
+        f(&doc);
+        let txn = doc.transact();
+        txn.encode_state_as_update_v1(&StateVector::default())
+    }
+
+    #[test]
+    fn three_way_merge_reports_conflicting_map_entries() {
+        let base = Doc::with_client_id(1);
+        let map = base.get_or_insert_map("map");
+        let text = base.get_or_insert_text("text");
+        {
+            let mut txn = base.transact_mut();
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", 1);
+            map.insert(&mut txn, "c", 1);
+            map.insert(&mut txn, "d", 1);
+            let nested = map.insert(&mut txn, "nested", MapPrelim::<Any>::new());
+            nested.insert(&mut txn, "x", "base");
+            text.push(&mut txn, "hello");
+        }
+        let base = base
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let ours = fork(&base, 2, |doc| {
+            let map = doc.get_or_insert_map("map");
+            let text = doc.get_or_insert_text("text");
+            let mut txn = doc.transact_mut();
+            map.insert(&mut txn, "a", 2); // conflict: both changed
+            map.insert(&mut txn, "b", 2); // changed only by us
+            map.insert(&mut txn, "d", 5); // same change on both sides
+            map.remove(&mut txn, "c"); // conflict: removed by us, changed by them
+            let nested: MapRef = map.get(&txn, "nested").unwrap().cast().unwrap();
+            nested.insert(&mut txn, "x", "ours");
+            text.push(&mut txn, " world");
+        });
+        let theirs = fork(&base, 3, |doc| {
+            let map = doc.get_or_insert_map("map");
+            let text = doc.get_or_insert_text("text");
+            let mut txn = doc.transact_mut();
+            map.insert(&mut txn, "a", 3);
+            map.insert(&mut txn, "c", 3);
+            map.insert(&mut txn, "d", 5);
+            let nested: MapRef = map.get(&txn, "nested").unwrap().cast().unwrap();
+            nested.insert(&mut txn, "x", "theirs");
+            text.insert(&mut txn, 0, ">");
+        });
+
+        let result = three_way(&base, &ours, &theirs).unwrap();
+        let paths: Vec<Vec<PathSegment>> = result
+            .conflicts
+            .iter()
+            .map(|c| c.path.iter().cloned().collect())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                vec![PathSegment::Key("map".into()), PathSegment::Key("a".into())],
+                vec![PathSegment::Key("map".into()), PathSegment::Key("c".into())],
+                vec![
+                    PathSegment::Key("map".into()),
+                    PathSegment::Key("nested".into()),
+                    PathSegment::Key("x".into())
+                ],
+            ]
+        );
+        let c = &result.conflicts[1];
+        assert_eq!(c.base, Some(Any::from(1)));
+        assert_eq!(c.ours, None);
+        assert_eq!(c.theirs, Some(Any::from(3)));
+
+        let merged = Doc::new();
+        let map = merged.get_or_insert_map("map");
+        let text = merged.get_or_insert_text("text");
+        merged
+            .transact_mut()
+            .apply_update(Update::decode_v1(&result.update).unwrap())
+            .unwrap();
+        let txn = merged.transact();
+        assert_eq!(text.get_string(&txn), ">hello world");
+        assert_eq!(map.get(&txn, "b").unwrap().to_json(&txn), any!(2));
+        assert_eq!(
+            result.conflicts[0].resolved,
+            Some(map.get(&txn, "a").unwrap().to_json(&txn))
+        );
+    }
+
+    #[test]
+    fn three_way_merge_invalid_input() {
+        assert!(three_way(&[0, 0], &[0, 0], &[1, 2, 3]).is_err());
+    }
+}
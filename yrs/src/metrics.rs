@@ -0,0 +1,216 @@
+//! Update integration metrics collected by a document when `metrics` feature is enabled.
+//!
+//! Every [Doc](crate::Doc) owns a [Metrics] registry, which is updated every time a remote update
+//! is applied via [TransactionMut::apply_update](crate::TransactionMut::apply_update) (or any of
+//! its variants). Registry is shared, so it can be retrieved once via
+//! [Doc::metrics](crate::Doc::metrics) and scraped periodically by a separate thread, e.g. in order
+//! to export collected values to monitoring systems like Prometheus.
+//!
+//! # Example
+//!
+//! ```rust
+//! use yrs::{Doc, ReadTxn, Text, Transact, Update};
+//! use yrs::updates::decoder::Decode;
+//!
+//! let source = Doc::new();
+//! source.get_or_insert_text("text").push(&mut source.transact_mut(), "hello");
+//! let update = source.transact().encode_state_as_update_v1(&Default::default());
+//!
+//! let doc = Doc::new();
+//! let metrics = doc.metrics();
+//! doc.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+//! // the same update applied twice is ignored
+//! doc.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+//!
+//! let snapshot = metrics.snapshot();
+//! assert_eq!(snapshot.updates_applied, 2);
+//! assert_eq!(snapshot.blocks_created, 1);
+//! assert_eq!(snapshot.blocks_skipped, 1);
+//! assert_eq!(snapshot.pending_blocks, 0);
+//! ```
+
+use crate::store::Store;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Registry of counters and gauges describing remote updates integrated by a document.
+/// All values are updated atomically, so registry can be safely read while the document is being
+/// modified. Use [Metrics::snapshot] to get a consistent view over all collected values.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    updates_applied: AtomicU64,
+    apply_time_nanos: AtomicU64,
+    last_apply_time_nanos: AtomicU64,
+    blocks_created: AtomicU64,
+    blocks_merged: AtomicU64,
+    blocks_skipped: AtomicU64,
+    pending_blocks: AtomicU64,
+    pending_delete_ranges: AtomicU64,
+}
+
+impl Metrics {
+    /// Returns a point-in-time copy of all values collected so far.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            updates_applied: self.updates_applied.load(Ordering::Relaxed),
+            apply_time: Duration::from_nanos(self.apply_time_nanos.load(Ordering::Relaxed)),
+            last_apply_time: Duration::from_nanos(
+                self.last_apply_time_nanos.load(Ordering::Relaxed),
+            ),
+            blocks_created: self.blocks_created.load(Ordering::Relaxed),
+            blocks_merged: self.blocks_merged.load(Ordering::Relaxed),
+            blocks_skipped: self.blocks_skipped.load(Ordering::Relaxed),
+            pending_blocks: self.pending_blocks.load(Ordering::Relaxed),
+            pending_delete_ranges: self.pending_delete_ranges.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets all counters back to zero. Gauges (sizes of pending queues) are left untouched, as
+    /// they describe the current state of a document.
+    pub fn reset(&self) {
+        self.updates_applied.store(0, Ordering::Relaxed);
+        self.apply_time_nanos.store(0, Ordering::Relaxed);
+        self.last_apply_time_nanos.store(0, Ordering::Relaxed);
+        self.blocks_created.store(0, Ordering::Relaxed);
+        self.blocks_merged.store(0, Ordering::Relaxed);
+        self.blocks_skipped.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn block_created(&self) {
+        self.blocks_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn block_skipped(&self) {
+        self.blocks_skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn blocks_merged(&self, count: usize) {
+        if count != 0 {
+            self.blocks_merged
+                .fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a completed update integration, which started at a given `start` time, together
+    /// with current sizes of the pending queues of a `store`.
+    pub(crate) fn update_applied(&self, start: Option<Stopwatch>, store: &Store) {
+        self.updates_applied.fetch_add(1, Ordering::Relaxed);
+        if let Some(start) = start {
+            let elapsed = start.elapsed().as_nanos() as u64;
+            self.apply_time_nanos.fetch_add(elapsed, Ordering::Relaxed);
+            self.last_apply_time_nanos.store(elapsed, Ordering::Relaxed);
+        }
+        let pending_blocks = match &store.pending {
+            Some(pending) => pending.update.blocks.len(),
+            None => 0,
+        };
+        let pending_delete_ranges: usize = match &store.pending_ds {
+            Some(ds) => ds.iter().map(|(_, range)| range.iter().count()).sum(),
+            None => 0,
+        };
+        self.pending_blocks
+            .store(pending_blocks as u64, Ordering::Relaxed);
+        self.pending_delete_ranges
+            .store(pending_delete_ranges as u64, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time copy of values collected by [Metrics].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Number of updates applied to a document. Pending updates, which are retried once their
+    /// missing dependencies arrive, are counted as part of the update that unblocked them.
+    pub updates_applied: u64,
+    /// Total time spent on integrating updates. Always zero on `wasm32-unknown-unknown` targets,
+    /// which have no monotonic clock available.
+    pub apply_time: Duration,
+    /// Time spent on integrating the most recently applied update.
+    pub last_apply_time: Duration,
+    /// Number of blocks (including GC blocks) integrated into a document store.
+    pub blocks_created: u64,
+    /// Number of blocks squashed together with their left neighbours, when transactions which
+    /// created them were committed.
+    pub blocks_merged: u64,
+    /// Number of blocks ignored during integration, because they were already present in
+    /// a document store or represented a skipped range.
+    pub blocks_skipped: u64,
+    /// Number of blocks currently waiting in a pending update for their missing dependencies.
+    pub pending_blocks: u64,
+    /// Number of deleted ranges currently waiting in a pending delete set for blocks they refer to.
+    pub pending_delete_ranges: u64,
+}
+
+/// Measures time elapsed since the start of an update integration.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stopwatch(#[cfg(not(target_family = "wasm"))] std::time::Instant);
+
+impl Stopwatch {
+    /// Starts a new stopwatch, if monotonic clock is available on a current target.
+    pub(crate) fn start() -> Option<Self> {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            Some(Stopwatch(std::time::Instant::now()))
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            None
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            self.0.elapsed()
+        }
+        #[cfg(target_family = "wasm")]
+        {
+            Duration::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+
+    #[test]
+    fn pending_queues_are_tracked() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        text.push(&mut source.transact_mut(), "a");
+        let sv = source.transact().state_vector();
+        text.push(&mut source.transact_mut(), "b");
+        let u2 = source.transact().encode_state_as_update_v1(&sv);
+        text.remove_range(&mut source.transact_mut(), 0, 2);
+        let u1 = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let doc = Doc::with_client_id(2);
+        let metrics = doc.metrics();
+        let text = doc.get_or_insert_text("text");
+
+        // second block arrives first and must wait for its predecessor
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&u2).unwrap())
+            .unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.updates_applied, 1);
+        assert_eq!(snapshot.blocks_created, 0);
+        assert_eq!(snapshot.pending_blocks, 1);
+
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&u1).unwrap())
+            .unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.updates_applied, 2);
+        assert_eq!(snapshot.pending_blocks, 0);
+        assert_eq!(snapshot.pending_delete_ranges, 0);
+        assert!(snapshot.blocks_created >= 1);
+        assert_eq!(text.get_string(&doc.transact()), "");
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot().updates_applied, 0);
+    }
+}
@@ -1,14 +1,15 @@
-use crate::block::{ItemContent, ItemPtr, Prelim, Unused};
+use crate::block::{ClientID, ItemContent, ItemPtr, Prelim, Unused};
 use crate::block_iter::BlockIter;
 use crate::branch::{Branch, BranchPtr};
 use crate::encoding::read::Error;
 use crate::transaction::TransactionMut;
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
-use crate::{BranchID, ReadTxn, WriteTxn, ID};
+use crate::{Any, BranchID, ReadTxn, WriteTxn, ID};
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt::Formatter;
 use std::sync::Arc;
 
@@ -449,6 +450,79 @@ impl StickyIndex {
         }
     }
 
+    /// Returns a JSON-like representation of current [StickyIndex], compatible with the one
+    /// produced by Yjs `relativePositionToJSON` function: `{ type?, tname?, item?, assoc }`,
+    /// where `type` and `item` are `{ client, clock }` objects.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use yrs::{any, Assoc, StickyIndex, ID};
+    ///
+    /// let pos = StickyIndex::from_id(ID::new(1, 2), Assoc::Before);
+    /// let json = pos.to_json();
+    /// assert_eq!(json, any!({"item": {"client": 1, "clock": 2}, "assoc": -1}));
+    /// assert_eq!(StickyIndex::from_json(&json).unwrap(), pos);
+    /// ```
+    pub fn to_json(&self) -> Any {
+        fn id_to_json(id: &ID) -> Any {
+            let mut m = HashMap::new();
+            m.insert("client".to_owned(), Any::Number(id.client as f64));
+            m.insert("clock".to_owned(), Any::from(id.clock));
+            Any::from(m)
+        }
+
+        let mut m = HashMap::new();
+        match &self.scope {
+            IndexScope::Relative(id) => m.insert("item".to_owned(), id_to_json(id)),
+            IndexScope::Nested(id) => m.insert("type".to_owned(), id_to_json(id)),
+            IndexScope::Root(name) => m.insert("tname".to_owned(), Any::String(name.clone())),
+        };
+        m.insert("assoc".to_owned(), Any::from(self.assoc as i32));
+        Any::from(m)
+    }
+
+    /// Parses [StickyIndex] from a JSON-like representation produced either by
+    /// [StickyIndex::to_json] or by Yjs `relativePositionToJSON` function. Missing `assoc` field
+    /// is treated as [Assoc::After], just like in Yjs.
+    pub fn from_json(json: &Any) -> Result<Self, Error> {
+        fn id_from_json(json: &Any) -> Result<ID, Error> {
+            if let Any::Map(m) = json {
+                let client = m.get("client").and_then(Any::as_i64);
+                let clock = m.get("clock").and_then(Any::as_i64);
+                if let (Some(client), Some(clock)) = (client, clock) {
+                    let client =
+                        ClientID::try_from(client).map_err(|_| Error::IntegerOverflow("client"))?;
+                    let clock =
+                        u32::try_from(clock).map_err(|_| Error::IntegerOverflow("clock"))?;
+                    return Ok(ID::new(client, clock));
+                }
+            }
+            Err(Error::UnexpectedValue)
+        }
+
+        let m = match json {
+            Any::Map(m) => m,
+            _ => return Err(Error::UnexpectedValue),
+        };
+        let assoc = match m.get("assoc").map(Any::as_i64) {
+            None => Assoc::After,
+            Some(Some(assoc)) if assoc >= 0 => Assoc::After,
+            Some(Some(_)) => Assoc::Before,
+            Some(None) => return Err(Error::UnexpectedValue),
+        };
+        let scope = if let Some(item) = m.get("item") {
+            IndexScope::Relative(id_from_json(item)?)
+        } else if let Some(Any::String(name)) = m.get("tname") {
+            IndexScope::Root(name.clone())
+        } else if let Some(branch) = m.get("type") {
+            IndexScope::Nested(id_from_json(branch)?)
+        } else {
+            return Err(Error::UnexpectedValue);
+        };
+        Ok(Self::new(scope, assoc))
+    }
+
     /// Maps current [StickyIndex] onto [Offset] which points to shared collection and a
     /// human-readable index in that collection.
     ///
@@ -843,10 +917,10 @@ impl Offset {
 
 #[cfg(test)]
 mod test {
-    use crate::moving::Assoc;
+    use crate::moving::{Assoc, IndexScope};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::Encode;
-    use crate::{Doc, IndexedSequence, StickyIndex, Text, TextRef, Transact};
+    use crate::{any, Any, Doc, IndexedSequence, StickyIndex, Text, TextRef, Transact, ID};
 
     fn check_sticky_indexes(doc: &Doc, text: &TextRef) {
         // test if all positions are encoded and restored correctly
@@ -958,4 +1032,30 @@ mod test {
         assert_eq!(pos_right.index, 2);
         assert_eq!(pos_left.index, 1);
     }
+
+    #[test]
+    fn sticky_index_json_compatible_with_yjs() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut txn = doc.transact_mut();
+
+        // empty root type
+        let pos = txt.sticky_index(&mut txn, 0, Assoc::Before).unwrap();
+        assert_eq!(pos.to_json(), any!({"tname": "test", "assoc": -1}));
+
+        txt.insert(&mut txn, 0, "abc");
+        let pos = txt.sticky_index(&mut txn, 1, Assoc::Before).unwrap();
+        let json = pos.to_json();
+        assert_eq!(json, any!({"item": {"client": 1, "clock": 0}, "assoc": -1}));
+        assert_eq!(StickyIndex::from_json(&json).unwrap(), pos);
+
+        // JSON produced by Yjs may skip `assoc` field
+        let json = Any::from_json(r#"{"type":{"client":2,"clock":5}}"#).unwrap();
+        let pos = StickyIndex::from_json(&json).unwrap();
+        assert_eq!(pos.scope(), &IndexScope::Nested(ID::new(2, 5)));
+        assert_eq!(pos.assoc, Assoc::After);
+
+        let json = Any::from_json(r#"{"item":{"client":2,"clock":-1}}"#).unwrap();
+        assert!(StickyIndex::from_json(&json).is_err());
+    }
 }
@@ -1,11 +1,30 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::{Arc, Weak};
 
-use arc_swap::{ArcSwapOption, AsRaw, Guard};
+use arc_swap::{ArcSwap, ArcSwapOption, Guard};
 
 use crate::Origin;
 
 /// Data structure used to handle publish/subscribe callbacks of specific type. Observers perform
 /// subscriber changes in thread-safe manner, using atomic hardware intrinsics.
+///
+/// # Ordering guarantees
+///
+/// Callbacks are always called in a deterministic order: callbacks with higher priority (see
+/// [Observer::subscribe_prioritized]) are called before the ones with lower priority, while
+/// callbacks with the same priority are called in the order of their registration. Callbacks
+/// subscribed via [Observer::subscribe] and [Observer::subscribe_with] have a priority of `0`.
+/// Keyed subscriptions of documents, shared types, undo managers and awareness use a priority of
+/// their [SubscriptionKey] (see [SubscriptionKey::with_priority]).
+/// This allows eg. to keep a document-derived model up to date before any UI component reads it,
+/// by subscribing the model with a higher priority.
+///
+/// Every trigger calls a snapshot of callbacks, which were subscribed at the moment when it
+/// started: callbacks subscribed or unsubscribed by other callbacks take effect only the next time
+/// the observer is triggered. Document and shared type events are triggered once per committed
+/// transaction.
 pub struct Observer<F> {
     inner: ArcSwapOption<Inner<F>>,
 }
@@ -23,7 +42,7 @@ where
 
     pub fn has_subscribers(&self) -> bool {
         if let Some(inner) = &*self.inner.load() {
-            inner.head.load().is_some()
+            !inner.nodes.load().is_empty()
         } else {
             false
         }
@@ -45,7 +64,7 @@ where
             None => {
                 // inner was not initialized yet, we need to create a new one
                 let inner = Arc::new(Inner {
-                    head: ArcSwapOption::new(None),
+                    nodes: ArcSwap::from_pointee(Vec::new()),
                 });
                 let old: Option<Arc<Inner<F>>> = None;
                 let prev = self.inner.compare_and_swap(&old, Some(inner.clone()));
@@ -56,17 +75,6 @@ where
         }
     }
 
-    fn remove(mut prev: Arc<Node<F>>, id: &Origin) -> bool {
-        while let Some(next) = prev.next.load_full() {
-            if &next.uid == id {
-                prev.next.store(next.next.load_full());
-                return true;
-            }
-            prev = next;
-        }
-        false
-    }
-
     pub fn unsubscribe(&self, id: &Origin) -> bool {
         if let Some(inner) = &*self.inner.load() {
             inner.remove(id)
@@ -77,45 +85,83 @@ where
 
     /// Returns a snapshot of callbacks subscribed to this observer at the moment when this method
     /// has been called. This snapshot can be iterated over to get access to individual callbacks
-    /// and trigger them.
-    pub fn trigger<E>(&self, mut each: E)
+    /// and trigger them. Callbacks are visited in order described in [Observer] docs.
+    ///
+    /// Panics raised by individual callbacks are caught, so that they don't prevent remaining
+    /// callbacks from being called. Caught panics are passed to a `hook` if one was provided.
+    /// Otherwise the payload of the first caught panic is returned, so that the caller can resume
+    /// it with [std::panic::resume_unwind] once it's done updating its own state (eg. transaction
+    /// commit defers it until the transaction is dropped).
+    #[must_use]
+    pub fn trigger<E>(
+        &self,
+        hook: Option<&CallbackPanicHook>,
+        mut each: E,
+    ) -> Option<Box<dyn Any + Send + 'static>>
     where
         E: FnMut(&F),
     {
+        let mut unhandled = None;
         if let Some(inner) = &*self.inner.load() {
-            let mut next = inner.head.load();
-            while let Some(node) = &*next {
-                each(&node.callback);
-                next = node.next.load();
+            let nodes = inner.nodes.load_full();
+            for node in nodes.iter() {
+                if let Err(payload) = catch_unwind(AssertUnwindSafe(|| each(&node.callback))) {
+                    match hook {
+                        Some(hook) => hook.report(CallbackPanic {
+                            id: node.uid.clone(),
+                            payload,
+                        }),
+                        None => {
+                            unhandled.get_or_insert(payload);
+                        }
+                    }
+                }
             }
         }
+        unhandled
     }
 
-    /// Subscribes a callback parameter to a current [Observer].
-    /// Returns a subscription object which - when dropped - will unsubscribe current callback.
-    /// If the `id` was already present in the observer, current callback will be ignored.
+    /// Subscribes a callback parameter to a current [Observer] under a given `id`. Such callback
+    /// stays subscribed until it's explicitly removed with [Observer::unsubscribe] called with the
+    /// same `id`. If the `id` was already present in the observer, previous callback is replaced
+    /// and new one is ordered as if it was registered for the first time.
+    ///
+    /// Subscription keys are scoped to a single observer: unsubscribing an `id` from one observer
+    /// never affects callbacks subscribed under the same `id` to another one.
     pub fn subscribe_with(&self, id: Origin, callback: F) {
-        let inner = self.inner();
-        let mut node = Arc::new(Node::new(id.clone(), callback));
-        let cur = inner.head.load();
-        let head = loop {
-            {
-                // update new node next pointer to point to current head
-                // it's safe to unwrap, since until current node is successfully inserted
-                // there will be no more that a single Arc reference to it
-                let n = Arc::get_mut(&mut node).unwrap();
-                n.next.store(cur.clone());
-            }
+        self.subscribe_with_prioritized(id, 0, callback)
+    }
 
-            let prev = inner.head.compare_and_swap(&*cur, Some(node.clone()));
-            let swapped = std::ptr::eq(prev.as_raw(), cur.as_raw());
-            if swapped {
-                // we successfully swapped the head, we can exit the loop
-                break node;
-            }
-        };
-        // remove all previous nodes that share the same ID
-        Self::remove(head.clone(), &id);
+    /// Works like [Observer::subscribe_with_prioritized], using an origin and a priority of
+    /// a given typed `key`.
+    pub fn subscribe_with_key<K, O: ObserverKey<K>>(&self, key: O, callback: F) {
+        let priority = key.priority();
+        self.subscribe_with_prioritized(key.into_origin(), priority, callback)
+    }
+
+    /// Works like [Observer::subscribe_with], but the callback is called before all callbacks
+    /// with lower `priority` and after all callbacks with higher one.
+    pub fn subscribe_with_prioritized(&self, id: Origin, priority: i32, callback: F) {
+        let inner = self.inner();
+        let node = Arc::new(Node {
+            uid: id,
+            priority,
+            callback,
+        });
+        inner.nodes.rcu(|nodes| {
+            let mut nodes: Vec<_> = nodes
+                .iter()
+                .filter(|n| n.uid != node.uid)
+                .cloned()
+                .collect();
+            // keep registration order among the callbacks of the same priority
+            let idx = nodes
+                .iter()
+                .position(|n| n.priority < priority)
+                .unwrap_or(nodes.len());
+            nodes.insert(idx, node.clone());
+            nodes
+        });
     }
 }
 
@@ -124,10 +170,16 @@ where
     F: Send + Sync + 'static,
 {
     pub fn subscribe(&self, callback: F) -> Subscription {
+        self.subscribe_prioritized(0, callback)
+    }
+
+    /// Works like [Observer::subscribe], but the callback is called before all callbacks with
+    /// lower `priority` and after all callbacks with higher one.
+    pub fn subscribe_prioritized(&self, priority: i32, callback: F) -> Subscription {
         let mut rng = fastrand::Rng::new();
         let id = rng.usize(0..usize::MAX);
         let origin = Origin::from(id);
-        self.subscribe_with(origin.clone(), callback);
+        self.subscribe_with_prioritized(origin.clone(), priority, callback);
         Arc::new(Cancel {
             id: origin,
             inner: Arc::downgrade(&self.inner()),
@@ -156,7 +208,10 @@ where
 }
 
 struct Inner<F> {
-    head: ArcSwapOption<Node<F>>,
+    /// Subscribed callbacks in order of their invocation. The list is never modified in place:
+    /// every change swaps it with an updated copy, so that triggers can iterate over a stable
+    /// snapshot without locking.
+    nodes: ArcSwap<Vec<Arc<Node<F>>>>,
 }
 
 impl<F> Inner<F>
@@ -164,43 +219,20 @@ where
     F: 'static,
 {
     fn remove(&self, id: &Origin) -> bool {
-        while let Some(head) = self.head.load_full() {
-            if &head.uid == id {
-                // the element to remove is the head of the list
-                // we need to swap head pointer of self to the next element
-                let next = head.next.load_full();
-                let prev = self.head.compare_and_swap(&head, next);
-                if !std::ptr::eq(prev.as_raw(), Arc::as_ptr(&head)) {
-                    // head changed, retry
-                    continue;
-                } else {
-                    return true;
-                }
-            } else {
-                // the element to remove is somewhere in the middle of the list
-                // we need to find it and repoint its predecessor's next pointer
-                // to its successor
-                return Observer::remove(head.clone(), id);
-            }
-        }
-        false
+        let mut removed = false;
+        self.nodes.rcu(|nodes| {
+            let remaining: Vec<_> = nodes.iter().filter(|n| &n.uid != id).cloned().collect();
+            removed = remaining.len() != nodes.len();
+            remaining
+        });
+        removed
     }
 }
 
 struct Node<T> {
     uid: Origin,
+    priority: i32,
     callback: T,
-    next: ArcSwapOption<Node<T>>,
-}
-
-impl<F> Node<F> {
-    fn new(uid: Origin, callback: F) -> Self {
-        Node {
-            uid,
-            callback,
-            next: Default::default(),
-        }
-    }
 }
 
 struct Cancel<F>
@@ -223,20 +255,265 @@ where
 }
 
 /// Subscription handle returned by [Observer::subscribe] methods, which will unsubscribe corresponding
-/// callback when dropped.
+/// callback when dropped. Subscription handle doesn't expose any identifier, so it cannot be used
+/// to accidentally unsubscribe a callback of a different observer.
 ///
-/// If implicit callback unsubscribe on drop is undesired, this structure can be cast [into](Subscription::into)
-/// [SubscriptionId] which is an identifier of the same subscription, which in turn must be used
-/// manually via [Observer::unsubscribe] to perform usubscribe.
+/// If implicit callback unsubscribe on drop is undesired, use [Observer::subscribe_with] with
+/// a caller-defined key instead, and [Observer::unsubscribe] it manually using the same key.
 pub type Subscription = Arc<dyn Drop + Send + Sync + 'static>;
 
+/// Typed key of a callback subscribed to a specific kind `K` of observers, eg. with
+/// [Doc::observe_update_v1_with] or [Observable::observe_with]. Keys are accepted only by methods
+/// of observers of the same kind. This way, using a key of one observer to unsubscribe a callback
+/// of another one is a compile error:
+///
+/// ```compile_fail
+/// use yrs::observer::{kind, SubscriptionKey};
+/// use yrs::Doc;
+///
+/// let doc = Doc::new();
+/// let key = SubscriptionKey::<kind::UpdateV1>::new("sync");
+/// doc.observe_update_v1_with(key.clone(), |_, _| {}).unwrap();
+/// doc.unobserve_after_transaction(key).unwrap(); // expected `kind::AfterTransaction` key
+/// ```
+///
+/// Kinds of shared type observers are identified by their event types (eg. [TextEvent] for
+/// [TextRef::observe_with]). Kinds of other observers are defined in [kind] module.
+///
+/// ```rust
+/// use yrs::observer::{kind, SubscriptionKey};
+/// use yrs::types::text::TextEvent;
+/// use yrs::{Doc, Observable};
+///
+/// let doc = Doc::new();
+/// let text = doc.get_or_insert_text("text");
+/// let key = SubscriptionKey::<TextEvent>::new("editor");
+/// text.observe_with(key.clone(), |_, _| {});
+/// assert!(text.unobserve(key));
+///
+/// let key = SubscriptionKey::<kind::UpdateV1>::new("sync");
+/// doc.observe_update_v1_with(key.clone(), |_, _| {}).unwrap();
+/// assert!(doc.unobserve_update_v1(key).unwrap());
+/// ```
+///
+/// Plain values convertible into [Origin] are no longer accepted as keys. Code which used them can
+/// be migrated by wrapping them with [SubscriptionKey::new], eg. `SubscriptionKey::new("sync")`.
+///
+/// Keys also carry a priority of a subscribed callback (see [SubscriptionKey::with_priority]),
+/// so that eg. a document-derived model can be updated before any UI component reads it. Keys
+/// are compared only by their origins, so a priority is not needed to unsubscribe a callback.
+///
+/// [Doc::observe_update_v1_with]: crate::Doc::observe_update_v1_with
+/// [Observable::observe_with]: crate::Observable::observe_with
+/// [TextEvent]: crate::types::text::TextEvent
+/// [TextRef::observe_with]: crate::Observable::observe_with
+pub struct SubscriptionKey<K> {
+    origin: Origin,
+    priority: i32,
+    _kind: PhantomData<fn() -> K>,
+}
+
+impl<K> SubscriptionKey<K> {
+    /// Creates a new typed subscription key.
+    pub fn new<O: Into<Origin>>(origin: O) -> Self {
+        SubscriptionKey {
+            origin: origin.into(),
+            priority: 0,
+            _kind: PhantomData,
+        }
+    }
+
+    /// Sets a priority of a callback subscribed with current key. Such callback is called before
+    /// all callbacks of the same observer with lower priority and after the ones with higher
+    /// priority. Default priority is `0`.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Returns a priority of a callback subscribed with current key.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Returns an untyped key, which callbacks are subscribed under.
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+}
+
+impl<K> Clone for SubscriptionKey<K> {
+    fn clone(&self) -> Self {
+        SubscriptionKey::new(self.origin.clone()).with_priority(self.priority)
+    }
+}
+
+impl<K> PartialEq for SubscriptionKey<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.origin == other.origin
+    }
+}
+
+impl<K> Eq for SubscriptionKey<K> {}
+
+impl<K> std::fmt::Debug for SubscriptionKey<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SubscriptionKey")
+            .field(&self.origin)
+            .field(&self.priority)
+            .finish()
+    }
+}
+
+/// Key of a callback subscribed to a specific kind `K` of observers, implemented by
+/// [SubscriptionKey] of the same kind.
+pub trait ObserverKey<K> {
+    /// Converts current key into an untyped key, which callbacks are subscribed under.
+    fn into_origin(self) -> Origin;
+
+    /// Returns a priority of a callback subscribed under current key.
+    fn priority(&self) -> i32 {
+        0
+    }
+}
+
+impl<K> ObserverKey<K> for SubscriptionKey<K> {
+    fn into_origin(self) -> Origin {
+        self.origin
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+/// Marker types identifying kinds of deep, document, undo manager and awareness observers, used
+/// by [SubscriptionKey].
+pub mod kind {
+    /// Kind of [DeepObservable::observe_deep_with](crate::DeepObservable::observe_deep_with)
+    /// observers.
+    pub enum Deep {}
+    /// Kind of [Doc::observe_update_v1_with](crate::Doc::observe_update_v1_with) observers.
+    pub enum UpdateV1 {}
+    /// Kind of [Doc::observe_update_v2_with](crate::Doc::observe_update_v2_with) observers.
+    pub enum UpdateV2 {}
+    /// Kind of [Doc::observe_subdoc_update_v1_with](crate::Doc::observe_subdoc_update_v1_with)
+    /// observers.
+    pub enum SubdocUpdateV1 {}
+    /// Kind of [Doc::observe_subdoc_update_v2_with](crate::Doc::observe_subdoc_update_v2_with)
+    /// observers.
+    pub enum SubdocUpdateV2 {}
+    /// Kind of [Doc::observe_quota_exceeded_with](crate::Doc::observe_quota_exceeded_with)
+    /// observers.
+    pub enum QuotaExceeded {}
+    /// Kind of
+    /// [Doc::observe_transaction_cleanup_with](crate::Doc::observe_transaction_cleanup_with)
+    /// observers.
+    pub enum TransactionCleanup {}
+    /// Kind of [Doc::observe_after_transaction_with](crate::Doc::observe_after_transaction_with)
+    /// observers.
+    pub enum AfterTransaction {}
+    /// Kind of [Doc::observe_subdocs_with](crate::Doc::observe_subdocs_with) observers.
+    pub enum Subdocs {}
+    /// Kind of [Doc::observe_destroy_with](crate::Doc::observe_destroy_with) observers.
+    pub enum Destroy {}
+    /// Kind of [Doc::observe_root_added_with](crate::Doc::observe_root_added_with) observers.
+    pub enum RootAdded {}
+    /// Kind of [UndoManager::observe_item_added_with](crate::UndoManager::observe_item_added_with)
+    /// observers.
+    pub enum UndoItemAdded {}
+    /// Kind of
+    /// [UndoManager::observe_item_updated_with](crate::UndoManager::observe_item_updated_with)
+    /// observers.
+    pub enum UndoItemUpdated {}
+    /// Kind of
+    /// [UndoManager::observe_item_popped_with](crate::UndoManager::observe_item_popped_with)
+    /// observers.
+    pub enum UndoItemPopped {}
+    /// Kind of [Awareness::on_update_with](crate::sync::Awareness::on_update_with) observers.
+    pub enum AwarenessUpdate {}
+    /// Kind of [Awareness::on_change_with](crate::sync::Awareness::on_change_with) observers.
+    pub enum AwarenessChange {}
+}
+
+/// Panic caught while calling one of the [Observer] callbacks.
+pub struct CallbackPanic {
+    /// Identifier of the subscription, which callback has panicked.
+    pub id: Origin,
+    /// Payload passed to the panic.
+    pub payload: Box<dyn Any + Send + 'static>,
+}
+
+impl CallbackPanic {
+    /// Returns a panic message, if panic payload was a string.
+    pub fn message(&self) -> Option<&str> {
+        if let Some(msg) = self.payload.downcast_ref::<&'static str>() {
+            Some(msg)
+        } else if let Some(msg) = self.payload.downcast_ref::<String>() {
+            Some(msg.as_str())
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Debug for CallbackPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackPanic")
+            .field("id", &self.id)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+/// Hook called whenever one of the observer callbacks of a document panics, configured via
+/// [Options::callback_panic_hook]. It allows to log or report panics of user callbacks. Panics
+/// passed to a hook are never propagated to the code that triggered them, while without a hook
+/// they are resumed once all callbacks have been called and the document state is consistent.
+///
+/// [Options::callback_panic_hook]: crate::Options::callback_panic_hook
+#[derive(Clone)]
+pub struct CallbackPanicHook(Arc<dyn Fn(CallbackPanic) + Send + Sync>);
+
+impl CallbackPanicHook {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(CallbackPanic) + Send + Sync + 'static,
+    {
+        CallbackPanicHook(Arc::new(f))
+    }
+
+    /// Passes a `panic` to a current hook.
+    pub fn report(&self, panic: CallbackPanic) {
+        // hook itself may panic, it should not unwind through the observer either
+        let _ = catch_unwind(AssertUnwindSafe(|| (self.0)(panic)));
+    }
+}
+
+impl PartialEq for CallbackPanicHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CallbackPanicHook {}
+
+impl std::fmt::Debug for CallbackPanicHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CallbackPanicHook({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
     use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
     use std::thread::spawn;
 
-    use crate::observer::Observer;
+    use crate::observer::{kind, CallbackPanicHook, Observer, SubscriptionKey};
+    use crate::types::text::TextEvent;
+    use crate::{DeepObservable, Doc, GetString, Observable, Options, Origin, Text, Transact};
 
     #[test]
     fn subscription() {
@@ -255,18 +532,18 @@ mod test {
                 b.store(value * 2, Ordering::Release);
             }));
 
-            o.trigger(|fun| fun(&1));
+            assert!(o.trigger(None, |fun| fun(&1)).is_none());
             assert_eq!(s1_state.load(Ordering::Acquire), 1);
             assert_eq!(s2_state.load(Ordering::Acquire), 2);
 
-            o.trigger(|fun| fun(&2));
+            assert!(o.trigger(None, |fun| fun(&2)).is_none());
             assert_eq!(s1_state.load(Ordering::Acquire), 2);
             assert_eq!(s2_state.load(Ordering::Acquire), 4);
         }
 
         // subscriptions were dropped, we don't expect updates to be propagated
 
-        o.trigger(|fun| fun(&3));
+        assert!(o.trigger(None, |fun| fun(&3)).is_none());
         assert_eq!(s1_state.load(Ordering::Acquire), 2);
         assert_eq!(s2_state.load(Ordering::Acquire), 4);
     }
@@ -298,7 +575,7 @@ mod test {
         let sub2 = o.subscribe(Box::new(move |v| b.store(v, Ordering::Release)));
 
         let handle = spawn(move || {
-            o.trigger(|fun| fun(1));
+            assert!(o.trigger(None, |fun| fun(1)).is_none());
             drop(sub1);
             drop(sub2);
         });
@@ -318,7 +595,7 @@ mod test {
             123.into(),
             Box::new(move |i| ta.send(format!("a-{i}")).unwrap()),
         );
-        o.trigger(|fun| fun(1));
+        assert!(o.trigger(None, |fun| fun(1)).is_none());
         assert_eq!(rx.try_recv().unwrap(), "a-1");
 
         // override the callback with the same key
@@ -326,10 +603,68 @@ mod test {
             123.into(),
             Box::new(move |i| tx.send(format!("b-{i}")).unwrap()),
         );
-        o.trigger(|fun| fun(2));
+        assert!(o.trigger(None, |fun| fun(2)).is_none());
         assert_eq!(rx.try_recv().unwrap(), "b-2");
     }
 
+    #[test]
+    fn unsubscribe_is_scoped_to_observer() {
+        let o1: Observer<Box<dyn Fn(&u32) + Send + Sync + 'static>> = Observer::new();
+        let o2: Observer<Box<dyn Fn(&u32) + Send + Sync + 'static>> = Observer::new();
+        let state = Arc::new(AtomicU32::new(0));
+        let s = state.clone();
+        o1.subscribe_with(1.into(), Box::new(|_| {}));
+        o2.subscribe_with(1.into(), Box::new(move |&v| s.store(v, Ordering::Release)));
+
+        assert!(o1.unsubscribe(&1.into()));
+        assert!(!o1.has_subscribers());
+        assert!(o2.trigger(None, |fun| fun(&1)).is_none());
+        assert_eq!(state.load(Ordering::Acquire), 1);
+
+        // subscriptions returned by `subscribe` don't interfere with caller-defined keys
+        let _sub = o1.subscribe(Box::new(|_| {}));
+        assert!(!o1.unsubscribe(&1.into()));
+        assert!(o1.has_subscribers());
+    }
+
+    #[test]
+    fn typed_subscription_keys() {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text("text");
+        let calls = Arc::new(AtomicU32::new(0));
+
+        let update_key = SubscriptionKey::<kind::UpdateV1>::new("key");
+        let c = calls.clone();
+        doc.observe_update_v1_with(update_key.clone(), move |_, _| {
+            c.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        let text_key = SubscriptionKey::<TextEvent>::new("key");
+        let c = calls.clone();
+        text.observe_with(text_key.clone(), move |_, _| {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        let deep_key = SubscriptionKey::<kind::Deep>::new("key");
+        let c = calls.clone();
+        text.observe_deep_with(deep_key.clone(), move |_, _| {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+
+        text.insert(&mut doc.transact_mut(), 0, "a");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // keys of different kinds may share the same origin
+        assert_eq!(update_key.origin(), &Origin::from("key"));
+        assert!(text.unobserve(text_key));
+        assert!(text.unobserve_deep(deep_key.clone()));
+        assert!(!text.unobserve_deep(deep_key));
+        assert!(doc
+            .unobserve_update_v1(SubscriptionKey::<kind::UpdateV1>::new("key"))
+            .unwrap());
+        text.insert(&mut doc.transact_mut(), 0, "b");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
     struct DropCounter(Arc<AtomicI32>);
 
     impl DropCounter {
@@ -417,4 +752,120 @@ mod test {
         o.clean();
         assert_eq!(counter.load(Ordering::SeqCst), 0);
     }
+
+    #[test]
+    fn panicking_callback_is_isolated() {
+        let o: Observer<Box<dyn Fn(&u32) + Send + Sync + 'static>> = Observer::new();
+        let calls = Arc::new(AtomicU32::new(0));
+        for i in 0..3u32 {
+            let calls = calls.clone();
+            o.subscribe_with(
+                Origin::from(i),
+                Box::new(move |&value| {
+                    if i == 1 {
+                        panic!("callback {} failed on {}", i, value);
+                    }
+                    calls.fetch_add(1, Ordering::SeqCst);
+                }),
+            );
+        }
+
+        // without a hook, remaining callbacks are called and panic payload is returned
+        let payload = o.trigger(None, |fun| fun(&10)).unwrap();
+        assert_eq!(
+            payload.downcast_ref::<String>().map(String::as_str),
+            Some("callback 1 failed on 10")
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let panics = Arc::new(Mutex::new(Vec::new()));
+        let hook = {
+            let panics = panics.clone();
+            CallbackPanicHook::new(move |p| {
+                let msg = p.message().map(String::from);
+                panics.lock().unwrap().push((p.id, msg));
+            })
+        };
+        assert!(o.trigger(Some(&hook), |fun| fun(&20)).is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // panicking type observer doesn't interrupt transaction commit
+        let mut options = Options::with_client_id(1);
+        options.callback_panic_hook = Some(hook);
+        let doc = Doc::with_options(options);
+        let text = doc.get_or_insert_text("text");
+        let _s1 = text.observe(|_, _| panic!("text observer failed"));
+        let _s2 = {
+            let calls = calls.clone();
+            doc.observe_update_v1(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        text.push(&mut doc.transact_mut(), "hello");
+        text.push(&mut doc.transact_mut(), " world");
+
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+        assert_eq!(text.get_string(&doc.transact()), "hello world");
+        let panics = panics.lock().unwrap();
+        assert_eq!(panics.len(), 3);
+        assert_eq!(
+            panics[0],
+            (
+                Origin::from(1u32),
+                Some("callback 1 failed on 20".to_string())
+            )
+        );
+        assert_eq!(panics[1].1.as_deref(), Some("text observer failed"));
+    }
+
+    #[test]
+    fn panicking_callback_without_hook() {
+        // without a hook, panic is resumed once the transaction has been committed
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let calls = Arc::new(AtomicU32::new(0));
+        let _s1 = text.observe(|_, _| panic!("text observer failed"));
+        let _s2 = {
+            let calls = calls.clone();
+            doc.observe_update_v1(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            text.push(&mut doc.transact_mut(), "hello");
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<&str>().copied(),
+            Some("text observer failed")
+        );
+        // remaining callbacks were called and the store lock was released
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(text.get_string(&doc.transact()), "hello");
+    }
+
+    #[test]
+    fn callbacks_are_ordered_by_priority_and_registration() {
+        let o: Observer<Box<dyn Fn(&mut Vec<&'static str>) + Send + Sync + 'static>> =
+            Observer::new();
+        let _ui1 = o.subscribe(Box::new(|log| log.push("ui-1")));
+        let _ui2 = o.subscribe(Box::new(|log| log.push("ui-2")));
+        let _late = o.subscribe_prioritized(-1, Box::new(|log| log.push("late")));
+        let _core = o.subscribe_prioritized(10, Box::new(|log| log.push("core")));
+        o.subscribe_with(1.into(), Box::new(|log| log.push("ui-3")));
+
+        let mut log = Vec::new();
+        assert!(o.trigger(None, |fun| fun(&mut log)).is_none());
+        assert_eq!(log, vec!["core", "ui-1", "ui-2", "ui-3", "late"]);
+
+        // replaced callback is ordered as a newly registered one
+        o.subscribe_with_prioritized(1.into(), 10, Box::new(|log| log.push("core-2")));
+        drop(_ui1);
+
+        let mut log = Vec::new();
+        assert!(o.trigger(None, |fun| fun(&mut log)).is_none());
+        assert_eq!(log, vec!["core", "core-2", "ui-2", "late"]);
+    }
 }
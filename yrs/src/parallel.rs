@@ -0,0 +1,189 @@
+//! Utilities for processing updates of many independent documents in parallel, powered by
+//! [rayon]. Available only with `rayon` feature flag enabled.
+
+use crate::doc::TransactionAcqError;
+use crate::encoding::read;
+use crate::updates::decoder::Decode;
+use crate::{Doc, Transact, Update, UpdateError};
+use rayon::prelude::*;
+use std::borrow::Borrow;
+use thiserror::Error;
+
+/// Error returned by [apply_updates_parallel_v1]/[apply_updates_parallel_v2] for a document,
+/// which updates could not be applied.
+#[derive(Debug, Error)]
+pub enum ApplyError {
+    /// Update at a given `index` (within the list of updates provided for the document) could not
+    /// be decoded.
+    #[error("failed to decode update at index {index}: {source}")]
+    Decode {
+        index: usize,
+        #[source]
+        source: read::Error,
+    },
+    /// Read-write transaction could not be acquired on the document.
+    #[error(transparent)]
+    Transaction(#[from] TransactionAcqError),
+    /// Update at a given `index` could not be integrated into the document.
+    #[error("failed to apply update at index {index}: {source}")]
+    Apply {
+        index: usize,
+        #[source]
+        source: UpdateError,
+    },
+}
+
+/// Applies lib0 v1 encoded updates to their corresponding documents. Updates are decoded in
+/// parallel and applied to each document in order in which they were provided, within a single
+/// transaction per document. Different documents are processed concurrently.
+///
+/// Returns a list of results - one for every document, in the same order as provided. If any of
+/// the updates of a given document could not be decoded, none of its updates will be applied.
+///
+/// This function is meant for bulk jobs like migrations of thousands of stored documents. Since
+/// documents are processed using rayon's global thread pool, observers registered on these
+/// documents will be called from that thread pool.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact};
+/// use yrs::parallel::apply_updates_parallel_v1;
+///
+/// let source = Doc::new();
+/// let text = source.get_or_insert_text("text");
+/// text.push(&mut source.transact_mut(), "hello");
+/// let update = source.transact().encode_state_as_update_v1(&StateVector::default());
+///
+/// let docs: Vec<_> = (0..10).map(|_| (Doc::new(), vec![update.clone()])).collect();
+/// let results = apply_updates_parallel_v1(&docs);
+/// assert!(results.iter().all(|r| r.is_ok()));
+///
+/// for (doc, _) in docs.iter() {
+///     let text = doc.get_or_insert_text("text");
+///     assert_eq!(text.get_string(&doc.transact()), "hello");
+/// }
+/// ```
+pub fn apply_updates_parallel_v1<D, U>(
+    docs_and_updates: &[(D, Vec<U>)],
+) -> Vec<Result<(), ApplyError>>
+where
+    D: Borrow<Doc> + Sync,
+    U: AsRef<[u8]> + Sync,
+{
+    apply_updates_parallel(docs_and_updates, Update::decode_v1)
+}
+
+/// Applies lib0 v2 encoded updates to their corresponding documents. Updates are decoded in
+/// parallel and applied to each document in order in which they were provided, within a single
+/// transaction per document. Different documents are processed concurrently.
+///
+/// See [apply_updates_parallel_v1] for more details.
+pub fn apply_updates_parallel_v2<D, U>(
+    docs_and_updates: &[(D, Vec<U>)],
+) -> Vec<Result<(), ApplyError>>
+where
+    D: Borrow<Doc> + Sync,
+    U: AsRef<[u8]> + Sync,
+{
+    apply_updates_parallel(docs_and_updates, Update::decode_v2)
+}
+
+fn apply_updates_parallel<D, U, F>(
+    docs_and_updates: &[(D, Vec<U>)],
+    decode: F,
+) -> Vec<Result<(), ApplyError>>
+where
+    D: Borrow<Doc> + Sync,
+    U: AsRef<[u8]> + Sync,
+    F: Fn(&[u8]) -> Result<Update, read::Error> + Sync,
+{
+    docs_and_updates
+        .par_iter()
+        .map(|(doc, updates)| {
+            let updates: Vec<Update> = updates
+                .par_iter()
+                .enumerate()
+                .map(|(index, update)| {
+                    decode(update.as_ref()).map_err(|source| ApplyError::Decode { index, source })
+                })
+                .collect::<Result<_, _>>()?;
+            let mut txn = doc.borrow().try_transact_mut()?;
+            for (index, update) in updates.into_iter().enumerate() {
+                txn.apply_update(update)
+                    .map_err(|source| ApplyError::Apply { index, source })?;
+            }
+            Ok(())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parallel::{apply_updates_parallel_v1, apply_updates_parallel_v2, ApplyError};
+    use crate::{Doc, GetString, ReadTxn, StateVector, Text, Transact};
+
+    #[test]
+    fn apply_in_order() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        let mut updates_v1 = Vec::new();
+        let mut updates_v2 = Vec::new();
+        for i in 0..10 {
+            let mut txn = source.transact_mut();
+            text.push(&mut txn, &i.to_string());
+            drop(txn);
+            let txn = source.transact();
+            updates_v1.push(txn.encode_state_as_update_v1(&StateVector::default()));
+            updates_v2.push(txn.encode_state_as_update_v2(&StateVector::default()));
+        }
+        let expected = text.get_string(&source.transact());
+
+        let docs: Vec<_> = (0..20)
+            .map(|i| (Doc::with_client_id(i + 100), updates_v1.clone()))
+            .collect();
+        for result in apply_updates_parallel_v1(&docs) {
+            result.unwrap();
+        }
+        for (doc, _) in docs.iter() {
+            let text = doc.get_or_insert_text("text");
+            assert_eq!(text.get_string(&doc.transact()), expected);
+        }
+
+        let docs: Vec<_> = (0..20)
+            .map(|i| (Doc::with_client_id(i + 100), updates_v2.clone()))
+            .collect();
+        for result in apply_updates_parallel_v2(&docs) {
+            result.unwrap();
+        }
+        for (doc, _) in docs.iter() {
+            let text = doc.get_or_insert_text("text");
+            assert_eq!(text.get_string(&doc.transact()), expected);
+        }
+    }
+
+    #[test]
+    fn malformed_update_is_not_applied() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        text.push(&mut source.transact_mut(), "hello");
+        let valid = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let invalid = vec![255u8, 255, 255];
+
+        let docs = vec![
+            (Doc::with_client_id(2), vec![valid.clone()]),
+            (Doc::with_client_id(3), vec![valid.clone(), invalid]),
+        ];
+        let results = apply_updates_parallel_v1(&docs);
+        assert!(results[0].is_ok());
+        match &results[1] {
+            Err(ApplyError::Decode { index, .. }) => assert_eq!(*index, 1),
+            other => panic!("expected decoding error, got: {:?}", other),
+        }
+        let doc = &docs[1].0;
+        let text = doc.get_or_insert_text("text");
+        assert_eq!(text.get_string(&doc.transact()), "");
+    }
+}
@@ -0,0 +1,140 @@
+//! `rewrite` module contains a set of auxiliary functions, which can be used to rewrite persisted
+//! document history directly on its binary [Update] representation, e.g. in order to fulfill
+//! a data removal request of a particular user.
+
+use crate::block::ClientID;
+use crate::encoding::read::Error;
+use crate::update::Update;
+use crate::updates::decoder::Decode;
+use crate::updates::encoder::Encode;
+
+/// Given an input `update` (encoded using lib0 v1 encoding), returns an equivalent lib0 v1
+/// encoded update, in which all content inserted by a given `client_id` has been removed and
+/// replaced with tombstones (see: [Update::purge_client]).
+///
+/// Documents which integrate returned update will see all changes made by other clients, while
+/// all elements inserted by purged client will be deleted. Returned update can be applied to
+/// documents which already integrated the original one: in that case purged elements will be
+/// deleted from them.
+///
+/// Returns an error whenever input update couldn't be decoded.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+/// use yrs::rewrite::purge_client;
+/// use yrs::updates::decoder::Decode;
+///
+/// let d1 = Doc::with_client_id(1);
+/// let txt1 = d1.get_or_insert_text("text");
+/// txt1.push(&mut d1.transact_mut(), "hello");
+///
+/// let d2 = Doc::with_client_id(2);
+/// let txt2 = d2.get_or_insert_text("text");
+/// let update = d1.transact().encode_state_as_update_v1(&StateVector::default());
+/// d2.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+/// txt2.push(&mut d2.transact_mut(), " world");
+///
+/// let update = d2.transact().encode_state_as_update_v1(&StateVector::default());
+/// let purged = purge_client(&update, 1).unwrap();
+///
+/// let d3 = Doc::new();
+/// let txt3 = d3.get_or_insert_text("text");
+/// d3.transact_mut().apply_update(Update::decode_v1(&purged).unwrap()).unwrap();
+/// assert_eq!(txt3.get_string(&d3.transact()), " world");
+/// ```
+pub fn purge_client(update: &[u8], client_id: ClientID) -> Result<Vec<u8>, Error> {
+    let mut update = Update::decode_v1(update)?;
+    update.purge_client(client_id);
+    Ok(update.encode_v1())
+}
+
+/// Given an input `update` (encoded using lib0 v2 encoding), returns an equivalent lib0 v2
+/// encoded update, in which all content inserted by a given `client_id` has been removed and
+/// replaced with tombstones. See [purge_client] for details.
+///
+/// Returns an error whenever input update couldn't be decoded.
+pub fn purge_client_v2(update: &[u8], client_id: ClientID) -> Result<Vec<u8>, Error> {
+    let mut update = Update::decode_v2(update)?;
+    update.purge_client(client_id);
+    Ok(update.encode_v2())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rewrite::{purge_client, purge_client_v2};
+    use crate::test_utils::exchange_updates;
+    use crate::types::ToJson;
+    use crate::updates::decoder::Decode;
+    use crate::{
+        Any, Array, Doc, GetString, Map, MapPrelim, MapRef, ReadTxn, StateVector, Text, Transact,
+        Update,
+    };
+
+    #[test]
+    fn purge_client_content() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        {
+            let text = d1.get_or_insert_text("text");
+            let map = d1.get_or_insert_map("map");
+            let mut txn = d1.transact_mut();
+            text.push(&mut txn, "secret");
+            map.insert(&mut txn, "owner", "alice");
+            map.insert(&mut txn, "nested", MapPrelim::<u32>::new());
+        }
+        exchange_updates(&[&d1, &d2]);
+        {
+            let text = d2.get_or_insert_text("text");
+            let map = d2.get_or_insert_map("map");
+            let array = d2.get_or_insert_array("array");
+            let mut txn = d2.transact_mut();
+            text.insert(&mut txn, 3, "public");
+            map.insert(&mut txn, "editor", "bob");
+            array.push_back(&mut txn, 1);
+            let nested = map.get(&txn, "nested").unwrap().cast::<MapRef>().unwrap();
+            nested.insert(&mut txn, "key", "value");
+        }
+        exchange_updates(&[&d2, &d1]);
+
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let purged = purge_client(&update, 1).unwrap();
+        assert!(!String::from_utf8_lossy(&purged).contains("secret"));
+        assert!(!String::from_utf8_lossy(&purged).contains("alice"));
+
+        let d3 = Doc::with_client_id(3);
+        d3.transact_mut()
+            .apply_update(Update::decode_v1(&purged).unwrap())
+            .unwrap();
+        let txn = d3.transact();
+        assert_eq!(txn.get_text("text").unwrap().get_string(&txn), "public");
+        assert_eq!(
+            txn.get_map("map").unwrap().to_json(&txn),
+            Any::from_json(r#"{"editor":"bob"}"#).unwrap()
+        );
+        assert_eq!(txn.get_array("array").unwrap().len(&txn), 1);
+
+        // purged update applied on a document which has seen the original content
+        d1.transact_mut()
+            .apply_update(Update::decode_v1(&purged).unwrap())
+            .unwrap();
+        let txn = d1.transact();
+        assert_eq!(txn.get_text("text").unwrap().get_string(&txn), "public");
+
+        // v2 encoding
+        let update = d2
+            .transact()
+            .encode_state_as_update_v2(&StateVector::default());
+        let purged = purge_client_v2(&update, 2).unwrap();
+        let d4 = Doc::with_client_id(4);
+        d4.transact_mut()
+            .apply_update(Update::decode_v2(&purged).unwrap())
+            .unwrap();
+        let txn = d4.transact();
+        assert_eq!(txn.get_text("text").unwrap().get_string(&txn), "secret");
+        assert_eq!(txn.get_array("array").unwrap().len(&txn), 0);
+    }
+}
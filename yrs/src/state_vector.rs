@@ -1,5 +1,5 @@
 use crate::block::ClientID;
-use crate::encoding::read::Error;
+use crate::encoding::read::{Error, MAX_PREALLOCATED_LEN};
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
@@ -7,6 +7,7 @@ use crate::{DeleteSet, ID};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
+use std::ops::Range;
 
 /// State vector is a compact representation of all known blocks inserted and integrated into
 /// a given document. This descriptor can be serialized and used to determine a difference between
@@ -107,7 +108,10 @@ impl StateVector {
 impl Decode for StateVector {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
         let len = decoder.read_var::<u32>()? as usize;
-        let mut sv = HashMap::with_capacity_and_hasher(len, BuildHasherDefault::default());
+        let mut sv = HashMap::with_capacity_and_hasher(
+            len.min(MAX_PREALLOCATED_LEN),
+            BuildHasherDefault::default(),
+        );
         let mut i = 0;
         while i < len {
             let client = decoder.read_var()?;
@@ -151,6 +155,78 @@ impl Snapshot {
     pub(crate) fn is_visible(&self, id: &ID) -> bool {
         self.state_map.get(&id.client) > id.clock && !self.delete_set.is_deleted(id)
     }
+
+    /// Calls `f` for every range of offsets within a block of `len` elements starting at a given
+    /// `id`, which elements are visible at current snapshot. Ranges are reported in order and
+    /// their offsets are relative to `id`.
+    pub(crate) fn visible_ranges<F: FnMut(Range<u32>)>(&self, id: &ID, len: u32, mut f: F) {
+        let start = id.clock;
+        let end = (start + len).min(self.state_map.get(&id.client));
+        if end <= start {
+            return;
+        }
+        let mut clock = start;
+        if let Some(deleted) = self.delete_set.range(&id.client) {
+            for r in deleted.overlapping(&(start..end)) {
+                if r.start > clock {
+                    f((clock - start)..(r.start - start));
+                }
+                clock = clock.max(r.end);
+            }
+        }
+        if clock < end {
+            f((clock - start)..(end - start));
+        }
+    }
+
+    /// Computes a difference between current snapshot and an `older` snapshot of the same
+    /// document. Returned [SnapshotDelta] can be applied onto the `older` snapshot using
+    /// [Snapshot::apply_delta] in order to recreate the current one.
+    ///
+    /// Deltas are usually much smaller than full snapshots, so they can be used to store history
+    /// of document versions as a single base snapshot followed by a sequence of deltas.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ReadTxn, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello world");
+    /// let v1 = doc.transact().snapshot();
+    ///
+    /// text.remove_range(&mut doc.transact_mut(), 0, 6);
+    /// let v2 = doc.transact().snapshot();
+    ///
+    /// let delta = v2.diff(&v1);
+    /// assert_eq!(v1.apply_delta(&delta), v2);
+    /// ```
+    pub fn diff(&self, older: &Snapshot) -> SnapshotDelta {
+        let mut state_map = StateVector::default();
+        for (&client, &clock) in self.state_map.iter() {
+            if older.state_map.get(&client) != clock {
+                state_map.set_max(client, clock);
+            }
+        }
+        let delete_set = self.delete_set.subtract(&older.delete_set);
+        SnapshotDelta {
+            state_map,
+            delete_set,
+        }
+    }
+
+    /// Returns a new snapshot, which is a result of applying a given `delta` (see:
+    /// [Snapshot::diff]) onto current snapshot.
+    pub fn apply_delta(&self, delta: &SnapshotDelta) -> Snapshot {
+        let mut state_map = self.state_map.clone();
+        for (&client, &clock) in delta.state_map.iter() {
+            state_map.set_max(client, clock);
+        }
+        let mut delete_set = self.delete_set.clone();
+        delete_set.merge(delta.delete_set.clone());
+        Snapshot::new(state_map, delete_set)
+    }
 }
 
 impl Encode for Snapshot {
@@ -167,3 +243,87 @@ impl Decode for Snapshot {
         Ok(Snapshot::new(sm, ds))
     }
 }
+
+/// Difference between two snapshots of the same document, computed using [Snapshot::diff]. It
+/// contains only the clock values of clients, which have changed between these snapshots and
+/// deletions, which happened in the meantime.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotDelta {
+    /// Deletions, which happened between older and newer snapshot.
+    pub delete_set: DeleteSet,
+    /// Clock values of clients, which have changed between older and newer snapshot.
+    pub state_map: StateVector,
+}
+
+impl SnapshotDelta {
+    /// Checks if there were no changes between snapshots, which this delta was computed from.
+    pub fn is_empty(&self) -> bool {
+        self.state_map.is_empty() && self.delete_set.is_empty()
+    }
+}
+
+impl Encode for SnapshotDelta {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        self.delete_set.encode(encoder);
+        self.state_map.encode(encoder)
+    }
+}
+
+impl Decode for SnapshotDelta {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let delete_set = DeleteSet::decode(decoder)?;
+        let state_map = StateVector::decode(decoder)?;
+        Ok(SnapshotDelta {
+            delete_set,
+            state_map,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, Options, ReadTxn, SnapshotDelta, Text, Transact};
+
+    #[test]
+    fn snapshot_delta() {
+        let options = |client_id| Options {
+            client_id,
+            skip_gc: true,
+            ..Options::default()
+        };
+        let d1 = Doc::with_options(options(1));
+        let d2 = Doc::with_options(options(2));
+        let text = d1.get_or_insert_text("text");
+        let text2 = d2.get_or_insert_text("text");
+        let mut versions = vec![d1.transact().snapshot()];
+        text.push(&mut d1.transact_mut(), "abcdefgh");
+        versions.push(d1.transact().snapshot());
+        text.remove_range(&mut d1.transact_mut(), 1, 2);
+        text2.push(&mut d2.transact_mut(), "xyz");
+        crate::test_utils::exchange_updates(&[&d1, &d2]);
+        versions.push(d1.transact().snapshot());
+        text.remove_range(&mut d1.transact_mut(), 3, 3);
+        versions.push(d1.transact().snapshot());
+        versions.push(d1.transact().snapshot());
+
+        let base = versions[0].clone();
+        let deltas: Vec<SnapshotDelta> = versions
+            .windows(2)
+            .map(|w| {
+                let delta = w[1].diff(&w[0]);
+                SnapshotDelta::decode_v1(&delta.encode_v1()).unwrap()
+            })
+            .collect();
+        assert!(deltas[3].is_empty());
+        // client 2 clock didn't change
+        assert_eq!(deltas[2].state_map.len(), 0);
+
+        let mut current = base;
+        for (delta, expected) in deltas.iter().zip(versions.iter().skip(1)) {
+            current = current.apply_delta(delta);
+            assert_eq!(&current, expected);
+        }
+    }
+}
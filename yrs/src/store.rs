@@ -1,13 +1,16 @@
 use crate::block::{BlockCell, ClientID, ItemContent, ItemPtr};
 use crate::block_store::BlockStore;
 use crate::branch::{Branch, BranchPtr};
-use crate::doc::{DocAddr, Options};
+use crate::commit::CommitLog;
+use crate::doc::{DocAddr, Options, QuotaExceeded};
 use crate::error::Error;
-use crate::event::SubdocsEvent;
+use crate::event::{SubdocUpdateEvent, SubdocsEvent};
 use crate::id_set::DeleteSet;
-use crate::slice::ItemSlice;
-use crate::types::{Path, PathSegment, TypeRef};
-use crate::update::PendingUpdate;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::slice::{BlockSlice, GCSlice, ItemSlice};
+use crate::types::{Path, PathSegment, TypePtr, TypeRef};
+use crate::update::{PendingUpdate, Update};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::{
     Doc, Observer, OffsetKind, Origin, Snapshot, TransactionCleanupEvent, TransactionMut,
@@ -16,10 +19,11 @@ use crate::{
 use crate::{StateVector, Subscription};
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut, BorrowError, BorrowMutError};
 use std::borrow::Borrow;
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Store is a core element of a document. It contains all of the information, like block store
 /// map of root types, pending updates waiting to be applied once a missing update information
@@ -32,6 +36,10 @@ pub struct Store {
     /// which can be called concurrently by remote peers in a conflict-free manner.
     pub(crate) types: HashMap<Arc<str>, Arc<Branch>>,
 
+    /// Names of root types defined since the last committed transaction, either locally or as
+    /// a result of integrating remote updates.
+    pub(crate) new_roots: Vec<Arc<str>>,
+
     /// Registry of all alive nodes in the document store.
     pub(crate) node_registry: HashSet<BranchPtr>,
 
@@ -51,6 +59,14 @@ pub struct Store {
 
     pub(crate) subdocs: HashMap<DocAddr, Doc>,
 
+    /// Clients marked via [TransactionMut::retire_client] as never going to modify current
+    /// document again.
+    pub(crate) retired_clients: HashSet<ClientID>,
+
+    /// Approximate number of bytes of content integrated into `blocks`, used to enforce
+    /// [Options::max_content_bytes]. Content of garbage collected blocks is subtracted.
+    pub(crate) content_bytes: usize,
+
     pub(crate) events: Option<Box<StoreEvents>>,
 
     /// Pointer to a parent block - present only if a current document is a sub-document of another
@@ -59,6 +75,15 @@ pub struct Store {
 
     /// Dependencies between items and weak links pointing to these items.
     pub(crate) linked_by: HashMap<ItemPtr, HashSet<BranchPtr>>,
+
+    /// Optional log of committed transactions metadata. Present only if it was explicitly
+    /// enabled via [Doc::enable_commit_log].
+    pub(crate) commit_log: Option<Box<CommitLog>>,
+
+    /// Metrics collected while integrating remote updates. Registry is created together with
+    /// the store and never replaced afterwards.
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Arc<Metrics>,
 }
 
 impl Store {
@@ -67,17 +92,41 @@ impl Store {
         Store {
             options,
             types: HashMap::default(),
+            new_roots: Vec::default(),
             node_registry: HashSet::default(),
             blocks: BlockStore::default(),
             subdocs: HashMap::default(),
+            retired_clients: HashSet::default(),
+            content_bytes: 0,
             linked_by: HashMap::default(),
             events: None,
             pending: None,
             pending_ds: None,
             parent: None,
+            commit_log: None,
+            #[cfg(feature = "metrics")]
+            metrics: Arc::default(),
         }
     }
 
+    /// Checks if adding a given number of `blocks` and `bytes` of content would exceed any of the
+    /// quotas configured via [Options::max_blocks] and [Options::max_content_bytes].
+    pub(crate) fn check_quota(&self, blocks: usize, bytes: usize) -> Result<(), QuotaExceeded> {
+        if let Some(limit) = self.options.max_blocks {
+            let requested = self.blocks.block_count() + blocks;
+            if requested > limit {
+                return Err(QuotaExceeded::Blocks { limit, requested });
+            }
+        }
+        if let Some(limit) = self.options.max_content_bytes {
+            let requested = self.content_bytes + bytes;
+            if requested > limit {
+                return Err(QuotaExceeded::ContentBytes { limit, requested });
+            }
+        }
+        Ok(())
+    }
+
     /// If there are any missing updates, this method will return a pending update which contains
     /// updates waiting for their predecessors to arrive in order to be integrated.
     pub fn pending_update(&self) -> Option<&PendingUpdate> {
@@ -90,6 +139,25 @@ impl Store {
         self.pending_ds.as_ref()
     }
 
+    /// Merges blocks, which couldn't be integrated yet, into a pending update of this store.
+    pub(crate) fn add_pending(&mut self, mut pending: PendingUpdate) {
+        if let Some(current) = self.pending.take() {
+            for (&client, &clock) in current.missing.iter() {
+                pending.missing.set_min(client, clock);
+            }
+            pending.update = Update::merge_updates(vec![current.update, pending.update]);
+        }
+        self.pending = Some(pending);
+    }
+
+    /// Merges deletions, which couldn't be applied yet, into a pending delete set of this store.
+    pub(crate) fn add_pending_ds(&mut self, ds: DeleteSet) {
+        match &mut self.pending_ds {
+            Some(pending) => pending.merge(ds),
+            None => self.pending_ds = Some(ds),
+        }
+    }
+
     pub fn is_subdoc(&self) -> bool {
         self.parent.is_some()
     }
@@ -102,6 +170,15 @@ impl Store {
         self.blocks.get_clock(&self.options.client_id)
     }
 
+    /// Resolves a `name` used to access a root-level type into a name under which that type is
+    /// stored, following [Options::root_aliases].
+    pub(crate) fn root_name(&self, name: &Arc<str>) -> Arc<str> {
+        match self.options.root_aliases.get(name) {
+            Some(stored_name) => stored_name.clone(),
+            None => name.clone(),
+        }
+    }
+
     /// Returns a branch reference to a complex type identified by its pointer. Returns `None` if
     /// no such type could be found or was ever defined.
     pub(crate) fn get_type<K: Borrow<str>>(&self, key: K) -> Option<BranchPtr> {
@@ -126,8 +203,9 @@ impl Store {
             Entry::Vacant(e) => {
                 let mut branch = Branch::new(type_ref);
                 let mut branch_ref = BranchPtr::from(&mut branch);
-                branch_ref.name = Some(key);
+                branch_ref.name = Some(key.clone());
                 self.node_registry.insert(branch_ref);
+                self.new_roots.push(key);
                 e.insert(branch);
                 branch_ref
             }
@@ -230,6 +308,138 @@ impl Store {
         }
     }
 
+    /// Encodes an update containing only blocks reachable from a given `branch`: blocks of that
+    /// branch and all of its nested collections as well as blocks of its parent collections, that
+    /// are necessary to integrate it. Blocks which are not part of the `branch` are replaced with
+    /// garbage collected placeholders, so that the update can be integrated on its own.
+    pub(crate) fn encode_branch_diff<E: Encoder>(
+        &self,
+        branch: BranchPtr,
+        sv: &StateVector,
+        encoder: &mut E,
+    ) {
+        let (included, delete_set) = self.branch_blocks(branch);
+        let local_sv = self.blocks.get_state_vector();
+        let mut diff = Self::diff_state_vectors(&local_sv, sv);
+
+        // Write items with higher client ids first
+        // This heavily improves the conflict algorithm.
+        diff.sort_by_key(|e| Reverse(e.0));
+
+        let mut clients = Vec::with_capacity(diff.len());
+        for (client, clock) in diff {
+            let blocks = self.blocks.get_client(&client).unwrap();
+            let clock = clock.max(blocks.get(0).map(|i| i.clock_start()).unwrap_or_default()); // make sure the first id exists
+            let start = blocks.find_pivot(clock).unwrap();
+            let mut slices: Vec<BlockSlice> = Vec::new();
+            let mut end = 0;
+            for i in start..blocks.len() {
+                let cell = &blocks[i];
+                let mut slice = cell.as_slice();
+                if i == start {
+                    slice.trim_start(clock - cell.clock_start());
+                }
+                let is_included = match cell {
+                    BlockCell::Block(item) => included.contains(&item.id),
+                    BlockCell::GC(_) => false,
+                };
+                if is_included {
+                    slices.push(slice);
+                    end = slices.len();
+                } else if let Some(BlockSlice::GC(gc)) = slices.last_mut() {
+                    gc.end = slice.clock_end();
+                } else {
+                    slices.push(BlockSlice::GC(GCSlice {
+                        start: slice.clock_start(),
+                        end: slice.clock_end(),
+                    }));
+                }
+            }
+            // trailing blocks which are not included can be skipped altogether
+            slices.truncate(end);
+            if !slices.is_empty() {
+                clients.push((client, slices));
+            }
+        }
+
+        encoder.write_var(clients.len());
+        for (client, slices) in clients {
+            encoder.write_var(slices.len());
+            encoder.write_client(client);
+            encoder.write_var(slices[0].clock_start());
+            for slice in slices.iter() {
+                slice.encode(encoder);
+            }
+        }
+        delete_set.encode(encoder);
+    }
+
+    /// Returns identifiers of all blocks reachable from a given `branch` together with a delete
+    /// set of these blocks. This includes blocks of parent collections containing the `branch`
+    /// and blocks they refer to as their origins.
+    fn branch_blocks(&self, branch: BranchPtr) -> (HashSet<ID>, DeleteSet) {
+        let mut included = HashSet::new();
+        let mut delete_set = DeleteSet::new();
+        let mut include = |item: ItemPtr| -> bool {
+            if included.insert(item.id) {
+                if item.is_deleted() {
+                    delete_set.insert(item.id, item.len);
+                }
+                true
+            } else {
+                false
+            }
+        };
+
+        // parent blocks are necessary to integrate a nested branch, and these in turn require
+        // the blocks they were originally inserted next to
+        let mut dependencies = Vec::new();
+        let mut current = branch.item;
+        while let Some(item) = current {
+            include(item);
+            dependencies.extend(item.origin);
+            dependencies.extend(item.right_origin);
+            current = match &item.parent {
+                TypePtr::Branch(parent) => parent.item,
+                _ => None,
+            };
+        }
+        while let Some(id) = dependencies.pop() {
+            if let Some(item) = self.blocks.get_item(&id) {
+                if include(item) {
+                    dependencies.extend(item.origin);
+                    dependencies.extend(item.right_origin);
+                }
+            }
+        }
+
+        let mut stack = vec![branch];
+        while let Some(branch) = stack.pop() {
+            let mut visit = |item: ItemPtr| {
+                if include(item) {
+                    if let ItemContent::Type(inner) = &item.content {
+                        stack.push(BranchPtr::from(inner));
+                    }
+                }
+            };
+            let mut current = branch.start;
+            while let Some(item) = current {
+                visit(item);
+                current = item.right;
+            }
+            for item in branch.map.values() {
+                // include all values ever assigned to a map entry
+                let mut current = Some(*item);
+                while let Some(item) = current {
+                    visit(item);
+                    current = item.left;
+                }
+            }
+        }
+        delete_set.squash();
+        (included, delete_set)
+    }
+
     fn diff_state_vectors(local_sv: &StateVector, remote_sv: &StateVector) -> Vec<(ClientID, u32)> {
         let mut diff = Vec::new();
         for (client, &remote_clock) in remote_sv.iter() {
@@ -340,6 +550,46 @@ impl Store {
         SubdocGuids(self.subdocs.values())
     }
 
+    /// Returns a sub document linked within the structures of this document store, which has
+    /// a given globally unique identifier.
+    pub fn subdoc(&self, guid: &Uuid) -> Option<&Doc> {
+        self.subdocs.values().find(|doc| doc.guid() == guid)
+    }
+
+    /// Checks if a given `client` has been marked as retired via [TransactionMut::retire_client].
+    pub fn is_retired(&self, client: &ClientID) -> bool {
+        self.retired_clients.contains(client)
+    }
+
+    /// Returns a storage usage report of every client, which has ever modified current document.
+    /// Reports are ordered from the clients occupying the most blocks to the ones occupying
+    /// the least.
+    pub fn client_usage(&self) -> Vec<ClientUsage> {
+        let mut result = Vec::new();
+        for (&client, blocks) in self.blocks.iter() {
+            let mut usage = ClientUsage {
+                client,
+                blocks: blocks.len(),
+                alive: 0,
+                deleted: 0,
+                collected: 0,
+                retired: self.is_retired(&client),
+            };
+            for cell in blocks.iter() {
+                let (start, end) = cell.clock_range();
+                let len = end - start + 1;
+                match cell {
+                    BlockCell::GC(_) => usage.collected += len,
+                    BlockCell::Block(item) if item.is_deleted() => usage.deleted += len,
+                    BlockCell::Block(_) => usage.alive += len,
+                }
+            }
+            result.push(usage);
+        }
+        result.sort_by(|a, b| b.blocks.cmp(&a.blocks).then(a.client.cmp(&b.client)));
+        result
+    }
+
     pub(crate) fn follow_redone(&self, id: &ID) -> Option<ItemSlice> {
         let mut next_id = Some(*id);
         let mut slice = None;
@@ -433,6 +683,12 @@ impl StoreRef {
         let store = unsafe { self.0.as_ptr().as_ref().unwrap() };
         &store.options
     }
+
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        let store = unsafe { self.0.as_ptr().as_ref().unwrap() };
+        &store.metrics
+    }
 }
 
 impl From<Store> for StoreRef {
@@ -441,6 +697,37 @@ impl From<Store> for StoreRef {
     }
 }
 
+/// Storage usage of a single client within a document store. See: [Store::client_usage].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientUsage {
+    /// Identifier of the client.
+    pub client: ClientID,
+    /// Number of blocks, which are kept in memory for this client.
+    pub blocks: usize,
+    /// Number of elements inserted by this client, which are still present in the document.
+    pub alive: u32,
+    /// Number of elements inserted by this client, which have been deleted but still keep their
+    /// tombstones in the document.
+    pub deleted: u32,
+    /// Number of elements inserted by this client, which have been garbage collected.
+    pub collected: u32,
+    /// Whether this client has been marked as retired.
+    pub retired: bool,
+}
+
+impl ClientUsage {
+    /// Returns a total number of elements ever inserted by this client. This is equal to
+    /// the clock of this client in the document's state vector.
+    pub fn len(&self) -> u32 {
+        self.alive + self.deleted + self.collected
+    }
+
+    /// Returns true if this client has not inserted any elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[repr(transparent)]
 pub struct SubdocsIter<'doc>(std::collections::hash_map::Values<'doc, DocAddr, Doc>);
 
@@ -475,6 +762,12 @@ pub type UpdateFn = Box<dyn Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 's
 pub type SubdocsFn = Box<dyn Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static>;
 #[cfg(not(target_family = "wasm"))]
 pub type DestroyFn = Box<dyn Fn(&TransactionMut, &Doc) + Send + Sync + 'static>;
+#[cfg(not(target_family = "wasm"))]
+pub type RootAddedFn = Box<dyn Fn(&TransactionMut, &str, &TypeRef) + Send + Sync + 'static>;
+#[cfg(not(target_family = "wasm"))]
+pub type SubdocUpdateFn = Box<dyn Fn(&TransactionMut, &SubdocUpdateEvent) + Send + Sync + 'static>;
+#[cfg(not(target_family = "wasm"))]
+pub type QuotaExceededFn = Box<dyn Fn(&TransactionMut, &QuotaExceeded) + Send + Sync + 'static>;
 
 #[cfg(target_family = "wasm")]
 pub type TransactionCleanupFn = Box<dyn Fn(&TransactionMut, &TransactionCleanupEvent) + 'static>;
@@ -486,6 +779,12 @@ pub type UpdateFn = Box<dyn Fn(&TransactionMut, &UpdateEvent) + 'static>;
 pub type SubdocsFn = Box<dyn Fn(&TransactionMut, &SubdocsEvent) + 'static>;
 #[cfg(target_family = "wasm")]
 pub type DestroyFn = Box<dyn Fn(&TransactionMut, &Doc) + 'static>;
+#[cfg(target_family = "wasm")]
+pub type RootAddedFn = Box<dyn Fn(&TransactionMut, &str, &TypeRef) + 'static>;
+#[cfg(target_family = "wasm")]
+pub type SubdocUpdateFn = Box<dyn Fn(&TransactionMut, &SubdocUpdateEvent) + 'static>;
+#[cfg(target_family = "wasm")]
+pub type QuotaExceededFn = Box<dyn Fn(&TransactionMut, &QuotaExceeded) + 'static>;
 
 #[derive(Default)]
 pub struct StoreEvents {
@@ -509,16 +808,109 @@ pub struct StoreEvents {
     pub subdocs_events: Observer<SubdocsFn>,
 
     pub destroy_events: Observer<DestroyFn>,
+
+    /// Handles subscriptions for events fired when new root types have been defined.
+    pub root_added_events: Observer<RootAddedFn>,
+
+    /// Handles subscriptions for v1-encoded updates of sub-documents nested within current
+    /// document.
+    pub subdoc_update_v1_events: Observer<SubdocUpdateFn>,
+
+    /// Handles subscriptions for v2-encoded updates of sub-documents nested within current
+    /// document.
+    pub subdoc_update_v2_events: Observer<SubdocUpdateFn>,
+
+    /// Handles subscriptions for events fired when changes have been rejected for exceeding
+    /// document quotas.
+    pub quota_exceeded_events: Observer<QuotaExceededFn>,
+
+    /// Update events of committed transactions, which have not been delivered yet, because update
+    /// observers have been paused. `None` if update observers are not paused.
+    deferred_updates: Mutex<Option<Vec<DeferredUpdate>>>,
+}
+
+/// Update events produced by a single transaction committed while update observers were paused.
+pub(crate) struct DeferredUpdate {
+    pub(crate) origin: Option<Origin>,
+    v1: Option<UpdateEvent>,
+    v2: Option<UpdateEvent>,
 }
 
 impl StoreEvents {
+    /// Starts deferring update events until [StoreEvents::resume_updates] is called.
+    pub(crate) fn pause_updates(&self) {
+        let mut deferred = self.deferred_updates.lock().unwrap();
+        if deferred.is_none() {
+            *deferred = Some(Vec::new());
+        }
+    }
+
+    /// Stops deferring update events, returning those that have not been delivered so far.
+    pub(crate) fn resume_updates(&self) -> Vec<DeferredUpdate> {
+        let mut deferred = self.deferred_updates.lock().unwrap();
+        deferred.take().unwrap_or_default()
+    }
+
+    /// Returns deferred update events that have not been delivered so far, while keeping update
+    /// events paused.
+    pub(crate) fn take_deferred_updates(&self) -> Vec<DeferredUpdate> {
+        let mut deferred = self.deferred_updates.lock().unwrap();
+        match deferred.as_mut() {
+            Some(updates) => std::mem::take(updates),
+            None => Vec::new(),
+        }
+    }
+
+    /// If update events are paused, stores update events produced by a given transaction for
+    /// later delivery and returns `true`. Otherwise returns `false`.
+    pub(crate) fn defer_updates(&self, txn: &TransactionMut) -> bool {
+        let mut deferred = self.deferred_updates.lock().unwrap();
+        if let Some(updates) = deferred.as_mut() {
+            if !txn.delete_set.is_empty() || txn.after_state != txn.before_state {
+                let v1 = if self.update_v1_events.has_subscribers() {
+                    Some(UpdateEvent::new_v1(txn))
+                } else {
+                    None
+                };
+                let v2 = if self.update_v2_events.has_subscribers() {
+                    Some(UpdateEvent::new_v2(txn))
+                } else {
+                    None
+                };
+                updates.push(DeferredUpdate {
+                    origin: txn.origin().cloned(),
+                    v1,
+                    v2,
+                });
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Delivers previously deferred update events of a single transaction. V1 update observers
+    /// are called before v2 ones.
+    pub(crate) fn emit_deferred_update(&self, txn: &TransactionMut, update: &DeferredUpdate) {
+        let hook = txn.store().options.callback_panic_hook.as_ref();
+        if let Some(e) = update.v1.as_ref() {
+            txn.defer_panic(self.update_v1_events.trigger(hook, |fun| fun(txn, e)));
+        }
+        if let Some(e) = update.v2.as_ref() {
+            txn.defer_panic(self.update_v2_events.trigger(hook, |fun| fun(txn, e)));
+        }
+    }
+
     pub fn emit_update_v1(&self, txn: &TransactionMut) {
         if self.update_v1_events.has_subscribers() {
             if !txn.delete_set.is_empty() || txn.after_state != txn.before_state {
                 // produce update only if anything changed
                 let update = UpdateEvent::new_v1(txn);
-                self.update_v1_events
-                    .trigger(|callback| callback(txn, &update));
+                let hook = txn.store().options.callback_panic_hook.as_ref();
+                txn.defer_panic(
+                    self.update_v1_events
+                        .trigger(hook, |callback| callback(txn, &update)),
+                );
             }
         }
     }
@@ -528,20 +920,69 @@ impl StoreEvents {
             if !txn.delete_set.is_empty() || txn.after_state != txn.before_state {
                 // produce update only if anything changed
                 let update = UpdateEvent::new_v2(txn);
-                self.update_v2_events.trigger(|fun| fun(txn, &update));
+                let hook = txn.store().options.callback_panic_hook.as_ref();
+                txn.defer_panic(self.update_v2_events.trigger(hook, |fun| fun(txn, &update)));
+            }
+        }
+    }
+
+    pub fn emit_root_added(&self, txn: &TransactionMut, names: &[Arc<str>]) {
+        if self.root_added_events.has_subscribers() {
+            let store = txn.store();
+            let hook = store.options.callback_panic_hook.as_ref();
+            for name in names {
+                if let Some(branch) = store.types.get(name) {
+                    let type_ref = &branch.type_ref;
+                    txn.defer_panic(
+                        self.root_added_events
+                            .trigger(hook, |fun| fun(txn, name, type_ref)),
+                    );
+                }
             }
         }
     }
 
+    /// Emits updates produced by a transaction `txn` of a nested sub-document. Encoded updates are
+    /// cached, so that they are produced only once for all ancestors of a sub-document.
+    pub(crate) fn emit_subdoc_update(
+        &self,
+        txn: &TransactionMut,
+        update_v1: &mut Option<SubdocUpdateEvent>,
+        update_v2: &mut Option<SubdocUpdateEvent>,
+    ) {
+        let hook = txn.store().options.callback_panic_hook.as_ref();
+        if self.subdoc_update_v1_events.has_subscribers() {
+            let e = update_v1.get_or_insert_with(|| SubdocUpdateEvent::new_v1(txn));
+            txn.defer_panic(
+                self.subdoc_update_v1_events
+                    .trigger(hook, |fun| fun(txn, e)),
+            );
+        }
+        if self.subdoc_update_v2_events.has_subscribers() {
+            let e = update_v2.get_or_insert_with(|| SubdocUpdateEvent::new_v2(txn));
+            txn.defer_panic(
+                self.subdoc_update_v2_events
+                    .trigger(hook, |fun| fun(txn, e)),
+            );
+        }
+    }
+
     pub fn emit_after_transaction(&self, txn: &mut TransactionMut) {
-        self.after_transaction_events.trigger(|fun| fun(txn));
+        let hook = txn.store().options.callback_panic_hook.clone();
+        let panic = self
+            .after_transaction_events
+            .trigger(hook.as_ref(), |fun| fun(txn));
+        txn.defer_panic(panic);
     }
 
     pub fn emit_transaction_cleanup(&self, txn: &TransactionMut) {
         if self.transaction_cleanup_events.has_subscribers() {
             let event = TransactionCleanupEvent::new(txn);
-            self.transaction_cleanup_events
-                .trigger(|fun| fun(txn, &event));
+            let hook = txn.store().options.callback_panic_hook.as_ref();
+            txn.defer_panic(
+                self.transaction_cleanup_events
+                    .trigger(hook, |fun| fun(txn, &event)),
+            );
         }
     }
 }
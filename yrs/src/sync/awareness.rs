@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::block::ClientID;
+use crate::observer::{kind, ObserverKey};
 use crate::sync::{Clock, Timestamp};
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
@@ -80,28 +81,28 @@ impl Awareness {
     #[cfg(not(target_family = "wasm"))]
     pub fn on_update_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AwarenessUpdate>,
         F: Fn(&Awareness, &Event, Option<&Origin>) + Send + Sync + 'static,
     {
-        self.on_update.subscribe_with(key.into(), Box::new(f))
+        self.on_update.subscribe_with_key(key, Box::new(f))
     }
 
     /// Returns a channel receiver for an incoming awareness events. This channel can be cloned.
     #[cfg(target_family = "wasm")]
     pub fn on_update_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AwarenessUpdate>,
         F: Fn(&Awareness, &Event, Option<&Origin>) + 'static,
     {
-        self.on_update.subscribe_with(key.into(), Box::new(f))
+        self.on_update.subscribe_with_key(key, Box::new(f))
     }
 
     /// Returns a channel receiver for an incoming awareness events. This channel can be cloned.
     pub fn unobserve_update<K>(&self, key: K) -> bool
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AwarenessUpdate>,
     {
-        self.on_update.unsubscribe(&key.into())
+        self.on_update.unsubscribe(&key.into_origin())
     }
 
     /// Returns a channel receiver for an incoming awareness events. This channel can be cloned.
@@ -117,28 +118,28 @@ impl Awareness {
     #[cfg(not(target_family = "wasm"))]
     pub fn on_change_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AwarenessChange>,
         F: Fn(&Awareness, &Event, Option<&Origin>) + Send + Sync + 'static,
     {
-        self.on_change.subscribe_with(key.into(), Box::new(f))
+        self.on_change.subscribe_with_key(key, Box::new(f))
     }
 
     /// Returns a channel receiver for an incoming awareness events. This channel can be cloned.
     #[cfg(target_family = "wasm")]
     pub fn on_change_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AwarenessChange>,
         F: Fn(&Awareness, &Event, Option<&Origin>) + 'static,
     {
-        self.on_change.subscribe_with(key.into(), Box::new(f))
+        self.on_change.subscribe_with_key(key, Box::new(f))
     }
 
     /// Returns a channel receiver for an incoming awareness events. This channel can be cloned.
     pub fn unobserve_change<K>(&self, key: K) -> bool
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::AwarenessChange>,
     {
-        self.on_change.unsubscribe(&key.into())
+        self.on_change.unsubscribe(&key.into_origin())
     }
 
     /// Returns a read-only reference to an underlying [Doc].
@@ -201,8 +202,12 @@ impl Awareness {
         let is_removed = self.states.remove(&client_id).is_some();
         if is_removed && self.on_update.has_subscribers() || self.on_change.has_subscribers() {
             let e = Event::new(Vec::default(), Vec::default(), vec![client_id]);
-            self.on_change.trigger(|fun| fun(self, &e, None));
-            self.on_update.trigger(|fun| fun(self, &e, None));
+            let hook = self.doc.options().callback_panic_hook.as_ref();
+            let change_panic = self.on_change.trigger(hook, |fun| fun(self, &e, None));
+            let update_panic = self.on_update.trigger(hook, |fun| fun(self, &e, None));
+            if let Some(payload) = change_panic.or(update_panic) {
+                std::panic::resume_unwind(payload);
+            }
         }
     }
 
@@ -249,12 +254,18 @@ impl Awareness {
                 }
             }
             let mut e = Event::new(added, changed, Vec::default());
+            let hook = self.doc.options().callback_panic_hook.as_ref();
+            let mut change_panic = None;
             if !e.is_empty() {
-                self.on_change.trigger(|fun| fun(self, &e, None));
+                change_panic = self.on_change.trigger(hook, |fun| fun(self, &e, None));
             }
             e.summary.updated = updated;
+            let mut update_panic = None;
             if !e.is_empty() {
-                self.on_update.trigger(|fun| fun(self, &e, None));
+                update_panic = self.on_update.trigger(hook, |fun| fun(self, &e, None));
+            }
+            if let Some(payload) = change_panic.or(update_panic) {
+                std::panic::resume_unwind(payload);
             }
         }
     }
@@ -428,11 +439,20 @@ impl Awareness {
         if !added.is_empty() || !updated.is_empty() || !removed.is_empty() {
             let summary = if self.on_update.has_subscribers() || self.on_change.has_subscribers() {
                 let mut e = Event::new(added, changed, removed);
+                let hook = self.doc.options().callback_panic_hook.as_ref();
+                let mut change_panic = None;
                 if !e.is_empty() {
-                    self.on_change.trigger(|fun| fun(self, &e, origin.as_ref()));
+                    change_panic = self
+                        .on_change
+                        .trigger(hook, |fun| fun(self, &e, origin.as_ref()));
                 }
                 e.summary.updated = updated;
-                self.on_update.trigger(|fun| fun(self, &e, origin.as_ref()));
+                let update_panic = self
+                    .on_update
+                    .trigger(hook, |fun| fun(self, &e, origin.as_ref()));
+                if let Some(payload) = change_panic.or(update_panic) {
+                    std::panic::resume_unwind(payload);
+                }
                 e.summary
             } else {
                 AwarenessUpdateSummary {
@@ -11,4 +11,5 @@ pub use crate::sync::protocol::MessageReader;
 pub use crate::sync::protocol::Protocol;
 pub use crate::sync::protocol::SyncMessage;
 pub use crate::sync::time::Clock;
+pub use crate::sync::time::ManualClock;
 pub use crate::sync::time::Timestamp;
@@ -1,8 +1,9 @@
+use crate::codec::{self, CodecError};
 use crate::encoding::read;
 use crate::sync::{awareness, Awareness, AwarenessUpdate};
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
-use crate::{ReadTxn, StateVector, Transact, Update};
+use crate::{ReadTxn, StateVector, Transact, Update, UpdateError};
 use thiserror::Error;
 
 /*
@@ -76,7 +77,7 @@ pub trait Protocol {
         update: Update,
     ) -> Result<Option<Message>, Error> {
         let mut txn = awareness.doc().transact_mut();
-        txn.apply_update(update);
+        txn.apply_update(update)?;
         Ok(None)
     }
 
@@ -122,6 +123,30 @@ pub trait Protocol {
         Ok(None)
     }
 
+    /// Dispatches an incoming `msg` to a corresponding handler method of current protocol. Returns
+    /// a reply message which should be send back to the sender, if any.
+    fn handle_msg(
+        &self,
+        awareness: &mut Awareness,
+        msg: Message,
+    ) -> Result<Option<Message>, Error> {
+        match msg {
+            Message::Sync(SyncMessage::SyncStep1(sv)) => self.handle_sync_step1(awareness, sv),
+            Message::Sync(SyncMessage::SyncStep2(update)) => {
+                let update = codec::open(awareness.doc().options().codec.as_ref(), &update)?;
+                self.handle_sync_step2(awareness, Update::decode_v1(&update)?)
+            }
+            Message::Sync(SyncMessage::Update(update)) => {
+                let update = codec::open(awareness.doc().options().codec.as_ref(), &update)?;
+                self.handle_update(awareness, Update::decode_v1(&update)?)
+            }
+            Message::Auth(deny_reason) => self.handle_auth(awareness, deny_reason),
+            Message::AwarenessQuery => self.handle_awareness_query(awareness),
+            Message::Awareness(update) => self.handle_awareness_update(awareness, update),
+            Message::Custom(tag, data) => self.missing_handle(awareness, tag, data),
+        }
+    }
+
     /// Y-sync protocol enables to extend its own settings with custom handles. These can be
     /// implemented here. By default it returns an [Error::Unsupported].
     fn missing_handle(
@@ -278,6 +303,15 @@ pub enum Error {
     #[error("failed to deserialize message: {0}")]
     DecodingError(#[from] read::Error),
 
+    /// Incoming document update couldn't be applied.
+    #[error("failed to apply update: {0}")]
+    InvalidUpdate(#[from] UpdateError),
+
+    /// Incoming document update envelope couldn't be opened by a document codec (see:
+    /// [Options::codec](crate::Options::codec)).
+    #[error("failed to open update: {0}")]
+    Codec(#[from] CodecError),
+
     /// Applying incoming Y-protocol awareness update has failed.
     #[error("failed to process awareness update: {0}")]
     AwarenessEncoding(#[from] awareness::Error),
@@ -383,6 +417,83 @@ mod test {
         }
     }
 
+    /// Binary payloads exchanged by y-websocket provider (yjs v13) with a server.
+    mod golden {
+        /// sync-step-1 with an empty state vector.
+        pub const SYNC_STEP_1_EMPTY: &[u8] = &[0, 0, 1, 0];
+        /// sync-step-2 with an empty update.
+        pub const SYNC_STEP_2_EMPTY: &[u8] = &[0, 1, 2, 0, 0];
+        /// Update message produced by client `1` inserting "hello" into an empty `Y.Text` named
+        /// "test".
+        pub const UPDATE_HELLO: &[u8] = &[
+            0, 2, 18, 1, 1, 1, 0, 4, 1, 4, b't', b'e', b's', b't', 5, b'h', b'e', b'l', b'l', b'o',
+            0,
+        ];
+        /// sync-step-1 with a state vector `{1: 5}`.
+        pub const SYNC_STEP_1_HELLO: &[u8] = &[0, 0, 3, 1, 1, 5];
+        /// Awareness update of client `1` with clock `1` and state `{}`.
+        pub const AWARENESS_EMPTY_STATE: &[u8] = &[1, 6, 1, 1, 1, 2, b'{', b'}'];
+        /// Awareness query.
+        pub const QUERY_AWARENESS: &[u8] = &[3];
+    }
+
+    #[test]
+    fn golden_messages() {
+        use crate::sync::{Message, SyncMessage};
+
+        let doc = Doc::with_client_id(1);
+        let protocol = crate::sync::DefaultProtocol;
+        let mut encoder = EncoderV1::new();
+        protocol
+            .start(&Awareness::new(doc.clone()), &mut encoder)
+            .unwrap();
+        let data = encoder.to_vec();
+        assert!(data.starts_with(golden::SYNC_STEP_1_EMPTY));
+
+        let mut awareness = Awareness::new(doc);
+        let reply = protocol
+            .handle_msg(
+                &mut awareness,
+                Message::decode_v1(golden::SYNC_STEP_1_EMPTY).unwrap(),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply.encode_v1(), golden::SYNC_STEP_2_EMPTY);
+
+        // apply update coming from yjs client
+        let msg = Message::decode_v1(golden::UPDATE_HELLO).unwrap();
+        assert!(protocol.handle_msg(&mut awareness, msg).unwrap().is_none());
+        let txt = awareness.doc().get_or_insert_text("test");
+        assert_eq!(txt.get_string(&awareness.doc().transact()), "hello");
+
+        // produce the same bytes as yjs client would
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut txn = doc.transact_mut();
+        txt.push(&mut txn, "hello");
+        let msg = Message::Sync(SyncMessage::Update(txn.encode_update_v1()));
+        assert_eq!(msg.encode_v1(), golden::UPDATE_HELLO);
+        let msg = Message::Sync(SyncMessage::SyncStep1(txn.state_vector()));
+        assert_eq!(msg.encode_v1(), golden::SYNC_STEP_1_HELLO);
+        drop(txn);
+
+        let mut awareness = Awareness::new(doc);
+        awareness.set_local_state_raw("{}".to_string());
+        let msg = Message::Awareness(awareness.update().unwrap());
+        assert_eq!(msg.encode_v1(), golden::AWARENESS_EMPTY_STATE);
+        let decoded = Message::decode_v1(golden::AWARENESS_EMPTY_STATE).unwrap();
+        assert_eq!(decoded, msg);
+
+        let reply = protocol
+            .handle_msg(
+                &mut awareness,
+                Message::decode_v1(golden::QUERY_AWARENESS).unwrap(),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply.encode_v1(), golden::AWARENESS_EMPTY_STATE);
+    }
+
     #[test]
     fn protocol_init() {
         let awareness = Awareness::default();
@@ -1,3 +1,6 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
 /// Timestamp used by [crate::sync::Awareness] to tag most recent updates.
 pub type Timestamp = u64;
 
@@ -30,3 +33,62 @@ impl Clock for SystemClock {
             .as_millis() as Timestamp
     }
 }
+
+/// A clock which is advanced only manually. It can be used to make time-dependent components -
+/// like [UndoManager](crate::UndoManager) grouping changes within
+/// [capture timeout](crate::undo::Options::capture_timeout_millis) - deterministic, eg. in tests
+/// or simulations. Cloned instances share the same time.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use yrs::{Doc, GetString, Text, Transact, UndoManager};
+/// use yrs::sync::time::ManualClock;
+/// use yrs::undo::Options;
+///
+/// let doc = Doc::new();
+/// let text = doc.get_or_insert_text("text");
+/// let clock = ManualClock::new(1);
+/// let mut options = Options::default();
+/// options.timestamp = Arc::new(clock.clone());
+/// let mut mgr: UndoManager = UndoManager::with_options(&doc, &text, options);
+///
+/// text.push(&mut doc.transact_mut(), "a");
+/// clock.advance(100);
+/// text.push(&mut doc.transact_mut(), "b"); // within capture timeout: merged with previous change
+/// clock.advance(1000);
+/// text.push(&mut doc.transact_mut(), "c");
+///
+/// mgr.undo().unwrap();
+/// assert_eq!(text.get_string(&doc.transact()), "ab");
+/// mgr.undo().unwrap();
+/// assert_eq!(text.get_string(&doc.transact()), "");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock(Arc<AtomicU64>);
+
+impl ManualClock {
+    /// Creates a new clock starting at a given timestamp. Keep in mind that
+    /// [UndoManager](crate::UndoManager) treats `0` as an absence of previous changes, so clocks
+    /// used by it should start from a positive value.
+    pub fn new(start: Timestamp) -> Self {
+        ManualClock(Arc::new(AtomicU64::new(start)))
+    }
+
+    /// Sets current time of this clock (and all of its clones) to a given timestamp.
+    pub fn set(&self, now: Timestamp) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves current time of this clock (and all of its clones) by a given number of milliseconds.
+    pub fn advance(&self, millis: u64) {
+        self.0.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        self.0.load(Ordering::SeqCst)
+    }
+}
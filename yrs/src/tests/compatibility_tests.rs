@@ -122,7 +122,7 @@ fn text_insert_delete() {
     {
         let mut txn = doc.transact_mut();
         let u = Update::decode_v1(update).unwrap();
-        txn.apply_update(u);
+        txn.apply_update(u).unwrap();
     }
     assert_eq!(txt.get_string(&doc.transact()), "abhi".to_string());
     assert!(visited.load(Ordering::Relaxed));
@@ -337,16 +337,16 @@ fn utf32_lib0_v2_decoding() {
     let xml = doc.get_or_insert_xml_fragment("prosemirror");
     let mut txn = doc.transact_mut();
     let update = Update::decode_v2(data).unwrap();
-    txn.apply_update(update);
+    txn.apply_update(update).unwrap();
     let actual: XmlElementRef = xml.get(&txn, 0).unwrap().try_into().unwrap();
 
     let expected_attrs = HashMap::from([
-        ("b_id", "JXbASa-a92j".to_string()),
-        ("indent", "0".to_string()),
-        ("tagName", "div".to_string()),
-        ("lineHeight", "".to_string()),
+        ("b_id", Any::from("JXbASa-a92j")),
+        ("indent", Any::from(0.0)),
+        ("tagName", Any::from("div")),
+        ("lineHeight", Any::from("")),
     ]);
-    let actual_attrs: HashMap<&str, String> = actual.attributes(&txn).collect();
+    let actual_attrs: HashMap<&str, Any> = actual.attributes(&txn).collect();
     assert_eq!(actual_attrs, expected_attrs);
 
     let txt: XmlTextRef = actual.get(&txn, 0).unwrap().try_into().unwrap();
@@ -412,7 +412,7 @@ fn negative_zero_decoding_v2() {
     let doc2 = Doc::new();
     let root = doc2.get_or_insert_map("root");
     let mut txn = doc2.transact_mut();
-    txn.apply_update(u);
+    txn.apply_update(u).unwrap();
     let actual = root.to_json(&txn);
 
     assert_eq!(actual, expected);
@@ -445,7 +445,7 @@ fn test_data_set<P: AsRef<std::path::Path>>(path: P) {
         let arr = doc.get_or_insert_array("array");
         for _ in 0..updates_len {
             let update = Update::decode_v1(decoder.read_buf().unwrap()).unwrap();
-            doc.transact_mut().apply_update(update);
+            doc.transact_mut().apply_update(update).unwrap();
         }
         let expected = decoder.read_string().unwrap();
         assert_eq!(
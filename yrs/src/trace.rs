@@ -0,0 +1,284 @@
+use crate::block::ClientID;
+use crate::doc::TransactionAcqError;
+use crate::encoding::read::{Error, MAX_PREALLOCATED_LEN};
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
+use crate::{Doc, Origin, StateVector, Subscription, Transact, Update, UpdateError};
+use atomic_refcell::BorrowMutError;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// A single recorded transaction, which is a part of a [DocTrace].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// Client identifier of a document, on which the transaction has been recorded.
+    pub client_id: ClientID,
+    /// Origin of the recorded transaction. Only binary origins are preserved when a trace is
+    /// encoded - typed origins (see: [Origin::new]) are skipped.
+    pub origin: Option<Origin>,
+    /// State of the recorded document at the moment when transaction has started.
+    pub before_state: StateVector,
+    /// Changes committed by the recorded transaction, encoded using lib0 v1 encoding.
+    pub update: Vec<u8>,
+}
+
+impl TraceStep {
+    /// Decodes the changes committed by the recorded transaction.
+    pub fn decode_update(&self) -> Result<Update, Error> {
+        Update::decode_v1(&self.update)
+    }
+
+    /// Applies the changes of this step to a given document, within a single transaction using
+    /// the same origin as the recorded one.
+    pub fn apply_to(&self, doc: &Doc) -> Result<(), ReplayError> {
+        let update = self.decode_update()?;
+        let mut txn = match &self.origin {
+            Some(origin) => doc.try_transact_mut_with(origin.clone())?,
+            None => doc.try_transact_mut()?,
+        };
+        txn.apply_update(update)?;
+        Ok(())
+    }
+}
+
+impl Encode for TraceStep {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        encoder.write_var(self.client_id);
+        match &self.origin {
+            Some(origin) if origin.type_id().is_none() => {
+                encoder.write_u8(1);
+                encoder.write_buf(origin);
+            }
+            _ => encoder.write_u8(0),
+        }
+        self.before_state.encode(encoder);
+        encoder.write_buf(&self.update);
+    }
+}
+
+impl Decode for TraceStep {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let client_id = decoder.read_var()?;
+        let origin = match decoder.read_u8()? {
+            0 => None,
+            _ => Some(Origin::from(decoder.read_buf()?)),
+        };
+        let before_state = StateVector::decode(decoder)?;
+        let update = decoder.read_buf()?.to_vec();
+        Ok(TraceStep {
+            client_id,
+            origin,
+            before_state,
+            update,
+        })
+    }
+}
+
+/// Error returned when a [DocTrace] could not be replayed.
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    /// Update of one of the trace steps could not be decoded.
+    #[error("failed to decode trace step update: {0}")]
+    Decode(#[from] Error),
+    /// Read-write transaction could not be acquired on the target document.
+    #[error(transparent)]
+    Transaction(#[from] TransactionAcqError),
+    /// Update of one of the trace steps could not be integrated into the target document.
+    #[error("failed to apply trace step update: {0}")]
+    Apply(#[from] UpdateError),
+}
+
+/// Deterministic trace of transactions committed on a document. It can be used to record all
+/// changes happening on a document (both local and remote ones) in the order in which they were
+/// committed, and then replay them - all at once or step by step - on another document.
+///
+/// Traces can be encoded using [Encode] and attached to bug reports, e.g. when investigating
+/// divergence between document replicas.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, DocTrace, GetString, Text, Transact};
+/// use yrs::updates::decoder::Decode;
+/// use yrs::updates::encoder::Encode;
+///
+/// let doc = Doc::new();
+/// let trace = DocTrace::record(&doc).unwrap();
+///
+/// let text = doc.get_or_insert_text("text");
+/// text.push(&mut doc.transact_mut(), "hello");
+/// text.push(&mut doc.transact_mut_with("user"), " world");
+///
+/// let trace = DocTrace::decode_v1(&trace.encode_v1()).unwrap();
+/// assert_eq!(trace.len(), 2);
+///
+/// let doc2 = Doc::new();
+/// let text2 = doc2.get_or_insert_text("text");
+/// trace.replay_step(&doc2, 0).unwrap();
+/// assert_eq!(text2.get_string(&doc2.transact()), "hello");
+///
+/// trace.replay_step(&doc2, 1).unwrap();
+/// assert_eq!(text2.get_string(&doc2.transact()), "hello world");
+/// ```
+#[derive(Default)]
+pub struct DocTrace {
+    steps: Arc<Mutex<Vec<TraceStep>>>,
+    subscription: Option<Subscription>,
+}
+
+impl DocTrace {
+    /// Creates a new trace, which will record all transactions committed on a given `doc` from
+    /// now on, until [DocTrace::stop] is called or trace is dropped.
+    pub fn record(doc: &Doc) -> Result<Self, BorrowMutError> {
+        let steps: Arc<Mutex<Vec<TraceStep>>> = Arc::default();
+        let sink = steps.clone();
+        let client_id = doc.client_id();
+        let subscription = doc.observe_update_v1(move |txn, e| {
+            let step = TraceStep {
+                client_id,
+                origin: txn.origin().cloned(),
+                before_state: txn.before_state().clone(),
+                update: e.update.clone(),
+            };
+            sink.lock().unwrap().push(step);
+        })?;
+        Ok(DocTrace {
+            steps,
+            subscription: Some(subscription),
+        })
+    }
+
+    /// Stops recording of new transactions. Steps recorded so far are preserved.
+    pub fn stop(&mut self) {
+        self.subscription = None;
+    }
+
+    /// Returns true if this trace is still recording new transactions.
+    pub fn is_recording(&self) -> bool {
+        self.subscription.is_some()
+    }
+
+    /// Returns a number of steps recorded so far.
+    pub fn len(&self) -> usize {
+        self.steps.lock().unwrap().len()
+    }
+
+    /// Returns true if no steps have been recorded so far.
+    pub fn is_empty(&self) -> bool {
+        self.steps.lock().unwrap().is_empty()
+    }
+
+    /// Returns a copy of the step at a given `index`, if it exists.
+    pub fn step(&self, index: usize) -> Option<TraceStep> {
+        self.steps.lock().unwrap().get(index).cloned()
+    }
+
+    /// Returns a copy of all steps recorded so far.
+    pub fn steps(&self) -> Vec<TraceStep> {
+        self.steps.lock().unwrap().clone()
+    }
+
+    /// Applies the step at a given `index` to a `doc`. Returns `false` if there was no such step.
+    pub fn replay_step(&self, doc: &Doc, index: usize) -> Result<bool, ReplayError> {
+        match self.step(index) {
+            Some(step) => {
+                step.apply_to(doc)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Applies all recorded steps to a `doc`, each in a separate transaction, in the same order
+    /// in which they were recorded.
+    pub fn replay_into(&self, doc: &Doc) -> Result<(), ReplayError> {
+        for step in self.steps() {
+            step.apply_to(doc)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for DocTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocTrace")
+            .field("steps", &self.steps)
+            .field("recording", &self.is_recording())
+            .finish()
+    }
+}
+
+impl Encode for DocTrace {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        let steps = self.steps.lock().unwrap();
+        encoder.write_var(steps.len());
+        for step in steps.iter() {
+            step.encode(encoder);
+        }
+    }
+}
+
+impl Decode for DocTrace {
+    /// Decodes a trace. Decoded trace is not recording any new transactions.
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let len: usize = decoder.read_var()?;
+        let mut steps = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..len {
+            steps.push(TraceStep::decode(decoder)?);
+        }
+        Ok(DocTrace {
+            steps: Arc::new(Mutex::new(steps)),
+            subscription: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::trace::DocTrace;
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, GetString, Map, Origin, ReadTxn, Text, Transact, Update};
+
+    #[test]
+    fn record_and_replay() {
+        let d1 = Doc::with_client_id(1);
+        let mut trace = DocTrace::record(&d1).unwrap();
+        let text = d1.get_or_insert_text("text");
+        let map = d1.get_or_insert_map("map");
+
+        text.insert(&mut d1.transact_mut_with("a"), 0, "abc");
+        map.insert(&mut d1.transact_mut_with(Origin::new(1u32)), "key", "value");
+
+        // remote changes are recorded as well
+        let d2 = Doc::with_client_id(2);
+        let text2 = d2.get_or_insert_text("text");
+        text2.insert(&mut d2.transact_mut(), 0, "xyz");
+        let update = d2.transact().encode_state_as_update_v1(&Default::default());
+        d1.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+
+        let expected = text.get_string(&d1.transact());
+        trace.stop();
+        text.insert(&mut d1.transact_mut(), 0, "not recorded");
+        assert!(!trace.is_recording());
+        assert_eq!(trace.len(), 3);
+
+        let decoded = DocTrace::decode_v1(&trace.encode_v1()).unwrap();
+        let steps = decoded.steps();
+        assert_eq!(steps[0].origin, Some("a".into()));
+        assert_eq!(steps[1].origin, None); // typed origins are not encoded
+        assert_eq!(steps[1].before_state, trace.steps()[1].before_state);
+        assert_eq!(trace.steps()[1].origin, Some(Origin::new(1u32)));
+
+        let d3 = Doc::with_client_id(3);
+        decoded.replay_into(&d3).unwrap();
+
+        let txn = d3.transact();
+        let text3 = txn.get_text("text").unwrap();
+        let map3 = txn.get_map("map").unwrap();
+        assert_eq!(text3.get_string(&txn), expected);
+        assert_eq!(map3.get(&txn, "key"), Some("value".into()));
+    }
+}
@@ -1,5 +1,7 @@
-use crate::block::{Item, ItemContent, ItemPtr, Prelim, ID};
-use crate::branch::{Branch, BranchPtr};
+use crate::block::{BlockCell, ClientID, Item, ItemContent, ItemPtr, Prelim, ID};
+use crate::branch::{Branch, BranchPtr, VisibleSequence};
+use crate::codec::{self, CodecError};
+use crate::commit::{CommitInfo, CommitLog};
 use crate::doc::DocAddr;
 use crate::error::Error;
 use crate::event::SubdocsEvent;
@@ -8,18 +10,22 @@ use crate::id_set::DeleteSet;
 use crate::iter::TxnIterator;
 use crate::slice::BlockSlice;
 use crate::store::{Store, StoreEvents, SubdocGuids, SubdocsIter};
-use crate::types::{Event, Events, RootRef, SharedRef, TypePtr, Value};
-use crate::update::Update;
+use crate::types::{
+    keyed_list, set, EditError, Event, Events, RootRef, SharedRef, TypePtr, TypeRef, Value,
+};
+use crate::update::{ApplyProgress, IntegrationOutcome, Update, UpdateError};
+use crate::updates::decoder::Decode;
 use crate::utils::OptionExt;
 use crate::*;
 use atomic_refcell::{AtomicRef, AtomicRefMut};
 use smallvec::SmallVec;
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Formatter;
 use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use updates::encoder::*;
 
 /// Trait defining read capabilities present in a transaction. Implemented by both lightweight
@@ -32,6 +38,13 @@ pub trait ReadTxn: Sized {
         self.store().blocks.get_state_vector()
     }
 
+    /// Returns a snapshot, which this transaction's reads are gated by. See [Doc::transact_at].
+    ///
+    /// Returns `None` for regular transactions, which read the latest state of the document.
+    fn gated_snapshot(&self) -> Option<&SnapshotGate> {
+        None
+    }
+
     /// Returns a snapshot which describes a current state of updates and removals made within
     /// the corresponding document.
     fn snapshot(&self) -> Snapshot {
@@ -58,16 +71,22 @@ pub trait ReadTxn: Sized {
         self.store().encode_diff(state_vector, encoder)
     }
 
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v1 encoding. Result is sealed with
+    /// [Options::codec](crate::Options::codec) if document has one configured.
     fn encode_diff_v1(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV1::new();
         self.encode_diff(state_vector, &mut encoder);
-        encoder.to_vec()
+        codec::seal(self.store().options.codec.as_ref(), encoder.to_vec())
     }
 
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v2 encoding. Result is sealed with
+    /// [Options::codec](crate::Options::codec) if document has one configured.
     fn encode_diff_v2(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV2::new();
         self.encode_diff(state_vector, &mut encoder);
-        encoder.to_vec()
+        codec::seal(self.store().options.codec.as_ref(), encoder.to_vec())
     }
 
     fn encode_state_as_update<E: Encoder>(&self, sv: &StateVector, encoder: &mut E) {
@@ -77,16 +96,91 @@ pub trait ReadTxn: Sized {
         ds.encode(encoder);
     }
 
+    /// Encodes the state of a document missing from a given state vector `sv` as an update using
+    /// lib0 v1 encoding. Result is sealed with [Options::codec](crate::Options::codec) if
+    /// document has one configured.
     fn encode_state_as_update_v1(&self, sv: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV1::new();
         self.encode_state_as_update(sv, &mut encoder);
-        encoder.to_vec()
+        codec::seal(self.store().options.codec.as_ref(), encoder.to_vec())
     }
 
+    /// Encodes the state of a document missing from a given state vector `sv` as an update using
+    /// lib0 v2 encoding. Result is sealed with [Options::codec](crate::Options::codec) if
+    /// document has one configured.
     fn encode_state_as_update_v2(&self, sv: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV2::new();
         self.encode_state_as_update(sv, &mut encoder);
-        encoder.to_vec()
+        codec::seal(self.store().options.codec.as_ref(), encoder.to_vec())
+    }
+
+    /// Same as [ReadTxn::encode_state_as_update_v1], but instead of building an entire update in
+    /// memory, it's written directly into a given `writer` (eg. file or network socket) in
+    /// chunks. This is useful when exporting very large documents in memory-constrained
+    /// environments. See [StreamEncoderV1] for details. If document has
+    /// [Options::codec](crate::Options::codec) configured, the update is sealed as a whole, so it
+    /// has to be built in memory before being written.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ReadTxn, StateVector, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello world");
+    ///
+    /// let txn = doc.transact();
+    /// let mut file = Vec::new(); // any std::io::Write implementation
+    /// txn.encode_state_as_update_to(&StateVector::default(), &mut file).unwrap();
+    /// assert_eq!(file, txn.encode_state_as_update_v1(&StateVector::default()));
+    /// ```
+    fn encode_state_as_update_to<W: std::io::Write>(
+        &self,
+        sv: &StateVector,
+        writer: W,
+    ) -> std::io::Result<()> {
+        if self.store().options.codec.is_some() {
+            let mut writer = writer;
+            return writer.write_all(&self.encode_state_as_update_v1(sv));
+        }
+        let mut encoder = StreamEncoderV1::new(writer);
+        self.encode_state_as_update(sv, &mut encoder);
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Same as [ReadTxn::encode_state_as_update_v1], but instead of allocating a new buffer,
+    /// the update is appended at the end of a given `buf`. This allows to reuse the same buffer
+    /// across many encodings.
+    fn encode_state_as_update_v1_into(&self, sv: &StateVector, buf: &mut Vec<u8>) {
+        if self.store().options.codec.is_some() {
+            buf.extend_from_slice(&self.encode_state_as_update_v1(sv));
+            return;
+        }
+        let mut encoder = EncoderV1::with_buffer(std::mem::take(buf));
+        self.encode_state_as_update(sv, &mut encoder);
+        *buf = encoder.to_vec();
+    }
+
+    /// Same as [ReadTxn::encode_state_as_update_v2], but instead of allocating a new buffer,
+    /// the update is appended at the end of a given `buf`. For high-throughput scenarios
+    /// consider using [EncoderPool](crate::updates::encoder::EncoderPool), which allows to reuse
+    /// intermediate buffers of [EncoderV2] as well.
+    fn encode_state_as_update_v2_into(&self, sv: &StateVector, buf: &mut Vec<u8>) {
+        if self.store().options.codec.is_some() {
+            buf.extend_from_slice(&self.encode_state_as_update_v2(sv));
+            return;
+        }
+        let mut encoder = EncoderV2::new();
+        self.encode_state_as_update(sv, &mut encoder);
+        encoder.drain_into(buf);
+    }
+
+    /// Returns a log of recently committed transactions, if it was enabled for the current document
+    /// (see: [Doc::enable_commit_log]).
+    fn commit_log(&self) -> Option<&CommitLog> {
+        self.store().commit_log.as_deref()
     }
 
     /// Check if given node is alive. Returns false if node has been deleted.
@@ -98,6 +192,35 @@ pub trait ReadTxn: Sized {
         self.store().is_alive(&ptr)
     }
 
+    /// Returns a shared collection identified by a given logical `id`. Unlike [BranchPtr]s,
+    /// [BranchID]s are stable across document replicas and reloads, so they can be persisted
+    /// and resolved later on. Returns `None` if no such collection exists in current document
+    /// (root-level collections must have been defined or received from a remote peer first)
+    /// or if it has been deleted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, MapPrelim, ReadTxn, Transact, TextPrelim, TextRef};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let text = map.insert(&mut doc.transact_mut(), "text", TextPrelim::new("hello"));
+    /// let id = text.as_ref().id();
+    ///
+    /// let txn = doc.transact();
+    /// let branch = txn.branch_by_id(&id).unwrap();
+    /// assert_eq!(TextRef::from(branch), text);
+    /// ```
+    fn branch_by_id(&self, id: &BranchID) -> Option<BranchPtr> {
+        let branch = id.get_branch(self)?;
+        if self.store().is_alive(&branch) {
+            Some(branch)
+        } else {
+            None
+        }
+    }
+
     /// Returns an iterator over top level (root) shared types available in current [Doc].
     fn root_refs(&self) -> RootRefs {
         let store = self.store();
@@ -117,6 +240,13 @@ pub trait ReadTxn: Sized {
         store.subdocs()
     }
 
+    /// Returns a sub document linked within the structures of this document store, which has
+    /// a given globally unique identifier. Returns `None` if no such sub document was found.
+    fn subdoc(&self, guid: &Uuid) -> Option<&Doc> {
+        let store = self.store();
+        store.subdoc(guid)
+    }
+
     /// Returns a [TextRef] data structure stored under a given `name`. Text structures are used for
     /// collaborative text editing: they expose operations to append and remove chunks of text,
     /// which are free to execute concurrently by multiple peers over remote boundaries.
@@ -248,11 +378,78 @@ pub trait WriteTxn: Sized {
 #[derive(Debug)]
 pub struct Transaction<'doc> {
     store: AtomicRef<'doc, Store>,
+    snapshot: Option<SnapshotGate>,
 }
 
 impl<'doc> Transaction<'doc> {
     pub(crate) fn new(store: AtomicRef<'doc, Store>) -> Self {
-        Transaction { store }
+        Transaction {
+            store,
+            snapshot: None,
+        }
+    }
+
+    pub(crate) fn with_snapshot(store: AtomicRef<'doc, Store>, snapshot: Snapshot) -> Self {
+        Transaction {
+            store,
+            snapshot: Some(SnapshotGate::new(snapshot)),
+        }
+    }
+}
+
+/// Snapshot, which gates the reads of a transaction created with [Doc::transact_at]. It
+/// dereferences to the [Snapshot] it was created from.
+///
+/// Gated reads don't modify the document store: blocks partially visible at a snapshot are read
+/// only in their visible parts. Positions of elements visible in indexed sequences (arrays, texts
+/// and XML nodes) are computed once per collection, when it's read for the first time, and reused
+/// by all subsequent reads made within the same transaction.
+pub struct SnapshotGate {
+    snapshot: Snapshot,
+    sequences: Mutex<HashMap<BranchPtr, Arc<VisibleSequence>>>,
+}
+
+impl SnapshotGate {
+    fn new(snapshot: Snapshot) -> Self {
+        SnapshotGate {
+            snapshot,
+            sequences: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a snapshot, which transaction reads are gated by.
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// Returns elements of an indexed sequence component of a given `branch`, which are visible
+    /// at current snapshot.
+    pub(crate) fn sequence<T: ReadTxn>(&self, txn: &T, branch: &Branch) -> Arc<VisibleSequence> {
+        let mut sequences = self.sequences.lock().unwrap();
+        sequences
+            .entry(BranchPtr::from(branch))
+            .or_insert_with(|| {
+                let kind = txn.store().options.offset_kind;
+                Arc::new(VisibleSequence::new(branch, &self.snapshot, kind))
+            })
+            .clone()
+    }
+}
+
+impl Deref for SnapshotGate {
+    type Target = Snapshot;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.snapshot
+    }
+}
+
+impl std::fmt::Debug for SnapshotGate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotGate")
+            .field("snapshot", &self.snapshot)
+            .finish()
     }
 }
 
@@ -261,6 +458,81 @@ impl<'doc> ReadTxn for Transaction<'doc> {
     fn store(&self) -> &Store {
         self.store.deref()
     }
+
+    #[inline]
+    fn gated_snapshot(&self) -> Option<&SnapshotGate> {
+        self.snapshot.as_ref()
+    }
+}
+
+/// Type-erased view over any [ReadTxn] implementation. [ReadTxn] itself is not object safe, so
+/// this wrapper can be used instead of `&dyn ReadTxn` by the code which needs to accept "some read
+/// transaction" without being generic over its concrete type (eg. plugins stored as trait objects
+/// or FFI layers). It can be created from a reference to both [Transaction] and [TransactionMut]
+/// and provides the same read capabilities as a transaction it was created from.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{AnyReadTxn, Doc, GetString, ReadTxn, Text, Transact};
+///
+/// trait Plugin {
+///     fn inspect(&self, txn: AnyReadTxn) -> String;
+/// }
+///
+/// struct TextLen;
+/// impl Plugin for TextLen {
+///     fn inspect(&self, txn: AnyReadTxn) -> String {
+///         let text = txn.get_text("text").unwrap();
+///         text.get_string(&txn)
+///     }
+/// }
+///
+/// let doc = Doc::new();
+/// let text = doc.get_or_insert_text("text");
+/// let plugins: Vec<Box<dyn Plugin>> = vec![Box::new(TextLen)];
+///
+/// let mut txn = doc.transact_mut();
+/// text.push(&mut txn, "hello");
+/// assert_eq!(plugins[0].inspect(AnyReadTxn::new(&txn)), "hello");
+/// drop(txn);
+///
+/// let txn = doc.transact();
+/// assert_eq!(plugins[0].inspect((&txn).into()), "hello");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct AnyReadTxn<'txn> {
+    store: &'txn Store,
+    snapshot: Option<&'txn SnapshotGate>,
+}
+
+impl<'txn> AnyReadTxn<'txn> {
+    /// Creates a type-erased view over a given read transaction.
+    pub fn new<T: ReadTxn>(txn: &'txn T) -> Self {
+        AnyReadTxn {
+            store: txn.store(),
+            snapshot: txn.gated_snapshot(),
+        }
+    }
+}
+
+impl<'txn, T: ReadTxn> From<&'txn T> for AnyReadTxn<'txn> {
+    #[inline]
+    fn from(txn: &'txn T) -> Self {
+        AnyReadTxn::new(txn)
+    }
+}
+
+impl<'txn> ReadTxn for AnyReadTxn<'txn> {
+    #[inline]
+    fn store(&self) -> &Store {
+        self.store
+    }
+
+    #[inline]
+    fn gated_snapshot(&self) -> Option<&SnapshotGate> {
+        self.snapshot
+    }
 }
 
 /// Read-write transaction. It can be used to modify an underlying state of the corresponding [Doc].
@@ -285,6 +557,10 @@ pub struct TransactionMut<'doc> {
     pub(crate) merge_blocks: Vec<ID>,
     /// Describes the set of deleted items by ids.
     pub(crate) delete_set: DeleteSet,
+    /// Metadata attached to updates produced by current transaction.
+    pub(crate) meta: HashMap<String, Any>,
+    /// If true, local edits are rejected. It's lifted for the time of remote update integration.
+    read_only: bool,
     /// We store the reference that last moved an item. This is needed to compute the delta
     /// when multiple ContentMove move the same item.
     pub(crate) prev_moved: HashMap<ItemPtr, ItemPtr>,
@@ -294,6 +570,21 @@ pub struct TransactionMut<'doc> {
     pub(crate) changed_parent_types: Vec<BranchPtr>,
     pub(crate) subdocs: Option<Box<Subdocs>>,
     pub(crate) origin: Option<Origin>,
+    /// Origins of transactions, which causally led to the current one - starting from the direct
+    /// parent.
+    pub(crate) parent_origins: Vec<Origin>,
+    pub(crate) commit_info: Option<CommitInfo>,
+    /// Per-transaction override of a document-wide [Options::skip_gc] setting.
+    gc: Option<bool>,
+    /// Concurrent writes to map keys detected during integration of remote updates, which are
+    /// waiting to be passed to [Options::map_conflict_resolver].
+    pub(crate) map_conflicts: Vec<(BranchPtr, Arc<str>, ItemPtr)>,
+    /// Clients retired within the scope of current transaction, which block lists are going to be
+    /// compacted once the transaction is committed.
+    retired_clients: Vec<ClientID>,
+    /// Payload of the first observer callback panic, which was not passed to
+    /// [Options::callback_panic_hook]. It's resumed once the transaction is dropped.
+    callback_panic: Cell<Option<Box<dyn std::any::Any + Send + 'static>>>,
     doc: Doc,
     committed: bool,
 }
@@ -318,29 +609,166 @@ impl<'doc> WriteTxn for TransactionMut<'doc> {
 
 impl<'doc> Drop for TransactionMut<'doc> {
     fn drop(&mut self) {
-        self.commit()
+        self.commit();
+        if let Some(payload) = self.callback_panic.get_mut().take() {
+            if !std::thread::panicking() {
+                // remaining fields - including the store lock - are still dropped while unwinding
+                std::panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+/// Options used to configure a single read-write transaction. See:
+/// [Doc::transact_mut_with_opts].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxnOptions {
+    /// Origin assigned to a transaction.
+    pub origin: Option<Origin>,
+    /// Overrides a document-wide [Options::skip_gc] setting for the scope of a single
+    /// transaction: when set to `Some(false)`, blocks deleted within that transaction will retain
+    /// their content (eg. so that they can be included in a snapshot), while `Some(true)` will
+    /// garbage collect them even if [Options::skip_gc] is enabled. `None` follows document
+    /// settings.
+    pub gc: Option<bool>,
+}
+
+/// Temporarily allows a transaction of a read-only document to integrate remote changes. Original
+/// read-only flag is restored when the scope is dropped, even if integration panicked.
+struct WritableScope<'a, 'doc> {
+    txn: &'a mut TransactionMut<'doc>,
+    read_only: bool,
+}
+
+impl<'a, 'doc> WritableScope<'a, 'doc> {
+    fn new(txn: &'a mut TransactionMut<'doc>) -> Self {
+        let read_only = std::mem::replace(&mut txn.read_only, false);
+        WritableScope { txn, read_only }
+    }
+}
+
+impl<'a, 'doc> Deref for WritableScope<'a, 'doc> {
+    type Target = TransactionMut<'doc>;
+
+    fn deref(&self) -> &Self::Target {
+        self.txn
+    }
+}
+
+impl<'a, 'doc> DerefMut for WritableScope<'a, 'doc> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.txn
+    }
+}
+
+impl<'a, 'doc> Drop for WritableScope<'a, 'doc> {
+    fn drop(&mut self) {
+        self.txn.read_only = self.read_only;
     }
 }
 
 impl<'doc> TransactionMut<'doc> {
     pub(crate) fn new(doc: Doc, store: AtomicRefMut<'doc, Store>, origin: Option<Origin>) -> Self {
         let begin_timestamp = store.blocks.get_state_vector();
+        let read_only = store.options.read_only;
         TransactionMut {
             store,
             doc,
             origin,
+            parent_origins: Vec::default(),
             before_state: begin_timestamp,
             merge_blocks: Vec::default(),
+            retired_clients: Vec::default(),
             delete_set: DeleteSet::new(),
+            meta: HashMap::default(),
+            read_only,
             after_state: StateVector::default(),
             changed: HashMap::default(),
             changed_parent_types: Vec::default(),
             prev_moved: HashMap::default(),
             subdocs: None,
+            commit_info: None,
+            gc: None,
+            map_conflicts: Vec::new(),
+            callback_panic: Cell::new(None),
             committed: false,
         }
     }
 
+    pub(crate) fn with_options(
+        doc: Doc,
+        store: AtomicRefMut<'doc, Store>,
+        opts: TxnOptions,
+    ) -> Self {
+        let mut txn = Self::new(doc, store, opts.origin);
+        txn.gc = opts.gc;
+        txn
+    }
+
+    /// Keeps a payload of observer callback panic returned by [Observer::trigger], so that it can be
+    /// resumed once current transaction is dropped. Only the first payload is kept.
+    pub(crate) fn defer_panic(&self, payload: Option<Box<dyn std::any::Any + Send + 'static>>) {
+        if let Some(payload) = payload {
+            let first = self.callback_panic.take().unwrap_or(payload);
+            self.callback_panic.set(Some(first));
+        }
+    }
+
+    /// Forces garbage collection of all blocks deleted within the scope of current transaction
+    /// once it's committed, even if document was configured with [Options::skip_gc] or current
+    /// transaction was created with [TxnOptions::gc] disabled.
+    pub fn force_gc(&mut self) {
+        self.gc = Some(true);
+    }
+
+    /// Marks a given `client` as retired - meaning that it's known to never modify current
+    /// document again - and compacts its block list: contents of all its deleted blocks are
+    /// garbage collected (even if document was configured with [Options::skip_gc]) and adjacent
+    /// deleted blocks are squashed together. Blocks which are still alive are left intact.
+    /// Compaction happens once, when current transaction is committed, after all observers have
+    /// been called, so the blocks they may refer to stay intact. Blocks of retired clients
+    /// deleted later on are garbage collected together with the transaction deleting them.
+    /// Use [Store::client_usage] to find clients occupying a significant share of document
+    /// storage.
+    ///
+    /// Retired client entries are never removed from the document state vector, so remote peers
+    /// will not resend its updates. If retired client ever comes back, its updates are still
+    /// integrated as usual. Deleted content of retired clients can no longer be restored by undo.
+    ///
+    /// Returns `false` if `client` is an identifier of current document's local client.
+    pub fn retire_client(&mut self, client: ClientID) -> bool {
+        if client == self.store.options.client_id {
+            return false;
+        }
+        if self.store.retired_clients.insert(client) {
+            self.retired_clients.push(client);
+        }
+        true
+    }
+
+    fn compact_client(&mut self, client: ClientID) {
+        let mut gc = GCCollector::new(&self.store);
+        if let Some(blocks) = self.store.blocks.get_client_mut(&client) {
+            for i in 0..blocks.len() {
+                if let BlockCell::Block(item) = &mut blocks[i] {
+                    item.gc(&mut gc, false);
+                }
+            }
+        }
+        gc.collect_all_marked(self);
+
+        let text_block_capacity = self.store.options.text_block_capacity;
+        if let Some(blocks) = self.store.blocks.get_client_mut(&client) {
+            let mut i = blocks.len();
+            while i > 1 {
+                i -= 1;
+                if blocks[i - 1].is_deleted() && blocks[i].is_deleted() {
+                    blocks.squash_left(i, text_block_capacity);
+                }
+            }
+        }
+    }
+
     pub fn doc(&self) -> &Doc {
         &self.doc
     }
@@ -370,12 +798,118 @@ impl<'doc> TransactionMut<'doc> {
         self.origin.as_ref()
     }
 
+    /// Checks if current transaction belongs to a read-only document replica (see:
+    /// [Options::read_only]), which accepts remote updates but rejects any local edits.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns [ReadOnlyError] if current transaction is not allowed to make local edits.
+    pub(crate) fn check_writable(&self) -> Result<(), ReadOnlyError> {
+        if self.read_only {
+            Err(ReadOnlyError)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Panics with [ReadOnlyError] if current transaction is not allowed to make local edits.
+    pub(crate) fn ensure_writable(&self) {
+        if let Err(e) = self.check_writable() {
+            panic!("{}", e);
+        }
+    }
+
+    /// Returns metadata, which will be attached to updates produced by current transaction. It
+    /// contains entries set via [TransactionMut::set_meta] as well as metadata of all remote
+    /// updates applied within current transaction.
+    pub fn meta(&self) -> &HashMap<String, Any> {
+        &self.meta
+    }
+
+    /// Sets a metadata entry (eg. an author, device or application version), which will be
+    /// attached to updates produced by current transaction (see: [Update::meta]) and exposed on
+    /// [UpdateEvent::meta]. Unlike transaction [Origin], metadata travels together with update
+    /// to remote peers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// txn.set_meta("author", "alice");
+    /// text.push(&mut txn, "hello");
+    /// let update = txn.encode_update_v1();
+    ///
+    /// let update = Update::decode_v1(&update).unwrap();
+    /// assert_eq!(update.meta().get("author"), Some(&Any::from("alice")));
+    /// ```
+    pub fn set_meta<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<Any>,
+    {
+        self.meta.insert(key.into(), value.into());
+    }
+
+    /// Returns origins of transactions, which causally led to the current one, starting from the
+    /// direct parent. Parent origins can be assigned via [Doc::transact_mut_caused_by] - usually
+    /// when a transaction is created in reaction to changes made by another one (e.g. when an
+    /// observer callback schedules a normalization pass).
+    pub fn parent_origins(&self) -> &[Origin] {
+        &self.parent_origins
+    }
+
+    /// Returns an origin chain of the current transaction: its own origin (if any) followed by its
+    /// [parent origins](Self::parent_origins). It can be passed to [Doc::transact_mut_caused_by]
+    /// in order to create a transaction causally linked to the current one.
+    pub fn origin_chain(&self) -> Vec<Origin> {
+        let mut chain = Vec::with_capacity(self.parent_origins.len() + 1);
+        chain.extend(self.origin.iter().cloned());
+        chain.extend(self.parent_origins.iter().cloned());
+        chain
+    }
+
+    /// Checks if a given `origin` is either an origin of the current transaction or any of the
+    /// transactions which causally led to it. This can be used to detect loops and echo effects
+    /// of changes made in reaction to other changes.
+    pub fn is_caused_by(&self, origin: &Origin) -> bool {
+        self.origin.as_ref() == Some(origin) || self.parent_origins.contains(origin)
+    }
+
+    /// Returns metadata of the current transaction recorded at the moment of its commit. It's only
+    /// available when commit log was enabled (see: [Doc::enable_commit_log]), once the
+    /// transaction has been committed and only if it changed the document state.
+    pub fn commit_info(&self) -> Option<&CommitInfo> {
+        self.commit_info.as_ref()
+    }
+
     /// Returns a list of root level types changed in a scope of the current transaction. This
     /// list is not filled right away, but as a part of [TransactionMut::commit] process.
     pub fn changed_parent_types(&self) -> &[BranchPtr] {
         &self.changed_parent_types
     }
 
+    /// Returns a list of shared collections directly modified (their elements or entries were
+    /// inserted, updated or removed) within the scope of the current transaction so far, in no
+    /// particular order. Collections created or deleted within the current transaction are not
+    /// included. Unlike [TransactionMut::changed_parent_types], this list is available before the
+    /// transaction is committed, so it can be used eg. to invalidate caches or schedule autosave
+    /// of modified document sections without observing all of them.
+    pub fn changed_branches(&self) -> Vec<Value> {
+        self.changed
+            .keys()
+            .filter_map(|ptr| match ptr {
+                TypePtr::Branch(branch) if !branch.is_deleted() => Some((*branch).into()),
+                _ => None,
+            })
+            .collect()
+    }
+
     #[inline]
     pub(crate) fn store(&self) -> &Store {
         &self.store
@@ -387,6 +921,8 @@ impl<'doc> TransactionMut<'doc> {
     }
 
     /// Encodes changes made within the scope of the current transaction using lib0 v1 encoding.
+    /// Result is sealed with [Options::codec](crate::Options::codec) if document has one
+    /// configured.
     ///
     /// Document updates are idempotent and commutative. Caveats:
     /// * It doesn't matter in which order document updates are applied.
@@ -397,10 +933,15 @@ impl<'doc> TransactionMut<'doc> {
     pub fn encode_update_v1(&self) -> Vec<u8> {
         let mut encoder = updates::encoder::EncoderV1::new();
         self.encode_update(&mut encoder);
-        encoder.to_vec()
+        let bytes = encoder.to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "encoded transaction update v1");
+        codec::seal(self.store.options.codec.as_ref(), bytes)
     }
 
     /// Encodes changes made within the scope of the current transaction using lib0 v2 encoding.
+    /// Result is sealed with [Options::codec](crate::Options::codec) if document has one
+    /// configured.
     ///
     /// Document updates are idempotent and commutative. Caveats:
     /// * It doesn't matter in which order document updates are applied.
@@ -411,7 +952,10 @@ impl<'doc> TransactionMut<'doc> {
     pub fn encode_update_v2(&self) -> Vec<u8> {
         let mut encoder = updates::encoder::EncoderV2::new();
         self.encode_update(&mut encoder);
-        encoder.to_vec()
+        let bytes = encoder.to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "encoded transaction update v2");
+        codec::seal(self.store.options.codec.as_ref(), bytes)
     }
 
     /// Encodes changes made within the scope of the current transaction.
@@ -426,6 +970,7 @@ impl<'doc> TransactionMut<'doc> {
         let store = self.store();
         store.write_blocks_from(&self.before_state, encoder);
         self.delete_set.encode(encoder);
+        crate::update::encode_meta(&self.meta, encoder);
     }
 
     /// Applies given `id_set` onto current transaction to run multi-range deletion.
@@ -442,7 +987,7 @@ impl<'doc> TransactionMut<'doc> {
 
                     if clock < state {
                         if state < clock_end {
-                            unapplied.insert(ID::new(*client, clock), clock_end - state);
+                            unapplied.insert(ID::new(*client, state), clock_end - state);
                         }
                         // We can ignore the case of GC and Delete structs, because we are going to skip them
                         if let Some(mut index) = blocks.find_pivot(clock) {
@@ -538,6 +1083,7 @@ impl<'doc> TransactionMut<'doc> {
     /// Delete item under given pointer.
     /// Returns true if block was successfully deleted, false if it was already deleted in the past.
     pub(crate) fn delete(&mut self, mut item: ItemPtr) -> bool {
+        self.ensure_writable();
         let mut recurse = Vec::new();
         let mut result = false;
 
@@ -623,6 +1169,24 @@ impl<'doc> TransactionMut<'doc> {
         result
     }
 
+    /// Decodes an `update` encoded using lib0 v1 encoding and applies it into a document owning
+    /// current transaction (see: [TransactionMut::apply_update]). If document has been configured
+    /// with [Options::codec](crate::Options::codec), `update` is expected to be an envelope
+    /// produced by that codec.
+    pub fn apply_update_v1(&mut self, update: &[u8]) -> Result<(), CodecError> {
+        let update = codec::open(self.store.options.codec.as_ref(), update)?;
+        self.apply_update(Update::decode_v1(&update)?)?;
+        Ok(())
+    }
+
+    /// Decodes an `update` encoded using lib0 v2 encoding and applies it into a document owning
+    /// current transaction. See [TransactionMut::apply_update_v1] for details.
+    pub fn apply_update_v2(&mut self, update: &[u8]) -> Result<(), CodecError> {
+        let update = codec::open(self.store.options.codec.as_ref(), update)?;
+        self.apply_update(Update::decode_v2(&update)?)?;
+        Ok(())
+    }
+
     /// Applies a deserialized [Update] contents into a document owning current transaction. Update
     /// payload can be generated by methods such as [TransactionMut::encode_diff] or passed to
     /// [Doc::observe_update_v1]/[Doc::observe_update_v2] callbacks. Updates are allowed to contain
@@ -633,12 +1197,172 @@ impl<'doc> TransactionMut<'doc> {
     /// Remote update integration requires that all to-be-integrated blocks must have their direct
     /// predecessors already in place. Out of order updates from the same peer will be stashed
     /// internally and their integration will be postponed until missing blocks arrive first.
-    pub fn apply_update(&mut self, update: Update) {
-        let (remaining, remaining_ds) = update.integrate(self);
+    ///
+    /// # Errors
+    ///
+    /// Malformed updates - eg. containing blocks which point to parents that are not shared types
+    /// or move elements of another shared type - are rejected with an [UpdateError] instead of
+    /// causing a panic. Malformed blocks are dropped, while the rest of an update is integrated
+    /// anyway - blocks depending on the dropped ones are stashed as pending.
+    pub fn apply_update(&mut self, update: Update) -> Result<(), UpdateError> {
+        self.apply_update_internal(update, None)?;
+        Ok(())
+    }
+
+    /// Applies multiple deserialized [Update]s into a document owning current transaction at once.
+    /// It's meant for servers, which batch several client messages before applying them.
+    ///
+    /// All updates are merged together before integration, so that it's performed in a single
+    /// pass. Since all changes are made within the same transaction, observers are notified only
+    /// once and a single update containing all of them is emitted to
+    /// [Doc::observe_update_v1]/[Doc::observe_update_v2] subscribers once current transaction
+    /// is committed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, ReadTxn, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let mut updates = Vec::new();
+    /// for client_id in 1..=3 {
+    ///     let doc = Doc::with_client_id(client_id);
+    ///     doc.get_or_insert_text("text").push(&mut doc.transact_mut(), "a");
+    ///     let u = doc.transact().encode_state_as_update_v1(&Default::default());
+    ///     updates.push(Update::decode_v1(&u).unwrap());
+    /// }
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// doc.transact_mut().apply_updates(updates).unwrap();
+    /// assert_eq!(text.get_string(&doc.transact()), "aaa");
+    /// ```
+    pub fn apply_updates<I>(&mut self, updates: I) -> Result<(), UpdateError>
+    where
+        I: IntoIterator<Item = Update>,
+    {
+        let update = Update::merge_updates(updates);
+        self.apply_update_internal(update, None)?;
+        Ok(())
+    }
+
+    /// Applies a deserialized [Update] contents into a document owning current transaction, the
+    /// same way as [TransactionMut::apply_update] does, while reporting its progress. It's meant
+    /// for integrating very large updates (e.g. initial synchronization of huge documents).
+    ///
+    /// A `progress` callback is called every time all blocks inserted by a single client have
+    /// been processed. It can return `false` to cooperatively cancel the integration: in that case
+    /// blocks which have not been processed yet, together with update's delete set, are not
+    /// integrated but returned as a new [Update]. It can be applied later on - possibly in
+    /// another transaction - in order to resume the integration process. Changes integrated
+    /// before cancellation are kept. Cancellation requested after the last client's blocks have
+    /// been processed has no effect.
+    ///
+    /// Returns `None` if an update has been integrated completely.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, ReadTxn, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let source = Doc::new();
+    /// for client_id in 1..=3 {
+    ///     let doc = Doc::with_client_id(client_id);
+    ///     doc.get_or_insert_text("text").push(&mut doc.transact_mut(), "a");
+    ///     let u = doc.transact().encode_state_as_update_v1(&Default::default());
+    ///     source.transact_mut().apply_update(Update::decode_v1(&u).unwrap()).unwrap();
+    /// }
+    /// let update = source.transact().encode_state_as_update_v1(&Default::default());
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut update = Update::decode_v1(&update).unwrap();
+    /// // integrate blocks of a single client per transaction
+    /// let mut ticks = 0;
+    /// loop {
+    ///     ticks += 1;
+    ///     let mut txn = doc.transact_mut();
+    ///     let rest = txn
+    ///         .apply_update_with_progress(update, |p| {
+    ///             println!("processed {}/{} blocks", p.processed, p.total);
+    ///             false // cancel after first client
+    ///         })
+    ///         .unwrap();
+    ///     match rest {
+    ///         Some(rest) => update = rest,
+    ///         None => break,
+    ///     }
+    /// }
+    /// assert_eq!(ticks, 3);
+    /// assert_eq!(text.get_string(&doc.transact()), "aaa");
+    /// ```
+    pub fn apply_update_with_progress<F>(
+        &mut self,
+        update: Update,
+        mut progress: F,
+    ) -> Result<Option<Update>, UpdateError>
+    where
+        F: FnMut(ApplyProgress) -> bool,
+    {
+        self.apply_update_internal(update, Some(&mut progress))
+    }
+
+    fn apply_update_internal(
+        &mut self,
+        mut update: Update,
+        progress: Option<&mut dyn FnMut(ApplyProgress) -> bool>,
+    ) -> Result<Option<Update>, UpdateError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "apply_update",
+            client_id = self.store.options.client_id,
+            blocks = update.blocks.block_count(),
+            deleted_clients = update.delete_set.iter().count(),
+        )
+        .entered();
+        if self.store.options.max_blocks.is_some() || self.store.options.max_content_bytes.is_some()
+        {
+            let (blocks, bytes) = update.quota_usage(&self.store.blocks.get_state_vector());
+            self.check_quota(blocks, bytes)?;
+        }
+        self.meta.extend(std::mem::take(&mut update.meta));
+        #[cfg(feature = "metrics")]
+        let start = crate::metrics::Stopwatch::start();
+        let result = WritableScope::new(self).integrate_update(update, progress);
+        #[cfg(feature = "metrics")]
+        self.store.metrics.update_applied(start, &self.store);
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(pending) => tracing::debug!(
+                blocks = self.store.blocks.block_count(),
+                pending = pending.is_some(),
+                "update applied"
+            ),
+            Err(e) => tracing::warn!(error = %e, "failed to apply update"),
+        }
+        result
+    }
+
+    fn integrate_update(
+        &mut self,
+        update: Update,
+        progress: Option<&mut dyn FnMut(ApplyProgress) -> bool>,
+    ) -> Result<Option<Update>, UpdateError> {
+        let IntegrationOutcome {
+            pending: remaining,
+            pending_ds: remaining_ds,
+            cancelled,
+        } = update.integrate(self, progress)?;
+        if !self.map_conflicts.is_empty() {
+            if let Some(resolver) = self.store.options.map_conflict_resolver.clone() {
+                resolver.resolve_pending(self);
+            }
+        }
         let mut retry = false;
         {
             let store = self.store_mut();
-            store.pending = if let Some(mut pending) = store.pending.take() {
+            if let Some(pending) = store.pending.as_ref() {
                 // check if we can apply something
                 for (client, &clock) in pending.missing.iter() {
                     if clock < store.blocks.get_clock(client) {
@@ -646,18 +1370,11 @@ impl<'doc> TransactionMut<'doc> {
                         break;
                     }
                 }
-
-                if let Some(remaining) = remaining {
-                    // merge restStructs into store.pending
-                    for (&client, &clock) in remaining.missing.iter() {
-                        pending.missing.set_min(client, clock);
-                    }
-                    pending.update = Update::merge_updates(vec![pending.update, remaining.update]);
-                }
-                Some(pending)
-            } else {
-                remaining
-            };
+            }
+            if let Some(remaining) = remaining {
+                // merge restStructs into store.pending
+                store.add_pending(remaining);
+            }
         }
         if let Some(pending) = self.store_mut().pending_ds.take() {
             let ds2 = self.apply_delete(&pending);
@@ -681,10 +1398,65 @@ impl<'doc> TransactionMut<'doc> {
                 let ds = store.pending_ds.take().unwrap_or_default();
                 let mut ds_update = Update::new();
                 ds_update.delete_set = ds;
-                self.apply_update(pending.update);
-                self.apply_update(ds_update)
+                self.integrate_update(pending.update, None)?;
+                self.integrate_update(ds_update, None)?;
             }
         }
+        Ok(cancelled)
+    }
+
+    /// Accounts a new locally created block with a given `content` against document quotas.
+    /// Nothing is accounted if document has no quotas configured.
+    pub(crate) fn try_reserve_quota(&mut self, content: &ItemContent) -> Result<(), QuotaExceeded> {
+        let options = &self.store.options;
+        if options.max_blocks.is_none() && options.max_content_bytes.is_none() {
+            return Ok(());
+        }
+        let bytes = content.byte_size();
+        self.check_quota(1, bytes)?;
+        self.store.content_bytes += bytes;
+        Ok(())
+    }
+
+    /// Checks if a given number of `blocks` with `bytes` of content would fit into document quotas
+    /// without accounting them. If they don't, [Doc::observe_quota_exceeded] callbacks are called.
+    pub(crate) fn check_quota(&self, blocks: usize, bytes: usize) -> Result<(), QuotaExceeded> {
+        if let Err(e) = self.store.check_quota(blocks, bytes) {
+            self.emit_quota_exceeded(&e);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Delivers update events deferred while update observers were paused (see:
+    /// [Doc::pause_observers]) in order of their transaction commits. If `resume` is set, update
+    /// observers are no longer paused afterwards. While events of a given transaction are
+    /// delivered, current transaction carries the origin of that transaction.
+    pub(crate) fn emit_deferred_updates(&mut self, resume: bool) {
+        let updates = match self.store.events.as_ref() {
+            Some(events) if resume => events.resume_updates(),
+            Some(events) => events.take_deferred_updates(),
+            None => return,
+        };
+        let origin = self.origin.take();
+        for mut update in updates {
+            self.origin = update.origin.take();
+            if let Some(events) = self.store.events.as_ref() {
+                events.emit_deferred_update(self, &update);
+            }
+        }
+        self.origin = origin;
+    }
+
+    fn emit_quota_exceeded(&self, e: &QuotaExceeded) {
+        if let Some(events) = self.store.events.as_ref() {
+            let hook = self.store.options.callback_panic_hook.as_ref();
+            self.defer_panic(
+                events
+                    .quota_exceeded_events
+                    .trigger(hook, |fun| fun(self, e)),
+            );
+        }
     }
 
     pub(crate) fn create_item<T: Prelim>(
@@ -693,6 +1465,22 @@ impl<'doc> TransactionMut<'doc> {
         value: T,
         parent_sub: Option<Arc<str>>,
     ) -> ItemPtr {
+        match self.try_create_item(pos, value, parent_sub) {
+            Ok(ptr) => ptr,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates and integrates a new block with a given `value` at a given position. Returns an
+    /// error without modifying a document if current transaction cannot make local edits or a new
+    /// block would exceed document quotas.
+    pub(crate) fn try_create_item<T: Prelim>(
+        &mut self,
+        pos: &block::ItemPosition,
+        value: T,
+        parent_sub: Option<Arc<str>>,
+    ) -> Result<ItemPtr, EditError> {
+        self.check_writable()?;
         let (left, right, origin, id) = {
             let store = self.store_mut();
             let left = pos.left;
@@ -723,6 +1511,7 @@ impl<'doc> TransactionMut<'doc> {
             parent_sub,
             content,
         );
+        self.try_reserve_quota(&block.content)?;
         let mut block_ptr = ItemPtr::from(&mut block);
 
         block_ptr.integrate(self, 0);
@@ -733,7 +1522,7 @@ impl<'doc> TransactionMut<'doc> {
             remainder.integrate(self, inner_ref.unwrap().into())
         }
 
-        block_ptr
+        Ok(block_ptr)
     }
 
     fn call_type_observers(
@@ -791,10 +1580,40 @@ impl<'doc> TransactionMut<'doc> {
             return;
         }
         self.committed = true;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "commit",
+            client_id = self.store.options.client_id,
+            changed = self.changed.len(),
+        )
+        .entered();
 
         // 1. sort and merge delete set
         self.delete_set.squash();
         self.after_state = self.store.blocks.get_state_vector();
+        if !self.delete_set.is_empty() || self.after_state != self.before_state {
+            let client_id = self.store.options.client_id;
+            if let Some(log) = self.store.commit_log.as_mut() {
+                let info = log.record(client_id, self.origin.clone());
+                for ptr in self.changed.keys() {
+                    if let TypePtr::Branch(branch) = ptr {
+                        log.mark_modified(*branch, &info);
+                    }
+                }
+                self.commit_info = Some(info);
+            }
+        }
+        // rebuild indexes of keyed lists and sets changed by remote updates, before observers
+        // read them
+        for ptr in self.changed.keys() {
+            if let TypePtr::Branch(branch) = ptr {
+                match branch.type_ref {
+                    TypeRef::KeyedList => keyed_list::reindex(*branch),
+                    TypeRef::Set => set::reindex(*branch),
+                    _ => {}
+                }
+            }
+        }
         // 2. emit 'beforeObserverCalls'
         // 3. for each change observed by the transaction call 'afterTransaction'
         if !self.changed.is_empty() {
@@ -844,50 +1663,108 @@ impl<'doc> TransactionMut<'doc> {
         }
 
         // 4. try GC delete set
-        if !self.store.options.skip_gc {
+        if self.gc.unwrap_or(!self.store.options.skip_gc) {
             GCCollector::collect(self);
+        } else {
+            // blocks of sub documents destroyed via Doc::destroy_subdoc are always collected
+            GCCollector::collect_destroyed_subdocs(self);
+        }
+        if !self.store.retired_clients.is_empty() {
+            if !self.gc.unwrap_or(!self.store.options.skip_gc) {
+                GCCollector::collect_retired(self);
+            }
+            for client in std::mem::take(&mut self.retired_clients) {
+                self.compact_client(client);
+            }
         }
 
         // 5. try merge delete set
         self.delete_set.try_squash_with(&mut self.store);
 
         // 6. get transaction after state and try to merge to left
+        let text_block_capacity = self.store.options.text_block_capacity;
         for (client, &clock) in self.after_state.iter() {
             let before_clock = self.before_state.get(client);
             if before_clock != clock {
                 let blocks = self.store.blocks.get_client_mut(client).unwrap();
+                #[cfg(feature = "metrics")]
+                let len = blocks.len();
                 let first_change = blocks.find_pivot(before_clock).unwrap().max(1);
                 let mut i = blocks.len() - 1;
                 while i >= first_change {
-                    blocks.squash_left(i);
+                    blocks.squash_left(i, text_block_capacity);
                     i -= 1;
                 }
+                #[cfg(feature = "metrics")]
+                {
+                    let merged = len - blocks.len();
+                    self.store.metrics.blocks_merged(merged);
+                }
             }
         }
 
         // 7. get merge_structs and try to merge to left
         for id in self.merge_blocks.iter() {
             if let Some(blocks) = self.store.blocks.get_client_mut(&id.client) {
+                #[cfg(feature = "metrics")]
+                let len = blocks.len();
                 if let Some(replaced_pos) = blocks.find_pivot(id.clock) {
                     if replaced_pos + 1 < blocks.len() {
-                        blocks.squash_left(replaced_pos + 1);
+                        blocks.squash_left(replaced_pos + 1, text_block_capacity);
                     } else if replaced_pos > 0 {
-                        blocks.squash_left(replaced_pos);
+                        blocks.squash_left(replaced_pos, text_block_capacity);
                     }
                 }
+                #[cfg(feature = "metrics")]
+                {
+                    let merged = len - blocks.len();
+                    self.store.metrics.blocks_merged(merged);
+                }
             }
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            blocks = self.store.blocks.block_count(),
+            content_bytes = self.store.content_bytes,
+            "transaction changes integrated"
+        );
+
+        #[cfg(feature = "debug-integrity")]
+        self.assert_integrity();
+
+        let new_roots = std::mem::take(&mut self.store.new_roots);
         if let Some(events) = self.store.events.as_ref() {
-            // 8. emit 'afterTransactionCleanup'
+            // 8. emit 'rootAdded'
+            if !new_roots.is_empty() {
+                events.emit_root_added(self, &new_roots);
+            }
+            // 9. emit 'afterTransactionCleanup'
             events.emit_transaction_cleanup(self);
-            // 9. emit 'update'
-            events.emit_update_v1(self);
-            // 10. emit 'updateV2'
-            events.emit_update_v2(self);
+            if !events.defer_updates(self) {
+                // 10. emit 'update'
+                events.emit_update_v1(self);
+                // 11. emit 'updateV2'
+                events.emit_update_v2(self);
+            }
+        }
+
+        // 12. emit 'subdocUpdate' on all ancestors of current sub-document
+        if self.store.parent.is_some()
+            && (!self.delete_set.is_empty() || self.after_state != self.before_state)
+        {
+            let mut update_v1 = None;
+            let mut update_v2 = None;
+            let mut parent = self.doc().parent_doc();
+            while let Some(doc) = parent {
+                if let Some(events) = doc.events() {
+                    events.emit_subdoc_update(self, &mut update_v1, &mut update_v2);
+                }
+                parent = doc.parent_doc();
+            }
         }
 
-        // 11. add and remove subdocs
+        // 13. add and remove subdocs
         let store = self.store.deref_mut();
         if let Some(mut subdocs) = self.subdocs.take() {
             let client_id = store.options.client_id;
@@ -907,7 +1784,8 @@ impl<'doc> TransactionMut<'doc> {
             let mut removed = if let Some(events) = store.events.as_ref() {
                 if events.subdocs_events.has_subscribers() {
                     let e = SubdocsEvent::new(subdocs);
-                    events.subdocs_events.trigger(|cb| cb(self, &e));
+                    let hook = store.options.callback_panic_hook.as_ref();
+                    self.defer_panic(events.subdocs_events.trigger(hook, |cb| cb(self, &e)));
                     e.removed
                 } else {
                     subdocs.removed
@@ -922,7 +1800,36 @@ impl<'doc> TransactionMut<'doc> {
         }
     }
 
+    /// Verifies that blocks and cached lengths of all collections changed within the scope of
+    /// the current transaction and its delete set are consistent, panicking otherwise.
+    #[cfg(feature = "debug-integrity")]
+    fn assert_integrity(&self) {
+        use crate::integrity::IntegrityReport;
+
+        let branches = self.changed.keys().filter_map(|ptr| match ptr {
+            TypePtr::Branch(branch) if !branch.is_deleted() => Some(*branch),
+            _ => None,
+        });
+        let reports = [
+            IntegrityReport::check_branches(self.store.options.offset_kind, branches),
+            IntegrityReport::check_delete_set(&self.store, &self.delete_set),
+        ];
+        for report in reports.iter() {
+            assert!(
+                report.is_ok(),
+                "transaction (origin: {:?}) left document in inconsistent state: {}",
+                self.origin,
+                report
+            );
+        }
+    }
+
     pub(crate) fn add_changed_type(&mut self, parent: BranchPtr, parent_sub: Option<Arc<str>>) {
+        match parent.type_ref {
+            TypeRef::KeyedList => keyed_list::invalidate_index(parent),
+            TypeRef::Set => set::invalidate_index(parent),
+            _ => {}
+        }
         let trigger = if let Some(ptr) = parent.item {
             (ptr.id().clock < self.before_state.get(&ptr.id().client)) && !ptr.is_deleted()
         } else {
@@ -1022,6 +1929,7 @@ pub struct Subdocs {
     pub(crate) added: HashMap<DocAddr, Doc>,
     pub(crate) removed: HashMap<DocAddr, Doc>,
     pub(crate) loaded: HashMap<DocAddr, Doc>,
+    pub(crate) destroyed: HashMap<DocAddr, Doc>,
 }
 
 /// A binary marker that can be assigned to a read-write transaction upon creation via
@@ -1029,38 +1937,199 @@ pub struct Subdocs {
 /// transaction updates within a specific context, which exists for the duration of a transaction
 /// (it's **not persisted** in the document store itself), i.e. *you can use unique document client
 /// identifiers to differentiate updates incoming from remote nodes from those performed locally*.
+///
+/// Apart from binary markers, origin can also carry a typed value created via [Origin::new]. Such
+/// value can be later retrieved using [Origin::downcast_ref]. Two typed origins are equal when they
+/// carry values of the same type, which are equal to each other.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::Origin;
+///
+/// #[derive(Debug, Hash, PartialEq, Eq)]
+/// struct RemotePeer { id: u64 }
+///
+/// let origin = Origin::new(RemotePeer { id: 1 });
+/// assert_eq!(origin.downcast_ref::<RemotePeer>(), Some(&RemotePeer { id: 1 }));
+/// assert!(origin.downcast_ref::<u64>().is_none());
+/// assert_eq!(origin, Origin::new(RemotePeer { id: 1 }));
+/// assert_ne!(origin, Origin::from(1u64));
+/// ```
 #[repr(transparent)]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-pub struct Origin(SmallVec<[u8; std::mem::size_of::<usize>()]>);
+#[derive(Clone)]
+pub struct Origin(OriginRepr);
+
+#[derive(Clone)]
+enum OriginRepr {
+    Binary(SmallVec<[u8; std::mem::size_of::<usize>()]>),
+    Typed(Arc<dyn OriginValue>),
+}
+
+impl Origin {
+    /// Creates a new origin carrying a typed `value`. It can be later retrieved using
+    /// [Origin::downcast_ref].
+    pub fn new<T>(value: T) -> Self
+    where
+        T: std::any::Any + Hash + Eq + Send + Sync,
+    {
+        Origin(OriginRepr::Typed(Arc::new(value)))
+    }
+
+    /// Returns a reference to a typed value of this origin, if it was created using
+    /// [Origin::new] with a value of type `T`. Returns `None` otherwise.
+    pub fn downcast_ref<T: std::any::Any>(&self) -> Option<&T> {
+        match &self.0 {
+            OriginRepr::Typed(value) => value.as_any().downcast_ref(),
+            OriginRepr::Binary(_) => None,
+        }
+    }
+
+    /// Checks if current origin carries a typed value of type `T`.
+    pub fn is<T: std::any::Any>(&self) -> bool {
+        self.type_id() == Some(std::any::TypeId::of::<T>())
+    }
+
+    /// Returns a type identifier of a value carried by this origin, if it was created using
+    /// [Origin::new]. Binary origins return `None`.
+    pub fn type_id(&self) -> Option<std::any::TypeId> {
+        match &self.0 {
+            OriginRepr::Typed(value) => Some(value.as_any().type_id()),
+            OriginRepr::Binary(_) => None,
+        }
+    }
+
+    #[inline]
+    fn binary(bytes: SmallVec<[u8; std::mem::size_of::<usize>()]>) -> Self {
+        Origin(OriginRepr::Binary(bytes))
+    }
+}
+
+/// Object-safe counterpart of `Any + Hash + Eq`, used to store typed [Origin] values.
+trait OriginValue: Send + Sync {
+    fn as_any(&self) -> &dyn std::any::Any;
+    fn type_name(&self) -> &'static str;
+    fn dyn_eq(&self, other: &dyn OriginValue) -> bool;
+    fn dyn_hash(&self) -> u64;
+}
+
+impl<T> OriginValue for T
+where
+    T: std::any::Any + Hash + Eq + Send + Sync,
+{
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn dyn_eq(&self, other: &dyn OriginValue) -> bool {
+        match other.as_any().downcast_ref::<T>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+
+    fn dyn_hash(&self) -> u64 {
+        use std::hash::Hasher;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl PartialEq for Origin {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (OriginRepr::Binary(a), OriginRepr::Binary(b)) => a == b,
+            (OriginRepr::Typed(a), OriginRepr::Typed(b)) => a.dyn_eq(b.as_ref()),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Origin {}
+
+impl Hash for Origin {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match &self.0 {
+            OriginRepr::Binary(bytes) => bytes.hash(state),
+            OriginRepr::Typed(value) => {
+                value.as_any().type_id().hash(state);
+                state.write_u64(value.dyn_hash());
+            }
+        }
+    }
+}
+
+impl PartialOrd for Origin {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Origin {
+    /// Binary origins are ordered by their byte representation and always precede typed ones.
+    /// Typed origins don't have a meaningful order: they are grouped by their type, but order of
+    /// values within the same type is unspecified (yet it's consistent with [Eq]).
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (&self.0, &other.0) {
+            (OriginRepr::Binary(a), OriginRepr::Binary(b)) => a.cmp(b),
+            (OriginRepr::Binary(_), OriginRepr::Typed(_)) => Ordering::Less,
+            (OriginRepr::Typed(_), OriginRepr::Binary(_)) => Ordering::Greater,
+            (OriginRepr::Typed(a), OriginRepr::Typed(b)) => {
+                if a.dyn_eq(b.as_ref()) {
+                    Ordering::Equal
+                } else {
+                    (a.as_any().type_id(), a.dyn_hash())
+                        .cmp(&(b.as_any().type_id(), b.dyn_hash()))
+                        .then_with(|| {
+                            let a = Arc::as_ptr(a) as *const u8 as usize;
+                            let b = Arc::as_ptr(b) as *const u8 as usize;
+                            a.cmp(&b)
+                        })
+                }
+            }
+        }
+    }
+}
 
 impl AsRef<[u8]> for Origin {
+    /// Returns a binary representation of this origin. Typed origins created via [Origin::new]
+    /// have no binary representation and return an empty slice.
     fn as_ref(&self) -> &[u8] {
-        self.0.as_ref()
+        match &self.0 {
+            OriginRepr::Binary(bytes) => bytes.as_ref(),
+            OriginRepr::Typed(_) => &[],
+        }
     }
 }
 
 impl<'a, T> From<Pin<&'a T>> for Origin {
     fn from(p: Pin<&T>) -> Self {
         let ptr = Pin::get_ref(p) as *const T as usize;
-        Origin(SmallVec::from_const(ptr.to_be_bytes()))
+        Origin::binary(SmallVec::from_const(ptr.to_be_bytes()))
     }
 }
 
 impl<'a> From<&'a [u8]> for Origin {
     fn from(slice: &'a [u8]) -> Self {
-        Origin(SmallVec::from_slice(slice))
+        Origin::binary(SmallVec::from_slice(slice))
     }
 }
 
 impl<'a> From<&'a str> for Origin {
     fn from(v: &'a str) -> Self {
-        Origin(SmallVec::from_slice(v.as_ref()))
+        Origin::binary(SmallVec::from_slice(v.as_ref()))
     }
 }
 
 impl From<String> for Origin {
     fn from(v: String) -> Self {
-        Origin(SmallVec::from(Vec::from(v)))
+        Origin::binary(SmallVec::from(Vec::from(v)))
     }
 }
 
@@ -1073,8 +2142,13 @@ impl std::fmt::Debug for Origin {
 impl std::fmt::Display for Origin {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "Origin(")?;
-        for b in self.0.iter() {
-            write!(f, "{:02x?}", b)?;
+        match &self.0 {
+            OriginRepr::Binary(bytes) => {
+                for b in bytes.iter() {
+                    write!(f, "{:02x?}", b)?;
+                }
+            }
+            OriginRepr::Typed(value) => write!(f, "{}", value.type_name())?,
         }
         write!(f, ")")
     }
@@ -1084,7 +2158,7 @@ macro_rules! impl_origin {
     ($t:ty) => {
         impl From<$t> for Origin {
             fn from(v: $t) -> Origin {
-                Origin(SmallVec::from_slice(&v.to_be_bytes()))
+                Origin::binary(SmallVec::from_slice(&v.to_be_bytes()))
             }
         }
     };
@@ -0,0 +1,300 @@
+use crate::moving::IndexedSequence;
+use crate::transaction::TransactionMut;
+use crate::types::{Delta, OutOfBounds};
+use crate::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+const START: &str = "start";
+const END: &str = "end";
+const PAYLOAD: &str = "payload";
+
+/// A layer of annotations (eg. comments or highlights) anchored to ranges of a [TextRef].
+///
+/// Every annotation is identified by a unique key and consists of a range and a payload.
+/// Range boundaries are stored as [StickyIndex]es, so annotations keep pointing to the same
+/// piece of text even when it's concurrently modified by other peers. Text inserted right before
+/// the start or right after the end of an annotated range doesn't become a part of it.
+///
+/// Annotations themselves are stored in a provided [MapRef], which makes them replicated together
+/// with the rest of the document. Annotation layer itself keeps no state and can be recreated at
+/// any time from the same text and map.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Any, Doc, Text, Transact};
+/// use yrs::types::annotation::AnnotationLayer;
+///
+/// let doc = Doc::new();
+/// let text = doc.get_or_insert_text("text");
+/// let comments = AnnotationLayer::new(text.clone(), doc.get_or_insert_map("comments"));
+///
+/// let mut txn = doc.transact_mut();
+/// text.push(&mut txn, "hello world");
+/// comments.annotate(&mut txn, "c1", 6..11, "nice!").unwrap(); // "world"
+///
+/// text.insert(&mut txn, 0, "oh, "); // => "oh, hello world"
+/// let c1 = comments.get(&txn, "c1").unwrap();
+/// assert_eq!(c1.range, 10..15);
+/// assert_eq!(c1.payload, Any::from("nice!"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnnotationLayer {
+    text: TextRef,
+    annotations: MapRef,
+}
+
+impl AnnotationLayer {
+    /// Creates a new annotation layer over a given `text`, which stores its annotations inside of
+    /// provided `annotations` map.
+    pub fn new(text: TextRef, annotations: MapRef) -> Self {
+        AnnotationLayer { text, annotations }
+    }
+
+    /// Returns a reference to an annotated text.
+    pub fn text(&self) -> &TextRef {
+        &self.text
+    }
+
+    /// Returns a reference to a map used to store annotations.
+    pub fn annotations(&self) -> &MapRef {
+        &self.annotations
+    }
+
+    /// Anchors a new annotation identified by a given `id` to a `range` of current text. If an
+    /// annotation with the same `id` already existed, it will be replaced.
+    ///
+    /// Returns an [OutOfBounds] error if `range` doesn't fit within the bounds of current text.
+    pub fn annotate<K, P>(
+        &self,
+        txn: &mut TransactionMut,
+        id: K,
+        range: Range<u32>,
+        payload: P,
+    ) -> Result<(), OutOfBounds>
+    where
+        K: Into<Arc<str>>,
+        P: Into<Any>,
+    {
+        let len = self.text.len(txn);
+        OutOfBounds::check(len, range.start, 0)?;
+        OutOfBounds::check(len, range.end, 0)?;
+        let out_of_bounds = OutOfBounds {
+            len,
+            index: range.end,
+        };
+        let start = self
+            .text
+            .sticky_index(txn, range.start, Assoc::After)
+            .ok_or(out_of_bounds)?;
+        let end = self
+            .text
+            .sticky_index(txn, range.end, Assoc::Before)
+            .ok_or(out_of_bounds)?;
+
+        let mut entry = HashMap::new();
+        entry.insert(START.to_owned(), start.to_json());
+        entry.insert(END.to_owned(), end.to_json());
+        entry.insert(PAYLOAD.to_owned(), payload.into());
+        self.annotations.insert(txn, id, Any::from(entry));
+        Ok(())
+    }
+
+    /// Removes an annotation identified by a given `id`. Returns `false` if no such annotation
+    /// existed.
+    pub fn remove(&self, txn: &mut TransactionMut, id: &str) -> bool {
+        self.annotations.remove(txn, id).is_some()
+    }
+
+    /// Returns an annotation identified by a given `id` with its range resolved against current
+    /// state of the text.
+    pub fn get<T: ReadTxn>(&self, txn: &T, id: &str) -> Option<Annotation> {
+        let value = self.annotations.get(txn, id)?;
+        Annotation::resolve(txn, id.into(), value)
+    }
+
+    /// Returns all annotations of current layer ordered by their resolved ranges.
+    pub fn iter<T: ReadTxn>(&self, txn: &T) -> Vec<Annotation> {
+        let mut result: Vec<_> = self
+            .annotations
+            .iter(txn)
+            .filter_map(|(id, value)| Annotation::resolve(txn, id.into(), value))
+            .collect();
+        result.sort_by(|a, b| {
+            (a.range.start, a.range.end, &a.id).cmp(&(b.range.start, b.range.end, &b.id))
+        });
+        result
+    }
+
+    /// Returns all annotations, which ranges contain a character at a given `index`.
+    pub fn at<T: ReadTxn>(&self, txn: &T, index: u32) -> Vec<Annotation> {
+        let mut result = self.iter(txn);
+        result.retain(|a| a.range.contains(&index));
+        result
+    }
+
+    /// Subscribes a given callback to be called whenever changes made to the annotated text
+    /// caused any annotations to collapse, ie. all characters of their ranges have been deleted.
+    /// Every collapsed annotation is reported once. Collapsed annotations are not removed
+    /// automatically.
+    ///
+    /// Returns a [Subscription] which, when dropped, will unsubscribe current callback.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use yrs::{Doc, Text, Transact};
+    /// use yrs::types::annotation::AnnotationLayer;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let comments = AnnotationLayer::new(text.clone(), doc.get_or_insert_map("comments"));
+    /// {
+    ///     let mut txn = doc.transact_mut();
+    ///     text.push(&mut txn, "hello world");
+    ///     comments.annotate(&mut txn, "c1", 0..5, "greeting").unwrap(); // "hello"
+    /// }
+    ///
+    /// let collapsed = Arc::new(Mutex::new(Vec::new()));
+    /// let c = collapsed.clone();
+    /// let _sub = comments.observe_collapsed(move |_, a| c.lock().unwrap().push(a.id.clone()));
+    ///
+    /// text.remove_range(&mut doc.transact_mut(), 0, 6); // => "world"
+    /// assert_eq!(*collapsed.lock().unwrap(), vec!["c1".into()]);
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_collapsed<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &Annotation) + Send + Sync + 'static,
+    {
+        let annotations = self.annotations.clone();
+        let reported = std::sync::Mutex::new(std::collections::HashSet::new());
+        self.text.observe(move |txn, e| {
+            let deleted = e.delta(txn).iter().any(|d| matches!(d, Delta::Deleted(_)));
+            let mut reported = reported.lock().unwrap();
+            for (id, value) in annotations.iter(txn) {
+                let annotation = match Annotation::resolve(txn, id.into(), value) {
+                    Some(annotation) => annotation,
+                    None => continue,
+                };
+                if !annotation.is_collapsed() {
+                    reported.remove(&annotation.id);
+                } else if deleted && reported.insert(annotation.id.clone()) {
+                    f(txn, &annotation);
+                }
+            }
+        })
+    }
+}
+
+/// Annotation of [AnnotationLayer] resolved against a current state of an annotated text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Unique identifier of current annotation.
+    pub id: Arc<str>,
+    /// Range of an annotated text at the moment when current annotation was read.
+    pub range: Range<u32>,
+    /// Payload attached to current annotation.
+    pub payload: Any,
+}
+
+impl Annotation {
+    /// Checks if all characters of an annotated text have been deleted.
+    pub fn is_collapsed(&self) -> bool {
+        self.range.start >= self.range.end
+    }
+
+    fn resolve<T: ReadTxn>(txn: &T, id: Arc<str>, value: Value) -> Option<Self> {
+        let entry = match value {
+            Value::Any(Any::Map(entry)) => entry,
+            _ => return None,
+        };
+        let start = StickyIndex::from_json(entry.get(START)?).ok()?;
+        let end = StickyIndex::from_json(entry.get(END)?).ok()?;
+        let start = start.get_offset(txn)?.index;
+        let end = end.get_offset(txn)?.index.max(start);
+        let payload = entry.get(PAYLOAD).cloned().unwrap_or(Any::Null);
+        Some(Annotation {
+            id,
+            range: start..end,
+            payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_utils::exchange_updates;
+    use crate::types::annotation::AnnotationLayer;
+    use crate::types::OutOfBounds;
+    use crate::{Any, Doc, GetString, Text, Transact};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn annotations_follow_concurrent_edits() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        let l1 = AnnotationLayer::new(t1.clone(), d1.get_or_insert_map("comments"));
+        {
+            let mut txn = d1.transact_mut();
+            t1.push(&mut txn, "abc def ghi");
+            l1.annotate(&mut txn, "a", 4..7, "def").unwrap();
+            l1.annotate(&mut txn, "b", 0..11, "all").unwrap();
+            assert_eq!(
+                l1.annotate(&mut txn, "c", 5..12, "oob").unwrap_err(),
+                OutOfBounds { len: 11, index: 12 }
+            );
+        }
+
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        let l2 = AnnotationLayer::new(t2.clone(), d2.get_or_insert_map("comments"));
+        exchange_updates(&[&d1, &d2]);
+
+        t1.insert(&mut d1.transact_mut(), 4, "xyz "); // "abc xyz def ghi"
+        t2.insert(&mut d2.transact_mut(), 7, "!"); // "abc def! ghi"
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d2.transact();
+        assert_eq!(t2.get_string(&txn), "abc xyz def! ghi");
+        let a = l2.get(&txn, "a").unwrap();
+        assert_eq!(a.range, 8..11);
+        assert_eq!(a.payload, Any::from("def"));
+        let at = l2.at(&txn, 9);
+        assert_eq!(at.len(), 2);
+        assert_eq!(at[0].id, "b".into());
+        assert_eq!(at[1].id, "a".into());
+    }
+
+    #[test]
+    fn observe_collapsed_annotations() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let layer = AnnotationLayer::new(text.clone(), doc.get_or_insert_map("comments"));
+        {
+            let mut txn = doc.transact_mut();
+            text.push(&mut txn, "hello world");
+            layer.annotate(&mut txn, "hello", 0..5, Any::Null).unwrap();
+            layer.annotate(&mut txn, "world", 6..11, Any::Null).unwrap();
+        }
+        let collapsed = Arc::new(Mutex::new(Vec::new()));
+        let c = collapsed.clone();
+        let _sub = layer.observe_collapsed(move |_, a| c.lock().unwrap().push(a.id.clone()));
+
+        text.remove_range(&mut doc.transact_mut(), 7, 2); // "hello wld"
+        assert!(collapsed.lock().unwrap().is_empty());
+
+        text.remove_range(&mut doc.transact_mut(), 6, 3); // "hello "
+        text.insert(&mut doc.transact_mut(), 0, ">"); // "> hello "
+        text.remove_range(&mut doc.transact_mut(), 0, 1); // "hello "
+        assert_eq!(*collapsed.lock().unwrap(), vec![Arc::<str>::from("world")]);
+
+        let txn = doc.transact();
+        assert!(layer.get(&txn, "world").unwrap().is_collapsed());
+        assert_eq!(layer.get(&txn, "hello").unwrap().range, 0..5);
+        assert!(!layer.get(&txn, "hello").unwrap().is_collapsed());
+    }
+}
@@ -1,10 +1,14 @@
 use crate::block::{EmbedPrelim, ItemContent, ItemPtr, Prelim, Unused};
 use crate::block_iter::BlockIter;
+use crate::branch::VisibleSequence;
+use crate::encoding::read::Error;
 use crate::moving::StickyIndex;
 use crate::transaction::TransactionMut;
+use crate::types::map::{BuilderValue, MapBuilder};
+use crate::types::text::TextPrelim;
 use crate::types::{
-    event_change_set, Branch, BranchPtr, Change, ChangeSet, Path, RootRef, SharedRef, ToJson,
-    TypeRef, Value,
+    event_change_set, Branch, BranchPtr, Change, ChangeSet, EditError, OutOfBounds, Path, RootRef,
+    SharedRef, ToJson, TypeRef, Value,
 };
 use crate::{Any, Assoc, DeepObservable, IndexedSequence, Observable, ReadTxn, ID};
 use std::borrow::Borrow;
@@ -13,6 +17,7 @@ use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 use std::marker::PhantomData;
 use std::ops::Deref;
+use std::sync::Arc;
 
 /// A collection used to store data in an indexed sequence structure. This type is internally
 /// implemented as a double linked list, which may squash values inserted directly one after another
@@ -86,6 +91,11 @@ impl crate::Quotable for ArrayRef {}
 
 impl ToJson for ArrayRef {
     fn to_json<T: ReadTxn>(&self, txn: &T) -> Any {
+        if let Some(gate) = txn.gated_snapshot() {
+            let visible = gate.sequence(txn, &self.0);
+            let values = (0..visible.len()).filter_map(|i| visible.get(i));
+            return Any::Array(values.map(|v| v.to_json(txn)).collect());
+        }
         let mut walker = BlockIter::new(self.0);
         let len = self.0.len();
         let mut buf = vec![Value::default(); len as usize];
@@ -145,7 +155,10 @@ impl TryFrom<Value> for ArrayRef {
 
 pub trait Array: AsRef<Branch> + Sized {
     /// Returns a number of elements stored in current array.
-    fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+    fn len<T: ReadTxn>(&self, txn: &T) -> u32 {
+        if let Some(gate) = txn.gated_snapshot() {
+            return gate.sequence(txn, self.as_ref()).len();
+        }
         self.as_ref().len()
     }
 
@@ -159,19 +172,61 @@ pub trait Array: AsRef<Branch> + Sized {
     ///
     /// This method will panic if provided `index` is greater than the current length of an [ArrayRef].
     fn insert<V>(&self, txn: &mut TransactionMut, index: u32, value: V) -> V::Return
+    where
+        V: Prelim,
+    {
+        match self.try_insert(txn, index, value) {
+            Ok(integrated) => integrated,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Inserts a `value` at the given `index`. Unlike [Array::insert], this method doesn't panic
+    /// when provided `index` is greater than the current length of an array, but returns an
+    /// [EditError::OutOfBounds] error instead. This makes it suitable for indexes coming from
+    /// untrusted sources (eg. scripts or remote plugins). Insertions exceeding document quotas
+    /// or made on a read-only document are rejected with an error as well.
+    ///
+    /// Returns a reference to an integrated preliminary input.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, EditError, OutOfBounds, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// let mut txn = doc.transact_mut();
+    /// array.try_insert(&mut txn, 0, 1).unwrap();
+    ///
+    /// let result = array.try_insert(&mut txn, 5, 2);
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(EditError::OutOfBounds(OutOfBounds { len: 1, index: 5 }))
+    /// ));
+    /// ```
+    fn try_insert<V>(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        value: V,
+    ) -> Result<V::Return, EditError>
     where
         V: Prelim,
     {
         let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
         if walker.try_forward(txn, index) {
-            let ptr = walker.insert_contents(txn, value);
+            let ptr = walker.try_insert_contents(txn, value)?;
             if let Ok(integrated) = ptr.try_into() {
-                integrated
+                Ok(integrated)
             } else {
                 panic!("Defect: unexpected integrated type")
             }
         } else {
-            panic!("Index {} is outside of the range of an array", index);
+            Err(EditError::OutOfBounds(OutOfBounds {
+                len: self.len(txn),
+                index,
+            }))
         }
     }
 
@@ -216,22 +271,99 @@ pub trait Array: AsRef<Branch> + Sized {
         self.remove_range(txn, index, 1)
     }
 
+    /// Removes a single element at provided `index`. Unlike [Array::remove], this method returns
+    /// an error instead of panicking (see: [Array::try_remove_range]).
+    fn try_remove(&self, txn: &mut TransactionMut, index: u32) -> Result<(), EditError> {
+        self.try_remove_range(txn, index, 1)
+    }
+
     /// Removes a range of elements from current array, starting at given `index` up until
     /// a particular number described by `len` has been deleted. This method panics in case when
     /// not all expected elements were removed (due to insufficient number of elements in an array)
     /// or `index` is outside of the bounds of an array.
     fn remove_range(&self, txn: &mut TransactionMut, index: u32, len: u32) {
+        if let Err(e) = self.try_remove_range(txn, index, len) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Removes a range of elements from current array, starting at given `index` up until
+    /// a particular number described by `len` has been deleted. Unlike [Array::remove_range],
+    /// this method doesn't panic when requested range doesn't fit within the bounds of an array
+    /// or current document is a read-only replica, but returns an [EditError] instead, leaving
+    /// an array unchanged.
+    fn try_remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+    ) -> Result<(), EditError> {
+        OutOfBounds::check(self.len(txn), index, len)?;
+        txn.check_writable()?;
         let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
         if walker.try_forward(txn, index) {
-            walker.delete(txn, len)
+            walker.delete(txn, len);
+            Ok(())
         } else {
-            panic!("Index {} is outside of the range of an array", index);
+            Err(EditError::OutOfBounds(OutOfBounds {
+                len: self.len(txn),
+                index,
+            }))
+        }
+    }
+
+    /// Removes all elements of current array, for which a given `predicate` returns `false`.
+    ///
+    /// Array is traversed only once and every contiguous range of rejected elements is removed
+    /// with a single delete operation, so that the produced delete set is minimal and all
+    /// observers receive a single event with coalesced changes once the transaction is committed.
+    ///
+    /// Example:
+    /// ```
+    /// use yrs::{Doc, Transact, Array};
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3, 4, 5, 6]);
+    /// // keep only even numbers
+    /// array.retain(&mut doc.transact_mut(), |value| {
+    ///     value.clone().cast::<f64>().map_or(false, |n| n % 2.0 == 0.0)
+    /// });
+    /// let values: Vec<_> = array.iter(&doc.transact()).collect();
+    /// assert_eq!(values, vec![2.into(), 4.into(), 6.into()]);
+    /// ```
+    fn retain<F>(&self, txn: &mut TransactionMut, mut predicate: F)
+    where
+        F: FnMut(&Value) -> bool,
+    {
+        // `walker` always points at the beginning of a range of elements rejected so far, while
+        // `reader` is used to look ahead over the elements of that range.
+        let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
+        let mut reader = walker.clone();
+        let mut rejected = 0;
+        loop {
+            match reader.read_value(txn) {
+                Some(value) if !predicate(&value) => rejected += 1,
+                next => {
+                    if rejected != 0 {
+                        walker.delete(txn, rejected);
+                        rejected = 0;
+                    }
+                    if next.is_none() {
+                        break;
+                    }
+                    walker.forward(txn, 1);
+                    reader = walker.clone();
+                }
+            }
         }
     }
 
     /// Retrieves a value stored at a given `index`. Returns `None` when provided index was out
     /// of the range of a current array.
     fn get<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<Value> {
+        if let Some(gate) = txn.gated_snapshot() {
+            return gate.sequence(txn, self.as_ref()).get(index);
+        }
         let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
         if walker.try_forward(txn, index) {
             walker.read_value(txn)
@@ -240,6 +372,46 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Returns a length (in bytes) of a binary buffer stored at a given `index`. Returns `None`
+    /// when provided index was out of the range of a current array or an element stored under it
+    /// was not inserted as a [BinaryPrelim].
+    ///
+    /// [BinaryPrelim]: crate::block::BinaryPrelim
+    fn binary_len<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<usize> {
+        let (item, _) = self.as_ref().find_visible(txn, index)?;
+        match &item.content {
+            ItemContent::Binary(data) => Some(data.len()),
+            _ => None,
+        }
+    }
+
+    /// Copies a chunk of a binary buffer stored at a given `index`, starting from a given byte
+    /// `offset`, into provided `buf` without materializing the whole buffer. Returns a number of
+    /// copied bytes, which is lower than the length of `buf` once the end of a binary buffer has
+    /// been reached. Returns `None` when provided index was out of the range of a current array
+    /// or an element stored under it was not inserted as a [BinaryPrelim].
+    ///
+    /// [BinaryPrelim]: crate::block::BinaryPrelim
+    fn read_binary_at<T: ReadTxn>(
+        &self,
+        txn: &T,
+        index: u32,
+        offset: usize,
+        buf: &mut [u8],
+    ) -> Option<usize> {
+        let (item, _) = self.as_ref().find_visible(txn, index)?;
+        match &item.content {
+            ItemContent::Binary(data) => {
+                let start = offset.min(data.len());
+                let end = (start + buf.len()).min(data.len());
+                let read = end - start;
+                buf[..read].copy_from_slice(&data[start..end]);
+                Some(read)
+            }
+            _ => None,
+        }
+    }
+
     /// Moves element found at `source` index into `target` index position. Both indexes refer to a
     /// current state of the document.
     ///
@@ -325,6 +497,100 @@ pub trait Array: AsRef<Branch> + Sized {
     fn iter<'a, T: ReadTxn + 'a>(&self, txn: &'a T) -> ArrayIter<&'a T, T> {
         ArrayIter::from_ref(self.as_ref(), txn)
     }
+
+    /// Returns an iterator, that can be used to lazily traverse over all values stored in a current
+    /// array together with unique [ID]s of the blocks containing them.
+    ///
+    /// Unlike indexes, these identifiers don't change when other elements are inserted or removed
+    /// (including concurrent changes made by remote peers), so they can be used to durably refer
+    /// to specific array entries, e.g. to implement element-level permalinks. Each entry also
+    /// carries a [StickyIndex], which can be used to resolve the current index of an element.
+    ///
+    /// Example:
+    /// ```
+    /// use yrs::{Array, Doc, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, ["a", "b"]);
+    ///
+    /// let entry = array.iter_entries(&doc.transact()).nth(1).unwrap();
+    /// assert_eq!(entry.value, "b".into());
+    ///
+    /// array.insert(&mut doc.transact_mut(), 0, "c");
+    /// let txn = doc.transact();
+    /// let offset = entry.index.get_offset(&txn).unwrap();
+    /// assert_eq!(offset.index, 2);
+    /// assert_eq!(array.get(&txn, offset.index), Some("b".into()));
+    /// ```
+    fn iter_entries<'a, T: ReadTxn + 'a>(&self, txn: &'a T) -> ArrayEntries<&'a T, T> {
+        ArrayEntries {
+            inner: ArrayIter::from_ref(self.as_ref(), txn),
+        }
+    }
+}
+
+/// A single element of an array, returned by [Array::iter_entries].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayEntry {
+    /// Value stored in an array.
+    pub value: Value,
+    /// Unique identifier of the block element containing the [ArrayEntry::value]. It's stable
+    /// across edits made on the array.
+    pub id: ID,
+    /// Sticky index pointing to the position of this element. It can be used to resolve
+    /// the current index of an element after other elements have been inserted or removed.
+    pub index: StickyIndex,
+}
+
+/// Iterator over [ArrayEntry] elements, created by [Array::iter_entries].
+pub struct ArrayEntries<B, T>
+where
+    B: Borrow<T>,
+    T: ReadTxn,
+{
+    inner: ArrayIter<B, T>,
+}
+
+impl<B, T> Iterator for ArrayEntries<B, T>
+where
+    B: Borrow<T>,
+    T: ReadTxn,
+{
+    type Item = ArrayEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let iter = &mut self.inner;
+        if let Some((visible, index)) = iter.gated.as_mut() {
+            let (item, offset) = visible.find(*index)?;
+            let value = visible.get(*index)?;
+            *index += 1;
+            let id = ID::new(item.id.client, item.id.clock + offset);
+            return Some(ArrayEntry {
+                value,
+                id,
+                index: StickyIndex::from_id(id, Assoc::After),
+            });
+        }
+        if iter.inner.finished() {
+            None
+        } else {
+            let mut buf = [Value::default(); 1];
+            let mut ids = [ID::new(0, 0); 1];
+            let txn = iter.txn.borrow();
+            if iter.inner.slice_with_ids(txn, &mut buf, Some(&mut ids)) != 0 {
+                let value = std::mem::take(&mut buf[0]);
+                let id = ids[0];
+                Some(ArrayEntry {
+                    value,
+                    id,
+                    index: StickyIndex::from_id(id, Assoc::After),
+                })
+            } else {
+                None
+            }
+        }
+    }
 }
 
 pub struct ArrayIter<B, T>
@@ -333,6 +599,11 @@ where
     T: ReadTxn,
 {
     inner: BlockIter,
+    /// Elements visible to a transaction gated by a snapshot (see [Doc::transact_at]) together
+    /// with the index of the next element to read.
+    ///
+    /// [Doc::transact_at]: crate::Doc::transact_at
+    gated: Option<(Arc<VisibleSequence>, u32)>,
     txn: B,
     _marker: PhantomData<T>,
 }
@@ -342,8 +613,10 @@ where
     T: Borrow<T> + ReadTxn,
 {
     pub fn from(array: &ArrayRef, txn: T) -> Self {
+        let gated = gated_values(&array.0, &txn);
         ArrayIter {
             inner: BlockIter::new(array.0),
+            gated,
             txn,
             _marker: PhantomData::default(),
         }
@@ -357,12 +630,18 @@ where
     pub fn from_ref(array: &Branch, txn: &'a T) -> Self {
         ArrayIter {
             inner: BlockIter::new(BranchPtr::from(array)),
+            gated: gated_values(array, txn),
             txn,
             _marker: PhantomData::default(),
         }
     }
 }
 
+fn gated_values<T: ReadTxn>(array: &Branch, txn: &T) -> Option<(Arc<VisibleSequence>, u32)> {
+    let gate = txn.gated_snapshot()?;
+    Some((gate.sequence(txn, array), 0))
+}
+
 impl<B, T> Iterator for ArrayIter<B, T>
 where
     B: Borrow<T>,
@@ -371,7 +650,11 @@ where
     type Item = Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.inner.finished() {
+        if let Some((visible, index)) = self.gated.as_mut() {
+            let value = visible.get(*index)?;
+            *index += 1;
+            Some(value)
+        } else if self.inner.finished() {
             None
         } else {
             let mut buf = [Value::default(); 1];
@@ -397,6 +680,21 @@ pub struct ArrayPrelim<T, V>(T)
 where
     T: IntoIterator<Item = V>;
 
+impl ArrayPrelim<Vec<Any>, Any> {
+    /// Parses a given JSON `src` - which must be a JSON array - into a preliminary array. Nested
+    /// JSON objects and arrays are turned into nested [MapRef]s and [ArrayRef]s. See:
+    /// [MapPrelim::from_json].
+    ///
+    /// [MapRef]: crate::MapRef
+    /// [MapPrelim::from_json]: crate::MapPrelim::from_json
+    pub fn from_json(src: &str) -> Result<ArrayBuilder, Error> {
+        match BuilderValue::from(Any::from_json(src)?) {
+            BuilderValue::Array(builder) => Ok(builder),
+            _ => Err(Error::UnexpectedValue),
+        }
+    }
+}
+
 impl<T, V> From<T> for ArrayPrelim<T, V>
 where
     T: IntoIterator<Item = V>,
@@ -436,6 +734,112 @@ where
     }
 }
 
+/// Builder used to define the contents of an array - including nested shared collections - in
+/// a declarative way. See: [MapBuilder].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArrayBuilder {
+    pub(crate) values: Vec<BuilderValue>,
+}
+
+impl ArrayBuilder {
+    pub fn new() -> Self {
+        ArrayBuilder::default()
+    }
+
+    /// Appends a primitive `value`.
+    pub fn value<V: Into<Any>>(mut self, value: V) -> Self {
+        self.values.push(BuilderValue::Any(value.into()));
+        self
+    }
+
+    /// Appends a sequence of primitive `values`.
+    pub fn values<I, V>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Any>,
+    {
+        self.values
+            .extend(values.into_iter().map(|v| BuilderValue::Any(v.into())));
+        self
+    }
+
+    /// Appends a text collection initialized with a given `text`.
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.values.push(BuilderValue::Text(text.into()));
+        self
+    }
+
+    /// Appends a nested map, which contents are defined by a given function `f`.
+    pub fn map<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(MapBuilder) -> MapBuilder,
+    {
+        self.values.push(BuilderValue::Map(f(MapBuilder::new())));
+        self
+    }
+
+    /// Appends a nested array, which contents are defined by a given function `f`.
+    pub fn array<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(ArrayBuilder) -> ArrayBuilder,
+    {
+        self.values
+            .push(BuilderValue::Array(f(ArrayBuilder::new())));
+        self
+    }
+
+    /// Returns a number of elements defined so far.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Checks if no elements have been defined so far.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Prelim for ArrayBuilder {
+    type Return = ArrayRef;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        let inner = Branch::new(TypeRef::Array);
+        (ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
+        let array = ArrayRef::from(inner_ref);
+        let mut primitives = Vec::new();
+        for value in self.values {
+            if let BuilderValue::Any(value) = value {
+                primitives.push(value);
+                continue;
+            }
+            if !primitives.is_empty() {
+                // consecutive primitive values are stored within a single block
+                let index = array.len(txn);
+                array.insert_range(txn, index, std::mem::take(&mut primitives));
+            }
+            match value {
+                BuilderValue::Text(text) => {
+                    array.push_back(txn, TextPrelim::new(text));
+                }
+                BuilderValue::Map(builder) => {
+                    array.push_back(txn, builder);
+                }
+                BuilderValue::Array(builder) => {
+                    array.push_back(txn, builder);
+                }
+                BuilderValue::Any(_) => { /* handled above */ }
+            }
+        }
+        if !primitives.is_empty() {
+            let index = array.len(txn);
+            array.insert_range(txn, index, primitives);
+        }
+    }
+}
+
 impl Default for ArrayPrelim<[u32; 0], u32> {
     fn default() -> Self {
         ArrayPrelim([])
@@ -465,6 +869,10 @@ where
 }
 
 /// Event generated by [ArrayRef::observe] method. Emitted during transaction commit phase.
+///
+/// Changes carried by this event are computed lazily on the first call of any of the
+/// [ArrayEvent::delta], [ArrayEvent::inserts] or [ArrayEvent::removes] methods and cached for
+/// the lifetime of the event, so that all subsequent calls (also made by other observers) are cheap.
 pub struct ArrayEvent {
     pub(crate) current_target: BranchPtr,
     target: ArrayRef,
@@ -492,7 +900,13 @@ impl ArrayEvent {
     }
 
     /// Returns summary of changes made over corresponding [ArrayRef] collection within
-    /// a bounds of current transaction.
+    /// a bounds of current transaction. Delta is a sequence of [Change]s, which applied in order
+    /// from the beginning of an array state before the transaction (skipping [Change::Retain]
+    /// elements, removing [Change::Removed] ones and inserting [Change::Added]) produces the
+    /// array state after transaction.
+    ///
+    /// Existing elements moved within current transaction are reported as [Change::Moved] at
+    /// their new position (and [Change::Removed] at the old one) instead of being re-inserted.
     pub fn delta(&self, txn: &TransactionMut) -> &[Change] {
         self.changes(txn).delta.as_slice()
     }
@@ -517,9 +931,12 @@ impl ArrayEvent {
 
 #[cfg(test)]
 mod test {
+    use crate::block::BinaryPrelim;
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
     use crate::types::map::MapPrelim;
-    use crate::types::{Change, DeepObservable, Event, Path, PathSegment, ToJson, Value};
+    use crate::types::{
+        Change, DeepObservable, EditError, Event, OutOfBounds, Path, PathSegment, ToJson, Value,
+    };
     use crate::{
         any, Any, Array, ArrayPrelim, Assoc, Doc, Map, MapRef, Observable, SharedRef, StateVector,
         Transact, Update, ID,
@@ -527,6 +944,78 @@ mod test {
     use std::collections::{HashMap, HashSet};
     use std::sync::{Arc, Mutex};
 
+    #[test]
+    fn binary_chunks() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let data: Vec<u8> = (0..=255).collect();
+        {
+            let mut txn = d1.transact_mut();
+            a1.push_back(&mut txn, "a");
+            a1.push_back(&mut txn, BinaryPrelim::from(data.clone()));
+        }
+
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d2.transact();
+        assert_eq!(a2.binary_len(&txn, 0), None);
+        assert_eq!(a2.binary_len(&txn, 1), Some(256));
+        assert_eq!(a2.get(&txn, 1), Some(Value::Any(Any::from(data.clone()))));
+
+        let mut buf = [0u8; 100];
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while let Some(read) = a2.read_binary_at(&txn, 1, offset, &mut buf) {
+            if read == 0 {
+                break;
+            }
+            chunks.push(read);
+            offset += read;
+        }
+        assert_eq!(chunks, vec![100, 100, 56]);
+        assert_eq!(&buf[..56], &data[200..]);
+        assert_eq!(a2.read_binary_at(&txn, 2, 0, &mut buf), None);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let doc = Doc::with_client_id(1);
+        let a = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        a.insert_range(&mut txn, 0, [1, 2, 3]);
+
+        assert!(matches!(
+            a.try_insert(&mut txn, 4, 4),
+            Err(EditError::OutOfBounds(OutOfBounds { len: 3, index: 4 }))
+        ));
+        assert_eq!(
+            a.try_remove_range(&mut txn, 4, 1).unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds { len: 3, index: 4 })
+        );
+        assert_eq!(
+            a.try_remove(&mut txn, 3).unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds { len: 3, index: 4 })
+        );
+        assert_eq!(
+            a.try_remove_range(&mut txn, 1, 3).unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds { len: 3, index: 4 })
+        );
+        assert_eq!(
+            a.try_remove_range(&mut txn, 1, u32::MAX).unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds {
+                len: 3,
+                index: u32::MAX
+            })
+        );
+        assert_eq!(a.to_json(&txn), any!([1, 2, 3]));
+
+        a.try_insert(&mut txn, 3, 4).unwrap();
+        a.try_remove_range(&mut txn, 0, 2).unwrap();
+        assert_eq!(a.to_json(&txn), any!([3, 4]));
+    }
+
     #[test]
     fn push_back() {
         let doc = Doc::with_client_id(1);
@@ -583,7 +1072,8 @@ mod test {
 
         let a2 = d2.get_or_insert_array("array");
         let mut t2 = d2.transact_mut();
-        t2.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+            .unwrap();
         let actual: Vec<_> = a2.iter(&t2).collect();
 
         assert_eq!(actual, vec!["Hi".into()]);
@@ -783,6 +1273,59 @@ mod test {
         assert_eq!(a2, a3, "Peer 2 and peer 3 states are different");
     }
 
+    #[test]
+    fn iter_entries_ids_are_stable() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4]);
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+
+        exchange_updates(&[&d1, &d2]);
+
+        let entries: Vec<_> = a1.iter_entries(&d1.transact()).collect();
+        let ids: Vec<_> = entries.iter().map(|e| e.id).collect();
+        assert_eq!(
+            ids,
+            vec![ID::new(1, 0), ID::new(1, 1), ID::new(1, 2), ID::new(1, 3)]
+        );
+
+        // concurrent changes on both peers
+        a1.insert(&mut d1.transact_mut(), 0, 0);
+        a2.remove(&mut d2.transact_mut(), 1);
+
+        exchange_updates(&[&d1, &d2]);
+
+        // sticky indexes resolve to the current positions of the original elements
+        {
+            let txn = d1.transact();
+            let e = &entries[2];
+            let offset = e.index.get_offset(&txn).unwrap();
+            assert_eq!(offset.index, 2);
+            assert_eq!(a1.get(&txn, offset.index), Some(e.value.clone()));
+        }
+
+        // moved elements retain their identifiers
+        a2.move_to(&mut d2.transact_mut(), 3, 0);
+
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d1.transact();
+        let actual: Vec<_> = a1
+            .iter_entries(&txn)
+            .map(|e| (e.value.to_json(&txn), e.id))
+            .collect();
+        assert_eq!(
+            actual,
+            vec![
+                (Any::from(4), ID::new(1, 3)),
+                (Any::from(0), ID::new(1, 4)),
+                (Any::from(1), ID::new(1, 0)),
+                (Any::from(3), ID::new(1, 2)),
+            ]
+        );
+    }
+
     #[test]
     fn removals_in_late_sync() {
         let d1 = Doc::with_client_id(1);
@@ -989,7 +1532,8 @@ mod test {
             let sv = t2.state_vector();
             let mut encoder = EncoderV1::new();
             t1.encode_diff(&sv, &mut encoder);
-            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap());
+            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap())
+                .unwrap();
         }
 
         assert_eq!(
@@ -1264,7 +1808,11 @@ mod test {
             Some(Arc::new(vec![
                 Change::Removed(1),
                 Change::Retain(1),
-                Change::Added(vec![2.into()])
+                Change::Moved {
+                    from: 0,
+                    to: 1,
+                    len: 1
+                }
             ]))
         )
     }
@@ -1297,7 +1845,11 @@ mod test {
             assert_eq!(
                 actual,
                 Some(Arc::new(vec![
-                    Change::Added(vec![2.into()]),
+                    Change::Moved {
+                        from: 1,
+                        to: 0,
+                        len: 1
+                    },
                     Change::Retain(1),
                     Change::Removed(1)
                 ]))
@@ -1324,12 +1876,56 @@ mod test {
                 Some(Arc::new(vec![
                     Change::Removed(1),
                     Change::Retain(1),
-                    Change::Added(vec![2.into()])
+                    Change::Moved {
+                        from: 0,
+                        to: 1,
+                        len: 1
+                    }
                 ]))
             );
         }
     }
 
+    #[test]
+    fn move_range_delta() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4, 5]);
+        exchange_updates(&[&d1, &d2]);
+
+        let e2 = Arc::new(ArcSwapOption::default());
+        let inner = e2.clone();
+        let _s2 = a2.observe(move |txn, e| {
+            inner.store(Some(Arc::new(e.delta(txn).to_vec())));
+        });
+
+        {
+            let mut txn = d1.transact_mut();
+            a1.move_range_to(&mut txn, 0, Assoc::After, 1, Assoc::Before, 5);
+            a1.insert(&mut txn, 0, 6);
+        }
+        assert_eq!(a1.to_json(&d1.transact()), vec![6, 3, 4, 5, 1, 2].into());
+        exchange_updates(&[&d1, &d2]);
+
+        // moves are also recognized on remote peers
+        assert_eq!(a2.to_json(&d2.transact()), vec![6, 3, 4, 5, 1, 2].into());
+        assert_eq!(
+            e2.load_full(),
+            Some(Arc::new(vec![
+                Change::Removed(2),
+                Change::Added(vec![6.into()]),
+                Change::Retain(3),
+                Change::Moved {
+                    from: 0,
+                    to: 4,
+                    len: 2
+                }
+            ]))
+        );
+    }
+
     #[test]
     fn move_cycles() {
         let d1 = Doc::with_client_id(1);
@@ -1642,4 +2238,49 @@ mod test {
         let v = iter.next();
         assert_eq!(v, None);
     }
+
+    #[test]
+    fn retain() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let removed = Arc::new(ArcSwapOption::default());
+        let delta = Arc::new(ArcSwapOption::default());
+        let (removed_c, delta_c) = (removed.clone(), delta.clone());
+        let _sub = array.observe(move |txn, e| {
+            removed_c.store(Some(Arc::new(e.removes(txn).clone())));
+            delta_c.store(Some(Arc::new(e.delta(txn).to_vec())));
+        });
+
+        let keep: HashSet<i32> = HashSet::from([1, 4, 8]);
+        array.retain(&mut doc.transact_mut(), |value| {
+            value
+                .clone()
+                .cast::<i32>()
+                .map_or(false, |n| keep.contains(&n))
+        });
+
+        let values: Vec<_> = array.iter(&doc.transact()).collect();
+        assert_eq!(values, vec![1.into(), 4.into(), 8.into()]);
+        assert_eq!(
+            delta.swap(None),
+            Some(
+                vec![
+                    Change::Retain(1),
+                    Change::Removed(2),
+                    Change::Retain(1),
+                    Change::Removed(3)
+                ]
+                .into()
+            )
+        );
+        // removed elements form two separate blocks
+        assert_eq!(removed.swap(None).unwrap().len(), 2);
+
+        // rejecting everything leaves an empty array
+        array.retain(&mut doc.transact_mut(), |_| false);
+        assert_eq!(array.len(&doc.transact()), 0);
+        assert_eq!(delta.swap(None), Some(vec![Change::Removed(3)].into()));
+    }
 }
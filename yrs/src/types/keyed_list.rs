@@ -0,0 +1,878 @@
+use crate::block::{EmbedPrelim, ItemContent, ItemPosition, ItemPtr, Prelim};
+use crate::slice::ItemSlice;
+use crate::transaction::TransactionMut;
+use crate::types::{
+    event_keys, Branch, BranchPtr, EntryChange, Path, RootRef, SharedRef, ToJson, TypeRef, Value,
+};
+use crate::*;
+use std::borrow::Borrow;
+use std::cell::UnsafeCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::{TryFrom, TryInto};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Collection of key-value entries, which - unlike [MapRef] - are kept in a user-defined order.
+/// Entries can be accessed both by their keys and by their index, inserted at any position and
+/// moved around without changing their values.
+///
+/// Internally [KeyedListRef] keeps entry values in its map component (with the same
+/// last-write-wins conflict resolution as [MapRef]) and a sequence of keys in its sequence
+/// component, which defines the entries order. Both are always kept consistent:
+///
+/// - If the same key has been inserted or moved concurrently to different positions, the first
+///   position in the sequence wins.
+/// - Entries without a position (e.g. when a key was moved on one peer and concurrently
+///   overridden on another) are ordered after all other entries, by their keys.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, KeyedList, Transact};
+///
+/// let doc = Doc::new();
+/// let sections = doc.get_or_insert_keyed_list("sections");
+/// let mut txn = doc.transact_mut();
+///
+/// sections.push_back(&mut txn, "intro", "Hello");
+/// sections.push_back(&mut txn, "summary", "Bye");
+/// sections.insert(&mut txn, 1, "body", "Lorem ipsum");
+/// assert_eq!(sections.keys(&txn), vec!["intro".into(), "body".into(), "summary".into()]);
+///
+/// // move entry without changing its value
+/// sections.move_to(&mut txn, "summary", 0);
+/// assert_eq!(sections.index_of(&txn, "summary"), Some(0));
+/// assert_eq!(sections.get(&txn, "summary"), Some("Bye".into()));
+///
+/// // update value without changing entry position
+/// sections.set(&mut txn, "body", "dolor sit amet");
+/// assert_eq!(sections.index_of(&txn, "body"), Some(2));
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone)]
+pub struct KeyedListRef(BranchPtr);
+
+impl RootRef for KeyedListRef {
+    fn type_ref() -> TypeRef {
+        TypeRef::KeyedList
+    }
+}
+impl SharedRef for KeyedListRef {}
+impl KeyedList for KeyedListRef {}
+
+impl DeepObservable for KeyedListRef {}
+impl Observable for KeyedListRef {
+    type Event = KeyedListEvent;
+}
+
+impl ToJson for KeyedListRef {
+    /// Converts current keyed list into a JSON-like array of `[key, value]` pairs, preserving the
+    /// entries order.
+    fn to_json<T: ReadTxn>(&self, txn: &T) -> Any {
+        let mut res = Vec::new();
+        for (key, value) in self.iter(txn) {
+            res.push(Any::from(vec![Any::String(key), value.to_json(txn)]));
+        }
+        Any::from(res)
+    }
+}
+
+impl AsRef<Branch> for KeyedListRef {
+    fn as_ref(&self) -> &Branch {
+        self.0.deref()
+    }
+}
+
+impl Eq for KeyedListRef {}
+impl PartialEq for KeyedListRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id() == other.0.id()
+    }
+}
+
+impl TryFrom<ItemPtr> for KeyedListRef {
+    type Error = ItemPtr;
+
+    fn try_from(value: ItemPtr) -> Result<Self, Self::Error> {
+        match value.as_branch() {
+            Some(branch) if matches!(branch.type_ref, TypeRef::KeyedList) => {
+                Ok(KeyedListRef::from(branch))
+            }
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for KeyedListRef {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::YKeyedList(value) => Ok(value),
+            other => Err(other),
+        }
+    }
+}
+
+impl From<BranchPtr> for KeyedListRef {
+    fn from(inner: BranchPtr) -> Self {
+        KeyedListRef(inner)
+    }
+}
+
+pub trait KeyedList: AsRef<Branch> + Sized {
+    /// Returns a number of entries stored within current keyed list.
+    fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+        with_index(self.as_ref(), |index| index.len())
+    }
+
+    /// Returns keys of all entries stored within current keyed list, in their order.
+    fn keys<T: ReadTxn>(&self, _txn: &T) -> Vec<Arc<str>> {
+        with_index(self.as_ref(), |index| index.keys().cloned().collect())
+    }
+
+    /// Returns an iterator over all entries of current keyed list, in their order.
+    fn iter<'a, T: ReadTxn + 'a>(&'a self, txn: &'a T) -> KeyedListIter<'a, &'a T, T> {
+        KeyedListIter::new(self.as_ref(), txn)
+    }
+
+    /// Returns a value stored under a given `key`, if it exists.
+    fn get<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<Value> {
+        self.as_ref().get(txn, key)
+    }
+
+    /// Returns a key of an entry at a given `index`, if it exists.
+    fn key_at<T: ReadTxn>(&self, _txn: &T, index: u32) -> Option<Arc<str>> {
+        with_index(self.as_ref(), |i| i.key_at(index).cloned())
+    }
+
+    /// Returns a current index of an entry stored under a given `key`, if it exists.
+    fn index_of<T: ReadTxn>(&self, _txn: &T, key: &str) -> Option<u32> {
+        with_index(self.as_ref(), |index| index.index_of(key))
+    }
+
+    /// Checks if an entry with a given `key` exists within current keyed list.
+    fn contains_key<T: ReadTxn>(&self, _txn: &T, key: &str) -> bool {
+        is_live(self.as_ref(), key)
+    }
+
+    /// Inserts a new entry with a given `key` and `value` at a given `index`. If an entry with the
+    /// same key already existed, its value is overridden and it's moved to a given `index`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if provided `index` is greater than the number of entries (not
+    /// counting the overridden entry).
+    fn insert<K, V>(&self, txn: &mut TransactionMut, index: u32, key: K, value: V) -> V::Return
+    where
+        K: Into<Arc<str>>,
+        V: Prelim,
+    {
+        let key = key.into();
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, move |txn, i| {
+            i.place(txn, branch, &key, index);
+            insert_value(txn, branch, key, value)
+        })
+    }
+
+    /// Inserts a new entry with a given `key` and `value` at the beginning of current keyed list.
+    /// If an entry with the same key already existed, its value is overridden and it's moved.
+    fn push_front<K, V>(&self, txn: &mut TransactionMut, key: K, value: V) -> V::Return
+    where
+        K: Into<Arc<str>>,
+        V: Prelim,
+    {
+        self.insert(txn, 0, key, value)
+    }
+
+    /// Inserts a new entry with a given `key` and `value` at the end of current keyed list.
+    /// If an entry with the same key already existed, its value is overridden and it's moved.
+    fn push_back<K, V>(&self, txn: &mut TransactionMut, key: K, value: V) -> V::Return
+    where
+        K: Into<Arc<str>>,
+        V: Prelim,
+    {
+        let key = key.into();
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, move |txn, i| {
+            i.forget(txn, &key);
+            // entries without position markers are ordered after the last marker
+            let index = i.placed.len() as u32;
+            i.place(txn, branch, &key, index);
+            insert_value(txn, branch, key, value)
+        })
+    }
+
+    /// Sets a `value` of an entry stored under a given `key`. If such entry already existed, it
+    /// will keep its current position. Otherwise, a new entry is added at the end of current
+    /// keyed list.
+    fn set<K, V>(&self, txn: &mut TransactionMut, key: K, value: V) -> V::Return
+    where
+        K: Into<Arc<str>>,
+        V: Prelim,
+    {
+        let key = key.into();
+        if self.contains_key(txn, &key) {
+            let branch = BranchPtr::from(self.as_ref());
+            update_index(txn, branch, move |txn, _| {
+                insert_value(txn, branch, key, value)
+            })
+        } else {
+            self.push_back(txn, key, value)
+        }
+    }
+
+    /// Moves an entry stored under a given `key` to a given `index` without changing its value.
+    /// Returns `false` if no such entry existed.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if provided `index` is greater than or equal to the number of
+    /// entries.
+    fn move_to(&self, txn: &mut TransactionMut, key: &str, index: u32) -> bool {
+        if !self.contains_key(txn, key) {
+            return false;
+        }
+        let branch = BranchPtr::from(self.as_ref());
+        let key: Arc<str> = key.into();
+        update_index(txn, branch, |txn, i| i.place(txn, branch, &key, index));
+        true
+    }
+
+    /// Removes an entry stored under a given `key`, returning its value if it existed.
+    fn remove(&self, txn: &mut TransactionMut, key: &str) -> Option<Value> {
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, |txn, i| {
+            i.forget(txn, key);
+            branch.remove(txn, key)
+        })
+    }
+
+    /// Removes all entries from current keyed list.
+    fn clear(&self, txn: &mut TransactionMut) {
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, |txn, i| {
+            branch.remove_at(txn, 0, branch.len());
+            for (_, ptr) in branch.map.iter() {
+                txn.delete(*ptr);
+            }
+            *i = KeyedListIndex::default();
+        })
+    }
+}
+
+/// Index of a keyed list entries order, cached in a [BranchMeta] of a keyed list branch, so that
+/// entries can be looked up by their position (and vice versa) without scanning the sequence
+/// component. It's kept up to date by [KeyedList] methods, dropped whenever a keyed list is
+/// changed by any other means (e.g. remote updates or undo/redo) and rebuilt when a transaction
+/// which made these changes is committed.
+///
+/// [BranchMeta]: crate::BranchMeta
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct KeyedListIndex {
+    /// Keys of live entries which have position markers, in their order.
+    placed: Vec<Arc<str>>,
+    /// Positions of `placed` keys.
+    positions: HashMap<Arc<str>, u32>,
+    /// Keys of live entries without position markers, ordered after all `placed` entries.
+    orphans: BTreeSet<Arc<str>>,
+    /// IDs of all visible position markers of each key (including removed entries), in their
+    /// sequence order. The first marker defines a position of an entry.
+    markers: HashMap<Arc<str>, Vec<ID>>,
+}
+
+impl KeyedListIndex {
+    fn new(branch: &Branch) -> Self {
+        let mut index = KeyedListIndex::default();
+        let mut current = branch.start;
+        while let Some(item) = current {
+            if !item.is_deleted() && item.is_countable() {
+                if let ItemContent::Any(markers) = &item.content {
+                    for (i, marker) in markers.iter().enumerate() {
+                        if let Any::String(key) = marker {
+                            let id = ID::new(item.id.client, item.id.clock + i as u32);
+                            let ids = index.markers.entry(key.clone()).or_default();
+                            ids.push(id);
+                            // concurrently inserted duplicates: first position wins
+                            if ids.len() == 1 && is_live(branch, key) {
+                                let position = index.placed.len() as u32;
+                                index.positions.insert(key.clone(), position);
+                                index.placed.push(key.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            current = item.right;
+        }
+        for (key, item) in branch.map.iter() {
+            if !item.is_deleted() && !index.positions.contains_key(key) {
+                index.orphans.insert(key.clone());
+            }
+        }
+        index
+    }
+
+    fn len(&self) -> u32 {
+        (self.placed.len() + self.orphans.len()) as u32
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.placed.iter().chain(self.orphans.iter())
+    }
+
+    fn key_at(&self, index: u32) -> Option<&Arc<str>> {
+        let index = index as usize;
+        match self.placed.get(index) {
+            Some(key) => Some(key),
+            None => self.orphans.iter().nth(index - self.placed.len()),
+        }
+    }
+
+    fn index_of(&self, key: &str) -> Option<u32> {
+        if let Some(position) = self.positions.get(key) {
+            Some(*position)
+        } else if self.orphans.contains(key) {
+            let preceding = self.orphans.iter().take_while(|k| k.as_ref() < key).count();
+            Some((self.placed.len() + preceding) as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Removes all position markers of a given `key` and drops it from the index.
+    fn forget(&mut self, txn: &mut TransactionMut, key: &str) {
+        if let Some(ids) = self.markers.remove(key) {
+            for id in ids {
+                if let Some(slice) = txn.store.blocks.get_item_clean_start(&id) {
+                    let slice = ItemSlice::new(slice.ptr, slice.start, slice.start);
+                    let ptr = txn.store.materialize(slice);
+                    txn.delete(ptr);
+                }
+            }
+        }
+        if let Some(position) = self.positions.remove(key) {
+            let position = position as usize;
+            self.placed.remove(position);
+            for (i, key) in self.placed.iter().enumerate().skip(position) {
+                if let Some(p) = self.positions.get_mut(key) {
+                    *p = i as u32;
+                }
+            }
+        }
+        self.orphans.remove(key);
+    }
+
+    /// Places a position marker of a given `key` so that it will be found at a given `index` of
+    /// a keyed list order. All previous markers of that key are removed.
+    fn place(&mut self, txn: &mut TransactionMut, branch: BranchPtr, key: &Arc<str>, index: u32) {
+        self.forget(txn, key);
+        if index > self.len() {
+            panic!(
+                "Cannot insert entry at index {} of keyed list of length {}",
+                index,
+                self.len()
+            );
+        }
+        // entries without markers are ordered at the end
+        let index = (index as usize).min(self.placed.len());
+        let (left, right) = if let Some(next) = self.placed.get(index) {
+            let id = self.markers[next][0];
+            let slice = txn.store.blocks.get_item_clean_start(&id).unwrap();
+            let right = txn.store.materialize(slice);
+            (right.left, Some(right))
+        } else if let Some(prev) = self.placed.last() {
+            let id = self.markers[prev][0];
+            let slice = txn.store.blocks.get_item_clean_end(&id).unwrap();
+            let left = txn.store.materialize(slice);
+            (Some(left), left.right)
+        } else {
+            (None, None)
+        };
+        let pos = ItemPosition {
+            parent: branch.into(),
+            left,
+            right,
+            index: 0,
+            current_attrs: None,
+        };
+        let ptr = txn.create_item(&pos, Any::String(key.clone()), None);
+        self.markers.insert(key.clone(), vec![*ptr.id()]);
+        self.placed.insert(index, key.clone());
+        for (i, key) in self.placed.iter().enumerate().skip(index + 1) {
+            if let Some(p) = self.positions.get_mut(key) {
+                *p = i as u32;
+            }
+        }
+        self.positions.insert(key.clone(), index as u32);
+    }
+}
+
+/// Rebuilds a cached [KeyedListIndex] of a given keyed list `branch` if it was dropped.
+pub(crate) fn reindex(branch: BranchPtr) {
+    // safety: it's called only by a read-write transaction
+    let meta = unsafe { branch.meta_mut() };
+    if !meta.contains::<KeyedListIndex>() {
+        meta.insert(KeyedListIndex::new(&branch));
+    }
+}
+
+/// Drops a cached [KeyedListIndex] of a given keyed list `branch`.
+pub(crate) fn invalidate_index(branch: BranchPtr) {
+    // safety: it's called only by a read-write transaction
+    unsafe { branch.meta_mut() }.remove::<KeyedListIndex>();
+}
+
+/// Calls `f` with a cached index of a keyed list `branch` or - if it's not there - with an index
+/// built on demand.
+fn with_index<F, R>(branch: &Branch, f: F) -> R
+where
+    F: FnOnce(&KeyedListIndex) -> R,
+{
+    match branch.meta.get::<KeyedListIndex>() {
+        Some(index) => f(index),
+        None => f(&KeyedListIndex::new(branch)),
+    }
+}
+
+/// Calls `f`, which modifies a keyed list `branch`, with its index taken out of the cache, so
+/// that it's not dropped by the changes made meanwhile. Index is cached again afterwards.
+fn update_index<F, R>(txn: &mut TransactionMut, branch: BranchPtr, f: F) -> R
+where
+    F: FnOnce(&mut TransactionMut, &mut KeyedListIndex) -> R,
+{
+    let cached = unsafe { branch.meta_mut() }.remove::<KeyedListIndex>();
+    let mut index = cached.unwrap_or_else(|| KeyedListIndex::new(&branch));
+    let result = f(txn, &mut index);
+    unsafe { branch.meta_mut() }.insert(index);
+    result
+}
+
+fn is_live(branch: &Branch, key: &str) -> bool {
+    match branch.map.get(key) {
+        Some(item) => !item.is_deleted(),
+        None => false,
+    }
+}
+
+fn insert_value<V: Prelim>(
+    txn: &mut TransactionMut,
+    branch: BranchPtr,
+    key: Arc<str>,
+    value: V,
+) -> V::Return {
+    let pos = ItemPosition {
+        parent: branch.into(),
+        left: branch.map.get(&key).cloned(),
+        right: None,
+        index: 0,
+        current_attrs: None,
+    };
+    let ptr = txn.create_item(&pos, value, Some(key));
+    if let Ok(integrated) = ptr.try_into() {
+        integrated
+    } else {
+        panic!("Defect: unexpected integrated type")
+    }
+}
+
+/// Iterator over entries of a [KeyedList], returned by [KeyedList::iter].
+#[derive(Debug)]
+pub struct KeyedListIter<'a, B, T> {
+    branch: &'a Branch,
+    keys: std::vec::IntoIter<Arc<str>>,
+    _txn: B,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, B, T> KeyedListIter<'a, B, T>
+where
+    B: Borrow<T>,
+    T: ReadTxn,
+{
+    pub fn new(branch: &'a Branch, txn: B) -> Self {
+        KeyedListIter {
+            branch,
+            keys: with_index(branch, |index| index.keys().cloned().collect::<Vec<_>>()).into_iter(),
+            _txn: txn,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, B, T> Iterator for KeyedListIter<'a, B, T>
+where
+    B: Borrow<T>,
+    T: ReadTxn,
+{
+    type Item = (Arc<str>, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        let item = self.branch.map.get(&key)?;
+        match item.content.get_last() {
+            Some(value) => Some((key, value)),
+            None => self.next(),
+        }
+    }
+}
+
+/// Preliminary keyed list. It can be used to initialize a [KeyedListRef], nested within another
+/// shared collection, with a given ordered list of entries.
+#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyedListPrelim<T>(Vec<(Arc<str>, T)>);
+
+impl<T> KeyedListPrelim<T> {
+    pub fn new() -> Self {
+        KeyedListPrelim(Vec::new())
+    }
+}
+
+impl<T> Default for KeyedListPrelim<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for KeyedListPrelim<V>
+where
+    K: Into<Arc<str>>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        KeyedListPrelim(iter.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+impl<K, V, const N: usize> From<[(K, V); N]> for KeyedListPrelim<V>
+where
+    K: Into<Arc<str>>,
+{
+    fn from(arr: [(K, V); N]) -> Self {
+        let mut entries = Vec::with_capacity(N);
+        for (k, v) in arr {
+            entries.push((k.into(), v));
+        }
+        KeyedListPrelim(entries)
+    }
+}
+
+impl<T: Prelim> Prelim for KeyedListPrelim<T> {
+    type Return = KeyedListRef;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        let inner = Branch::new(TypeRef::KeyedList);
+        (ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
+        let list = KeyedListRef::from(inner_ref);
+        for (key, value) in self.0 {
+            list.push_back(txn, key, value);
+        }
+    }
+}
+
+impl<T: Prelim> From<KeyedListPrelim<T>> for EmbedPrelim<KeyedListPrelim<T>> {
+    #[inline]
+    fn from(prelim: KeyedListPrelim<T>) -> Self {
+        EmbedPrelim::Shared(prelim)
+    }
+}
+
+/// Entry changes of a [KeyedListEvent], computed lazily from a set of changed keys.
+type KeyChanges = Result<HashMap<Arc<str>, EntryChange>, HashSet<Option<Arc<str>>>>;
+
+/// Event generated by [KeyedListRef::observe] method. Emitted during transaction commit phase.
+pub struct KeyedListEvent {
+    pub(crate) current_target: BranchPtr,
+    target: KeyedListRef,
+    order_changed: bool,
+    keys: UnsafeCell<KeyChanges>,
+}
+
+impl KeyedListEvent {
+    pub(crate) fn new(branch_ref: BranchPtr, key_changes: HashSet<Option<Arc<str>>>) -> Self {
+        let current_target = branch_ref;
+        KeyedListEvent {
+            target: KeyedListRef::from(branch_ref),
+            current_target,
+            // position markers are stored in a sequence component
+            order_changed: key_changes.contains(&None),
+            keys: UnsafeCell::new(Err(key_changes)),
+        }
+    }
+
+    /// Returns a [KeyedListRef] instance which emitted this event.
+    pub fn target(&self) -> &KeyedListRef {
+        &self.target
+    }
+
+    /// Returns a path from root type down to [KeyedListRef] instance which emitted this event.
+    pub fn path(&self) -> Path {
+        Branch::path(self.current_target, self.target.0)
+    }
+
+    /// Returns true if order of entries might have been changed within bounds of current
+    /// transaction, either because entries were inserted, moved or removed.
+    pub fn order_changed(&self) -> bool {
+        self.order_changed
+    }
+
+    /// Returns a summary of entry values changes made over corresponding [KeyedListRef] within
+    /// bounds of current transaction. Entries which were only moved are not present.
+    pub fn keys(&self, txn: &TransactionMut) -> &HashMap<Arc<str>, EntryChange> {
+        let keys = unsafe { self.keys.get().as_mut().unwrap() };
+        if let Err(subs) = keys {
+            *keys = Ok(event_keys(txn, self.target.0, subs));
+        }
+        match keys {
+            Ok(keys) => keys,
+            Err(_) => panic!("Defect: should not happen"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block::ItemPtr;
+    use crate::branch::BranchPtr;
+    use crate::test_utils::exchange_updates;
+    use crate::types::keyed_list::{KeyedListIndex, KeyedListPrelim};
+    use crate::types::{EntryChange, ToJson};
+    use crate::{
+        any, Doc, KeyedList, KeyedListRef, Map, MapPrelim, MapRef, Observable, Transact, Value,
+    };
+    use std::convert::TryFrom;
+    use std::sync::{Arc, Mutex};
+
+    fn keys(list: &KeyedListRef, doc: &Doc) -> Vec<String> {
+        let txn = doc.transact();
+        list.keys(&txn).iter().map(|k| k.to_string()).collect()
+    }
+
+    fn assert_index(list: &KeyedListRef) {
+        let branch = list.as_ref();
+        let cached = branch.meta.get::<KeyedListIndex>();
+        assert_eq!(cached, Some(&KeyedListIndex::new(branch)));
+    }
+
+    #[test]
+    fn insert_move_remove() {
+        let doc = Doc::with_client_id(1);
+        let list = doc.get_or_insert_keyed_list("list");
+        let mut txn = doc.transact_mut();
+        list.push_back(&mut txn, "b", 2);
+        list.push_front(&mut txn, "a", 1);
+        list.push_back(&mut txn, "d", 4);
+        list.insert(&mut txn, 2, "c", 3);
+        assert_eq!(
+            list.to_json(&txn),
+            any!([["a", 1], ["b", 2], ["c", 3], ["d", 4]])
+        );
+
+        // override and move
+        list.insert(&mut txn, 0, "c", 30);
+        assert_eq!(
+            list.keys(&txn),
+            vec!["c".into(), "a".into(), "b".into(), "d".into()]
+        );
+        assert_eq!(list.len(&txn), 4);
+
+        assert!(list.move_to(&mut txn, "c", 3));
+        assert!(!list.move_to(&mut txn, "x", 0));
+        assert_eq!(list.key_at(&txn, 3), Some("c".into()));
+        assert_eq!(list.index_of(&txn, "d"), Some(2));
+
+        list.set(&mut txn, "a", 10);
+        list.set(&mut txn, "e", 5);
+        assert_eq!(
+            list.to_json(&txn),
+            any!([["a", 10], ["b", 2], ["d", 4], ["c", 30], ["e", 5]])
+        );
+
+        assert_eq!(list.remove(&mut txn, "b"), Some(2.into()));
+        assert_eq!(list.remove(&mut txn, "b"), None);
+        assert!(!list.contains_key(&txn, "b"));
+        assert_eq!(
+            list.keys(&txn),
+            vec!["a".into(), "d".into(), "c".into(), "e".into()]
+        );
+
+        let nested = list.push_back(&mut txn, "nested", MapPrelim::<u32>::new());
+        nested.insert(&mut txn, "x", 1);
+        let prelim = list.insert(&mut txn, 0, "sub", KeyedListPrelim::from([("y", 2)]));
+        assert_eq!(prelim.to_json(&txn), any!([["y", 2]]));
+        assert_eq!(
+            list.get(&txn, "nested").unwrap().cast::<MapRef>().unwrap(),
+            nested
+        );
+
+        list.clear(&mut txn);
+        assert_eq!(list.len(&txn), 0);
+        assert_eq!(list.to_json(&txn), any!([]));
+    }
+
+    #[test]
+    fn concurrent_changes_converge() {
+        let d1 = Doc::with_client_id(1);
+        let l1 = d1.get_or_insert_keyed_list("list");
+        let d2 = Doc::with_client_id(2);
+        let l2 = d2.get_or_insert_keyed_list("list");
+        {
+            let mut txn = d1.transact_mut();
+            for key in ["a", "b", "c", "d"] {
+                l1.push_back(&mut txn, key, key);
+            }
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        // the same entry moved concurrently to different positions
+        l1.move_to(&mut d1.transact_mut(), "a", 3);
+        l2.move_to(&mut d2.transact_mut(), "a", 1);
+        // the same key inserted concurrently
+        l1.push_front(&mut d1.transact_mut(), "e", 1);
+        l2.push_back(&mut d2.transact_mut(), "e", 2);
+        exchange_updates(&[&d1, &d2]);
+
+        let k1 = keys(&l1, &d1);
+        assert_eq!(k1, keys(&l2, &d2));
+        assert_eq!(k1.len(), 5);
+        assert_eq!(l1.to_json(&d1.transact()), l2.to_json(&d2.transact()));
+
+        // entry moved on one peer and removed on another
+        l1.move_to(&mut d1.transact_mut(), "b", 0);
+        l2.remove(&mut d2.transact_mut(), "b");
+        // entry moved on one peer and overridden on another
+        l1.move_to(&mut d1.transact_mut(), "c", 0);
+        l2.set(&mut d2.transact_mut(), "c", "c2");
+        exchange_updates(&[&d1, &d2]);
+
+        let k1 = keys(&l1, &d1);
+        assert_eq!(k1, keys(&l2, &d2));
+        assert!(!k1.contains(&"b".to_string()));
+        assert_eq!(l1.get(&d1.transact(), "c"), Some("c2".into()));
+        assert_eq!(l1.len(&d1.transact()), 4);
+    }
+
+    #[test]
+    fn observe_keyed_list() {
+        let doc = Doc::with_client_id(1);
+        let list = doc.get_or_insert_keyed_list("list");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let events = events.clone();
+            list.observe(move |txn, e| {
+                let mut keys: Vec<_> = e
+                    .keys(txn)
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect();
+                keys.sort_by(|a, b| a.0.cmp(&b.0));
+                events.lock().unwrap().push((e.order_changed(), keys));
+            })
+        };
+
+        {
+            let mut txn = doc.transact_mut();
+            list.push_back(&mut txn, "a", 1);
+            list.push_back(&mut txn, "b", 2);
+        }
+        list.move_to(&mut doc.transact_mut(), "b", 0);
+        list.set(&mut doc.transact_mut(), "a", 3);
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events.as_slice(),
+            &[
+                (
+                    true,
+                    vec![
+                        ("a".to_string(), EntryChange::Inserted(1.into())),
+                        ("b".to_string(), EntryChange::Inserted(2.into())),
+                    ]
+                ),
+                (true, vec![]),
+                (
+                    false,
+                    vec![("a".to_string(), EntryChange::Updated(1.into(), 3.into()))]
+                ),
+            ]
+        );
+        let value: Value = BranchPtr::from(list.as_ref()).into();
+        assert_eq!(value.cast::<KeyedListRef>(), Ok(list));
+    }
+
+    #[test]
+    fn cached_index_follows_local_and_remote_changes() {
+        let d1 = Doc::with_client_id(1);
+        let l1 = d1.get_or_insert_keyed_list("list");
+        let d2 = Doc::with_client_id(2);
+        let l2 = d2.get_or_insert_keyed_list("list");
+        {
+            let mut txn = d1.transact_mut();
+            for key in ["a", "b", "c", "d", "e"] {
+                l1.push_back(&mut txn, key, key);
+            }
+            l1.insert(&mut txn, 2, "f", "f");
+            l1.move_to(&mut txn, "a", 4);
+            l1.set(&mut txn, "b", "b2");
+            l1.remove(&mut txn, "c");
+            l1.push_front(&mut txn, "e", "e2");
+        }
+        assert_index(&l1);
+        assert_eq!(
+            keys(&l1, &d1),
+            vec!["e", "b", "f", "d", "a"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(l1.index_of(&d1.transact(), "d"), Some(3));
+        assert_eq!(l1.key_at(&d1.transact(), 2), Some("f".into()));
+
+        exchange_updates(&[&d1, &d2]);
+        assert_index(&l2);
+        assert_eq!(keys(&l1, &d1), keys(&l2, &d2));
+
+        // entry moved on one peer and overridden on another has no position
+        l1.move_to(&mut d1.transact_mut(), "b", 0);
+        l2.set(&mut d2.transact_mut(), "b", "b3");
+        l2.move_to(&mut d2.transact_mut(), "f", 0);
+        exchange_updates(&[&d1, &d2]);
+        assert_index(&l1);
+        assert_index(&l2);
+        assert_eq!(keys(&l1, &d1), keys(&l2, &d2));
+
+        l2.insert(&mut d2.transact_mut(), 1, "g", "g");
+        l2.push_back(&mut d2.transact_mut(), "b", "b4");
+        assert_index(&l2);
+        exchange_updates(&[&d1, &d2]);
+        assert_index(&l1);
+        assert_eq!(keys(&l1, &d1), keys(&l2, &d2));
+
+        l1.clear(&mut d1.transact_mut());
+        assert_index(&l1);
+        assert_eq!(l1.len(&d1.transact()), 0);
+    }
+
+    #[test]
+    fn try_from_checks_type() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        let nested_list = map.insert(&mut txn, "list", KeyedListPrelim::<u32>::new());
+        let nested_map = map.insert(&mut txn, "map", MapPrelim::<u32>::new());
+
+        let item = BranchPtr::from(nested_list.as_ref()).item.unwrap();
+        assert_eq!(KeyedListRef::try_from(item).ok(), Some(nested_list));
+        let item = BranchPtr::from(nested_map.as_ref()).item.unwrap();
+        assert_eq!(
+            KeyedListRef::try_from(item),
+            Err::<KeyedListRef, ItemPtr>(item)
+        );
+    }
+}
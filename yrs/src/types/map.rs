@@ -1,8 +1,9 @@
 use crate::block::{EmbedPrelim, ItemContent, ItemPosition, ItemPtr, Prelim};
+use crate::encoding::read::Error;
 use crate::transaction::TransactionMut;
 use crate::types::{
-    event_keys, Branch, BranchPtr, Entries, EntryChange, Path, RootRef, SharedRef, ToJson, TypeRef,
-    Value,
+    event_keys, map_entry, Branch, BranchPtr, Entries, EntryChange, Path, RootRef, SharedRef,
+    ToJson, TypeRef, Value,
 };
 use crate::*;
 use std::borrow::Borrow;
@@ -75,7 +76,7 @@ impl ToJson for MapRef {
         let inner = self.0;
         let mut res = HashMap::new();
         for (key, item) in inner.map.iter() {
-            if !item.is_deleted() {
+            if let Some(item) = map_entry(item, txn) {
                 let last = item.content.get_last().unwrap_or(Value::Any(Any::Null));
                 res.insert(key.to_string(), last.to_json(txn));
             }
@@ -122,12 +123,12 @@ impl TryFrom<Value> for MapRef {
 
 pub trait Map: AsRef<Branch> + Sized {
     /// Returns a number of entries stored within current map.
-    fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+    fn len<T: ReadTxn>(&self, txn: &T) -> u32 {
         let mut len = 0;
         let inner = self.as_ref();
         for item in inner.map.values() {
             //TODO: maybe it would be better to just cache len in the map itself?
-            if !item.is_deleted() {
+            if map_entry(item, txn).is_some() {
                 len += 1;
             }
         }
@@ -153,6 +154,27 @@ pub trait Map: AsRef<Branch> + Sized {
 
     /// Inserts a new `value` under given `key` into current map. Returns an integrated value.
     fn insert<K, V>(&self, txn: &mut TransactionMut, key: K, value: V) -> V::Return
+    where
+        K: Into<Arc<str>>,
+        V: Prelim,
+    {
+        match self.try_insert(txn, key, value) {
+            Ok(integrated) => integrated,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Inserts a new `value` under given `key` into current map. Unlike [Map::insert], this method
+    /// doesn't panic when insertion would exceed document quotas or was made on a read-only
+    /// document, but returns an error instead.
+    ///
+    /// Returns an integrated value.
+    fn try_insert<K, V>(
+        &self,
+        txn: &mut TransactionMut,
+        key: K,
+        value: V,
+    ) -> Result<V::Return, EditError>
     where
         K: Into<Arc<str>>,
         V: Prelim,
@@ -170,9 +192,9 @@ pub trait Map: AsRef<Branch> + Sized {
             }
         };
 
-        let ptr = txn.create_item(&pos, value, Some(key));
+        let ptr = txn.try_create_item(&pos, value, Some(key))?;
         if let Ok(integrated) = ptr.try_into() {
-            integrated
+            Ok(integrated)
         } else {
             panic!("Defect: unexpected integrated type")
         }
@@ -192,6 +214,15 @@ pub trait Map: AsRef<Branch> + Sized {
         ptr.remove(txn, key)
     }
 
+    /// Removes a stored within current map under a given `key`. Unlike [Map::remove], this method
+    /// doesn't panic when called on a read-only document, but returns an error instead.
+    ///
+    /// Returns removed value or `None` if no entry with a given `key` was present in current map.
+    fn try_remove(&self, txn: &mut TransactionMut, key: &str) -> Result<Option<Value>, EditError> {
+        txn.check_writable()?;
+        Ok(self.remove(txn, key))
+    }
+
     /// Returns [WeakPrelim] to a given `key`, if it exists in a current map.
     #[cfg(feature = "weak")]
     fn link<T: ReadTxn>(&self, _txn: &T, key: &str) -> Option<crate::WeakPrelim<Self>> {
@@ -211,9 +242,9 @@ pub trait Map: AsRef<Branch> + Sized {
     }
 
     /// Checks if an entry with given `key` can be found within current map.
-    fn contains_key<T: ReadTxn>(&self, _txn: &T, key: &str) -> bool {
+    fn contains_key<T: ReadTxn>(&self, txn: &T, key: &str) -> bool {
         if let Some(item) = self.as_ref().map.get(key) {
-            !item.is_deleted()
+            map_entry(item, txn).is_some()
         } else {
             false
         }
@@ -225,6 +256,223 @@ pub trait Map: AsRef<Branch> + Sized {
             txn.delete(ptr.clone());
         }
     }
+
+    /// Inserts multiple - possibly deeply nested - entries into current map at once, using
+    /// a [MapBuilder] passed to a given function `f`. Entries are inserted in the order in which
+    /// they were defined, and consecutive primitive values of nested arrays are batched together.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, Map, Transact};
+    /// use yrs::types::ToJson;
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// map.build(&mut txn, |b| {
+    ///     b.value("name", "settings")
+    ///         .map("config", |c| c.value("theme", "dark").array("tags", ["a", "b"]))
+    /// });
+    ///
+    /// assert_eq!(
+    ///     map.to_json(&txn),
+    ///     Any::from_json(r#"{"name":"settings","config":{"theme":"dark","tags":["a","b"]}}"#).unwrap()
+    /// );
+    /// ```
+    fn build<F>(&self, txn: &mut TransactionMut, f: F)
+    where
+        F: FnOnce(MapBuilder) -> MapBuilder,
+    {
+        let builder = f(MapBuilder::new());
+        builder.integrate(txn, BranchPtr::from(self.as_ref()));
+    }
+
+    /// Updates current map, so that its content becomes equal to a given `snapshot` (eg. a row
+    /// read from a database or a parsed config file). Instead of replacing all entries, only keys
+    /// which values have changed are updated, while keys missing from a `snapshot` are removed.
+    ///
+    /// Nested shared types are reconciled recursively in place: [MapRef]s with maps, [ArrayRef]s
+    /// with arrays (keeping unchanged elements intact) and [TextRef]s with strings (using
+    /// [Text::reconcile]). All other changed values - including nested maps and arrays which
+    /// didn't exist before - are inserted as [Any] primitives.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{any, Any, Doc, Map, MapPrelim, Transact};
+    /// use yrs::types::ToJson;
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// map.insert(&mut txn, "name", "John");
+    /// map.insert(&mut txn, "address", MapPrelim::from([("city", "London")]));
+    ///
+    /// let snapshot = any!({"name": "John", "address": {"city": "Paris", "zip": "75001"}});
+    /// if let Any::Map(snapshot) = &snapshot {
+    ///     map.reconcile(&mut txn, snapshot);
+    /// }
+    /// assert_eq!(map.to_json(&txn), snapshot);
+    /// ```
+    fn reconcile(&self, txn: &mut TransactionMut, snapshot: &HashMap<String, Any>) {
+        let removed: Vec<String> = self
+            .keys(txn)
+            .filter(|key| !snapshot.contains_key(*key))
+            .map(String::from)
+            .collect();
+        for key in removed {
+            self.remove(txn, &key);
+        }
+        for (key, value) in snapshot.iter() {
+            let reconciled = match self.get(txn, key) {
+                Some(current) => reconcile_in_place(txn, &current, value),
+                None => false,
+            };
+            if !reconciled {
+                self.insert(txn, key.as_str(), value.clone());
+            }
+        }
+    }
+
+    /// Returns a number of tombstones (deleted blocks left behind by overridden or removed values)
+    /// kept under each key of current map. Keys without any tombstones are not included.
+    ///
+    /// Every time a value is inserted under an existing key, the previous value is deleted, but its
+    /// block is still kept around, as remote peers may refer to it in their concurrent updates.
+    /// This means that long-lived maps, which keys are updated frequently, may grow in memory
+    /// over time. See [Map::compact] for a way to reduce that overhead.
+    fn tombstone_depths<T: ReadTxn>(&self, _txn: &T) -> HashMap<Arc<str>, u32> {
+        let mut result = HashMap::new();
+        for (key, item) in self.as_ref().map.iter() {
+            let mut depth = 0;
+            let mut current = Some(*item);
+            while let Some(item) = current {
+                if item.is_deleted() {
+                    depth += 1;
+                }
+                current = item.left;
+            }
+            if depth > 0 {
+                result.insert(key.clone(), depth);
+            }
+        }
+        result
+    }
+
+    /// Compacts tombstones left behind by overridden or removed values of current map (see:
+    /// [Map::tombstone_depths]). Contents of tombstones which are no longer needed (e.g. were kept
+    /// around by an [UndoManager](crate::UndoManager), which has been cleared since) are released
+    /// and consecutive tombstones are squashed together whenever possible, i.e. when they have been
+    /// created by the same client one after another. Returns a number of tombstones, which
+    /// contents have been released.
+    ///
+    /// Compaction follows garbage collection rules of the document: if [Options::skip_gc] is set,
+    /// this method does nothing. Tombstones cannot be removed altogether, as remote peers may still
+    /// refer to them.
+    fn compact(&self, txn: &mut TransactionMut) -> u32 {
+        if txn.store().options.skip_gc {
+            return 0;
+        }
+        let mut tombstones: HashMap<ClientID, Vec<u32>> = HashMap::new();
+        let mut collector = crate::gc::GCCollector::new(txn.store());
+        let mut released = 0;
+        for item in self.as_ref().map.values() {
+            let mut current = Some(*item);
+            while let Some(mut item) = current {
+                if item.is_deleted() {
+                    let is_gc = matches!(item.content, ItemContent::Deleted(_));
+                    if !is_gc && !item.info.is_keep() {
+                        item.gc(&mut collector, false);
+                        released += 1;
+                    }
+                    tombstones
+                        .entry(item.id.client)
+                        .or_default()
+                        .push(item.id.clock);
+                }
+                current = item.left;
+            }
+        }
+        collector.collect_all_marked(txn);
+
+        let store = txn.store_mut();
+        let text_block_capacity = store.options.text_block_capacity;
+        for (client, mut clocks) in tombstones {
+            if let Some(blocks) = store.blocks.get_client_mut(&client) {
+                clocks.sort_unstable_by(|a, b| b.cmp(a));
+                for clock in clocks {
+                    if let Some(index) = blocks.find_pivot(clock) {
+                        if index + 1 < blocks.len() {
+                            blocks.squash_left(index + 1, text_block_capacity);
+                        }
+                        if index > 0 {
+                            blocks.squash_left(index, text_block_capacity);
+                        }
+                    }
+                }
+            }
+        }
+        released
+    }
+}
+
+/// Tries to update a `current` value in place, so that it becomes equal to a `target`. Returns
+/// `false` if `current` value must be replaced with `target` altogether.
+fn reconcile_in_place(txn: &mut TransactionMut, current: &Value, target: &Any) -> bool {
+    match (current, target) {
+        (Value::Any(current), target) => current == target,
+        (Value::YMap(map), Any::Map(target)) => {
+            map.reconcile(txn, target);
+            true
+        }
+        (Value::YArray(array), Any::Array(target)) => {
+            reconcile_array(txn, array, target);
+            true
+        }
+        (Value::YText(text), Any::String(target)) => {
+            text.reconcile(txn, target);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Updates an `array`, so that its content becomes equal to a `target`, keeping unchanged
+/// elements at the beginning and the end of an array intact.
+fn reconcile_array(txn: &mut TransactionMut, array: &ArrayRef, target: &[Any]) {
+    let current: Vec<Value> = array.iter(txn).collect();
+    let unchanged = |(current, target): &(&Value, &Any)| match current {
+        Value::Any(current) => current == *target,
+        _ => false,
+    };
+    let prefix = current
+        .iter()
+        .zip(target.iter())
+        .take_while(unchanged)
+        .count();
+    let suffix = current[prefix..]
+        .iter()
+        .rev()
+        .zip(target[prefix..].iter().rev())
+        .take_while(unchanged)
+        .count();
+    let removed = current.len() - prefix - suffix;
+    let inserted = target.len() - prefix - suffix;
+    let common = removed.min(inserted);
+    for i in prefix..prefix + common {
+        if !reconcile_in_place(txn, &current[i], &target[i]) {
+            array.remove(txn, i as u32);
+            array.insert(txn, i as u32, target[i].clone());
+        }
+    }
+    let index = (prefix + common) as u32;
+    if removed > common {
+        array.remove_range(txn, index, (removed - common) as u32);
+    } else if inserted > common {
+        let values = target[prefix + common..prefix + inserted].iter().cloned();
+        array.insert_range(txn, index, values);
+    }
 }
 
 #[derive(Debug)]
@@ -336,6 +584,36 @@ impl<T> MapPrelim<T> {
     }
 }
 
+impl MapPrelim<Any> {
+    /// Parses a given JSON `src` - which must be a JSON object - into a preliminary map. Unlike
+    /// inserting [Any::from_json] result directly, nested JSON objects and arrays are turned into
+    /// nested [MapRef]s and [ArrayRef]s (all other values are stored as primitives), so that they
+    /// can be edited collaboratively later on. This is useful for seeding documents from JSON
+    /// templates.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, ArrayRef, Doc, Map, MapPrelim, MapRef, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let root = doc.get_or_insert_map("root");
+    /// let mut txn = doc.transact_mut();
+    /// let prelim = MapPrelim::from_json(r#"{"title":"todo","items":[{"done":false}]}"#).unwrap();
+    /// let map = root.insert(&mut txn, "template", prelim);
+    ///
+    /// let items: ArrayRef = map.get(&txn, "items").unwrap().cast().unwrap();
+    /// let item: MapRef = items.get(&txn, 0).unwrap().cast().unwrap();
+    /// item.insert(&mut txn, "done", true);
+    /// ```
+    pub fn from_json(src: &str) -> Result<MapBuilder, Error> {
+        match BuilderValue::from(Any::from_json(src)?) {
+            BuilderValue::Map(builder) => Ok(builder),
+            _ => Err(Error::UnexpectedValue),
+        }
+    }
+}
+
 impl<T> From<HashMap<String, T>> for MapPrelim<T> {
     fn from(map: HashMap<String, T>) -> Self {
         MapPrelim(map)
@@ -379,7 +657,154 @@ impl<T: Prelim> Into<EmbedPrelim<MapPrelim<T>>> for MapPrelim<T> {
     }
 }
 
+/// Builder used to define the contents of a map - including nested shared collections - in
+/// a declarative way. It can be used either via [Map::build] or inserted as a preliminary value
+/// into other collections.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapBuilder {
+    entries: Vec<(Arc<str>, BuilderValue)>,
+}
+
+impl MapBuilder {
+    pub fn new() -> Self {
+        MapBuilder::default()
+    }
+
+    fn entry<K: Into<Arc<str>>>(mut self, key: K, value: BuilderValue) -> Self {
+        let key = key.into();
+        // overridden entries would only produce tombstones
+        self.entries.retain(|(k, _)| k != &key);
+        self.entries.push((key, value));
+        self
+    }
+
+    /// Defines an entry with a primitive `value` under a given `key`.
+    pub fn value<K, V>(self, key: K, value: V) -> Self
+    where
+        K: Into<Arc<str>>,
+        V: Into<Any>,
+    {
+        self.entry(key, BuilderValue::Any(value.into()))
+    }
+
+    /// Defines an entry with a text collection initialized with a given `text` under a given `key`.
+    pub fn text<K, S>(self, key: K, text: S) -> Self
+    where
+        K: Into<Arc<str>>,
+        S: Into<String>,
+    {
+        self.entry(key, BuilderValue::Text(text.into()))
+    }
+
+    /// Defines an entry with a nested map under a given `key`. Contents of the nested map are
+    /// defined by a given function `f`.
+    pub fn map<K, F>(self, key: K, f: F) -> Self
+    where
+        K: Into<Arc<str>>,
+        F: FnOnce(MapBuilder) -> MapBuilder,
+    {
+        self.entry(key, BuilderValue::Map(f(MapBuilder::new())))
+    }
+
+    /// Defines an entry with a nested array of primitive `values` under a given `key`.
+    pub fn array<K, I, V>(self, key: K, values: I) -> Self
+    where
+        K: Into<Arc<str>>,
+        I: IntoIterator<Item = V>,
+        V: Into<Any>,
+    {
+        self.array_with(key, |a| a.values(values))
+    }
+
+    /// Defines an entry with a nested array under a given `key`. Contents of the nested array
+    /// are defined by a given function `f`.
+    pub fn array_with<K, F>(self, key: K, f: F) -> Self
+    where
+        K: Into<Arc<str>>,
+        F: FnOnce(ArrayBuilder) -> ArrayBuilder,
+    {
+        self.entry(key, BuilderValue::Array(f(ArrayBuilder::new())))
+    }
+
+    /// Returns a number of entries defined so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if no entries have been defined so far.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Prelim for MapBuilder {
+    type Return = MapRef;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        let inner = Branch::new(TypeRef::Map);
+        (ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
+        let map = MapRef::from(inner_ref);
+        for (key, value) in self.entries {
+            match value {
+                BuilderValue::Any(value) => {
+                    map.insert(txn, key, value);
+                }
+                BuilderValue::Text(text) => {
+                    map.insert(txn, key, TextPrelim::new(text));
+                }
+                BuilderValue::Map(builder) => {
+                    map.insert(txn, key, builder);
+                }
+                BuilderValue::Array(builder) => {
+                    map.insert(txn, key, builder);
+                }
+            }
+        }
+    }
+}
+
+/// Value defined by [MapBuilder] or [ArrayBuilder].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BuilderValue {
+    Any(Any),
+    Text(String),
+    Map(MapBuilder),
+    Array(ArrayBuilder),
+}
+
+impl From<Any> for BuilderValue {
+    /// Converts JSON-like maps and arrays into nested builders.
+    fn from(value: Any) -> Self {
+        match value {
+            Any::Map(entries) => {
+                let mut entries: Vec<_> = Arc::try_unwrap(entries)
+                    .unwrap_or_else(|entries| (*entries).clone())
+                    .into_iter()
+                    .collect();
+                // keep the order of inserted entries deterministic
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, value)| (Arc::from(key), BuilderValue::from(value)))
+                    .collect();
+                BuilderValue::Map(MapBuilder { entries })
+            }
+            Any::Array(values) => {
+                let values = values.iter().cloned().map(BuilderValue::from).collect();
+                BuilderValue::Array(ArrayBuilder { values })
+            }
+            other => BuilderValue::Any(other),
+        }
+    }
+}
+
 /// Event generated by [Map::observe] method. Emitted during transaction commit phase.
+///
+/// Key changes carried by this event are computed lazily on the first call of [MapEvent::keys] and
+/// cached for the lifetime of the event.
 pub struct MapEvent {
     pub(crate) current_target: BranchPtr,
     target: MapRef,
@@ -407,7 +832,9 @@ impl MapEvent {
     }
 
     /// Returns a summary of key-value changes made over corresponding [Map] collection within
-    /// bounds of current transaction.
+    /// bounds of current transaction. Every changed key is mapped to an [EntryChange] describing
+    /// if its entry has been inserted, updated (together with previous value) or removed. Keys
+    /// which have been both inserted and removed within the same transaction are not present.
     pub fn keys(&self, txn: &TransactionMut) -> &HashMap<Arc<str>, EntryChange> {
         let keys = unsafe { self.keys.get().as_mut().unwrap() };
 
@@ -428,6 +855,159 @@ impl MapEvent {
     }
 }
 
+/// Concurrent writes to the same key of a [Map], detected while integrating a remote update into
+/// a document configured with [Options::map_conflict_resolver].
+#[derive(Debug, Clone)]
+pub struct MapConflict {
+    /// Map containing conflicting entries.
+    pub target: MapRef,
+    /// Key under which conflicting values have been written.
+    pub key: Arc<str>,
+    /// Value which won according to default last-writer-wins rule and which is currently stored
+    /// under the `key`.
+    pub winner: Value,
+    /// Value written concurrently, which has been overridden by the `winner`.
+    pub loser: Value,
+}
+
+/// Decision made by [MapConflictResolver] about a given [MapConflict].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapConflictResolution {
+    /// Keep the winner chosen by default last-writer-wins rule.
+    Keep,
+    /// Store a given value under the conflicting key instead, eg. the loser value or a value
+    /// merged from both of them. It's written as a regular local change of the transaction, which
+    /// integrated the conflict, and will be propagated to remote peers. If a given value is equal
+    /// to the winner, nothing is written.
+    Replace(Any),
+}
+
+/// Application-defined conflict resolution strategy for concurrent writes to the same [Map] key,
+/// configured via [Options::map_conflict_resolver]. By default such conflicts are resolved by
+/// picking the write of a peer with higher client ID. Resolver is called every time a remote
+/// update containing a conflicting write is integrated, and can either accept that choice or
+/// replace it with another value.
+///
+/// Every peer resolves conflicts on its own, so resolver must be deterministic and must not
+/// depend on the order of its arguments (eg. picking a maximum of numeric values or a union of
+/// sets), otherwise peers may keep overriding each other's decisions. Resolver can also be used
+/// to record made decisions.
+///
+/// Resolver is not called on read-only documents (see: [Options::read_only]), since they cannot
+/// store a replaced value. Replacements exceeding document quotas are skipped as well.
+///
+/// # Conflict detection
+///
+/// Conflicts are detected heuristically while a remote write is being integrated, without
+/// walking the whole history of a key: a write is reported as a loser if it's integrated before
+/// an already existing entry, while a live entry is reported as a loser if the integrated write
+/// overrides it without having it as its origin (meaning that its author didn't know about that
+/// entry). This has following limits:
+///
+/// - Entries, which have been already deleted (eg. removed concurrently) or whose content has
+///   been garbage collected, are never reported.
+/// - When more than two concurrent writes are integrated, every loser is reported separately
+///   against the final winner, so that a resolver can be called several times for the same key.
+/// - Writes made locally are never considered to be conflicting, since they always override the
+///   entry known at the moment.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Any, Doc, Map, MapConflictResolution, MapConflictResolver, Options, ReadTxn, StateVector, Transact, Update, Value};
+/// use yrs::updates::decoder::Decode;
+///
+/// let resolver = MapConflictResolver::new(|_txn, conflict| {
+///     match (&conflict.winner, &conflict.loser) {
+///         (Value::Any(Any::Number(a)), Value::Any(Any::Number(b))) if b > a => {
+///             MapConflictResolution::Replace(Any::Number(*b))
+///         }
+///         _ => MapConflictResolution::Keep,
+///     }
+/// });
+/// let mut options = Options::with_client_id(1);
+/// options.map_conflict_resolver = Some(resolver);
+/// let d1 = Doc::with_options(options);
+/// let m1 = d1.get_or_insert_map("map");
+/// m1.insert(&mut d1.transact_mut(), "score", 10.0);
+///
+/// let d2 = Doc::with_client_id(2);
+/// let m2 = d2.get_or_insert_map("map");
+/// m2.insert(&mut d2.transact_mut(), "score", 5.0);
+///
+/// let update = d2.transact().encode_state_as_update_v1(&StateVector::default());
+/// d1.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+/// // client 2 would win by default, but the resolver picked the maximum
+/// assert_eq!(m1.get(&d1.transact(), "score"), Some(10.0.into()));
+/// ```
+#[derive(Clone)]
+pub struct MapConflictResolver(Arc<ResolveFn>);
+
+type ResolveFn = dyn Fn(&TransactionMut, &MapConflict) -> MapConflictResolution + Send + Sync;
+
+impl MapConflictResolver {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&TransactionMut, &MapConflict) -> MapConflictResolution + Send + Sync + 'static,
+    {
+        MapConflictResolver(Arc::new(f))
+    }
+
+    /// Resolves a given `conflict`.
+    pub fn resolve(&self, txn: &TransactionMut, conflict: &MapConflict) -> MapConflictResolution {
+        (self.0)(txn, conflict)
+    }
+
+    /// Resolves all map key conflicts detected so far within a given transaction. Conflicts are
+    /// not resolved on read-only documents, as they cannot make local edits. Replacements, which
+    /// would exceed document quotas, are skipped (keeping the winner) and reported to
+    /// [Doc::observe_quota_exceeded] callbacks instead.
+    pub(crate) fn resolve_pending(&self, txn: &mut TransactionMut) {
+        let conflicts = std::mem::take(&mut txn.map_conflicts);
+        // transaction is temporarily writable while integrating remote updates, so document
+        // options must be checked instead
+        if txn.store().options.read_only {
+            return;
+        }
+        for (branch, key, loser) in conflicts {
+            let winner = match branch.map.get(&key) {
+                Some(winner) if !winner.is_deleted() && *winner != loser => *winner,
+                _ => continue,
+            };
+            let (winner, loser) = match (winner.content.get_last(), loser.content.get_last()) {
+                (Some(winner), Some(loser)) => (winner, loser),
+                _ => continue,
+            };
+            let conflict = MapConflict {
+                target: MapRef::from(branch),
+                key,
+                winner,
+                loser,
+            };
+            if let MapConflictResolution::Replace(value) = self.resolve(txn, &conflict) {
+                if conflict.winner != Value::Any(value.clone()) {
+                    // quota violations have been already reported, a winner is kept in that case
+                    let _ = conflict.target.try_insert(txn, conflict.key, value);
+                }
+            }
+        }
+    }
+}
+
+impl PartialEq for MapConflictResolver {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MapConflictResolver {}
+
+impl std::fmt::Debug for MapConflictResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MapConflictResolver({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
@@ -437,8 +1017,9 @@ mod test {
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
     use crate::{
-        any, Any, Array, ArrayPrelim, ArrayRef, Doc, Map, MapPrelim, MapRef, Observable,
-        StateVector, Text, Transact, Update,
+        any, Any, Array, ArrayPrelim, ArrayRef, BlockInfo, ContentKind, Doc, GetString, Map,
+        MapBuilder, MapConflictResolution, MapConflictResolver, MapPrelim, MapRef, Observable,
+        Options, SharedRef, StateVector, Text, TextRef, Transact, Update, ID,
     };
     use arc_swap::ArcSwapOption;
     use fastrand::Rng;
@@ -447,6 +1028,363 @@ mod test {
     use std::sync::{Arc, Mutex};
     use std::time::Duration;
 
+    #[test]
+    fn reconcile_nested_snapshot() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            m1.insert(&mut txn, "id", 1);
+            m1.insert(&mut txn, "removed", true);
+            m1.insert(&mut txn, "bio", TextPrelim::new("hello world"));
+            m1.insert(&mut txn, "tags", ArrayPrelim::from(["a", "b", "c"]));
+            m1.insert(&mut txn, "address", MapPrelim::from([("city", "London")]));
+        }
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.get_or_insert_map("map");
+        exchange_updates(&[&d1, &d2]);
+
+        let snapshot = any!({
+            "id": 1,
+            "bio": "hello brave world",
+            "tags": ["a", "x", "c", "d"],
+            "address": {"city": "Paris"},
+            "created": {"by": "admin"}
+        });
+        let sv = d1.transact().state_vector();
+        {
+            let mut txn = d1.transact_mut();
+            let bio: TextRef = m1.get(&txn, "bio").unwrap().cast().unwrap();
+            match &snapshot {
+                Any::Map(snapshot) => m1.reconcile(&mut txn, snapshot),
+                _ => unreachable!(),
+            }
+            assert_eq!(m1.to_json(&txn), snapshot);
+            // nested shared types were updated in place
+            let same: TextRef = m1.get(&txn, "bio").unwrap().cast().unwrap();
+            assert_eq!(bio, same);
+        }
+
+        // unchanged values were not rewritten
+        let update = d1.transact().encode_state_as_update_v1(&sv);
+        let update = Update::decode_v1(&update).unwrap();
+        let inserted: Vec<_> = update
+            .blocks
+            .into_blocks(false)
+            .filter_map(|mut b| b.as_item_ptr().and_then(|i| i.parent_sub.clone()))
+            .collect();
+        assert!(!inserted.contains(&"id".into()));
+
+        // concurrent edits of nested types are preserved
+        let bio: TextRef = m2.get(&d2.transact(), "bio").unwrap().cast().unwrap();
+        bio.push(&mut d2.transact_mut(), "!");
+        exchange_updates(&[&d1, &d2]);
+        let bio: TextRef = m1.get(&d1.transact(), "bio").unwrap().cast().unwrap();
+        assert_eq!(bio.get_string(&d1.transact()), "hello brave world!");
+    }
+
+    #[test]
+    fn map_tombstones_compaction() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        let other = d1.get_or_insert_map("other");
+        for i in 0..10u32 {
+            m1.insert(&mut d1.transact_mut(), "a", i);
+            // interleaved updates prevent tombstones from being squashed on commit
+            other.insert(&mut d1.transact_mut(), "b", i);
+        }
+        m1.insert(&mut d1.transact_mut(), "c", 1);
+        m1.insert(&mut d1.transact_mut(), "d", 1);
+        m1.remove(&mut d1.transact_mut(), "d");
+
+        let depths = m1.tombstone_depths(&d1.transact());
+        assert_eq!(depths.len(), 2);
+        assert_eq!(depths["a"], 9);
+        assert_eq!(depths["d"], 1);
+
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.get_or_insert_map("map");
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrently update map on the remote peer
+        m2.insert(&mut d2.transact_mut(), "a", 100);
+
+        assert_eq!(m1.compact(&mut d1.transact_mut()), 0); // already released on commit
+        m1.insert(&mut d1.transact_mut(), "a", 10);
+        assert!(d1.check_integrity(&d1.transact()).is_ok());
+
+        exchange_updates(&[&d1, &d2]);
+        let a1 = m1.get(&d1.transact(), "a");
+        let a2 = m2.get(&d2.transact(), "a");
+        assert_eq!(a1, a2);
+        assert_eq!(m1.tombstone_depths(&d1.transact())["a"], 11);
+    }
+
+    #[test]
+    fn map_compact_releases_tombstones_kept_by_undo_manager() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let other = doc.get_or_insert_map("other");
+        let mut mgr = crate::UndoManager::new(&doc, &map);
+        for i in 0..10u32 {
+            map.insert(&mut doc.transact_mut(), "a", format!("value-{}", i));
+            other.insert(&mut doc.transact_mut(), "b", i);
+        }
+        // tombstones are kept alive by undo manager
+        assert_eq!(map.compact(&mut doc.transact_mut()), 0);
+
+        mgr.clear().unwrap();
+        assert_eq!(map.compact(&mut doc.transact_mut()), 9);
+        assert_eq!(map.compact(&mut doc.transact_mut()), 0);
+        assert_eq!(map.tombstone_depths(&doc.transact())["a"], 9);
+        assert_eq!(map.get(&doc.transact(), "a"), Some("value-9".into()));
+        assert!(doc.check_integrity(&doc.transact()).is_ok());
+
+        // tombstones are not compacted when GC is disabled
+        let doc = Doc::with_options(Options {
+            client_id: 1,
+            skip_gc: true,
+            ..Options::default()
+        });
+        let map = doc.get_or_insert_map("map");
+        let other = doc.get_or_insert_map("other");
+        for i in 0..10u32 {
+            map.insert(&mut doc.transact_mut(), "a", i);
+            other.insert(&mut doc.transact_mut(), "b", i);
+        }
+        assert_eq!(map.compact(&mut doc.transact_mut()), 0);
+    }
+
+    #[test]
+    fn map_builder() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        map.build(&mut txn, |b| {
+            b.value("a", 1)
+                .value("a", 2)
+                .text("text", "hello")
+                .map("config", |c| {
+                    c.value("theme", "dark")
+                        .array("tags", ["a", "b"])
+                        .array_with("rows", |a| {
+                            a.values([1, 2]).map(|m| m.value("key", "value")).value(3)
+                        })
+                })
+        });
+        assert_eq!(
+            map.to_json(&txn),
+            any!({
+                "a": 2,
+                "text": "hello",
+                "config": {
+                    "theme": "dark",
+                    "tags": ["a", "b"],
+                    "rows": [1, 2, {"key": "value"}, 3]
+                }
+            })
+        );
+        let text: TextRef = map.get(&txn, "text").unwrap().cast().unwrap();
+        assert_eq!(text.get_string(&txn), "hello");
+
+        // overridden entries and consecutive primitives don't produce extra blocks: a, text,
+        // "hello", config, theme, tags, ["a", "b"], rows, [1, 2], {..}, key and [3]
+        let blocks = txn.store().blocks.get_client(&1).unwrap().len();
+        assert_eq!(blocks, 12);
+
+        let prelim = MapBuilder::new().value("x", 1);
+        let nested = map.insert(&mut txn, "nested", prelim);
+        assert_eq!(nested.get(&txn, "x"), Some(1.into()));
+    }
+
+    #[test]
+    fn prelim_from_json() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        let json = r#"{"title":"todo","tags":["a",1,null],"items":[{"done":false,"sub":[]}]}"#;
+        let nested = map.insert(&mut txn, "nested", MapPrelim::from_json(json).unwrap());
+        assert_eq!(nested.to_json(&txn), Any::from_json(json).unwrap());
+
+        let items: ArrayRef = nested.get(&txn, "items").unwrap().cast().unwrap();
+        let item: MapRef = items.get(&txn, 0).unwrap().cast().unwrap();
+        let sub: ArrayRef = item.get(&txn, "sub").unwrap().cast().unwrap();
+        assert_eq!(sub.len(&txn), 0);
+        assert_eq!(nested.get(&txn, "title"), Some("todo".into()));
+
+        let array = map.insert(
+            &mut txn,
+            "array",
+            ArrayPrelim::from_json(r#"[1, {"a": [true]}]"#).unwrap(),
+        );
+        assert_eq!(array.to_json(&txn), any!([1, {"a": [true]}]));
+        let inner: MapRef = array.get(&txn, 1).unwrap().cast().unwrap();
+        assert!(inner.get(&txn, "a").unwrap().cast::<ArrayRef>().is_ok());
+
+        assert!(MapPrelim::from_json("[1, 2]").is_err());
+        assert!(ArrayPrelim::from_json(r#"{"a": 1}"#).is_err());
+        assert!(MapPrelim::from_json("{").is_err());
+    }
+
+    #[test]
+    fn map_conflict_resolver() {
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let resolver = {
+            let decisions = decisions.clone();
+            MapConflictResolver::new(move |_, conflict| {
+                decisions
+                    .lock()
+                    .unwrap()
+                    .push((conflict.winner.clone(), conflict.loser.clone()));
+                match (&conflict.winner, &conflict.loser) {
+                    (Value::Any(Any::Number(a)), Value::Any(Any::Number(b))) => {
+                        MapConflictResolution::Replace(Any::Number(a.max(*b)))
+                    }
+                    _ => MapConflictResolution::Keep,
+                }
+            })
+        };
+        let docs: Vec<_> = (1..=3)
+            .map(|client_id| {
+                let mut options = Options::with_client_id(client_id);
+                options.map_conflict_resolver = Some(resolver.clone());
+                Doc::with_options(options)
+            })
+            .collect();
+        let maps: Vec<_> = docs.iter().map(|d| d.get_or_insert_map("map")).collect();
+        maps[0].insert(&mut docs[0].transact_mut(), "max", 30);
+        maps[1].insert(&mut docs[1].transact_mut(), "max", 10);
+        maps[2].insert(&mut docs[2].transact_mut(), "max", 20);
+        maps[0].insert(&mut docs[0].transact_mut(), "other", "a");
+        maps[1].insert(&mut docs[1].transact_mut(), "other", "b");
+
+        let docs: Vec<_> = docs.iter().collect();
+        exchange_updates(&docs);
+        exchange_updates(&docs);
+
+        for (doc, map) in docs.iter().zip(maps.iter()) {
+            let txn = doc.transact();
+            assert_eq!(map.get(&txn, "max"), Some(30.into()));
+            assert_eq!(map.get(&txn, "other"), Some("b".into()));
+        }
+        assert!(decisions
+            .lock()
+            .unwrap()
+            .contains(&("b".into(), "a".into())));
+
+        // sequential writes are not conflicts
+        decisions.lock().unwrap().clear();
+        maps[1].insert(&mut docs[1].transact_mut(), "max", 5);
+        exchange_updates(&docs);
+        assert!(decisions.lock().unwrap().is_empty());
+        assert_eq!(maps[0].get(&docs[0].transact(), "max"), Some(5.into()));
+    }
+
+    #[test]
+    fn map_conflict_resolver_on_read_only_and_quota_limited_docs() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let resolver = {
+            let calls = calls.clone();
+            MapConflictResolver::new(move |_, conflict| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                match (&conflict.winner, &conflict.loser) {
+                    (Value::Any(Any::Number(a)), Value::Any(Any::Number(b))) => {
+                        MapConflictResolution::Replace(Any::Number(a.max(*b)))
+                    }
+                    _ => MapConflictResolution::Keep,
+                }
+            })
+        };
+        let updates: Vec<_> = vec![(1, 10), (2, 5)]
+            .into_iter()
+            .map(|(client_id, value)| {
+                let doc = Doc::with_client_id(client_id);
+                let map = doc.get_or_insert_map("map");
+                map.insert(&mut doc.transact_mut(), "max", value);
+                let txn = doc.transact();
+                txn.encode_state_as_update_v1(&StateVector::default())
+            })
+            .collect();
+
+        // read-only replica doesn't resolve conflicts, as it cannot write a replaced value
+        let mut options = Options::with_client_id(3);
+        options.read_only = true;
+        options.map_conflict_resolver = Some(resolver.clone());
+        let doc = Doc::with_options(options);
+        let map = doc.get_or_insert_map("map");
+        for update in updates.iter() {
+            let update = Update::decode_v1(update).unwrap();
+            doc.transact_mut().apply_update(update).unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(map.get(&doc.transact(), "max"), Some(5.into()));
+
+        // replacement exceeding document quota is skipped, keeping the winner
+        let mut options = Options::with_client_id(3);
+        options.max_blocks = Some(2);
+        options.map_conflict_resolver = Some(resolver);
+        let doc = Doc::with_options(options);
+        let map = doc.get_or_insert_map("map");
+        let exceeded = Arc::new(AtomicU32::new(0));
+        let _sub = {
+            let exceeded = exceeded.clone();
+            doc.observe_quota_exceeded(move |_, _| {
+                exceeded.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        for update in updates.iter() {
+            let update = Update::decode_v1(update).unwrap();
+            doc.transact_mut().apply_update(update).unwrap();
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(exceeded.load(Ordering::SeqCst), 1);
+        assert_eq!(map.get(&doc.transact(), "max"), Some(5.into()));
+    }
+
+    #[test]
+    fn map_blocks() {
+        let doc = Doc::with_options(Options {
+            skip_gc: true,
+            ..Options::with_client_id(1)
+        });
+        let map = doc.get_or_insert_map("map");
+        map.insert(&mut doc.transact_mut(), "b", 1);
+        map.insert(&mut doc.transact_mut(), "a", MapPrelim::<u32>::new());
+        map.insert(&mut doc.transact_mut(), "b", "x");
+        map.remove(&mut doc.transact_mut(), "a");
+
+        let blocks: Vec<_> = map.blocks(&doc.transact()).collect();
+        let a: Arc<str> = "a".into();
+        let b: Arc<str> = "b".into();
+        assert_eq!(
+            blocks,
+            vec![
+                BlockInfo {
+                    id: ID::new(1, 1),
+                    kind: ContentKind::Type,
+                    len: 1,
+                    deleted: true,
+                    key: Some(a),
+                },
+                BlockInfo {
+                    id: ID::new(1, 0),
+                    kind: ContentKind::Any,
+                    len: 1,
+                    deleted: true,
+                    key: Some(b.clone()),
+                },
+                BlockInfo {
+                    id: ID::new(1, 2),
+                    kind: ContentKind::Any,
+                    len: 1,
+                    deleted: false,
+                    key: Some(b),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn map_basic() {
         let d1 = Doc::with_client_id(1);
@@ -499,7 +1437,8 @@ mod test {
         compare_all(&m1, &t1);
 
         let update = t1.encode_state_as_update_v1(&StateVector::default());
-        t2.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+            .unwrap();
 
         compare_all(&m2, &t2);
     }
@@ -519,7 +1458,8 @@ mod test {
         let m2 = d2.get_or_insert_map("map");
         let mut t2 = d2.transact_mut();
 
-        t2.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+            .unwrap();
 
         assert_eq!(
             m2.get(&t2, &"stuff".to_owned()),
@@ -544,8 +1484,10 @@ mod test {
         let u1 = t1.encode_state_as_update_v1(&StateVector::default());
         let u2 = t2.encode_state_as_update_v1(&StateVector::default());
 
-        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap())
+            .unwrap();
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         assert_eq!(m1.get(&t1, &"stuff".to_owned()), Some(Value::from("c1")));
         assert_eq!(m2.get(&t2, &"stuff".to_owned()), Some(Value::from("c1")));
@@ -596,7 +1538,8 @@ mod test {
         let mut t2 = d2.transact_mut();
 
         let u1 = t1.encode_state_as_update_v1(&StateVector::default());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         assert_eq!(m2.len(&t2), 0);
         assert_eq!(m2.get(&t2, &"key1".to_owned()), None);
@@ -866,7 +1809,8 @@ mod test {
             let sv = t2.state_vector();
             let mut encoder = EncoderV1::new();
             t1.encode_diff(&sv, &mut encoder);
-            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap());
+            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap())
+                .unwrap();
         }
         assert_eq!(
             entries.swap(None),
@@ -980,6 +1924,65 @@ mod test {
         );
     }
 
+    #[test]
+    fn observe_deep_event_accessors() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let array = map.insert(
+            &mut doc.transact_mut(),
+            "array",
+            ArrayPrelim::from(vec![1, 2]),
+        );
+        let text = map.insert(&mut doc.transact_mut(), "text", TextPrelim::new("abc"));
+
+        let summary = Arc::new(Mutex::new(Vec::new()));
+        let summary_copy = summary.clone();
+        let _sub = map.observe_deep(move |txn, events| {
+            let mut summary = summary_copy.lock().unwrap();
+            for e in events.iter() {
+                // changes are computed once and cached
+                if let Some(keys) = e.keys(txn) {
+                    assert!(std::ptr::eq(keys, e.keys(txn).unwrap()));
+                    let mut keys: Vec<_> = keys.keys().map(|k| k.to_string()).collect();
+                    keys.sort();
+                    summary.push(format!("keys: {:?}", keys));
+                }
+                if let Some(delta) = e.delta(txn) {
+                    assert!(std::ptr::eq(delta, e.delta(txn).unwrap()));
+                    summary.push(format!("delta: {:?}", delta));
+                }
+                if let Some(delta) = e.text_delta(txn) {
+                    assert!(std::ptr::eq(delta, e.text_delta(txn).unwrap()));
+                    summary.push(format!("text: {:?}", delta));
+                }
+                assert!(matches!(
+                    (e, e.target()),
+                    (Event::Map(_), Value::YMap(_))
+                        | (Event::Array(_), Value::YArray(_))
+                        | (Event::Text(_), Value::YText(_))
+                ));
+            }
+        });
+
+        {
+            let mut txn = doc.transact_mut();
+            map.insert(&mut txn, "key", "value");
+            array.remove(&mut txn, 0);
+            text.push(&mut txn, "d");
+        }
+
+        let mut actual = summary.lock().unwrap().clone();
+        actual.sort();
+        assert_eq!(
+            actual,
+            vec![
+                "delta: [Removed(1)]".to_string(),
+                "keys: [\"key\"]".to_string(),
+                "text: [Retain(3, None), Inserted(Any(String(\"d\")), None)]".to_string(),
+            ]
+        );
+    }
+
     #[test]
     fn multi_threading() {
         use std::sync::{Arc, RwLock};
@@ -1,5 +1,8 @@
+pub mod annotation;
 pub mod array;
+pub mod keyed_list;
 pub mod map;
+pub mod set;
 pub mod text;
 #[cfg(feature = "weak")]
 pub mod weak;
@@ -13,18 +16,22 @@ pub use text::Text;
 pub use text::TextRef;
 
 use crate::block::{Item, ItemContent, ItemPtr};
-use crate::branch::{Branch, BranchPtr};
+use crate::branch::{Branch, BranchBlocks, BranchMeta, BranchPtr};
 use crate::encoding::read::Error;
+use crate::observer::{kind, ObserverKey};
 use crate::transaction::TransactionMut;
 use crate::types::array::{ArrayEvent, ArrayRef};
+use crate::types::keyed_list::{KeyedListEvent, KeyedListRef};
 use crate::types::map::MapEvent;
+use crate::types::set::{SetEvent, SetRef};
 use crate::types::text::TextEvent;
 #[cfg(feature = "weak")]
 use crate::types::weak::{LinkSource, WeakEvent, WeakRef};
 use crate::types::xml::{XmlElementRef, XmlEvent, XmlTextEvent, XmlTextRef};
 use crate::updates::decoder::{Decode, Decoder};
-use crate::updates::encoder::{Encode, Encoder};
-use serde::{Serialize, Serializer};
+use crate::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
+use serde::de::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Formatter;
@@ -55,9 +62,15 @@ pub const TYPE_REFS_XML_TEXT: u8 = 6;
 /// Type ref identifier for a [WeakRef] type.
 pub const TYPE_REFS_WEAK: u8 = 7;
 
+/// Type ref identifier for a [KeyedListRef] type.
+pub const TYPE_REFS_KEYED_LIST: u8 = 11;
+
 /// Type ref identifier for a [DocRef] type.
 pub const TYPE_REFS_DOC: u8 = 9;
 
+/// Type ref identifier for a [SetRef] type.
+pub const TYPE_REFS_SET: u8 = 12;
+
 /// Placeholder type ref identifier for non-specialized AbstractType. Used only for root-level types
 /// which have been integrated from remote peers before they were defined locally.
 pub const TYPE_REFS_UNDEFINED: u8 = 15;
@@ -70,11 +83,13 @@ pub enum TypeRef {
     Text = TYPE_REFS_TEXT,
     XmlElement(Arc<str>) = TYPE_REFS_XML_ELEMENT,
     XmlFragment = TYPE_REFS_XML_FRAGMENT,
-    XmlHook = TYPE_REFS_XML_HOOK,
+    XmlHook(Arc<str>) = TYPE_REFS_XML_HOOK,
     XmlText = TYPE_REFS_XML_TEXT,
     SubDoc = TYPE_REFS_DOC,
     #[cfg(feature = "weak")]
     WeakLink(Arc<LinkSource>) = TYPE_REFS_WEAK,
+    KeyedList = TYPE_REFS_KEYED_LIST,
+    Set = TYPE_REFS_SET,
     Undefined = TYPE_REFS_UNDEFINED,
 }
 
@@ -86,11 +101,13 @@ impl TypeRef {
             TypeRef::Text => TYPE_REFS_TEXT,
             TypeRef::XmlElement(_) => TYPE_REFS_XML_ELEMENT,
             TypeRef::XmlFragment => TYPE_REFS_XML_FRAGMENT,
-            TypeRef::XmlHook => TYPE_REFS_XML_HOOK,
+            TypeRef::XmlHook(_) => TYPE_REFS_XML_HOOK,
             TypeRef::XmlText => TYPE_REFS_XML_TEXT,
             TypeRef::SubDoc => TYPE_REFS_DOC,
             #[cfg(feature = "weak")]
             TypeRef::WeakLink(_) => TYPE_REFS_WEAK,
+            TypeRef::KeyedList => TYPE_REFS_KEYED_LIST,
+            TypeRef::Set => TYPE_REFS_SET,
             TypeRef::Undefined => TYPE_REFS_UNDEFINED,
         }
     }
@@ -104,11 +121,13 @@ impl std::fmt::Display for TypeRef {
             TypeRef::Text => write!(f, "Text"),
             TypeRef::XmlElement(name) => write!(f, "XmlElement({})", name),
             TypeRef::XmlFragment => write!(f, "XmlFragment"),
-            TypeRef::XmlHook => write!(f, "XmlHook"),
+            TypeRef::XmlHook(name) => write!(f, "XmlHook({})", name),
             TypeRef::XmlText => write!(f, "XmlText"),
             TypeRef::SubDoc => write!(f, "Doc"),
             #[cfg(feature = "weak")]
             TypeRef::WeakLink(_) => write!(f, "WeakRef"),
+            TypeRef::KeyedList => write!(f, "KeyedList"),
+            TypeRef::Set => write!(f, "Set"),
             TypeRef::Undefined => write!(f, "(undefined)"),
         }
     }
@@ -125,7 +144,10 @@ impl Encode for TypeRef {
                 encoder.write_key(&name);
             }
             TypeRef::XmlFragment => encoder.write_type_ref(TYPE_REFS_XML_FRAGMENT),
-            TypeRef::XmlHook => encoder.write_type_ref(TYPE_REFS_XML_HOOK),
+            TypeRef::XmlHook(name) => {
+                encoder.write_type_ref(TYPE_REFS_XML_HOOK);
+                encoder.write_key(name);
+            }
             TypeRef::XmlText => encoder.write_type_ref(TYPE_REFS_XML_TEXT),
             TypeRef::SubDoc => encoder.write_type_ref(TYPE_REFS_DOC),
             #[cfg(feature = "weak")]
@@ -151,6 +173,8 @@ impl Encode for TypeRef {
                     encoder.write_var(end.clock);
                 }
             }
+            TypeRef::KeyedList => encoder.write_type_ref(TYPE_REFS_KEYED_LIST),
+            TypeRef::Set => encoder.write_type_ref(TYPE_REFS_SET),
             TypeRef::Undefined => encoder.write_type_ref(TYPE_REFS_UNDEFINED),
         }
     }
@@ -165,7 +189,7 @@ impl Decode for TypeRef {
             TYPE_REFS_TEXT => Ok(TypeRef::Text),
             TYPE_REFS_XML_ELEMENT => Ok(TypeRef::XmlElement(decoder.read_key()?)),
             TYPE_REFS_XML_FRAGMENT => Ok(TypeRef::XmlFragment),
-            TYPE_REFS_XML_HOOK => Ok(TypeRef::XmlHook),
+            TYPE_REFS_XML_HOOK => Ok(TypeRef::XmlHook(decoder.read_key()?)),
             TYPE_REFS_XML_TEXT => Ok(TypeRef::XmlText),
             TYPE_REFS_DOC => Ok(TypeRef::SubDoc),
             #[cfg(feature = "weak")]
@@ -192,6 +216,8 @@ impl Decode for TypeRef {
                 let end = StickyIndex::from_id(end_id, end_assoc);
                 Ok(TypeRef::WeakLink(Arc::new(LinkSource::new(start, end))))
             }
+            TYPE_REFS_KEYED_LIST => Ok(TypeRef::KeyedList),
+            TYPE_REFS_SET => Ok(TypeRef::Set),
             TYPE_REFS_UNDEFINED => Ok(TypeRef::Undefined),
             _ => Err(Error::UnexpectedValue),
         }
@@ -223,6 +249,44 @@ pub trait Observable: AsRef<Branch> {
         })
     }
 
+    /// Works like [Observable::observe], but a given callback is called before all callbacks
+    /// subscribed to current y-type with lower `priority` and after the ones with higher priority.
+    /// Callbacks subscribed using [Observable::observe] have a priority of `0`. Callbacks with
+    /// the same priority are called in order of their registration.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use yrs::{Doc, Observable, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let log = Arc::new(Mutex::new(Vec::new()));
+    ///
+    /// let l = log.clone();
+    /// let _ui = text.observe(move |_, _| l.lock().unwrap().push("ui"));
+    /// let l = log.clone();
+    /// let _core = text.observe_prioritized(1, move |_, _| l.lock().unwrap().push("core"));
+    ///
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// assert_eq!(*log.lock().unwrap(), vec!["core", "ui"]);
+    /// ```
+    fn observe_prioritized<F>(&self, priority: i32, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &Self::Event) + Send + Sync + 'static,
+        Event: AsRef<Self::Event>,
+    {
+        let branch = BranchPtr::from(self.as_ref());
+        branch.observers.subscribe_prioritized(
+            priority,
+            Box::new(move |txn, e| {
+                let mapped_event = e.as_ref();
+                f(txn, mapped_event)
+            }),
+        )
+    }
+
     /// Subscribes a given callback to be triggered whenever current y-type is changed.
     /// A callback is triggered whenever a transaction gets committed. This function does not
     /// trigger if changes have been observed by nested shared collections.
@@ -231,24 +295,29 @@ pub trait Observable: AsRef<Branch> {
     /// All map-like event changes can be tracked by using [Event::keys] method.
     /// All text-like event changes can be tracked by using [TextEvent::delta] method.
     ///
-    /// Provided key may be used later to unsubscribe from the event.
+    /// Provided key may be used later to unsubscribe from the event. Its priority (see
+    /// [SubscriptionKey::with_priority](crate::observer::SubscriptionKey::with_priority)) defines
+    /// an order in which callbacks are called.
     fn observe_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<Self::Event>,
         F: Fn(&TransactionMut, &Self::Event) + Send + Sync + 'static,
         Event: AsRef<Self::Event>,
     {
-        let mut branch = BranchPtr::from(self.as_ref());
-        branch.observe_with(key.into(), move |txn, e| {
-            let mapped_event = e.as_ref();
-            f(txn, mapped_event)
-        })
+        let branch = BranchPtr::from(self.as_ref());
+        branch.observers.subscribe_with_key(
+            key,
+            Box::new(move |txn, e| {
+                let mapped_event = e.as_ref();
+                f(txn, mapped_event)
+            }),
+        )
     }
 
     /// Unsubscribes a given callback identified by key, that was previously subscribed using [Self::observe_with].
-    fn unobserve<K: Into<Origin>>(&self, key: K) -> bool {
+    fn unobserve<K: ObserverKey<Self::Event>>(&self, key: K) -> bool {
         let mut branch = BranchPtr::from(self.as_ref());
-        branch.unobserve(&key.into())
+        branch.unobserve(&key.into_origin())
     }
 }
 
@@ -264,31 +333,64 @@ pub trait Observable: AsRef<Branch> {
     /// All map-like event changes can be tracked by using [Event::keys] method.
     /// All text-like event changes can be tracked by using [TextEvent::delta] method.
     ///
-    /// Provided key may be used later to unsubscribe from the event.
+    /// Provided key may be used later to unsubscribe from the event. Its priority (see
+    /// [SubscriptionKey::with_priority](crate::observer::SubscriptionKey::with_priority)) defines
+    /// an order in which callbacks are called.
     fn observe_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<Self::Event>,
         F: Fn(&TransactionMut, &Self::Event) + 'static,
         Event: AsRef<Self::Event>,
     {
-        let mut branch = BranchPtr::from(self.as_ref());
-        branch.observe_with(key.into(), move |txn, e| {
-            let mapped_event = e.as_ref();
-            f(txn, mapped_event)
-        })
+        let branch = BranchPtr::from(self.as_ref());
+        branch.observers.subscribe_with_key(
+            key,
+            Box::new(move |txn, e| {
+                let mapped_event = e.as_ref();
+                f(txn, mapped_event)
+            }),
+        )
     }
 
     /// Unsubscribes a given callback identified by key, that was previously subscribed using [Self::observe_with].
-    fn unobserve<K: Into<Origin>>(&self, key: K) -> bool {
+    fn unobserve<K: ObserverKey<Self::Event>>(&self, key: K) -> bool {
         let mut branch = BranchPtr::from(self.as_ref());
-        branch.unobserve(&key.into())
+        branch.unobserve(&key.into_origin())
     }
 }
 
 /// Trait implemented by shared types to display their contents in string format.
 pub trait GetString {
     /// Displays the content of a current collection in string format.
-    fn get_string<T: ReadTxn>(&self, txn: &T) -> String;
+    fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
+        let mut buf = String::new();
+        self.write_string(txn, &mut buf)
+            .expect("Defect: writing to String should never fail");
+        buf
+    }
+
+    /// Writes the content of a current collection in string format into a given `writer`. This
+    /// can be used to render collection contents into an existing buffer (or eg. to count their
+    /// length) without allocating a new string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "world");
+    ///
+    /// let mut buf = String::from("hello ");
+    /// text.write_string(&doc.transact(), &mut buf).unwrap();
+    /// assert_eq!(buf, "hello world");
+    /// ```
+    fn write_string<T: ReadTxn, W: std::fmt::Write>(
+        &self,
+        txn: &T,
+        writer: &mut W,
+    ) -> std::fmt::Result;
 }
 
 /// A subset of [SharedRef] used to mark collaborative collections that can be used as a
@@ -317,6 +419,127 @@ pub trait SharedRef: From<BranchPtr> + AsRef<Branch> {
         let branch = self.as_ref();
         Hook::from(branch.id())
     }
+
+    /// Returns metadata of the latest commit, which modified current shared collection directly
+    /// or through any of its nested collections. It's available only if last-modified tracking was
+    /// enabled (see: [Doc::enable_last_modified](crate::Doc::enable_last_modified)).
+    fn last_modified<'a, T: ReadTxn>(&self, txn: &'a T) -> Option<&'a CommitInfo> {
+        let branch = self.as_ref();
+        txn.commit_log()?.last_modified(&branch.id())
+    }
+
+    /// Returns an iterator over all blocks of a current shared collection, including deleted ones
+    /// (tombstones). It's meant for debugging and developer tools, eg. to visualize how fragmented
+    /// the collection is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{ContentKind, Doc, SharedRef, Text, Transact};
+    ///
+    /// let doc = Doc::with_client_id(1);
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// text.insert(&mut doc.transact_mut(), 2, "--");
+    /// text.remove_range(&mut doc.transact_mut(), 0, 1);
+    ///
+    /// let blocks: Vec<_> = text
+    ///     .blocks(&doc.transact())
+    ///     .map(|b| (b.kind, b.len, b.deleted))
+    ///     .collect();
+    /// assert_eq!(blocks, vec![
+    ///     (ContentKind::Deleted, 1, true),
+    ///     (ContentKind::String, 1, false),
+    ///     (ContentKind::String, 2, false),
+    ///     (ContentKind::String, 3, false),
+    /// ]);
+    /// ```
+    fn blocks<'a, T: ReadTxn>(&self, _txn: &'a T) -> BranchBlocks<'a> {
+        BranchBlocks::new(self.as_ref())
+    }
+
+    /// Returns local, non-replicated metadata attached to a current shared collection. See
+    /// [BranchMeta] for more details.
+    fn meta<'a, T: ReadTxn>(&'a self, _txn: &'a T) -> &'a BranchMeta {
+        &self.as_ref().meta
+    }
+
+    /// Returns a mutable reference to local, non-replicated metadata attached to a current shared
+    /// collection. Metadata changes are not recorded in a transaction and don't emit any events.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, SharedRef, Transact};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct SchemaVersion(u32);
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.meta_mut(&mut doc.transact_mut()).insert(SchemaVersion(2));
+    ///
+    /// // metadata is bound to the collection, not to a particular reference to it
+    /// let text = doc.get_or_insert_text("text");
+    /// let txn = doc.transact();
+    /// assert_eq!(text.meta(&txn).get::<SchemaVersion>(), Some(&SchemaVersion(2)));
+    /// ```
+    fn meta_mut<'a>(&'a self, _txn: &'a mut TransactionMut) -> &'a mut BranchMeta {
+        let branch = BranchPtr::from(self.as_ref());
+        // safety: read-write transaction guarantees exclusive access to document store
+        unsafe { branch.meta_mut() }
+    }
+
+    /// Encodes the state of a current shared collection as an update, which contains only the
+    /// changes (not observed in a given state vector `sv`) made to this collection and all of its
+    /// nested collections. This enables selective synchronization, e.g. sending only a lightweight
+    /// metadata map without the rest of the document. Update is encoded using lib0 v1 encoding
+    /// and sealed with [Options::codec](crate::Options::codec) if document has one configured.
+    ///
+    /// If current collection is nested, produced update contains also the blocks necessary to
+    /// integrate it: the blocks of its parent collections and blocks they've been inserted next
+    /// to. All other blocks are replaced with garbage collected placeholders. It means, that
+    /// a document which has integrated such update will not be able to integrate the replaced
+    /// blocks later on.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Map, ReadTxn, SharedRef, StateVector, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let d1 = Doc::new();
+    /// let meta = d1.get_or_insert_map("meta");
+    /// let body = d1.get_or_insert_text("body");
+    /// {
+    ///     let mut txn = d1.transact_mut();
+    ///     body.push(&mut txn, "very long content");
+    ///     meta.insert(&mut txn, "title", "hello");
+    /// }
+    ///
+    /// let update = meta.encode_state_as_update_v1(&d1.transact(), &StateVector::default());
+    ///
+    /// let d2 = Doc::new();
+    /// let mut txn = d2.transact_mut();
+    /// txn.apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+    /// assert_eq!(txn.get_map("meta").unwrap().get(&txn, "title"), Some("hello".into()));
+    /// assert!(txn.get_text("body").is_none());
+    /// ```
+    fn encode_state_as_update_v1<T: ReadTxn>(&self, txn: &T, sv: &StateVector) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        let branch = BranchPtr::from(self.as_ref());
+        txn.store().encode_branch_diff(branch, sv, &mut encoder);
+        codec::seal(txn.store().options.codec.as_ref(), encoder.to_vec())
+    }
+
+    /// Encodes the state of a current shared collection as an update using lib0 v2 encoding.
+    /// See [SharedRef::encode_state_as_update_v1] for more details.
+    fn encode_state_as_update_v2<T: ReadTxn>(&self, txn: &T, sv: &StateVector) -> Vec<u8> {
+        let mut encoder = EncoderV2::new();
+        let branch = BranchPtr::from(self.as_ref());
+        txn.store().encode_branch_diff(branch, sv, &mut encoder);
+        codec::seal(txn.store().options.codec.as_ref(), encoder.to_vec())
+    }
 }
 
 /// Trait implemented by all Y-types, allowing for observing events which are emitted by
@@ -353,20 +576,18 @@ pub trait DeepObservable: AsRef<Branch> {
     /// [Self::unobserve_deep].
     fn observe_deep_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Deep>,
         F: Fn(&TransactionMut, &Events) + Send + Sync + 'static,
     {
         let branch = self.as_ref();
-        branch
-            .deep_observers
-            .subscribe_with(key.into(), Box::new(f))
+        branch.deep_observers.subscribe_with_key(key, Box::new(f))
     }
 
     /// Unsubscribe a callback identified by a given key, that was previously subscribed using
     /// [Self::observe_deep_with].
-    fn unobserve_deep<K: Into<Origin>>(&self, key: K) -> bool {
+    fn unobserve_deep<K: ObserverKey<kind::Deep>>(&self, key: K) -> bool {
         let branch = self.as_ref();
-        branch.deep_observers.unsubscribe(&key.into())
+        branch.deep_observers.unsubscribe(&key.into_origin())
     }
 }
 
@@ -386,20 +607,18 @@ pub trait DeepObservable: AsRef<Branch> {
     /// [Self::unobserve_deep].
     fn observe_deep_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::Deep>,
         F: Fn(&TransactionMut, &Events) + 'static,
     {
         let branch = self.as_ref();
-        branch
-            .deep_observers
-            .subscribe_with(key.into(), Box::new(f))
+        branch.deep_observers.subscribe_with_key(key, Box::new(f))
     }
 
     /// Unsubscribe a callback identified by a given key, that was previously subscribed using
     /// [Self::observe_deep_with].
-    fn unobserve_deep<K: Into<Origin>>(&self, key: K) -> bool {
+    fn unobserve_deep<K: ObserverKey<kind::Deep>>(&self, key: K) -> bool {
         let branch = self.as_ref();
-        branch.deep_observers.unsubscribe(&key.into())
+        branch.deep_observers.unsubscribe(&key.into_origin())
     }
 }
 
@@ -421,6 +640,10 @@ pub enum Value {
     YXmlFragment(XmlFragmentRef),
     /// Instance of a [XmlTextRef].
     YXmlText(XmlTextRef),
+    /// Instance of a [KeyedListRef].
+    YKeyedList(KeyedListRef),
+    /// Instance of a [SetRef].
+    YSet(SetRef),
     /// Subdocument.
     YDoc(Doc),
     /// Instance of a [WeakRef] or unspecified type (requires manual casting).
@@ -447,6 +670,68 @@ impl Value {
         T::try_from(self)
     }
 
+    /// Works like [Value::cast], but in case of failure returns a [CastError] describing both
+    /// expected and actual type of the value, which makes it easy to propagate with `?` operator.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, MapRef, Transact, TextPrelim, TextRef};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// map.insert(&mut txn, "name", "John");
+    /// map.insert(&mut txn, "age", 30);
+    /// map.insert(&mut txn, "bio", TextPrelim::new("hello"));
+    ///
+    /// let name: String = map.get(&txn, "name").unwrap().try_cast().unwrap();
+    /// let age: i64 = map.get(&txn, "age").unwrap().try_cast().unwrap();
+    /// let bio: TextRef = map.get(&txn, "bio").unwrap().try_cast().unwrap();
+    /// assert_eq!(name, "John");
+    /// assert_eq!(age, 30);
+    ///
+    /// let err = map.get(&txn, "bio").unwrap().try_cast::<MapRef>().unwrap_err();
+    /// assert_eq!(err.value.type_name(), "text");
+    /// assert!(err.to_string().ends_with("found text"));
+    /// ```
+    pub fn try_cast<T>(self) -> Result<T, CastError>
+    where
+        T: TryFrom<Self, Error = Self>,
+    {
+        T::try_from(self).map_err(|value| CastError {
+            expected: std::any::type_name::<T>(),
+            value,
+        })
+    }
+
+    /// Returns a human readable name of the type of current value, eg. `"string"` or `"map"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Any(Any::Null) => "null",
+            Value::Any(Any::Undefined) => "undefined",
+            Value::Any(Any::Bool(_)) => "boolean",
+            Value::Any(Any::Number(_)) => "number",
+            Value::Any(Any::BigInt(_)) => "bigint",
+            Value::Any(Any::String(_)) => "string",
+            Value::Any(Any::Buffer(_)) => "buffer",
+            Value::Any(Any::Array(_)) => "array",
+            Value::Any(Any::Map(_)) => "object",
+            Value::YText(_) => "text",
+            Value::YArray(_) => "y-array",
+            Value::YMap(_) => "y-map",
+            Value::YXmlElement(_) => "xml element",
+            Value::YXmlFragment(_) => "xml fragment",
+            Value::YXmlText(_) => "xml text",
+            Value::YKeyedList(_) => "keyed list",
+            Value::YSet(_) => "set",
+            Value::YDoc(_) => "document",
+            #[cfg(feature = "weak")]
+            Value::YWeakLink(_) => "weak link",
+            Value::UndefinedRef(_) => "undefined shared type",
+        }
+    }
+
     /// Converts current value into stringified representation.
     pub fn to_string<T: ReadTxn>(self, txn: &T) -> String {
         match self {
@@ -457,6 +742,8 @@ impl Value {
             Value::YXmlElement(v) => v.get_string(txn),
             Value::YXmlFragment(v) => v.get_string(txn),
             Value::YXmlText(v) => v.get_string(txn),
+            Value::YKeyedList(v) => v.to_json(txn).to_string(),
+            Value::YSet(v) => v.to_json(txn).to_string(),
             Value::YDoc(v) => v.to_string(),
             #[cfg(feature = "weak")]
             Value::YWeakLink(v) => {
@@ -475,6 +762,8 @@ impl Value {
             Value::YXmlElement(b) => Some(b.as_ref()),
             Value::YXmlFragment(b) => Some(b.as_ref()),
             Value::YXmlText(b) => Some(b.as_ref()),
+            Value::YKeyedList(b) => Some(b.as_ref()),
+            Value::YSet(b) => Some(b.as_ref()),
             #[cfg(feature = "weak")]
             Value::YWeakLink(b) => Some(b.as_ref()),
             Value::UndefinedRef(b) => Some(b.as_ref()),
@@ -518,13 +807,70 @@ impl_try_from!(i16);
 impl_try_from!(i32);
 impl_try_from!(u16);
 impl_try_from!(u32);
+impl_try_from!(u64);
 impl_try_from!(i64);
+impl_try_from!(usize);
 impl_try_from!(isize);
 impl_try_from!(String);
 impl_try_from!(Arc<str>);
 impl_try_from!(Vec<u8>);
 impl_try_from!(Arc<[u8]>);
 
+/// Error returned by [Value::try_cast], when a value could not be converted into requested type.
+#[derive(Debug, thiserror::Error)]
+#[error("expected {expected}, found {}", value.type_name())]
+pub struct CastError {
+    /// Name of the type, which value was supposed to be converted into.
+    pub expected: &'static str,
+    /// Value which failed to be converted.
+    pub value: Value,
+}
+
+/// Error returned by fallible index-based operations (eg. [Array::try_insert] or
+/// [Text::try_remove_range]), when provided index is outside of the bounds of a collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("index {index} is outside of the bounds of a collection of length {len}")]
+pub struct OutOfBounds {
+    /// Length of a collection at the moment when an operation was called.
+    pub len: u32,
+    /// Index which exceeded the bounds of a collection. For range operations this is an end of
+    /// requested range.
+    pub index: u32,
+}
+
+impl OutOfBounds {
+    /// Checks if a range starting at a given `index` and spanning over `range_len` elements fits
+    /// within the bounds of a collection of a given `len`.
+    pub(crate) fn check(len: u32, index: u32, range_len: u32) -> Result<(), Self> {
+        if index > len {
+            return Err(OutOfBounds { len, index });
+        }
+        match index.checked_add(range_len) {
+            Some(end) if end <= len => Ok(()),
+            end => Err(OutOfBounds {
+                len,
+                index: end.unwrap_or(u32::MAX),
+            }),
+        }
+    }
+}
+
+/// Error returned by fallible insertions (eg. [Array::try_insert] or [Text::try_insert]), which
+/// reject a local edit instead of panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum EditError {
+    /// Provided index was outside of the bounds of a collection.
+    #[error(transparent)]
+    OutOfBounds(#[from] OutOfBounds),
+    /// Inserted content would exceed one of the document quotas (see: [Options::max_blocks] and
+    /// [Options::max_content_bytes]).
+    #[error(transparent)]
+    QuotaExceeded(#[from] QuotaExceeded),
+    /// Document is a read-only replica (see: [Options::read_only]).
+    #[error(transparent)]
+    ReadOnly(#[from] ReadOnlyError),
+}
+
 impl ToJson for Value {
     /// Converts current value into [Any] object equivalent that resembles enhanced JSON payload.
     /// Rules are:
@@ -543,6 +889,8 @@ impl ToJson for Value {
             Value::YXmlElement(v) => Any::from(v.get_string(txn)),
             Value::YXmlText(v) => Any::from(v.get_string(txn)),
             Value::YXmlFragment(v) => Any::from(v.get_string(txn)),
+            Value::YKeyedList(v) => v.to_json(txn),
+            Value::YSet(v) => v.to_json(txn),
             Value::YDoc(doc) => any!({"guid": doc.guid().as_ref()}),
             #[cfg(feature = "weak")]
             Value::YWeakLink(_) => Any::Undefined,
@@ -561,6 +909,8 @@ impl std::fmt::Display for Value {
             Value::YXmlElement(_) => write!(f, "XmlElementRef"),
             Value::YXmlFragment(_) => write!(f, "XmlFragmentRef"),
             Value::YXmlText(_) => write!(f, "XmlTextRef"),
+            Value::YKeyedList(_) => write!(f, "KeyedListRef"),
+            Value::YSet(_) => write!(f, "SetRef"),
             #[cfg(feature = "weak")]
             Value::YWeakLink(_) => write!(f, "WeakRef"),
             Value::YDoc(v) => write!(f, "Doc(guid:{})", v.options().guid),
@@ -622,13 +972,9 @@ impl std::fmt::Display for Branch {
                 }
                 Ok(())
             }
-            TypeRef::XmlHook => {
-                write!(f, "YXmlHook(")?;
-                let mut iter = self.map.iter();
-                if let Some((k, v)) = iter.next() {
-                    write!(f, "'{}': {}", k, v)?;
-                }
-                while let Some((k, v)) = iter.next() {
+            TypeRef::XmlHook(name) => {
+                write!(f, "YXmlHook('{}'", name)?;
+                for (k, v) in self.map.iter() {
                     write!(f, ", '{}': {}", k, v)?;
                 }
                 write!(f, ")")
@@ -643,6 +989,20 @@ impl std::fmt::Display for Branch {
             TypeRef::SubDoc => {
                 write!(f, "Subdoc")
             }
+            TypeRef::KeyedList => {
+                write!(f, "YKeyedList")?;
+                if let Some(start) = self.start.as_ref() {
+                    write!(f, "(start: {})", start)?;
+                }
+                Ok(())
+            }
+            TypeRef::Set => {
+                write!(f, "YSet")?;
+                if let Some(start) = self.start.as_ref() {
+                    write!(f, "(start: {})", start)?;
+                }
+                Ok(())
+            }
             #[cfg(feature = "weak")]
             TypeRef::WeakLink(w) => {
                 if w.is_single() {
@@ -720,11 +1080,33 @@ where
     type Item = (&'a str, &'a Item);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (mut key, mut ptr) = self.iter.next()?;
-        while ptr.is_deleted() {
-            (key, ptr) = self.iter.next()?;
+        loop {
+            let (key, ptr) = self.iter.next()?;
+            if let Some(item) = map_entry(ptr, self.txn.borrow()) {
+                return Some((key, item));
+            }
+        }
+    }
+}
+
+/// Given the `latest` item assigned to a map component entry, returns an item holding the value
+/// of that entry, which is visible to a given transaction (see [Item::is_visible]), or `None`
+/// if entry was deleted or didn't exist yet.
+pub(crate) fn map_entry<'a, T: ReadTxn>(latest: &'a Item, txn: &T) -> Option<&'a Item> {
+    match txn.gated_snapshot() {
+        None if latest.is_deleted() => None,
+        None => Some(latest),
+        Some(snapshot) => {
+            // skip over values assigned to that entry after the snapshot was taken
+            let mut current = Some(latest);
+            while let Some(item) = current {
+                if snapshot.state_map.get(&item.id.client) > item.id.clock {
+                    break;
+                }
+                current = item.left.as_deref();
+            }
+            current.filter(|item| snapshot.is_visible(&item.id))
         }
-        Some((key, ptr))
     }
 }
 
@@ -798,6 +1180,10 @@ impl std::fmt::Display for TypePtr {
 /// collection of segments which refer to either index (in case of [Array] or [XmlElement]) or
 /// string key (in case of [Map]) where successor shared collection can be found within subsequent
 /// parent types.
+///
+/// Paths can be serialized, e.g. as a JSON array of keys and indexes like `["pages", 3, "title"]`,
+/// and sent to another process, which can resolve them against its own document replica using
+/// [Doc::resolve_path](crate::Doc::resolve_path).
 pub type Path = VecDeque<PathSegment>;
 
 /// A single segment of a [Path].
@@ -823,6 +1209,51 @@ impl Serialize for PathSegment {
     }
 }
 
+impl<'de> Deserialize<'de> for PathSegment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PathSegmentVisitor;
+        impl Visitor<'_> for PathSegmentVisitor {
+            type Value = PathSegment;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                write!(formatter, "map key string or array index")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                match u32::try_from(v) {
+                    Ok(index) => Ok(PathSegment::Index(index)),
+                    Err(_) => Err(E::custom(format!("index out of range: {}", v))),
+                }
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                match u32::try_from(v) {
+                    Ok(index) => Ok(PathSegment::Index(index)),
+                    Err(_) => Err(E::custom(format!("index out of range: {}", v))),
+                }
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(PathSegment::Key(v.into()))
+            }
+        }
+
+        deserializer.deserialize_any(PathSegmentVisitor)
+    }
+}
+
 pub(crate) struct ChangeSet<D> {
     added: HashSet<ID>,
     deleted: HashSet<ID>,
@@ -840,7 +1271,11 @@ impl<D> ChangeSet<D> {
 }
 
 /// A single change done over an array-component of shared data type.
+///
+/// New kinds of changes may be added in the future (like [Change::Moved] was), therefore matching
+/// over this enum outside of this crate requires a wildcard arm.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Change {
     /// Determines a change that resulted in adding a consecutive number of new elements:
     /// - For [Array] it's a range of inserted elements.
@@ -854,6 +1289,13 @@ pub enum Change {
     /// Determines a number of consecutive unchanged elements. Used to recognize non-edited spaces
     /// between [Change::Added] and/or [Change::Removed] chunks.
     Retain(u32),
+
+    /// Determines a range of `len` existing elements of an [Array], which have been moved from
+    /// index `from` (counted in array state before the transaction) to index `to` (counted in
+    /// array state after the transaction). Moved elements are inserted at the current position
+    /// of the delta (which is always equal to `to`) - just like [Change::Added] ones would be -
+    /// while their former position is represented by [Change::Removed].
+    Moved { from: u32, to: u32, len: u32 },
 }
 
 /// A single change done over a map-component of shared data type.
@@ -953,6 +1395,11 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
     let mut curr_move_is_deleted = false;
     let mut curr_move_end: Option<ItemPtr> = None;
     let mut last_op = None;
+    // indexes of moved elements in array state before (old) and after (new) the transaction
+    let mut old_index = 0;
+    let mut new_index = 0;
+    let mut moved_from = HashMap::new();
+    let mut moved_to = HashMap::new();
 
     #[derive(Default)]
     struct MoveStackItem {
@@ -1023,6 +1470,11 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
                         match item.moved {
                             Some(ptr) if txn.has_added(ptr.id()) => {
                                 let len = item.content_len(encoding);
+                                for i in 0..len {
+                                    let id = ID::new(item.id.client, item.id.clock + i);
+                                    moved_from.insert(id, old_index + i);
+                                }
+                                old_index += len;
                                 last_op = match last_op.take() {
                                     Some(Change::Removed(i)) => Some(Change::Removed(i + len)),
                                     Some(op) => {
@@ -1050,6 +1502,7 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
                             }
                         };
                         last_op = Some(Change::Removed(removed + item.len()));
+                        old_index += item.len();
                         deleted.insert(item.id);
                     } // else nop
                 } else {
@@ -1065,7 +1518,16 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
                                 Vec::with_capacity(item.len() as usize)
                             }
                         };
-                        inserts.append(&mut item.content.get_content());
+                        let mut content = item.content.get_content();
+                        if !txn.has_added(&item.id) {
+                            // existing element has been moved here
+                            for i in 0..content.len() as u32 {
+                                let id = ID::new(item.id.client, item.id.clock + i);
+                                moved_to.insert(new_index + i, id);
+                            }
+                        }
+                        new_index += content.len() as u32;
+                        inserts.append(&mut content);
                         last_op = Some(Change::Added(inserts));
                         added.insert(item.id);
                     } else {
@@ -1078,6 +1540,8 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
                             }
                         };
                         last_op = Some(Change::Retain(retain + item.len()));
+                        old_index += item.len();
+                        new_index += item.len();
                     }
                 }
             } else {
@@ -1097,9 +1561,64 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
         Some(change) => delta.push(change),
     }
 
+    if !moved_to.is_empty() {
+        delta = detect_moves(delta, &moved_from, &moved_to);
+    }
+
     ChangeSet::new(added, deleted, delta)
 }
 
+/// Replaces ranges of [Change::Added] elements, which existed before the transaction and have
+/// been moved from a known position, with [Change::Moved] chunks. `moved_from` maps IDs of moved
+/// elements to their index before the transaction, while `moved_to` maps indexes after the
+/// transaction to IDs of elements moved there.
+fn detect_moves(
+    delta: Vec<Change>,
+    moved_from: &HashMap<ID, u32>,
+    moved_to: &HashMap<u32, ID>,
+) -> Vec<Change> {
+    let mut result = Vec::with_capacity(delta.len());
+    let mut index = 0;
+    for change in delta {
+        match change {
+            Change::Added(values) => {
+                let mut inserted = Vec::new();
+                for value in values {
+                    let from = moved_to.get(&index).and_then(|id| moved_from.get(id));
+                    match (from, result.last_mut()) {
+                        (Some(&from), Some(Change::Moved { from: f, to, len }))
+                            if inserted.is_empty() && *f + *len == from && *to + *len == index =>
+                        {
+                            *len += 1;
+                        }
+                        (Some(&from), _) => {
+                            if !inserted.is_empty() {
+                                result.push(Change::Added(std::mem::take(&mut inserted)));
+                            }
+                            result.push(Change::Moved {
+                                from,
+                                to: index,
+                                len: 1,
+                            });
+                        }
+                        (None, _) => inserted.push(value),
+                    }
+                    index += 1;
+                }
+                if !inserted.is_empty() {
+                    result.push(Change::Added(inserted));
+                }
+            }
+            Change::Retain(len) => {
+                index += len;
+                result.push(Change::Retain(len));
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
 pub struct Events<'a>(Vec<&'a Event>);
 
 impl<'a> Events<'a> {
@@ -1146,6 +1665,8 @@ pub enum Event {
     Map(MapEvent),
     XmlFragment(XmlEvent),
     XmlText(XmlTextEvent),
+    KeyedList(KeyedListEvent),
+    Set(SetEvent),
     #[cfg(feature = "weak")]
     Weak(WeakEvent),
 }
@@ -1200,6 +1721,26 @@ impl AsRef<XmlEvent> for Event {
     }
 }
 
+impl AsRef<KeyedListEvent> for Event {
+    fn as_ref(&self) -> &KeyedListEvent {
+        if let Event::KeyedList(e) = self {
+            e
+        } else {
+            panic!("subscribed callback expected KeyedListRef collection");
+        }
+    }
+}
+
+impl AsRef<SetEvent> for Event {
+    fn as_ref(&self) -> &SetEvent {
+        if let Event::Set(e) = self {
+            e
+        } else {
+            panic!("subscribed callback expected SetRef collection");
+        }
+    }
+}
+
 #[cfg(feature = "weak")]
 impl AsRef<WeakEvent> for Event {
     fn as_ref(&self) -> &WeakEvent {
@@ -1219,6 +1760,8 @@ impl Event {
             Event::Map(e) => e.current_target = target,
             Event::XmlText(e) => e.current_target = target,
             Event::XmlFragment(e) => e.current_target = target,
+            Event::KeyedList(e) => e.current_target = target,
+            Event::Set(e) => e.current_target = target,
             #[cfg(feature = "weak")]
             Event::Weak(e) => e.current_target = target,
         }
@@ -1233,11 +1776,54 @@ impl Event {
             Event::Map(e) => e.path(),
             Event::XmlText(e) => e.path(),
             Event::XmlFragment(e) => e.path(),
+            Event::KeyedList(e) => e.path(),
+            Event::Set(e) => e.path(),
             #[cfg(feature = "weak")]
             Event::Weak(e) => e.path(),
         }
     }
 
+    /// Returns a summary of key-value changes made within a bounds of current transaction over
+    /// a shared type, which triggered current event. Available for [MapRef] and [KeyedListRef]
+    /// entries and attributes of XML nodes. For other types it returns `None`.
+    ///
+    /// See: [MapEvent::keys], [KeyedListEvent::keys], [XmlEvent::keys], [XmlTextEvent::keys].
+    pub fn keys(&self, txn: &TransactionMut) -> Option<&HashMap<Arc<str>, EntryChange>> {
+        match self {
+            Event::Map(e) => Some(e.keys(txn)),
+            Event::KeyedList(e) => Some(e.keys(txn)),
+            Event::XmlFragment(e) => Some(e.keys(txn)),
+            Event::XmlText(e) => Some(e.keys(txn)),
+            _ => None,
+        }
+    }
+
+    /// Returns a summary of sequence changes made within a bounds of current transaction over
+    /// a shared type, which triggered current event. Available for [ArrayRef] elements and child
+    /// nodes of XML elements and fragments. For other types it returns `None`.
+    ///
+    /// See: [ArrayEvent::delta], [XmlEvent::delta].
+    pub fn delta(&self, txn: &TransactionMut) -> Option<&[Change]> {
+        match self {
+            Event::Array(e) => Some(e.delta(txn)),
+            Event::XmlFragment(e) => Some(e.delta(txn)),
+            _ => None,
+        }
+    }
+
+    /// Returns a summary of text changes made within a bounds of current transaction over
+    /// a shared type, which triggered current event. Available for [TextRef] and [XmlTextRef].
+    /// For other types it returns `None`.
+    ///
+    /// See: [TextEvent::delta], [XmlTextEvent::delta].
+    pub fn text_delta(&self, txn: &TransactionMut) -> Option<&[Delta]> {
+        match self {
+            Event::Text(e) => Some(e.delta(txn)),
+            Event::XmlText(e) => Some(e.delta(txn)),
+            _ => None,
+        }
+    }
+
     /// Returns a shared data types which triggered current [Event].
     pub fn target(&self) -> Value {
         match self {
@@ -1245,6 +1831,8 @@ impl Event {
             Event::Array(e) => Value::YArray(e.target().clone()),
             Event::Map(e) => Value::YMap(e.target().clone()),
             Event::XmlText(e) => Value::YXmlText(e.target().clone()),
+            Event::KeyedList(e) => Value::YKeyedList(e.target().clone()),
+            Event::Set(e) => Value::YSet(e.target().clone()),
             Event::XmlFragment(e) => match e.target() {
                 XmlNode::Element(n) => Value::YXmlElement(n.clone()),
                 XmlNode::Fragment(n) => Value::YXmlFragment(n.clone()),
@@ -0,0 +1,666 @@
+use crate::block::{EmbedPrelim, ItemContent, ItemPosition, ItemPtr, Prelim};
+use crate::slice::ItemSlice;
+use crate::transaction::TransactionMut;
+use crate::types::{Branch, BranchPtr, Path, RootRef, SharedRef, ToJson, TypeRef, Value};
+use crate::*;
+use std::cell::UnsafeCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::ops::Deref;
+
+/// Unordered collection of unique primitive values, with observed-remove semantics: when the same
+/// value has been concurrently inserted on one peer and removed on another, insertion wins.
+///
+/// Set members are stored as elements of a sequence component of a set (duplicates originating
+/// from concurrent insertions of the same value are deduplicated when read). Members inserted
+/// one after another by the same peer are merged together into a single block, which makes
+/// the encoding of large sets - e.g. tags, reactions or presence rosters - compact, without
+/// paying the price of a separate map entry per member.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, Set, Transact};
+///
+/// let doc = Doc::new();
+/// let tags = doc.get_or_insert_set("tags");
+/// let mut txn = doc.transact_mut();
+///
+/// assert!(tags.insert(&mut txn, "draft"));
+/// assert!(tags.insert(&mut txn, "urgent"));
+/// assert!(!tags.insert(&mut txn, "draft")); // already present
+/// assert_eq!(tags.len(&txn), 2);
+///
+/// assert!(tags.remove(&mut txn, "draft"));
+/// assert!(!tags.contains(&txn, "draft"));
+/// assert_eq!(tags.iter(&txn).collect::<Vec<_>>(), vec!["urgent".into()]);
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone)]
+pub struct SetRef(BranchPtr);
+
+impl RootRef for SetRef {
+    fn type_ref() -> TypeRef {
+        TypeRef::Set
+    }
+}
+impl SharedRef for SetRef {}
+impl Set for SetRef {}
+
+impl DeepObservable for SetRef {}
+impl Observable for SetRef {
+    type Event = SetEvent;
+}
+
+impl ToJson for SetRef {
+    /// Converts current set into a JSON-like array of its members, in their insertion order.
+    fn to_json<T: ReadTxn>(&self, txn: &T) -> Any {
+        Any::from(self.iter(txn).collect::<Vec<_>>())
+    }
+}
+
+impl AsRef<Branch> for SetRef {
+    fn as_ref(&self) -> &Branch {
+        self.0.deref()
+    }
+}
+
+impl Eq for SetRef {}
+impl PartialEq for SetRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id() == other.0.id()
+    }
+}
+
+impl TryFrom<ItemPtr> for SetRef {
+    type Error = ItemPtr;
+
+    fn try_from(value: ItemPtr) -> Result<Self, Self::Error> {
+        match value.as_branch() {
+            Some(branch) if matches!(branch.type_ref, TypeRef::Set) => Ok(SetRef::from(branch)),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for SetRef {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::YSet(value) => Ok(value),
+            other => Err(other),
+        }
+    }
+}
+
+impl From<BranchPtr> for SetRef {
+    fn from(inner: BranchPtr) -> Self {
+        SetRef(inner)
+    }
+}
+
+pub trait Set: AsRef<Branch> + Sized {
+    /// Returns a number of unique members of current set.
+    fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+        with_index(self.as_ref(), |index| index.members.len() as u32)
+    }
+
+    /// Checks if current set has no members.
+    fn is_empty<T: ReadTxn>(&self, _txn: &T) -> bool {
+        // every live element of a sequence component is a set member
+        self.as_ref().len() == 0
+    }
+
+    /// Checks if a given `value` is a member of current set.
+    fn contains<T: ReadTxn, V: Into<Any>>(&self, _txn: &T, value: V) -> bool {
+        let key = member_key(&value.into());
+        with_index(self.as_ref(), |index| index.members.contains_key(&key))
+    }
+
+    /// Returns an iterator over all unique members of current set, in their insertion order.
+    fn iter<T: ReadTxn>(&self, _txn: &T) -> SetIter {
+        let members = with_index(self.as_ref(), |index| {
+            index.order.values().cloned().collect::<Vec<_>>()
+        });
+        SetIter(members.into_iter())
+    }
+
+    /// Inserts a given `value` into current set. Returns `false` if it was already a member.
+    fn insert<V: Into<Any>>(&self, txn: &mut TransactionMut, value: V) -> bool {
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, |txn, index| {
+            index.insert(txn, branch, value.into())
+        })
+    }
+
+    /// Removes a given `value` from current set. Returns `false` if it was not a member.
+    fn remove<V: Into<Any>>(&self, txn: &mut TransactionMut, value: V) -> bool {
+        let key = member_key(&value.into());
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, |txn, index| index.remove(txn, &key))
+    }
+
+    /// Removes all members from current set.
+    fn clear(&self, txn: &mut TransactionMut) {
+        let branch = BranchPtr::from(self.as_ref());
+        update_index(txn, branch, |txn, index| {
+            branch.remove_at(txn, 0, branch.len());
+            *index = SetIndex::default();
+        })
+    }
+}
+
+/// Returns a binary representation of a set `member`, used to compare members for equality.
+fn member_key(member: &Any) -> Vec<u8> {
+    let mut buf = Vec::new();
+    member.encode(&mut buf);
+    buf
+}
+
+/// Index of set members, cached in a [BranchMeta] of a set branch, so that members can be looked
+/// up by their value without scanning the sequence component. It's kept up to date by [Set]
+/// methods, dropped whenever a set is changed by any other means (e.g. remote updates or
+/// undo/redo) and rebuilt when a transaction which made these changes is committed.
+///
+/// [BranchMeta]: crate::BranchMeta
+#[derive(Debug, Default)]
+pub(crate) struct SetIndex {
+    /// Unique members, by their binary representation.
+    members: HashMap<Vec<u8>, SetMember>,
+    /// Unique members in their insertion order.
+    order: BTreeMap<u64, Any>,
+    /// Sequence number assigned to the next inserted member.
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct SetMember {
+    /// Key of a member within [SetIndex::order].
+    seq: u64,
+    /// IDs of all live elements of a sequence component representing this member (there can be
+    /// many of them when the same value was inserted concurrently), in their sequence order.
+    elements: Vec<ID>,
+}
+
+impl SetIndex {
+    fn new(branch: &Branch) -> Self {
+        let mut index = SetIndex::default();
+        let mut current = branch.start;
+        while let Some(item) = current {
+            if !item.is_deleted() && item.is_countable() {
+                if let ItemContent::Any(values) = &item.content {
+                    for (i, value) in values.iter().enumerate() {
+                        let id = ID::new(item.id.client, item.id.clock + i as u32);
+                        // concurrently inserted duplicates: first occurrence wins
+                        index.member_entry(value).elements.push(id);
+                    }
+                }
+            }
+            current = item.right;
+        }
+        index
+    }
+
+    fn member_entry(&mut self, value: &Any) -> &mut SetMember {
+        let order = &mut self.order;
+        let next_seq = &mut self.next_seq;
+        self.members.entry(member_key(value)).or_insert_with(|| {
+            let seq = *next_seq;
+            *next_seq += 1;
+            order.insert(seq, value.clone());
+            SetMember {
+                seq,
+                elements: Vec::new(),
+            }
+        })
+    }
+
+    fn insert(&mut self, txn: &mut TransactionMut, branch: BranchPtr, value: Any) -> bool {
+        if self.members.contains_key(&member_key(&value)) {
+            return false;
+        }
+        // appending after the latest member keeps consecutive insertions within a single block
+        let latest = self.order.values().next_back().map(member_key);
+        let left = match latest.and_then(|key| self.members.get(&key)) {
+            Some(member) => {
+                let slice = txn.store.blocks.get_item_clean_end(&member.elements[0]);
+                Some(txn.store.materialize(slice.unwrap()))
+            }
+            None => None,
+        };
+        let pos = ItemPosition {
+            parent: branch.into(),
+            left,
+            right: left.and_then(|ptr| ptr.right),
+            index: 0,
+            current_attrs: None,
+        };
+        let ptr = txn.create_item(&pos, value.clone(), None);
+        self.member_entry(&value).elements.push(*ptr.id());
+        true
+    }
+
+    fn remove(&mut self, txn: &mut TransactionMut, key: &[u8]) -> bool {
+        match self.members.remove(key) {
+            Some(member) => {
+                for id in member.elements {
+                    if let Some(slice) = txn.store.blocks.get_item_clean_start(&id) {
+                        let slice = ItemSlice::new(slice.ptr, slice.start, slice.start);
+                        let ptr = txn.store.materialize(slice);
+                        txn.delete(ptr);
+                    }
+                }
+                self.order.remove(&member.seq);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Rebuilds a cached [SetIndex] of a given set `branch` if it was dropped.
+pub(crate) fn reindex(branch: BranchPtr) {
+    // safety: it's called only by a read-write transaction
+    let meta = unsafe { branch.meta_mut() };
+    if !meta.contains::<SetIndex>() {
+        meta.insert(SetIndex::new(&branch));
+    }
+}
+
+/// Drops a cached [SetIndex] of a given set `branch`.
+pub(crate) fn invalidate_index(branch: BranchPtr) {
+    // safety: it's called only by a read-write transaction
+    unsafe { branch.meta_mut() }.remove::<SetIndex>();
+}
+
+/// Calls `f` with a cached index of a set `branch` or - if it's not there - with an index built
+/// on demand.
+fn with_index<F, R>(branch: &Branch, f: F) -> R
+where
+    F: FnOnce(&SetIndex) -> R,
+{
+    match branch.meta.get::<SetIndex>() {
+        Some(index) => f(index),
+        None => f(&SetIndex::new(branch)),
+    }
+}
+
+/// Calls `f`, which modifies a set `branch`, with its index taken out of the cache, so that it's
+/// not dropped by the changes made meanwhile. Index is cached again afterwards.
+fn update_index<F, R>(txn: &mut TransactionMut, branch: BranchPtr, f: F) -> R
+where
+    F: FnOnce(&mut TransactionMut, &mut SetIndex) -> R,
+{
+    let cached = unsafe { branch.meta_mut() }.remove::<SetIndex>();
+    let mut index = cached.unwrap_or_else(|| SetIndex::new(&branch));
+    let result = f(txn, &mut index);
+    unsafe { branch.meta_mut() }.insert(index);
+    result
+}
+
+/// Iterator over members of a [Set], returned by [Set::iter].
+#[derive(Debug)]
+pub struct SetIter(std::vec::IntoIter<Any>);
+
+impl Iterator for SetIter {
+    type Item = Any;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Preliminary set. It can be used to initialize a [SetRef], nested within another shared
+/// collection, with a given collection of members.
+#[repr(transparent)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SetPrelim(Vec<Any>);
+
+impl SetPrelim {
+    pub fn new() -> Self {
+        SetPrelim(Vec::new())
+    }
+}
+
+impl<V: Into<Any>> FromIterator<V> for SetPrelim {
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        SetPrelim(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<V: Into<Any>, const N: usize> From<[V; N]> for SetPrelim {
+    fn from(arr: [V; N]) -> Self {
+        let mut members = Vec::with_capacity(N);
+        for v in arr {
+            members.push(v.into());
+        }
+        SetPrelim(members)
+    }
+}
+
+impl Prelim for SetPrelim {
+    type Return = SetRef;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        let inner = Branch::new(TypeRef::Set);
+        (ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
+        let set = SetRef::from(inner_ref);
+        for value in self.0 {
+            set.insert(txn, value);
+        }
+    }
+}
+
+impl From<SetPrelim> for EmbedPrelim<SetPrelim> {
+    #[inline]
+    fn from(prelim: SetPrelim) -> Self {
+        EmbedPrelim::Shared(prelim)
+    }
+}
+
+/// Event generated by [SetRef::observe] method. Emitted during transaction commit phase.
+pub struct SetEvent {
+    pub(crate) current_target: BranchPtr,
+    target: SetRef,
+    changes: UnsafeCell<Option<(Vec<Any>, Vec<Any>)>>,
+}
+
+impl SetEvent {
+    pub(crate) fn new(branch_ref: BranchPtr) -> Self {
+        let current_target = branch_ref;
+        SetEvent {
+            target: SetRef::from(branch_ref),
+            current_target,
+            changes: UnsafeCell::new(None),
+        }
+    }
+
+    /// Returns a [SetRef] instance which emitted this event.
+    pub fn target(&self) -> &SetRef {
+        &self.target
+    }
+
+    /// Returns a path from root type down to [SetRef] instance which emitted this event.
+    pub fn path(&self) -> Path {
+        Branch::path(self.current_target, self.target.0)
+    }
+
+    /// Returns members which were added to corresponding [SetRef] within bounds of current
+    /// transaction and were not present before.
+    pub fn added(&self, txn: &TransactionMut) -> &[Any] {
+        &self.changes(txn).0
+    }
+
+    /// Returns members which were removed from corresponding [SetRef] within bounds of current
+    /// transaction and are no longer present.
+    pub fn removed(&self, txn: &TransactionMut) -> &[Any] {
+        &self.changes(txn).1
+    }
+
+    fn changes(&self, txn: &TransactionMut) -> &(Vec<Any>, Vec<Any>) {
+        let changes = unsafe { self.changes.get().as_mut().unwrap() };
+        changes.get_or_insert_with(|| set_changes(txn, self.target.0))
+    }
+}
+
+/// Computes members added to and removed from a set `branch` within a given transaction.
+fn set_changes(txn: &TransactionMut, branch: BranchPtr) -> (Vec<Any>, Vec<Any>) {
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let mut current = branch.start;
+    while let Some(item) = current {
+        if let ItemContent::Any(values) = &item.content {
+            let added = txn.has_added(&item.id);
+            if !added && (!item.is_deleted() || txn.has_deleted(&item.id)) {
+                before.extend(values.iter().cloned());
+            }
+            if !item.is_deleted() {
+                after.extend(values.iter().cloned());
+            }
+        }
+        current = item.right;
+    }
+    let before_keys: HashSet<_> = before.iter().map(member_key).collect();
+    let after_keys: HashSet<_> = after.iter().map(member_key).collect();
+    let mut seen = HashSet::new();
+    let added = after
+        .into_iter()
+        .filter(|v| {
+            let key = member_key(v);
+            !before_keys.contains(&key) && seen.insert(key)
+        })
+        .collect();
+    let removed = before
+        .into_iter()
+        .filter(|v| {
+            let key = member_key(v);
+            !after_keys.contains(&key) && seen.insert(key)
+        })
+        .collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block::ItemPtr;
+    use crate::branch::BranchPtr;
+    use crate::test_utils::exchange_updates;
+    use crate::types::set::{member_key, SetIndex, SetPrelim};
+    use crate::types::ToJson;
+    use crate::{
+        any, Any, Doc, Map, MapPrelim, Observable, ReadTxn, Set, SetRef, Transact, Value, ID,
+    };
+    use std::convert::TryFrom;
+    use std::sync::{Arc, Mutex};
+
+    fn index_entries(index: &SetIndex) -> Vec<(Any, Vec<ID>)> {
+        index
+            .order
+            .values()
+            .map(|value| {
+                let member = &index.members[&member_key(value)];
+                assert_eq!(index.order.get(&member.seq), Some(value));
+                (value.clone(), member.elements.clone())
+            })
+            .collect()
+    }
+
+    fn assert_index(set: &SetRef) {
+        let branch = set.as_ref();
+        let cached = branch.meta.get::<SetIndex>().unwrap();
+        assert_eq!(cached.members.len(), cached.order.len());
+        assert_eq!(index_entries(cached), index_entries(&SetIndex::new(branch)));
+    }
+
+    #[test]
+    fn insert_remove() {
+        let doc = Doc::with_client_id(1);
+        let set = doc.get_or_insert_set("set");
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        assert!(set.is_empty(&txn));
+        assert!(set.insert(&mut txn, "a"));
+        assert!(set.insert(&mut txn, 1));
+        assert!(set.insert(&mut txn, "1"));
+        assert!(!set.insert(&mut txn, "a"));
+        assert_eq!(set.len(&txn), 3);
+        assert!(set.contains(&txn, 1));
+        assert!(!set.contains(&txn, 2));
+        assert_eq!(set.to_json(&txn), any!(["a", 1, "1"]));
+
+        assert!(set.remove(&mut txn, 1));
+        assert!(!set.remove(&mut txn, 1));
+        assert_eq!(
+            set.iter(&txn).collect::<Vec<_>>(),
+            vec!["a".into(), "1".into()]
+        );
+
+        let nested = map.insert(&mut txn, "nested", SetPrelim::from(["x", "y", "x"]));
+        assert_eq!(nested.to_json(&txn), any!(["x", "y"]));
+
+        set.clear(&mut txn);
+        assert!(set.is_empty(&txn));
+    }
+
+    #[test]
+    fn concurrent_changes_converge() {
+        let d1 = Doc::with_client_id(1);
+        let s1 = d1.get_or_insert_set("set");
+        let d2 = Doc::with_client_id(2);
+        let s2 = d2.get_or_insert_set("set");
+        s1.insert(&mut d1.transact_mut(), "a");
+        exchange_updates(&[&d1, &d2]);
+
+        // the same value inserted concurrently
+        s1.insert(&mut d1.transact_mut(), "b");
+        s2.insert(&mut d2.transact_mut(), "b");
+        // concurrent insert wins over remove
+        s1.remove(&mut d1.transact_mut(), "a");
+        s2.remove(&mut d2.transact_mut(), "a");
+        s2.insert(&mut d2.transact_mut(), "a");
+        exchange_updates(&[&d1, &d2]);
+
+        let t1 = d1.transact();
+        let t2 = d2.transact();
+        assert_eq!(s1.len(&t1), 2);
+        assert_eq!(s1.to_json(&t1), s2.to_json(&t2));
+        assert!(s1.contains(&t1, "a"));
+
+        // removal of a duplicated member removes all of its occurrences
+        drop((t1, t2));
+        assert!(s1.remove(&mut d1.transact_mut(), "b"));
+        exchange_updates(&[&d1, &d2]);
+        assert!(!s2.contains(&d2.transact(), "b"));
+    }
+
+    #[test]
+    fn compact_encoding() {
+        let doc = Doc::with_client_id(1);
+        let set = doc.get_or_insert_set("set");
+        {
+            let mut txn = doc.transact_mut();
+            for i in 0..1000 {
+                set.insert(&mut txn, i);
+            }
+        }
+        let txn = doc.transact();
+        let update = txn.encode_state_as_update_v1(&Default::default());
+        // all members are stored within a single block
+        assert_eq!(txn.store().blocks.get_client(&1).unwrap().len(), 1);
+        let members: usize = (0..1000).map(|i| member_key(&Any::from(i)).len()).sum();
+        assert!(update.len() < members + 32);
+    }
+
+    #[test]
+    fn observe_set() {
+        let doc = Doc::with_client_id(1);
+        let set = doc.get_or_insert_set("set");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let events = events.clone();
+            set.observe(move |txn, e| {
+                let added = e.added(txn).to_vec();
+                let removed = e.removed(txn).to_vec();
+                events.lock().unwrap().push((added, removed));
+            })
+        };
+
+        {
+            let mut txn = doc.transact_mut();
+            set.insert(&mut txn, "a");
+            set.insert(&mut txn, "b");
+        }
+        {
+            let mut txn = doc.transact_mut();
+            set.remove(&mut txn, "a");
+            set.insert(&mut txn, "c");
+        }
+        {
+            // removed and inserted again within the same transaction
+            let mut txn = doc.transact_mut();
+            set.remove(&mut txn, "b");
+            set.insert(&mut txn, "b");
+        }
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events.as_slice(),
+            &[
+                (vec!["a".into(), "b".into()], vec![]),
+                (vec!["c".into()], vec!["a".into()]),
+                (vec![], vec![]),
+            ]
+        );
+        let value: Value = BranchPtr::from(set.as_ref()).into();
+        assert_eq!(value.cast::<SetRef>(), Ok(set));
+    }
+
+    #[test]
+    fn cached_index_follows_local_and_remote_changes() {
+        let d1 = Doc::with_client_id(1);
+        let s1 = d1.get_or_insert_set("set");
+        let d2 = Doc::with_client_id(2);
+        let s2 = d2.get_or_insert_set("set");
+        {
+            let mut txn = d1.transact_mut();
+            for i in 0..10 {
+                s1.insert(&mut txn, i);
+            }
+            s1.remove(&mut txn, 4);
+            s1.remove(&mut txn, 9);
+            s1.insert(&mut txn, 4);
+        }
+        assert_index(&s1);
+        assert_eq!(
+            s1.to_json(&d1.transact()),
+            any!([0, 1, 2, 3, 5, 6, 7, 8, 4])
+        );
+
+        exchange_updates(&[&d1, &d2]);
+        assert_index(&s2);
+
+        // concurrently inserted duplicates are all removed
+        s1.insert(&mut d1.transact_mut(), "a");
+        s2.insert(&mut d2.transact_mut(), "a");
+        s2.remove(&mut d2.transact_mut(), 0);
+        exchange_updates(&[&d1, &d2]);
+        assert_index(&s1);
+        assert_index(&s2);
+        assert_eq!(s1.to_json(&d1.transact()), s2.to_json(&d2.transact()));
+
+        assert!(s2.remove(&mut d2.transact_mut(), "a"));
+        s2.insert(&mut d2.transact_mut(), "b");
+        assert_index(&s2);
+        exchange_updates(&[&d1, &d2]);
+        assert_index(&s1);
+        assert!(!s1.contains(&d1.transact(), "a"));
+        assert_eq!(s1.to_json(&d1.transact()), s2.to_json(&d2.transact()));
+
+        s1.clear(&mut d1.transact_mut());
+        assert_index(&s1);
+        assert_eq!(s1.len(&d1.transact()), 0);
+    }
+
+    #[test]
+    fn try_from_checks_type() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        let nested_set = map.insert(&mut txn, "set", SetPrelim::new());
+        let nested_map = map.insert(&mut txn, "map", MapPrelim::<u32>::new());
+
+        let item = BranchPtr::from(nested_set.as_ref()).item.unwrap();
+        assert_eq!(SetRef::try_from(item).ok(), Some(nested_set));
+        let item = BranchPtr::from(nested_map.as_ref()).item.unwrap();
+        assert_eq!(SetRef::try_from(item), Err::<SetRef, ItemPtr>(item));
+    }
+}
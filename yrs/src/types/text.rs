@@ -1,14 +1,17 @@
-use crate::block::{EmbedPrelim, Item, ItemContent, ItemPosition, ItemPtr, Prelim};
+use crate::block::{utf16_slice, EmbedPrelim, Item, ItemContent, ItemPosition, ItemPtr, Prelim};
 use crate::transaction::TransactionMut;
-use crate::types::{Attrs, Branch, BranchPtr, Delta, Path, RootRef, SharedRef, TypeRef, Value};
+use crate::types::{
+    Attrs, Branch, BranchPtr, Delta, OutOfBounds, Path, RootRef, SharedRef, ToJson, TypeRef, Value,
+};
 use crate::utils::OptionExt;
 use crate::*;
 use std::borrow::Borrow;
 use std::cell::UnsafeCell;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Formatter;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 
 /// A shared data type used for collaborative text editing. It enables multiple users to add and
 /// remove chunks of text in efficient manner. This type is internally represented as a mutable
@@ -114,18 +117,25 @@ impl GetString for TextRef {
     /// Converts context of this text data structure into a single string value. This method doesn't
     /// render formatting attributes or embedded content. In order to retrieve it, use
     /// [TextRef::diff] method.
-    fn get_string<T: ReadTxn>(&self, _txn: &T) -> String {
+    fn write_string<T: ReadTxn, W: std::fmt::Write>(
+        &self,
+        txn: &T,
+        writer: &mut W,
+    ) -> std::fmt::Result {
         let mut start = self.as_ref().start;
-        let mut s = String::new();
         while let Some(item) = start.as_deref() {
-            if !item.is_deleted() {
-                if let ItemContent::String(item_string) = &item.content {
-                    s.push_str(item_string);
-                }
+            if let ItemContent::String(item_string) = &item.content {
+                let mut result = Ok(());
+                item.visible_ranges(txn, |range| {
+                    if result.is_ok() {
+                        result = writer.write_str(visible_str(item, item_string, range));
+                    }
+                });
+                result?;
             }
             start = item.right.clone();
         }
-        s
+        Ok(())
     }
 }
 
@@ -154,8 +164,11 @@ impl TryFrom<Value> for TextRef {
 
 pub trait Text: AsRef<Branch> + Sized {
     /// Returns a number of characters visible in a current text data structure.
-    fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
-        self.as_ref().content_len
+    fn len<T: ReadTxn>(&self, txn: &T) -> u32 {
+        match txn.gated_snapshot() {
+            Some(gate) => gate.sequence(txn, self.as_ref()).content_len(),
+            None => self.as_ref().content_len,
+        }
     }
 
     /// Inserts a `chunk` of text at a given `index`.
@@ -211,22 +224,41 @@ pub trait Text: AsRef<Branch> + Sized {
             return;
         }
         let this = BranchPtr::from(self.as_ref());
-        if let Some(mut pos) = find_position(this, txn, index) {
-            let value = crate::block::PrelimString(chunk.into());
-            while let Some(right) = pos.right.as_ref() {
-                if right.is_deleted() {
-                    // skip over deleted blocks, just like Yjs does
-                    pos.forward();
-                } else {
-                    break;
-                }
+        if let Some(pos) = find_position(this, txn, index) {
+            if let Err(e) = insert_chunk(txn, pos, chunk) {
+                panic!("{}", e);
             }
-            txn.create_item(&pos, value, None);
         } else {
             panic!("The type or the position doesn't exist!");
         }
     }
 
+    /// Inserts a `chunk` of text at a given `index`. Unlike [Text::insert], this method verifies
+    /// that provided `index` is within the bounds of current text and returns an
+    /// [EditError::OutOfBounds] error otherwise. This makes it suitable for indexes coming from
+    /// untrusted sources (eg. scripts or remote plugins). Insertions exceeding document quotas
+    /// or made on a read-only document are rejected with an error as well.
+    fn try_insert(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        chunk: &str,
+    ) -> Result<(), EditError> {
+        OutOfBounds::check(self.len(txn), index, 0)?;
+        txn.check_writable()?;
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        let this = BranchPtr::from(self.as_ref());
+        match find_position(this, txn, index) {
+            Some(pos) => insert_chunk(txn, pos, chunk).map(|_| ()),
+            None => Err(EditError::OutOfBounds(OutOfBounds {
+                len: self.len(txn),
+                index,
+            })),
+        }
+    }
+
     /// Inserts a `chunk` of text at a given `index`.
     /// If `index` is `0`, this `chunk` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
@@ -333,6 +365,79 @@ pub trait Text: AsRef<Branch> + Sized {
         self.insert(txn, idx, chunk)
     }
 
+    /// Appends a given (potentially very large) `text` at the end of a current text structure.
+    ///
+    /// Unlike [Text::push], this method splits the `text` into multiple blocks of up to
+    /// [Options::text_block_capacity] bytes each. This way loading megabyte-scale documents
+    /// doesn't produce a single huge block, which would need to be copied over every time a new
+    /// edit splits it. See [Text::push_large_with] for a version of this method with configurable
+    /// chunk size.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let content = "lorem ipsum ".repeat(100_000);
+    /// text.push_large(&mut doc.transact_mut(), &content);
+    /// assert_eq!(text.get_string(&doc.transact()), content);
+    /// ```
+    fn push_large(&self, txn: &mut TransactionMut, text: &str) {
+        let chunk_len = txn.store().options.text_block_capacity.max(1);
+        self.push_large_with(txn, text, chunk_len)
+    }
+
+    /// Appends a given (potentially very large) `text` at the end of a current text structure,
+    /// splitting it into multiple blocks of at most `chunk_len` bytes each. Chunks are never split
+    /// in the middle of a character, so a chunk may be longer if `chunk_len` is less than 4.
+    /// Consecutive chunks may still be squashed together once the transaction is committed, up
+    /// to [Options::text_block_capacity] bytes.
+    ///
+    /// This method panics if `chunk_len` is 0.
+    fn push_large_with(&self, txn: &mut TransactionMut, text: &str, chunk_len: usize) {
+        assert!(chunk_len > 0, "chunk length must be greater than 0");
+        if text.is_empty() {
+            return;
+        }
+        let this = BranchPtr::from(self.as_ref());
+        let index = self.len(txn);
+        if let Some(mut pos) = find_position(this, txn, index) {
+            while let Some(right) = pos.right.as_ref() {
+                if right.is_deleted() {
+                    // skip over deleted blocks, just like Yjs does
+                    pos.forward();
+                } else {
+                    break;
+                }
+            }
+            let client_id = txn.store().options.client_id;
+            txn.store_mut()
+                .blocks
+                .get_client_blocks_mut(client_id)
+                .reserve(text.len() / chunk_len + 1);
+
+            let mut remaining = text;
+            while !remaining.is_empty() {
+                let mut end = chunk_len.min(remaining.len());
+                while !remaining.is_char_boundary(end) {
+                    end -= 1;
+                }
+                if end == 0 {
+                    // chunk is shorter than a single character
+                    end = remaining.chars().next().map(char::len_utf8).unwrap_or(1);
+                }
+                let (chunk, rest) = remaining.split_at(end);
+                let value = block::PrelimString(chunk.into());
+                pos.left = Some(txn.create_item(&pos, value, None));
+                remaining = rest;
+            }
+        } else {
+            panic!("The type or the position doesn't exist!");
+        }
+    }
+
     /// Removes up to a `len` characters from a current text structure, starting at given `index`.
     /// This method panics in case when not all expected characters were removed (due to
     /// insufficient number of characters to remove) or `index` is outside of the bounds of text.
@@ -345,6 +450,197 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Removes `len` characters from a current text structure, starting at given `index`. Unlike
+    /// [Text::remove_range], this method returns an [EditError] and leaves current text
+    /// unchanged, when requested range doesn't fit within the bounds of current text or current
+    /// document is a read-only replica.
+    fn try_remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+    ) -> Result<(), EditError> {
+        OutOfBounds::check(self.len(txn), index, len)?;
+        txn.check_writable()?;
+        self.remove_range(txn, index, len);
+        Ok(())
+    }
+
+    /// Replaces `len` characters starting at given `index` with a given `chunk` of text. This is
+    /// done as a single operation sharing the same cursor position: inserted `chunk` takes over
+    /// the formatting attributes of the replaced range, while formatting of neighboring content is
+    /// left untouched.
+    ///
+    /// This method panics under the same conditions as [Text::remove_range].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.push(&mut txn, "hello world");
+    /// text.replace_range(&mut txn, 6, 5, "there");
+    /// assert_eq!(text.get_string(&txn), "hello there");
+    /// ```
+    fn replace_range(&self, txn: &mut TransactionMut, index: u32, len: u32, chunk: &str) {
+        if len == 0 {
+            self.insert(txn, index, chunk);
+            return;
+        }
+        let this = BranchPtr::from(self.as_ref());
+        if let Some(mut pos) = find_position(this, txn, index) {
+            // move past formatting markers opening the replaced range, so that inserted chunk
+            // will be wrapped with the same formatting attributes as the replaced content
+            while let Some(right) = pos.right.as_ref() {
+                if right.is_deleted() || matches!(right.content, ItemContent::Format(_, _)) {
+                    pos.forward();
+                } else {
+                    break;
+                }
+            }
+            if !chunk.is_empty() {
+                let value = crate::block::PrelimString(chunk.into());
+                let item = txn.create_item(&pos, value, None);
+                pos.right = Some(item);
+                pos.forward();
+            }
+            remove(txn, pos, len)
+        } else {
+            panic!("The type or the position doesn't exist!");
+        }
+    }
+
+    /// Replaces all non-overlapping occurrences of a `pattern` within a current text with
+    /// a `replacement`. Each occurrence is replaced using [Text::replace_range]. Returns a number
+    /// of replaced occurrences.
+    ///
+    /// Embedded content is represented as a single U+FFFC (object replacement) character for
+    /// the purpose of matching.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.push(&mut txn, "one fish, two fish");
+    /// assert_eq!(text.replace_all(&mut txn, "fish", "cat"), 2);
+    /// assert_eq!(text.get_string(&txn), "one cat, two cat");
+    /// ```
+    fn replace_all(&self, txn: &mut TransactionMut, pattern: &str, replacement: &str) -> u32 {
+        if pattern.is_empty() {
+            return 0;
+        }
+        let content = SearchableContent::new(self.as_ref(), txn);
+        let matches = content
+            .text
+            .match_indices(pattern)
+            .map(|(start, m)| (start..start + m.len(), replacement.to_string()))
+            .collect();
+        content.replace(self, txn, matches)
+    }
+
+    /// Replaces all non-overlapping matches of a `regex` within a current text with
+    /// a `replacement`, which can refer to capture groups using the syntax supported by
+    /// [regex::Captures::expand]. Returns a number of replaced matches. Empty matches are ignored.
+    ///
+    /// Embedded content is represented as a single U+FFFC (object replacement) character for
+    /// the purpose of matching. Available only with `regex` feature flag enabled.
+    #[cfg(feature = "regex")]
+    fn replace_all_regex(
+        &self,
+        txn: &mut TransactionMut,
+        regex: &regex::Regex,
+        replacement: &str,
+    ) -> u32 {
+        let content = SearchableContent::new(self.as_ref(), txn);
+        let matches = regex
+            .captures_iter(&content.text)
+            .filter_map(|captures| {
+                let m = captures.get(0)?;
+                if m.is_empty() {
+                    return None;
+                }
+                let mut replaced = String::new();
+                captures.expand(replacement, &mut replaced);
+                Some((m.range(), replaced))
+            })
+            .collect();
+        content.replace(self, txn, matches)
+    }
+
+    /// Updates current text, so that its content becomes equal to `new_content`, eg. a buffer of
+    /// a plain-text editor. Instead of replacing the whole text, a minimal character-level diff
+    /// between both strings is computed and applied as a series of insertions and deletions. This
+    /// way unchanged parts of the text keep their identity, so concurrent edits made to them by
+    /// other peers are preserved.
+    ///
+    /// Embedded content is represented as a single U+FFFC (object replacement) character for
+    /// the purpose of comparison.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.push(&mut txn, "hello world");
+    /// text.reconcile(&mut txn, "hello brave new world!");
+    /// assert_eq!(text.get_string(&txn), "hello brave new world!");
+    /// ```
+    fn reconcile(&self, txn: &mut TransactionMut, new_content: &str) {
+        let content = SearchableContent::new(self.as_ref(), txn);
+        if content.text == new_content {
+            return;
+        }
+        let old: Vec<(usize, char)> = content.text.char_indices().collect();
+        let new: Vec<(usize, char)> = new_content.char_indices().collect();
+        // common prefix and suffix don't need to be compared char by char
+        let prefix = old
+            .iter()
+            .zip(new.iter())
+            .take_while(|(a, b)| a.1 == b.1)
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a.1 == b.1)
+            .count();
+        let old_chars: Vec<char> = old[prefix..old.len() - suffix]
+            .iter()
+            .map(|(_, c)| *c)
+            .collect();
+        let new_chars: Vec<char> = new[prefix..new.len() - suffix]
+            .iter()
+            .map(|(_, c)| *c)
+            .collect();
+        let byte_offset = |chars: &[(usize, char)], len: usize, i: usize| {
+            chars
+                .get(prefix + i)
+                .map(|(offset, _)| *offset)
+                .unwrap_or(len)
+        };
+        let matches = diff_hunks(&old_chars, &new_chars)
+            .into_iter()
+            .map(|(o, n)| {
+                let start = byte_offset(&old, content.text.len(), o.start);
+                let end = byte_offset(&old, content.text.len(), o.end);
+                let new_start = byte_offset(&new, new_content.len(), n.start);
+                let new_end = byte_offset(&new, new_content.len(), n.end);
+                (start..end, new_content[new_start..new_end].to_string())
+            })
+            .collect();
+        content.replace(self, txn, matches);
+    }
+
     /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
     /// formatting blocks containing provided `attributes` metadata.
     fn format(&self, txn: &mut TransactionMut, index: u32, len: u32, attributes: Attrs) {
@@ -356,6 +652,24 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
+    /// formatting blocks containing provided `attributes` metadata. Unlike [Text::format], this
+    /// method returns an [EditError] and leaves current text unchanged, when requested range
+    /// doesn't fit within the bounds of current text or current document is a read-only replica.
+    /// Formatting exceeding document quotas panics the same way as [Text::format].
+    fn try_format(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+        attributes: Attrs,
+    ) -> Result<(), EditError> {
+        OutOfBounds::check(self.len(txn), index, len)?;
+        txn.check_writable()?;
+        self.format(txn, index, len, attributes);
+        Ok(())
+    }
+
     /// Returns an ordered sequence of formatted chunks, current [Text] corresponds of. These chunks
     /// may contain inserted pieces of text or more complex elements like embedded binaries of
     /// shared objects. Chunks are organized by type of inserted value and formatting attributes
@@ -395,17 +709,137 @@ pub trait Text: AsRef<Branch> + Sized {
     ///     Diff::new("world".into(), Some(Box::new(italic_and_bold))),
     /// ]);
     /// ```
-    fn diff<T, D, F>(&self, _txn: &T, compute_ychange: F) -> Vec<Diff<D>>
+    fn diff<T, D, F>(&self, txn: &T, compute_ychange: F) -> Vec<Diff<D>>
     where
         T: ReadTxn,
         F: Fn(YChange) -> D,
     {
+        let hi = txn.gated_snapshot().map(|gate| gate.snapshot());
         let mut asm = DiffAssembler::new(compute_ychange);
-        asm.process(self.as_ref().start, None, None, None, None);
+        asm.process(self.as_ref().start, hi, None, None, None);
         asm.finish()
     }
 
-    /// Returns the Delta representation of this YText type.
+    /// Returns current content of this text in a serializable delta format compatible with Quill
+    /// (see: [QuillDelta]), which consists only of insert operations. Shared types embedded in
+    /// this text are converted into their JSON representation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{any, Doc, Text, Transact};
+    /// use yrs::types::Attrs;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("article");
+    /// let mut txn = doc.transact_mut();
+    /// let bold = Attrs::from([("bold".into(), true.into())]);
+    /// text.insert(&mut txn, 0, "hello ");
+    /// text.insert_with_attributes(&mut txn, 6, "world", bold);
+    ///
+    /// let delta = text.to_delta_json(&txn);
+    /// let json = serde_json::to_string(&delta).unwrap();
+    /// assert_eq!(json, r#"{"ops":[{"insert":"hello "},{"insert":"world","attributes":{"bold":true}}]}"#);
+    /// ```
+    fn to_delta_json<T: ReadTxn>(&self, txn: &T) -> QuillDelta {
+        QuillDelta::from_diff(txn, self.diff(txn, YChange::identity))
+    }
+
+    /// Applies changes described by a given `delta` (see: [QuillDelta]) to current text, starting
+    /// from its beginning. Inserted strings and embeds are formatted only with attributes attached
+    /// to their operations, while attributes of retain operations are used to format existing
+    /// content. Lengths of retain and delete operations, as well as strings, are measured using
+    /// [OffsetKind] configured for current document.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    /// use yrs::types::text::QuillDelta;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("article");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "hello world");
+    ///
+    /// let delta: QuillDelta = serde_json::from_str(
+    ///     r#"{"ops":[{"retain":6},{"delete":5},{"insert":"there"}]}"#
+    /// ).unwrap();
+    /// text.apply_delta_json(&mut txn, delta);
+    /// assert_eq!(text.get_string(&txn), "hello there");
+    /// ```
+    fn apply_delta_json(&self, txn: &mut TransactionMut, delta: QuillDelta) {
+        let encoding = txn.store().options.offset_kind;
+        let mut index = 0;
+        for op in delta.ops {
+            if let Some(insert) = op.insert {
+                let attrs = op.attributes.unwrap_or_default();
+                match insert {
+                    Any::String(chunk) => {
+                        self.insert_with_attributes(txn, index, &chunk, attrs);
+                        index += match encoding {
+                            OffsetKind::Bytes => chunk.len() as u32,
+                            OffsetKind::Utf16 => chunk.encode_utf16().count() as u32,
+                        };
+                    }
+                    embed => {
+                        self.insert_embed_with_attributes(txn, index, embed, attrs);
+                        index += 1;
+                    }
+                }
+            } else if let Some(len) = op.delete {
+                self.remove_range(txn, index, len);
+            } else if let Some(len) = op.retain {
+                if let Some(attrs) = op.attributes {
+                    self.format(txn, index, len, attrs);
+                }
+                index += len;
+            }
+        }
+    }
+
+    /// Returns an ordered sequence of formatted chunks (see: [Text::diff]) representing the state
+    /// of this text at the time when `hi` snapshot was taken, annotated with changes made since the
+    /// `lo` snapshot. It's meant to be used to build track changes or suggestion views.
+    ///
+    /// Chunks which were inserted after `lo` snapshot are marked with [ChangeKind::Added], while
+    /// chunks which were present in `lo` but have been removed before `hi` snapshot are included in
+    /// result and marked with [ChangeKind::Removed]. Consecutive changes of the same kind made by
+    /// the same client are grouped together. `compute_ychange` callback is used to convert these
+    /// markers into custom data attached to produced chunks. When `hi` is `None`, the current state
+    /// of the document is used instead.
+    ///
+    /// Removed content can only be reported if it was not garbage collected, which requires
+    /// a document to be created with [Options::skip_gc] enabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Options, ReadTxn, Text, Transact, ID};
+    /// use yrs::types::text::{ChangeKind, Diff, YChange};
+    ///
+    /// let doc = Doc::with_options(Options {
+    ///     client_id: 1,
+    ///     skip_gc: true,
+    ///     ..Options::default()
+    /// });
+    /// let text = doc.get_or_insert_text("article");
+    /// text.insert(&mut doc.transact_mut(), 0, "hello world");
+    /// let prev = doc.transact().snapshot();
+    ///
+    /// text.remove_range(&mut doc.transact_mut(), 5, 6); // remove " world"
+    /// text.insert(&mut doc.transact_mut(), 5, "!");
+    /// let next = doc.transact().snapshot();
+    ///
+    /// let chunks = text.diff_range(&mut doc.transact_mut(), Some(&next), Some(&prev), YChange::identity);
+    /// assert_eq!(chunks, vec![
+    ///     Diff::new("hello".into(), None),
+    ///     Diff::with_change(" world".into(), None, Some(YChange::new(ChangeKind::Removed, ID::new(1, 5)))),
+    ///     Diff::with_change("!".into(), None, Some(YChange::new(ChangeKind::Added, ID::new(1, 11)))),
+    /// ]);
+    /// ```
+    ///
+    /// [Options::skip_gc]: crate::Options::skip_gc
     fn diff_range<D, F>(
         &self,
         txn: &mut TransactionMut,
@@ -427,6 +861,192 @@ pub trait Text: AsRef<Branch> + Sized {
         asm.process(self.as_ref().start, hi, lo, None, None);
         asm.finish()
     }
+
+    /// Returns a number of lines in a current text, separated by `\n` characters. Empty text is
+    /// considered to have a single (empty) line.
+    ///
+    /// All line-oriented methods operate on text content without materializing it as a string.
+    /// Indexes are expressed using [OffsetKind] configured for a current document and embedded
+    /// content is counted as a single character, the same way as in [Text::insert].
+    fn line_count<T: ReadTxn>(&self, txn: &T) -> u32 {
+        let mut count = 1;
+        scan_line_breaks(self.as_ref(), txn, |_| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    /// Returns a range of indexes occupied by a line with a given zero-based `line_idx`. Returned
+    /// range doesn't include the trailing `\n` character. Returns `None` if text has no line with
+    /// such index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.push(&mut txn, "hello\nworld\n");
+    ///
+    /// assert_eq!(text.line_count(&txn), 3);
+    /// assert_eq!(text.line_range(&txn, 1), Some(6..11));
+    /// assert_eq!(text.line_range(&txn, 2), Some(12..12));
+    /// assert_eq!(text.index_to_line_col(&txn, 8), Some((1, 2)));
+    /// assert_eq!(text.line_col_to_index(&txn, 1, 2), Some(8));
+    /// ```
+    fn line_range<T: ReadTxn>(&self, txn: &T, line_idx: u32) -> Option<Range<u32>> {
+        let mut line = 0;
+        let mut start = 0;
+        let mut end = None;
+        let len = scan_line_breaks(self.as_ref(), txn, |offset| {
+            if line == line_idx {
+                end = Some(offset);
+                false
+            } else {
+                line += 1;
+                start = offset + 1;
+                true
+            }
+        });
+        if line == line_idx {
+            Some(start..end.unwrap_or(len))
+        } else {
+            None
+        }
+    }
+
+    /// Converts a given `index` into zero-based line number and column offset within that line.
+    /// Returns `None` if `index` is outside of the bounds of a current text.
+    fn index_to_line_col<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<(u32, u32)> {
+        if index > self.len(txn) {
+            return None;
+        }
+        let mut line = 0;
+        let mut start = 0;
+        scan_line_breaks(self.as_ref(), txn, |offset| {
+            if offset < index {
+                line += 1;
+                start = offset + 1;
+                true
+            } else {
+                false
+            }
+        });
+        Some((line, index - start))
+    }
+
+    /// Converts zero-based `line` number and `col` offset within that line into an index within
+    /// a current text. Returns `None` if there's no such line or `col` exceeds line length.
+    fn line_col_to_index<T: ReadTxn>(&self, txn: &T, line: u32, col: u32) -> Option<u32> {
+        let range = self.line_range(txn, line)?;
+        let index = range.start.checked_add(col)?;
+        if index <= range.end {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over edit operations committed on a current text, reconstructed from
+    /// the blocks it consists of. This way an edit history of a text can be presented without
+    /// storing every update separately.
+    ///
+    /// Insertions are returned in causal order: every insertion comes after the insertions of
+    /// its neighbors at the moment when it was made, and after the preceding insertions of the
+    /// same client. Concurrent insertions are ordered by their clock. Since the document doesn't
+    /// store the information about when (or by whom) a given piece of text was deleted, deletions
+    /// are returned after all insertions.
+    ///
+    /// Garbage collected blocks don't carry any information about their content, therefore
+    /// formatting attributes which were garbage collected are reported as regular insertions.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ID, Text, TextEdit, Transact};
+    ///
+    /// let doc = Doc::with_client_id(1);
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// text.remove_range(&mut doc.transact_mut(), 1, 4);
+    ///
+    /// let history: Vec<_> = text.history(&doc.transact()).collect();
+    /// assert_eq!(history, vec![
+    ///     TextEdit::Insert { id: ID::new(1, 0), len: 1, content: Some("h".into()) },
+    ///     TextEdit::Insert { id: ID::new(1, 1), len: 4, content: None }, // garbage collected
+    ///     TextEdit::Delete { id: ID::new(1, 1), len: 4 },
+    /// ]);
+    /// ```
+    fn history<T: ReadTxn>(&self, _txn: &T) -> TextHistory {
+        TextHistory::new(self.as_ref())
+    }
+
+    /// Removes redundant formatting markers from a current text: markers which are overridden by
+    /// another marker for the same attribute before any content appears and markers which
+    /// don't change the value of an attribute already in effect. Such markers may accumulate
+    /// over time (eg. as a result of concurrent formatting of the same range), increasing the
+    /// number of blocks and the size of encoded document. Formatting visible to the users is
+    /// not changed.
+    ///
+    /// Returns the number of removed formatting markers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, ReadTxn, StateVector, Text, Transact, Update};
+    /// use yrs::types::Attrs;
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let d1 = Doc::with_client_id(1);
+    /// let t1 = d1.get_or_insert_text("text");
+    /// t1.push(&mut d1.transact_mut(), "hello world");
+    ///
+    /// let d2 = Doc::with_client_id(2);
+    /// let t2 = d2.get_or_insert_text("text");
+    /// let update = d1.transact().encode_state_as_update_v1(&StateVector::default());
+    /// d2.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+    ///
+    /// // both peers concurrently bold the same range
+    /// let bold = Attrs::from([("bold".into(), Any::Bool(true))]);
+    /// t1.format(&mut d1.transact_mut(), 0, 5, bold.clone());
+    /// t2.format(&mut d2.transact_mut(), 0, 5, bold);
+    /// let update = d2.transact().encode_state_as_update_v1(&StateVector::default());
+    /// d1.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+    ///
+    /// let mut txn = d1.transact_mut();
+    /// let diff = t1.diff(&txn, |_| ());
+    /// assert_eq!(t1.cleanup_formatting(&mut txn), 2);
+    /// assert_eq!(t1.diff(&txn, |_| ()), diff);
+    /// ```
+    fn cleanup_formatting(&self, txn: &mut TransactionMut) -> u32 {
+        let branch = BranchPtr::from(self.as_ref());
+        let mut cleanups = 0;
+        let mut attrs = Attrs::new();
+        let mut gap = Vec::new();
+        let mut current = branch.start;
+        loop {
+            match current {
+                Some(item) if item.is_deleted() => {}
+                Some(item) if !item.is_countable() => {
+                    if let ItemContent::Format(_, _) = &item.content {
+                        gap.push(item);
+                    }
+                }
+                _ => {
+                    cleanups += clean_formatting_gap(txn, &gap, &mut attrs);
+                    gap.clear();
+                }
+            }
+            match current {
+                Some(item) => current = item.right,
+                None => break,
+            }
+        }
+        cleanups
+    }
 }
 
 impl From<BranchPtr> for TextRef {
@@ -477,8 +1097,8 @@ where
             let attrs = self.attrs_boxed();
             let mut buf = std::mem::replace(&mut self.buf, String::new());
             buf.shrink_to_fit();
-            let change = if let Some(ychange) = self.curr_ychange.take() {
-                Some((self.compute_ychange)(ychange))
+            let change = if let Some(ychange) = self.curr_ychange.as_ref() {
+                Some((self.compute_ychange)(ychange.clone()))
             } else {
                 None
             };
@@ -513,6 +1133,18 @@ where
                 !item.is_deleted()
             }
         }
+        // checks if string `item` is visible at `hi` snapshot only partially, which can happen
+        // when snapshot was not used to split blocks beforehand (see ReadTxn::gated_snapshot)
+        fn partially_seen(hi: Option<&Snapshot>, item: &Item) -> bool {
+            match (hi, &item.content) {
+                (Some(s), ItemContent::String(_)) => {
+                    let mut visible = 0;
+                    s.visible_ranges(&item.id, item.len, |r| visible += r.end - r.start);
+                    visible != 0 && visible != item.len
+                }
+                _ => false,
+            }
+        }
         let (start, start_assoc) = if let Some(index) = start {
             (index.id(), index.assoc)
         } else {
@@ -546,23 +1178,66 @@ where
                     break;
                 }
             }
-            if seen(hi, item) || (lo.is_some() && seen(lo, item)) {
+            if lo.is_none() && partially_seen(hi, item) {
+                if let (Some(hi), ItemContent::String(s)) = (hi, &item.content) {
+                    if self.curr_ychange.is_some() {
+                        self.pack_str();
+                        self.curr_ychange = None;
+                    }
+                    let mut window = (start_offset.max(0) as u32)..item.len;
+                    let mut reached_end = false;
+                    if let Some(end) = end {
+                        if item.contains(end) {
+                            window.end = end.clock - item.id.clock;
+                            if end_assoc == Assoc::After {
+                                window.end += 1;
+                            }
+                            reached_end = true;
+                        }
+                    }
+                    if start_offset >= 0 {
+                        hi.visible_ranges(&item.id, item.len, |r| {
+                            let r = r.start.max(window.start)..r.end.min(window.end);
+                            if r.start < r.end {
+                                self.buf.push_str(utf16_slice(s, r));
+                            }
+                        });
+                        start_offset = 0;
+                    }
+                    if reached_end {
+                        self.pack_str();
+                        break 'LOOP;
+                    }
+                }
+            } else if seen(hi, item) || (lo.is_some() && seen(lo, item)) {
                 match &item.content {
                     ItemContent::String(s) => {
-                        if let Some(snapshot) = hi {
-                            if !snapshot.is_visible(&item.id) {
-                                self.pack_str();
-                                self.curr_ychange =
-                                    Some(YChange::new(ChangeKind::Removed, item.id));
-                            } else if let Some(snapshot) = lo {
-                                if !snapshot.is_visible(&item.id) {
-                                    self.pack_str();
-                                    self.curr_ychange =
-                                        Some(YChange::new(ChangeKind::Added, item.id));
-                                } else if self.curr_ychange.is_some() {
+                        let kind = if !seen(hi, item) {
+                            Some(ChangeKind::Removed)
+                        } else if lo.is_some() && !seen(lo, item) {
+                            Some(ChangeKind::Added)
+                        } else {
+                            None
+                        };
+                        match kind {
+                            Some(kind) => {
+                                let continued = match &self.curr_ychange {
+                                    Some(curr) => {
+                                        curr.kind == kind && curr.id.client == item.id.client
+                                    }
+                                    None => false,
+                                };
+                                if !continued {
+                                    // changes made by different clients are reported separately
                                     self.pack_str();
+                                    self.curr_ychange = Some(YChange::new(kind, item.id));
                                 }
                             }
+                            None if self.curr_ychange.is_some() => {
+                                self.pack_str();
+                                self.curr_ychange = None;
+                            }
+                            None => {}
                         }
                         if start_offset > 0 {
                             let slice = &s.as_str()[start_offset as usize..];
@@ -591,7 +1266,7 @@ where
                             }
                         }
                     }
-                    ItemContent::Type(_) | ItemContent::Embed(_) => {
+                    ItemContent::Type(_) | ItemContent::Embed(_) | ItemContent::Binary(_) => {
                         self.pack_str();
                         if let Some(value) = item.content.get_first() {
                             let attrs = self.attrs_boxed();
@@ -620,6 +1295,7 @@ where
 
 pub(crate) fn diff_between<D, F>(
     ptr: Option<ItemPtr>,
+    snapshot: Option<&Snapshot>,
     start: Option<&StickyIndex>,
     end: Option<&StickyIndex>,
     compute_ychange: F,
@@ -628,7 +1304,7 @@ where
     F: Fn(YChange) -> D,
 {
     let mut asm = DiffAssembler::new(compute_ychange);
-    asm.process(ptr, None, None, start, end);
+    asm.process(ptr, snapshot, None, start, end);
     asm.finish()
 }
 
@@ -640,6 +1316,277 @@ pub(crate) fn update_current_attributes(attrs: &mut Attrs, key: &str, value: &An
     }
 }
 
+/// Iterates over visible content of a given text `branch`, calling `f` with an index of every
+/// `\n` character found, until `f` returns false. Returns the index at which scan has finished.
+fn scan_line_breaks<T, F>(branch: &Branch, txn: &T, mut f: F) -> u32
+where
+    T: ReadTxn,
+    F: FnMut(u32) -> bool,
+{
+    let offset_kind = txn.store().options.offset_kind;
+    let mut offset = 0;
+    let mut current = branch.start;
+    let mut ranges = Vec::new();
+    while let Some(item) = current.as_deref() {
+        if item.is_countable() {
+            ranges.clear();
+            item.visible_ranges(txn, |range| ranges.push(range));
+            for range in ranges.drain(..) {
+                if let ItemContent::String(str) = &item.content {
+                    for c in visible_str(item, str, range).chars() {
+                        if c == '\n' && !f(offset) {
+                            return offset;
+                        }
+                        offset += match offset_kind {
+                            OffsetKind::Bytes => c.len_utf8() as u32,
+                            OffsetKind::Utf16 => c.len_utf16() as u32,
+                        };
+                    }
+                } else {
+                    // embedded content is always a single element
+                    offset += range.end - range.start;
+                }
+            }
+        }
+        current = item.right;
+    }
+    offset
+}
+
+/// Returns a part of a string content of a given `item` within a given `range` of its offsets.
+fn visible_str<'a>(item: &Item, str: &'a str, range: Range<u32>) -> &'a str {
+    if range.start == 0 && range.end == item.len {
+        str
+    } else {
+        utf16_slice(str, range)
+    }
+}
+
+/// Visible content of a text, which can be searched using string matching.
+struct SearchableContent {
+    /// Text content with embedded values represented as U+FFFC character.
+    text: String,
+    /// Byte offsets of embedded values within `text`.
+    embeds: Vec<usize>,
+    offset_kind: OffsetKind,
+}
+
+impl SearchableContent {
+    const EMBED: char = '\u{FFFC}';
+
+    fn new<T: ReadTxn>(branch: &Branch, txn: &T) -> Self {
+        let mut text = String::new();
+        let mut embeds = Vec::new();
+        let mut current = branch.start;
+        while let Some(item) = current.as_deref() {
+            if !item.is_deleted() && item.is_countable() {
+                if let ItemContent::String(str) = &item.content {
+                    text.push_str(str);
+                } else {
+                    for _ in 0..item.len() {
+                        embeds.push(text.len());
+                        text.push(Self::EMBED);
+                    }
+                }
+            }
+            current = item.right;
+        }
+        SearchableContent {
+            text,
+            embeds,
+            offset_kind: txn.store().options.offset_kind,
+        }
+    }
+
+    /// Replaces all `matches` (given as non-overlapping, ordered byte ranges of `self.text`)
+    /// within a `target` text.
+    fn replace<T: Text>(
+        &self,
+        target: &T,
+        txn: &mut TransactionMut,
+        matches: Vec<(Range<usize>, String)>,
+    ) -> u32 {
+        // translate byte ranges into text indexes
+        let mut replacements = Vec::with_capacity(matches.len());
+        let mut embeds = self.embeds.iter().peekable();
+        let mut byte_offset = 0;
+        let mut index = 0;
+        let mut advance = |to: usize| {
+            for (i, c) in self.text[byte_offset..to].char_indices() {
+                if embeds.peek() == Some(&&(byte_offset + i)) {
+                    embeds.next();
+                    index += 1;
+                } else {
+                    index += match self.offset_kind {
+                        OffsetKind::Bytes => c.len_utf8() as u32,
+                        OffsetKind::Utf16 => c.len_utf16() as u32,
+                    };
+                }
+            }
+            byte_offset = to;
+            index
+        };
+        for (range, replacement) in matches {
+            let start = advance(range.start);
+            let end = advance(range.end);
+            replacements.push((start, end - start, replacement));
+        }
+        // apply replacements from the end, so that indexes of preceding ones stay valid
+        let count = replacements.len() as u32;
+        for (index, len, replacement) in replacements.into_iter().rev() {
+            target.replace_range(txn, index, len, &replacement);
+        }
+        count
+    }
+}
+
+/// Computes a minimal sequence of changes turning `old` into `new` using a linear space variant
+/// of Myers' diff algorithm. Changes are returned in order as non-overlapping hunks, each one
+/// being a pair of a replaced range of `old` and a corresponding range of `new`.
+fn diff_hunks(old: &[char], new: &[char]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut hunks = Vec::new();
+    diff_hunks_into(old, new, 0, 0, &mut hunks);
+    hunks
+}
+
+fn diff_hunks_into(
+    old: &[char],
+    new: &[char],
+    old_offset: usize,
+    new_offset: usize,
+    hunks: &mut Vec<(Range<usize>, Range<usize>)>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+    let (old_offset, new_offset) = (old_offset + prefix, new_offset + prefix);
+    if !old.is_empty() && !new.is_empty() {
+        if let Some((x, y)) = middle_snake(old, new) {
+            diff_hunks_into(&old[..x], &new[..y], old_offset, new_offset, hunks);
+            diff_hunks_into(&old[x..], &new[y..], old_offset + x, new_offset + y, hunks);
+            return;
+        }
+    }
+    if old.is_empty() && new.is_empty() {
+        return;
+    }
+    let o = old_offset..old_offset + old.len();
+    let n = new_offset..new_offset + new.len();
+    match hunks.last_mut() {
+        Some((last_o, last_n)) if last_o.end == o.start && last_n.end == n.start => {
+            last_o.end = o.end;
+            last_n.end = n.end;
+        }
+        _ => hunks.push((o, n)),
+    }
+}
+
+/// Finds a point splitting the shortest edit path between `old` and `new` into two halves, by
+/// running Myers' search from both ends at once until they meet. Only the furthest reaching
+/// paths of a current edit distance are kept, so memory usage is linear. Returns `None` if `old`
+/// and `new` have nothing in common.
+fn middle_snake(old: &[char], new: &[char]) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let len = 2 * max_d as usize + 2;
+    let mut forward = vec![-1isize; len];
+    let mut backward = vec![-1isize; len];
+    forward[offset as usize + 1] = 0;
+    backward[offset as usize + 1] = 0;
+    let delta = n - m;
+    // if the delta is odd, forward path will be the one to meet with the backward path
+    let front = delta % 2 != 0;
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+    for d in 0..max_d {
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let i = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && old[x1 as usize] == new[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[i] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let j = offset + delta - k1;
+                if j >= 0 && j < len as isize && backward[j as usize] != -1 {
+                    let x2 = n - backward[j as usize];
+                    if x1 >= x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k1 += 2;
+        }
+
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let i = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && old[(n - x2 - 1) as usize] == new[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            backward[i] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let j = offset + delta - k2;
+                if j >= 0 && j < len as isize && forward[j as usize] != -1 {
+                    let x1 = forward[j as usize];
+                    let y1 = offset + x1 - j;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k2 += 2;
+        }
+    }
+    None
+}
+
+/// Inserts a string `chunk` at a given position, skipping over deleted blocks first.
+fn insert_chunk(
+    txn: &mut TransactionMut,
+    mut pos: ItemPosition,
+    chunk: &str,
+) -> Result<ItemPtr, EditError> {
+    while let Some(right) = pos.right.as_ref() {
+        if right.is_deleted() {
+            // skip over deleted blocks, just like Yjs does
+            pos.forward();
+        } else {
+            break;
+        }
+    }
+    let value = crate::block::PrelimString(chunk.into());
+    txn.try_create_item(&pos, value, None)
+}
+
 fn find_position(this: BranchPtr, txn: &mut TransactionMut, index: u32) -> Option<ItemPosition> {
     let mut pos = {
         ItemPosition {
@@ -724,7 +1671,10 @@ fn remove(txn: &mut TransactionMut, mut pos: ItemPosition, len: u32) {
 
         if !item.is_deleted() {
             match &item.content {
-                ItemContent::Embed(_) | ItemContent::String(_) | ItemContent::Type(_) => {
+                ItemContent::Embed(_)
+                | ItemContent::Binary(_)
+                | ItemContent::String(_)
+                | ItemContent::Type(_) => {
                     let content_len = item.content_len(encoding);
                     let ptr = pos.right.unwrap();
                     if remaining < content_len {
@@ -957,49 +1907,240 @@ fn insert_negated_attributes(
 
         txn.store_mut().blocks.push_block(item);
 
-        pos.forward();
-        store = txn.store_mut();
-    }
-}
+        pos.forward();
+        store = txn.store_mut();
+    }
+}
+
+fn clean_format_gap(
+    txn: &mut TransactionMut,
+    mut start: Option<ItemPtr>,
+    mut end: Option<ItemPtr>,
+    start_attrs: &Attrs,
+    end_attrs: &mut Attrs,
+) -> u32 {
+    while let Some(item) = end.as_deref() {
+        match &item.content {
+            ItemContent::String(_) | ItemContent::Embed(_) | ItemContent::Binary(_) => break,
+            ItemContent::Format(key, value) if !item.is_deleted() => {
+                update_current_attributes(end_attrs, key.as_ref(), value);
+            }
+            _ => {}
+        }
+        end = item.right.clone();
+    }
+
+    let mut cleanups = 0;
+    while start != end {
+        if let Some(item) = start.as_deref() {
+            let right = item.right.clone();
+            if !item.is_deleted() {
+                if let ItemContent::Format(key, value) = &item.content {
+                    let e = end_attrs.get(key).unwrap_or(&Any::Null);
+                    let s = start_attrs.get(key).unwrap_or(&Any::Null);
+                    if e != value.as_ref() || s == value.as_ref() {
+                        txn.delete(start.unwrap());
+                        cleanups += 1;
+                    }
+                }
+            }
+            start = right;
+        } else {
+            break;
+        }
+    }
+    cleanups
+}
+
+/// Removes redundant formatting markers from a `gap` of consecutive markers, placed between
+/// two pieces of content. `attrs` are attributes in effect at the beginning of the gap. Once
+/// finished, they are updated to the attributes in effect at the end of the gap.
+fn clean_formatting_gap(txn: &mut TransactionMut, gap: &[ItemPtr], attrs: &mut Attrs) -> u32 {
+    // the last marker of each attribute is the one which is in effect after the gap
+    let mut effective = HashMap::new();
+    for &item in gap {
+        if let ItemContent::Format(key, _) = &item.content {
+            effective.insert(key.clone(), item);
+        }
+    }
+    let mut cleanups = 0;
+    for &item in gap {
+        if let ItemContent::Format(key, value) = &item.content {
+            let overridden = effective.get(key) != Some(&item);
+            let unchanged = attrs.get(key).unwrap_or(&Any::Null) == value.as_ref();
+            if overridden || unchanged {
+                txn.delete(item);
+                cleanups += 1;
+            }
+        }
+    }
+    for (key, item) in effective {
+        if let ItemContent::Format(_, value) = &item.content {
+            update_current_attributes(attrs, &key, value);
+        }
+    }
+    cleanups
+}
+
+/// A single edit operation committed on a text-like type. See: [Text::history].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextEdit {
+    /// Insertion of `len` elements (characters or embeds) with consecutive identifiers, starting
+    /// from a given `id`. Length of a text is measured in UTF-16 code units. Inserted `content`
+    /// is available only if it was a string, which has not been garbage collected yet.
+    Insert {
+        id: ID,
+        len: u32,
+        content: Option<String>,
+    },
+    /// Deletion of `len` elements with consecutive identifiers, starting from a given `id`.
+    Delete { id: ID, len: u32 },
+}
+
+/// Iterator over edit operations committed on a text-like type. See: [Text::history].
+#[derive(Debug)]
+pub struct TextHistory {
+    edits: std::vec::IntoIter<TextEdit>,
+}
+
+impl TextHistory {
+    fn new(branch: &Branch) -> Self {
+        let mut items = Vec::new();
+        let mut current = branch.start;
+        while let Some(item) = current {
+            // garbage collected blocks are no longer countable, but they were inserted as well
+            if item.is_countable() || matches!(item.content, ItemContent::Deleted(_)) {
+                items.push(item);
+            }
+            current = item.right;
+        }
+
+        // index items by their identifiers, so that we can resolve their dependencies
+        let mut by_client: HashMap<ClientID, Vec<(u32, usize)>> = HashMap::new();
+        for (i, item) in items.iter().enumerate() {
+            by_client
+                .entry(item.id.client)
+                .or_default()
+                .push((item.id.clock, i));
+        }
+        for blocks in by_client.values_mut() {
+            blocks.sort_unstable();
+        }
+        let find = |id: &ID| -> Option<usize> {
+            let blocks = by_client.get(&id.client)?;
+            let pivot = blocks.partition_point(|(clock, _)| *clock <= id.clock);
+            let (_, i) = blocks[pivot.checked_sub(1)?];
+            let item = &items[i];
+            if item.id.clock + item.len > id.clock {
+                Some(i)
+            } else {
+                None
+            }
+        };
+
+        let mut pending = vec![0usize; items.len()];
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); items.len()];
+        for (i, item) in items.iter().enumerate() {
+            let prev = if item.id.clock > 0 {
+                Some(ID::new(item.id.client, item.id.clock - 1))
+            } else {
+                None
+            };
+            let mut deps: Vec<usize> = [item.origin, item.right_origin, prev]
+                .iter()
+                .flatten()
+                .filter_map(&find)
+                .filter(|&dep| dep != i)
+                .collect();
+            deps.sort_unstable();
+            deps.dedup();
+            pending[i] = deps.len();
+            for dep in deps {
+                dependents[dep].push(i);
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<(u32, ClientID, usize)>> = BinaryHeap::new();
+        for (i, item) in items.iter().enumerate() {
+            if pending[i] == 0 {
+                ready.push(Reverse((item.id.clock, item.id.client, i)));
+            }
+        }
+        let mut edits: Vec<TextEdit> = Vec::with_capacity(items.len());
+        let mut deleted = Vec::new();
+        while let Some(Reverse((_, _, i))) = ready.pop() {
+            let item = &items[i];
+            let content = match &item.content {
+                ItemContent::String(s) => Some(s.as_str()),
+                _ => None,
+            };
+            Self::push_insert(&mut edits, item.id, item.len, content);
+            if item.is_deleted() {
+                deleted.push((item.id, item.len));
+            }
+            for &next in dependents[i].iter() {
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    let id = items[next].id;
+                    ready.push(Reverse((id.clock, id.client, next)));
+                }
+            }
+        }
 
-fn clean_format_gap(
-    txn: &mut TransactionMut,
-    mut start: Option<ItemPtr>,
-    mut end: Option<ItemPtr>,
-    start_attrs: &Attrs,
-    end_attrs: &mut Attrs,
-) -> u32 {
-    while let Some(item) = end.as_deref() {
-        match &item.content {
-            ItemContent::String(_) | ItemContent::Embed(_) => break,
-            ItemContent::Format(key, value) if !item.is_deleted() => {
-                update_current_attributes(end_attrs, key.as_ref(), value);
+        deleted.sort_unstable_by_key(|(id, _)| (id.client, id.clock));
+        for (id, len) in deleted {
+            match edits.last_mut() {
+                Some(TextEdit::Delete {
+                    id: last,
+                    len: last_len,
+                }) if last.client == id.client && last.clock + *last_len == id.clock => {
+                    *last_len += len;
+                }
+                _ => edits.push(TextEdit::Delete { id, len }),
             }
-            _ => {}
         }
-        end = item.right.clone();
+        TextHistory {
+            edits: edits.into_iter(),
+        }
     }
 
-    let mut cleanups = 0;
-    while start != end {
-        if let Some(item) = start.as_deref() {
-            let right = item.right.clone();
-            if !item.is_deleted() {
-                if let ItemContent::Format(key, value) = &item.content {
-                    let e = end_attrs.get(key).unwrap_or(&Any::Null);
-                    let s = start_attrs.get(key).unwrap_or(&Any::Null);
-                    if e != value.as_ref() || s == value.as_ref() {
-                        txn.delete(start.unwrap());
-                        cleanups += 1;
+    /// Pushes a new insertion to `edits`, merging it with the last one if they are adjacent.
+    fn push_insert(edits: &mut Vec<TextEdit>, id: ID, len: u32, content: Option<&str>) {
+        if let Some(TextEdit::Insert {
+            id: last,
+            len: last_len,
+            content: last_content,
+        }) = edits.last_mut()
+        {
+            if last.client == id.client && last.clock + *last_len == id.clock {
+                match (last_content, content) {
+                    (Some(last_content), Some(content)) => {
+                        last_content.push_str(content);
+                        *last_len += len;
+                        return;
+                    }
+                    (None, None) => {
+                        *last_len += len;
+                        return;
                     }
+                    _ => {}
                 }
             }
-            start = right;
-        } else {
-            break;
         }
+        edits.push(TextEdit::Insert {
+            id,
+            len,
+            content: content.map(String::from),
+        });
+    }
+}
+
+impl Iterator for TextHistory {
+    type Item = TextEdit;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.edits.next()
     }
-    cleanups
 }
 
 /// A representation of an uniformly-formatted chunk of rich context stored by [TextRef] or
@@ -1033,6 +2174,109 @@ impl<T> Diff<T> {
     }
 }
 
+/// Rich text delta in a format compatible with [Quill](https://quilljs.com/docs/delta/), which
+/// can be (de)serialized using serde, eg. in order to be passed through REST APIs. It can be
+/// produced using [Text::to_delta_json] and applied using [Text::apply_delta_json], or converted
+/// from [Diff] chunks and [TextEvent::delta] changes.
+///
+/// When serialized to JSON it has a form of `{ "ops": [...] }`, where every operation is either
+/// `{ "insert": <string or embed>, "attributes"?: {...} }`, `{ "retain": <len>, "attributes"?: {...} }`
+/// or `{ "delete": <len> }`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QuillDelta {
+    /// Sequence of operations of current delta.
+    pub ops: Vec<QuillOp>,
+}
+
+impl QuillDelta {
+    /// Converts a sequence of [Diff] chunks (see: [Text::diff]) into a delta consisting only of
+    /// insert operations. Shared types are converted into their JSON representation.
+    pub fn from_diff<T: ReadTxn, D>(txn: &T, diff: Vec<Diff<D>>) -> Self {
+        let ops = diff
+            .into_iter()
+            .map(|diff| QuillOp::insert(diff.insert.to_json(txn), diff.attributes.map(|a| *a)))
+            .collect();
+        QuillDelta { ops }
+    }
+
+    /// Converts changes reported by [TextEvent::delta] into a serializable delta. Shared types
+    /// are converted into their JSON representation.
+    pub fn from_delta<T: ReadTxn>(txn: &T, delta: &[Delta]) -> Self {
+        let ops = delta
+            .iter()
+            .map(|d| match d {
+                Delta::Inserted(value, attrs) => {
+                    QuillOp::insert(value.to_json(txn), attrs.as_deref().cloned())
+                }
+                Delta::Deleted(len) => QuillOp::delete(*len),
+                Delta::Retain(len, attrs) => QuillOp::retain(*len, attrs.as_deref().cloned()),
+            })
+            .collect();
+        QuillDelta { ops }
+    }
+
+    /// Converts current delta into a sequence of [Delta] changes.
+    pub fn into_delta(self) -> Vec<Delta> {
+        self.ops
+            .into_iter()
+            .filter_map(|op| {
+                let attrs = op.attributes.map(Box::new);
+                if let Some(insert) = op.insert {
+                    Some(Delta::Inserted(Value::Any(insert), attrs))
+                } else if let Some(len) = op.delete {
+                    Some(Delta::Deleted(len))
+                } else {
+                    op.retain.map(|len| Delta::Retain(len, attrs))
+                }
+            })
+            .collect()
+    }
+}
+
+/// A single operation of [QuillDelta]. Exactly one of `insert`, `retain` or `delete` fields is
+/// expected to be set.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QuillOp {
+    /// Inserted string or embed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert: Option<Any>,
+    /// Number of retained elements.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retain: Option<u32>,
+    /// Number of deleted elements.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete: Option<u32>,
+    /// Formatting attributes of inserted or retained elements. Attributes with `null` values
+    /// remove corresponding formatting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Attrs>,
+}
+
+impl QuillOp {
+    pub fn insert(value: Any, attributes: Option<Attrs>) -> Self {
+        QuillOp {
+            insert: Some(value),
+            attributes,
+            ..Default::default()
+        }
+    }
+
+    pub fn retain(len: u32, attributes: Option<Attrs>) -> Self {
+        QuillOp {
+            retain: Some(len),
+            attributes,
+            ..Default::default()
+        }
+    }
+
+    pub fn delete(len: u32) -> Self {
+        QuillOp {
+            delete: Some(len),
+            ..Default::default()
+        }
+    }
+}
+
 impl<T> std::fmt::Display for Diff<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{{ insert: '{}'", self.insert)?;
@@ -1051,9 +2295,14 @@ impl<T> std::fmt::Display for Diff<T> {
     }
 }
 
+/// Marker describing a change made to a chunk of text between two snapshots, produced by
+/// [Text::diff_range].
 #[derive(Debug, Clone, PartialEq)]
 pub struct YChange {
+    /// Kind of the change made to a chunk of text.
     pub kind: ChangeKind,
+    /// Identifier of the first block of changed chunk. Its client ID identifies the peer which
+    /// inserted that chunk.
     pub id: ID,
 }
 
@@ -1062,19 +2311,26 @@ impl YChange {
         YChange { kind, id }
     }
 
+    /// A `compute_ychange` callback, which returns change markers unchanged.
     #[inline]
     pub fn identity(change: YChange) -> YChange {
         change
     }
 }
 
+/// Kind of a change described by [YChange].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChangeKind {
+    /// Chunk of text was inserted after the older snapshot was taken.
     Added,
+    /// Chunk of text was present in the older snapshot, but was removed before the newer one.
     Removed,
 }
 
 /// Event generated by [Text::observe] method. Emitted during transaction commit phase.
+///
+/// Text delta carried by this event is computed lazily on the first call of [TextEvent::delta] and
+/// cached for the lifetime of the event.
 pub struct TextEvent {
     pub(crate) current_target: BranchPtr,
     target: TextRef,
@@ -1103,7 +2359,10 @@ impl TextEvent {
     }
 
     /// Returns a summary of text changes made over corresponding [Text] collection within
-    /// bounds of current transaction.
+    /// bounds of current transaction. Delta is a sequence of [Delta] operations (compatible with
+    /// Quill delta format): retaining a number of characters (optionally with formatting
+    /// attributes changed), deleting a number of characters or inserting a new chunk of text
+    /// or embedded value, applied in order from the beginning of the text.
     pub fn delta(&self, txn: &TransactionMut) -> &[Delta] {
         let delta = unsafe { self.delta.get().as_mut().unwrap() };
         delta
@@ -1190,7 +2449,7 @@ impl TextEvent {
 
         while let Some(item) = current.as_deref() {
             match &item.content {
-                ItemContent::Type(_) | ItemContent::Embed(_) => {
+                ItemContent::Type(_) | ItemContent::Embed(_) | ItemContent::Binary(_) => {
                     if txn.has_added(&item.id) {
                         if !txn.has_deleted(&item.id) {
                             asm.add_op();
@@ -1347,8 +2606,8 @@ mod test {
     use crate::doc::{OffsetKind, Options};
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
     use crate::transaction::ReadTxn;
-    use crate::types::text::{Attrs, ChangeKind, Delta, Diff, YChange};
-    use crate::types::Value;
+    use crate::types::text::{Attrs, ChangeKind, Delta, Diff, QuillDelta, QuillOp, YChange};
+    use crate::types::{EditError, OutOfBounds, Value};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
     use crate::{
@@ -1360,6 +2619,329 @@ mod test {
     use std::sync::Arc;
     use std::time::Duration;
 
+    #[test]
+    fn quill_delta_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        let delta: QuillDelta = serde_json::from_str(
+            r#"{"ops":[
+                {"insert":"hello ","attributes":{"bold":true}},
+                {"insert":{"image":"cat.png"}},
+                {"insert":"world"}
+            ]}"#,
+        )
+        .unwrap();
+        t1.apply_delta_json(&mut d1.transact_mut(), delta.clone());
+        assert_eq!(t1.to_delta_json(&d1.transact()), delta);
+
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        let d3 = Doc::with_client_id(3);
+        let t3 = d3.get_or_insert_text("text");
+        exchange_updates(&[&d1, &d2, &d3]);
+
+        let changes = Arc::new(ArcSwapOption::default());
+        let c = changes.clone();
+        let _sub = t2.observe(move |txn, e| {
+            c.store(Some(Arc::new(QuillDelta::from_delta(txn, e.delta(txn)))));
+        });
+        let update: QuillDelta = serde_json::from_str(
+            r#"{"ops":[{"retain":6,"attributes":{"bold":null}},{"retain":1},{"delete":5},{"insert":"!"}]}"#,
+        )
+        .unwrap();
+        t2.apply_delta_json(&mut d2.transact_mut(), update);
+
+        let expected = QuillDelta {
+            ops: vec![
+                QuillOp::insert("hello ".into(), None),
+                QuillOp::insert(any!({"image": "cat.png"}), None),
+                QuillOp::insert("!".into(), None),
+            ],
+        };
+        assert_eq!(t2.to_delta_json(&d2.transact()), expected);
+        assert_eq!(
+            serde_json::to_string(&expected).unwrap(),
+            r#"{"ops":[{"insert":"hello "},{"insert":{"image":"cat.png"}},{"insert":"!"}]}"#
+        );
+
+        // delta observed on one peer can be replayed on another one
+        let observed = changes.load_full().unwrap();
+        let observed: QuillDelta =
+            serde_json::from_str(&serde_json::to_string(&*observed).unwrap()).unwrap();
+        t3.apply_delta_json(&mut d3.transact_mut(), observed);
+        assert_eq!(t3.to_delta_json(&d3.transact()), expected);
+    }
+
+    #[test]
+    fn reconcile_preserves_concurrent_edits() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        t1.push(&mut d1.transact_mut(), "the quick brown fox");
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        exchange_updates(&[&d1, &d2]);
+
+        t1.reconcile(&mut d1.transact_mut(), "a quick red fox jumps");
+        assert_eq!(t1.get_string(&d1.transact()), "a quick red fox jumps");
+        t2.insert(&mut d2.transact_mut(), 4, "very "); // "the very quick brown fox"
+        t2.insert(&mut d2.transact_mut(), 20, "ish"); // "the very quick brownish fox"
+        exchange_updates(&[&d1, &d2]);
+
+        let expected = "a very quick redish fox jumps";
+        assert_eq!(t1.get_string(&d1.transact()), expected);
+        assert_eq!(t2.get_string(&d2.transact()), expected);
+
+        // reconciling with the same content is no-op
+        let sv = d1.transact().state_vector();
+        t1.reconcile(&mut d1.transact_mut(), expected);
+        assert_eq!(d1.transact().state_vector(), sv);
+
+        t1.reconcile(&mut d1.transact_mut(), "zażółć 😀");
+        assert_eq!(t1.get_string(&d1.transact()), "zażółć 😀");
+        t1.reconcile(&mut d1.transact_mut(), "");
+        assert_eq!(t1.get_string(&d1.transact()), "");
+    }
+
+    #[test]
+    fn diff_hunks_are_minimal() {
+        fn lcs(a: &[char], b: &[char]) -> usize {
+            let mut row = vec![0; b.len() + 1];
+            for x in a {
+                let mut prev = 0;
+                for (j, y) in b.iter().enumerate() {
+                    let tmp = row[j + 1];
+                    row[j + 1] = if x == y {
+                        prev + 1
+                    } else {
+                        row[j + 1].max(row[j])
+                    };
+                    prev = tmp;
+                }
+            }
+            row[b.len()]
+        }
+
+        let mut rng = fastrand::Rng::with_seed(0x1234);
+        for _ in 0..200 {
+            let old: Vec<char> = (0..rng.usize(0..40)).map(|_| rng.char('a'..='d')).collect();
+            let new: Vec<char> = (0..rng.usize(0..40)).map(|_| rng.char('a'..='d')).collect();
+            let hunks = super::diff_hunks(&old, &new);
+
+            let mut patched = Vec::new();
+            let mut last = 0;
+            let mut changed = 0;
+            for (o, n) in hunks {
+                patched.extend_from_slice(&old[last..o.start]);
+                patched.extend_from_slice(&new[n.clone()]);
+                changed += o.len() + n.len();
+                last = o.end;
+            }
+            patched.extend_from_slice(&old[last..]);
+            assert_eq!(patched, new);
+            assert_eq!(changed, old.len() + new.len() - 2 * lcs(&old, &new));
+        }
+    }
+
+    #[test]
+    fn try_insert_and_remove_out_of_bounds() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        txt.try_insert(&mut txn, 0, "hello").unwrap();
+
+        assert_eq!(
+            txt.try_insert(&mut txn, 6, "!").unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds { len: 5, index: 6 })
+        );
+        assert_eq!(
+            txt.try_remove_range(&mut txn, 3, 3).unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds { len: 5, index: 6 })
+        );
+        assert_eq!(
+            txt.try_format(&mut txn, 3, 3, Attrs::new()).unwrap_err(),
+            EditError::OutOfBounds(OutOfBounds { len: 5, index: 6 })
+        );
+        assert_eq!(txt.get_string(&txn), "hello");
+
+        txt.try_remove_range(&mut txn, 3, 2).unwrap();
+        txt.try_insert(&mut txn, 3, "p!").unwrap();
+        assert_eq!(txt.get_string(&txn), "help!");
+    }
+
+    #[test]
+    fn push_large_splits_text_into_chunks() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        txt1.push(&mut d1.transact_mut(), "abc");
+        let content = "zażółć gęślą jaźń ".repeat(10_000); // ~260KiB
+        txt1.push_large(&mut d1.transact_mut(), &content);
+        txt1.push_large_with(&mut d1.transact_mut(), "ąę", 1);
+
+        let expected = format!("abc{}ąę", content);
+        assert_eq!(txt1.get_string(&d1.transact()), expected);
+        {
+            let txn = d1.transact();
+            let blocks = txn.store().blocks.get_client(&1).unwrap();
+            // chunks have not been squashed back into a single block on commit
+            let capacity = Options::DEFAULT_TEXT_BLOCK_CAPACITY;
+            assert!(blocks.len() > content.len() / capacity);
+            for cell in blocks.iter() {
+                let item = cell.as_item().unwrap();
+                assert!(item.content_len(OffsetKind::Bytes) as usize <= capacity);
+            }
+        }
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+        exchange_updates(&[&d1, &d2]);
+        let idx = (70_000..).find(|&i| expected.is_char_boundary(i)).unwrap();
+        txt2.insert(&mut d2.transact_mut(), idx as u32, "!");
+        exchange_updates(&[&d1, &d2]);
+
+        let mut expected = expected;
+        expected.insert(idx, '!');
+        assert_eq!(txt1.get_string(&d1.transact()), expected);
+        assert_eq!(txt2.get_string(&d2.transact()), expected);
+    }
+
+    #[test]
+    fn text_block_capacity() {
+        fn blocks_count(text_block_capacity: usize) -> usize {
+            let doc = Doc::with_options(Options {
+                client_id: 1,
+                text_block_capacity,
+                ..Options::default()
+            });
+            let txt = doc.get_or_insert_text("text");
+            for chunk in ["ab", "c", "de", "f"] {
+                txt.push(&mut doc.transact_mut(), chunk);
+            }
+            assert_eq!(txt.get_string(&doc.transact()), "abcdef");
+            let txn = doc.transact();
+            let blocks = txn.store().blocks.get_client(&1).unwrap();
+            blocks.len()
+        }
+
+        assert_eq!(blocks_count(0), 4);
+        assert_eq!(blocks_count(3), 2); // "abc", "def"
+        assert_eq!(blocks_count(Options::DEFAULT_TEXT_BLOCK_CAPACITY), 1);
+    }
+
+    #[test]
+    fn text_normalizer() {
+        use crate::TextNormalizer;
+        use std::borrow::Cow;
+
+        let normalizer = TextNormalizer::new(|s: &str| {
+            if s.contains("e\u{301}") {
+                Cow::Owned(s.replace("e\u{301}", "\u{e9}"))
+            } else {
+                Cow::Borrowed(s)
+            }
+        });
+        let d1 = Doc::with_options(Options {
+            client_id: 1,
+            text_normalizer: Some(normalizer),
+            ..Options::default()
+        });
+        let txt1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+
+        txt1.push(&mut d1.transact_mut(), "cafe\u{301}");
+        let bold = Attrs::from([("bold".into(), true.into())]);
+        txt1.insert_with_attributes(&mut d1.transact_mut(), 0, "e\u{301}", bold);
+        assert_eq!(txt1.get_string(&d1.transact()), "\u{e9}caf\u{e9}");
+
+        // remote changes are not normalized
+        txt2.push(&mut d2.transact_mut(), " e\u{301}");
+        exchange_updates(&[&d1, &d2]);
+        let expected = "\u{e9}caf\u{e9} e\u{301}";
+        assert_eq!(txt1.get_string(&d1.transact()), expected);
+        assert_eq!(txt2.get_string(&d2.transact()), expected);
+    }
+
+    #[test]
+    fn cleanup_formatting() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        let d3 = Doc::with_client_id(3);
+        let t3 = d3.get_or_insert_text("text");
+        t1.push(&mut d1.transact_mut(), "abcdefghij");
+        exchange_updates(&[&d1, &d2, &d3]);
+
+        let bold = Attrs::from([("bold".into(), Any::Bool(true))]);
+        let italic = Attrs::from([("italic".into(), Any::Bool(true))]);
+        t1.format(&mut d1.transact_mut(), 2, 6, bold.clone());
+        t2.format(&mut d2.transact_mut(), 0, 4, bold.clone());
+        t2.format(&mut d2.transact_mut(), 4, 4, italic.clone());
+        t3.format(&mut d3.transact_mut(), 2, 6, bold);
+        t3.format(&mut d3.transact_mut(), 2, 6, italic);
+        exchange_updates(&[&d1, &d2, &d3]);
+
+        let expected = t1.diff(&d1.transact(), YChange::identity);
+        let cleanups = t1.cleanup_formatting(&mut d1.transact_mut());
+        assert!(cleanups > 0);
+        assert_eq!(t1.cleanup_formatting(&mut d1.transact_mut()), 0);
+        assert_eq!(t1.diff(&d1.transact(), YChange::identity), expected);
+
+        exchange_updates(&[&d1, &d2, &d3]);
+        assert_eq!(t2.diff(&d2.transact(), YChange::identity), expected);
+        assert_eq!(t2.cleanup_formatting(&mut d2.transact_mut()), 0);
+        let report = d2.check_integrity(&d2.transact());
+        assert!(report.is_ok(), "{}", report);
+    }
+
+    #[test]
+    fn text_history() {
+        use crate::TextEdit;
+
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+
+        txt1.push(&mut d1.transact_mut(), "abc");
+        exchange_updates(&[&d1, &d2]);
+        txt2.insert(&mut d2.transact_mut(), 1, "X");
+        exchange_updates(&[&d1, &d2]);
+        txt1.push(&mut d1.transact_mut(), "Y");
+        txt1.remove_range(&mut d1.transact_mut(), 2, 2);
+        exchange_updates(&[&d1, &d2]);
+
+        assert_eq!(txt1.get_string(&d1.transact()), "aXY");
+        let expected = vec![
+            TextEdit::Insert {
+                id: ID::new(1, 0),
+                len: 1,
+                content: Some("a".into()),
+            },
+            TextEdit::Insert {
+                id: ID::new(1, 1),
+                len: 2,
+                content: None,
+            },
+            TextEdit::Insert {
+                id: ID::new(2, 0),
+                len: 1,
+                content: Some("X".into()),
+            },
+            TextEdit::Insert {
+                id: ID::new(1, 3),
+                len: 1,
+                content: Some("Y".into()),
+            },
+            TextEdit::Delete {
+                id: ID::new(1, 1),
+                len: 2,
+            },
+        ];
+        assert_eq!(txt1.history(&d1.transact()).collect::<Vec<_>>(), expected);
+        assert_eq!(txt2.history(&d2.transact()).collect::<Vec<_>>(), expected);
+    }
+
     #[test]
     fn insert_empty_string() {
         let doc = Doc::new();
@@ -1474,8 +3056,10 @@ mod test {
         let u1 = t1.encode_diff_v1(&StateVector::decode_v1(&d2_sv).unwrap());
         let u2 = t2.encode_diff_v1(&StateVector::decode_v1(&d1_sv).unwrap());
 
-        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap())
+            .unwrap();
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         let a = txt1.get_string(&t1);
         let b = txt2.get_string(&t2);
@@ -1499,7 +3083,8 @@ mod test {
 
         let d2_sv = t2.state_vector().encode_v1();
         let u1 = t1.encode_diff_v1(&StateVector::decode_v1(&d2_sv).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         assert_eq!(txt2.get_string(&t2).as_str(), "I expect that");
 
@@ -1514,8 +3099,10 @@ mod test {
         let d1_sv = t1.state_vector().encode_v1();
         let u1 = t1.encode_diff_v1(&StateVector::decode_v1(&d2_sv.as_slice()).unwrap());
         let u2 = t2.encode_diff_v1(&StateVector::decode_v1(&d1_sv.as_slice()).unwrap());
-        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap())
+            .unwrap();
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         let a = txt1.get_string(&t1);
         let b = txt2.get_string(&t2);
@@ -1539,7 +3126,8 @@ mod test {
 
         let d2_sv = t2.state_vector().encode_v1();
         let u1 = t1.encode_diff_v1(&StateVector::decode_v1(&d2_sv.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         assert_eq!(txt2.get_string(&t2).as_str(), "aaa");
 
@@ -1555,8 +3143,10 @@ mod test {
         let u1 = t1.encode_diff_v1(&StateVector::decode_v1(&d2_sv.as_slice()).unwrap());
         let u2 = t2.encode_diff_v1(&StateVector::decode_v1(&d1_sv.as_slice()).unwrap());
 
-        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap())
+            .unwrap();
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         let a = txt1.get_string(&t1);
         let b = txt2.get_string(&t2);
@@ -1665,7 +3255,8 @@ mod test {
         let d2 = Doc::with_client_id(2);
         let txt2 = d2.get_or_insert_text("test");
         let mut t2 = d2.transact_mut();
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
         assert_eq!(txt2.get_string(&t2).as_str(), "hello world");
 
         txt1.insert(&mut t1, 5, " beautiful");
@@ -1683,8 +3274,10 @@ mod test {
         let u1 = t1.encode_diff_v1(&StateVector::decode_v1(&sv2.as_slice()).unwrap());
         let u2 = t2.encode_diff_v1(&StateVector::decode_v1(&sv1.as_slice()).unwrap());
 
-        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t1.apply_update(Update::decode_v1(u2.as_slice()).unwrap())
+            .unwrap();
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
 
         let a = txt1.get_string(&t1);
         let b = txt2.get_string(&t2);
@@ -1799,7 +3392,8 @@ mod test {
             let sv = t2.state_vector();
             let mut encoder = EncoderV1::new();
             t1.encode_diff(&sv, &mut encoder);
-            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap());
+            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap())
+                .unwrap();
         }
 
         assert_eq!(
@@ -1880,7 +3474,8 @@ mod test {
             assert_eq!(delta1.swap(None), expected);
 
             let mut txn = d2.transact_mut();
-            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                .unwrap();
             drop(txn);
 
             assert_eq!(txt2.get_string(&d2.transact()), "abc".to_string());
@@ -1904,7 +3499,8 @@ mod test {
             assert_eq!(delta1.swap(None), expected);
 
             let mut txn = d2.transact_mut();
-            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                .unwrap();
             drop(txn);
 
             assert_eq!(txt2.get_string(&d2.transact()), "bc".to_string());
@@ -1928,7 +3524,8 @@ mod test {
             assert_eq!(delta1.swap(None), expected);
 
             let mut txn = d2.transact_mut();
-            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                .unwrap();
             drop(txn);
 
             assert_eq!(txt2.get_string(&d2.transact()), "b".to_string());
@@ -1955,7 +3552,8 @@ mod test {
             assert_eq!(delta1.swap(None), expected);
 
             let mut txn = d2.transact_mut();
-            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                .unwrap();
             drop(txn);
 
             assert_eq!(txt2.get_string(&d2.transact()), "zb".to_string());
@@ -1982,7 +3580,8 @@ mod test {
             assert_eq!(delta1.swap(None), expected);
 
             let mut txn = d2.transact_mut();
-            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                .unwrap();
             drop(txn);
 
             assert_eq!(txt2.get_string(&d2.transact()), "yzb".to_string());
@@ -2013,7 +3612,8 @@ mod test {
             assert_eq!(delta1.swap(None), expected);
 
             let mut txn = d2.transact_mut();
-            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap());
+            txn.apply_update(Update::decode_v1(update.as_slice()).unwrap())
+                .unwrap();
             drop(txn);
 
             assert_eq!(txt2.get_string(&d2.transact()), "yzb".to_string());
@@ -2086,7 +3686,8 @@ mod test {
         let txt2 = d2.get_or_insert_text("text");
         {
             let txn = &mut d2.transact_mut();
-            txn.apply_update(Update::decode_v1(&update_v1).unwrap());
+            txn.apply_update(Update::decode_v1(&update_v1).unwrap())
+                .unwrap();
             assert_eq!(txt2.diff(txn, YChange::identity), expected);
         }
 
@@ -2094,7 +3695,8 @@ mod test {
         let txt3 = d3.get_or_insert_text("text");
         {
             let txn = &mut d3.transact_mut();
-            txn.apply_update(Update::decode_v2(&update_v2).unwrap());
+            txn.apply_update(Update::decode_v2(&update_v2).unwrap())
+                .unwrap();
             let actual = txt3.diff(txn, YChange::identity);
             assert_eq!(actual, expected);
         }
@@ -2192,6 +3794,101 @@ mod test {
         assert_eq!(diff, expected);
     }
 
+    #[test]
+    fn line_utilities() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::with_client_id(1)
+        });
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        assert_eq!(txt.line_count(&txn), 1);
+        assert_eq!(txt.line_range(&txn, 0), Some(0..0));
+        assert_eq!(txt.line_range(&txn, 1), None);
+
+        txt.push(&mut txn, "first\nsecond");
+        txt.push(&mut txn, " line\n");
+        txt.insert_embed(&mut txn, 18, vec![1u8, 2, 3]);
+        txt.push(&mut txn, "★\nlast");
+        txt.remove_range(&mut txn, 1, 2); // remove "ir" from "first"
+        assert_eq!(txt.get_string(&txn), "fst\nsecond line\n★\nlast");
+
+        assert_eq!(txt.line_count(&txn), 4);
+        assert_eq!(txt.line_range(&txn, 0), Some(0..3));
+        assert_eq!(txt.line_range(&txn, 1), Some(4..15));
+        assert_eq!(txt.line_range(&txn, 2), Some(16..18)); // embed + ★
+        assert_eq!(txt.line_range(&txn, 3), Some(19..23));
+        assert_eq!(txt.line_range(&txn, 4), None);
+
+        assert_eq!(txt.index_to_line_col(&txn, 0), Some((0, 0)));
+        assert_eq!(txt.index_to_line_col(&txn, 3), Some((0, 3)));
+        assert_eq!(txt.index_to_line_col(&txn, 4), Some((1, 0)));
+        assert_eq!(txt.index_to_line_col(&txn, 23), Some((3, 4)));
+        assert_eq!(txt.index_to_line_col(&txn, 24), None);
+
+        assert_eq!(txt.line_col_to_index(&txn, 1, 6), Some(10));
+        assert_eq!(txt.line_col_to_index(&txn, 2, 2), Some(18));
+        assert_eq!(txt.line_col_to_index(&txn, 2, 3), None);
+        assert_eq!(txt.line_col_to_index(&txn, 5, 0), None);
+    }
+
+    #[test]
+    fn replace_range_keeps_formatting() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        let bold = Attrs::from([("b".into(), true.into())]);
+        txt.insert(&mut txn, 0, "hello world!");
+        txt.format(&mut txn, 6, 5, bold.clone());
+
+        txt.replace_range(&mut txn, 6, 5, "there");
+        assert_eq!(txt.get_string(&txn), "hello there!");
+        assert_eq!(
+            txt.diff(&txn, YChange::identity),
+            vec![
+                Diff::new("hello ".into(), None),
+                Diff::new("there".into(), Some(Box::new(bold))),
+                Diff::new("!".into(), None),
+            ]
+        );
+
+        txt.replace_range(&mut txn, 0, 5, "");
+        txt.replace_range(&mut txn, 0, 0, "oh");
+        assert_eq!(txt.get_string(&txn), "oh there!");
+    }
+
+    #[test]
+    fn replace_all_occurrences() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::with_client_id(1)
+        });
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        txt.push(&mut txn, "★ aa ★ b");
+        txt.insert_embed(&mut txn, 2, Any::from(1));
+        txt.push(&mut txn, "aa");
+
+        assert_eq!(txt.replace_all(&mut txn, "aa", "c"), 2);
+        assert_eq!(txt.get_string(&txn), "★ c ★ bc");
+        assert_eq!(txt.len(&txn), 9);
+        assert_eq!(txt.replace_all(&mut txn, "x", "y"), 0);
+        assert_eq!(txt.replace_all(&mut txn, "", "y"), 0);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn replace_all_regex() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        txt.push(&mut txn, "2024-01-15 and 2023-12-31");
+
+        let regex = regex::Regex::new(r"(\d{4})-(\d{2})-(\d{2})").unwrap();
+        assert_eq!(txt.replace_all_regex(&mut txn, &regex, "$3.$2.$1"), 2);
+        assert_eq!(txt.get_string(&txn), "15.01.2024 and 31.12.2023");
+    }
+
     #[test]
     fn text_remove_4_byte_range() {
         let d1 = Doc::new();
@@ -2361,7 +4058,8 @@ mod test {
         let doc = Doc::new();
         let txt = doc.get_or_insert_text("test");
         let mut txn = doc.transact_mut();
-        txn.apply_update(Update::decode_v1(bin.as_slice()).unwrap());
+        txn.apply_update(Update::decode_v1(bin.as_slice()).unwrap())
+            .unwrap();
 
         assert_eq!(txt.get_string(&txn).as_str(), "abc");
     }
@@ -2394,6 +4092,78 @@ mod test {
         )
     }
 
+    #[test]
+    fn snapshots_with_changes_from_multiple_clients() {
+        let options = |client_id| Options {
+            client_id,
+            skip_gc: true,
+            ..Options::default()
+        };
+        let d1 = Doc::with_options(options(1));
+        let d2 = Doc::with_options(options(2));
+        let txt1 = d1.get_or_insert_text("text");
+        let txt2 = d2.get_or_insert_text("text");
+        txt1.insert(&mut d1.transact_mut(), 0, "abcdef");
+        exchange_updates(&[&d1, &d2]);
+        let prev = d1.transact().snapshot();
+
+        txt1.insert(&mut d1.transact_mut(), 0, "x");
+        txt1.insert(&mut d1.transact_mut(), 1, "y");
+        txt2.insert(&mut d2.transact_mut(), 5, "z");
+        txt2.remove_range(&mut d2.transact_mut(), 1, 2); // remove "bc"
+        txt1.remove_range(&mut d1.transact_mut(), 5, 1); // remove "d"
+        exchange_updates(&[&d1, &d2]);
+
+        let next = d1.transact().snapshot();
+        let bold = Attrs::from([("b".into(), true.into())]);
+        txt1.format(&mut d1.transact_mut(), 0, 3, bold);
+        txt1.insert(&mut d1.transact_mut(), 0, "not included");
+
+        let diff = txt1.diff_range(
+            &mut d1.transact_mut(),
+            Some(&next),
+            Some(&prev),
+            YChange::identity,
+        );
+        assert_eq!(
+            diff,
+            vec![
+                Diff::with_change(
+                    "xy".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Added, ID::new(1, 6)))
+                ),
+                Diff::new("a".into(), None),
+                Diff::with_change(
+                    "bcd".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Removed, ID::new(1, 1)))
+                ),
+                Diff::new("e".into(), None),
+                Diff::with_change(
+                    "z".into(),
+                    None,
+                    Some(YChange::new(ChangeKind::Added, ID::new(2, 0)))
+                ),
+                Diff::new("f".into(), None),
+            ]
+        );
+
+        // without upper bound, current state of the document is used
+        let diff = txt2.diff_range(&mut d2.transact_mut(), None, Some(&prev), |c| c.kind);
+        assert_eq!(
+            diff,
+            vec![
+                Diff::with_change("xy".into(), None, Some(ChangeKind::Added)),
+                Diff::new("a".into(), None),
+                Diff::with_change("bcd".into(), None, Some(ChangeKind::Removed)),
+                Diff::new("e".into(), None),
+                Diff::with_change("z".into(), None, Some(ChangeKind::Added)),
+                Diff::new("f".into(), None),
+            ]
+        );
+    }
+
     #[test]
     fn diff_with_embedded_items() {
         let doc = Doc::new();
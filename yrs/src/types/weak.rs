@@ -6,14 +6,21 @@ use crate::iter::{
 };
 use crate::types::{Branch, BranchPtr, Path, SharedRef, TypeRef, Value};
 use crate::{
-    Array, Assoc, DeepObservable, GetString, Map, Observable, ReadTxn, StickyIndex, TextRef,
-    TransactionMut, XmlTextRef, ID,
+    Any, Array, Assoc, DeepObservable, Doc, GetString, Map, Observable, ReadTxn, StickyIndex,
+    TextRef, TransactionMut, Uuid, XmlTextRef, ID,
 };
+#[cfg(not(target_family = "wasm"))]
+use crate::{Subscription, Transact};
+#[cfg(not(target_family = "wasm"))]
+use atomic_refcell::BorrowMutError;
 use std::collections::hash_map::Entry;
-use std::collections::{Bound, HashSet};
+use std::collections::{Bound, HashMap, HashSet};
 use std::convert::TryFrom;
+use std::fmt::Write;
 use std::marker::PhantomData;
 use std::ops::{DerefMut, RangeBounds};
+#[cfg(not(target_family = "wasm"))]
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -193,6 +200,10 @@ impl GetString for WeakRef<TextRef> {
     fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
         self.source().to_string(txn)
     }
+
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
+        writer.write_str(&self.get_string(txn))
+    }
 }
 
 impl GetString for WeakRef<XmlTextRef> {
@@ -226,6 +237,10 @@ impl GetString for WeakRef<XmlTextRef> {
     fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
         self.source().to_xml_string(txn)
     }
+
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
+        writer.write_str(&self.get_string(txn))
+    }
 }
 
 impl<P: AsRef<Branch>> WeakRef<P> {
@@ -381,12 +396,20 @@ impl GetString for WeakPrelim<TextRef> {
     fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
         self.source.to_string(txn)
     }
+
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
+        writer.write_str(&self.get_string(txn))
+    }
 }
 
 impl GetString for WeakPrelim<XmlTextRef> {
     fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
         self.source.to_xml_string(txn)
     }
+
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
+        writer.write_str(&self.get_string(txn))
+    }
 }
 
 impl<P: AsRef<Branch>> From<WeakRef<P>> for WeakPrelim<P> {
@@ -602,11 +625,16 @@ impl LinkSource {
         let curr = self.first_item.get_owned();
         if let Some(item) = curr.as_deref() {
             if let Some(branch) = item.parent.as_branch() {
-                return XmlTextRef::get_string_fragment(
+                let mut buf = String::new();
+                XmlTextRef::write_string_fragment(
+                    &mut buf,
                     branch.start,
+                    None,
                     Some(&self.quote_start),
                     Some(&self.quote_end),
-                );
+                )
+                .unwrap();
+                return buf;
             }
         }
         String::new()
@@ -771,6 +799,155 @@ pub enum QuoteError {
     UnboundedRange,
 }
 
+/// Reference to a range of elements quoted from a collection living in another document.
+///
+/// Unlike [WeakRef], which can only quote elements of the same document, [DocLink] identifies
+/// the referenced document by its [Doc::guid](crate::Doc::guid) and the quoted range by a pair
+/// of [StickyIndex]es. This way it can be stored as a plain value (see: [DocLink::to_json]) in
+/// any document of a workspace, eg. to represent backlinks or transclusions.
+///
+/// Links are resolved lazily: [DocLink::unquote] returns `None` until the referenced document is
+/// loaded and contains quoted elements. If all quoted elements have been deleted, a link is
+/// considered dangling (see: [DocLink::observe_dangling]).
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Array, Doc, DocLink, Transact};
+/// use yrs::types::ToJson;
+///
+/// let target = Doc::new();
+/// let array = target.get_or_insert_array("array");
+/// array.insert_range(&mut target.transact_mut(), 0, [1, 2, 3, 4]);
+/// let link = DocLink::quote(&target.transact(), &array, 1..3).unwrap();
+///
+/// // store link in another document
+/// let source = Doc::new();
+/// let links = source.get_or_insert_array("links");
+/// links.push_back(&mut source.transact_mut(), link.to_json());
+///
+/// let json = links.get(&source.transact(), 0).unwrap().to_json(&source.transact());
+/// let link = DocLink::from_json(&json).unwrap();
+/// let txn = target.transact();
+/// let quoted: Vec<_> = link.unquote(&txn).unwrap().collect();
+/// assert_eq!(quoted, vec![2.into(), 3.into()]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocLink {
+    /// Unique identifier of a document containing quoted elements.
+    pub guid: Uuid,
+    /// Beginning of a quoted range.
+    pub start: StickyIndex,
+    /// End of a quoted range.
+    pub end: StickyIndex,
+}
+
+impl DocLink {
+    /// Creates a new link to a given `range` of elements of a `quotable` collection. Range
+    /// boundaries are interpreted the same way as in [Quotable::quote].
+    pub fn quote<T, Q, R>(txn: &T, quotable: &Q, range: R) -> Result<Self, QuoteError>
+    where
+        T: ReadTxn,
+        Q: Quotable,
+        R: RangeBounds<u32>,
+    {
+        let prelim = quotable.quote(txn, range)?;
+        let source = prelim.source();
+        Ok(DocLink {
+            guid: txn.store().options.guid.clone(),
+            start: source.quote_start.clone(),
+            end: source.quote_end.clone(),
+        })
+    }
+
+    /// Returns a sub document referenced by current link, if it's one of the sub documents of
+    /// a document owning a given transaction (eg. a workspace document). Returned document may
+    /// need to be loaded first (see: [Doc::load](crate::Doc::load)).
+    pub fn target_doc<T: ReadTxn>(&self, txn: &T) -> Option<Doc> {
+        txn.subdoc(&self.guid).cloned()
+    }
+
+    /// Returns an iterator over quoted elements, which have not been deleted. Returns `None` if
+    /// a given transaction doesn't belong to a referenced document or quoted elements are not
+    /// present in it (eg. because a document has not been loaded or they have been garbage
+    /// collected).
+    pub fn unquote<'a, T: ReadTxn>(&self, txn: &'a T) -> Option<Unquote<'a, T>> {
+        let store = txn.store();
+        if store.options.guid != self.guid {
+            return None;
+        }
+        let item = store.blocks.get_item(self.start.id()?)?;
+        let parent = *item.parent.as_branch()?;
+        Some(Unquote::new(
+            txn,
+            parent,
+            self.start.clone(),
+            self.end.clone(),
+        ))
+    }
+
+    /// Checks if all quoted elements of a referenced document have been deleted, or cannot be
+    /// found within it.
+    pub fn is_dangling<T: ReadTxn>(&self, txn: &T) -> bool {
+        match self.unquote(txn) {
+            Some(mut values) => values.next().is_none(),
+            None => true,
+        }
+    }
+
+    /// Subscribes a given callback to be called whenever changes made to a `target` document
+    /// caused current link to become dangling (see: [DocLink::is_dangling]). Callback is called
+    /// once per every such transition.
+    ///
+    /// # Panics
+    ///
+    /// Current state of a link is checked upon subscription, which requires opening a read-only
+    /// transaction over a `target` document. If there's a read-write transaction in process,
+    /// this method will panic.
+    ///
+    /// Returns a [Subscription] which, when dropped, will unsubscribe current callback.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn observe_dangling<F>(&self, target: &Doc, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &DocLink) + Send + Sync + 'static,
+    {
+        let link = self.clone();
+        let dangling = AtomicBool::new(self.is_dangling(&target.transact()));
+        target.observe_after_transaction(move |txn| {
+            let is_dangling = link.is_dangling(txn);
+            if dangling.swap(is_dangling, Ordering::AcqRel) != is_dangling && is_dangling {
+                f(txn, &link);
+            }
+        })
+    }
+
+    /// Converts current link into a JSON-like representation, which can be stored in any
+    /// document. See [DocLink::from_json].
+    pub fn to_json(&self) -> Any {
+        let mut m = HashMap::new();
+        m.insert("guid".to_owned(), Any::String(self.guid.clone()));
+        m.insert("start".to_owned(), self.start.to_json());
+        m.insert("end".to_owned(), self.end.to_json());
+        Any::from(m)
+    }
+
+    /// Parses [DocLink] from a JSON-like representation produced by [DocLink::to_json].
+    pub fn from_json(json: &Any) -> Result<Self, crate::encoding::read::Error> {
+        if let Any::Map(m) = json {
+            if let (Some(Any::String(guid)), Some(start), Some(end)) =
+                (m.get("guid"), m.get("start"), m.get("end"))
+            {
+                return Ok(DocLink {
+                    guid: guid.clone(),
+                    start: StickyIndex::from_json(start)?,
+                    end: StickyIndex::from_json(end)?,
+                });
+            }
+        }
+        Err(crate::encoding::read::Error::UnexpectedValue)
+    }
+}
+
 pub(crate) fn join_linked_range(mut block: ItemPtr, txn: &mut TransactionMut) {
     let block_copy = block.clone();
     let item = block.deref_mut();
@@ -846,7 +1023,7 @@ mod test {
     use crate::branch::BranchPtr;
     use crate::test_utils::exchange_updates;
     use crate::types::text::YChange;
-    use crate::types::weak::{WeakPrelim, WeakRef};
+    use crate::types::weak::{DocLink, WeakPrelim, WeakRef};
     use crate::types::{Attrs, EntryChange, Event, ToJson, Value};
     use crate::Assoc::{After, Before};
     use crate::{
@@ -860,6 +1037,43 @@ mod test {
     use std::rc::Rc;
     use std::sync::{Arc, Mutex};
 
+    #[test]
+    fn doc_link_across_subdocs() {
+        let workspace = Doc::with_client_id(1);
+        let docs = workspace.get_or_insert_map("docs");
+        let target = docs.insert(&mut workspace.transact_mut(), "target", Doc::new());
+        let array = target.get_or_insert_array("array");
+        array.insert_range(&mut target.transact_mut(), 0, [1, 2, 3, 4]);
+
+        let link = DocLink::quote(&target.transact(), &array, 1..=2).unwrap();
+        let links = workspace.get_or_insert_array("links");
+        links.push_back(&mut workspace.transact_mut(), link.to_json());
+
+        let txn = workspace.transact();
+        let json = links.get(&txn, 0).unwrap().to_json(&txn);
+        let link = DocLink::from_json(&json).unwrap();
+        // quoted elements don't belong to the workspace itself
+        assert!(link.unquote(&txn).is_none());
+        let doc = link.target_doc(&txn).unwrap();
+        drop(txn);
+        assert_eq!(doc.guid(), target.guid());
+        let quoted: Vec<Value> = link.unquote(&doc.transact()).unwrap().collect();
+        assert_eq!(quoted, vec![2.into(), 3.into()]);
+
+        let dangling = Arc::new(Mutex::new(0));
+        let d = dangling.clone();
+        let _sub = link
+            .observe_dangling(&doc, move |_, _| *d.lock().unwrap() += 1)
+            .unwrap();
+        array.remove(&mut target.transact_mut(), 1); // [1, 3, 4]
+        assert_eq!(*dangling.lock().unwrap(), 0);
+        array.remove(&mut target.transact_mut(), 1); // [1, 4]
+        assert_eq!(*dangling.lock().unwrap(), 1);
+        assert!(link.is_dangling(&target.transact()));
+        array.insert(&mut target.transact_mut(), 1, 5); // [1, 5, 4]
+        assert_eq!(*dangling.lock().unwrap(), 1);
+    }
+
     #[test]
     fn basic_map_link() {
         let doc = Doc::new();
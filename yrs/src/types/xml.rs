@@ -12,16 +12,16 @@ use crate::block_iter::BlockIter;
 use crate::transaction::TransactionMut;
 use crate::types::text::{diff_between, TextEvent, YChange};
 use crate::types::{
-    event_change_set, event_keys, Branch, BranchPtr, Change, ChangeSet, Delta, Entries,
-    EntryChange, MapRef, Path, RootRef, SharedRef, ToJson, TypePtr, TypeRef, Value,
+    event_change_set, event_keys, Branch, BranchPtr, Change, ChangeSet, Delta, EditError, Entries,
+    EntryChange, MapRef, OutOfBounds, Path, RootRef, SharedRef, ToJson, TypePtr, TypeRef, Value,
 };
 use crate::{
     Any, ArrayRef, BranchID, DeepObservable, GetString, IndexedSequence, Map, Observable, ReadTxn,
-    StickyIndex, Text, TextRef, ID,
+    Snapshot, StickyIndex, Text, TextRef, ID,
 };
 
 /// Trait shared by preliminary types that can be used as XML nodes: [XmlElementPrelim],
-/// [XmlFragmentPrelim] and [XmlTextPrelim].
+/// [XmlFragmentPrelim], [XmlHookPrelim], [XmlTextPrelim] and [XmlNodePrelim].
 pub trait XmlPrelim: Prelim {}
 
 /// An return type from XML elements retrieval methods. It's an enum of all supported values, that
@@ -131,6 +131,17 @@ impl TryFrom<BranchPtr> for XmlNode {
     }
 }
 
+impl TryFrom<ItemPtr> for XmlNode {
+    type Error = ItemPtr;
+
+    fn try_from(value: ItemPtr) -> Result<Self, Self::Error> {
+        match value.as_branch() {
+            Some(branch) => XmlNode::try_from(branch).map_err(|_| value),
+            None => Err(value),
+        }
+    }
+}
+
 impl TryFrom<Value> for XmlNode {
     type Error = Value;
 
@@ -204,25 +215,17 @@ impl XmlElementRef {
 impl GetString for XmlElementRef {
     /// Converts current XML node into a textual representation. This representation if flat, it
     /// doesn't include any indentation.
-    fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
         let tag: &str = self.tag();
         let inner = self.0;
-        let mut s = String::new();
-        write!(&mut s, "<{}", tag).unwrap();
+        write!(writer, "<{}", tag)?;
         let attributes = Attributes(inner.entries(txn));
         for (k, v) in attributes {
-            write!(&mut s, " {}=\"{}\"", k, v).unwrap();
+            write!(writer, " {}=\"{}\"", k, v)?;
         }
-        write!(&mut s, ">").unwrap();
-        for i in inner.iter(txn) {
-            if !i.is_deleted() {
-                for content in i.content.get_content() {
-                    write!(&mut s, "{}", content.to_string(txn)).unwrap();
-                }
-            }
-        }
-        write!(&mut s, "</{}>", tag).unwrap();
-        s
+        write!(writer, ">")?;
+        write_children(inner, txn, writer)?;
+        write!(writer, "</{}>", tag)
     }
 }
 
@@ -275,8 +278,25 @@ impl TryFrom<Value> for XmlElementRef {
 
 /// A preliminary type that will be materialized into an [XmlElementRef] once it will be integrated
 /// into Yrs document.
+///
+/// Whole XML subtrees can be constructed up front and then integrated using a single insert:
+///
+/// ```rust
+/// use yrs::{Doc, GetString, Transact, XmlElementPrelim, XmlFragment, XmlTextPrelim};
+///
+/// let doc = Doc::new();
+/// let root = doc.get_or_insert_xml_fragment("xml");
+/// let mut txn = doc.transact_mut();
+/// let p = XmlElementPrelim::empty("p")
+///     .attr("class", "x")
+///     .child(XmlTextPrelim::new("hi "))
+///     .child(XmlElementPrelim::empty("b").child(XmlTextPrelim::new("there")));
+/// root.push_back(&mut txn, p);
+///
+/// assert_eq!(root.get_string(&txn), r#"<p class="x">hi <b>there</b></p>"#);
+/// ```
 #[derive(Debug, Clone)]
-pub struct XmlElementPrelim<I, T>(Arc<str>, I)
+pub struct XmlElementPrelim<I, T>(Arc<str>, I, Vec<(Arc<str>, Any)>)
 where
     I: IntoIterator<Item = T>,
     T: XmlPrelim;
@@ -287,13 +307,33 @@ where
     T: XmlPrelim,
 {
     pub fn new<S: Into<Arc<str>>>(tag: S, iter: I) -> Self {
-        XmlElementPrelim(tag.into(), iter)
+        XmlElementPrelim(tag.into(), iter, Vec::new())
+    }
+
+    /// Sets an attribute, which will be inserted into XML element once it's integrated. Setting
+    /// the same attribute multiple times will keep the last value.
+    pub fn attr<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: Into<Arc<str>>,
+        V: Into<Any>,
+    {
+        self.2.push((name.into(), value.into()));
+        self
     }
 }
 
-impl XmlElementPrelim<Option<XmlTextPrelim<String>>, XmlTextPrelim<String>> {
+impl XmlElementPrelim<Vec<XmlNodePrelim>, XmlNodePrelim> {
+    /// Creates a new XML element without any attributes nor children. These can be added using
+    /// [XmlElementPrelim::attr] and [XmlElementPrelim::child] builder methods.
     pub fn empty<S: Into<Arc<str>>>(tag: S) -> Self {
-        XmlElementPrelim(tag.into(), None)
+        XmlElementPrelim(tag.into(), Vec::new(), Vec::new())
+    }
+
+    /// Appends a child node (either an XML element or text), which will be integrated together
+    /// with current element.
+    pub fn child<C: Into<XmlNodePrelim>>(mut self, child: C) -> Self {
+        self.1.push(child.into());
+        self
     }
 }
 
@@ -318,6 +358,9 @@ where
 
     fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
         let xml = XmlElementRef::from(inner_ref);
+        for (name, value) in self.2 {
+            xml.insert_attribute(txn, name, value);
+        }
         for value in self.1 {
             xml.push_back(txn, value);
         }
@@ -335,6 +378,190 @@ where
     }
 }
 
+/// A preliminary XML node, which can be either an XML element or text. It's used to build
+/// subtrees of heterogeneous nodes with [XmlElementPrelim::child].
+#[derive(Debug)]
+pub enum XmlNodePrelim {
+    Element(XmlElementPrelim<Vec<XmlNodePrelim>, XmlNodePrelim>),
+    Text(XmlTextPrelim<String>),
+}
+
+impl<I, T> From<XmlElementPrelim<I, T>> for XmlNodePrelim
+where
+    I: IntoIterator<Item = T>,
+    T: XmlPrelim + Into<XmlNodePrelim>,
+{
+    fn from(value: XmlElementPrelim<I, T>) -> Self {
+        let XmlElementPrelim(tag, children, attributes) = value;
+        let children = children.into_iter().map(T::into).collect();
+        XmlNodePrelim::Element(XmlElementPrelim(tag, children, attributes))
+    }
+}
+
+impl<T: Borrow<str>> From<XmlTextPrelim<T>> for XmlNodePrelim {
+    fn from(value: XmlTextPrelim<T>) -> Self {
+        XmlNodePrelim::Text(XmlTextPrelim::new(value.0.borrow().to_string()))
+    }
+}
+
+impl XmlPrelim for XmlNodePrelim {}
+
+impl Prelim for XmlNodePrelim {
+    type Return = XmlNode;
+
+    fn into_content(self, txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        match self {
+            XmlNodePrelim::Element(prelim) => {
+                let (content, rest) = prelim.into_content(txn);
+                (content, rest.map(XmlNodePrelim::Element))
+            }
+            XmlNodePrelim::Text(prelim) => {
+                let (content, rest) = prelim.into_content(txn);
+                (content, rest.map(XmlNodePrelim::Text))
+            }
+        }
+    }
+
+    fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
+        match self {
+            XmlNodePrelim::Element(prelim) => prelim.integrate(txn, inner_ref),
+            XmlNodePrelim::Text(prelim) => prelim.integrate(txn, inner_ref),
+        }
+    }
+}
+
+/// A lightweight description of a desired XML node (eg. an output of a UI framework's render
+/// function), which can be applied to a shared XML tree using [XmlFragment::reconcile].
+///
+/// Elements can be given an `id` attribute, which is used as a key when matching them against
+/// existing XML elements: keyed elements are only matched with existing elements of the same tag
+/// and `id`, while elements without `id` are matched with existing elements of the same tag in
+/// order of their appearance.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlTarget {
+    Element {
+        tag: Arc<str>,
+        attributes: Vec<(Arc<str>, Any)>,
+        children: Vec<XmlTarget>,
+    },
+    Text(String),
+}
+
+impl XmlTarget {
+    /// Name of an attribute used as a key when matching target elements with existing ones.
+    pub const KEY_ATTRIBUTE: &'static str = "id";
+
+    /// Creates a new XML element description without any attributes nor children. These can be
+    /// added using [XmlTarget::attr] and [XmlTarget::child] builder methods.
+    pub fn element<S: Into<Arc<str>>>(tag: S) -> Self {
+        XmlTarget::Element {
+            tag: tag.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Creates a new XML text description.
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        XmlTarget::Text(text.into())
+    }
+
+    /// Sets an attribute of a current element description. Setting the same attribute multiple
+    /// times will keep the last value. Has no effect on text descriptions.
+    pub fn attr<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: Into<Arc<str>>,
+        V: Into<Any>,
+    {
+        if let XmlTarget::Element { attributes, .. } = &mut self {
+            attributes.push((name.into(), value.into()));
+        }
+        self
+    }
+
+    /// Appends a child node description to a current element description. Has no effect on text
+    /// descriptions.
+    pub fn child(mut self, child: XmlTarget) -> Self {
+        if let XmlTarget::Element { children, .. } = &mut self {
+            children.push(child);
+        }
+        self
+    }
+
+    /// Returns a key of a current element description, if it has one.
+    fn key(&self) -> Option<&Any> {
+        match self {
+            XmlTarget::Element { attributes, .. } => attributes
+                .iter()
+                .rev()
+                .find(|(name, _)| name.as_ref() == Self::KEY_ATTRIBUTE)
+                .map(|(_, value)| value),
+            XmlTarget::Text(_) => None,
+        }
+    }
+
+    /// Checks if an existing XML `node` can be reconciled in place with current description.
+    fn matches<T: ReadTxn>(&self, txn: &T, node: &XmlNode) -> bool {
+        match (self, node) {
+            (XmlTarget::Text(_), XmlNode::Text(_)) => true,
+            (XmlTarget::Element { tag, .. }, XmlNode::Element(elem)) => {
+                elem.try_tag() == Some(tag)
+                    && self.key() == elem.get_attribute_value(txn, Self::KEY_ATTRIBUTE).as_ref()
+            }
+            _ => false,
+        }
+    }
+
+    /// Updates an existing XML `node`, which [matches](XmlTarget::matches) current description.
+    fn reconcile(&self, txn: &mut TransactionMut, node: &XmlNode) {
+        match (self, node) {
+            (XmlTarget::Text(target), XmlNode::Text(text)) => text.reconcile(txn, target),
+            (
+                XmlTarget::Element {
+                    attributes,
+                    children,
+                    ..
+                },
+                XmlNode::Element(elem),
+            ) => {
+                let target: HashMap<&str, &Any> = attributes
+                    .iter()
+                    .map(|(name, value)| (name.as_ref(), value))
+                    .collect();
+                let removed: Vec<String> = elem
+                    .attributes(txn)
+                    .filter(|(name, _)| !target.contains_key(name))
+                    .map(|(name, _)| name.to_string())
+                    .collect();
+                for name in removed {
+                    elem.remove_attribute(txn, &name);
+                }
+                for (name, value) in target {
+                    if elem.get_attribute_value(txn, name).as_ref() != Some(value) {
+                        elem.insert_attribute(txn, name, value.clone());
+                    }
+                }
+                elem.reconcile(txn, children);
+            }
+            _ => {}
+        }
+    }
+
+    fn into_prelim(self) -> XmlNodePrelim {
+        match self {
+            XmlTarget::Element {
+                tag,
+                attributes,
+                children,
+            } => {
+                let children = children.into_iter().map(XmlTarget::into_prelim).collect();
+                XmlNodePrelim::Element(XmlElementPrelim(tag, children, attributes))
+            }
+            XmlTarget::Text(text) => XmlNodePrelim::Text(XmlTextPrelim::new(text)),
+        }
+    }
+}
+
 /// A shared data type used for collaborative text editing, that can be used in a context of
 /// [XmlElementRef] node. It enables multiple users to add and remove chunks of text in efficient
 /// manner. This type is internally represented as a mutable double-linked list of text chunks
@@ -408,13 +635,14 @@ where
 pub struct XmlTextRef(BranchPtr);
 
 impl XmlTextRef {
-    pub(crate) fn get_string_fragment(
+    pub(crate) fn write_string_fragment<W: Write>(
+        buf: &mut W,
         head: Option<ItemPtr>,
+        snapshot: Option<&Snapshot>,
         start: Option<&StickyIndex>,
         end: Option<&StickyIndex>,
-    ) -> String {
-        let mut buf = String::new();
-        for d in diff_between(head, start, end, YChange::identity) {
+    ) -> std::fmt::Result {
+        for d in diff_between(head, snapshot, start, end, YChange::identity) {
             let mut attrs = Vec::new();
             if let Some(attributes) = d.attributes.as_ref() {
                 for (key, value) in attributes.iter() {
@@ -425,27 +653,27 @@ impl XmlTextRef {
 
             // write attributes as xml opening tags
             for (node, at) in attrs.iter() {
-                write!(buf, "<{}", node).unwrap();
+                write!(buf, "<{}", node)?;
                 if let Any::Map(at) = at {
                     for (k, v) in at.iter() {
-                        write!(buf, " {}=\"{}\"", k, v).unwrap();
+                        write!(buf, " {}=\"{}\"", k, v)?;
                     }
                 }
-                buf.push('>');
+                buf.write_char('>')?;
             }
 
             // write string content of delta
             if let Value::Any(any) = d.insert {
-                write!(buf, "{}", any).unwrap();
+                write!(buf, "{}", any)?;
             }
 
             // write attributes as xml closing tags
             attrs.reverse();
             for (key, _) in attrs {
-                write!(buf, "</{}>", key).unwrap();
+                write!(buf, "</{}>", key)?;
             }
         }
-        buf
+        Ok(())
     }
 }
 
@@ -468,8 +696,9 @@ impl Observable for XmlTextRef {
 }
 
 impl GetString for XmlTextRef {
-    fn get_string<T: ReadTxn>(&self, _txn: &T) -> String {
-        XmlTextRef::get_string_fragment(self.0.start, None, None)
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
+        let snapshot = txn.gated_snapshot().map(|gate| gate.snapshot());
+        XmlTextRef::write_string_fragment(writer, self.0.start, snapshot, None, None)
     }
 }
 
@@ -584,18 +813,30 @@ impl XmlFragmentRef {
 impl GetString for XmlFragmentRef {
     /// Converts current XML node into a textual representation. This representation if flat, it
     /// doesn't include any indentation.
-    fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
-        let inner = self.0;
-        let mut s = String::new();
-        for i in inner.iter(txn) {
-            if !i.is_deleted() {
-                for content in i.content.get_content() {
-                    write!(&mut s, "{}", content.to_string(txn)).unwrap();
+    fn write_string<T: ReadTxn, W: Write>(&self, txn: &T, writer: &mut W) -> std::fmt::Result {
+        write_children(self.0, txn, writer)
+    }
+}
+
+/// Writes textual representation of all child nodes of a given XML node `branch`.
+fn write_children<T: ReadTxn, W: Write>(
+    branch: BranchPtr,
+    txn: &T,
+    writer: &mut W,
+) -> std::fmt::Result {
+    for i in branch.iter(txn) {
+        if i.is_visible(txn) {
+            for content in i.content.get_content() {
+                match content {
+                    Value::YXmlElement(node) => node.write_string(txn, writer)?,
+                    Value::YXmlFragment(node) => node.write_string(txn, writer)?,
+                    Value::YXmlText(node) => node.write_string(txn, writer)?,
+                    other => writer.write_str(&other.to_string(txn))?,
                 }
             }
         }
-        s
     }
+    Ok(())
 }
 
 impl DeepObservable for XmlFragmentRef {}
@@ -703,10 +944,24 @@ where
     }
 }
 
-/// (Obsolete) an Yjs-compatible XML node used for nesting Map elements.
+/// (Obsolete) an Yjs-compatible XML node used for nesting Map elements. Hooks are custom embedded
+/// components identified by their hook name, which store their state as map entries.
+///
+/// Hook instances are exposed as [Value::YMap]. Use [XmlHookRef::try_from] to check if
+/// a given value is a hook and read its name.
 #[derive(Debug, Clone)]
 pub struct XmlHookRef(BranchPtr);
 
+impl XmlHookRef {
+    /// Returns a name of a current hook.
+    pub fn hook_name(&self) -> &str {
+        match &self.0.type_ref {
+            TypeRef::XmlHook(name) => name,
+            _ => unreachable!("XmlHookRef doesn't point to an XML hook"),
+        }
+    }
+}
+
 impl Map for XmlHookRef {}
 
 impl ToJson for XmlHookRef {
@@ -741,6 +996,73 @@ impl Into<MapRef> for XmlHookRef {
     }
 }
 
+impl TryFrom<ItemPtr> for XmlHookRef {
+    type Error = ItemPtr;
+
+    fn try_from(value: ItemPtr) -> Result<Self, Self::Error> {
+        match value.as_branch() {
+            Some(branch) if matches!(branch.type_ref, TypeRef::XmlHook(_)) => {
+                Ok(Self::from(branch))
+            }
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for XmlHookRef {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::YMap(map) => {
+                let branch = BranchPtr::from(map.as_ref());
+                if let TypeRef::XmlHook(_) = branch.type_ref {
+                    Ok(XmlHookRef(branch))
+                } else {
+                    Err(Value::YMap(map))
+                }
+            }
+            other => Err(other),
+        }
+    }
+}
+
+/// A preliminary type that will be materialized into an [XmlHookRef] once it will be integrated
+/// into Yrs document.
+#[derive(Debug, Clone)]
+pub struct XmlHookPrelim<T>(Arc<str>, HashMap<String, T>);
+
+impl<T: Prelim> XmlHookPrelim<T> {
+    pub fn new<S: Into<Arc<str>>>(hook_name: S, entries: HashMap<String, T>) -> Self {
+        XmlHookPrelim(hook_name.into(), entries)
+    }
+}
+
+impl<T: Prelim> XmlPrelim for XmlHookPrelim<T> {}
+
+impl<T: Prelim> Prelim for XmlHookPrelim<T> {
+    type Return = XmlHookRef;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        let inner = Branch::new(TypeRef::XmlHook(self.0.clone()));
+        (ItemContent::Type(inner), Some(self))
+    }
+
+    fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
+        let hook = XmlHookRef::from(inner_ref);
+        for (key, value) in self.1 {
+            hook.insert(txn, key, value);
+        }
+    }
+}
+
+impl<T: Prelim> From<XmlHookPrelim<T>> for EmbedPrelim<XmlHookPrelim<T>> {
+    #[inline]
+    fn from(prelim: XmlHookPrelim<T>) -> Self {
+        EmbedPrelim::Shared(prelim)
+    }
+}
+
 pub trait Xml: AsRef<Branch> {
     fn parent(&self) -> Option<XmlNode> {
         let item = self.as_ref().item?;
@@ -756,11 +1078,30 @@ pub trait Xml: AsRef<Branch> {
         self.as_ref().remove(txn, attr_name.as_ref());
     }
 
-    /// Inserts an attribute entry into current XML element.
+    /// Inserts an attribute entry into current XML element. Attribute values are not limited to
+    /// strings - any primitive value (numbers, booleans, JSON-like objects and arrays) can be used.
+    /// These are encoded the same way as typed attributes in Yjs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, Transact, Xml, XmlElementPrelim, XmlFragment};
+    ///
+    /// let doc = Doc::new();
+    /// let root = doc.get_or_insert_xml_fragment("xml");
+    /// let mut txn = doc.transact_mut();
+    /// let div = root.push_back(&mut txn, XmlElementPrelim::empty("div"));
+    /// div.insert_attribute(&mut txn, "class", "note");
+    /// div.insert_attribute(&mut txn, "height", 10);
+    /// div.insert_attribute(&mut txn, "hidden", false);
+    ///
+    /// assert_eq!(div.get_attribute_value(&txn, "height"), Some(Any::from(10)));
+    /// assert_eq!(div.get_attribute(&txn, "height"), Some("10".to_string()));
+    /// ```
     fn insert_attribute<K, V>(&self, txn: &mut TransactionMut, attr_name: K, attr_value: V)
     where
         K: Into<Arc<str>>,
-        V: Into<String>,
+        V: Into<Any>,
     {
         let key = attr_name.into();
         let value = attr_value.into();
@@ -787,8 +1128,17 @@ pub trait Xml: AsRef<Branch> {
         Some(value.to_string(txn))
     }
 
+    /// Returns a typed value of an attribute given its `attr_name`. Returns `None` if no such
+    /// attribute has been found in a current XML element. Unlike [Xml::get_attribute], this
+    /// method doesn't stringify attribute values.
+    fn get_attribute_value<T: ReadTxn>(&self, txn: &T, attr_name: &str) -> Option<Any> {
+        let branch = self.as_ref();
+        let value = branch.get(txn, attr_name)?;
+        Some(value.to_json(txn))
+    }
+
     /// Returns an unordered iterator over all attributes (key-value pairs), that can be found
-    /// inside of a current XML element.
+    /// inside of a current XML element. Attribute values are returned as typed [Any] values.
     fn attributes<'a, T: ReadTxn>(&'a self, txn: &'a T) -> Attributes<'a, &'a T, T> {
         Attributes(Entries::new(&self.as_ref().map, txn))
     }
@@ -811,8 +1161,11 @@ pub trait XmlFragment: AsRef<Branch> {
         }
     }
     /// Returns a number of elements stored in current array.
-    fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
-        self.as_ref().len()
+    fn len<T: ReadTxn>(&self, txn: &T) -> u32 {
+        match txn.gated_snapshot() {
+            Some(gate) => gate.sequence(txn, self.as_ref()).len(),
+            None => self.as_ref().len(),
+        }
     }
 
     /// Inserts a `value` at the given `index`. Inserting at index `0` is equivalent to prepending
@@ -832,6 +1185,35 @@ pub trait XmlFragment: AsRef<Branch> {
         }
     }
 
+    /// Inserts a `value` at the given `index`. Unlike [XmlFragment::insert], this method doesn't
+    /// panic when provided `index` is outside of the bounds of current node, insertion would
+    /// exceed document quotas or current document is a read-only replica, but returns an
+    /// [EditError] instead.
+    fn try_insert<V>(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        xml_node: V,
+    ) -> Result<V::Return, EditError>
+    where
+        V: XmlPrelim,
+    {
+        let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
+        if walker.try_forward(txn, index) {
+            let ptr = walker.try_insert_contents(txn, xml_node)?;
+            if let Ok(integrated) = V::Return::try_from(ptr) {
+                Ok(integrated)
+            } else {
+                panic!("Defect: inserted XML element returned primitive value block")
+            }
+        } else {
+            Err(EditError::OutOfBounds(OutOfBounds {
+                len: self.len(txn),
+                index,
+            }))
+        }
+    }
+
     /// Inserts given `value` at the end of the current array.
     fn push_back<V>(&self, txn: &mut TransactionMut, xml_node: V) -> V::Return
     where
@@ -867,12 +1249,34 @@ pub trait XmlFragment: AsRef<Branch> {
         }
     }
 
+    /// Removes a single element at provided `index`. Unlike [XmlFragment::remove], this method
+    /// returns an error instead of panicking (see: [XmlFragment::try_remove_range]).
+    fn try_remove(&self, txn: &mut TransactionMut, index: u32) -> Result<(), EditError> {
+        self.try_remove_range(txn, index, 1)
+    }
+
+    /// Removes a range of elements from current array, starting at given `index` up until
+    /// a particular number described by `len` has been deleted. Unlike [XmlFragment::remove_range],
+    /// this method doesn't panic when requested range doesn't fit within the bounds of current
+    /// node or current document is a read-only replica, but returns an [EditError] instead,
+    /// leaving current node unchanged.
+    fn try_remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+    ) -> Result<(), EditError> {
+        OutOfBounds::check(self.len(txn), index, len)?;
+        txn.check_writable()?;
+        self.remove_range(txn, index, len);
+        Ok(())
+    }
+
     /// Retrieves a value stored at a given `index`. Returns `None` when provided index was out
     /// of the range of a current array.
-    fn get<T: ReadTxn>(&self, _txn: &T, index: u32) -> Option<XmlNode> {
-        let branch = self.as_ref();
-        let (content, _) = branch.get_at(index)?;
-        if let ItemContent::Type(inner) = content {
+    fn get<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<XmlNode> {
+        let (item, _) = self.as_ref().find_visible(txn, index)?;
+        if let ItemContent::Type(inner) = &item.content {
             let ptr: BranchPtr = inner.into();
             XmlNode::try_from(ptr).ok()
         } else {
@@ -880,6 +1284,75 @@ pub trait XmlFragment: AsRef<Branch> {
         }
     }
 
+    /// Updates children of a current XML node, so that they match a given `target` description
+    /// (see: [XmlTarget]), eg. produced by a UI framework's render function. Instead of replacing
+    /// all children, existing nodes are matched against target ones and updated in place - only
+    /// changed attributes are set and texts are updated using [Text::reconcile]. Nodes which
+    /// couldn't be matched are removed, while new ones are inserted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Transact, XmlFragment};
+    /// use yrs::types::xml::XmlTarget;
+    ///
+    /// let doc = Doc::new();
+    /// let root = doc.get_or_insert_xml_fragment("root");
+    /// let mut txn = doc.transact_mut();
+    /// let render = |items: &[&str]| -> Vec<XmlTarget> {
+    ///     let list = items.iter().fold(XmlTarget::element("ul"), |ul, item| {
+    ///         ul.child(XmlTarget::element("li").attr("id", *item).child(XmlTarget::text(*item)))
+    ///     });
+    ///     vec![list]
+    /// };
+    ///
+    /// root.reconcile(&mut txn, &render(&["a", "b"]));
+    /// assert_eq!(root.get_string(&txn), r#"<ul><li id="a">a</li><li id="b">b</li></ul>"#);
+    ///
+    /// root.reconcile(&mut txn, &render(&["b", "c"]));
+    /// assert_eq!(root.get_string(&txn), r#"<ul><li id="b">b</li><li id="c">c</li></ul>"#);
+    /// ```
+    fn reconcile(&self, txn: &mut TransactionMut, target: &[XmlTarget]) {
+        let mut current = Vec::new();
+        let mut next = self.as_ref().start;
+        while let Some(item) = next.as_deref() {
+            if !item.is_deleted() {
+                if let ItemContent::Type(inner) = &item.content {
+                    if let Ok(node) = XmlNode::try_from(BranchPtr::from(inner)) {
+                        current.push(node);
+                    }
+                }
+            }
+            next = item.right;
+        }
+
+        let mut index = 0;
+        let mut i = 0;
+        for t in target {
+            let found = current[i..]
+                .iter()
+                .position(|node| t.matches(txn, node))
+                .map(|offset| i + offset);
+            match found {
+                Some(k) => {
+                    // nodes skipped in order to reach a matching one are removed
+                    if k > i {
+                        self.remove_range(txn, index, (k - i) as u32);
+                    }
+                    t.reconcile(txn, &current[k]);
+                    i = k + 1;
+                }
+                None => {
+                    self.insert(txn, index, t.clone().into_prelim());
+                }
+            }
+            index += 1;
+        }
+        if i < current.len() {
+            self.remove_range(txn, index, (current.len() - i) as u32);
+        }
+    }
+
     /// Returns an iterator that can be used to traverse over the successors of a current
     /// XML element. This includes recursive step over children of its children. The recursive
     /// iteration is depth-first.
@@ -925,7 +1398,7 @@ pub trait XmlFragment: AsRef<Branch> {
     }
 }
 
-/// Iterator over the attributes (key-value pairs represented as a strings) of an [XmlElement].
+/// Iterator over the attributes (key-value pairs with typed values) of an [XmlElement].
 pub struct Attributes<'a, B, T>(Entries<'a, B, T>);
 
 impl<'a, B, T> Attributes<'a, B, T>
@@ -944,7 +1417,7 @@ where
     B: Borrow<T>,
     T: ReadTxn,
 {
-    type Item = (&'a str, String);
+    type Item = (&'a str, Any);
 
     fn next(&mut self) -> Option<Self::Item> {
         let (key, block) = self.0.next()?;
@@ -952,8 +1425,8 @@ where
         let value = block
             .content
             .get_last()
-            .map(|v| v.to_string(txn))
-            .unwrap_or(String::default());
+            .map(|v| v.to_json(txn))
+            .unwrap_or(Any::Undefined);
         Some((key.as_ref(), value))
     }
 }
@@ -1243,6 +1716,7 @@ impl XmlEvent {
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
+    use std::convert::{TryFrom, TryInto};
     use std::sync::Arc;
 
     use arc_swap::ArcSwapOption;
@@ -1250,15 +1724,71 @@ mod test {
     use crate::branch::BranchPtr;
     use crate::test_utils::exchange_updates;
     use crate::transaction::ReadTxn;
-    use crate::types::xml::{Xml, XmlFragment, XmlNode};
-    use crate::types::{Attrs, Change, EntryChange, Value};
+    use crate::types::xml::{Xml, XmlFragment, XmlNode, XmlTarget};
+    use crate::types::{Attrs, Change, EntryChange, ToJson, Value};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
     use crate::{
-        Any, Doc, GetString, Observable, SharedRef, StateVector, Text, Transact, Update,
-        XmlElementPrelim, XmlTextPrelim, XmlTextRef,
+        any, Any, Doc, GetString, Observable, SharedRef, StateVector, Text, Transact, Update,
+        XmlElementPrelim, XmlElementRef, XmlHookPrelim, XmlHookRef, XmlTextPrelim, XmlTextRef,
     };
 
+    #[test]
+    fn reconcile_keyed_children() {
+        let d1 = Doc::with_client_id(1);
+        let f1 = d1.get_or_insert_xml_fragment("xml");
+        let render = |title: &str, items: &[(&str, &str)]| {
+            let list = items
+                .iter()
+                .fold(XmlTarget::element("ul"), |ul, (id, label)| {
+                    ul.child(
+                        XmlTarget::element("li")
+                            .attr("id", *id)
+                            .child(XmlTarget::text(*label)),
+                    )
+                });
+            vec![XmlTarget::element("h1").child(XmlTarget::text(title)), list]
+        };
+        f1.reconcile(
+            &mut d1.transact_mut(),
+            &render("todo", &[("1", "milk"), ("2", "eggs"), ("3", "bread")]),
+        );
+        assert_eq!(
+            f1.get_string(&d1.transact()),
+            r#"<h1>todo</h1><ul><li id="1">milk</li><li id="2">eggs</li><li id="3">bread</li></ul>"#
+        );
+
+        let d2 = Doc::with_client_id(2);
+        let f2 = d2.get_or_insert_xml_fragment("xml");
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrently edit text of a list item, which is going to be kept
+        {
+            let mut txn = d2.transact_mut();
+            let ul: XmlElementRef = f2.get(&txn, 1).unwrap().try_into().unwrap();
+            let li: XmlElementRef = ul.get(&txn, 2).unwrap().try_into().unwrap();
+            let text: XmlTextRef = li.get(&txn, 0).unwrap().try_into().unwrap();
+            text.push(&mut txn, " (whole grain)");
+        }
+        let sv = d1.transact().state_vector();
+        f1.reconcile(
+            &mut d1.transact_mut(),
+            &render("todo!", &[("1", "milk"), ("3", "bread"), ("4", "butter")]),
+        );
+        // only changed parts were inserted: "!" and a new list item with its attribute and text
+        let update = d1.transact().encode_state_as_update_v1(&sv);
+        let update = Update::decode_v1(&update).unwrap();
+        assert_eq!(update.blocks.into_blocks(false).count(), 5);
+
+        exchange_updates(&[&d1, &d2]);
+        let expected = r#"<h1>todo!</h1><ul><li id="1">milk</li><li id="3">bread (whole grain)</li><li id="4">butter</li></ul>"#;
+        assert_eq!(f1.get_string(&d1.transact()), expected);
+        assert_eq!(f2.get_string(&d2.transact()), expected);
+
+        f1.reconcile(&mut d1.transact_mut(), &[]);
+        assert_eq!(f1.get_string(&d1.transact()), "");
+    }
+
     #[test]
     fn insert_attribute() {
         let d1 = Doc::with_client_id(1);
@@ -1273,7 +1803,8 @@ mod test {
         let mut t2 = d2.transact_mut();
         let xml2 = f.push_back(&mut t2, XmlElementPrelim::empty("div"));
         let u = t1.encode_state_as_update_v1(&StateVector::default());
-        t2.apply_update(Update::decode_v1(u.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(u.as_slice()).unwrap())
+            .unwrap();
         assert_eq!(xml2.get_attribute(&t2, "height"), Some("10".to_string()));
     }
 
@@ -1328,7 +1859,39 @@ mod test {
 
         assert_eq!(txt.get_attribute(&txn, "test"), Some("42".to_string()));
         let actual: Vec<_> = txt.attributes(&txn).collect();
-        assert_eq!(actual, vec![("test", "42".to_string())]);
+        assert_eq!(actual, vec![("test", Any::from("42"))]);
+    }
+
+    #[test]
+    fn typed_attributes() {
+        let d1 = Doc::with_client_id(1);
+        let f = d1.get_or_insert_xml_fragment("xml");
+        let mut t1 = d1.transact_mut();
+        let xml1 = f.push_back(&mut t1, XmlElementPrelim::empty("div"));
+        xml1.insert_attribute(&mut t1, "height", 10);
+        xml1.insert_attribute(&mut t1, "hidden", true);
+        xml1.insert_attribute(&mut t1, "data", any!({"key": [1, "a"]}));
+        assert_eq!(xml1.get_attribute(&t1, "height"), Some("10".to_string()));
+
+        let d2 = Doc::with_client_id(2);
+        let f = d2.get_or_insert_xml_fragment("xml");
+        let mut t2 = d2.transact_mut();
+        let u = t1.encode_state_as_update_v1(&StateVector::default());
+        t2.apply_update(Update::decode_v1(u.as_slice()).unwrap())
+            .unwrap();
+        let xml2 = f.get(&t2, 0).unwrap().into_xml_element().unwrap();
+
+        assert_eq!(xml2.get_attribute_value(&t2, "height"), Some(Any::from(10)));
+        assert_eq!(xml2.get_attribute_value(&t2, "missing"), None);
+        let actual: HashMap<_, _> = xml2.attributes(&t2).collect();
+        assert_eq!(
+            actual,
+            HashMap::from([
+                ("height", Any::from(10)),
+                ("hidden", Any::from(true)),
+                ("data", any!({"key": [1, "a"]})),
+            ])
+        );
     }
 
     #[test]
@@ -1379,7 +1942,8 @@ mod test {
         let r2 = d2.get_or_insert_xml_fragment("root");
         let mut t2 = d2.transact_mut();
 
-        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap());
+        t2.apply_update(Update::decode_v1(u1.as_slice()).unwrap())
+            .unwrap();
         assert_eq!(r2.get_string(&t2), expected);
     }
 
@@ -1528,7 +2092,8 @@ mod test {
             let sv = t2.state_vector();
             let mut encoder = EncoderV1::new();
             t1.encode_diff(&sv, &mut encoder);
-            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap());
+            t2.apply_update(Update::decode_v1(encoder.to_vec().as_slice()).unwrap())
+                .unwrap();
         }
         assert_eq!(
             nodes.swap(None),
@@ -1572,6 +2137,51 @@ mod test {
         )
     }
 
+    #[test]
+    fn xml_element_prelim_builder() {
+        let d1 = Doc::with_client_id(1);
+        let f = d1.get_or_insert_xml_fragment("xml");
+        let mut t1 = d1.transact_mut();
+        let div = f.push_back(
+            &mut t1,
+            XmlElementPrelim::empty("div")
+                .attr("class", "note")
+                .attr("height", 10)
+                .child(XmlTextPrelim::new("hello "))
+                .child(
+                    XmlElementPrelim::empty("a")
+                        .attr("href", "http://domain.org")
+                        .child(XmlTextPrelim::new("world")),
+                )
+                .child(XmlElementPrelim::new("ul", [XmlElementPrelim::empty("li")])),
+        );
+        assert_eq!(div.len(&t1), 3);
+        assert_eq!(div.get_attribute_value(&t1, "height"), Some(Any::from(10)));
+        let link = div.get(&t1, 1).unwrap().into_xml_element().unwrap();
+        assert_eq!(
+            link.get_attribute(&t1, "href").as_deref(),
+            Some("http://domain.org")
+        );
+        let mut attrs: Vec<_> = div.attributes(&t1).map(|(k, _)| k.to_string()).collect();
+        attrs.sort();
+        assert_eq!(attrs, vec!["class", "height"]);
+        drop(t1);
+
+        // whole subtree is replicated within a single update
+        let d2 = Doc::with_client_id(2);
+        let f2 = d2.get_or_insert_xml_fragment("xml");
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        let actual = f2.get_string(&d2.transact());
+        assert!(actual.starts_with("<div "));
+        assert!(actual
+            .ends_with(">hello <a href=\"http://domain.org\">world</a><ul><li></li></ul></div>"));
+    }
+
     #[test]
     fn xml_to_string_2() {
         let doc = Doc::new();
@@ -1608,7 +2218,7 @@ mod test {
         let txt = XmlTextRef::from(BranchPtr::from(txt.as_ref()));
         let mut txn = doc.transact_mut();
 
-        txn.apply_update(update);
+        txn.apply_update(update).unwrap();
         assert_eq!(txt.get_string(&txn), "<i>hello </i><b><i>world</i></b>");
 
         let actual = txn.encode_state_as_update_v1(&StateVector::default());
@@ -1628,10 +2238,81 @@ mod test {
         let txt = XmlTextRef::from(BranchPtr::from(txt.as_ref()));
         let mut txn = doc.transact_mut();
 
-        txn.apply_update(update);
+        txn.apply_update(update).unwrap();
         assert_eq!(txt.get_string(&txn), "<i>hello </i><b><i>world</i></b>");
 
         let actual = txn.encode_state_as_update_v2(&StateVector::default());
         assert_eq!(actual, data);
     }
+
+    #[test]
+    fn xml_hook_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let f = d1.get_or_insert_xml_fragment("xml");
+        {
+            let mut txn = d1.transact_mut();
+            let hook = f.push_back(
+                &mut txn,
+                XmlHookPrelim::new("custom", HashMap::from([("a".to_string(), 1)])),
+            );
+            assert_eq!(hook.hook_name(), "custom");
+            f.push_back(&mut txn, XmlTextPrelim::new("hello"));
+        }
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let d2 = Doc::with_client_id(2);
+        let f2 = d2.get_or_insert_xml_fragment("xml");
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+
+        let txn = d2.transact();
+        assert!(f2.get(&txn, 0).is_none()); // hooks are not XML nodes
+        let (content, _) = f2.as_ref().get_at(0).unwrap();
+        let hook = XmlHookRef::try_from(content.get_first().unwrap()).unwrap();
+        assert_eq!(hook.hook_name(), "custom");
+        assert_eq!(hook.to_json(&txn), any!({"a": 1}));
+        let hook_item = f2.as_ref().start.unwrap();
+        assert!(XmlHookRef::try_from(hook_item).is_ok());
+        assert!(XmlHookRef::try_from(hook_item.right.unwrap()).is_err()); // XmlText
+        let text = f2.get(&txn, 1).unwrap().into_xml_text().unwrap();
+        assert_eq!(text.get_string(&txn), "hello");
+        assert_eq!(
+            txn.encode_state_as_update_v1(&StateVector::default()),
+            update
+        );
+    }
+
+    #[test]
+    fn write_string_into_sink() {
+        struct Counter(usize);
+        impl std::fmt::Write for Counter {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+
+        let doc = Doc::with_client_id(1);
+        let f = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        let div = f.push_back(&mut txn, XmlElementPrelim::empty("div"));
+        div.insert_attribute(&mut txn, "class", "a");
+        let txt = div.push_back(&mut txn, XmlTextPrelim::new("hello"));
+        txt.format(&mut txn, 0, 5, Attrs::from([("b".into(), true.into())]));
+        f.push_back(&mut txn, XmlTextPrelim::new(" world"));
+
+        let expected = "<div class=\"a\"><b>hello</b></div> world";
+        assert_eq!(f.get_string(&txn), expected);
+
+        let mut buf = String::from(">");
+        f.write_string(&txn, &mut buf).unwrap();
+        assert_eq!(buf, format!(">{}", expected));
+
+        let mut counter = Counter(0);
+        f.write_string(&txn, &mut counter).unwrap();
+        assert_eq!(counter.0, expected.len());
+    }
 }
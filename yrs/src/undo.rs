@@ -1,17 +1,26 @@
-use std::collections::HashSet;
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Formatter;
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::{AtomicPtr, Ordering};
 use std::sync::Arc;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
 use crate::block::ItemPtr;
-use crate::branch::{Branch, BranchPtr};
-use crate::doc::TransactionAcqError;
+use crate::branch::{Branch, BranchID, BranchPtr};
+use crate::doc::{QuotaExceeded, TransactionAcqError};
+use crate::encoding::read::{Error, MAX_PREALLOCATED_LEN};
 use crate::iter::TxnIterator;
+use crate::observer::{kind, ObserverKey, SubscriptionKey};
 use crate::slice::BlockSlice;
 use crate::sync::Clock;
 use crate::transaction::Origin;
-use crate::{DeleteSet, Doc, Observer, Subscription, Transact, TransactionMut, ID};
+use crate::types::TypePtr;
+use crate::updates::decoder::{Decode, Decoder, DecoderV1};
+use crate::updates::encoder::{Encode, Encoder, EncoderV1};
+use crate::{DeleteSet, Doc, Observer, ReadTxn, Subscription, Transact, TransactionMut, ID};
 
 /// Undo manager is a structure used to perform undo/redo operations over the associated shared
 /// type(s).
@@ -56,18 +65,90 @@ impl<M> Meta for M where M: Default {}
 
 struct Inner<M> {
     doc: Doc,
-    scope: HashSet<BranchPtr>,
+    scope: Scope,
     options: Options,
     undo_stack: UndoStack<M>,
     redo_stack: UndoStack<M>,
     undoing: bool,
     redoing: bool,
+    /// Origin captured by the stack item, which is currently being undone or redone.
+    restored_origin: Option<Origin>,
     last_change: u64,
     observer_added: Observer<UndoFn<M>>,
     observer_updated: Observer<UndoFn<M>>,
     observer_popped: Observer<UndoFn<M>>,
 }
 
+/// Set of shared types tracked by an [UndoManager].
+#[derive(Debug, Default)]
+struct Scope {
+    /// Shared types tracked as a whole.
+    branches: HashSet<BranchPtr>,
+    /// Map-like shared types, which are tracked only for entries stored under specific keys.
+    keys: HashMap<BranchPtr, HashSet<Arc<str>>>,
+}
+
+impl Scope {
+    /// Checks if a given `item` (or any of its ancestors) belongs to current scope.
+    fn contains(&self, item: ItemPtr) -> bool {
+        let mut ptr = Some(item);
+        while let Some(item) = ptr.as_deref() {
+            if let Some(parent) = item.parent.as_branch() {
+                if self.branches.contains(parent) {
+                    return true;
+                }
+                if let (Some(keys), Some(key)) = (self.keys.get(parent), item.parent_sub.as_ref()) {
+                    if keys.contains(key) {
+                        return true;
+                    }
+                }
+                ptr = parent.item;
+            } else {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Checks if any of the tracked shared types has been changed within a given transaction.
+    fn is_changed(&self, txn: &TransactionMut) -> bool {
+        if self
+            .branches
+            .iter()
+            .any(|parent| txn.changed_parent_types.contains(parent))
+        {
+            return true;
+        }
+        if self.keys.is_empty() {
+            return false;
+        }
+        txn.changed.iter().any(|(ptr, subs)| {
+            if let TypePtr::Branch(branch) = ptr {
+                if let Some(keys) = self.keys.get(branch) {
+                    let changed_key = subs
+                        .iter()
+                        .any(|key| key.as_ref().map(|k| keys.contains(k)).unwrap_or(false));
+                    if changed_key {
+                        return true;
+                    }
+                }
+                // changes made to a collection nested under one of the tracked keys
+                if let Some(item) = branch.item {
+                    return self.contains(item);
+                }
+            }
+            false
+        })
+    }
+
+    fn ids(&self) -> impl Iterator<Item = BranchID> + '_ {
+        self.branches
+            .iter()
+            .chain(self.keys.keys())
+            .map(|branch| branch.id())
+    }
+}
+
 impl<M> UndoManager<M>
 where
     M: Meta + 'static,
@@ -103,12 +184,16 @@ where
         let scope = BranchPtr::from(scope.as_ref());
         let mut inner = Arc::new(Inner {
             doc: doc.clone(),
-            scope: HashSet::from([scope]),
+            scope: Scope {
+                branches: HashSet::from([scope]),
+                keys: HashMap::new(),
+            },
             options,
             undo_stack: UndoStack::default(),
             redo_stack: UndoStack::default(),
             undoing: false,
             redoing: false,
+            restored_origin: None,
             last_change: 0,
             observer_added: Observer::new(),
             observer_updated: Observer::new(),
@@ -119,7 +204,7 @@ where
         inner_mut.options.tracked_origins.insert(origin.clone());
         let ptr = AtomicPtr::new(inner_mut as *mut Inner<M>);
 
-        doc.observe_destroy_with(origin.clone(), move |txn, _| {
+        doc.observe_destroy_with(SubscriptionKey::new(origin.clone()), move |txn, _| {
             let ptr = ptr.load(Ordering::Acquire);
             let inner = unsafe { ptr.as_mut().unwrap() };
             Self::handle_destroy(txn, inner)
@@ -127,7 +212,7 @@ where
         .unwrap();
         let ptr = AtomicPtr::new(inner_mut as *mut Inner<M>);
 
-        doc.observe_after_transaction_with(origin, move |txn| {
+        doc.observe_after_transaction_with(SubscriptionKey::new(origin), move |txn| {
             let ptr = ptr.load(Ordering::Acquire);
             let inner = unsafe { ptr.as_mut().unwrap() };
             Self::handle_after_transaction(inner, txn);
@@ -143,14 +228,17 @@ where
                 return true;
             }
         }
-        !inner
-            .scope
-            .iter()
-            .any(|parent| txn.changed_parent_types.contains(parent))
-            || !txn
-                .origin()
-                .map(|o| inner.options.tracked_origins.contains(o))
-                .unwrap_or(inner.options.tracked_origins.len() == 1) // tracked origins contain only undo manager itself
+        !inner.scope.is_changed(txn)
+            || !(inner.undoing // undo/redo transactions may use origin that is not tracked
+                || inner.redoing
+                || txn
+                    .origin()
+                    .map(|o| inner.options.is_tracked(o))
+                    // tracked origins contain only undo manager itself
+                    .unwrap_or(
+                        inner.options.tracked_origins.len() == 1
+                            && inner.options.tracked_origin_types.is_empty(),
+                    ))
     }
 
     fn handle_after_transaction(inner: &mut Inner<M>, txn: &mut TransactionMut) {
@@ -198,7 +286,12 @@ where
             }
         } else {
             // create a new stack op
-            let item = StackItem::new(txn.delete_set.clone(), insertions);
+            let origin = if undoing || redoing {
+                inner.restored_origin.clone()
+            } else {
+                txn.origin.clone()
+            };
+            let item = StackItem::new(txn.delete_set.clone(), insertions, origin);
             stack.push(item);
         }
 
@@ -210,7 +303,7 @@ where
         let mut deleted = ds.deleted_blocks();
         while let Some(slice) = deleted.next(txn) {
             if let Some(item) = slice.as_item() {
-                if inner.scope.iter().any(|b| b.is_parent_of(Some(item))) {
+                if inner.scope.contains(item) {
                     item.keep(true);
                 }
             }
@@ -225,11 +318,21 @@ where
         };
         if !extend {
             if inner.observer_added.has_subscribers() {
-                inner.observer_added.trigger(|fun| fun(txn, &mut event));
+                let hook = txn.store().options.callback_panic_hook.as_ref();
+                txn.defer_panic(
+                    inner
+                        .observer_added
+                        .trigger(hook, |fun| fun(txn, &mut event)),
+                );
             }
         } else {
             if inner.observer_updated.has_subscribers() {
-                inner.observer_updated.trigger(|fun| fun(txn, &mut event));
+                let hook = txn.store().options.callback_panic_hook.as_ref();
+                txn.defer_panic(
+                    inner
+                        .observer_updated
+                        .trigger(hook, |fun| fun(txn, &mut event)),
+                );
             }
         }
         last_op.meta = event.meta;
@@ -269,12 +372,10 @@ where
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_item_added_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemAdded>,
         F: Fn(&TransactionMut, &mut Event<M>) + Send + Sync + 'static,
     {
-        self.0
-            .observer_added
-            .subscribe_with(key.into(), Box::new(f))
+        self.0.observer_added.subscribe_with_key(key, Box::new(f))
     }
 
     /// Registers a callback function to be called every time a new [StackItem] is created. This
@@ -287,19 +388,17 @@ where
     #[cfg(target_family = "wasm")]
     pub fn observe_item_added_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemAdded>,
         F: Fn(&TransactionMut, &mut Event<M>) + 'static,
     {
-        self.0
-            .observer_added
-            .subscribe_with(key.into(), Box::new(f))
+        self.0.observer_added.subscribe_with_key(key, Box::new(f))
     }
 
     pub fn unobserve_item_added<K>(&self, key: K) -> bool
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemAdded>,
     {
-        self.0.observer_added.unsubscribe(&key.into())
+        self.0.observer_added.unsubscribe(&key.into_origin())
     }
 
     /// Registers a callback function to be called every time an existing [StackItem] has been
@@ -324,12 +423,10 @@ where
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_item_updated_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemUpdated>,
         F: Fn(&TransactionMut, &mut Event<M>) + Send + Sync + 'static,
     {
-        self.0
-            .observer_updated
-            .subscribe_with(key.into(), Box::new(f))
+        self.0.observer_updated.subscribe_with_key(key, Box::new(f))
     }
 
     /// Registers a callback function to be called every time an existing [StackItem] has been
@@ -341,19 +438,17 @@ where
     #[cfg(target_family = "wasm")]
     pub fn observe_item_updated_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemUpdated>,
         F: Fn(&TransactionMut, &mut Event<M>) + 'static,
     {
-        self.0
-            .observer_updated
-            .subscribe_with(key.into(), Box::new(f))
+        self.0.observer_updated.subscribe_with_key(key, Box::new(f))
     }
 
     pub fn unobserve_item_updated<K>(&self, key: K) -> bool
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemUpdated>,
     {
-        self.0.observer_updated.unsubscribe(&key.into())
+        self.0.observer_updated.unsubscribe(&key.into_origin())
     }
 
     /// Registers a callback function to be called every time an existing [StackItem] has been
@@ -376,12 +471,10 @@ where
     #[cfg(not(target_family = "wasm"))]
     pub fn observe_item_popped_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemPopped>,
         F: Fn(&TransactionMut, &mut Event<M>) + Send + Sync + 'static,
     {
-        self.0
-            .observer_popped
-            .subscribe_with(key.into(), Box::new(f))
+        self.0.observer_popped.subscribe_with_key(key, Box::new(f))
     }
 
     /// Registers a callback function to be called every time an existing [StackItem] has been
@@ -392,19 +485,17 @@ where
     #[cfg(target_family = "wasm")]
     pub fn observe_item_popped_with<K, F>(&self, key: K, f: F)
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemPopped>,
         F: Fn(&TransactionMut, &mut Event<M>) + 'static,
     {
-        self.0
-            .observer_popped
-            .subscribe_with(key.into(), Box::new(f))
+        self.0.observer_popped.subscribe_with_key(key, Box::new(f))
     }
 
     pub fn unobserve_item_popped<K>(&self, key: K) -> bool
     where
-        K: Into<Origin>,
+        K: ObserverKey<kind::UndoItemPopped>,
     {
-        self.0.observer_popped.unsubscribe(&key.into())
+        self.0.observer_popped.unsubscribe(&key.into_origin())
     }
 
     /// Extends a list of shared types tracked by current undo manager by a given `scope`.
@@ -414,7 +505,98 @@ where
     {
         let ptr = BranchPtr::from(scope.as_ref());
         let inner = self.inner();
-        inner.scope.insert(ptr);
+        inner.scope.branches.insert(ptr);
+    }
+
+    /// Restricts tracking of a given map-like shared type (eg. [MapRef]) to entries stored under
+    /// provided `keys` only. Changes made to other entries - including collections nested under
+    /// them - will neither be captured nor reverted by current undo manager. If `scope` was not
+    /// tracked before, it's added to the scope. Calling this method again for the same shared
+    /// type extends a set of its tracked keys.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, Transact, UndoManager};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut mgr = UndoManager::new(&doc, &map);
+    /// mgr.scope_to_keys(&map, ["content"]);
+    ///
+    /// map.insert(&mut doc.transact_mut(), "metadata", "draft");
+    /// assert!(!mgr.can_undo());
+    ///
+    /// map.insert(&mut doc.transact_mut(), "content", "hello");
+    /// mgr.undo().unwrap();
+    /// assert!(map.get(&doc.transact(), "content").is_none());
+    /// assert!(map.get(&doc.transact(), "metadata").is_some());
+    /// ```
+    ///
+    /// [MapRef]: crate::MapRef
+    pub fn scope_to_keys<T, I, K>(&mut self, scope: &T, keys: I)
+    where
+        T: AsRef<Branch>,
+        I: IntoIterator<Item = K>,
+        K: Into<Arc<str>>,
+    {
+        let ptr = BranchPtr::from(scope.as_ref());
+        let inner = self.inner();
+        inner.scope.branches.remove(&ptr);
+        inner
+            .scope
+            .keys
+            .entry(ptr)
+            .or_default()
+            .extend(keys.into_iter().map(Into::into));
+    }
+
+    /// Extends a list of shared types tracked by current undo manager by a shared type identified
+    /// by a given `id`. Returns `false` if no such shared type exists in the document.
+    ///
+    /// Together with [UndoManager::scope] this can be used to persist the scope of an undo manager
+    /// and restore it once the document has been reloaded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, MapPrelim, ReadTxn, StateVector, Text, TextPrelim, Transact, Update};
+    /// use yrs::undo::UndoManager;
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let text = map.insert(&mut doc.transact_mut(), "text", TextPrelim::new(""));
+    /// let mgr: UndoManager<()> = UndoManager::new(&doc, &text);
+    /// let scope = serde_json::to_string(&mgr.scope()).unwrap();
+    ///
+    /// // reload document and restore undo manager scope
+    /// let update = doc.transact().encode_state_as_update_v1(&StateVector::default());
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// doc.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+    /// let mut mgr: UndoManager<()> = UndoManager::new(&doc, &map);
+    /// for id in serde_json::from_str::<Vec<yrs::BranchID>>(&scope).unwrap() {
+    ///     assert!(mgr.expand_scope_by_id(&doc.transact(), &id));
+    /// }
+    /// ```
+    pub fn expand_scope_by_id<T: ReadTxn>(&mut self, txn: &T, id: &BranchID) -> bool {
+        match txn.branch_by_id(id) {
+            Some(ptr) => {
+                self.inner().scope.branches.insert(ptr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns identifiers of all shared types tracked by current undo manager. Unlike the shared
+    /// type references, these identifiers can be persisted and resolved after the document has
+    /// been reloaded (see: [UndoManager::expand_scope_by_id]).
+    pub fn scope(&self) -> Vec<BranchID> {
+        let mut scope: Vec<BranchID> = self.0.scope.ids().collect();
+        scope.sort();
+        scope
     }
 
     /// Extends a list of origins tracked by current undo manager by given `origin`. Origin markers
@@ -437,6 +619,44 @@ where
         inner.options.tracked_origins.remove(&origin.into());
     }
 
+    /// Extends a list of origins tracked by current undo manager by all typed origins carrying
+    /// a value of type `T` (see: [Origin::new]), no matter what the actual value is. This is
+    /// an equivalent of tracking origins by their class in Yjs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Origin, Text, Transact, UndoManager};
+    ///
+    /// #[derive(Hash, PartialEq, Eq)]
+    /// struct Peer(u32);
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut mgr = UndoManager::new(&doc, &text);
+    /// mgr.include_origin_type::<Peer>();
+    ///
+    /// text.push(&mut doc.transact_mut_with(Origin::new(Peer(1))), "a");
+    /// text.push(&mut doc.transact_mut_with(Origin::new(Peer(2))), "b");
+    /// text.push(&mut doc.transact_mut_with("other"), "c");
+    /// mgr.undo().unwrap();
+    /// assert_eq!(text.get_string(&doc.transact()), "c");
+    /// ```
+    pub fn include_origin_type<T: 'static>(&mut self) {
+        let inner = self.inner();
+        inner.options.tracked_origin_types.insert(TypeId::of::<T>());
+    }
+
+    /// Removes a type `T` from the list of origin types tracked by a current undo manager
+    /// (see: [UndoManager::include_origin_type]).
+    pub fn exclude_origin_type<T: 'static>(&mut self) {
+        let inner = self.inner();
+        inner
+            .options
+            .tracked_origin_types
+            .remove(&TypeId::of::<T>());
+    }
+
     /// Clears all [StackItem]s stored within current UndoManager, effectively resetting its state.
     pub fn clear(&mut self) -> Result<(), TransactionAcqError> {
         let inner = self.inner();
@@ -455,11 +675,155 @@ where
         Ok(())
     }
 
-    fn clear_item(scope: &HashSet<BranchPtr>, txn: &mut TransactionMut, stack_item: StackItem<M>) {
+    /// Serializes the undo and redo stacks of current undo manager using lib0 v1 encoding, so that
+    /// they can be persisted together with the document and restored in another session using
+    /// [UndoManager::restore_state]. Stack items are encoded as ranges of inserted and deleted
+    /// block identifiers together with their metadata (serialized as JSON) and origins. Only
+    /// binary origins are preserved - typed origins (see: [Origin::new]) are skipped.
+    ///
+    /// Undo manager scope is not a part of encoded state (see: [UndoManager::scope]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if metadata of any of the stack items could not be serialized.
+    pub fn encode_state(&self) -> Result<Vec<u8>, serde_json::Error>
+    where
+        M: Serialize,
+    {
+        let mut encoder = EncoderV1::new();
+        Self::encode_stack(&self.0.undo_stack, &mut encoder)?;
+        Self::encode_stack(&self.0.redo_stack, &mut encoder)?;
+        Ok(encoder.to_vec())
+    }
+
+    fn encode_stack<E: Encoder>(
+        stack: &UndoStack<M>,
+        encoder: &mut E,
+    ) -> Result<(), serde_json::Error>
+    where
+        M: Serialize,
+    {
+        encoder.write_var(stack.len());
+        for item in stack.iter() {
+            item.insertions.encode(encoder);
+            item.deletions.encode(encoder);
+            match &item.origin {
+                Some(origin) if origin.type_id().is_none() => {
+                    encoder.write_u8(1);
+                    encoder.write_buf(origin);
+                }
+                _ => encoder.write_u8(0),
+            }
+            let meta = serde_json::to_string(&item.meta)?;
+            encoder.write_string(&meta);
+        }
+        Ok(())
+    }
+
+    /// Replaces undo and redo stacks of current undo manager with the ones serialized by
+    /// [UndoManager::encode_state], eg. in a previous session of an editor which has been reloaded
+    /// from persistence. Stack items referring to blocks which are not present in a document
+    /// observed by `txn` are skipped.
+    ///
+    /// In order to undo deletions made in a previous session, the deleted content must have been
+    /// retained by the document: it should be loaded with garbage collection disabled (see:
+    /// [TxnOptions::gc]). Deleted content referenced by restored stack items is retained from now
+    /// on, just like in case of changes tracked by current undo manager.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, TxnOptions, Update};
+    /// use yrs::undo::UndoManager;
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut mgr: UndoManager<()> = UndoManager::new(&doc, &text);
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// mgr.reset(); // finish capturing current stack item
+    /// text.remove_range(&mut doc.transact_mut(), 0, 5);
+    ///
+    /// let update = doc.transact().encode_state_as_update_v1(&StateVector::default());
+    /// let state = mgr.encode_state().unwrap();
+    ///
+    /// // reload the document in another session
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// doc.transact_mut_with_opts(TxnOptions { origin: None, gc: Some(false) })
+    ///     .apply_update(Update::decode_v1(&update).unwrap())
+    ///     .unwrap();
+    /// let mut mgr: UndoManager<()> = UndoManager::new(&doc, &text);
+    /// mgr.restore_state(&doc.transact(), &state).unwrap();
+    ///
+    /// mgr.undo().unwrap();
+    /// assert_eq!(text.get_string(&doc.transact()), "hello");
+    /// mgr.undo().unwrap();
+    /// assert_eq!(text.get_string(&doc.transact()), "");
+    /// ```
+    pub fn restore_state<T: ReadTxn>(&mut self, txn: &T, data: &[u8]) -> Result<(), Error>
+    where
+        M: DeserializeOwned,
+    {
+        let mut decoder = DecoderV1::from(data);
+        let undo_stack = Self::decode_stack(txn, &mut decoder)?;
+        let redo_stack = Self::decode_stack(txn, &mut decoder)?;
+        let inner = self.inner();
+        for item in undo_stack.iter().chain(redo_stack.iter()) {
+            // make sure that deleted structs are not gc'd
+            let mut deleted = item.deletions.deleted_blocks();
+            while let Some(slice) = deleted.next(txn) {
+                if let Some(item) = slice.as_item() {
+                    if inner.scope.contains(item) {
+                        item.keep(true);
+                    }
+                }
+            }
+        }
+        inner.undo_stack = undo_stack;
+        inner.redo_stack = redo_stack;
+        inner.last_change = 0;
+        Ok(())
+    }
+
+    fn decode_stack<T: ReadTxn, D: Decoder>(txn: &T, decoder: &mut D) -> Result<UndoStack<M>, Error>
+    where
+        M: DeserializeOwned,
+    {
+        let state = txn.state_vector();
+        let len: usize = decoder.read_var()?;
+        let mut stack = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..len {
+            let insertions = DeleteSet::decode(decoder)?;
+            let deletions = DeleteSet::decode(decoder)?;
+            let origin = match decoder.read_u8()? {
+                0 => None,
+                _ => Some(Origin::from(decoder.read_buf()?)),
+            };
+            let meta: M = serde_json::from_str(decoder.read_string()?)?;
+            let is_known = |ds: &DeleteSet| {
+                ds.iter().all(|(client, ranges)| {
+                    let clock = state.get(client);
+                    ranges.iter().all(|range| range.end <= clock)
+                })
+            };
+            if is_known(&insertions) && is_known(&deletions) {
+                stack.push(StackItem {
+                    deletions,
+                    insertions,
+                    origin,
+                    meta,
+                });
+            }
+        }
+        Ok(UndoStack(stack))
+    }
+
+    fn clear_item(scope: &Scope, txn: &mut TransactionMut, stack_item: StackItem<M>) {
         let mut deleted = stack_item.deletions.deleted_blocks();
         while let Some(slice) = deleted.next(txn) {
             if let Some(item) = slice.as_item() {
-                if scope.iter().any(|b| b.is_parent_of(Some(item))) {
+                if scope.contains(item) {
                     item.keep(false);
                 }
             }
@@ -517,23 +881,38 @@ where
     ///
     /// This method requires an exclusive access to underlying document store. This means that
     /// no other transaction on that same document can be active while calling this method.
-    /// Otherwise an error will be returned.
-    pub fn undo(&mut self) -> Result<bool, TransactionAcqError> {
+    /// Otherwise an [UndoError::Transaction] error will be returned, which is also the case when
+    /// tracked document is a read-only replica ([TransactionAcqError::ReadOnly]). If restored
+    /// content would exceed one of the document quotas, [UndoError::QuotaExceeded] is returned and
+    /// no changes are made.
+    pub fn undo(&mut self) -> Result<bool, UndoError> {
         let origin = self.as_origin();
         let inner = self.inner();
-        let mut txn = inner.doc.try_transact_mut_with(origin.clone())?;
+        let mut txn = inner.doc.try_transact_edit_with(origin.clone())?;
         inner.undoing = true;
-        let result = Self::pop(
+        let result = match Self::pop(
             &mut inner.undo_stack,
             &inner.redo_stack,
             &mut txn,
             &inner.scope,
-        );
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                inner.undoing = false;
+                return Err(e.into());
+            }
+        };
+        if let Some(item) = &result {
+            txn.origin = inner.options.undo_origin.resolve(&origin, item.origin());
+            inner.restored_origin = item.origin.clone();
+        }
         txn.commit();
+        inner.restored_origin = None;
         let changed = if let Some(item) = result {
-            let mut e = Event::undo(item.meta, Some(origin), txn.changed_parent_types.clone());
+            let mut e = Event::undo(item.meta, item.origin, txn.changed_parent_types.clone());
             if inner.observer_popped.has_subscribers() {
-                inner.observer_popped.trigger(|fun| fun(&txn, &mut e));
+                let hook = txn.store().options.callback_panic_hook.as_ref();
+                txn.defer_panic(inner.observer_popped.trigger(hook, |fun| fun(&txn, &mut e)));
             }
             true
         } else {
@@ -558,23 +937,38 @@ where
     ///
     /// This method requires an exclusive access to underlying document store. This means that
     /// no other transaction on that same document can be active while calling this method.
-    /// Otherwise an error will be returned.
-    pub fn redo(&mut self) -> Result<bool, TransactionAcqError> {
+    /// Otherwise an [UndoError::Transaction] error will be returned, which is also the case when
+    /// tracked document is a read-only replica ([TransactionAcqError::ReadOnly]). If restored
+    /// content would exceed one of the document quotas, [UndoError::QuotaExceeded] is returned and
+    /// no changes are made.
+    pub fn redo(&mut self) -> Result<bool, UndoError> {
         let origin = self.as_origin();
         let inner = self.inner();
-        let mut txn = inner.doc.try_transact_mut_with(origin.clone())?;
+        let mut txn = inner.doc.try_transact_edit_with(origin.clone())?;
         inner.redoing = true;
-        let result = Self::pop(
+        let result = match Self::pop(
             &mut inner.redo_stack,
             &inner.undo_stack,
             &mut txn,
             &inner.scope,
-        );
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                inner.redoing = false;
+                return Err(e.into());
+            }
+        };
+        if let Some(item) = &result {
+            txn.origin = inner.options.undo_origin.resolve(&origin, item.origin());
+            inner.restored_origin = item.origin.clone();
+        }
         txn.commit();
+        inner.restored_origin = None;
         let changed = if let Some(item) = result {
-            let mut e = Event::redo(item.meta, Some(origin), txn.changed_parent_types.clone());
+            let mut e = Event::redo(item.meta, item.origin, txn.changed_parent_types.clone());
             if inner.observer_popped.has_subscribers() {
-                inner.observer_popped.trigger(|fun| fun(&txn, &mut e));
+                let hook = txn.store().options.callback_panic_hook.as_ref();
+                txn.defer_panic(inner.observer_popped.trigger(hook, |fun| fun(&txn, &mut e)));
             }
             true
         } else {
@@ -588,8 +982,8 @@ where
         stack: &mut UndoStack<M>,
         other: &UndoStack<M>,
         txn: &mut TransactionMut,
-        scope: &HashSet<BranchPtr>,
-    ) -> Option<StackItem<M>> {
+        scope: &Scope,
+    ) -> Result<Option<StackItem<M>>, QuotaExceeded> {
         let mut result = None;
         while let Some(item) = stack.pop() {
             let mut to_redo = HashSet::<ItemPtr>::new();
@@ -601,11 +995,14 @@ where
                 if let BlockSlice::Item(slice) = slice {
                     let mut item = txn.store.materialize(slice);
                     if item.redone.is_some() {
-                        let slice = txn.store_mut().follow_redone(item.id())?;
+                        let slice = match txn.store_mut().follow_redone(item.id()) {
+                            Some(slice) => slice,
+                            None => return Ok(None),
+                        };
                         item = txn.store.materialize(slice);
                     }
 
-                    if !item.is_deleted() && scope.iter().any(|b| b.is_parent_of(Some(item))) {
+                    if !item.is_deleted() && scope.contains(item) {
                         to_delete.push(item);
                     }
                 }
@@ -615,8 +1012,7 @@ where
             while let Some(slice) = deleted.next(txn) {
                 if let BlockSlice::Item(slice) = slice {
                     let ptr = txn.store.materialize(slice);
-                    if scope.iter().any(|b| b.is_parent_of(Some(ptr)))
-                        && !item.insertions.is_deleted(ptr.id())
+                    if scope.contains(ptr) && !item.insertions.is_deleted(ptr.id())
                     // Never redo structs in stackItem.insertions because they were created and deleted in the same capture interval.
                     {
                         to_redo.insert(ptr);
@@ -624,10 +1020,19 @@ where
                 }
             }
 
+            if !to_redo.is_empty() {
+                // check quotas upfront, so that stack item is either restored entirely or not at all
+                let bytes = to_redo.iter().map(|ptr| ptr.content.byte_size()).sum();
+                if let Err(e) = txn.check_quota(to_redo.len(), bytes) {
+                    stack.push(item);
+                    return Err(e);
+                }
+            }
+
             for &ptr in to_redo.iter() {
                 let mut ptr = ptr;
                 change_performed |= ptr
-                    .redo(txn, &to_redo, &item.insertions, stack, other)
+                    .redo(txn, &to_redo, &item.insertions, stack, other)?
                     .is_some();
             }
 
@@ -644,15 +1049,30 @@ where
                 break;
             }
         }
-        result
+        Ok(result)
     }
 }
 
+/// Error returned by [UndoManager::undo] and [UndoManager::redo].
+#[derive(Debug, thiserror::Error)]
+pub enum UndoError {
+    /// Read-write transaction could not be acquired on the tracked document.
+    #[error(transparent)]
+    Transaction(#[from] TransactionAcqError),
+    /// Content restored by undo/redo operation would exceed one of the document quotas (see:
+    /// [crate::Options::max_blocks] and [crate::Options::max_content_bytes]).
+    #[error(transparent)]
+    QuotaExceeded(#[from] QuotaExceeded),
+}
+
 impl<M: std::fmt::Debug> std::fmt::Debug for UndoManager<M> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut s = f.debug_struct("UndoManager");
         s.field("scope", &self.0.scope);
         s.field("tracked_origins", &self.0.options.tracked_origins);
+        if !self.0.options.tracked_origin_types.is_empty() {
+            s.field("tracked_origin_types", &self.0.options.tracked_origin_types);
+        }
         if !self.0.undo_stack.is_empty() {
             s.field("undo", &self.0.undo_stack);
         }
@@ -667,8 +1087,14 @@ impl<M> Drop for UndoManager<M> {
     fn drop(&mut self) {
         let inner = &self.0;
         let origin = Origin::from(Arc::as_ptr(&inner) as usize);
-        inner.doc.unobserve_destroy(origin.clone()).unwrap();
-        inner.doc.unobserve_after_transaction(origin).unwrap();
+        inner
+            .doc
+            .unobserve_destroy(SubscriptionKey::new(origin.clone()))
+            .unwrap();
+        inner
+            .doc
+            .unobserve_after_transaction(SubscriptionKey::new(origin))
+            .unwrap();
     }
 }
 
@@ -712,13 +1138,23 @@ pub struct Options {
     /// If not provided, it will track only updates made within transaction with no origin defined.
     pub tracked_origins: HashSet<Origin>,
 
+    /// List of types of typed origins (see: [Origin::new]) tracked by corresponding [UndoManager].
+    /// Transaction with a typed origin will be tracked if the type of its value is present
+    /// in this set, no matter what the actual value is.
+    pub tracked_origin_types: HashSet<TypeId>,
+
     /// Custom logic decider, that along with [tracked_origins] can be used to determine if
     /// transaction changes should be captured or not.
     pub capture_transaction: Option<CaptureTransactionFn>,
 
     /// Custom clock function, that can be used to generate timestamps used by
-    /// [Options::capture_timeout_millis].
+    /// [Options::capture_timeout_millis]. [ManualClock](crate::sync::time::ManualClock) can be
+    /// used to control grouping of changes deterministically, eg. in tests.
     pub timestamp: Arc<dyn Clock>,
+
+    /// Determines the origin of transactions used to apply [UndoManager::undo] and
+    /// [UndoManager::redo] changes. Default: [UndoOrigin::Manager].
+    pub undo_origin: UndoOrigin,
 }
 
 pub type CaptureTransactionFn = Arc<dyn Fn(&TransactionMut) -> bool + Send + Sync + 'static>;
@@ -729,8 +1165,49 @@ impl Default for Options {
         Options {
             capture_timeout_millis: 500,
             tracked_origins: HashSet::new(),
+            tracked_origin_types: HashSet::new(),
             capture_transaction: None,
             timestamp: Arc::new(crate::sync::time::SystemClock),
+            undo_origin: UndoOrigin::default(),
+        }
+    }
+}
+
+impl Options {
+    /// Checks if given `origin` is tracked either directly (see: [Options::tracked_origins])
+    /// or by the type of its value (see: [Options::tracked_origin_types]).
+    pub fn is_tracked(&self, origin: &Origin) -> bool {
+        if self.tracked_origins.contains(origin) {
+            return true;
+        }
+        match origin.type_id() {
+            Some(type_id) => self.tracked_origin_types.contains(&type_id),
+            None => false,
+        }
+    }
+}
+
+/// Origin assigned to transactions, which apply changes of [UndoManager::undo] and
+/// [UndoManager::redo] operations (see: [Options::undo_origin]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UndoOrigin {
+    /// Use the origin of an undo manager itself (see: [UndoManager::as_origin]). This is
+    /// the default.
+    #[default]
+    Manager,
+    /// Re-use the origin of a transaction, which originally produced undone/redone [StackItem]
+    /// (see: [StackItem::origin]).
+    Captured,
+    /// Use a provided origin.
+    Custom(Origin),
+}
+
+impl UndoOrigin {
+    fn resolve(&self, manager: &Origin, captured: Option<&Origin>) -> Option<Origin> {
+        match self {
+            UndoOrigin::Manager => Some(manager.clone()),
+            UndoOrigin::Captured => captured.cloned(),
+            UndoOrigin::Custom(origin) => Some(origin.clone()),
         }
     }
 }
@@ -749,6 +1226,7 @@ impl Default for Options {
 pub struct StackItem<T> {
     deletions: DeleteSet,
     insertions: DeleteSet,
+    origin: Option<Origin>,
 
     /// A custom user metadata that can be attached to a particular [StackItem]. It can be used
     /// to carry over the additional information (such as ie. user cursor position) between
@@ -757,10 +1235,11 @@ pub struct StackItem<T> {
 }
 
 impl<M: Default> StackItem<M> {
-    fn new(deletions: DeleteSet, insertions: DeleteSet) -> Self {
+    fn new(deletions: DeleteSet, insertions: DeleteSet, origin: Option<Origin>) -> Self {
         StackItem {
             deletions,
             insertions,
+            origin,
             meta: M::default(),
         }
     }
@@ -776,6 +1255,12 @@ impl<M: Default> StackItem<M> {
     pub fn insertions(&self) -> &DeleteSet {
         &self.insertions
     }
+
+    /// Origin of a transaction, which produced current [StackItem]. When multiple transactions
+    /// were merged into a single stack item, this is the origin of the first one.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
 }
 
 impl<M> std::fmt::Display for StackItem<M> {
@@ -832,7 +1317,9 @@ impl<M> Event<M> {
         self.changed_parent_types.contains(&ptr)
     }
 
-    /// Returns a transaction origin related to this update notification.
+    /// Returns a transaction origin related to this update notification. For stack items popped
+    /// by [UndoManager::undo] and [UndoManager::redo] this is the origin captured by the stack item
+    /// (see: [StackItem::origin]).
     pub fn origin(&self) -> Option<&Origin> {
         self.origin.as_ref()
     }
@@ -864,19 +1351,157 @@ mod test {
     use std::collections::HashMap;
     use std::convert::TryInto;
     use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
+    use crate::sync::time::ManualClock;
     use crate::test_utils::exchange_updates;
     use crate::types::text::{Diff, YChange};
     use crate::types::{Attrs, ToJson};
-    use crate::undo::Options;
+    use crate::undo::{Options, UndoError, UndoOrigin};
     use crate::updates::decoder::Decode;
     use crate::{
-        any, Any, Array, ArrayPrelim, Doc, GetString, Map, MapPrelim, MapRef, ReadTxn, StateVector,
-        Text, TextPrelim, TextRef, Transact, UndoManager, Update, Xml, XmlElementPrelim,
-        XmlElementRef, XmlFragment, XmlTextPrelim,
+        any, Any, Array, ArrayPrelim, Doc, GetString, Map, MapPrelim, MapRef, Origin,
+        QuotaExceeded, ReadTxn, StateVector, Text, TextPrelim, TextRef, Transact, TxnOptions,
+        UndoManager, Update, Xml, XmlElementPrelim, XmlElementRef, XmlFragment, XmlTextPrelim,
     };
 
+    #[test]
+    fn capture_timeout_with_manual_clock() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let clock = ManualClock::new(1);
+        let mut mgr = UndoManager::with_options(&doc, &text, {
+            let mut o = Options::default();
+            o.capture_timeout_millis = 100;
+            o.timestamp = Arc::new(clock.clone());
+            o
+        });
+
+        text.push(&mut doc.transact_mut(), "a");
+        clock.advance(99);
+        text.push(&mut doc.transact_mut(), "b");
+        clock.advance(100);
+        text.push(&mut doc.transact_mut(), "c");
+        clock.advance(10);
+        mgr.reset();
+        text.push(&mut doc.transact_mut(), "d");
+
+        mgr.undo().unwrap();
+        assert_eq!(text.get_string(&doc.transact()), "abc");
+        mgr.undo().unwrap();
+        assert_eq!(text.get_string(&doc.transact()), "ab");
+        mgr.undo().unwrap();
+        assert_eq!(text.get_string(&doc.transact()), "");
+    }
+
+    #[test]
+    fn restore_scope_by_id() {
+        let d1 = Doc::with_client_id(1);
+        let root = d1.get_or_insert_map("map");
+        let other = d1.get_or_insert_text("other");
+        let text = root.insert(&mut d1.transact_mut(), "text", TextPrelim::new("a"));
+        let mut mgr = UndoManager::new(&d1, &text);
+        mgr.expand_scope(&other);
+        let scope = mgr.scope();
+        assert_eq!(scope, vec![text.as_ref().id(), other.as_ref().id()]);
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let d2 = Doc::with_client_id(2);
+        let root = d2.get_or_insert_map("map");
+        d2.get_or_insert_text("other");
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        let mut mgr = UndoManager::new(&d2, &root);
+        for id in scope.iter() {
+            assert!(mgr.expand_scope_by_id(&d2.transact(), id));
+        }
+        let text: TextRef = root.get(&d2.transact(), "text").unwrap().cast().unwrap();
+        text.push(&mut d2.transact_mut(), "b");
+        assert_eq!(text.get_string(&d2.transact()), "ab");
+        mgr.undo().unwrap();
+        assert_eq!(text.get_string(&d2.transact()), "a");
+
+        // removed shared types can no longer be resolved
+        root.remove(&mut d2.transact_mut(), "text");
+        assert!(!mgr.expand_scope_by_id(&d2.transact(), &scope[0]));
+    }
+
+    #[test]
+    fn persist_undo_state() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let mut mgr: crate::undo::UndoManager<u32> = crate::undo::UndoManager::new(&d1, &txt1);
+        let _sub = mgr.observe_item_added(|_, e| *e.meta_mut() += 1);
+        mgr.include_origin("a");
+        txt1.push(&mut d1.transact_mut_with("a"), "abc");
+        mgr.reset();
+        txt1.remove_range(&mut d1.transact_mut_with("a"), 1, 1);
+        mgr.reset();
+        txt1.push(&mut d1.transact_mut_with("a"), "d");
+        mgr.undo().unwrap();
+        assert_eq!(txt1.get_string(&d1.transact()), "ac");
+
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let state = mgr.encode_state().unwrap();
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+        d2.transact_mut_with_opts(TxnOptions {
+            origin: None,
+            gc: Some(false),
+        })
+        .apply_update(Update::decode_v1(&update).unwrap())
+        .unwrap();
+        let mut mgr: crate::undo::UndoManager<u32> = crate::undo::UndoManager::new(&d2, &txt2);
+        mgr.restore_state(&d2.transact(), &state).unwrap();
+        let popped = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let popped = popped.clone();
+            mgr.observe_item_popped(move |_, e| {
+                let mut popped = popped.lock().unwrap();
+                popped.push((*e.meta(), e.origin().cloned()));
+            })
+        };
+
+        mgr.redo().unwrap();
+        assert_eq!(txt2.get_string(&d2.transact()), "acd");
+        mgr.undo().unwrap();
+        mgr.undo().unwrap();
+        assert_eq!(txt2.get_string(&d2.transact()), "abc");
+        mgr.undo().unwrap();
+        assert_eq!(txt2.get_string(&d2.transact()), "");
+        assert!(!mgr.can_undo());
+        let popped = popped.lock().unwrap();
+        assert_eq!(popped.len(), 4);
+        assert_eq!(popped[3], (1, Some(Origin::from("a"))));
+
+        // stack items referring to unknown blocks are skipped
+        let d3 = Doc::with_client_id(3);
+        let txt3 = d3.get_or_insert_text("text");
+        let mut mgr: crate::undo::UndoManager<u32> = crate::undo::UndoManager::new(&d3, &txt3);
+        mgr.restore_state(&d3.transact(), &state).unwrap();
+        assert!(!mgr.can_undo());
+        assert!(!mgr.can_redo());
+    }
+
+    #[test]
+    fn persist_undo_state_with_unserializable_meta() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut mgr: crate::undo::UndoManager<HashMap<Vec<u8>, u32>> =
+            crate::undo::UndoManager::new(&doc, &txt);
+        let _sub = mgr.observe_item_added(|_, e| {
+            e.meta_mut().insert(vec![1], 1);
+        });
+        txt.push(&mut doc.transact_mut(), "abc");
+        assert!(mgr.encode_state().is_err());
+    }
+
     #[test]
     fn undo_text() {
         let d1 = Doc::with_client_id(1);
@@ -1021,6 +1646,40 @@ mod test {
         assert_eq!(map1.get(&d1.transact(), "b").unwrap(), "initial".into());
     }
 
+    #[test]
+    fn undo_map_scoped_to_keys() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("test");
+        let mut mgr = UndoManager::new(&doc, &map);
+        mgr.scope_to_keys(&map, ["content"]);
+
+        map.insert(&mut doc.transact_mut(), "metadata", 1);
+        assert!(!mgr.can_undo());
+
+        let content = map.insert(&mut doc.transact_mut(), "content", MapPrelim::<u32>::new());
+        mgr.reset();
+        {
+            let mut txn = doc.transact_mut();
+            content.insert(&mut txn, "title", "a");
+            map.insert(&mut txn, "metadata", 2);
+        }
+        mgr.reset();
+
+        mgr.undo().unwrap();
+        let expected = Any::from_json(r#"{ "content": {}, "metadata": 2 }"#).unwrap();
+        assert_eq!(map.to_json(&doc.transact()), expected);
+
+        mgr.undo().unwrap();
+        let expected = Any::from_json(r#"{ "metadata": 2 }"#).unwrap();
+        assert_eq!(map.to_json(&doc.transact()), expected);
+        assert!(!mgr.can_undo());
+
+        mgr.redo().unwrap();
+        mgr.redo().unwrap();
+        let expected = Any::from_json(r#"{ "content": { "title": "a" }, "metadata": 2 }"#).unwrap();
+        assert_eq!(map.to_json(&doc.transact()), expected);
+    }
+
     #[test]
     fn undo_array() {
         let d1 = Doc::with_client_id(1);
@@ -1542,7 +2201,7 @@ mod test {
                     .encode_state_as_update_v1(&StateVector::default()),
             )
             .unwrap();
-            dst.transact_mut().apply_update(update)
+            dst.transact_mut().apply_update(update).unwrap()
         }
 
         let doc1 = Doc::with_client_id(1);
@@ -1623,6 +2282,60 @@ mod test {
         assert!(s == r#"<test a="1" b="2"></test>"# || s == r#"<test b="2" a="1"></test>"#);
     }
 
+    #[test]
+    fn typed_origin_tracking() {
+        #[derive(Debug, Hash, PartialEq, Eq)]
+        struct Peer {
+            id: u32,
+        }
+
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut mgr = UndoManager::new(&doc, &txt);
+        mgr.include_origin(Origin::new(Peer { id: 1 }));
+
+        txt.insert(
+            &mut doc.transact_mut_with(Origin::new(Peer { id: 1 })),
+            0,
+            "abc",
+        );
+        txt.insert(
+            &mut doc.transact_mut_with(Origin::new(Peer { id: 2 })),
+            3,
+            "def",
+        );
+        // binary origin with the same bytes as a typed one is not tracked
+        txt.insert(&mut doc.transact_mut_with(1u32), 6, "ghi");
+        assert_eq!(txt.get_string(&doc.transact()), "abcdefghi");
+
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "defghi");
+        assert!(!mgr.can_undo());
+    }
+
+    #[test]
+    fn track_class() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut mgr = UndoManager::new(&doc, &txt);
+        // only track origins that are numbers
+        mgr.include_origin_type::<u32>();
+
+        txt.insert(&mut doc.transact_mut_with(Origin::new(42u32)), 0, "abc");
+        assert_eq!(txt.get_string(&doc.transact()), "abc");
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "");
+
+        // binary origins and typed origins of other types are not tracked
+        txt.insert(&mut doc.transact_mut_with(42u32), 0, "abc");
+        txt.insert(&mut doc.transact_mut_with(Origin::new(42u64)), 3, "def");
+        assert!(!mgr.can_undo());
+
+        mgr.exclude_origin_type::<u32>();
+        txt.insert(&mut doc.transact_mut_with(Origin::new(1u32)), 6, "ghi");
+        assert!(!mgr.can_undo());
+    }
+
     #[test]
     fn undo_in_embed() {
         let d1 = Doc::with_client_id(1);
@@ -1917,4 +2630,100 @@ mod test {
             any!({"s1":{"b1":[{"b2":[[232291652, -30]]}]}})
         );
     }
+
+    #[test]
+    fn undo_with_captured_origin() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut mgr = UndoManager::with_options(
+            &doc,
+            &txt,
+            Options {
+                undo_origin: UndoOrigin::Captured,
+                ..Options::default()
+            },
+        );
+        mgr.include_origin("alice");
+
+        let txn_origins = Arc::new(Mutex::new(Vec::new()));
+        let popped_origins = Arc::new(Mutex::new(Vec::new()));
+        let _sub1 = {
+            let txn_origins = txn_origins.clone();
+            doc.observe_update_v1(move |txn, _| {
+                txn_origins.lock().unwrap().push(txn.origin().cloned());
+            })
+            .unwrap()
+        };
+        let _sub2 = {
+            let popped_origins = popped_origins.clone();
+            mgr.observe_item_popped(move |_, e| {
+                popped_origins.lock().unwrap().push(e.origin().cloned());
+            })
+        };
+
+        txt.insert(&mut doc.transact_mut_with("alice"), 0, "abc");
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "");
+        mgr.redo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "abc");
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "");
+
+        let alice = Some(Origin::from("alice"));
+        assert_eq!(
+            std::mem::take(&mut *txn_origins.lock().unwrap()),
+            vec![alice.clone(); 4]
+        );
+        assert_eq!(
+            std::mem::take(&mut *popped_origins.lock().unwrap()),
+            vec![alice.clone(); 3]
+        );
+
+        // custom origin is used for undo transactions, but stack items keep the captured one
+        mgr.redo().unwrap();
+        mgr.inner().options.undo_origin = UndoOrigin::Custom("undo".into());
+        txt.insert(&mut doc.transact_mut_with("alice"), 3, "def");
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "abc");
+        mgr.redo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "abcdef");
+
+        let undo = Some(Origin::from("undo"));
+        assert_eq!(
+            std::mem::take(&mut *txn_origins.lock().unwrap()),
+            vec![alice.clone(), alice.clone(), undo.clone(), undo]
+        );
+        assert_eq!(
+            std::mem::take(&mut *popped_origins.lock().unwrap()),
+            vec![alice.clone(), alice.clone(), alice]
+        );
+    }
+
+    #[test]
+    fn undo_exceeding_quota() {
+        let doc = Doc::with_options(crate::Options {
+            max_content_bytes: Some(8),
+            ..crate::Options::with_client_id(1)
+        });
+        let txt = doc.get_or_insert_text("text");
+        let mut mgr = UndoManager::new(&doc, &txt);
+
+        txt.insert(&mut doc.transact_mut(), 0, "hello");
+        mgr.reset();
+        txt.remove_range(&mut doc.transact_mut(), 0, 5);
+        txt.insert(&mut doc.transact_mut(), 0, "abc");
+
+        // deleted content is kept for undo, so restoring it would exceed the quota
+        let err = mgr.undo().unwrap_err();
+        assert!(matches!(
+            err,
+            UndoError::QuotaExceeded(QuotaExceeded::ContentBytes {
+                limit: 8,
+                requested: 13
+            })
+        ));
+        assert_eq!(txt.get_string(&doc.transact()), "abc");
+        // stack item was left in place
+        assert!(mgr.can_undo());
+    }
 }
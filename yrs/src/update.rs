@@ -1,8 +1,8 @@
 use crate::block::{
-    BlockRange, ClientID, Item, ItemContent, ItemPtr, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER,
-    HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
+    BlockRange, ClientID, Item, ItemContent, ItemPtr, SplittableString, BLOCK_GC_REF_NUMBER,
+    BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
 };
-use crate::encoding::read::Error;
+use crate::encoding::read::{Error, MAX_PREALLOCATED_LEN};
 use crate::id_set::DeleteSet;
 use crate::slice::ItemSlice;
 #[cfg(test)]
@@ -12,12 +12,41 @@ use crate::types::TypePtr;
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
-use crate::{OffsetKind, StateVector, ID};
+use crate::{Any, OffsetKind, QuotaExceeded, StateVector, ID};
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::BuildHasherDefault;
 use std::sync::Arc;
+use thiserror::Error;
+
+/// Error returned when a decoded [Update] could not be integrated into a document, because its
+/// contents were malformed or inconsistent with the current document state.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum UpdateError {
+    /// Block with a given ID points to a parent (identified by the second ID), which is not
+    /// a shared collection.
+    #[error("cannot integrate block {0}: its parent {1} is not a shared type")]
+    InvalidParent(ID, ID),
+    /// Block with a given ID moves a range of elements, which doesn't belong to the same shared
+    /// collection as the block itself.
+    #[error("cannot integrate block {0}: it moves elements of another shared type")]
+    InvalidMove(ID),
+    /// Integrating an update would exceed one of the quotas configured for a document.
+    #[error(transparent)]
+    QuotaExceeded(#[from] QuotaExceeded),
+}
+
+/// Progress of an [Update] integration, reported by [TransactionMut::apply_update_with_progress]
+/// after all blocks of a single client have been processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyProgress {
+    /// Number of update blocks processed so far, including the ones which couldn't be integrated
+    /// yet because of missing dependencies and have been stashed as pending.
+    pub processed: usize,
+    /// Total number of blocks in the update.
+    pub total: usize,
+}
 
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct UpdateBlocks {
@@ -25,6 +54,12 @@ pub(crate) struct UpdateBlocks {
 }
 
 impl UpdateBlocks {
+    /// Returns a total number of blocks (including skip ranges) of all clients.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn block_count(&self) -> usize {
+        self.clients.values().map(VecDeque::len).sum()
+    }
+
     /**
     @todo this should be refactored.
     I'm currently using this to add blocks to the Update
@@ -38,6 +73,83 @@ impl UpdateBlocks {
         self.clients.is_empty()
     }
 
+    /// Removes redundant information from the blocks of every client: blocks (or their parts)
+    /// already covered by preceding blocks are dropped, skip ranges are replaced by the blocks
+    /// overlapping with them, adjacent blocks are squashed together whenever possible and
+    /// trailing skip ranges, which don't carry any information, are removed.
+    /// Blocks are expected to be ordered by their clock.
+    fn deduplicate(&mut self) {
+        for blocks in self.clients.values_mut() {
+            let mut result: VecDeque<BlockCarrier> = VecDeque::with_capacity(blocks.len());
+            for block in blocks.drain(..) {
+                let mut tail = None;
+                if !matches!(block, BlockCarrier::Skip(_)) {
+                    // skip ranges don't carry any information: cut out the part overlapping
+                    // with current block and keep the remainder after it
+                    while let Some(BlockCarrier::Skip(skip)) = result.back_mut() {
+                        let clock = block.id().clock;
+                        let skip_end = skip.id.clock + skip.len;
+                        if clock >= skip_end {
+                            break;
+                        }
+                        let end = clock + block.len();
+                        if end < skip_end {
+                            let id = ID::new(skip.id.client, end);
+                            tail = Some(BlockCarrier::Skip(BlockRange::new(id, skip_end - end)));
+                        }
+                        if clock > skip.id.clock {
+                            skip.len = clock - skip.id.clock;
+                            break;
+                        }
+                        result.pop_back();
+                    }
+                }
+                if let Some(block) = Self::cut_covered(result.back_mut(), block) {
+                    result.push_back(block);
+                }
+                if let Some(tail) = tail {
+                    result.push_back(tail);
+                }
+            }
+            while let Some(BlockCarrier::Skip(_)) = result.back() {
+                result.pop_back();
+            }
+            *blocks = result;
+        }
+        self.clients.retain(|_, blocks| !blocks.is_empty());
+    }
+
+    /// Returns the part of a given `block`, which is not already covered by the `last` block.
+    /// Returns `None` if `block` is fully covered or it has been squashed into `last`.
+    fn cut_covered(last: Option<&mut BlockCarrier>, block: BlockCarrier) -> Option<BlockCarrier> {
+        let last = match last {
+            None => return Some(block),
+            Some(last) => last,
+        };
+        let end = last.id().clock + last.len();
+        let clock = block.id().clock;
+        if clock + block.len() <= end {
+            // block is fully covered by already written ones
+            return None;
+        }
+        let block = if clock < end {
+            block.splice(end - clock)?
+        } else {
+            block
+        };
+        if block.id().clock == end && last.try_squash(&block) {
+            None
+        } else {
+            Some(block)
+        }
+    }
+
+    /// Returns a total number of blocks stored for all clients.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn len(&self) -> usize {
+        self.clients.values().map(VecDeque::len).sum()
+    }
+
     /// Returns an iterator that allows a traversal of all of the blocks
     /// which consist into this [Update].
     pub(crate) fn blocks(&self) -> Blocks<'_> {
@@ -90,6 +202,7 @@ impl std::fmt::Display for BlockCarrier {
 pub struct Update {
     pub(crate) blocks: UpdateBlocks,
     pub(crate) delete_set: DeleteSet,
+    pub(crate) meta: HashMap<String, Any>,
 }
 
 impl Update {
@@ -101,6 +214,50 @@ impl Update {
         self.blocks.is_empty() && self.delete_set.is_empty()
     }
 
+    /// Returns metadata (eg. an author, device or application version) attached to current update
+    /// by a transaction which produced it (see: [TransactionMut::set_meta]).
+    ///
+    /// Metadata is encoded as an optional map placed after the delete set, which is ignored by
+    /// decoders not aware of it (including Yjs). When updates are merged, their metadata are merged
+    /// as well, with entries of later updates overriding the earlier ones.
+    pub fn meta(&self) -> &HashMap<String, Any> {
+        &self.meta
+    }
+
+    /// Sets a metadata entry attached to current update. See [Update::meta] for details.
+    pub fn set_meta<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<Any>,
+    {
+        self.meta.insert(key.into(), value.into());
+    }
+
+    /// Returns a number of blocks and an approximate number of content bytes, which integrating
+    /// current update would add to a document with a given state vector `sv`. Blocks already
+    /// present in the document are not counted.
+    pub(crate) fn quota_usage(&self, sv: &StateVector) -> (usize, usize) {
+        let mut blocks = 0;
+        let mut bytes = 0;
+        for (client, carriers) in self.blocks.clients.iter() {
+            let clock = sv.get(client);
+            for carrier in carriers.iter() {
+                if carrier.id().clock + carrier.len() <= clock {
+                    continue;
+                }
+                match carrier {
+                    BlockCarrier::Item(item) => {
+                        blocks += 1;
+                        bytes += item.content.byte_size();
+                    }
+                    BlockCarrier::GC(_) => blocks += 1,
+                    BlockCarrier::Skip(_) => {}
+                }
+            }
+        }
+        (blocks, bytes)
+    }
+
     /// Returns a state vector representing an upper bound of client clocks included by blocks
     /// stored in current update.
     pub fn state_vector(&self) -> StateVector {
@@ -165,10 +322,24 @@ impl Update {
     /// pending update object is returned which contains blocks that couldn't be integrated, most
     /// likely because there were missing blocks that are used as a dependencies of other blocks
     /// contained in this update.
+    ///
+    /// If `progress` callback is provided, it's called after all blocks of a single client have
+    /// been processed. If it returns `false`, integration is cancelled: blocks which were not
+    /// processed yet together with the delete set of current update are not integrated, but
+    /// returned as [IntegrationOutcome::cancelled] instead. Cancellation has no effect once
+    /// all blocks were processed or a malformed block was found.
+    ///
+    /// A malformed block (eg. one pointing to a parent which is not a shared collection) is
+    /// dropped, while the rest of an update is integrated anyway. Blocks which couldn't be
+    /// integrated are stashed as pending in the document store before an error is returned.
     pub(crate) fn integrate(
         mut self,
         txn: &mut TransactionMut,
-    ) -> (Option<PendingUpdate>, Option<Update>) {
+        mut progress: Option<&mut dyn FnMut(ApplyProgress) -> bool>,
+    ) -> Result<IntegrationOutcome, UpdateError> {
+        let total: usize = self.blocks.clients.values().map(VecDeque::len).sum();
+        let mut cancelled = false;
+        let mut error = None;
         let remaining_blocks = if self.blocks.is_empty() {
             None
         } else {
@@ -192,7 +363,12 @@ impl Update {
 
             while let Some(mut block) = stack_head {
                 let id = *block.id();
-                if local_sv.contains(&id) {
+                if block.is_skip() {
+                    // skipped ranges carry no content: blocks following them must be checked
+                    // against the actual local state
+                    #[cfg(feature = "metrics")]
+                    store.metrics.block_skipped();
+                } else if local_sv.contains(&id) {
                     let offset = local_sv.get(&id.client) as i32 - id.clock as i32;
                     if let Some(dep) = Self::missing(&block, &local_sv) {
                         stack.push(block);
@@ -213,37 +389,52 @@ impl Update {
                         }
                     } else if offset == 0 || (offset as u32) < block.len() {
                         let offset = offset as u32;
-                        let client = id.client;
-                        local_sv.set_max(client, id.clock + block.len());
-                        if let BlockCarrier::Item(item) = &mut block {
-                            item.repair(store);
-                        }
-                        let should_delete = block.integrate(txn, offset);
-                        let mut delete_ptr = if should_delete {
-                            let ptr = block.as_item_ptr();
-                            ptr
-                        } else {
-                            None
+                        let repaired = match &mut block {
+                            BlockCarrier::Item(item) => item.repair(store),
+                            _ => Ok(()),
                         };
-                        store = txn.store_mut();
-                        match block {
-                            BlockCarrier::Item(item) => {
-                                if item.parent != TypePtr::Unknown {
-                                    store.blocks.push_block(item)
-                                } else {
-                                    // parent is not defined. Integrate GC struct instead
-                                    store.blocks.push_gc(BlockRange::new(item.id, item.len));
-                                    delete_ptr = None;
+                        if let Err(e) = repaired {
+                            // malformed block is dropped: blocks depending on it end up as
+                            // pending, while the rest of an update is integrated as usual
+                            error.get_or_insert(e);
+                        } else {
+                            local_sv.set_max(id.client, id.clock + block.len());
+                            let should_delete = block.integrate(txn, offset);
+                            let mut delete_ptr = if should_delete {
+                                let ptr = block.as_item_ptr();
+                                ptr
+                            } else {
+                                None
+                            };
+                            store = txn.store_mut();
+                            match block {
+                                BlockCarrier::Item(item) => {
+                                    if item.parent != TypePtr::Unknown {
+                                        if store.options.max_content_bytes.is_some() {
+                                            store.content_bytes += item.content.byte_size();
+                                        }
+                                        store.blocks.push_block(item)
+                                    } else {
+                                        // parent is not defined. Integrate GC struct instead
+                                        store.blocks.push_gc(BlockRange::new(item.id, item.len));
+                                        delete_ptr = None;
+                                    }
                                 }
+                                BlockCarrier::GC(gc) => store.blocks.push_gc(gc),
+                                BlockCarrier::Skip(_) => { /* do nothing */ }
                             }
-                            BlockCarrier::GC(gc) => store.blocks.push_gc(gc),
-                            BlockCarrier::Skip(_) => { /* do nothing */ }
-                        }
+                            #[cfg(feature = "metrics")]
+                            store.metrics.block_created();
 
-                        if let Some(ptr) = delete_ptr {
-                            txn.delete(ptr);
+                            if let Some(ptr) = delete_ptr {
+                                txn.delete(ptr);
+                            }
+                            store = txn.store_mut();
                         }
-                        store = txn.store_mut();
+                    } else {
+                        // block has been already integrated before
+                        #[cfg(feature = "metrics")]
+                        store.metrics.block_skipped();
                     }
                 } else {
                     // update from the same client is missing
@@ -266,6 +457,16 @@ impl Update {
                             current_target = Some(v);
                         }
                         _ => {
+                            if let Some(progress) = progress.as_mut() {
+                                let left: usize =
+                                    self.blocks.clients.values().map(VecDeque::len).sum();
+                                let processed = total - left;
+                                if !progress(ApplyProgress { processed, total }) && error.is_none()
+                                {
+                                    cancelled = true;
+                                    break;
+                                }
+                            }
                             if let Some((client_id, target)) =
                                 Self::next_target(&mut client_block_ref_ids, &mut self.blocks)
                             {
@@ -288,18 +489,48 @@ impl Update {
                     update: Update {
                         blocks: remaining,
                         delete_set: DeleteSet::new(),
+                        meta: HashMap::new(),
                     },
                     missing: missing_sv,
                 })
             }
         };
 
-        let remaining_ds = txn.apply_delete(&self.delete_set).map(|ds| {
+        if cancelled {
+            self.blocks.clients.retain(|_, blocks| !blocks.is_empty());
+            if !self.blocks.is_empty() {
+                return Ok(IntegrationOutcome {
+                    pending: remaining_blocks,
+                    pending_ds: None,
+                    cancelled: Some(self),
+                });
+            }
+            // all blocks have been processed anyway
+        }
+
+        let remaining_ds = txn.apply_delete(&self.delete_set);
+        if let Some(e) = error {
+            // stash what couldn't be integrated, so that it's not lost together with
+            // a malformed block
+            let store = txn.store_mut();
+            if let Some(pending) = remaining_blocks {
+                store.add_pending(pending);
+            }
+            if let Some(ds) = remaining_ds {
+                store.add_pending_ds(ds);
+            }
+            return Err(e);
+        }
+        let remaining_ds = remaining_ds.map(|ds| {
             let mut update = Update::new();
             update.delete_set = ds;
             update
         });
-        return (remaining_blocks, remaining_ds);
+        Ok(IntegrationOutcome {
+            pending: remaining_blocks,
+            pending_ds: remaining_ds,
+            cancelled: None,
+        })
     }
 
     fn missing(block: &BlockCarrier, local_sv: &StateVector) -> Option<ClientID> {
@@ -524,7 +755,310 @@ impl Update {
                 block.encode_with_offset(encoder, 0);
             }
         }
-        self.delete_set.encode(encoder)
+        self.delete_set.encode(encoder);
+        encode_meta(&self.meta, encoder);
+    }
+
+    /// Removes contents of all blocks inserted by a given `client` from current update, turning
+    /// them into tombstones. Removed blocks retain their identifiers, neighbors and parents, so
+    /// that the structure of the document is not affected, however their content is discarded
+    /// and they are added to the delete set of current update. Content of other clients nested
+    /// in shared collections created by the purged client becomes unreachable as well.
+    ///
+    /// Keys of the map entries assigned by the purged client are retained, as they are
+    /// necessary to position the tombstones.
+    pub fn purge_client(&mut self, client: ClientID) {
+        if let Some(blocks) = self.blocks.clients.get_mut(&client) {
+            for block in blocks.iter_mut() {
+                if let BlockCarrier::Item(item) = block {
+                    let len = item.len;
+                    item.content = ItemContent::Deleted(len);
+                    item.info.clear_countable();
+                    self.delete_set.insert(item.id, len);
+                }
+            }
+            self.delete_set.squash();
+        }
+    }
+
+    /// Returns an anonymized version of current update, which is structurally identical to it
+    /// (same block identifiers, lengths, neighbors, parents and delete set), but has all of its
+    /// user-provided contents replaced by placeholders:
+    ///
+    /// - Text chunks are replaced with `x` characters of the same UTF-16 length.
+    /// - JSON-like values (including embeds) are replaced with `null`s.
+    /// - Binary blobs are replaced with zeroed buffers of the same size.
+    /// - Formatting attribute values other than `null` are replaced with `true`.
+    ///
+    /// Root collection names, map keys, formatting attribute names and XML tag names are retained
+    /// as they define the structure of the document. This way an update, which causes issues, can
+    /// be attached to a bug report without leaking the actual document contents.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "secret");
+    /// let update = doc.transact().encode_state_as_update_v1(&StateVector::default());
+    ///
+    /// let stripped = Update::decode_v1(&update).unwrap().strip_content();
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// doc.transact_mut().apply_update(stripped).unwrap();
+    /// assert_eq!(text.get_string(&doc.transact()), "xxxxxx");
+    /// ```
+    pub fn strip_content(mut self) -> Update {
+        for blocks in self.blocks.clients.values_mut() {
+            for block in blocks.iter_mut() {
+                if let BlockCarrier::Item(item) = block {
+                    Self::strip_item_content(&mut item.content);
+                }
+            }
+        }
+        self
+    }
+
+    fn strip_item_content(content: &mut ItemContent) {
+        match content {
+            ItemContent::Any(values) => {
+                for value in values.iter_mut() {
+                    *value = Any::Null;
+                }
+            }
+            ItemContent::Binary(buf) => buf.iter_mut().for_each(|b| *b = 0),
+            ItemContent::JSON(values) => {
+                for value in values.iter_mut() {
+                    *value = "null".to_string();
+                }
+            }
+            ItemContent::Embed(value) => *value = Any::Null,
+            ItemContent::Format(_, value) => {
+                if **value != Any::Null {
+                    **value = Any::Bool(true);
+                }
+            }
+            ItemContent::String(str) => {
+                let placeholder: String = str
+                    .chars()
+                    .flat_map(|c| std::iter::repeat_n('x', c.len_utf16()))
+                    .collect();
+                *str = SplittableString::from(placeholder.as_str());
+            }
+            ItemContent::Deleted(_)
+            | ItemContent::Doc(_, _)
+            | ItemContent::Type(_)
+            | ItemContent::Move(_) => { /* no user content */ }
+        }
+    }
+
+    /// Partitions current update by the root-level collection each of its blocks belongs to.
+    /// Returned map is keyed by the root collection name. Blocks (and deleted ranges) which
+    /// cannot be attributed to any root collection - because they refer to blocks not present in
+    /// current update or because they are garbage collected - are stored under `None` key.
+    ///
+    /// Every partition is a valid update on its own, however blocks of different partitions are
+    /// usually interleaved with each other on the clock sequences of their clients. For this
+    /// reason integrating a single partition into a document may leave some of its blocks pending
+    /// until the blocks of other partitions they follow have been integrated. Merging all
+    /// partitions back together produces an update equivalent to the original one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use yrs::{Doc, Map, ReadTxn, StateVector, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let doc = Doc::new();
+    /// let meta = doc.get_or_insert_map("meta");
+    /// let body = doc.get_or_insert_text("body");
+    /// let mut txn = doc.transact_mut();
+    /// meta.insert(&mut txn, "title", "hello");
+    /// body.push(&mut txn, "hello world");
+    /// let update = txn.encode_state_as_update_v1(&StateVector::default());
+    ///
+    /// let mut parts = Update::decode_v1(&update).unwrap().split_by_root();
+    /// let body_part = parts.remove(&Some(Arc::from("body"))).unwrap();
+    /// let meta_part = parts.remove(&Some(Arc::from("meta"))).unwrap();
+    /// assert!(parts.is_empty());
+    /// ```
+    pub fn split_by_root(self) -> HashMap<Option<Arc<str>>, Update> {
+        let roots = self.resolve_roots();
+        let mut result: HashMap<Option<Arc<str>>, Update> = HashMap::new();
+
+        // distribute deleted ranges among blocks they refer to
+        for (client, range) in self.delete_set.iter() {
+            let blocks = self.blocks.clients.get(client);
+            for range in range.iter() {
+                let mut clock = range.start;
+                while clock < range.end {
+                    let (root, end) = match blocks.and_then(|b| Self::find_block(b, clock)) {
+                        Some(i) => {
+                            let block = &blocks.unwrap()[i];
+                            let root = roots.get(block.id()).cloned().flatten();
+                            (root, range.end.min(block.id().clock + block.len()))
+                        }
+                        None => {
+                            // find where the next block starts
+                            let next = blocks
+                                .and_then(|b| b.iter().find(|b| b.id().clock > clock))
+                                .map(|b| b.id().clock)
+                                .unwrap_or(range.end);
+                            (None, range.end.min(next))
+                        }
+                    };
+                    let update = result.entry(root).or_default();
+                    update
+                        .delete_set
+                        .insert(ID::new(*client, clock), end - clock);
+                    clock = end;
+                }
+            }
+        }
+
+        for (client, blocks) in self.blocks.clients {
+            for block in blocks {
+                if block.is_skip() {
+                    continue;
+                }
+                let root = roots.get(block.id()).cloned().flatten();
+                let update = result.entry(root).or_default();
+                let e = update.blocks.clients.entry(client).or_default();
+                if let Some(last) = e.back() {
+                    let next_clock = last.id().clock + last.len();
+                    let clock = block.id().clock;
+                    if next_clock < clock {
+                        // fill the gap with blocks which belong to other partitions
+                        let skip = BlockRange::new(ID::new(client, next_clock), clock - next_clock);
+                        e.push_back(BlockCarrier::Skip(skip));
+                    }
+                }
+                e.push_back(block);
+            }
+        }
+
+        for update in result.values_mut() {
+            update.delete_set.squash();
+        }
+        result
+    }
+
+    /// Returns an update containing only blocks (and deleted ranges) which belong to root-level
+    /// collections with given `names`. See [Update::split_by_root] for details.
+    ///
+    /// Unlike partitions returned by [Update::split_by_root], clock ranges of blocks belonging
+    /// to other collections are replaced with garbage collected placeholders, so that returned
+    /// update can be integrated right away. Keep in mind that a document, which integrated
+    /// a filtered update, will not be able to integrate the excluded blocks later on.
+    pub fn filter_roots<S: AsRef<str>>(self, names: &[S]) -> Update {
+        let starts: HashMap<ClientID, u32> = self
+            .blocks
+            .clients
+            .iter()
+            .filter_map(|(client, blocks)| Some((*client, blocks.front()?.id().clock)))
+            .collect();
+        let parts = self.split_by_root();
+        let matching = parts.into_iter().filter_map(|(root, update)| {
+            let root = root?;
+            if names.iter().any(|name| name.as_ref() == root.as_ref()) {
+                Some(update)
+            } else {
+                None
+            }
+        });
+        let mut update = Update::merge_updates(matching);
+        for (client, blocks) in update.blocks.clients.iter_mut() {
+            for block in blocks.iter_mut() {
+                if let BlockCarrier::Skip(range) = block {
+                    *block = BlockCarrier::GC(range.clone());
+                }
+            }
+            let start = starts.get(client).cloned().unwrap_or_default();
+            if let Some(first) = blocks.front() {
+                let clock = first.id().clock;
+                if start < clock {
+                    let gc = BlockRange::new(ID::new(*client, start), clock - start);
+                    blocks.push_front(BlockCarrier::GC(gc));
+                }
+            }
+        }
+        update
+    }
+
+    /// Returns an index of a block within `blocks` containing given `clock`.
+    fn find_block(blocks: &VecDeque<BlockCarrier>, clock: u32) -> Option<usize> {
+        let i = blocks.partition_point(|b| b.id().clock + b.len() <= clock);
+        let block = blocks.get(i)?;
+        if block.id().clock <= clock && !block.is_skip() {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a block, which determines the parent of a given block: either its parent block
+    /// or one of its neighbors.
+    fn parent_dependency(block: &BlockCarrier) -> Result<Option<Arc<str>>, Option<ID>> {
+        match block {
+            BlockCarrier::Item(item) => match &item.parent {
+                TypePtr::Named(name) => Ok(Some(name.clone())),
+                TypePtr::ID(id) => Err(Some(*id)),
+                _ => Err(item.origin.or(item.right_origin)),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Resolves root-level collection names of all blocks of current update, keyed by their IDs.
+    fn resolve_roots(&self) -> HashMap<ID, Option<Arc<str>>> {
+        let mut roots: HashMap<ID, Option<Arc<str>>> = HashMap::new();
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+        for blocks in self.blocks.clients.values() {
+            for block in blocks.iter() {
+                if block.is_skip() || roots.contains_key(block.id()) {
+                    continue;
+                }
+                // follow dependencies until reaching a block with known root
+                let mut current = block;
+                let root = loop {
+                    path.push(*current.id());
+                    visited.insert(*current.id());
+                    match Self::parent_dependency(current) {
+                        Ok(root) => break root,
+                        Err(None) => break None,
+                        Err(Some(id)) => {
+                            let next = self
+                                .blocks
+                                .clients
+                                .get(&id.client)
+                                .and_then(|b| Some(&b[Self::find_block(b, id.clock)?]));
+                            match next {
+                                None => break None,
+                                Some(next) => {
+                                    if let Some(root) = roots.get(next.id()) {
+                                        break root.clone();
+                                    } else if visited.contains(next.id()) {
+                                        // malformed update
+                                        break None;
+                                    }
+                                    current = next;
+                                }
+                            }
+                        }
+                    }
+                };
+                visited.clear();
+                for id in path.drain(..) {
+                    roots.insert(id, root.clone());
+                }
+            }
+        }
+        roots
     }
 
     pub fn merge_updates<T>(block_stores: T) -> Update
@@ -536,6 +1070,7 @@ impl Update {
             .into_iter()
             .map(|update| {
                 result.delete_set.merge(update.delete_set);
+                result.meta.extend(update.meta);
                 update.blocks
             })
             .collect();
@@ -692,6 +1227,7 @@ impl Update {
         if let Some(block) = curr_write.take() {
             result.blocks.add_block(block);
         }
+        result.blocks.deduplicate();
 
         result
     }
@@ -709,7 +1245,7 @@ impl Decode for Update {
         // read blocks
         let clients_len: u32 = decoder.read_var()?;
         let mut clients = HashMap::with_hasher(BuildHasherDefault::default());
-        clients.try_reserve(clients_len as usize)?;
+        clients.try_reserve((clients_len as usize).min(MAX_PREALLOCATED_LEN))?;
 
         let mut blocks = UpdateBlocks { clients };
         for _ in 0..clients_len {
@@ -722,8 +1258,9 @@ impl Decode for Update {
                 .entry(client)
                 .or_insert_with(|| VecDeque::new());
             // Attempt to pre-allocate memory for the blocks. If the capacity overflows and
-            // allocation fails, return an error.
-            blocks.try_reserve(blocks_len)?;
+            // allocation fails, return an error. Length prefix comes from the wire, so don't
+            // trust it beyond a reasonable limit.
+            blocks.try_reserve(blocks_len.min(MAX_PREALLOCATED_LEN))?;
 
             for _ in 0..blocks_len {
                 let id = ID::new(client, clock);
@@ -731,14 +1268,48 @@ impl Decode for Update {
                 if block.len() > 0 {
                     // due to bug in the past it was possible for empty bugs to be generated
                     // even though they had no effect on the document store
-                    clock += block.len();
+                    clock = clock
+                        .checked_add(block.len())
+                        .ok_or(Error::IntegerOverflow("block clock"))?;
                     blocks.push_back(block);
                 }
             }
         }
         // read delete set
         let delete_set = DeleteSet::decode(decoder)?;
-        Ok(Update { blocks, delete_set })
+        // read optional metadata
+        let meta = decode_meta(decoder)?;
+        Ok(Update {
+            blocks,
+            delete_set,
+            meta,
+        })
+    }
+}
+
+/// Marker preceding update metadata. `0xFF 0x00` is never produced as a variable-length integer
+/// by lib0 encoders, so it cannot be confused with a beginning of another update written right
+/// after the current one.
+const META_MARKER: [u8; 2] = [0xFF, 0x00];
+
+/// Writes update metadata after the delete set. Empty metadata is not written at all, keeping
+/// update payload compatible with Yjs, which ignores any data trailing the delete set.
+pub(crate) fn encode_meta<E: Encoder>(meta: &HashMap<String, Any>, encoder: &mut E) {
+    if !meta.is_empty() {
+        encoder.write_u8(META_MARKER[0]);
+        encoder.write_u8(META_MARKER[1]);
+        encoder.write_any(&Any::from(meta.clone()));
+    }
+}
+
+/// Reads update metadata if it's present. Any other data trailing the delete set is left unread.
+fn decode_meta<D: Decoder>(decoder: &mut D) -> Result<HashMap<String, Any>, Error> {
+    if !decoder.read_marker(&META_MARKER) {
+        return Ok(HashMap::new());
+    }
+    match decoder.read_any()? {
+        Any::Map(meta) => Ok(Arc::try_unwrap(meta).unwrap_or_else(|meta| (*meta).clone())),
+        _ => Err(Error::UnexpectedValue),
     }
 }
 
@@ -849,6 +1420,10 @@ impl BlockCarrier {
                 a.merge(b);
                 true
             }
+            (BlockCarrier::GC(a), BlockCarrier::GC(b)) => {
+                a.merge(b);
+                true
+            }
             _ => false,
         }
     }
@@ -934,6 +1509,17 @@ pub struct PendingUpdate {
     pub missing: StateVector,
 }
 
+/// Result of integrating an [Update] into a document store.
+#[derive(Debug)]
+pub(crate) struct IntegrationOutcome {
+    /// Blocks which couldn't be integrated because of missing dependencies.
+    pub pending: Option<PendingUpdate>,
+    /// Deletions which couldn't be applied because of missing blocks.
+    pub pending_ds: Option<Update>,
+    /// Part of an update which was not processed because integration has been cancelled.
+    pub cancelled: Option<Update>,
+}
+
 impl std::fmt::Debug for Update {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         std::fmt::Display::fmt(self, f)
@@ -1061,13 +1647,184 @@ impl Iterator for IntoBlocks {
 
 #[cfg(test)]
 mod test {
-    use crate::block::{Item, ItemContent};
+    use crate::block::{BlockRange, Item, ItemContent};
     use crate::encoding::read::Cursor;
-    use crate::types::TypePtr;
-    use crate::update::{BlockCarrier, Update};
-    use crate::updates::decoder::{Decode, DecoderV1};
+    use crate::moving::Move;
+    use crate::types::text::YChange;
+    use crate::types::{Attrs, ToJson, TypePtr, Value};
+    use crate::update::{BlockCarrier, Update, UpdateError};
+    use crate::updates::decoder::{Decode, DecoderV1, StreamDecoderV1};
     use crate::updates::encoder::Encode;
-    use crate::{Doc, GetString, Options, Text, Transact, XmlFragment, XmlNode, ID};
+    use crate::{
+        any, Any, Array, Assoc, Doc, GetString, Map, MapPrelim, MapRef, Options, ReadTxn,
+        StateVector, StickyIndex, Text, Transact, XmlFragment, XmlNode, ID,
+    };
+
+    #[test]
+    fn update_meta() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        let mut txn = d1.transact_mut();
+        txn.set_meta("author", "alice");
+        txn.set_meta("version", 2);
+        t1.push(&mut txn, "hello");
+        let u1 = txn.encode_update_v1();
+        let u2 = txn.encode_update_v2();
+        drop(txn);
+
+        let mut txn = d1.transact_mut();
+        txn.set_meta("author", "bob");
+        t1.push(&mut txn, " world");
+        let u3 = txn.encode_update_v1();
+        drop(txn);
+
+        let u1 = Update::decode_v1(&u1).unwrap();
+        assert_eq!(u1.meta().get("author"), Some(&Any::from("alice")));
+        assert_eq!(u1.meta().get("version"), Some(&Any::from(2)));
+        assert_eq!(Update::decode_v2(&u2).unwrap().meta(), u1.meta());
+
+        // metadata survives merging and re-encoding
+        let merged = Update::merge_updates([u1, Update::decode_v1(&u3).unwrap()]);
+        let merged = Update::decode_v2(&merged.encode_v2()).unwrap();
+        assert_eq!(merged.meta().get("author"), Some(&Any::from("bob")));
+        assert_eq!(merged.meta().get("version"), Some(&Any::from(2)));
+
+        // metadata of applied updates is exposed on update events
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        let meta = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let m = meta.clone();
+        let _sub = d2
+            .observe_update_v1(move |_, e| *m.lock().unwrap() = Some(e.meta().clone()))
+            .unwrap();
+        d2.transact_mut().apply_update(merged).unwrap();
+        assert_eq!(t2.get_string(&d2.transact()), "hello world");
+        let meta = meta.lock().unwrap().take().unwrap();
+        assert_eq!(meta.get("author"), Some(&Any::from("bob")));
+
+        // updates without metadata stay compatible with decoders unaware of it
+        let plain = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        assert!(Update::decode_v1(&plain).unwrap().meta().is_empty());
+
+        // trailing data which is not metadata is ignored
+        let mut trailing = plain.clone();
+        trailing.extend_from_slice(&[1, 2, 3]);
+        let decoded = Update::decode_v1(&trailing).unwrap();
+        assert!(decoded.meta().is_empty());
+        assert_eq!(decoded, Update::decode_v1(&plain).unwrap());
+
+        // stream decoder doesn't consume updates following the one with metadata
+        let mut stream = u3.clone();
+        stream.extend_from_slice(&plain);
+        let mut decoder = StreamDecoderV1::new(stream.as_slice());
+        let first = Update::decode(&mut decoder).unwrap();
+        assert_eq!(first.meta().get("author"), Some(&Any::from("bob")));
+        let second = Update::decode(&mut decoder).unwrap();
+        assert!(second.meta().is_empty());
+        assert_eq!(second, Update::decode_v1(&plain).unwrap());
+    }
+
+    #[test]
+    fn strip_update_content() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        let map = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            text.push(&mut txn, "hello 🌍 world");
+            let bold = Attrs::from([("bold".into(), true.into())]);
+            text.format(&mut txn, 0, 5, bold);
+            text.remove_range(&mut txn, 0, 1);
+            map.insert(&mut txn, "name", "John");
+            map.insert(&mut txn, "blob", vec![1u8, 2, 3]);
+        }
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let stripped = Update::decode_v1(&update).unwrap().strip_content();
+
+        let d2 = Doc::with_client_id(2);
+        let text = d2.get_or_insert_text("text");
+        let map = d2.get_or_insert_map("map");
+        d2.transact_mut().apply_update(stripped).unwrap();
+
+        let txn = d2.transact();
+        assert_eq!(txn.state_vector(), d1.transact().state_vector());
+        assert_eq!(text.get_string(&txn), "x".repeat(13)); // emoji is 2 UTF-16 code units
+        assert_eq!(
+            text.diff(&txn, YChange::identity)[0].attributes,
+            Some(Box::new(Attrs::from([("bold".into(), true.into())])))
+        );
+        assert_eq!(map.get(&txn, "name"), Some(Value::Any(Any::Null)));
+        assert_eq!(map.get(&txn, "blob"), Some(Value::Any(Any::Null)));
+    }
+
+    #[test]
+    fn split_update_by_root() {
+        let d1 = Doc::with_client_id(1);
+        let meta = d1.get_or_insert_map("meta");
+        let body = d1.get_or_insert_text("body");
+        let list = d1.get_or_insert_array("list");
+        {
+            let mut txn = d1.transact_mut();
+            meta.insert(&mut txn, "title", "hello");
+            body.push(&mut txn, "hello world");
+            let nested = list.push_back(&mut txn, MapPrelim::<u32>::new());
+            nested.insert(&mut txn, "key", "value");
+            meta.insert(&mut txn, "title", "hello world");
+            body.remove_range(&mut txn, 0, 6);
+            list.push_front(&mut txn, 1);
+        }
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let parts = Update::decode_v1(&update).unwrap().split_by_root();
+        let mut keys: Vec<_> = parts.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                Some("body".into()),
+                Some("list".into()),
+                Some("meta".into())
+            ]
+        );
+
+        // partitions can be encoded and applied separately
+        let d2 = Doc::with_client_id(2);
+        for update in parts.into_values() {
+            let bin = update.encode_v1();
+            d2.transact_mut()
+                .apply_update(Update::decode_v1(&bin).unwrap())
+                .unwrap();
+        }
+        let txn = d2.transact();
+        assert!(txn.store().pending.is_none());
+        assert_eq!(
+            txn.get_text("body").unwrap().get_string(&txn),
+            "world".to_string()
+        );
+        assert_eq!(
+            txn.get_map("meta").unwrap().to_json(&txn),
+            meta.to_json(&d1.transact())
+        );
+        assert_eq!(txn.get_array("list").unwrap().len(&txn), 2);
+
+        let filtered = Update::decode_v1(&update)
+            .unwrap()
+            .filter_roots(&["meta", "body"]);
+        let d3 = Doc::with_client_id(3);
+        d3.transact_mut().apply_update(filtered).unwrap();
+        let txn = d3.transact();
+        assert!(txn.get_array("list").is_none());
+        assert_eq!(
+            txn.get_map("meta").unwrap().get(&txn, "title"),
+            Some("hello world".into())
+        );
+    }
 
     #[test]
     fn update_decode() {
@@ -1129,8 +1886,10 @@ mod test {
         let binary1 = t1.encode_update_v1();
         let binary2 = t2.encode_update_v1();
 
-        t1.apply_update(Update::decode_v1(binary2.as_slice()).unwrap());
-        t2.apply_update(Update::decode_v1(binary1.as_slice()).unwrap());
+        t1.apply_update(Update::decode_v1(binary2.as_slice()).unwrap())
+            .unwrap();
+        t2.apply_update(Update::decode_v1(binary1.as_slice()).unwrap())
+            .unwrap();
 
         let u1 = Update::decode(&mut DecoderV1::new(Cursor::new(binary1.as_slice()))).unwrap();
         let u2 = Update::decode(&mut DecoderV1::new(Cursor::new(binary2.as_slice()))).unwrap();
@@ -1142,7 +1901,7 @@ mod test {
         let d3 = Doc::with_client_id(3);
         let txt3 = d3.get_or_insert_text("test");
         let mut t3 = d3.transact_mut();
-        t3.apply_update(u12);
+        t3.apply_update(u12).unwrap();
 
         let str1 = txt1.get_string(&t1);
         let str2 = txt2.get_string(&t2);
@@ -1168,6 +1927,66 @@ mod test {
         assert_eq!(merged_update, u3);
     }
 
+    #[test]
+    fn merge_overlapping_updates() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut deltas = Vec::new();
+        for chunk in ["abc", "def", "ghi"] {
+            let sv = doc.transact().state_vector();
+            txt.push(&mut doc.transact_mut(), chunk);
+            deltas.push(doc.transact().encode_state_as_update_v1(&sv));
+        }
+        let full = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let merge = |updates: &[&Vec<u8>]| {
+            Update::merge_updates(updates.iter().map(|u| decode_update(u))).encode_v1()
+        };
+        let expected = merge(&[&deltas[0], &deltas[1], &deltas[2]]);
+
+        // duplicated updates are squashed
+        let merged = merge(&[
+            &deltas[0], &deltas[0], &deltas[1], &deltas[2], &deltas[1], &deltas[2],
+        ]);
+        assert_eq!(merged, expected);
+
+        // deltas overlapping with full document state don't make the result any larger
+        let merged = merge(&[&deltas[0], &full, &deltas[1], &deltas[2]]);
+        assert!(
+            merged.len() <= expected.len(),
+            "merged update has {} bytes, expected at most {}",
+            merged.len(),
+            expected.len()
+        );
+        let merged = merge(&[&merged, &full]);
+        assert!(merged.len() <= expected.len());
+
+        // items covered by skip ranges are not dropped
+        let mut blocks: Vec<_> = deltas
+            .iter()
+            .map(|u| decode_update(u).blocks.clients.remove(&1).unwrap())
+            .collect();
+        let mut update = Update::new();
+        update.blocks.add_block(blocks[0].pop_front().unwrap());
+        let skip = BlockRange::new(ID::new(1, 3), 9);
+        update.blocks.add_block(BlockCarrier::Skip(skip));
+        update.blocks.add_block(blocks[1].pop_front().unwrap());
+        update.blocks.add_block(blocks[2].pop_front().unwrap());
+        update.blocks.deduplicate();
+        let d3 = Doc::with_client_id(3);
+        let txt3 = d3.get_or_insert_text("test");
+        d3.transact_mut().apply_update(update).unwrap();
+        assert_eq!(txt3.get_string(&d3.transact()), "abcdefghi");
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("test");
+        d2.transact_mut()
+            .apply_update(decode_update(&merged))
+            .unwrap();
+        assert_eq!(txt2.get_string(&d2.transact()), "abcdefghi");
+    }
+
     #[test]
     fn test_multiple_clients_in_one_update() {
         let binary1 = {
@@ -1232,7 +2051,7 @@ mod test {
         {
             let mut txn = doc.transact_mut();
             let u = Update::decode_v2(&before).unwrap();
-            txn.apply_update(u);
+            txn.apply_update(u).unwrap();
             let linknote = prosemirror.get(&txn, 0);
             let actual = linknote.and_then(|xml| match xml {
                 XmlNode::Element(elem) => Some(elem.tag().clone()),
@@ -1243,7 +2062,7 @@ mod test {
         {
             let mut txn = doc.transact_mut();
             let u = Update::decode_v2(&update).unwrap();
-            txn.apply_update(u);
+            txn.apply_update(u).unwrap();
 
             // this should not panic
             let binary = txn.encode_update_v2();
@@ -1263,6 +2082,249 @@ mod test {
         assert_eq!(update_v1, update_v2);
     }
 
+    #[test]
+    fn update_decode_malformed() {
+        use crate::encoding::read::Error;
+        use crate::encoding::write::Write;
+
+        // declares u32::MAX clients, none of which are present
+        let mut buf = Vec::new();
+        buf.write_var(u32::MAX);
+        assert!(Update::decode_v1(&buf).is_err());
+
+        // block clocks overflowing u32 range
+        let mut buf = Vec::new();
+        buf.write_var(1u32); // clients count
+        buf.write_var(2u32); // blocks count
+        buf.write_var(1u64); // client id
+        buf.write_var(u32::MAX - 1); // starting clock
+        for _ in 0..2 {
+            buf.write_u8(0); // GC block
+            buf.write_var(2u32); // GC length
+        }
+        buf.write_var(0u32); // empty delete set
+        match Update::decode_v1(&buf) {
+            Err(Error::IntegerOverflow(_)) => { /* expected */ }
+            other => panic!("expected integer overflow, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_update_from_reader() {
+        /// Reader returning at most 3 bytes at once.
+        struct SlowReader<'a>(&'a [u8]);
+
+        impl<'a> std::io::Read for SlowReader<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(self.0.len()).min(3);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        for i in 0..100 {
+            let mut txn = doc.transact_mut();
+            text.insert(&mut txn, 0, "hello world");
+            map.insert(&mut txn, format!("key{}", i), "x".repeat(i * 100));
+            text.remove_range(&mut txn, 2, 3);
+        }
+        let mut file = Vec::new();
+        doc.transact()
+            .encode_state_as_update_to(&StateVector::default(), &mut file)
+            .unwrap();
+
+        let expected = Update::decode_v1(&file).unwrap();
+        let actual = Update::decode_v1_from(SlowReader(&file)).unwrap();
+        assert_eq!(actual, expected);
+
+        let actual = Update::decode_v1_from(file.as_slice()).unwrap();
+        assert_eq!(actual, expected);
+
+        let doc2 = Doc::with_client_id(2);
+        doc2.transact_mut().apply_update(actual).unwrap();
+        assert_eq!(
+            text.get_string(&doc.transact()),
+            doc2.get_or_insert_text("text").get_string(&doc2.transact())
+        );
+
+        let truncated = &file[..file.len() / 2];
+        assert!(matches!(
+            Update::decode_v1_from(SlowReader(truncated)),
+            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        ));
+    }
+
+    #[test]
+    fn apply_update_with_invalid_parent() {
+        // client 1 creates a nested map, client 2 inserts an entry into it
+        let d1 = Doc::with_client_id(1);
+        let list = d1.get_or_insert_array("list");
+        let nested = list.push_back(&mut d1.transact_mut(), MapPrelim::<u32>::new());
+        let d2 = Doc::with_client_id(2);
+        let u1 = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u1).unwrap())
+            .unwrap();
+        let nested2 = d2
+            .get_or_insert_array("list")
+            .get(&d2.transact(), 0)
+            .unwrap()
+            .cast::<MapRef>()
+            .unwrap();
+        nested2.insert(&mut d2.transact_mut(), "key", "value");
+        let u2 = d2
+            .transact()
+            .encode_state_as_update_v1(&d1.transact().state_vector());
+        drop(nested);
+
+        // on another peer, block 1#0 is a plain value, not a shared type
+        let d3 = Doc::with_client_id(1);
+        d3.get_or_insert_array("list")
+            .push_back(&mut d3.transact_mut(), 1);
+        let d4 = Doc::with_client_id(4);
+        let u3 = d3
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let mut txn = d4.transact_mut();
+        txn.apply_update(Update::decode_v1(&u3).unwrap()).unwrap();
+        let err = txn
+            .apply_update(Update::decode_v1(&u2).unwrap())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            UpdateError::InvalidParent(ID::new(2, 0), ID::new(1, 0))
+        );
+    }
+
+    #[test]
+    fn apply_update_with_move_of_another_collection() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.get_or_insert_text("text");
+        let list = d1.get_or_insert_array("list");
+        txt.push(&mut d1.transact_mut(), "abc");
+        list.push_back(&mut d1.transact_mut(), 1);
+
+        // block 2#0 is inserted into a list, but moves characters of a text
+        let mut update = Update::new();
+        update.blocks.add_block(
+            Item::new(
+                ID::new(2, 0),
+                None,
+                Some(ID::new(1, 3)),
+                None,
+                None,
+                TypePtr::Unknown,
+                None,
+                ItemContent::Move(Box::new(Move::new(
+                    StickyIndex::from_id(ID::new(1, 0), Assoc::After),
+                    StickyIndex::from_id(ID::new(1, 2), Assoc::After),
+                    -1,
+                ))),
+            )
+            .into(),
+        );
+        // block 2#1 depends on a malformed block, while 3#0 and a deletion of 1#0 don't
+        update.blocks.add_block(
+            Item::new(
+                ID::new(2, 1),
+                None,
+                Some(ID::new(2, 0)),
+                None,
+                None,
+                TypePtr::Unknown,
+                None,
+                ItemContent::Any(vec![Any::from(2)]),
+            )
+            .into(),
+        );
+        update.blocks.add_block(
+            Item::new(
+                ID::new(3, 0),
+                None,
+                Some(ID::new(1, 3)),
+                None,
+                None,
+                TypePtr::Unknown,
+                None,
+                ItemContent::Any(vec![Any::from(3)]),
+            )
+            .into(),
+        );
+        update.delete_set.insert(ID::new(1, 0), 1);
+        let mut txn = d1.transact_mut();
+        let err = txn.apply_update(update).unwrap_err();
+        assert_eq!(err, UpdateError::InvalidMove(ID::new(2, 0)));
+        assert_eq!(txt.get_string(&txn), "bc");
+        assert_eq!(list.to_json(&txn), any!([1, 3]));
+        let pending = txn.store().pending.as_ref().unwrap();
+        let missing: Vec<_> = pending.missing.iter().collect();
+        assert_eq!(missing, vec![(&2, &0)]);
+        let blocks = &pending.update.blocks.clients[&2];
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].id(), &ID::new(2, 1));
+    }
+
+    #[test]
+    fn apply_update_with_progress_cancelled() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        for client_id in 2..=4 {
+            let doc = Doc::with_client_id(client_id);
+            doc.get_or_insert_text("text")
+                .push(&mut doc.transact_mut(), "abc");
+            let u = doc
+                .transact()
+                .encode_state_as_update_v1(&StateVector::default());
+            source
+                .transact_mut()
+                .apply_update(Update::decode_v1(&u).unwrap())
+                .unwrap();
+        }
+        text.remove_range(&mut source.transact_mut(), 1, 4);
+        let expected = text.get_string(&source.transact());
+        let u = source
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let doc = Doc::with_client_id(5);
+        let text = doc.get_or_insert_text("text");
+        let mut reported = Vec::new();
+        let rest = doc
+            .transact_mut()
+            .apply_update_with_progress(Update::decode_v1(&u).unwrap(), |p| {
+                reported.push(p);
+                false
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(reported.len(), 1);
+        let total = reported[0].total;
+        assert!(reported[0].processed < total);
+        // delete set was not applied yet
+        assert!(!rest.delete_set.is_empty());
+
+        let mut reported = Vec::new();
+        let rest = doc
+            .transact_mut()
+            .apply_update_with_progress(rest, |p| {
+                reported.push(p);
+                true
+            })
+            .unwrap();
+        assert!(rest.is_none());
+        // progress of a resumed integration is relative to the remaining blocks
+        let last = reported.last().unwrap();
+        assert!(last.total < total);
+        assert_eq!(last.processed, last.total);
+        assert_eq!(text.get_string(&doc.transact()), expected);
+    }
+
     fn decode_update(bin: &[u8]) -> Update {
         Update::decode(&mut DecoderV1::new(Cursor::new(bin))).unwrap()
     }
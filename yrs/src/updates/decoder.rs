@@ -1,6 +1,7 @@
 use crate::block::ClientID;
 use crate::encoding::read::{Cursor, Error, Read};
 use crate::*;
+use std::convert::TryFrom;
 use std::sync::Arc;
 
 /// A trait that can be implemented by any other type in order to support lib0 decoding capability.
@@ -9,15 +10,37 @@ pub trait Decode: Sized {
 
     /// Helper function for decoding 1st version of lib0 encoding.
     fn decode_v1(data: &[u8]) -> Result<Self, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "decode_v1",
+            r#type = std::any::type_name::<Self>(),
+            bytes = data.len()
+        )
+        .entered();
         let mut decoder = DecoderV1::from(data);
         Self::decode(&mut decoder)
     }
 
     /// Helper function for decoding 2nd version of lib0 encoding.
     fn decode_v2(data: &[u8]) -> Result<Self, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "decode_v2",
+            r#type = std::any::type_name::<Self>(),
+            bytes = data.len()
+        )
+        .entered();
         let mut decoder = DecoderV2::new(Cursor::new(data))?;
         Self::decode(&mut decoder)
     }
+
+    /// Helper function for decoding 1st version of lib0 encoding directly from a given `reader`
+    /// (eg. file or network socket), without loading the whole payload into memory first.
+    /// See [StreamDecoderV1] for details.
+    fn decode_v1_from<R: std::io::Read>(reader: R) -> Result<Self, Error> {
+        let mut decoder = StreamDecoderV1::new(reader);
+        Self::decode(&mut decoder)
+    }
 }
 
 /// Trait used by lib0 decoders. Natively lib0 encoding supports two versions:
@@ -70,6 +93,14 @@ pub trait Decoder: Read {
 
     /// Consume a rest of the decoded buffer data and return it without parsing.
     fn read_to_end(&mut self) -> Result<&[u8], Error>;
+
+    /// Checks if the remaining data starts with a given `marker` and consumes it if so. Otherwise
+    /// decoder position is left untouched. Used to detect optional sections (like update metadata),
+    /// which may follow the encoded payload. By default no markers are recognized.
+    fn read_marker(&mut self, marker: &[u8]) -> bool {
+        let _ = marker;
+        false
+    }
 }
 
 /// Version 1 of lib0 decoder.
@@ -187,6 +218,182 @@ impl<'a> Decoder for DecoderV1<'a> {
     fn read_to_end(&mut self) -> Result<&[u8], Error> {
         Ok(&self.cursor.buf[self.cursor.next..])
     }
+
+    fn read_marker(&mut self, marker: &[u8]) -> bool {
+        if self.cursor.buf[self.cursor.next..].starts_with(marker) {
+            self.cursor.next += marker.len();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Version 1 of lib0 decoder, which reads data directly from a given `reader` (eg. file or network
+/// socket) using an internal buffer, which only grows as much as needed to decode a single value.
+/// It's a counterpart of [StreamEncoderV1](crate::updates::encoder::StreamEncoderV1).
+///
+/// Decoder may read ahead more bytes from the `reader` than necessary to decode a value.
+pub struct StreamDecoderV1<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// Position of the next unread byte in `buf`.
+    next: usize,
+    /// End of the data read into `buf` so far.
+    end: usize,
+}
+
+impl<R: std::io::Read> StreamDecoderV1<R> {
+    /// Number of bytes by which the internal buffer grows, when more space is needed.
+    const CHUNK_SIZE: usize = 8 * 1024;
+
+    /// Creates a new decoder, which will read encoded data from a given `reader`.
+    pub fn new(reader: R) -> Self {
+        StreamDecoderV1 {
+            reader,
+            buf: Vec::new(),
+            next: 0,
+            end: 0,
+        }
+    }
+
+    /// Makes sure that at least `len` unread bytes are available in the internal buffer.
+    fn fill(&mut self, len: usize) -> Result<(), Error> {
+        if self.end - self.next >= len {
+            return Ok(());
+        }
+        if self.next > 0 {
+            self.buf.copy_within(self.next..self.end, 0);
+            self.end -= self.next;
+            self.next = 0;
+        }
+        while self.end < len {
+            if self.end == self.buf.len() {
+                // buffer grows only when it's full, so that a malicious length prefix cannot
+                // cause an enormous allocation without providing the data to fill it
+                self.buf.resize(self.end + Self::CHUNK_SIZE, 0);
+            }
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => return Err(Error::EndOfBuffer(len)),
+                Ok(n) => self.end += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => { /* retry */ }
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn read_id(&mut self) -> Result<ID, Error> {
+        let client: u32 = self.read_var()?;
+        let clock = self.read_var()?;
+        Ok(ID::new(client as ClientID, clock))
+    }
+}
+
+impl<R: std::io::Read> Read for StreamDecoderV1<R> {
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        self.fill(1)?;
+        let b = self.buf[self.next];
+        self.next += 1;
+        Ok(b)
+    }
+
+    fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
+        self.fill(len)?;
+        let start = self.next;
+        self.next += len;
+        Ok(&self.buf[start..self.next])
+    }
+}
+
+impl<R: std::io::Read> Decoder for StreamDecoderV1<R> {
+    #[inline]
+    fn reset_ds_cur_val(&mut self) {
+        /* no op */
+    }
+
+    #[inline]
+    fn read_ds_clock(&mut self) -> Result<u32, Error> {
+        self.read_var()
+    }
+
+    #[inline]
+    fn read_ds_len(&mut self) -> Result<u32, Error> {
+        self.read_var()
+    }
+
+    #[inline]
+    fn read_left_id(&mut self) -> Result<ID, Error> {
+        self.read_id()
+    }
+
+    #[inline]
+    fn read_right_id(&mut self) -> Result<ID, Error> {
+        self.read_id()
+    }
+
+    #[inline]
+    fn read_client(&mut self) -> Result<ClientID, Error> {
+        let client: u32 = self.read_var()?;
+        Ok(client as ClientID)
+    }
+
+    #[inline]
+    fn read_info(&mut self) -> Result<u8, Error> {
+        self.read_u8()
+    }
+
+    #[inline]
+    fn read_parent_info(&mut self) -> Result<bool, Error> {
+        let info: u32 = self.read_var()?;
+        Ok(info == 1)
+    }
+
+    #[inline]
+    fn read_type_ref(&mut self) -> Result<u8, Error> {
+        self.read_u8()
+    }
+
+    #[inline]
+    fn read_len(&mut self) -> Result<u32, Error> {
+        self.read_var()
+    }
+
+    #[inline]
+    fn read_any(&mut self) -> Result<Any, Error> {
+        Any::decode(self)
+    }
+
+    fn read_json(&mut self) -> Result<Any, Error> {
+        let src = self.read_string()?;
+        Any::from_json(src)
+    }
+
+    #[inline]
+    fn read_key(&mut self) -> Result<Arc<str>, Error> {
+        let str: Arc<str> = self.read_string()?.into();
+        Ok(str)
+    }
+
+    fn read_to_end(&mut self) -> Result<&[u8], Error> {
+        self.buf.truncate(self.end);
+        self.reader.read_to_end(&mut self.buf)?;
+        let start = self.next;
+        self.end = self.buf.len();
+        self.next = self.end;
+        Ok(&self.buf[start..])
+    }
+
+    /// Stream decoder only checks the data which has been already read ahead from the underlying
+    /// reader, so that it never blocks waiting for bytes which may not belong to a decoded value.
+    fn read_marker(&mut self, marker: &[u8]) -> bool {
+        if self.buf[self.next..self.end].starts_with(marker) {
+            self.next += marker.len();
+            true
+        } else {
+            false
+        }
+    }
 }
 
 /// Version 2 of lib0 decoder.
@@ -244,15 +451,12 @@ impl<'a> DecoderV2<'a> {
     }
 
     fn read_usize(buf: &[u8], idx: &mut usize) -> Result<usize, Error> {
-        if *idx >= buf.len() {
-            return Err(Error::InvalidVarInt);
-        }
         let mut num: usize = 0;
-        let mut len: usize = 0;
+        let mut len: u32 = 0;
         loop {
-            let r = buf[*idx];
+            let r = *buf.get(*idx).ok_or(Error::InvalidVarInt)?;
             *idx += 1;
-            num |= (r as usize & 127) << len;
+            num |= usize::wrapping_shl(r as usize & 127, len);
             len += 7;
             if r < 128 {
                 return Ok(num);
@@ -266,13 +470,12 @@ impl<'a> DecoderV2<'a> {
     fn read_buf(buf: &'a [u8], idx: &mut usize) -> Result<&'a [u8], Error> {
         let len = Self::read_usize(buf, idx)?;
         let start = *idx;
-        let end = start + len;
-        if end <= buf.len() {
-            let slice = &buf[start..end];
-            *idx += len as usize;
-            Ok(slice)
-        } else {
-            Err(Error::EndOfBuffer(len))
+        match start.checked_add(len) {
+            Some(end) if end <= buf.len() => {
+                *idx = end;
+                Ok(&buf[start..end])
+            }
+            _ => Err(Error::EndOfBuffer(len)),
         }
     }
 }
@@ -300,13 +503,24 @@ impl<'a> Decoder for DecoderV2<'a> {
     }
 
     fn read_ds_clock(&mut self) -> Result<u32, Error> {
-        self.ds_curr_val += self.cursor.read_var::<u32>()?;
+        let diff = self.cursor.read_var::<u32>()?;
+        self.ds_curr_val = self
+            .ds_curr_val
+            .checked_add(diff)
+            .ok_or(Error::IntegerOverflow("delete set clock"))?;
         Ok(self.ds_curr_val)
     }
 
     fn read_ds_len(&mut self) -> Result<u32, Error> {
-        let diff = self.cursor.read_var::<u32>()? + 1;
-        self.ds_curr_val += diff;
+        let diff = self
+            .cursor
+            .read_var::<u32>()?
+            .checked_add(1)
+            .ok_or(Error::IntegerOverflow("delete set range length"))?;
+        self.ds_curr_val = self
+            .ds_curr_val
+            .checked_add(diff)
+            .ok_or(Error::IntegerOverflow("delete set clock"))?;
         Ok(diff)
     }
 
@@ -367,6 +581,15 @@ impl<'a> Decoder for DecoderV2<'a> {
     fn read_to_end(&mut self) -> Result<&[u8], Error> {
         Ok(&self.cursor.buf[self.cursor.next..])
     }
+
+    fn read_marker(&mut self, marker: &[u8]) -> bool {
+        if self.cursor.buf[self.cursor.next..].starts_with(marker) {
+            self.cursor.next += marker.len();
+            true
+        } else {
+            false
+        }
+    }
 }
 
 struct IntDiffOptRleDecoder<'a> {
@@ -393,12 +616,15 @@ impl<'a> IntDiffOptRleDecoder<'a> {
             let has_count = diff & 1;
             self.diff = (diff >> 1) as i32;
             self.count = if has_count != 0 {
-                self.cursor.read_var::<u32>()? + 2
+                self.cursor
+                    .read_var::<u32>()?
+                    .checked_add(2)
+                    .ok_or(Error::IntegerOverflow("run-length count"))?
             } else {
                 1
             };
         }
-        self.last = ((self.last as i32) + self.diff) as u32;
+        self.last = (self.last as i32).wrapping_add(self.diff) as u32;
         self.count -= 1;
         Ok(self.last)
     }
@@ -425,8 +651,12 @@ impl<'a> UIntOptRleDecoder<'a> {
             // if the sign is negative, we read the count too, otherwise count is 1
             let is_negative = s.is_negative();
             if is_negative {
-                self.count = self.cursor.read_var::<u32>()? + 2;
-                self.last = (-s.value()) as u64;
+                self.count = self
+                    .cursor
+                    .read_var::<u32>()?
+                    .checked_add(2)
+                    .ok_or(Error::IntegerOverflow("run-length count"))?;
+                self.last = s.value().wrapping_neg() as u64;
             } else {
                 self.count = 1;
                 self.last = s.value() as u64;
@@ -456,7 +686,11 @@ impl<'a> RleDecoder<'a> {
         if self.count == 0 {
             self.last = self.cursor.read_u8()?;
             if self.cursor.has_content() {
-                self.count = (self.cursor.read_var::<u32>()? as i32) + 1; // see encoder implementation for the reason why this is incremented
+                // see encoder implementation for the reason why this is incremented
+                self.count = i32::try_from(self.cursor.read_var::<u32>()?)
+                    .ok()
+                    .and_then(|count| count.checked_add(1))
+                    .ok_or(Error::IntegerOverflow("run-length count"))?;
             } else {
                 self.count = -1; // read the current value forever
             }
@@ -477,7 +711,7 @@ impl<'a> StringDecoder<'a> {
         let buf = cursor.buf;
         let mut next = cursor.next;
         let str_bin = DecoderV2::read_buf(buf, &mut next)?;
-        let str = unsafe { std::str::from_utf8_unchecked(str_bin) };
+        let str = std::str::from_utf8(str_bin)?;
         let len_decoder = UIntOptRleDecoder::new(Cursor { buf, next });
         Ok(StringDecoder {
             pos: 0,
@@ -495,7 +729,7 @@ impl<'a> StringDecoder<'a> {
                 break;
             }
             i += c.len_utf8(); // rust uses offsets as utf-8 bytes
-            remaining -= c.len_utf16(); // but yjs provides them as utf-16
+            remaining = remaining.saturating_sub(c.len_utf16()); // but yjs provides them as utf-16
         }
         let result = &start[..i];
         self.pos += i;
@@ -3,6 +3,7 @@ use crate::encoding::varint::Signed;
 use crate::encoding::write::Write;
 use crate::*;
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 /// A trait that can be implemented by any other type in order to support lib0 encoding capability.
 pub trait Encode {
@@ -10,16 +11,28 @@ pub trait Encode {
 
     /// Helper function for encoding 1st version of lib0 encoding.
     fn encode_v1(&self) -> Vec<u8> {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("encode_v1", r#type = std::any::type_name::<Self>()).entered();
         let mut encoder = EncoderV1::new();
         self.encode(&mut encoder);
-        encoder.to_vec()
+        let bytes = encoder.to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "encoded");
+        bytes
     }
 
     /// Helper function for encoding 1st version of lib0 encoding.
     fn encode_v2(&self) -> Vec<u8> {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("encode_v2", r#type = std::any::type_name::<Self>()).entered();
         let mut encoder = EncoderV2::new();
         self.encode(&mut encoder);
-        encoder.to_vec()
+        let bytes = encoder.to_vec();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(bytes = bytes.len(), "encoded");
+        bytes
     }
 }
 
@@ -33,6 +46,15 @@ pub trait Encoder: Write {
     /// Consume current encoder and return a binary with all data encoded so far.
     fn to_vec(self) -> Vec<u8>;
 
+    /// Append all data encoded so far at the end of a given `buf` and [reset](Encoder::reset)
+    /// current encoder, so that it can be reused. Unlike [Encoder::to_vec] this method keeps the
+    /// memory already allocated by the encoder.
+    fn drain_into(&mut self, buf: &mut Vec<u8>);
+
+    /// Clear all data encoded so far, returning encoder to its initial state while keeping
+    /// its allocated memory for reuse.
+    fn reset(&mut self);
+
     /// Reset the state of currently encoded [DeleteSet].
     fn reset_ds_cur_val(&mut self);
 
@@ -88,6 +110,11 @@ impl EncoderV1 {
         }
     }
 
+    /// Creates a new encoder, which will append all encoded data at the end of a given `buf`.
+    pub fn with_buffer(buf: Vec<u8>) -> Self {
+        EncoderV1 { buf }
+    }
+
     fn write_id(&mut self, id: &ID) {
         self.write_var(id.client);
         self.write_var(id.clock)
@@ -106,12 +133,29 @@ impl Write for EncoderV1 {
     }
 }
 
+impl Default for EncoderV1 {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Encoder for EncoderV1 {
     #[inline]
     fn to_vec(self) -> Vec<u8> {
         self.buf
     }
 
+    fn drain_into(&mut self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.buf);
+        self.reset();
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.buf.clear();
+    }
+
     #[inline]
     fn reset_ds_cur_val(&mut self) {
         /* no op */
@@ -179,6 +223,170 @@ impl Encoder for EncoderV1 {
     }
 }
 
+/// Encoder using lib0 v1 encoding, which - instead of building an entire payload in memory - writes
+/// encoded data directly into a given `writer` (eg. file or network socket) in chunks of up to
+/// [StreamEncoderV1::DEFAULT_CHUNK_SIZE] bytes. Once all data has been encoded,
+/// [StreamEncoderV1::finish] must be called to flush the remaining bytes.
+///
+/// Since [Encoder] methods are infallible, the first I/O error is stored and returned by
+/// [StreamEncoderV1::finish]. All data encoded after an error occurred is discarded.
+///
+/// Methods of [Encoder] trait operating on the encoded payload ([Encoder::to_vec],
+/// [Encoder::drain_into]) only return bytes, which have not been written into `writer` yet.
+pub struct StreamEncoderV1<W> {
+    inner: EncoderV1,
+    writer: W,
+    chunk_size: usize,
+    error: Option<std::io::Error>,
+}
+
+impl<W: std::io::Write> StreamEncoderV1<W> {
+    /// Default number of bytes buffered before being written into an underlying writer.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Creates a new encoder, which will write encoded data into a given `writer`.
+    pub fn new(writer: W) -> Self {
+        Self::with_chunk_size(writer, Self::DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates a new encoder, which will buffer up to `chunk_size` bytes before writing them into
+    /// a given `writer`.
+    pub fn with_chunk_size(writer: W, chunk_size: usize) -> Self {
+        StreamEncoderV1 {
+            inner: EncoderV1::with_buffer(Vec::with_capacity(chunk_size.min(1024 * 1024))),
+            writer,
+            chunk_size,
+            error: None,
+        }
+    }
+
+    /// Writes all remaining data into an underlying writer and flushes it. Returns the writer or
+    /// the first I/O error, which occurred during encoding.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.write_chunk();
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    #[inline]
+    fn try_write_chunk(&mut self) {
+        if self.inner.buf.len() >= self.chunk_size {
+            self.write_chunk();
+        }
+    }
+
+    fn write_chunk(&mut self) {
+        if self.error.is_none() && !self.inner.buf.is_empty() {
+            if let Err(e) = self.writer.write_all(&self.inner.buf) {
+                self.error = Some(e);
+            }
+        }
+        self.inner.reset();
+    }
+}
+
+impl<W: std::io::Write> Write for StreamEncoderV1<W> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) {
+        self.inner.write_all(buf);
+        self.try_write_chunk();
+    }
+
+    #[inline]
+    fn write_u8(&mut self, value: u8) {
+        self.inner.write_u8(value);
+        self.try_write_chunk();
+    }
+}
+
+impl<W: std::io::Write> Encoder for StreamEncoderV1<W> {
+    #[inline]
+    fn to_vec(self) -> Vec<u8> {
+        self.inner.to_vec()
+    }
+
+    #[inline]
+    fn drain_into(&mut self, buf: &mut Vec<u8>) {
+        self.inner.drain_into(buf)
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    #[inline]
+    fn reset_ds_cur_val(&mut self) {
+        /* no op */
+    }
+
+    #[inline]
+    fn write_ds_clock(&mut self, clock: u32) {
+        self.write_var(clock)
+    }
+
+    #[inline]
+    fn write_ds_len(&mut self, len: u32) {
+        self.write_var(len)
+    }
+
+    #[inline]
+    fn write_left_id(&mut self, id: &ID) {
+        self.write_var(id.client);
+        self.write_var(id.clock)
+    }
+
+    #[inline]
+    fn write_right_id(&mut self, id: &ID) {
+        self.write_var(id.client);
+        self.write_var(id.clock)
+    }
+
+    #[inline]
+    fn write_client(&mut self, client: ClientID) {
+        self.write_var(client)
+    }
+
+    #[inline]
+    fn write_info(&mut self, info: u8) {
+        self.write_u8(info)
+    }
+
+    #[inline]
+    fn write_parent_info(&mut self, is_y_key: bool) {
+        self.write_var(u32::from(is_y_key))
+    }
+
+    #[inline]
+    fn write_type_ref(&mut self, info: u8) {
+        self.write_u8(info)
+    }
+
+    #[inline]
+    fn write_len(&mut self, len: u32) {
+        self.write_var(len)
+    }
+
+    #[inline]
+    fn write_any(&mut self, any: &Any) {
+        any.encode(self)
+    }
+
+    fn write_json(&mut self, any: &Any) {
+        let mut buf = String::new();
+        any.to_json(&mut buf);
+        self.write_string(buf.as_str())
+    }
+
+    #[inline]
+    fn write_key(&mut self, key: &str) {
+        self.write_string(key)
+    }
+}
+
 pub struct EncoderV2 {
     key_table: HashMap<String, u32>,
     buf: Vec<u8>,
@@ -215,6 +423,13 @@ impl EncoderV2 {
     }
 }
 
+impl Default for EncoderV2 {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Write for EncoderV2 {
     #[inline]
     fn write_all(&mut self, buf: &[u8]) {
@@ -233,32 +448,43 @@ impl Write for EncoderV2 {
 }
 
 impl Encoder for EncoderV2 {
-    fn to_vec(self) -> Vec<u8> {
-        let key_clock = self.key_clock_encoder.to_vec();
-        let client = self.client_encoder.to_vec();
-        let left_clock = self.left_clock_encoder.to_vec();
-        let right_clock = self.right_clock_encoder.to_vec();
-        let info = self.info_encoder.to_vec();
-        let string = self.string_encoder.to_vec();
-        let parent_info = self.parent_info_encoder.to_vec();
-        let type_ref = self.type_ref_encoder.to_vec();
-        let len = self.len_encoder.to_vec();
-        let rest = self.buf;
+    fn to_vec(mut self) -> Vec<u8> {
         let mut buf = Vec::new();
-        buf.write_u8(0); // this is a feature flag that we might use in the future
-        buf.write_buf(key_clock);
-        buf.write_buf(client);
-        buf.write_buf(left_clock);
-        buf.write_buf(right_clock);
-        buf.write_buf(info);
-        buf.write_buf(string);
-        buf.write_buf(parent_info);
-        buf.write_buf(type_ref);
-        buf.write_buf(len);
-        buf.write_all(rest.as_slice());
+        self.drain_into(&mut buf);
         buf
     }
 
+    fn drain_into(&mut self, buf: &mut Vec<u8>) {
+        buf.write_u8(0); // this is a feature flag that we might use in the future
+        buf.write_buf(self.key_clock_encoder.flush());
+        buf.write_buf(self.client_encoder.flush());
+        buf.write_buf(self.left_clock_encoder.flush());
+        buf.write_buf(self.right_clock_encoder.flush());
+        buf.write_buf(self.info_encoder.flush());
+        self.string_encoder.write_to(buf);
+        buf.write_buf(self.parent_info_encoder.flush());
+        buf.write_buf(self.type_ref_encoder.flush());
+        buf.write_buf(self.len_encoder.flush());
+        buf.write_all(self.buf.as_slice());
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.key_table.clear();
+        self.buf.clear();
+        self.ds_curr_val = 0;
+        self.seqeuncer = 0;
+        self.key_clock_encoder.reset();
+        self.client_encoder.reset();
+        self.left_clock_encoder.reset();
+        self.right_clock_encoder.reset();
+        self.info_encoder.reset();
+        self.string_encoder.reset();
+        self.parent_info_encoder.reset();
+        self.type_ref_encoder.reset();
+        self.len_encoder.reset();
+    }
+
     #[inline]
     fn reset_ds_cur_val(&mut self) {
         self.ds_curr_val = 0;
@@ -373,14 +599,14 @@ impl IntDiffOptRleEncoder {
             self.last = value;
             self.count += 1;
         } else {
-            self.flush();
+            self.flush_pending();
             self.count = 1;
             self.diff = diff;
             self.last = value;
         }
     }
 
-    fn flush(&mut self) {
+    fn flush_pending(&mut self) {
         if self.count > 0 {
             // 31 bit making up the diff | wether to write the counter
             let encode_diff = self.diff << 1 | (if self.count == 1 { 0 } else { 1 });
@@ -394,9 +620,18 @@ impl IntDiffOptRleEncoder {
         }
     }
 
-    fn to_vec(mut self) -> Vec<u8> {
-        self.flush();
-        self.buf
+    /// Writes pending data and returns all data encoded so far.
+    fn flush(&mut self) -> &[u8] {
+        self.flush_pending();
+        self.count = 0;
+        &self.buf
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.last = 0;
+        self.count = 0;
+        self.diff = 0;
     }
 }
 
@@ -425,18 +660,26 @@ impl UIntOptRleEncoder {
         if self.last == value {
             self.count += 1;
         } else {
-            self.flush();
+            self.flush_pending();
             self.count = 1;
             self.last = value;
         }
     }
 
-    fn to_vec(mut self) -> Vec<u8> {
-        self.flush();
-        self.buf
+    /// Writes pending data and returns all data encoded so far.
+    fn flush(&mut self) -> &[u8] {
+        self.flush_pending();
+        self.count = 0;
+        &self.buf
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.last = 0;
+        self.count = 0;
     }
 
-    fn flush(&mut self) {
+    fn flush_pending(&mut self) {
         if self.count > 0 {
             // flush counter, unless this is the first value (count = 0)
             // case 1: just a single value. set sign to positive
@@ -486,8 +729,15 @@ impl RleEncoder {
         }
     }
 
-    fn to_vec(self) -> Vec<u8> {
-        self.buf
+    /// Returns all data encoded so far.
+    fn flush(&mut self) -> &[u8] {
+        &self.buf
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.last = None;
+        self.count = 0;
     }
 }
 
@@ -518,11 +768,121 @@ impl StringEncoder {
         self.len_encoder.write_u64(utf16_len as u64);
     }
 
-    fn to_vec(self) -> Vec<u8> {
-        let lengths = self.len_encoder.to_vec();
-        let mut buf = Vec::with_capacity(self.buf.len() + lengths.len());
+    /// Writes all data encoded so far into a given `buf` as a single length-prefixed binary.
+    fn write_to(&mut self, buf: &mut Vec<u8>) {
+        let lengths = self.len_encoder.flush();
+        let str_len = self.buf.len();
+        buf.write_var(var_len(str_len) + str_len + lengths.len());
         buf.write_string(&self.buf);
-        buf.write_all(lengths.as_slice());
-        buf
+        buf.write_all(lengths);
+    }
+
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.len_encoder.reset();
+    }
+}
+
+/// Returns a number of bytes needed to write a given `value` using variable length encoding.
+fn var_len(mut value: usize) -> usize {
+    let mut len = 1;
+    while value >= 0b1000_0000 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+/// A pool of reusable lib0 encoders. Encoding a document state allocates multiple buffers,
+/// which can be costly when performed many times per second (eg. by servers broadcasting updates
+/// to many peers). [EncoderPool] allows to amortize these allocations by reusing encoders
+/// together with the memory they have already allocated.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::{Doc, Text, Transact, ReadTxn, StateVector};
+/// use yrs::updates::encoder::{Encoder, EncoderPool, EncoderV1};
+///
+/// let pool: EncoderPool<EncoderV1> = EncoderPool::new(8);
+/// let doc = Doc::new();
+/// let text = doc.get_or_insert_text("text");
+/// text.push(&mut doc.transact_mut(), "hello");
+///
+/// let mut update = Vec::new();
+/// pool.with_encoder(|encoder| {
+///     let txn = doc.transact();
+///     txn.encode_state_as_update(&StateVector::default(), encoder);
+///     encoder.drain_into(&mut update);
+/// });
+///
+/// assert_eq!(update, doc.transact().encode_state_as_update_v1(&StateVector::default()));
+/// ```
+pub struct EncoderPool<E> {
+    encoders: Mutex<Vec<E>>,
+    max_size: usize,
+}
+
+impl<E: Encoder + Default> EncoderPool<E> {
+    /// Creates a new pool, which will keep at most `max_size` idle encoders around.
+    pub fn new(max_size: usize) -> Self {
+        EncoderPool {
+            encoders: Mutex::new(Vec::with_capacity(max_size)),
+            max_size,
+        }
+    }
+
+    /// Borrows an encoder from the pool (or creates a new one if pool is empty) and passes it into
+    /// a given function `f`. Once `f` completes, encoder is [reset](Encoder::reset) and returned
+    /// back to the pool. Use [Encoder::drain_into] to retrieve the encoded data.
+    pub fn with_encoder<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut E) -> T,
+    {
+        let mut encoder = self.take();
+        let result = f(&mut encoder);
+        self.release(encoder);
+        result
+    }
+
+    /// Encodes data using an encoder from the pool and writes the result at the end of a given
+    /// `buf`.
+    pub fn encode_into<F>(&self, buf: &mut Vec<u8>, f: F)
+    where
+        F: FnOnce(&mut E),
+    {
+        self.with_encoder(|encoder| {
+            f(encoder);
+            encoder.drain_into(buf);
+        })
+    }
+
+    /// Returns a number of idle encoders currently held by the pool.
+    pub fn len(&self) -> usize {
+        self.encoders.lock().unwrap().len()
+    }
+
+    /// Checks if there are no idle encoders held by the pool.
+    pub fn is_empty(&self) -> bool {
+        self.encoders.lock().unwrap().is_empty()
+    }
+
+    fn take(&self) -> E {
+        let mut encoders = self.encoders.lock().unwrap();
+        encoders.pop().unwrap_or_default()
+    }
+
+    fn release(&self, mut encoder: E) {
+        encoder.reset();
+        let mut encoders = self.encoders.lock().unwrap();
+        if encoders.len() < self.max_size {
+            encoders.push(encoder);
+        }
+    }
+}
+
+impl<E: Encoder + Default> Default for EncoderPool<E> {
+    fn default() -> Self {
+        Self::new(16)
     }
 }
@@ -7,6 +7,7 @@ use gloo_utils::format::JsValueSerdeExt;
 use std::iter::FromIterator;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
+use yrs::observer::SubscriptionKey;
 use yrs::types::array::ArrayEvent;
 use yrs::types::{ToJson, TYPE_REFS_ARRAY};
 use yrs::{Array, ArrayRef, DeepObservable, Observable, Quotable, SharedRef, TransactionMut};
@@ -33,6 +34,23 @@ use yrs::{Array, ArrayRef, DeepObservable, Observable, Quotable, SharedRef, Tran
 #[repr(transparent)]
 pub struct YArray(pub(crate) SharedCollection<Vec<JsValue>, ArrayRef>);
 
+impl YArray {
+    pub(crate) fn to_plain(&self, txn: &ImplicitTransaction) -> Result<JsValue> {
+        match &self.0 {
+            SharedCollection::Prelim(c) => {
+                let a = js_sys::Array::new();
+                for js in c.iter() {
+                    a.push(&Js::to_plain(js, txn)?);
+                }
+                Ok(a.into())
+            }
+            SharedCollection::Integrated(c) => {
+                c.readonly(txn, |c, txn| Ok(Js::from_any(&c.to_json(txn)).into()))
+            }
+        }
+    }
+}
+
 #[wasm_bindgen]
 impl YArray {
     /// Creates a new preliminary instance of a `YArray` shared data type, with its state
@@ -107,6 +125,19 @@ impl YArray {
         }
     }
 
+    /// Converts contents of this `YArray` instance into their JSON representation. If `options`
+    /// object has a `plain: true` flag set, returned value is guaranteed to consist only of plain
+    /// JS objects, arrays and primitives (no wasm-backed handles), which makes it safe to send via
+    /// `postMessage` or store in IndexedDB.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(&self, options: JsValue, txn: &ImplicitTransaction) -> Result<JsValue> {
+        if Js::plain_option(&options) {
+            self.to_plain(txn)
+        } else {
+            self.to_json(txn)
+        }
+    }
+
     /// Inserts a given range of `items` into this `YArray` instance, starting at given `index`.
     #[wasm_bindgen(js_name = insert)]
     pub fn insert(
@@ -276,12 +307,11 @@ impl YArray {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_with(abi, move |txn, e| {
+                array.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YArrayEvent::new(e, txn);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e.into(), &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e.into(), &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -298,7 +328,7 @@ impl YArray {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(array.unobserve(abi))
+                Ok(array.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -316,12 +346,11 @@ impl YArray {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_deep_with(abi, move |txn, e| {
+                array.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -338,7 +367,7 @@ impl YArray {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(array.unobserve_deep(abi))
+                Ok(array.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -358,6 +387,7 @@ pub(crate) trait ArrayExt: Array + SharedRef {
                 ValueRef::Any(any) => primitive.push(any),
                 ValueRef::Shared(shared) => {
                     if shared.prelim() {
+                        shared.validate_prelim()?;
                         let len = primitive.len() as u32;
                         if len > 0 {
                             self.insert_range(txn, j, std::mem::take(&mut primitive));
@@ -438,6 +468,7 @@ impl YArrayEvent {
     /// - { insert: any[] }
     /// - { delete: number }
     /// - { retain: number }
+    /// - { move: number, from: number, to: number }
     #[wasm_bindgen(getter)]
     pub fn delta(&mut self) -> JsValue {
         let inner = &self.inner;
@@ -453,4 +484,19 @@ impl YArrayEvent {
         });
         js.clone()
     }
+
+    /// Returns a plain JS object representing current event in a shape compatible with yjs
+    /// `YEvent` (with `target`, `path`, `changes.delta`, `changes.keys`, `keysChanged` and
+    /// `transaction.origin` fields), which can be passed to editor bindings written for yjs.
+    /// `currentTarget` is always the same as `target`.
+    #[wasm_bindgen(js_name = toYjs)]
+    pub fn to_yjs(&mut self) -> crate::Result<JsValue> {
+        crate::js::convert::yjs_event_into_js(
+            self.target(),
+            self.path(),
+            self.delta(),
+            JsValue::UNDEFINED,
+            self.origin(),
+        )
+    }
 }
@@ -3,6 +3,7 @@ use js_sys::Uint8Array;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
+use yrs::observer::SubscriptionKey;
 use yrs::sync::{Awareness as YAwareness, AwarenessUpdate, Timestamp};
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
@@ -10,7 +11,10 @@ use yrs::updates::encoder::Encode;
 use crate::doc::YDoc;
 use crate::js::{Callback, Js};
 
-#[wasm_bindgen]
+/// Awareness is a CRDT-free ephemeral channel used to exchange information about users (cursor
+/// positions, names, online status etc.) working on the same document. Its binary update format
+/// is compatible with the one used by `y-protocols/awareness`.
+#[wasm_bindgen(js_name = YAwareness)]
 pub struct Awareness {
     inner: YAwareness,
 }
@@ -28,7 +32,7 @@ impl Awareness {
     }
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(js_class = YAwareness)]
 impl Awareness {
     #[wasm_bindgen(constructor)]
     pub fn new(doc: YDoc) -> Awareness {
@@ -53,8 +57,11 @@ impl Awareness {
     }
 
     #[wasm_bindgen(js_name = destroy)]
-    pub fn destroy(&self) {
-        self.inner_mut().clean_local_state();
+    pub fn destroy(&self) -> crate::Result<()> {
+        crate::js::callbacks::guard(|| {
+            self.inner_mut().clean_local_state();
+            Ok(())
+        })
     }
 
     #[wasm_bindgen(js_name = getLocalState)]
@@ -68,13 +75,15 @@ impl Awareness {
     #[wasm_bindgen(js_name = setLocalState)]
     pub fn set_local_state(&self, state: JsValue) -> crate::Result<()> {
         let inner = self.inner_mut();
-        if state.is_null() {
-            inner.clean_local_state();
-        } else {
-            let json = js_sys::JSON::stringify(&state)?.as_string().unwrap();
-            inner.set_local_state_raw(json);
-        }
-        Ok(())
+        crate::js::callbacks::guard(|| {
+            if state.is_null() {
+                inner.clean_local_state();
+            } else {
+                let json: String = js_sys::JSON::stringify(&state)?.into();
+                inner.set_local_state_raw(json);
+            }
+            Ok(())
+        })
     }
 
     #[wasm_bindgen(js_name = setLocalStateField)]
@@ -98,22 +107,32 @@ impl Awareness {
     pub fn on(&self, event: &str, callback: js_sys::Function) -> crate::Result<()> {
         let abi = callback.subscription_key();
         match event {
-            "update" => self.inner.on_update_with(abi, move |_, e, origin| {
-                let json = JsValue::from_serde(e.summary()).unwrap();
-                let origin = match origin {
-                    None => JsValue::UNDEFINED,
-                    Some(origin) => Js::from(origin).into(),
-                };
-                callback.call2(&JsValue::NULL, &json, &origin).unwrap();
-            }),
-            "change" => self.inner.on_change_with(abi, move |_, e, origin| {
-                let json = JsValue::from_serde(e.summary()).unwrap();
-                let origin = match origin {
-                    None => JsValue::UNDEFINED,
-                    Some(origin) => Js::from(origin).into(),
-                };
-                callback.call2(&JsValue::NULL, &json, &origin).unwrap();
-            }),
+            "update" => {
+                self.inner
+                    .on_update_with(SubscriptionKey::new(abi), move |_, e, origin| {
+                        let origin = match origin {
+                            None => JsValue::UNDEFINED,
+                            Some(origin) => Js::from(origin).into(),
+                        };
+                        let result = JsValue::from_serde(e.summary())
+                            .map_err(|e| JsValue::from_str(&e.to_string()))
+                            .and_then(|json| callback.call2(&JsValue::NULL, &json, &origin));
+                        crate::js::callbacks::catch(result);
+                    })
+            }
+            "change" => {
+                self.inner
+                    .on_change_with(SubscriptionKey::new(abi), move |_, e, origin| {
+                        let origin = match origin {
+                            None => JsValue::UNDEFINED,
+                            Some(origin) => Js::from(origin).into(),
+                        };
+                        let result = JsValue::from_serde(e.summary())
+                            .map_err(|e| JsValue::from_str(&e.to_string()))
+                            .and_then(|json| callback.call2(&JsValue::NULL, &json, &origin));
+                        crate::js::callbacks::catch(result);
+                    })
+            }
             unknown => return Err(JsValue::from_str(&format!("Unknown event: {}", unknown))),
         }
         Ok(())
@@ -123,35 +142,128 @@ impl Awareness {
     pub fn off(&self, event: &str, callback: js_sys::Function) -> crate::Result<bool> {
         let abi = callback.subscription_key();
         match event {
-            "update" => Ok(self.inner.unobserve_update(abi)),
-            "change" => Ok(self.inner.unobserve_change(abi)),
+            "update" => Ok(self.inner.unobserve_update(SubscriptionKey::new(abi))),
+            "change" => Ok(self.inner.unobserve_change(SubscriptionKey::new(abi))),
             unknown => return Err(JsValue::from_str(&format!("Unknown event: {}", unknown))),
         }
     }
+
+    /// Removes states of given clients. Removal is propagated to remote peers on the next update.
+    #[wasm_bindgen(js_name = removeStates)]
+    pub fn remove_states(&self, clients: Vec<u64>) -> crate::Result<()> {
+        let inner = self.inner_mut();
+        crate::js::callbacks::guard(|| {
+            for client_id in clients {
+                inner.remove_state(client_id);
+            }
+            Ok(())
+        })
+    }
+
+    /// Encodes states of given `clients` (or all known clients if `clients` is not provided)
+    /// into binary update compatible with `y-protocols/awareness`.
+    #[wasm_bindgen(js_name = encodeUpdate)]
+    pub fn encode_update(&self, clients: JsValue) -> crate::Result<Uint8Array> {
+        let res = if clients.is_null() || clients.is_undefined() {
+            self.inner.update()
+        } else {
+            let client_ids: Vec<u64> =
+                JsValue::into_serde(&clients).map_err(|e| JsValue::from_str(&e.to_string()))?;
+            self.inner.update_with_clients(client_ids)
+        };
+
+        let update = res.map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let bytes = update.encode_v1();
+        Ok(Uint8Array::from(bytes.as_slice()))
+    }
+
+    /// Applies binary update generated by remote `YAwareness` (or `y-protocols/awareness`)
+    /// instance. Optional `origin` is passed to `update` and `change` event callbacks.
+    #[wasm_bindgen(js_name = applyUpdate)]
+    pub fn apply_update(&self, update: Uint8Array, origin: JsValue) -> crate::Result<()> {
+        let update = AwarenessUpdate::decode_v1(&update.to_vec())
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let inner = self.inner_mut();
+        crate::js::callbacks::guard(|| {
+            let res = if origin.is_null() || origin.is_undefined() {
+                inner.apply_update(update)
+            } else {
+                inner.apply_update_with(update, Js::from(origin))
+            };
+            res.map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
 }
 
-#[wasm_bindgen(js_name = removeAwarenessStates)]
-pub fn remove_states(awareness: &Awareness, clients: Vec<u64>) -> crate::Result<()> {
-    let inner = awareness.inner_mut();
-    for client_id in clients {
-        inner.remove_state(client_id);
+/// Former name of `YAwareness`, kept for compatibility with existing `new Y.Awareness(doc)`
+/// callers. Its instances are also accepted by `removeAwarenessStates`, `encodeAwarenessUpdate`
+/// and `applyAwarenessUpdate` functions.
+///
+/// @deprecated Use `YAwareness` instead.
+#[wasm_bindgen(js_name = Awareness)]
+pub struct LegacyAwareness(Awareness);
+
+#[wasm_bindgen(js_class = Awareness)]
+impl LegacyAwareness {
+    #[wasm_bindgen(constructor)]
+    pub fn new(doc: YDoc) -> LegacyAwareness {
+        LegacyAwareness(Awareness::new(doc))
+    }
+
+    #[wasm_bindgen(getter, js_name = doc)]
+    pub fn doc(&self) -> YDoc {
+        self.0.doc()
+    }
+
+    #[wasm_bindgen(getter, js_name = meta)]
+    pub fn meta(&self) -> crate::Result<js_sys::Map> {
+        self.0.meta()
+    }
+
+    #[wasm_bindgen(js_name = destroy)]
+    pub fn destroy(&self) -> crate::Result<()> {
+        self.0.destroy()
+    }
+
+    #[wasm_bindgen(js_name = getLocalState)]
+    pub fn local_state(&self) -> crate::Result<JsValue> {
+        self.0.local_state()
+    }
+
+    #[wasm_bindgen(js_name = setLocalState)]
+    pub fn set_local_state(&self, state: JsValue) -> crate::Result<()> {
+        self.0.set_local_state(state)
+    }
+
+    #[wasm_bindgen(js_name = setLocalStateField)]
+    pub fn set_field(&self, key: &str, value: JsValue) -> crate::Result<()> {
+        self.0.set_field(key, value)
+    }
+
+    #[wasm_bindgen(js_name = getStates)]
+    pub fn states(&self) -> crate::Result<js_sys::Map> {
+        self.0.states()
+    }
+
+    #[wasm_bindgen(js_name = on)]
+    pub fn on(&self, event: &str, callback: js_sys::Function) -> crate::Result<()> {
+        self.0.on(event, callback)
+    }
+
+    #[wasm_bindgen(js_name = off)]
+    pub fn off(&self, event: &str, callback: js_sys::Function) -> crate::Result<bool> {
+        self.0.off(event, callback)
     }
-    Ok(())
+}
+
+#[wasm_bindgen(js_name = removeAwarenessStates)]
+pub fn remove_states(awareness: &LegacyAwareness, clients: Vec<u64>) -> crate::Result<()> {
+    awareness.0.remove_states(clients)
 }
 
 #[wasm_bindgen(js_name = encodeAwarenessUpdate)]
-pub fn encode_update(awareness: &Awareness, clients: JsValue) -> crate::Result<Uint8Array> {
-    let res = if clients.is_null() || clients.is_undefined() {
-        awareness.inner.update()
-    } else {
-        let client_ids: Vec<u64> =
-            JsValue::into_serde(&clients).map_err(|e| JsValue::from_str(&e.to_string()))?;
-        awareness.inner.update_with_clients(client_ids)
-    };
-
-    let update = res.map_err(|e| JsValue::from_str(&e.to_string()))?;
-    let bytes = update.encode_v1();
-    Ok(Uint8Array::from(bytes.as_slice()))
+pub fn encode_update(awareness: &LegacyAwareness, clients: JsValue) -> crate::Result<Uint8Array> {
+    awareness.0.encode_update(clients)
 }
 
 #[wasm_bindgen(js_name = modifyAwarenessUpdate)]
@@ -168,17 +280,11 @@ pub fn modify_update(update: Uint8Array, modify: js_sys::Function) -> crate::Res
 
 #[wasm_bindgen(js_name = applyAwarenessUpdate)]
 pub fn apply_update(
-    awareness: &Awareness,
+    awareness: &LegacyAwareness,
     update: Uint8Array,
-    _origin: JsValue, //TODO: use origin in Awareness::apply_update
+    origin: JsValue,
 ) -> crate::Result<()> {
-    let update = AwarenessUpdate::decode_v1(&update.to_vec())
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    awareness
-        .inner_mut()
-        .apply_update(update)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    Ok(())
+    awareness.0.apply_update(update, origin)
 }
 
 pub struct JsClock;
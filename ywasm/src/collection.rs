@@ -112,11 +112,12 @@ impl<S: SharedRef + 'static> Integrated<S> {
                 let shared_ref = self.resolve(txn)?;
                 f(&shared_ref, txn)
             }
-            None => {
+            None => crate::js::callbacks::guard(|| {
+                // implicit transaction is committed (and its observers called) once dropped
                 let mut txn = self.transact_mut()?;
                 let shared_ref = self.resolve(&mut txn)?;
                 f(&shared_ref, &mut txn)
-            }
+            }),
         }
     }
 
@@ -12,8 +12,9 @@ use std::iter::FromIterator;
 use std::ops::Deref;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
-use yrs::types::TYPE_REFS_DOC;
-use yrs::{Doc, OffsetKind, Options, ReadTxn, Transact};
+use yrs::observer::SubscriptionKey;
+use yrs::types::{ToJson, TYPE_REFS_DOC};
+use yrs::{Doc, OffsetKind, Options, ReadTxn, Transact, TransactionMut};
 
 /// A ywasm document type. Documents are most important units of collaborative resources management.
 /// All shared collections live within a scope of their corresponding documents. All updates are
@@ -126,27 +127,21 @@ impl YDoc {
     /// transaction at the time - subsequent attempts will cause exception to be thrown.
     ///
     /// Transactions started with `doc.beginTransaction` can be released using `transaction.free`
-    /// method.
+    /// method. Prefer using `doc.transact`, which releases transaction automatically.
     ///
     /// Example:
     ///
     /// ```javascript
     /// import YDoc from 'ywasm'
     ///
-    /// // helper function used to simplify transaction
-    /// // create/release cycle
-    /// YDoc.prototype.transact = callback => {
-    ///     const txn = this.transaction()
-    ///     try {
-    ///         return callback(txn)
-    ///     } finally {
-    ///         txn.free()
-    ///     }
-    /// }
-    ///
     /// const doc = new YDoc()
     /// const text = doc.getText('name')
-    /// doc.transact(txn => text.insert(txn, 0, 'hello world'))
+    /// const txn = doc.beginTransaction()
+    /// try {
+    ///     text.insert(0, 'hello world', undefined, txn)
+    /// } finally {
+    ///     txn.free()
+    /// }
     /// ```
     #[wasm_bindgen(js_name = beginTransaction)]
     pub fn transaction(&self, origin: JsValue) -> YTransaction {
@@ -157,6 +152,51 @@ impl YDoc {
         }
     }
 
+    /// Opens a new read-write transaction (with an optional `origin`), passes it to a given
+    /// `callback` and returns the callback result. Once the callback finishes, transaction is
+    /// committed and released. If the callback throws an exception, transaction is still released
+    /// - changes made so far are kept - and the exception is rethrown.
+    ///
+    /// Transaction passed to a callback must not be used after the callback has finished.
+    ///
+    /// Example:
+    ///
+    /// ```javascript
+    /// import YDoc from 'ywasm'
+    ///
+    /// const doc = new YDoc()
+    /// const text = doc.getText('name')
+    /// const len = doc.transact(txn => {
+    ///     text.insert(0, 'hello world', undefined, txn)
+    ///     return text.length(txn)
+    /// }, 'origin')
+    /// ```
+    #[wasm_bindgen(js_name = transact)]
+    pub fn transact_with(&self, callback: js_sys::Function, origin: JsValue) -> Result<JsValue> {
+        let txn = if origin.is_undefined() {
+            self.try_transact_mut()
+        } else {
+            self.try_transact_mut_with(Js::from(origin))
+        }
+        .map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_RW_TX))?;
+        let txn: JsValue = YTransaction::from(txn).into();
+        let result = callback.call1(&JsValue::UNDEFINED, &txn);
+        // callback could have already released the transaction on its own
+        let ptr = js_sys::Reflect::get(&txn, &JsValue::from_str(crate::js::JS_PTR))?;
+        if ptr.as_f64().unwrap_or_default() != 0.0 {
+            if result.is_ok() {
+                let mut txn = YTransaction::try_mut_from_js_value(&txn)?;
+                txn.commit()?;
+            }
+            let free: js_sys::Function =
+                js_sys::Reflect::get(&txn, &JsValue::from_str("free"))?.into();
+            // uncommitted transaction will be committed when freed, which may trigger callbacks
+            let freed = crate::js::callbacks::guard(|| free.call0(&txn));
+            return result.and_then(|result| freed.map(|_| result));
+        }
+        result
+    }
+
     /// Returns a `YText` shared data type, that's accessible for subsequent accesses using given
     /// `name`.
     ///
@@ -209,38 +249,69 @@ impl YDoc {
         YXmlFragment(SharedCollection::integrated(shared_ref, self.0.clone()))
     }
 
+    /// Subscribes a `callback` to a given document `event`. Supported events:
+    ///
+    /// - `update` and `updateV2` - called with a copy of an update (encoded using lib0 v1 or v2
+    ///   encoding respectively) generated by a committed transaction.
+    /// - `updateView` and `updateV2View` - zero-copy alternatives of the events above. Callback is
+    ///   called with a `Uint8Array` view over the wasm memory. This view is valid **only until the
+    ///   callback returns** and it must not be used after any other call into ywasm (as wasm
+    ///   memory may be reallocated). Use `update.slice()` to retain a copy of it.
+    /// - `subdocs`, `destroy`, `afterTransaction` and `cleanup`.
     #[wasm_bindgen(js_name = on)]
     pub fn on(&self, event: &str, callback: js_sys::Function) -> Result<()> {
         let abi = callback.subscription_key();
         let result = match event {
-            "update" => self.observe_update_v1_with(abi, move |txn, e| {
+            "update" => self.observe_update_v1_with(SubscriptionKey::new(abi), move |txn, e| {
                 let update = js_sys::Uint8Array::from(e.update.as_slice());
                 let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &update, &txn).unwrap();
+                crate::js::callbacks::catch(callback.call2(&JsValue::UNDEFINED, &update, &txn));
             }),
-            "updateV2" => self.observe_update_v2_with(abi, move |txn, e| {
+            "updateV2" => self.observe_update_v2_with(SubscriptionKey::new(abi), move |txn, e| {
                 let update = js_sys::Uint8Array::from(e.update.as_slice());
                 let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &update, &txn).unwrap();
+                crate::js::callbacks::catch(callback.call2(&JsValue::UNDEFINED, &update, &txn));
             }),
-            "subdocs" => self.observe_subdocs_with(abi, move |txn, e| {
+            "updateView" => {
+                self.observe_update_v1_with(SubscriptionKey::new(abi), move |txn, e| {
+                    let txn: JsValue = YTransaction::from_ref(txn).into();
+                    // safety: view is valid only until callback returns, see docs of `on`. It must be
+                    // created after all other allocations, as they may reallocate wasm memory.
+                    let update = unsafe { js_sys::Uint8Array::view(e.update.as_slice()) };
+                    crate::js::callbacks::catch(callback.call2(&JsValue::UNDEFINED, &update, &txn));
+                })
+            }
+            "updateV2View" => {
+                self.observe_update_v2_with(SubscriptionKey::new(abi), move |txn, e| {
+                    let txn: JsValue = YTransaction::from_ref(txn).into();
+                    // safety: view is valid only until callback returns, see docs of `on`. It must be
+                    // created after all other allocations, as they may reallocate wasm memory.
+                    let update = unsafe { js_sys::Uint8Array::view(e.update.as_slice()) };
+                    crate::js::callbacks::catch(callback.call2(&JsValue::UNDEFINED, &update, &txn));
+                })
+            }
+            "subdocs" => self.observe_subdocs_with(SubscriptionKey::new(abi), move |txn, e| {
                 let event: JsValue = YSubdocsEvent::new(e).into();
                 let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &event, &txn).unwrap();
+                crate::js::callbacks::catch(callback.call2(&JsValue::UNDEFINED, &event, &txn));
             }),
-            "destroy" => self.observe_destroy_with(abi, move |txn, e| {
+            "destroy" => self.observe_destroy_with(SubscriptionKey::new(abi), move |txn, e| {
                 let event: JsValue = YDoc::from(e.clone()).into();
                 let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &event, &txn).unwrap();
-            }),
-            "afterTransaction" => self.observe_after_transaction_with(abi, move |txn| {
-                let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call1(&JsValue::UNDEFINED, &txn).unwrap();
-            }),
-            "cleanup" => self.observe_transaction_cleanup_with(abi, move |txn, _| {
-                let txn = YTransaction::from_ref(txn).into();
-                callback.call1(&JsValue::UNDEFINED, &txn).unwrap();
+                crate::js::callbacks::catch(callback.call2(&JsValue::UNDEFINED, &event, &txn));
             }),
+            "afterTransaction" => {
+                self.observe_after_transaction_with(SubscriptionKey::new(abi), move |txn| {
+                    let txn: JsValue = YTransaction::from_ref(txn).into();
+                    crate::js::callbacks::catch(callback.call1(&JsValue::UNDEFINED, &txn));
+                })
+            }
+            "cleanup" => {
+                self.observe_transaction_cleanup_with(SubscriptionKey::new(abi), move |txn, _| {
+                    let txn = YTransaction::from_ref(txn).into();
+                    crate::js::callbacks::catch(callback.call1(&JsValue::UNDEFINED, &txn));
+                })
+            }
             other => {
                 return Err(JsValue::from_str(&format!("unknown event: '{}'", other)).into());
             }
@@ -253,12 +324,14 @@ impl YDoc {
     pub fn off(&self, event: &str, callback: js_sys::Function) -> Result<bool> {
         let abi = callback.subscription_key();
         let result = match event {
-            "update" => self.unobserve_update_v1(abi),
-            "updateV2" => self.unobserve_update_v2(abi),
-            "subdocs" => self.unobserve_subdocs(abi),
-            "destroy" => self.unobserve_destroy(abi),
-            "afterTransaction" => self.unobserve_after_transaction(abi),
-            "cleanup" => self.unobserve_transaction_cleanup(abi),
+            "update" => self.unobserve_update_v1(SubscriptionKey::new(abi)),
+            "updateV2" => self.unobserve_update_v2(SubscriptionKey::new(abi)),
+            "updateView" => self.unobserve_update_v1(SubscriptionKey::new(abi)),
+            "updateV2View" => self.unobserve_update_v2(SubscriptionKey::new(abi)),
+            "subdocs" => self.unobserve_subdocs(SubscriptionKey::new(abi)),
+            "destroy" => self.unobserve_destroy(SubscriptionKey::new(abi)),
+            "afterTransaction" => self.unobserve_after_transaction(SubscriptionKey::new(abi)),
+            "cleanup" => self.unobserve_transaction_cleanup(SubscriptionKey::new(abi)),
             other => {
                 return Err(JsValue::from_str(&format!("unknown event: '{}'", other)).into());
             }
@@ -280,8 +353,11 @@ impl YDoc {
                 } else {
                     return Ok(());
                 };
-                let mut parent_txn = parent_doc.transact_mut();
-                self.0.load(&mut parent_txn);
+                crate::js::callbacks::guard(|| {
+                    let mut parent_txn = parent_doc.transact_mut();
+                    self.0.load(&mut parent_txn);
+                    Ok(())
+                })?;
             }
         }
         Ok(())
@@ -300,8 +376,11 @@ impl YDoc {
                 } else {
                     return Ok(());
                 };
-                let mut parent_txn = parent_doc.transact_mut();
-                self.0.destroy(&mut parent_txn);
+                crate::js::callbacks::guard(|| {
+                    let mut parent_txn = parent_doc.transact_mut();
+                    self.0.destroy(&mut parent_txn);
+                    Ok(())
+                })?;
             }
         }
         Ok(())
@@ -389,6 +468,28 @@ impl YDoc {
             }
         }
     }
+
+    /// Returns JSON representation of all root-level types defined in this document, keyed by
+    /// their names. Returned value always consists of plain JS objects, arrays and primitives
+    /// (no wasm-backed handles), so `options` object is accepted only for parity with
+    /// `toJsonDeep` methods of other shared types.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(&self, _options: JsValue, txn: &ImplicitTransaction) -> Result<JsValue> {
+        let doc = &self.0;
+        let any = match YTransaction::from_implicit(&txn)? {
+            Some(txn) => {
+                let txn: &TransactionMut = &*txn;
+                doc.to_json(txn)
+            }
+            None => {
+                let txn = doc
+                    .try_transact()
+                    .map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_RW_TX))?;
+                doc.to_json(&txn)
+            }
+        };
+        Ok(Js::from_any(&any).into())
+    }
 }
 
 #[wasm_bindgen]
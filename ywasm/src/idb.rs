@@ -0,0 +1,429 @@
+use crate::doc::YDoc;
+use crate::js::Js;
+use crate::Result;
+use js_sys::{Promise, Uint8Array};
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{future_to_promise, spawn_local, JsFuture};
+use web_sys::{
+    EventTarget, IdbCursor, IdbCursorDirection, IdbDatabase, IdbFactory, IdbKeyRange,
+    IdbObjectStore, IdbObjectStoreParameters, IdbRequest, IdbTransactionMode,
+};
+use yrs::observer::SubscriptionKey;
+use yrs::{Doc, Origin, ReadTxn, StateVector, Transact};
+
+const UPDATES_STORE: &str = "updates";
+const CUSTOM_STORE: &str = "custom";
+
+/// Number of updates stored in a database, after which they are compacted into a single one.
+const PREFERRED_TRIM_SIZE: u32 = 500;
+
+/// Offline persistence of ywasm documents backed by the browser's IndexedDB, which follows the
+/// semantics of `y-indexeddb` provider:
+///
+/// - All updates produced by a document are appended to the `updates` object store of a database
+///   with a given name.
+/// - When persistence is created, all stored updates are applied to a document and then compacted,
+///   together with the current document state, into a single update.
+/// - Once the number of stored updates exceeds 500, they are compacted again.
+/// - The `custom` object store can be used to keep any additional metadata (see `get`, `set` and
+///   `del` methods).
+///
+/// Updates loaded from the database are applied within transactions, which origin is
+/// `persistence.origin`.
+///
+/// Example:
+///
+/// ```javascript
+/// import {YDoc, IndexeddbPersistence} from 'ywasm'
+///
+/// const doc = new YDoc()
+/// const persistence = new IndexeddbPersistence('my-document', doc)
+/// await persistence.whenSynced
+/// // document state has been restored from IndexedDB
+/// ```
+#[wasm_bindgen(js_name = IndexeddbPersistence)]
+pub struct YIndexeddbPersistence {
+    inner: Rc<Persistence>,
+    when_synced: Promise,
+}
+
+#[wasm_bindgen(js_class = IndexeddbPersistence)]
+impl YIndexeddbPersistence {
+    /// Creates a new persistence for a given `doc`, stored in the IndexedDB database with
+    /// a given `name`. Database is opened asynchronously: use `whenSynced` to await until
+    /// the stored document state has been loaded.
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: String, doc: &YDoc) -> YIndexeddbPersistence {
+        let inner = Rc::new(Persistence {
+            name,
+            doc: doc.0.clone(),
+            origin: js_sys::Object::new().into(),
+            db: RefCell::new(None),
+            db_ref: Cell::new(0.0),
+            db_size: Cell::new(0),
+            synced: Cell::new(false),
+            compacting: Cell::new(false),
+            destroyed: Cell::new(false),
+        });
+        let p = inner.clone();
+        let when_synced = future_to_promise(async move {
+            p.open().await?;
+            Ok(JsValue::UNDEFINED)
+        });
+        YIndexeddbPersistence { inner, when_synced }
+    }
+
+    /// Name of the IndexedDB database used by this persistence.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    /// Origin of the transactions used to apply updates loaded from IndexedDB. It can be compared
+    /// with `transaction.origin` using `sameOrigin`.
+    #[wasm_bindgen(getter)]
+    pub fn origin(&self) -> JsValue {
+        self.inner.origin.clone()
+    }
+
+    /// Returns true, once the document state stored in IndexedDB has been loaded.
+    #[wasm_bindgen(getter)]
+    pub fn synced(&self) -> bool {
+        self.inner.synced.get()
+    }
+
+    /// Promise, which resolves once the document state stored in IndexedDB has been loaded.
+    #[wasm_bindgen(getter, js_name = whenSynced)]
+    pub fn when_synced(&self) -> Promise {
+        self.when_synced.clone()
+    }
+
+    /// Stops persisting document updates and closes the database. Stored data is left untouched.
+    #[wasm_bindgen(js_name = destroy)]
+    pub fn destroy(&self) {
+        self.inner.destroy()
+    }
+
+    /// Destroys this persistence and removes its database together with all data stored in it.
+    #[wasm_bindgen(js_name = clearData)]
+    pub fn clear_data(&self) -> Promise {
+        self.inner.destroy();
+        let name = self.inner.name.clone();
+        future_to_promise(async move {
+            let req = factory()?.delete_database(&name)?;
+            request(&req).await?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// Returns a value stored under a given `key` in the `custom` object store.
+    #[wasm_bindgen(js_name = get)]
+    pub fn get(&self, key: JsValue) -> Promise {
+        let p = self.inner.clone();
+        let synced = self.when_synced.clone();
+        future_to_promise(async move {
+            JsFuture::from(synced).await?;
+            let store = p.store(CUSTOM_STORE, IdbTransactionMode::Readonly)?;
+            request(&store.get(&key)?).await
+        })
+    }
+
+    /// Stores a `value` under a given `key` in the `custom` object store.
+    #[wasm_bindgen(js_name = set)]
+    pub fn set(&self, key: JsValue, value: JsValue) -> Promise {
+        let p = self.inner.clone();
+        let synced = self.when_synced.clone();
+        future_to_promise(async move {
+            JsFuture::from(synced).await?;
+            let store = p.store(CUSTOM_STORE, IdbTransactionMode::Readwrite)?;
+            request(&store.put_with_key(&value, &key)?).await
+        })
+    }
+
+    /// Removes a value stored under a given `key` in the `custom` object store.
+    #[wasm_bindgen(js_name = del)]
+    pub fn del(&self, key: JsValue) -> Promise {
+        let p = self.inner.clone();
+        let synced = self.when_synced.clone();
+        future_to_promise(async move {
+            JsFuture::from(synced).await?;
+            let store = p.store(CUSTOM_STORE, IdbTransactionMode::Readwrite)?;
+            request(&store.delete(&key)?).await
+        })
+    }
+}
+
+struct Persistence {
+    name: String,
+    doc: Doc,
+    /// JS object used as an origin of transactions applying updates loaded from the database. It's
+    /// also used as a key of the document update subscription.
+    origin: JsValue,
+    db: RefCell<Option<IdbDatabase>>,
+    /// Key of the first update, which has not been applied to a document yet.
+    db_ref: Cell<f64>,
+    /// Number of updates stored in the database.
+    db_size: Cell<u32>,
+    synced: Cell<bool>,
+    compacting: Cell<bool>,
+    destroyed: Cell<bool>,
+}
+
+impl Persistence {
+    async fn open(self: &Rc<Self>) -> Result<()> {
+        let db = open_db(&self.name).await?;
+        if self.destroyed.get() {
+            db.close();
+            return Ok(());
+        }
+        self.db.replace(Some(db));
+        self.subscribe()?;
+        // compaction also persists changes made to a document before persistence was created
+        self.store_state(true).await?;
+        self.synced.set(true);
+        Ok(())
+    }
+
+    fn subscribe(self: &Rc<Self>) -> Result<()> {
+        let weak: Weak<Self> = Rc::downgrade(self);
+        let origin: Origin = Js::from(self.origin.clone()).into();
+        let own_origin = origin.clone();
+        self.doc
+            .observe_update_v1_with(SubscriptionKey::new(origin), move |txn, e| {
+                if let Some(p) = weak.upgrade() {
+                    if txn.origin() != Some(&own_origin) {
+                        p.store_update(e.update.clone());
+                    }
+                }
+            })
+            .map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_TX))
+    }
+
+    fn destroy(&self) {
+        if !self.destroyed.replace(true) {
+            let origin: Origin = Js::from(self.origin.clone()).into();
+            let _ = self.doc.unobserve_update_v1(SubscriptionKey::new(origin));
+            if let Some(db) = self.db.borrow_mut().take() {
+                db.close();
+            }
+        }
+    }
+
+    fn store(&self, name: &str, mode: IdbTransactionMode) -> Result<IdbObjectStore> {
+        match &*self.db.borrow() {
+            Some(db) => db
+                .transaction_with_str_and_mode(name, mode)?
+                .object_store(name),
+            None => Err(JsValue::from_str(crate::js::errors::IDB_CLOSED)),
+        }
+    }
+
+    /// Appends a document `update` to the database, compacting stored updates once there are too
+    /// many of them. Since document observers cannot await, errors are silently dropped, just like
+    /// they are in `y-indexeddb`.
+    fn store_update(self: &Rc<Self>, update: Vec<u8>) {
+        let p = self.clone();
+        spawn_local(async move {
+            let _ = p.append(update).await;
+        })
+    }
+
+    async fn append(&self, update: Vec<u8>) -> Result<()> {
+        let store = self.store(UPDATES_STORE, IdbTransactionMode::Readwrite)?;
+        request(&store.add(&Uint8Array::from(update.as_slice()))?).await?;
+        let size = self.db_size.get() + 1;
+        self.db_size.set(size);
+        if size >= PREFERRED_TRIM_SIZE && !self.compacting.replace(true) {
+            let result = self.store_state(false).await;
+            self.compacting.set(false);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Applies all updates stored in the database, which have not been seen by a document so far.
+    async fn fetch_updates(&self) -> Result<()> {
+        // IndexedDB transaction commits as soon as it has no pending requests, so all of them
+        // must be issued before awaiting any
+        let store = self.store(UPDATES_STORE, IdbTransactionMode::Readonly)?;
+        let range = IdbKeyRange::lower_bound_with_open(&JsValue::from(self.db_ref.get()), false)?;
+        let updates = request(&store.get_all_with_key(&range)?);
+        let cursor =
+            request(&store.open_key_cursor_with_range_and_direction(
+                &JsValue::NULL,
+                IdbCursorDirection::Prev,
+            )?);
+        let count = request(&store.count()?);
+
+        let updates: js_sys::Array = updates.await?.into();
+        if !self.destroyed.get() && updates.length() != 0 {
+            let mut txn = self
+                .doc
+                .try_transact_mut_with(Js::from(self.origin.clone()))
+                .map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_TX))?;
+            crate::js::callbacks::guard(move || {
+                for update in updates.iter() {
+                    let update = Uint8Array::from(update).to_vec();
+                    txn.apply_update_v1(&update)
+                        .map_err(|e| JsValue::from(e.to_string()))?;
+                }
+                Ok(())
+            })?;
+        }
+        let cursor = cursor.await?;
+        if !cursor.is_null() {
+            let last_key = cursor.unchecked_into::<IdbCursor>().key()?;
+            self.db_ref.set(last_key.as_f64().unwrap_or_default() + 1.0);
+        }
+        let count = count.await?;
+        self.db_size.set(count.as_f64().unwrap_or_default() as u32);
+        Ok(())
+    }
+
+    /// Fetches all pending updates and - if there are too many of them or `force` flag was set -
+    /// replaces all stored updates with a single one, containing the whole document state.
+    async fn store_state(&self, force: bool) -> Result<()> {
+        self.fetch_updates().await?;
+        if force || self.db_size.get() >= PREFERRED_TRIM_SIZE {
+            let state = self
+                .doc
+                .try_transact()
+                .map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_RW_TX))?
+                .encode_state_as_update_v1(&StateVector::default());
+            // updates stored after the fetch have keys >= db_ref, so they are not removed here
+            let store = self.store(UPDATES_STORE, IdbTransactionMode::Readwrite)?;
+            let range =
+                IdbKeyRange::upper_bound_with_open(&JsValue::from(self.db_ref.get()), true)?;
+            let added = request(&store.add(&Uint8Array::from(state.as_slice()))?);
+            let deleted = request(&store.delete(&range)?);
+            let count = request(&store.count()?);
+            added.await?;
+            deleted.await?;
+            let count = count.await?;
+            self.db_size.set(count.as_f64().unwrap_or_default() as u32);
+        }
+        Ok(())
+    }
+}
+
+fn factory() -> Result<IdbFactory> {
+    let factory = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))?;
+    factory
+        .dyn_into::<IdbFactory>()
+        .map_err(|_| JsValue::from_str(crate::js::errors::IDB_UNSUPPORTED))
+}
+
+/// Opens a database with a given `name`, creating its object stores if necessary.
+async fn open_db(name: &str) -> Result<IdbDatabase> {
+    let req = factory()?.open(name)?;
+    let _on_upgrade = Listener::new(&req, "upgradeneeded", {
+        let req = req.clone();
+        move |_| {
+            if let Ok(db) = req.result() {
+                let db: IdbDatabase = db.unchecked_into();
+                let stores = db.object_store_names();
+                if !stores.contains(UPDATES_STORE) {
+                    let params = js_sys::Object::new();
+                    let _ = js_sys::Reflect::set(
+                        &params,
+                        &JsValue::from_str("autoIncrement"),
+                        &JsValue::from(true),
+                    );
+                    let params: IdbObjectStoreParameters = params.unchecked_into();
+                    let _ = db.create_object_store_with_optional_parameters(UPDATES_STORE, &params);
+                }
+                if !stores.contains(CUSTOM_STORE) {
+                    let _ = db.create_object_store(CUSTOM_STORE);
+                }
+            }
+        }
+    });
+    let db = request(&req).await?;
+    Ok(db.unchecked_into())
+}
+
+/// Converts IndexedDB request into a future, which completes with a request result. Request
+/// callbacks are attached right away, so the future doesn't need to be awaited immediately.
+fn request(req: &IdbRequest) -> Request {
+    let mut listeners = None;
+    let promise = Promise::new(&mut |resolve, reject| {
+        let on_success = Listener::new(req, "success", {
+            let req = req.clone();
+            move |_| {
+                let result = req.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::UNDEFINED, &result);
+            }
+        });
+        let on_error = Listener::new(req, "error", {
+            let req = req.clone();
+            move |_| {
+                let error = js_sys::Reflect::get(&req, &JsValue::from_str("error"))
+                    .unwrap_or(JsValue::UNDEFINED);
+                let _ = reject.call1(&JsValue::UNDEFINED, &error);
+            }
+        });
+        listeners = Some((on_success, on_error));
+    });
+    // promise executor is called synchronously, so listeners are already attached
+    let (on_success, on_error) = listeners.unwrap();
+    Request {
+        result: JsFuture::from(promise),
+        _on_success: on_success,
+        _on_error: on_error,
+    }
+}
+
+/// Future of an IndexedDB request returned by [request]. Request callbacks are released once
+/// this future is dropped - which happens right after it completes when awaited.
+struct Request {
+    result: JsFuture,
+    _on_success: Listener,
+    _on_error: Listener,
+}
+
+impl Future for Request {
+    type Output = Result<JsValue>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.result).poll(cx)
+    }
+}
+
+/// One-shot callback listening to a given `event` of an IndexedDB request. It's detached from
+/// the request and released when dropped, so it doesn't leak if an event never happens.
+struct Listener {
+    target: EventTarget,
+    event: &'static str,
+    callback: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Listener {
+    fn new<F>(target: &EventTarget, event: &'static str, f: F) -> Self
+    where
+        F: FnOnce(JsValue) + 'static,
+    {
+        let callback = Closure::once(f);
+        let _ = target.add_event_listener_with_callback(event, callback.as_ref().unchecked_ref());
+        Listener {
+            target: target.clone(),
+            event,
+            callback,
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let callback = self.callback.as_ref().unchecked_ref();
+        let _ = self
+            .target
+            .remove_event_listener_with_callback(self.event, callback);
+    }
+}
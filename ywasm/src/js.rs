@@ -3,6 +3,7 @@ use crate::collection::{Integrated, SharedCollection};
 use crate::doc::YDoc;
 use crate::map::YMap;
 use crate::text::YText;
+use crate::transaction::ImplicitTransaction;
 use crate::weak::YWeakLink;
 use crate::xml_elem::YXmlElement;
 use crate::xml_frag::YXmlFragment;
@@ -14,7 +15,7 @@ use std::convert::TryInto;
 use std::ops::{Deref, RangeBounds};
 use std::sync::Arc;
 use wasm_bindgen::__rt::RefMut;
-use wasm_bindgen::convert::{FromWasmAbi, IntoWasmAbi};
+use wasm_bindgen::convert::IntoWasmAbi;
 use wasm_bindgen::JsValue;
 use yrs::block::{EmbedPrelim, ItemContent, Prelim, Unused};
 use yrs::branch::{Branch, BranchPtr};
@@ -85,6 +86,36 @@ impl Js {
         }
     }
 
+    /// Checks if `plain` flag was set on the options object passed to `toJsonDeep` methods.
+    pub fn plain_option(options: &JsValue) -> bool {
+        if options.is_object() {
+            if let Ok(js) = js_sys::Reflect::get(options, &JsValue::from_str("plain")) {
+                return js.as_bool().unwrap_or(false);
+            }
+        }
+        false
+    }
+
+    /// Returns a structured clone friendly copy of a given JS value. Any wasm-backed shared
+    /// collections found inside are replaced with their JSON representation, so that returned
+    /// value can outlive the wasm objects it was created from.
+    pub fn to_plain(value: &JsValue, txn: &ImplicitTransaction) -> Result<JsValue> {
+        match Js(value.clone()).as_value()? {
+            ValueRef::Any(any) => Ok(Self::from_any(&any).0),
+            ValueRef::Shared(shared) => shared.to_plain(txn),
+        }
+    }
+
+    /// Checks if current value can be inserted into a shared collection: it must be either
+    /// a primitive value or a preliminary shared type, which nested values can be inserted as
+    /// well. Values failing this check would otherwise abort wasm module during integration.
+    pub fn validate_prelim(&self) -> Result<()> {
+        match self.as_value()? {
+            ValueRef::Any(_) => Ok(()),
+            ValueRef::Shared(shared) => shared.validate_prelim(),
+        }
+    }
+
     pub fn from_xml(value: XmlNode, doc: Doc) -> Self {
         Js(match value {
             XmlNode::Element(v) => YXmlElement(SharedCollection::integrated(v, doc)).into(),
@@ -116,7 +147,10 @@ impl Js {
             Value::YXmlText(c) => {
                 Js(YXmlText(SharedCollection::integrated(c.clone(), doc.clone())).into())
             }
-            Value::UndefinedRef(_) => Js(JsValue::UNDEFINED),
+            // keyed lists and sets are not supported by ywasm yet
+            Value::YKeyedList(_) | Value::YSet(_) | Value::UndefinedRef(_) => {
+                Js(JsValue::UNDEFINED)
+            }
         }
     }
 
@@ -132,8 +166,11 @@ impl Js {
         } else if let Some(b) = self.0.as_bool() {
             Ok(ValueRef::Any(Any::Bool(b)))
         } else if self.0.is_bigint() {
-            let i = js_sys::BigInt::from(self.0.clone()).as_f64().unwrap();
-            Ok(ValueRef::Any(Any::BigInt(i as i64)))
+            let str: String = js_sys::BigInt::from(self.0.clone()).to_string(10)?.into();
+            match str.parse::<i64>() {
+                Ok(i) => Ok(ValueRef::Any(Any::BigInt(i))),
+                Err(_) => Err(JsValue::from_str(crate::js::errors::BIGINT_OVERFLOW)),
+            }
         } else if js_sys::Array::is_array(&self.0) {
             let array = js_sys::Array::from(&self.0);
             let mut result = Vec::with_capacity(array.length() as usize);
@@ -211,8 +248,7 @@ impl Into<Origin> for Js {
         if let Some(js_str) = self.0.as_string() {
             Origin::from(js_str)
         } else {
-            let abi = self.0.into_abi();
-            Origin::from(abi)
+            Origin::from(&origins::register(&self.0)[..])
         }
     }
 }
@@ -220,26 +256,27 @@ impl Into<Origin> for Js {
 impl<'a> From<&'a Origin> for Js {
     fn from(value: &'a Origin) -> Self {
         let bytes = value.as_ref();
-        match bytes.len() {
-            0 => Js(JsValue::UNDEFINED),
-            4 => {
-                let abi = u32::from_be_bytes(bytes.try_into().unwrap());
-                Js(unsafe { JsValue::from_abi(abi) })
-            }
-            _ => Js(JsValue::from_str(unsafe {
-                std::str::from_utf8_unchecked(bytes)
-            })),
+        if bytes.is_empty() {
+            Js(JsValue::UNDEFINED)
+        } else if let Some(js) = origins::lookup(bytes) {
+            Js(js)
+        } else {
+            Js(JsValue::from_str(&String::from_utf8_lossy(bytes)))
         }
     }
 }
 
+/// Values are checked with [Js::validate_prelim] before being inserted into shared collections,
+/// therefore their conversion can no longer fail once integration has started.
+const PRELIM_VALIDATED: &str = "value should have been validated before insertion";
+
 impl XmlPrelim for Js {}
 
 impl Prelim for Js {
     type Return = Unused;
 
     fn into_content(self, txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
-        match self.as_value().unwrap() {
+        match self.as_value().expect(PRELIM_VALIDATED) {
             ValueRef::Any(any) => (ItemContent::Any(vec![any]), None),
             ValueRef::Shared(shared) => {
                 match &shared {
@@ -260,7 +297,7 @@ impl Prelim for Js {
     }
 
     fn integrate(self, txn: &mut TransactionMut, inner_ref: BranchPtr) {
-        match self.as_value().unwrap() {
+        match self.as_value().expect(PRELIM_VALIDATED) {
             ValueRef::Any(_) => { /* nothing to do */ }
             ValueRef::Shared(shared) => shared.integrate(txn, inner_ref),
         }
@@ -269,7 +306,7 @@ impl Prelim for Js {
 
 impl Into<EmbedPrelim<Js>> for Js {
     fn into(self) -> EmbedPrelim<Js> {
-        match self.as_value().unwrap() {
+        match self.as_value().expect(PRELIM_VALIDATED) {
             ValueRef::Any(any) => EmbedPrelim::Primitive(any),
             ValueRef::Shared(_) => EmbedPrelim::Shared(self),
         }
@@ -312,6 +349,37 @@ impl Shared {
         }
     }
 
+    pub fn validate_prelim(&self) -> Result<()> {
+        let children = match self {
+            // weak links can always be integrated
+            Shared::Weak(_) => return Ok(()),
+            other if !other.prelim() => {
+                return Err(JsValue::from_str(crate::js::errors::NOT_PRELIM))
+            }
+            Shared::Map(v) => match &v.0 {
+                SharedCollection::Prelim(raw) => raw.values().cloned().collect(),
+                _ => Vec::new(),
+            },
+            Shared::Array(v) => match &v.0 {
+                SharedCollection::Prelim(raw) => raw.clone(),
+                _ => Vec::new(),
+            },
+            Shared::XmlElement(v) => match &v.0 {
+                SharedCollection::Prelim(raw) => raw.children.clone(),
+                _ => Vec::new(),
+            },
+            Shared::XmlFragment(v) => match &v.0 {
+                SharedCollection::Prelim(raw) => raw.clone(),
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        for child in children {
+            Js::new(child).validate_prelim()?;
+        }
+        Ok(())
+    }
+
     pub fn prelim(&self) -> bool {
         match self {
             Shared::Text(v) => v.prelim(),
@@ -351,6 +419,25 @@ impl Shared {
         }
     }
 
+    /// Returns a structured clone friendly JSON representation of this shared collection.
+    pub fn to_plain(&self, txn: &ImplicitTransaction) -> Result<JsValue> {
+        match self {
+            Shared::Text(v) => v.to_json(txn),
+            Shared::Map(v) => v.to_plain(txn),
+            Shared::Array(v) => v.to_plain(txn),
+            Shared::Weak(v) => v.to_plain(txn),
+            Shared::XmlText(v) => Ok(v.to_string(txn)?.into()),
+            Shared::XmlElement(v) => Ok(v.to_string(txn)?.into()),
+            Shared::XmlFragment(v) => Ok(v.to_string(txn)?.into()),
+            Shared::Doc(v) => {
+                // nested documents are represented by their guid, just like in `ToJson`
+                let result = js_sys::Object::new();
+                js_sys::Reflect::set(&result, &JsValue::from_str("guid"), &v.guid().into())?;
+                Ok(result.into())
+            }
+        }
+    }
+
     fn type_ref(&self, txn: &TransactionMut) -> TypeRef {
         match self {
             Shared::Text(_) => TypeRef::Text,
@@ -418,7 +505,7 @@ impl Prelim for Shared {
                         doc,
                     ))),
                 ) {
-                    array.insert_at(txn, 0, raw).unwrap();
+                    array.insert_at(txn, 0, raw).expect(PRELIM_VALIDATED);
                 }
             }
             Shared::XmlText(mut cell) => {
@@ -587,6 +674,19 @@ pub(crate) mod convert {
                 let value = JsValue::from(*len);
                 js_sys::Reflect::set(&result, &JsValue::from("retain"), &value).unwrap();
             }
+            Change::Moved { from, to, len } => {
+                let value = JsValue::from(*len);
+                js_sys::Reflect::set(&result, &JsValue::from("move"), &value).unwrap();
+                let value = JsValue::from(*from);
+                js_sys::Reflect::set(&result, &JsValue::from("from"), &value).unwrap();
+                let value = JsValue::from(*to);
+                js_sys::Reflect::set(&result, &JsValue::from("to"), &value).unwrap();
+            }
+            _ => {
+                // changes not known to this version of bindings are skipped
+                let value = JsValue::from(0);
+                js_sys::Reflect::set(&result, &JsValue::from("retain"), &value).unwrap();
+            }
         }
         result.into()
     }
@@ -665,6 +765,50 @@ pub(crate) mod convert {
         result.into()
     }
 
+    /// Builds a plain JS object shaped like a yjs `YEvent`, so that it can be passed to libraries
+    /// written against yjs event API. `keys` is expected to be a JS object mapping changed keys
+    /// into their change descriptors or `undefined` if a corresponding event has no key changes.
+    pub fn yjs_event_into_js(
+        target: JsValue,
+        path: JsValue,
+        delta: JsValue,
+        keys: JsValue,
+        origin: JsValue,
+    ) -> crate::Result<JsValue> {
+        let keys_map = js_sys::Map::new();
+        let keys_changed = js_sys::Set::new(&JsValue::UNDEFINED);
+        if keys.is_object() {
+            for entry in js_sys::Object::entries(&keys.into()).iter() {
+                let entry: js_sys::Array = entry.into();
+                let key = entry.get(0);
+                keys_map.set(&key, &entry.get(1));
+                keys_changed.add(&key);
+            }
+        }
+        let changes = js_sys::Object::new();
+        js_sys::Reflect::set(&changes, &JsValue::from("delta"), &delta)?;
+        js_sys::Reflect::set(&changes, &JsValue::from("keys"), &keys_map)?;
+        // individual block items are not exposed by ywasm
+        let empty = js_sys::Set::new(&JsValue::UNDEFINED);
+        js_sys::Reflect::set(&changes, &JsValue::from("added"), &empty)?;
+        let empty = js_sys::Set::new(&JsValue::UNDEFINED);
+        js_sys::Reflect::set(&changes, &JsValue::from("deleted"), &empty)?;
+
+        let transaction = js_sys::Object::new();
+        js_sys::Reflect::set(&transaction, &JsValue::from("origin"), &origin)?;
+
+        let result = js_sys::Object::new();
+        js_sys::Reflect::set(&result, &JsValue::from("target"), &target)?;
+        js_sys::Reflect::set(&result, &JsValue::from("currentTarget"), &target)?;
+        js_sys::Reflect::set(&result, &JsValue::from("path"), &path)?;
+        js_sys::Reflect::set(&result, &JsValue::from("delta"), &delta)?;
+        js_sys::Reflect::set(&result, &JsValue::from("keys"), &keys_map)?;
+        js_sys::Reflect::set(&result, &JsValue::from("keysChanged"), &keys_changed)?;
+        js_sys::Reflect::set(&result, &JsValue::from("changes"), &changes)?;
+        js_sys::Reflect::set(&result, &JsValue::from("transaction"), &transaction)?;
+        Ok(result.into())
+    }
+
     pub fn events_into_js(txn: &TransactionMut, e: &Events) -> JsValue {
         let mut array = js_sys::Array::new();
         let mapped = e.iter().map(|e| {
@@ -675,6 +819,8 @@ pub(crate) mod convert {
                 Event::Weak(e) => YWeakLinkEvent::new(e, txn).into(),
                 Event::XmlFragment(e) => YXmlEvent::new(e, txn).into(),
                 Event::XmlText(e) => YXmlTextEvent::new(e, txn).into(),
+                // keyed lists and sets are not supported by ywasm yet
+                Event::KeyedList(_) | Event::Set(_) => JsValue::UNDEFINED,
             };
             js
         });
@@ -734,10 +880,10 @@ pub(crate) mod convert {
         let result = if let Some(func) = compute_ychange {
             let id =
                 JsValue::from_serde(&change.id).map_err(|e| JsValue::from_str(&e.to_string()))?;
-            func.call2(&JsValue::UNDEFINED, &kind, &id).unwrap()
+            func.call2(&JsValue::UNDEFINED, &kind, &id)?
         } else {
             let js: JsValue = js_sys::Object::new().into();
-            js_sys::Reflect::set(&js, &JsValue::from("type"), &kind).unwrap();
+            js_sys::Reflect::set(&js, &JsValue::from("type"), &kind)?;
             js
         };
         Ok(result)
@@ -751,11 +897,11 @@ pub(crate) mod convert {
                 Ok(attrs) if attrs.is_object() => attrs,
                 _ => {
                     let attrs: JsValue = js_sys::Object::new().into();
-                    js_sys::Reflect::set(&js, &JsValue::from("attributes"), &attrs).unwrap();
+                    js_sys::Reflect::set(&js, &JsValue::from("attributes"), &attrs)?;
                     attrs
                 }
             };
-            js_sys::Reflect::set(&attrs, &JsValue::from("ychange"), &ychange).unwrap();
+            js_sys::Reflect::set(&attrs, &JsValue::from("ychange"), &ychange)?;
         }
         Ok(js)
     }
@@ -775,4 +921,205 @@ pub(crate) mod errors {
     pub const NOT_XML_TYPE: &'static str = "provided object is not a valid XML shared type";
     pub const NOT_PRELIM: &'static str = "this operation only works on preliminary types";
     pub const NOT_WASM_OBJ: &'static str = "provided reference is not a WebAssembly object";
+    pub const BIGINT_OVERFLOW: &'static str = "BigInt value doesn't fit into 64-bit integer";
+    pub const IDB_CLOSED: &'static str = "IndexedDB database is closed";
+    pub const IDB_UNSUPPORTED: &'static str = "IndexedDB is not supported in this environment";
+}
+
+/// Exceptions thrown by user callbacks, which are called from within yrs event handlers. Since
+/// these handlers cannot return errors, exceptions are stashed instead of aborting wasm module and
+/// rethrown once the ywasm operation which triggered them completes.
+pub(crate) mod callbacks {
+    use std::cell::RefCell;
+    use wasm_bindgen::JsValue;
+
+    thread_local! {
+        static EXCEPTION: RefCell<Option<JsValue>> = RefCell::new(None);
+    }
+
+    /// Stashes an exception thrown by a user callback, if there was any. Only the first exception
+    /// thrown within the scope of a current [guard] is kept.
+    pub fn catch(result: crate::Result<JsValue>) {
+        if let Err(e) = result {
+            EXCEPTION.with(|cell| {
+                let mut cell = cell.borrow_mut();
+                if cell.is_none() {
+                    *cell = Some(e);
+                }
+            });
+        }
+    }
+
+    /// Executes `f`, rethrowing the first exception thrown by user callbacks triggered during its
+    /// execution (e.g. when a transaction is committed). Errors returned by `f` itself take
+    /// precedence. Guards can be nested: exceptions are always rethrown by the innermost one.
+    pub fn guard<T, F>(f: F) -> crate::Result<T>
+    where
+        F: FnOnce() -> crate::Result<T>,
+    {
+        let outer = take();
+        let result = f();
+        let thrown = take();
+        EXCEPTION.with(|cell| *cell.borrow_mut() = outer);
+        match (result, thrown) {
+            (Ok(_), Some(e)) => Err(e),
+            (result, _) => result,
+        }
+    }
+
+    fn take() -> Option<JsValue> {
+        EXCEPTION.with(|cell| cell.borrow_mut().take())
+    }
+}
+
+/// Registry of non-string JavaScript values used as transaction origins. Yrs origins are binary,
+/// so such values are represented by a tagged identifier of their registry entry, which is later
+/// used to give the very same JS value back to observer callbacks. Numbers are encoded inline.
+/// Objects are held weakly and their entries are removed once they are garbage collected, while
+/// other primitives (booleans, `null`, `undefined`, symbols and big integers) are kept for
+/// the lifetime of the module.
+pub(crate) mod origins {
+    use js_sys::{Function, Map, Object, WeakMap};
+    use std::cell::Cell;
+    use std::convert::TryInto;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::prelude::wasm_bindgen;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    /// First byte of binary origins referring to a registry entry. It never occurs in UTF-8
+    /// strings, so registered values cannot be confused with string origins.
+    const TAG: u8 = 0xff;
+
+    /// First byte of binary origins representing a number, followed by its 8-byte big endian
+    /// IEEE 754 representation.
+    const TAG_NUMBER: u8 = 0xfe;
+
+    #[wasm_bindgen]
+    extern "C" {
+        type WeakRef;
+
+        #[wasm_bindgen(constructor)]
+        fn new(target: &JsValue) -> WeakRef;
+
+        #[wasm_bindgen(method)]
+        fn deref(this: &WeakRef) -> JsValue;
+
+        type FinalizationRegistry;
+
+        #[wasm_bindgen(constructor)]
+        fn new(cleanup: &Function) -> FinalizationRegistry;
+
+        #[wasm_bindgen(method)]
+        fn register(this: &FinalizationRegistry, target: &JsValue, held: &JsValue);
+    }
+
+    struct Registry {
+        /// Identifiers of registered objects.
+        objects: WeakMap,
+        /// Identifiers of registered primitive values.
+        primitives: Map,
+        /// Registered values by their identifiers. Objects are stored as weak references.
+        values: Map,
+        /// Removes entries of garbage collected objects from `values`.
+        finalizer: FinalizationRegistry,
+        next_id: Cell<u32>,
+    }
+
+    impl Registry {
+        fn new() -> Self {
+            let values = Map::new();
+            let cleanup = {
+                let values = values.clone();
+                Closure::wrap(Box::new(move |id: JsValue| {
+                    values.delete(&id);
+                }) as Box<dyn FnMut(JsValue)>)
+            };
+            let finalizer = FinalizationRegistry::new(cleanup.into_js_value().unchecked_ref());
+            Registry {
+                objects: WeakMap::new(),
+                primitives: Map::new(),
+                values,
+                finalizer,
+                next_id: Cell::new(0),
+            }
+        }
+
+        fn id(&self, value: &JsValue) -> u32 {
+            if value.is_object() || value.is_function() {
+                let key: &Object = value.unchecked_ref();
+                if let Some(id) = self.objects.get(key).as_f64() {
+                    return id as u32;
+                }
+                let id = self.next_id();
+                self.objects.set(key, &id);
+                self.values.set(&id, &WeakRef::new(value));
+                self.finalizer.register(value, &id);
+                id.as_f64().unwrap() as u32
+            } else {
+                if let Some(id) = self.primitives.get(value).as_f64() {
+                    return id as u32;
+                }
+                let id = self.next_id();
+                self.primitives.set(value, &id);
+                self.values.set(&id, value);
+                id.as_f64().unwrap() as u32
+            }
+        }
+
+        fn next_id(&self) -> JsValue {
+            let id = self.next_id.get();
+            self.next_id.set(id + 1);
+            JsValue::from(id)
+        }
+
+        fn get(&self, id: u32) -> Option<JsValue> {
+            let id = JsValue::from(id);
+            if !self.values.has(&id) {
+                return None;
+            }
+            let value = self.values.get(&id);
+            match value.dyn_ref::<WeakRef>() {
+                Some(weak) => Some(weak.deref()),
+                None => Some(value),
+            }
+        }
+    }
+
+    thread_local! {
+        static REGISTRY: Registry = Registry::new();
+    }
+
+    /// Returns a binary representation of a given `value`, registering it first if it has not
+    /// been used as an origin before.
+    pub fn register(value: &JsValue) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9);
+        if let Some(n) = value.as_f64() {
+            bytes.push(TAG_NUMBER);
+            bytes.extend_from_slice(&n.to_be_bytes());
+        } else {
+            let id = REGISTRY.with(|registry| registry.id(value));
+            bytes.push(TAG);
+            bytes.extend_from_slice(&id.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Returns a JS value registered under a given binary origin representation, if any.
+    pub fn lookup(bytes: &[u8]) -> Option<JsValue> {
+        match bytes {
+            [TAG_NUMBER, n @ ..] if n.len() == 8 => {
+                Some(JsValue::from_f64(f64::from_be_bytes(n.try_into().unwrap())))
+            }
+            [TAG, id @ ..] if id.len() == 4 => {
+                let id = u32::from_be_bytes(id.try_into().unwrap());
+                REGISTRY.with(|registry| registry.get(id))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks if two JS values represent the same transaction origin.
+    pub fn same(a: &JsValue, b: &JsValue) -> bool {
+        js_sys::Object::is(a, b)
+    }
 }
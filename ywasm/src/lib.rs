@@ -12,6 +12,8 @@ mod array;
 mod awareness;
 mod collection;
 mod doc;
+#[cfg(feature = "indexeddb")]
+mod idb;
 mod js;
 mod map;
 mod text;
@@ -27,6 +29,8 @@ type Result<T> = std::result::Result<T, JsValue>;
 pub use crate::array::YArray as Array;
 pub use crate::array::YArrayEvent as ArrayEvent;
 pub use crate::doc::YDoc as Doc;
+#[cfg(feature = "indexeddb")]
+pub use crate::idb::YIndexeddbPersistence as IndexeddbPersistence;
 use crate::js::Shared;
 pub use crate::map::YMap as Map;
 pub use crate::map::YMapEvent as MapEvent;
@@ -204,10 +208,12 @@ pub fn apply_update(doc: &Doc, update: js_sys::Uint8Array, origin: JsValue) -> R
     };
     let mut txn = txn.map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_TX))?;
     let diff: Vec<u8> = update.to_vec();
-    match Update::decode_v1(&diff) {
-        Ok(update) => Ok(txn.apply_update(update)),
+    js::callbacks::guard(move || match Update::decode_v1(&diff) {
+        Ok(update) => txn
+            .apply_update(update)
+            .map_err(|e| JsValue::from(e.to_string())),
         Err(e) => Err(JsValue::from(e.to_string())),
-    }
+    })
 }
 
 /// Applies delta update generated by the remote document replica to a current document. This
@@ -237,10 +243,143 @@ pub fn apply_update_v2(doc: &Doc, update: js_sys::Uint8Array, origin: JsValue) -
     };
     let mut txn = txn.map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_TX))?;
     let diff: Vec<u8> = update.to_vec();
-    match Update::decode_v2(&diff) {
-        Ok(update) => Ok(txn.apply_update(update)),
+    js::callbacks::guard(move || match Update::decode_v2(&diff) {
+        Ok(update) => txn
+            .apply_update(update)
+            .map_err(|e| JsValue::from(e.to_string())),
         Err(e) => Err(JsValue::from(e.to_string())),
+    })
+}
+
+/// Applies delta update generated by the remote document replica to a current document in
+/// multiple chunks, yielding to the JavaScript event loop in between them. This way a large
+/// update (eg. during initial synchronization) doesn't block the main thread for the whole time
+/// of its integration. This method assumes that a payload maintains lib0 v1 encoding format.
+///
+/// Every chunk is applied within its own transaction. Optional `options` object can contain
+/// following fields:
+///
+/// - `chunkSize`: minimal number of update blocks integrated within a single chunk (default: 1000).
+///   Blocks of a single client are always integrated together.
+/// - `onProgress`: callback called after each chunk with the number of processed blocks and
+///   the total number of blocks in the update.
+/// - `origin`: origin assigned to the transactions used to apply the update.
+///
+/// Returns a promise, which completes once the whole update has been applied.
+///
+/// Example:
+///
+/// ```javascript
+/// import {YDoc, encodeStateAsUpdate, applyUpdateChunked} from 'ywasm'
+///
+/// const remoteDoc = new YDoc()
+/// const update = encodeStateAsUpdate(remoteDoc)
+///
+/// const localDoc = new YDoc()
+/// await applyUpdateChunked(localDoc, update, {
+///     chunkSize: 500,
+///     onProgress: (processed, total) => console.log(`synced ${processed}/${total}`)
+/// })
+/// ```
+#[wasm_bindgen(js_name = applyUpdateChunked)]
+pub fn apply_update_chunked(
+    doc: &Doc,
+    update: js_sys::Uint8Array,
+    options: JsValue,
+) -> Result<js_sys::Promise> {
+    let mut chunk_size = 1000;
+    let mut on_progress = None;
+    let mut origin = JsValue::UNDEFINED;
+    if options.is_object() {
+        if let Ok(js) = js_sys::Reflect::get(&options, &JsValue::from_str("chunkSize")) {
+            if let Some(size) = js.as_f64() {
+                chunk_size = (size as usize).max(1);
+            }
+        }
+        if let Ok(js) = js_sys::Reflect::get(&options, &JsValue::from_str("onProgress")) {
+            if js.is_function() {
+                on_progress = Some(js_sys::Function::from(js));
+            }
+        }
+        if let Ok(js) = js_sys::Reflect::get(&options, &JsValue::from_str("origin")) {
+            origin = js;
+        }
     }
+    let diff: Vec<u8> = update.to_vec();
+    let mut update = Update::decode_v1(&diff).map_err(|e| JsValue::from(e.to_string()))?;
+    let doc = doc.0.clone();
+    let future = async move {
+        let mut total = None;
+        loop {
+            let mut progress = None;
+            let rest = js::callbacks::guard(|| {
+                let txn = if !origin.is_undefined() {
+                    doc.try_transact_mut_with(js::Js::from(origin.clone()))
+                } else {
+                    doc.try_transact_mut()
+                };
+                let mut txn = txn.map_err(|_| JsValue::from_str(crate::js::errors::ANOTHER_TX))?;
+                txn.apply_update_with_progress(update, |p| {
+                    // remaining updates report their progress relative to what's left
+                    let total = *total.get_or_insert(p.total);
+                    let processed = total - (p.total - p.processed);
+                    progress = Some((processed, total));
+                    p.processed < chunk_size
+                })
+                .map_err(|e| JsValue::from(e.to_string()))
+            })?;
+            if let (Some(callback), Some((processed, total))) = (&on_progress, progress) {
+                callback.call2(
+                    &JsValue::UNDEFINED,
+                    &JsValue::from(processed as f64),
+                    &JsValue::from(total as f64),
+                )?;
+            }
+            match rest {
+                Some(rest) => {
+                    update = rest;
+                    yield_to_event_loop().await?;
+                }
+                None => return Ok(JsValue::UNDEFINED),
+            }
+        }
+    };
+    Ok(wasm_bindgen_futures::future_to_promise(future))
+}
+
+/// Returns a future, which completes after JavaScript event loop had a chance to process other
+/// pending tasks.
+async fn yield_to_event_loop() -> Result<()> {
+    let set_timeout = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))?;
+    let set_timeout = js_sys::Function::from(set_timeout);
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = set_timeout.call2(&JsValue::UNDEFINED, &resolve, &JsValue::from(0));
+    });
+    wasm_bindgen_futures::JsFuture::from(promise).await?;
+    Ok(())
+}
+
+/// Checks if two values represent the same transaction origin. Strings and numbers are compared
+/// by value, while objects are compared by reference, so the origin received by an observer
+/// callback (`transaction.origin` or `event.origin`) can be compared with the one passed when the
+/// transaction was created.
+///
+/// Example:
+///
+/// ```javascript
+/// import {YDoc, sameOrigin} from 'ywasm'
+///
+/// const provider = {}
+/// const doc = new YDoc()
+/// doc.on('afterTransaction', txn => {
+///     if (sameOrigin(txn.origin, provider)) {
+///         // ignore changes made by provider itself
+///     }
+/// })
+/// ```
+#[wasm_bindgen(js_name = sameOrigin)]
+pub fn same_origin(a: &JsValue, b: &JsValue) -> bool {
+    crate::js::origins::same(a, b)
 }
 
 #[wasm_bindgen]
@@ -7,6 +7,7 @@ use gloo_utils::format::JsValueSerdeExt;
 use std::collections::HashMap;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
+use yrs::observer::SubscriptionKey;
 use yrs::types::map::MapEvent;
 use yrs::types::{ToJson, TYPE_REFS_MAP};
 use yrs::{DeepObservable, Map, MapRef, Observable, TransactionMut};
@@ -22,6 +23,23 @@ use yrs::{DeepObservable, Map, MapRef, Observable, TransactionMut};
 #[wasm_bindgen]
 pub struct YMap(pub(crate) SharedCollection<HashMap<String, JsValue>, MapRef>);
 
+impl YMap {
+    pub(crate) fn to_plain(&self, txn: &ImplicitTransaction) -> crate::Result<JsValue> {
+        match &self.0 {
+            SharedCollection::Prelim(c) => {
+                let map = js_sys::Object::new();
+                for (k, v) in c.iter() {
+                    js_sys::Reflect::set(&map, &k.into(), &Js::to_plain(v, txn)?)?;
+                }
+                Ok(map.into())
+            }
+            SharedCollection::Integrated(c) => {
+                c.readonly(txn, |c, txn| Ok(Js::from_any(&c.to_json(txn)).into()))
+            }
+        }
+    }
+}
+
 #[wasm_bindgen]
 impl YMap {
     /// Creates a new preliminary instance of a `YMap` shared data type, with its state
@@ -107,6 +125,23 @@ impl YMap {
         }
     }
 
+    /// Converts contents of this `YMap` instance into their JSON representation. If `options`
+    /// object has a `plain: true` flag set, returned value is guaranteed to consist only of plain
+    /// JS objects, arrays and primitives (no wasm-backed handles), which makes it safe to send via
+    /// `postMessage` or store in IndexedDB.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(
+        &self,
+        options: JsValue,
+        txn: &ImplicitTransaction,
+    ) -> crate::Result<JsValue> {
+        if Js::plain_option(&options) {
+            self.to_plain(txn)
+        } else {
+            self.to_json(txn)
+        }
+    }
+
     /// Sets a given `key`-`value` entry within this instance of `YMap`. If another entry was
     /// already stored under given `key`, it will be overridden with new `value`.
     #[wasm_bindgen(js_name = set)]
@@ -122,7 +157,9 @@ impl YMap {
                 Ok(())
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
-                c.insert(txn, key.to_string(), Js::new(value));
+                let value = Js::new(value);
+                value.validate_prelim()?;
+                c.insert(txn, key.to_string(), value);
                 Ok(())
             }),
         }
@@ -233,12 +270,11 @@ impl YMap {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_with(abi, move |txn, e| {
+                array.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YMapEvent::new(e, txn);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e.into(), &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e.into(), &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -256,7 +292,7 @@ impl YMap {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve(abi))
+                Ok(shared_ref.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -274,12 +310,11 @@ impl YMap {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                shared_ref.observe_deep_with(abi, move |txn, e| {
+                shared_ref.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -297,7 +332,7 @@ impl YMap {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve_deep(abi))
+                Ok(shared_ref.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -375,4 +410,19 @@ impl YMapEvent {
             Ok(keys)
         }
     }
+
+    /// Returns a plain JS object representing current event in a shape compatible with yjs
+    /// `YEvent` (with `target`, `path`, `changes.delta`, `changes.keys`, `keysChanged` and
+    /// `transaction.origin` fields), which can be passed to editor bindings written for yjs.
+    /// `currentTarget` is always the same as `target`.
+    #[wasm_bindgen(js_name = toYjs)]
+    pub fn to_yjs(&mut self) -> crate::Result<JsValue> {
+        crate::js::convert::yjs_event_into_js(
+            self.target(),
+            self.path(),
+            js_sys::Array::new().into(),
+            self.keys()?,
+            self.origin(),
+        )
+    }
 }
@@ -5,6 +5,7 @@ use crate::weak::YWeakLink;
 use crate::{ImplicitTransaction, YSnapshot};
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
+use yrs::observer::SubscriptionKey;
 use yrs::types::text::TextEvent;
 use yrs::types::{Attrs, TYPE_REFS_TEXT};
 use yrs::{DeepObservable, GetString, Observable, Quotable, Text, TextRef, TransactionMut};
@@ -102,6 +103,18 @@ impl YText {
         }
     }
 
+    /// Returns an underlying shared string stored in this data type. Strings are always
+    /// structured clone friendly, so `options` have no effect here. This method exists for
+    /// parity with other shared types.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(
+        &self,
+        _options: JsValue,
+        txn: &ImplicitTransaction,
+    ) -> crate::Result<JsValue> {
+        self.to_json(txn)
+    }
+
     /// Inserts a given `chunk` of text into this `YText` instance, starting at a given `index`.
     ///
     /// Optional object with defined `attributes` will be used to wrap provided text `chunk`
@@ -156,11 +169,13 @@ impl YText {
                 Err(JsValue::from_str(crate::js::errors::INVALID_PRELIM_OP))
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
+                let embed = Js::new(embed);
+                embed.validate_prelim()?;
                 if attributes.is_undefined() || attributes.is_null() {
-                    c.insert_embed(txn, index, Js::new(embed));
+                    c.insert_embed(txn, index, embed);
                     Ok(())
                 } else if let Some(attrs) = Self::parse_fmt(attributes) {
-                    c.insert_embed_with_attributes(txn, index, Js::new(embed), attrs);
+                    c.insert_embed_with_attributes(txn, index, embed, attrs);
                     Ok(())
                 } else {
                     Err(JsValue::from_str(crate::js::errors::INVALID_FMT))
@@ -314,9 +329,20 @@ impl YText {
                 let hi = snapshot.map(|s| s.0);
                 let lo = prev_snapshot.map(|s| s.0);
                 let array = js_sys::Array::new();
+                let error = std::cell::RefCell::new(None);
                 let delta = c.diff_range(txn, hi.as_ref(), lo.as_ref(), |change| {
-                    crate::js::convert::ychange_to_js(change, &compute_ychange).unwrap()
+                    match crate::js::convert::ychange_to_js(change, &compute_ychange) {
+                        Ok(js) => js,
+                        Err(e) => {
+                            // keep the first exception thrown by user's compute_ychange callback
+                            error.borrow_mut().get_or_insert(e);
+                            JsValue::UNDEFINED
+                        }
+                    }
                 });
+                if let Some(e) = error.into_inner() {
+                    return Err(e);
+                }
                 for d in delta {
                     let d = crate::js::convert::diff_into_js(d, &doc)?;
                     array.push(&d);
@@ -338,12 +364,11 @@ impl YText {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_with(abi, move |txn, e| {
+                array.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YTextEvent::new(e, txn);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e.into(), &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e.into(), &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -361,7 +386,7 @@ impl YText {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve(abi))
+                Ok(shared_ref.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -379,12 +404,11 @@ impl YText {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_deep_with(abi, move |txn, e| {
+                array.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -402,7 +426,7 @@ impl YText {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve_deep(abi))
+                Ok(shared_ref.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -480,4 +504,19 @@ impl YTextEvent {
             Ok(delta)
         }
     }
+
+    /// Returns a plain JS object representing current event in a shape compatible with yjs
+    /// `YEvent` (with `target`, `path`, `changes.delta`, `changes.keys`, `keysChanged` and
+    /// `transaction.origin` fields), which can be passed to editor bindings written for yjs.
+    /// `currentTarget` is always the same as `target`.
+    #[wasm_bindgen(js_name = toYjs)]
+    pub fn to_yjs(&mut self) -> crate::Result<JsValue> {
+        crate::js::convert::yjs_event_into_js(
+            self.target(),
+            self.path(),
+            self.delta()?,
+            JsValue::UNDEFINED,
+            self.origin(),
+        )
+    }
 }
@@ -217,7 +217,9 @@ impl YTransaction {
                     None => JsValue::UNDEFINED,
                     Some(doc) => YDoc(doc).into(),
                 },
-                TypeRef::XmlHook | TypeRef::Undefined => JsValue::UNDEFINED,
+                TypeRef::XmlHook(_) | TypeRef::KeyedList | TypeRef::Set | TypeRef::Undefined => {
+                    JsValue::UNDEFINED
+                }
             },
         })
     }
@@ -230,8 +232,10 @@ impl YTransaction {
         let txn = self
             .as_mut()
             .map_err(|_| crate::js::errors::INVALID_TRANSACTION_CTX)?;
-        txn.commit();
-        Ok(())
+        crate::js::callbacks::guard(|| {
+            txn.commit();
+            Ok(())
+        })
     }
 
     /// Encodes a state vector of a given transaction document into its binary representation using
@@ -372,8 +376,8 @@ impl YTransaction {
 
     fn try_apply(&mut self, update: Update) -> Result<()> {
         let txn = self.as_mut()?;
-        txn.apply_update(update);
-        Ok(())
+        txn.apply_update(update)
+            .map_err(|e| JsValue::from(e.to_string()))
     }
 
     /// Applies delta update generated by the remote document replica to a current transaction's
@@ -6,7 +6,8 @@ use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
 use yrs::branch::BranchPtr;
-use yrs::undo::{EventKind, UndoManager};
+use yrs::observer::SubscriptionKey;
+use yrs::undo::{EventKind, UndoError, UndoManager, UndoOrigin};
 use yrs::{Doc, Transact};
 
 use crate::doc::YDoc;
@@ -43,8 +44,10 @@ impl YUndoManager {
         let mut o = yrs::undo::Options {
             capture_timeout_millis: 500,
             tracked_origins: HashSet::new(),
+            tracked_origin_types: HashSet::new(),
             capture_transaction: None,
             timestamp: Arc::new(crate::awareness::JsClock),
+            undo_origin: UndoOrigin::Manager,
         };
         if options.is_object() {
             if let Ok(js) = Reflect::get(&options, &JsValue::from_str("captureTimeout")) {
@@ -86,11 +89,10 @@ impl YUndoManager {
 
     #[wasm_bindgen(js_name = clear)]
     pub fn clear(&mut self) -> Result<()> {
-        if let Err(_) = self.0.clear() {
-            Err(JsValue::from_str(crate::js::errors::ANOTHER_TX))
-        } else {
-            Ok(())
-        }
+        crate::js::callbacks::guard(|| match self.0.clear() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(JsValue::from_str(crate::js::errors::ANOTHER_TX)),
+        })
     }
 
     #[wasm_bindgen(js_name = stopCapturing)]
@@ -100,20 +102,20 @@ impl YUndoManager {
 
     #[wasm_bindgen(js_name = undo)]
     pub fn undo(&mut self) -> Result<()> {
-        if let Err(_) = self.0.undo() {
-            Err(JsValue::from_str(crate::js::errors::ANOTHER_TX))
-        } else {
-            Ok(())
-        }
+        crate::js::callbacks::guard(|| match self.0.undo() {
+            Ok(_) => Ok(()),
+            Err(UndoError::Transaction(_)) => Err(JsValue::from_str(crate::js::errors::ANOTHER_TX)),
+            Err(e) => Err(JsValue::from_str(&e.to_string())),
+        })
     }
 
     #[wasm_bindgen(js_name = redo)]
     pub fn redo(&mut self) -> Result<()> {
-        if let Err(_) = self.0.redo() {
-            Err(JsValue::from_str(crate::js::errors::ANOTHER_TX))
-        } else {
-            Ok(())
-        }
+        crate::js::callbacks::guard(|| match self.0.redo() {
+            Ok(_) => Ok(()),
+            Err(UndoError::Transaction(_)) => Err(JsValue::from_str(crate::js::errors::ANOTHER_TX)),
+            Err(e) => Err(JsValue::from_str(&e.to_string())),
+        })
     }
 
     #[wasm_bindgen(getter, js_name = canUndo)]
@@ -130,30 +132,51 @@ impl YUndoManager {
     pub fn on(&mut self, event: &str, callback: js_sys::Function) -> crate::Result<()> {
         let abi = callback.subscription_key();
         match event {
-            "stack-item-added" => self.0.observe_item_added_with(abi, move |txn, e| {
-                let event: JsValue = YUndoEvent::new(e).into();
-                let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &event, &txn).unwrap();
-                let meta =
-                    Reflect::get(&event, &JsValue::from_str("meta")).unwrap_or(JsValue::UNDEFINED);
-                *e.meta_mut() = meta;
-            }),
-            "stack-item-popped" => self.0.observe_item_popped_with(abi, move |txn, e| {
-                let event: JsValue = YUndoEvent::new(e).into();
-                let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &event, &txn).unwrap();
-                let meta =
-                    Reflect::get(&event, &JsValue::from_str("meta")).unwrap_or(JsValue::UNDEFINED);
-                *e.meta_mut() = meta;
-            }),
-            "stack-item-updated" => self.0.observe_item_updated_with(abi, move |txn, e| {
-                let event: JsValue = YUndoEvent::new(e).into();
-                let txn: JsValue = YTransaction::from_ref(txn).into();
-                callback.call2(&JsValue::UNDEFINED, &event, &txn).unwrap();
-                let meta =
-                    Reflect::get(&event, &JsValue::from_str("meta")).unwrap_or(JsValue::UNDEFINED);
-                *e.meta_mut() = meta;
-            }),
+            "stack-item-added" => {
+                self.0
+                    .observe_item_added_with(SubscriptionKey::new(abi), move |txn, e| {
+                        let event: JsValue = YUndoEvent::new(e).into();
+                        let txn: JsValue = YTransaction::from_ref(txn).into();
+                        crate::js::callbacks::catch(callback.call2(
+                            &JsValue::UNDEFINED,
+                            &event,
+                            &txn,
+                        ));
+                        let meta = Reflect::get(&event, &JsValue::from_str("meta"))
+                            .unwrap_or(JsValue::UNDEFINED);
+                        *e.meta_mut() = meta;
+                    })
+            }
+            "stack-item-popped" => {
+                self.0
+                    .observe_item_popped_with(SubscriptionKey::new(abi), move |txn, e| {
+                        let event: JsValue = YUndoEvent::new(e).into();
+                        let txn: JsValue = YTransaction::from_ref(txn).into();
+                        crate::js::callbacks::catch(callback.call2(
+                            &JsValue::UNDEFINED,
+                            &event,
+                            &txn,
+                        ));
+                        let meta = Reflect::get(&event, &JsValue::from_str("meta"))
+                            .unwrap_or(JsValue::UNDEFINED);
+                        *e.meta_mut() = meta;
+                    })
+            }
+            "stack-item-updated" => {
+                self.0
+                    .observe_item_updated_with(SubscriptionKey::new(abi), move |txn, e| {
+                        let event: JsValue = YUndoEvent::new(e).into();
+                        let txn: JsValue = YTransaction::from_ref(txn).into();
+                        crate::js::callbacks::catch(callback.call2(
+                            &JsValue::UNDEFINED,
+                            &event,
+                            &txn,
+                        ));
+                        let meta = Reflect::get(&event, &JsValue::from_str("meta"))
+                            .unwrap_or(JsValue::UNDEFINED);
+                        *e.meta_mut() = meta;
+                    })
+            }
             unknown => return Err(JsValue::from_str(&format!("Unknown event: {}", unknown))),
         }
         Ok(())
@@ -163,9 +186,9 @@ impl YUndoManager {
     pub fn off(&mut self, event: &str, callback: js_sys::Function) -> crate::Result<bool> {
         let abi = callback.subscription_key();
         match event {
-            "stack-item-added" => Ok(self.0.unobserve_item_added(abi)),
-            "stack-item-popped" => Ok(self.0.unobserve_item_popped(abi)),
-            "stack-item-updated" => Ok(self.0.unobserve_item_updated(abi)),
+            "stack-item-added" => Ok(self.0.unobserve_item_added(SubscriptionKey::new(abi))),
+            "stack-item-popped" => Ok(self.0.unobserve_item_popped(SubscriptionKey::new(abi))),
+            "stack-item-updated" => Ok(self.0.unobserve_item_updated(SubscriptionKey::new(abi))),
             unknown => Err(JsValue::from_str(&format!("Unknown event: {}", unknown))),
         }
     }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 use yrs::branch::BranchPtr;
+use yrs::observer::SubscriptionKey;
 use yrs::types::weak::{LinkSource, WeakEvent};
 use yrs::types::TYPE_REFS_WEAK;
 use yrs::{
@@ -40,6 +41,26 @@ impl YWeakLink {
             SharedCollection::Prelim(v) => v.prelim.source().clone(),
         }
     }
+
+    fn is_single(&self, txn: &ImplicitTransaction) -> Result<bool> {
+        match &self.0 {
+            SharedCollection::Prelim(c) => Ok(c.prelim.source().is_single()),
+            SharedCollection::Integrated(c) => c.readonly(txn, |c, _| Ok(c.source().is_single())),
+        }
+    }
+
+    pub(crate) fn to_plain(&self, txn: &ImplicitTransaction) -> Result<JsValue> {
+        if self.is_single(txn)? {
+            let value = self.deref(txn)?;
+            Js::to_plain(&value, txn)
+        } else {
+            let result = js_sys::Array::new();
+            for value in self.unquote(txn)?.iter() {
+                result.push(&Js::to_plain(&value, txn)?);
+            }
+            Ok(result.into())
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -153,6 +174,21 @@ impl YWeakLink {
         }
     }
 
+    /// Returns JSON representation of the value(s) this `YWeakLink` points to: a single value
+    /// for links created with `YMap.link`, or an array of values for quotations. If `options`
+    /// object has a `plain: true` flag set, returned value is guaranteed to consist only of plain
+    /// JS objects, arrays and primitives (no wasm-backed handles).
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(&self, options: JsValue, txn: &ImplicitTransaction) -> Result<JsValue> {
+        if Js::plain_option(&options) {
+            self.to_plain(txn)
+        } else if self.is_single(txn)? {
+            self.deref(txn)
+        } else {
+            Ok(self.unquote(txn)?.into())
+        }
+    }
+
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self, txn: &ImplicitTransaction) -> Result<String> {
         use yrs::XmlTextRef;
@@ -195,12 +231,11 @@ impl YWeakLink {
                 let txn = c.transact()?;
                 let weak = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                weak.observe_with(abi, move |txn, e| {
+                weak.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YWeakLinkEvent::new(e, txn).into();
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -218,7 +253,7 @@ impl YWeakLink {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve(abi))
+                Ok(shared_ref.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -236,12 +271,11 @@ impl YWeakLink {
                 let txn = c.transact()?;
                 let weak = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                weak.observe_deep_with(abi, move |txn, e| {
+                weak.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -259,7 +293,7 @@ impl YWeakLink {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve_deep(abi))
+                Ok(shared_ref.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -8,6 +8,7 @@ use std::collections::HashMap;
 use std::iter::FromIterator;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
+use yrs::observer::SubscriptionKey;
 use yrs::types::TYPE_REFS_XML_ELEMENT;
 use yrs::{DeepObservable, GetString, Observable, Xml, XmlElementRef, XmlFragment};
 
@@ -160,7 +161,9 @@ impl YXmlElement {
                 Ok(())
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
-                c.insert(txn, index, Js::new(xml_node));
+                let xml_node = Js::new(xml_node);
+                xml_node.validate_prelim()?;
+                c.insert(txn, index, xml_node);
                 Ok(())
             }),
         }
@@ -175,7 +178,9 @@ impl YXmlElement {
                 Ok(())
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
-                c.push_back(txn, Js::new(xml_node));
+                let xml_node = Js::new(xml_node);
+                xml_node.validate_prelim()?;
+                c.push_back(txn, xml_node);
                 Ok(())
             }),
         }
@@ -269,6 +274,18 @@ impl YXmlElement {
         }
     }
 
+    /// Returns a string representation of this XML node. Strings are always structured clone
+    /// friendly, so `options` have no effect here. This method exists for parity with other
+    /// shared types.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(
+        &self,
+        _options: JsValue,
+        txn: &ImplicitTransaction,
+    ) -> crate::Result<JsValue> {
+        Ok(self.to_string(txn)?.into())
+    }
+
     /// Returns a string representation of this XML node.
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self, txn: &ImplicitTransaction) -> crate::Result<String> {
@@ -347,7 +364,7 @@ impl YXmlElement {
                     js_sys::Reflect::set(
                         &map,
                         &JsValue::from_str(name),
-                        &JsValue::from_str(&value),
+                        &JsValue::from_str(&value.to_string()),
                     )?;
                 }
                 Ok(map.into())
@@ -387,12 +404,11 @@ impl YXmlElement {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_with(abi, move |txn, e| {
+                array.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YXmlEvent::new(e, txn);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e.into(), &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e.into(), &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -410,7 +426,7 @@ impl YXmlElement {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve(abi))
+                Ok(shared_ref.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -428,12 +444,11 @@ impl YXmlElement {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_deep_with(abi, move |txn, e| {
+                array.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -451,7 +466,7 @@ impl YXmlElement {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve_deep(abi))
+                Ok(shared_ref.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -5,6 +5,7 @@ use crate::ImplicitTransaction;
 use std::iter::FromIterator;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
+use yrs::observer::SubscriptionKey;
 use yrs::types::xml::XmlEvent;
 use yrs::types::TYPE_REFS_XML_FRAGMENT;
 use yrs::{DeepObservable, GetString, Observable, TransactionMut, XmlFragment, XmlFragmentRef};
@@ -85,7 +86,9 @@ impl YXmlFragment {
                 Ok(())
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
-                c.insert(txn, index, Js::new(xml_node));
+                let xml_node = Js::new(xml_node);
+                xml_node.validate_prelim()?;
+                c.insert(txn, index, xml_node);
                 Ok(())
             }),
         }
@@ -100,7 +103,9 @@ impl YXmlFragment {
                 Ok(())
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
-                c.push_back(txn, Js::new(xml_node));
+                let xml_node = Js::new(xml_node);
+                xml_node.validate_prelim()?;
+                c.push_back(txn, xml_node);
                 Ok(())
             }),
         }
@@ -139,6 +144,18 @@ impl YXmlFragment {
         }
     }
 
+    /// Returns a string representation of this XML node. Strings are always structured clone
+    /// friendly, so `options` have no effect here. This method exists for parity with other
+    /// shared types.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(
+        &self,
+        _options: JsValue,
+        txn: &ImplicitTransaction,
+    ) -> crate::Result<JsValue> {
+        Ok(self.to_string(txn)?.into())
+    }
+
     /// Returns a string representation of this XML node.
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self, txn: &ImplicitTransaction) -> crate::Result<String> {
@@ -192,12 +209,11 @@ impl YXmlFragment {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_with(abi, move |txn, e| {
+                array.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YXmlEvent::new(e, txn);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e.into(), &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e.into(), &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -215,7 +231,7 @@ impl YXmlFragment {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve(abi))
+                Ok(shared_ref.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -233,12 +249,11 @@ impl YXmlFragment {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_deep_with(abi, move |txn, e| {
+                array.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -256,7 +271,7 @@ impl YXmlFragment {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve_deep(abi))
+                Ok(shared_ref.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -337,6 +352,24 @@ impl YXmlEvent {
         }
     }
 
+    /// Returns a list of names of XML attributes, which have been added, updated or removed
+    /// within bounds of current transaction.
+    #[wasm_bindgen(getter, js_name = attributesChanged)]
+    pub fn attributes_changed(&self) -> js_sys::Array {
+        self.inner
+            .keys(self.txn)
+            .keys()
+            .map(|key| JsValue::from(key.as_ref()))
+            .collect()
+    }
+
+    /// Returns true if any child nodes of corresponding XML node have been inserted or removed
+    /// within bounds of current transaction. Details of these changes are available via `delta`.
+    #[wasm_bindgen(getter, js_name = childListChanged)]
+    pub fn child_list_changed(&self) -> bool {
+        self.inner.children_changed()
+    }
+
     /// Returns a list of XML child node changes made over corresponding `YXmlElement` collection
     /// within bounds of current transaction. These changes follow a format:
     ///
@@ -362,4 +395,19 @@ impl YXmlEvent {
             delta.clone()
         }
     }
+
+    /// Returns a plain JS object representing current event in a shape compatible with yjs
+    /// `YEvent` (with `target`, `path`, `changes.delta`, `changes.keys`, `keysChanged` and
+    /// `transaction.origin` fields), which can be passed to editor bindings written for yjs.
+    /// `currentTarget` is always the same as `target`.
+    #[wasm_bindgen(js_name = toYjs)]
+    pub fn to_yjs(&mut self) -> crate::Result<JsValue> {
+        crate::js::convert::yjs_event_into_js(
+            self.target(),
+            self.path(),
+            self.delta(),
+            self.keys()?,
+            self.origin(),
+        )
+    }
 }
@@ -9,6 +9,7 @@ use gloo_utils::format::JsValueSerdeExt;
 use std::collections::HashMap;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
+use yrs::observer::SubscriptionKey;
 use yrs::types::xml::XmlTextEvent;
 use yrs::types::TYPE_REFS_XML_TEXT;
 use yrs::{DeepObservable, GetString, Observable, Quotable, Text, TransactionMut, Xml, XmlTextRef};
@@ -193,9 +194,20 @@ impl YXmlText {
                 let hi = snapshot.map(|s| s.0);
                 let lo = prev_snapshot.map(|s| s.0);
                 let array = js_sys::Array::new();
+                let error = std::cell::RefCell::new(None);
                 let delta = c.diff_range(txn, hi.as_ref(), lo.as_ref(), |change| {
-                    crate::js::convert::ychange_to_js(change, &compute_ychange).unwrap()
+                    match crate::js::convert::ychange_to_js(change, &compute_ychange) {
+                        Ok(js) => js,
+                        Err(e) => {
+                            // keep the first exception thrown by user's compute_ychange callback
+                            error.borrow_mut().get_or_insert(e);
+                            JsValue::UNDEFINED
+                        }
+                    }
                 });
+                if let Some(e) = error.into_inner() {
+                    return Err(e);
+                }
                 for d in delta {
                     let d = crate::js::convert::diff_into_js(d, &doc)?;
                     array.push(&d);
@@ -223,11 +235,13 @@ impl YXmlText {
                 Err(JsValue::from_str(crate::js::errors::INVALID_PRELIM_OP))
             }
             SharedCollection::Integrated(c) => c.mutably(txn, |c, txn| {
+                let embed = Js::new(embed);
+                embed.validate_prelim()?;
                 if attributes.is_undefined() || attributes.is_null() {
-                    c.insert_embed(txn, index, Js::new(embed));
+                    c.insert_embed(txn, index, embed);
                     Ok(())
                 } else if let Some(attrs) = YText::parse_fmt(attributes) {
-                    c.insert_embed_with_attributes(txn, index, Js::new(embed), attrs);
+                    c.insert_embed_with_attributes(txn, index, embed, attrs);
                     Ok(())
                 } else {
                     Err(JsValue::from_str(crate::js::errors::INVALID_FMT))
@@ -344,6 +358,18 @@ impl YXmlText {
         }
     }
 
+    /// Returns a string representation of this XML node. Strings are always structured clone
+    /// friendly, so `options` have no effect here. This method exists for parity with other
+    /// shared types.
+    #[wasm_bindgen(js_name = toJsonDeep)]
+    pub fn to_json_deep(
+        &self,
+        _options: JsValue,
+        txn: &ImplicitTransaction,
+    ) -> crate::Result<JsValue> {
+        Ok(self.to_string(txn)?.into())
+    }
+
     /// Returns an underlying string stored in this `YXmlText` instance.
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self, txn: &ImplicitTransaction) -> crate::Result<String> {
@@ -422,7 +448,7 @@ impl YXmlText {
                     js_sys::Reflect::set(
                         &map,
                         &JsValue::from_str(name),
-                        &JsValue::from_str(&value),
+                        &JsValue::from_str(&value.to_string()),
                     )?;
                 }
                 Ok(map.into())
@@ -442,12 +468,11 @@ impl YXmlText {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_with(abi, move |txn, e| {
+                array.observe_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = YXmlTextEvent::new(e, txn);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e.into(), &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e.into(), &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -465,7 +490,7 @@ impl YXmlText {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve(abi))
+                Ok(shared_ref.unobserve(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -483,12 +508,11 @@ impl YXmlText {
                 let txn = c.transact()?;
                 let array = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                array.observe_deep_with(abi, move |txn, e| {
+                array.observe_deep_with(SubscriptionKey::new(abi), move |txn, e| {
                     let e = crate::js::convert::events_into_js(txn, e);
                     let txn = YTransaction::from_ref(txn);
-                    callback
-                        .call2(&JsValue::UNDEFINED, &e, &txn.into())
-                        .unwrap();
+                    let result = callback.call2(&JsValue::UNDEFINED, &e, &txn.into());
+                    crate::js::callbacks::catch(result);
                 });
                 Ok(())
             }
@@ -506,7 +530,7 @@ impl YXmlText {
                 let txn = c.transact()?;
                 let shared_ref = c.resolve(&txn)?;
                 let abi = callback.subscription_key();
-                Ok(shared_ref.unobserve_deep(abi))
+                Ok(shared_ref.unobserve_deep(SubscriptionKey::new(abi)))
             }
         }
     }
@@ -587,6 +611,17 @@ impl YXmlTextEvent {
         }
     }
 
+    /// Returns a list of names of XML attributes, which have been added, updated or removed
+    /// within bounds of current transaction.
+    #[wasm_bindgen(getter, js_name = attributesChanged)]
+    pub fn attributes_changed(&self) -> js_sys::Array {
+        self.inner
+            .keys(self.txn)
+            .keys()
+            .map(|key| JsValue::from(key.as_ref()))
+            .collect()
+    }
+
     /// Returns a list of attribute changes made over corresponding `YXmlText` collection within
     /// bounds of current transaction. These changes follow a format:
     ///
@@ -609,4 +644,19 @@ impl YXmlTextEvent {
             Ok(keys)
         }
     }
+
+    /// Returns a plain JS object representing current event in a shape compatible with yjs
+    /// `YEvent` (with `target`, `path`, `changes.delta`, `changes.keys`, `keysChanged` and
+    /// `transaction.origin` fields), which can be passed to editor bindings written for yjs.
+    /// `currentTarget` is always the same as `target`.
+    #[wasm_bindgen(js_name = toYjs)]
+    pub fn to_yjs(&mut self) -> crate::Result<JsValue> {
+        crate::js::convert::yjs_event_into_js(
+            self.target(),
+            self.path(),
+            self.delta()?,
+            self.keys()?,
+            self.origin(),
+        )
+    }
 }